@@ -0,0 +1,17 @@
+//!
+//! 在async-std上调用公众号接口
+//!
+//! cargo run --example async_std --no-default-features --features "async-std-runtime wechat-mp"
+//!
+use labrador::prelude::*;
+
+#[async_std::main]
+async fn main() {
+    let appid = std::env::var("WECHAT_MP_APPID").unwrap_or_else(|_| "appid".to_string());
+    let secret = std::env::var("WECHAT_MP_SECRET").unwrap_or_else(|_| "secret".to_string());
+    let client = WechatMpClient::<SimpleStorage>::new(appid, secret);
+    match client.get_api_domain_ip().await {
+        Ok(ips) => println!("api.weixin.qq.com: {:?}", ips),
+        Err(err) => println!("failed: {}", err),
+    }
+}
@@ -0,0 +1,44 @@
+#!/usr/bin/env bash
+# 逐个检查各feature组合能否单独编译，并运行对应的单元测试
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+FEATURES=(
+  ""
+  "wechat-mp"
+  "wechat-cp"
+  "wechat-ma"
+  "wechat-pay"
+  "server"
+  "alipay"
+  "taobao"
+  "pdd"
+  "jd"
+  "toml"
+  "axum-integration"
+  "wechat-cp alipay"
+  "wechat"
+  "async-std-runtime"
+  "async-std-runtime wechat-cp"
+  "async-std-runtime wechat"
+)
+
+for features in "${FEATURES[@]}"; do
+  echo "==> cargo check --no-default-features --features \"${features}\""
+  cargo check --no-default-features --features "${features}"
+done
+
+for features in "${FEATURES[@]}"; do
+  echo "==> cargo test --no-default-features --features \"${features}\" --lib --no-run"
+  cargo test --no-default-features --features "${features}" --lib --no-run
+done
+
+echo "==> cargo clippy --no-default-features --features \"async-std-runtime\""
+cargo clippy --all-targets --no-default-features --features "async-std-runtime" -- -D warnings
+
+echo "==> cargo test --no-default-features --features \"async-std-runtime wechat\""
+cargo test --no-default-features --features "async-std-runtime wechat" --lib
+
+echo "==> cargo test (full)"
+cargo test --lib
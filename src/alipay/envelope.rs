@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use serde_json::value::RawValue;
+
+use crate::{LabraError, LabradorResult, ResponseEnvelope, RequestMethod};
+use crate::alipay::AlipayBaseResponse;
+use crate::alipay::constants::{ERROR_RESPONSE_KEY, SIGN};
+
+/// 验签函数，参数依次为待验签的原始内容与签名
+type AlipayVerifier<'a> = Box<dyn Fn(&str, &str) -> LabradorResult<bool> + 'a>;
+
+/// <pre>
+/// 支付宝的公共返回：{"xxx_response":{...},"sign":"..."}，出错时为{"error_response":{...}}
+/// 签名针对`xxx_response`在原始返回中的子串计算，因此这里直接截取原始内容，不做重新序列化，
+/// 加密的返回为密文字符串，签名同样包含两侧的引号
+/// </pre>
+pub struct AlipayEnvelope<'a> {
+    response_key: String,
+    verifier: Option<AlipayVerifier<'a>>,
+}
+
+impl<'a> AlipayEnvelope<'a> {
+
+    pub fn new(method: impl RequestMethod) -> Self {
+        AlipayEnvelope {
+            response_key: method.get_response_key(),
+            verifier: None,
+        }
+    }
+
+    /// 设置验签函数，成功或带有签名的返回均需验签
+    pub fn verifier<F>(mut self, verifier: F) -> Self where F: Fn(&str, &str) -> LabradorResult<bool> + 'a {
+        self.verifier = Some(Box::new(verifier));
+        self
+    }
+
+    /// <pre>
+    /// 解析并验签，返回公共参数与业务数据的原始内容（body）
+    /// 业务失败（code不为10000）时不返回错误，由调用方通过`is_success`判断
+    /// </pre>
+    pub fn open_response(&self, raw: &str) -> LabradorResult<AlipayBaseResponse> {
+        let fields = serde_json::from_str::<BTreeMap<String, &RawValue>>(raw)?;
+        if let Some(err) = fields.get(ERROR_RESPONSE_KEY) {
+            let resp = serde_json::from_str::<AlipayBaseResponse>(err.get())?;
+            return Err(client_error(&resp));
+        }
+        let content = match fields.get(&self.response_key) {
+            Some(content) if content.get() != "null" => content.get(),
+            _ => return Err(LabraError::MissingField(format!("无法获取解析返回结果：【{}】", raw))),
+        };
+        let mut resp = if content.starts_with('"') { AlipayBaseResponse::new() } else { serde_json::from_str::<AlipayBaseResponse>(content)? };
+        if resp.code.is_none() {
+            resp.code = "10000".to_string().into();
+        }
+        let sign = match fields.get(SIGN) {
+            Some(sign) => serde_json::from_str::<Option<String>>(sign.get())?.unwrap_or_default(),
+            None => String::default(),
+        };
+        if let Some(verifier) = &self.verifier {
+            if (!sign.is_empty() || resp.is_success()) && !verifier(content, &sign)? {
+                return Err(LabraError::InvalidSignature("sign check fail: check Sign and Data Fail!".to_string()))
+            }
+        }
+        resp.sign = sign.into();
+        resp.body = content.to_string().into();
+        Ok(resp)
+    }
+}
+
+/// 支付宝的错误以code为错误码，sub_msg（缺失时为msg）为错误信息
+pub(crate) fn client_error(resp: &AlipayBaseResponse) -> LabraError {
+    LabraError::ClientError {
+        errcode: resp.code.to_owned().unwrap_or_default(),
+        errmsg: resp.sub_msg.to_owned().or_else(|| resp.msg.to_owned()).unwrap_or_default(),
+    }
+}
+
+impl<'a> ResponseEnvelope for AlipayEnvelope<'a> {
+    fn open<'r>(&self, raw: &'r str) -> LabradorResult<Cow<'r, str>> {
+        let resp = self.open_response(raw)?;
+        if !resp.is_success() {
+            return Err(client_error(&resp));
+        }
+        Ok(Cow::Owned(resp.body.unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::sign::{Signer, Verifier};
+    use serde::Deserialize;
+
+    use crate::{LabraError, ResponseEnvelope};
+    use crate::alipay::method::AlipayMethod;
+    use super::AlipayEnvelope;
+
+    #[derive(Debug, Deserialize)]
+    struct QueryResponse {
+        out_trade_no: String,
+        trade_status: String,
+    }
+
+    fn sign(pkey: &PKey<openssl::pkey::Private>, content: &str) -> String {
+        let mut signer = Signer::new(MessageDigest::sha256(), pkey).unwrap();
+        signer.update(content.as_bytes()).unwrap();
+        base64::encode(signer.sign_to_vec().unwrap())
+    }
+
+    fn verify(pkey: &PKey<openssl::pkey::Private>, content: &str, signature: &str) -> crate::LabradorResult<bool> {
+        let mut verifier = Verifier::new(MessageDigest::sha256(), pkey)?;
+        verifier.update(content.as_bytes())?;
+        Ok(verifier.verify(&base64::decode(signature)?)?)
+    }
+
+    #[test]
+    fn test_alipay_success() {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        // 原始返回中带有空格与转义的斜杠，签名针对原始子串计算
+        let content = r#"{"code": "10000", "msg": "Success", "out_trade_no": "2015\/03\/20", "trade_status": "TRADE_SUCCESS"}"#;
+        let raw = format!(r#"{{"alipay_trade_query_response": {},"sign":"{}"}}"#, content, sign(&pkey, content));
+        let envelope = AlipayEnvelope::new(AlipayMethod::QueryOrder).verifier(|content, signature| verify(&pkey, content, signature));
+        let res = envelope.extract::<QueryResponse>(&raw).unwrap();
+        assert_eq!(res.out_trade_no, "2015/03/20");
+        assert_eq!(res.trade_status, "TRADE_SUCCESS");
+        assert_eq!(envelope.open_response(&raw).unwrap().body.as_deref(), Some(content));
+
+        let tampered = raw.replace("TRADE_SUCCESS", "TRADE_CLOSED");
+        assert!(matches!(envelope.extract::<QueryResponse>(&tampered), Err(LabraError::InvalidSignature(_))));
+        assert!(matches!(envelope.extract::<QueryResponse>(r#"{"sign":"SIGN"}"#), Err(LabraError::MissingField(_))));
+    }
+
+    #[test]
+    fn test_alipay_error_response() {
+        let raw = r#"{"error_response":{"code":"40002","msg":"Invalid Arguments","sub_code":"isv.invalid-signature","sub_msg":"验签出错"},"sign":""}"#;
+        let err = AlipayEnvelope::new(AlipayMethod::QueryOrder).extract::<QueryResponse>(raw).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "40002" && errmsg == "验签出错"));
+    }
+
+    #[test]
+    fn test_alipay_sub_code_in_success_wrapper() {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let content = r#"{"code":"40004","msg":"Business Failed","sub_code":"ACQ.TRADE_NOT_EXIST","sub_msg":"交易不存在"}"#;
+        let raw = format!(r#"{{"alipay_trade_query_response":{},"sign":"{}"}}"#, content, sign(&pkey, content));
+        let envelope = AlipayEnvelope::new(AlipayMethod::QueryOrder).verifier(|content, signature| verify(&pkey, content, signature));
+        let resp = envelope.open_response(&raw).unwrap();
+        assert!(!resp.is_success());
+        assert_eq!(resp.sub_code.as_deref(), Some("ACQ.TRADE_NOT_EXIST"));
+        let err = envelope.extract::<QueryResponse>(&raw).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "40004" && errmsg == "交易不存在"));
+    }
+}
@@ -0,0 +1,251 @@
+//!
+//! 第三方应用授权
+//!
+//! 商户把名下应用授权给服务商（ISV）后，服务商用授权码（app_auth_code）换取应用授权令牌（app_auth_token），
+//! 之后在请求中带上app_auth_token即可代商户调用接口。令牌按授权商户的appid保存在会话存储中，
+//! [`AlipayIsv::merchant_client`]获取代商户调用的客户端，令牌过期时自动用刷新令牌换取新的令牌。
+//!
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, util::current_timestamp, AlipayClient, AlipayOpenAuthTokenAppModel, AlipayOpenAuthTokenAppRequest, AlipayPassRequest, LabraError, LabradorResult};
+use crate::alipay::method::AlipayMethod;
+use crate::serde_helper::{string_or_number, option_string_or_number};
+
+/// 提前刷新的时间（秒）
+const TOKEN_EXPIRE_RESERVE_SECONDS: i64 = 200;
+
+/// 第三方应用授权
+#[derive(Debug, Clone)]
+pub struct AlipayIsv<T: SessionStore> {
+    client: AlipayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> AlipayIsv<T> {
+
+    #[inline]
+    pub fn from_client(mut client: AlipayClient<T>) -> AlipayIsv<T> {
+        // 换取、查询令牌以服务商自己的身份调用
+        client.app_auth_token = None;
+        AlipayIsv {
+            client,
+        }
+    }
+
+    /// # 换取应用授权令牌
+    /// <pre>
+    /// 使用应用授权码换取app_auth_token，授权码只能使用一次，换取的令牌按授权商户的appid保存
+    /// 一次授权多个应用时返回多个令牌
+    /// </pre>
+    /// 详见 [文档](https://opendocs.alipay.com/isv/04h3uf)
+    pub async fn get_app_auth_token(&self, app_auth_code: &str) -> LabradorResult<Vec<AlipayAppAuthToken>> {
+        self.token_app(AlipayOpenAuthTokenAppModel {
+            grant_type: "authorization_code".to_string(),
+            code: app_auth_code.to_string().into(),
+            refresh_token: None,
+        }).await
+    }
+
+    /// # 刷新应用授权令牌
+    /// <pre>
+    /// 使用app_refresh_token换取新的app_auth_token，刷新后老的刷新令牌会在一段时间后失效
+    /// </pre>
+    /// 详见 [文档](https://opendocs.alipay.com/isv/04h3uf)
+    pub async fn refresh_app_auth_token(&self, refresh_token: &str) -> LabradorResult<AlipayAppAuthToken> {
+        let tokens = self.token_app(AlipayOpenAuthTokenAppModel {
+            grant_type: "refresh_token".to_string(),
+            code: None,
+            refresh_token: refresh_token.to_string().into(),
+        }).await?;
+        tokens.into_iter().next().ok_or_else(|| LabraError::MissingField("app_auth_token".to_string()))
+    }
+
+    /// # 查询应用授权令牌
+    /// <pre>
+    /// 查询app_auth_token对应的授权商户、授权接口与有效期
+    /// </pre>
+    /// 详见 [文档](https://opendocs.alipay.com/isv/04hgcp)
+    pub async fn query_app_auth_token(&self, app_auth_token: &str) -> LabradorResult<AlipayAppAuthTokenInfo> {
+        let req = AlipayPassRequest::new(AlipayMethod::OpenAuthTokenAppQuery, json!({"app_auth_token": app_auth_token}));
+        let resp = self.client.excute(req, None, None, None).await?;
+        resp.get_biz_model::<AlipayAppAuthTokenInfo>()
+    }
+
+    /// 已保存的授权商户令牌，未授权或刷新令牌已过期时返回None
+    pub fn get_stored_token(&self, auth_app_id: &str) -> LabradorResult<Option<AlipayAppAuthToken>> {
+        let token = self.client.api_client.session().get::<_, String>(self.token_key(auth_app_id), None)?;
+        Ok(token.and_then(|v| serde_json::from_str::<AlipayAppAuthToken>(&v).ok()))
+    }
+
+    /// 保存授权商户的令牌，有效期与刷新令牌一致
+    pub fn store_token(&self, token: &AlipayAppAuthToken) -> LabradorResult<()> {
+        let ttl = (token.re_expires_at - current_timestamp()).max(1) as usize;
+        self.client.api_client.session().set(self.token_key(&token.auth_app_id), serde_json::to_string(token)?, Some(ttl))
+    }
+
+    /// <pre>
+    /// 代授权商户调用接口的客户端，之后的每次请求都会带上该商户的app_auth_token
+    /// 令牌将要过期时先用刷新令牌换取新的令牌；客户端持有获取时的令牌，长时间使用时应重新获取
+    /// 商户未授权或刷新令牌已过期时返回错误，需商户重新授权
+    /// </pre>
+    pub async fn merchant_client(&self, auth_app_id: &str) -> LabradorResult<AlipayClient<T>> {
+        let token = self.get_stored_token(auth_app_id)?
+            .ok_or_else(|| LabraError::ApiError(format!("商户[{}]未授权或授权已过期，请重新授权", auth_app_id)))?;
+        let token = if token.is_expired() {
+            self.refresh_app_auth_token(&token.app_refresh_token).await?
+        } else {
+            token
+        };
+        let mut client = self.client.to_owned();
+        client.app_auth_token = token.app_auth_token.into();
+        Ok(client)
+    }
+
+    async fn token_app(&self, model: AlipayOpenAuthTokenAppModel) -> LabradorResult<Vec<AlipayAppAuthToken>> {
+        let mut req = AlipayOpenAuthTokenAppRequest::new();
+        req.biz_model = model.into();
+        let resp = self.client.excute(req, None, None, None).await?;
+        let v = resp.get_biz_model::<Value>()?;
+        // 新版接口的令牌放在tokens数组中，旧版直接放在返回结果中
+        let tokens = match v.get("tokens") {
+            Some(tokens) => serde_json::from_value::<Vec<AlipayAppAuthToken>>(tokens.to_owned())?,
+            None => vec![serde_json::from_value::<AlipayAppAuthToken>(v)?],
+        };
+        let now = current_timestamp();
+        tokens.into_iter().map(|mut token| {
+            token.expires_at = now + token.expires_in;
+            token.re_expires_at = now + token.re_expires_in;
+            self.store_token(&token)?;
+            Ok(token)
+        }).collect()
+    }
+
+    fn token_key(&self, auth_app_id: &str) -> String {
+        format!("{}_{}_alipay_app_auth_token", self.client.api_client.app_key, auth_app_id)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 应用授权令牌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlipayAppAuthToken {
+    /// 应用授权令牌
+    pub app_auth_token: String,
+    /// 刷新令牌
+    pub app_refresh_token: String,
+    /// 授权商户的appid
+    pub auth_app_id: String,
+    /// 授权商户的user_id
+    pub user_id: Option<String>,
+    /// 令牌的有效时间，单位秒
+    #[serde(default, with = "string_or_number")]
+    pub expires_in: i64,
+    /// 刷新令牌的有效时间，单位秒
+    #[serde(default, with = "string_or_number")]
+    pub re_expires_in: i64,
+    /// 令牌的过期时间戳，按换取时间计算
+    #[serde(default)]
+    pub expires_at: i64,
+    /// 刷新令牌的过期时间戳，按换取时间计算
+    #[serde(default)]
+    pub re_expires_at: i64,
+}
+
+impl AlipayAppAuthToken {
+    /// 令牌已过期或即将过期
+    pub fn is_expired(&self) -> bool {
+        self.expires_at - TOKEN_EXPIRE_RESERVE_SECONDS <= current_timestamp()
+    }
+}
+
+/// 应用授权令牌的授权信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlipayAppAuthTokenInfo {
+    /// 授权商户的user_id
+    pub user_id: Option<String>,
+    /// 授权商户的appid
+    pub auth_app_id: Option<String>,
+    /// 令牌的有效时间，单位秒
+    #[serde(default, with = "option_string_or_number")]
+    pub expires_in: Option<i64>,
+    /// 授权的接口列表
+    #[serde(default)]
+    pub auth_methods: Vec<String>,
+    /// 授权生效时间
+    pub auth_start: Option<String>,
+    /// 授权失效时间
+    pub auth_end: Option<String>,
+    /// 授权状态，valid：有效，invalid：无效
+    pub status: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+    use serde_json::{json, Value};
+
+    use crate::{AlipayClient, LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_merchant_client_refresh_and_inject() {
+        // 支付宝公钥与应用私钥使用同一对密钥，便于在测试中对返回结果签名
+        let rsa = Rsa::generate(2048).unwrap();
+        let pkey = PKey::from_rsa(rsa.clone()).unwrap();
+        let response = |key: &str, content: &str| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+            signer.update(content.as_bytes()).unwrap();
+            MockResponse::json(&format!(r#"{{"{}":{},"sign":"{}"}}"#, key, content, base64::encode(signer.sign_to_vec().unwrap())))
+        };
+        // 令牌有效期短于提前刷新的时间，获取客户端时需先刷新
+        let exchanged = r#"{"code":"10000","msg":"Success","tokens":[{"app_auth_token":"TOKEN_OLD","app_refresh_token":"REFRESH_OLD","auth_app_id":"2021000000000001","user_id":"2088000000000001","expires_in":100,"re_expires_in":32140800}]}"#;
+        let refreshed = r#"{"code":"10000","msg":"Success","app_auth_token":"TOKEN_NEW","app_refresh_token":"REFRESH_NEW","auth_app_id":"2021000000000001","user_id":"2088000000000001","expires_in":"31536000","re_expires_in":"32140800"}"#;
+        let queried = r#"{"code":"10000","msg":"Success","user_id":"2088000000000001","auth_app_id":"2021000000000001","expires_in":31536000,"auth_methods":["alipay.pass.instance.add"],"status":"valid"}"#;
+        let server = MockServer::start(vec![
+            response("alipay_open_auth_token_app_response", exchanged),
+            response("alipay_open_auth_token_app_response", refreshed),
+            response("alipay_open_auth_token_app_query_response", queried),
+            response("alipay_open_auth_token_app_query_response", queried),
+        ]).await;
+        let mut client = AlipayClient::<SimpleStorage>::new("2021000000000099", false)
+            .set_private_key(&base64::encode(rsa.private_key_to_der().unwrap())).unwrap()
+            .set_alipay_public_key(&base64::encode(rsa.public_key_to_der().unwrap()));
+        client.api_client.api_path = server.url.to_owned();
+        let isv = client.isv();
+
+        assert!(matches!(isv.merchant_client("2021000000000001").await, Err(LabraError::ApiError(_))));
+        let tokens = isv.get_app_auth_token("APP_AUTH_CODE").await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens[0].is_expired());
+        let merchant = isv.merchant_client("2021000000000001").await.unwrap();
+        let stored = isv.get_stored_token("2021000000000001").unwrap().unwrap();
+        assert_eq!(stored.app_auth_token, "TOKEN_NEW");
+        assert!(!stored.is_expired());
+        // 未过期时直接使用保存的令牌
+        let merchant = isv.merchant_client("2021000000000001").await.unwrap();
+        let info = merchant.isv().query_app_auth_token("TOKEN_NEW").await.unwrap();
+        assert_eq!(info.auth_methods, vec!["alipay.pass.instance.add".to_string()]);
+        assert_eq!(info.expires_in, Some(31536000));
+        merchant.pass().add_template("unique", &json!({})).await.ok();
+
+        let requests = server.requests();
+        let form = |request: &str| serde_urlencoded::from_str::<BTreeMap<String, String>>(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+        let biz_content = |request: &str| serde_json::from_str::<Value>(form(request).get("biz_content").unwrap()).unwrap();
+        assert_eq!(biz_content(&requests[0]), json!({"grant_type": "authorization_code", "code": "APP_AUTH_CODE", "refresh_token": null}));
+        assert_eq!(biz_content(&requests[1]), json!({"grant_type": "refresh_token", "code": null, "refresh_token": "REFRESH_OLD"}));
+        // 换取与查询令牌以服务商身份调用，代商户调用的接口带上令牌
+        assert!(!form(&requests[0]).contains_key("app_auth_token"));
+        assert!(!form(&requests[2]).contains_key("app_auth_token"));
+        assert!(requests[3].contains("method=alipay.pass.template.add"));
+        assert_eq!(form(&requests[3]).get("app_auth_token").map(String::as_str), Some("TOKEN_NEW"));
+        assert_eq!(requests.len(), 4);
+    }
+}
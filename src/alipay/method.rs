@@ -29,6 +29,14 @@ pub enum AlipayMethod {
     SystemOauthToken,
     /// 换取应用授权令牌
     OpenAuthTokenApp,
+    /// 查询应用授权令牌
+    OpenAuthTokenAppQuery,
+    /// 卡券模板创建
+    PassTemplateAdd,
+    /// 卡券实例发放
+    PassInstanceAdd,
+    /// 卡券实例更新
+    PassInstanceUpdate,
     /// 自定义方法
     Custom { method: String, response_key: String }
 }
@@ -50,6 +58,10 @@ impl RequestMethod for AlipayMethod {
             AlipayMethod::CancelOrder => String::from("alipay.trade.cancel"),
             AlipayMethod::SystemOauthToken => String::from("alipay.system.oauth.token"),
             AlipayMethod::OpenAuthTokenApp => String::from("alipay.open.auth.token.app"),
+            AlipayMethod::OpenAuthTokenAppQuery => String::from("alipay.open.auth.token.app.query"),
+            AlipayMethod::PassTemplateAdd => String::from("alipay.pass.template.add"),
+            AlipayMethod::PassInstanceAdd => String::from("alipay.pass.instance.add"),
+            AlipayMethod::PassInstanceUpdate => String::from("alipay.pass.instance.update"),
             AlipayMethod::Custom{ ref method, .. } => method.to_string()
         }
     }
@@ -69,6 +81,10 @@ impl RequestMethod for AlipayMethod {
             AlipayMethod::CancelOrder => String::from("alipay_trade_cancel_response"),
             AlipayMethod::SystemOauthToken => String::from("alipay_system_oauth_token_response"),
             AlipayMethod::OpenAuthTokenApp => String::from("alipay_open_auth_token_app_response"),
+            AlipayMethod::OpenAuthTokenAppQuery => String::from("alipay_open_auth_token_app_query_response"),
+            AlipayMethod::PassTemplateAdd => String::from("alipay_pass_template_add_response"),
+            AlipayMethod::PassInstanceAdd => String::from("alipay_pass_instance_add_response"),
+            AlipayMethod::PassInstanceUpdate => String::from("alipay_pass_instance_update_response"),
             AlipayMethod::Custom{ ref response_key, .. } => response_key.to_string()
         }
     }
@@ -1,5 +1,5 @@
-use chrono::Local;
-use crate::{client::{APIClient}, request::{RequestType, Method, LabraRequest}, errors::LabraError, session::{SimpleStorage, SessionStore}, RequestMethod, LabradorResult, get_nonce_str, RequestParametersHolder};
+use chrono::{Local, NaiveDateTime};
+use crate::{client::{APIClient}, request::{RequestType, Method, LabraRequest}, errors::LabraError, session::{SimpleStorage, SessionStore}, RequestMethod, LabradorResult, RequestParametersHolder};
 
 use std::collections::{BTreeMap};
 use std::fs;
@@ -8,18 +8,28 @@ use openssl::nid::Nid;
 use openssl::pkey::PKey;
 use openssl::sign::{Signer, Verifier};
 use openssl::x509::{X509, X509NameEntries};
-use rustc_serialize::hex::ToHex;
+use crate::util::hex;
 use serde::Serialize;
 use crate::alipay::method::AlipayMethod;
 
 mod request;
 mod response;
 mod method;
+mod sign_debug;
+mod envelope;
+mod pass;
+mod isv;
+mod open_message;
 #[allow(unused)]
 mod constants;
 
 pub use request::*;
 pub use response::*;
+pub use sign_debug::*;
+pub use envelope::*;
+pub use pass::*;
+pub use isv::*;
+pub use open_message::*;
 use crate::alipay::constants::{ENCRYPT_TYPE_AES, FORMAT_JSON, SIGN_TYPE_RSA2};
 use crate::prp::PrpCrypto;
 
@@ -43,7 +53,8 @@ pub struct AlipayClient<T: SessionStore> {
     alipay_public_cert: Option<String>,
     /// 设置支付宝根证书路径
     alipay_root_cert: Option<String>,
-
+    /// 代商户调用时的应用授权令牌，设置后每次请求都会带上
+    app_auth_token: Option<String>,
 }
 
 
@@ -189,6 +200,7 @@ impl <T: SessionStore> AlipayClient<T> {
             app_cert: None,
             alipay_public_cert: None,
             alipay_root_cert: None,
+            app_auth_token: None,
         }
     }
 
@@ -209,9 +221,20 @@ impl <T: SessionStore> AlipayClient<T> {
             app_cert: None,
             alipay_public_cert: None,
             alipay_root_cert: None,
+            app_auth_token: None,
         }
     }
 
+    /// 卡券服务
+    pub fn pass(&self) -> AlipayPass<T> {
+        AlipayPass::from_client(self.clone())
+    }
+
+    /// 第三方应用授权（服务商代商户调用）
+    pub fn isv(&self) -> AlipayIsv<T> {
+        AlipayIsv::from_client(self.clone())
+    }
+
     /// 获取应用证书SN
     pub fn get_app_cert_sn(&self) -> LabradorResult<String> {
         let pem = self.app_cert.to_owned().unwrap_or_default();
@@ -219,7 +242,7 @@ impl <T: SessionStore> AlipayClient<T> {
         let issuer = iter2string(x509.issuer_name().entries())?;
         let serial_number = x509.serial_number().to_bn()?.to_dec_str()?;
         let data = issuer + &serial_number;
-        Ok(hash(MessageDigest::md5(), data.as_ref())?.to_hex())
+        Ok(hex::encode(hash(MessageDigest::md5(), data.as_ref())?))
     }
 
     /// 获取根证书SN
@@ -233,8 +256,8 @@ impl <T: SessionStore> AlipayClient<T> {
             let issuer = iter2string(x509.issuer_name().entries())?;
             let serial_number = x509.serial_number().to_bn()?.to_dec_str()?;
             let data = issuer + &serial_number;
-            Ok(hash(MessageDigest::md5(), data.as_ref())?.to_hex())
-        }).map(|cert: LabradorResult<String>| cert.unwrap_or_default()).collect::<Vec<String>>().join("_");
+            Ok(hex::encode(hash(MessageDigest::md5(), data.as_ref())?))
+        }).collect::<LabradorResult<Vec<String>>>()?.join("_");
         Ok(alipay_root_cert_sn)
     }
 
@@ -283,6 +306,12 @@ impl <T: SessionStore> AlipayClient<T> {
         self
     }
 
+    /// 设置应用授权令牌，之后的请求都以授权商户的身份调用，一般通过[`AlipayIsv::merchant_client`]获取
+    pub fn set_app_auth_token(mut self, app_auth_token: &str) -> Self {
+        self.app_auth_token = app_auth_token.to_string().into();
+        self
+    }
+
     /// 设置APP证书路径
     pub fn set_app_cert_path(mut self, cert_path: &str) -> LabradorResult<Self> {
         if cert_path.is_empty() {
@@ -329,20 +358,7 @@ impl <T: SessionStore> AlipayClient<T> {
 
     /// 签名
     fn sign(&self, params: &str) -> LabradorResult<String> {
-        let private_key = self.private_key.to_owned().unwrap_or_default();
-        let content = base64::decode(&private_key)?;
-        let pkey = match PKey::private_key_from_der(&content) {
-            Ok(k) => k,
-            Err(err) => {
-                // 用pkcs8进行
-                PKey::private_key_from_pkcs8(&content)?
-            }
-        };
-        // let pkey = PKey::private_key_from_pkcs8(&content)?;
-        let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
-        signer.update(params.as_bytes())?;
-        let sign = base64::encode(&signer.sign_to_vec()?);
-        Ok(sign)
+        rsa2_sign(params, &self.private_key.to_owned().unwrap_or_default())
     }
 
     /// 验签
@@ -378,6 +394,31 @@ impl <T: SessionStore> AlipayClient<T> {
         Ok(url_sb)
     }
 
+    /// 拼装页面跳转地址，全部参数按RFC 3986进行编码（空格编码为%20而非+）
+    fn get_encoded_redirect_url(&self, holder: &RequestParametersHolder) -> String {
+        let query = holder.get_sorted_map().iter()
+            .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+            .collect::<Vec<String>>().join("&");
+        format!("{}?{}", self.api_client.api_path, query)
+    }
+
+    /// 拼装表单提交地址，仅包含公共参数，业务参数放在表单中
+    fn get_encoded_request_url(&self, holder: &RequestParametersHolder) -> String {
+        let query = holder.protocal_must_params.iter().chain(holder.protocal_opt_params.iter())
+            .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+            .collect::<Vec<String>>().join("&");
+        format!("{}?{}", self.api_client.api_path, query)
+    }
+
+    /// 页面跳转类支付签名原文按charset对应的字节计算，而Rust字符串均为UTF-8，
+    /// 支持GBK需引入额外的转码依赖并保证签名与编码一致，故目前仅支持UTF-8
+    fn check_page_charset(&self) -> LabradorResult<()> {
+        if !self.charset.eq_ignore_ascii_case(constants::CHARSET_UTF8) {
+            return Err(LabraError::ApiError(format!("页面跳转支付仅支持UTF-8字符集，当前为：{}", self.charset)));
+        }
+        Ok(())
+    }
+
     /// 拼装sdk调用时所传参数
     fn get_sdk_params(&self, holder: &RequestParametersHolder) -> LabradorResult<String> {
         let mut url_sb = String::default();
@@ -395,14 +436,17 @@ impl <T: SessionStore> AlipayClient<T> {
     /// @param appAuthToken
     /// @param appCertSN    应用证书序列号
     fn get_request_holder_with_sign<D, M>(&self, request: D, access_token: Option<String>, app_auth_token: Option<String>, target_app_id: Option<String>) -> LabradorResult<RequestParametersHolder> where D: AlipayRequest<M>, M: Serialize {
+        self.get_request_holder_with_sign_at(request, access_token, app_auth_token, target_app_id, Local::now().naive_local())
+    }
+
+    /// 组装接口参数，timestamp为公共参数中的请求时间（北京时间）
+    fn get_request_holder_with_sign_at<D, M>(&self, request: D, access_token: Option<String>, app_auth_token: Option<String>, target_app_id: Option<String>, timestamp: NaiveDateTime) -> LabradorResult<RequestParametersHolder> where D: AlipayRequest<M>, M: Serialize {
         let mut holder = RequestParametersHolder::new();
         let mut app_params = request.get_text_params();
         let empty_str = &"".to_string();
         // 仅当API包含biz_content参数且值为空时，序列化bizModel填充bizContent
         let biz_content = app_params.get(constants::BIZ_CONTENT_KEY).unwrap_or_else(|| empty_str);
-        let biz_model = request.get_biz_model();
-        if biz_content.is_empty() && biz_model.is_some() {
-            let biz_model = biz_model.unwrap();
+        if let Some(biz_model) = request.get_biz_model().filter(|_| biz_content.is_empty()) {
             app_params.insert(constants::BIZ_CONTENT_KEY.to_string(), serde_json::to_string(&biz_model)?);
         }
         // 只有新接口和设置密钥才能支持加密
@@ -416,13 +460,11 @@ impl <T: SessionStore> AlipayClient<T> {
             if self.encrypt_type.is_empty() || self.encrypt_key.is_none() {
                 return Err(LabraError::ApiError("API请求要求加密，则必须设置密钥类型[encryptType]和加密密钥[encryptKey]".to_string()))
             }
-            let key = self.encrypt_key.to_owned().unwrap_or_default();
-            let prp = PrpCrypto::new(key.into_bytes());
-            let encrypt_content = prp.aes_128_cbc_encrypt_data(biz_content, &get_nonce_str())?;
+            let encrypt_content = self.content_crypto()?.aes_128_cbc_base64_encrypt(biz_content)?;
             app_params.insert(constants::BIZ_CONTENT_KEY.to_string(), encrypt_content);
         }
 
-        if let Some(app_auth_token) = app_auth_token {
+        if let Some(app_auth_token) = app_auth_token.or_else(|| self.app_auth_token.to_owned()) {
             app_params.insert(constants::APP_AUTH_TOKEN.to_string(), app_auth_token);
         }
 
@@ -470,7 +512,7 @@ impl <T: SessionStore> AlipayClient<T> {
             }
         }
         // TODO: 如果SM2根证书序列号非空，添加SM2根证书序列号
-        protocal_must_params.insert(constants::TIMESTAMP.to_string(), Local::now().naive_local().format(constants::FORMAT_TIME).to_string());
+        protocal_must_params.insert(constants::TIMESTAMP.to_string(), timestamp.format(constants::FORMAT_TIME).to_string());
         holder.set_protocal_must_params(protocal_must_params.to_owned());
 
         let mut protocal_opt_params = BTreeMap::new();
@@ -492,26 +534,39 @@ impl <T: SessionStore> AlipayClient<T> {
         Ok(holder)
     }
 
+    /// 内容加解密，encrypt_key为支付宝开放平台生成的base64编码的AES密钥
+    fn content_crypto(&self) -> LabradorResult<PrpCrypto> {
+        if !self.encrypt_type.eq(ENCRYPT_TYPE_AES) {
+            return Err(LabraError::ApiError(format!("不支持的加密类型[{}]", self.encrypt_type)))
+        }
+        let key = base64::decode(self.encrypt_key.to_owned().unwrap_or_default())?;
+        Ok(PrpCrypto::new(key))
+    }
+
+    /// 解密返回内容，加密时返回结果为base64编码的密文字符串，需在验签之后调用
+    fn decrypt_response(&self, mut resp: AlipayBaseResponse) -> LabradorResult<AlipayBaseResponse> {
+        let body = resp.body.to_owned().unwrap_or_default();
+        let content = match serde_json::from_str::<serde_json::Value>(&body) {
+            Ok(serde_json::Value::String(content)) => content,
+            _ => return Ok(resp),
+        };
+        if self.encrypt_key.is_none() {
+            return Err(LabraError::ApiError("返回结果已加密，必须设置加密密钥[encryptKey]".to_string()))
+        }
+        let content = self.content_crypto()?.aes_128_cbc_base64_decrypt(&content)?;
+        let mut decrypted = serde_json::from_str::<AlipayBaseResponse>(&content)?;
+        if decrypted.code.is_none() {
+            decrypted.code = "10000".to_string().into();
+        }
+        decrypted.sign = resp.sign.take();
+        decrypted.body = content.into();
+        Ok(decrypted)
+    }
+
     /// 签名
     fn sign_with_type(&self, sign_content: &str) -> LabradorResult<String> {
         match self.sign_type.as_str() {
-            constants::SIGN_TYPE_RSA2 => {
-                let private_key = self.private_key.to_owned().unwrap_or_default();
-                let content = base64::decode(&private_key)?;
-                let pkey = match PKey::private_key_from_der(&content) {
-                    Ok(k) => k,
-                    Err(err) => {
-                        // 用pkcs8进行
-                        PKey::private_key_from_pkcs8(&content)?
-                    }
-                };
-                let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
-                signer.update(sign_content.as_bytes())?;
-                println!("sign:{}",sign_content);
-                let sign = base64::encode(&signer.sign_to_vec()?);
-                println!("sign:{}",sign);
-                Ok(sign)
-            }
+            constants::SIGN_TYPE_RSA2 => rsa2_sign(sign_content, &self.private_key.to_owned().unwrap_or_default()),
             // constants::SIGN_TYPE_RSA => {
             //
             // }
@@ -562,22 +617,9 @@ impl <T: SessionStore> AlipayClient<T> {
         let url = self.get_request_url(&holder)?;
         let req = LabraRequest::new().url(url).method(Method::Post).form(&holder.application_params).req_type(RequestType::Form);
         let result = self.api_client.request(req).await?.text()?;
-        match AlipayBaseResponse::parse(&result, method) {
-            Ok(mut resp) => {
-                let sign = resp.get_sign();
-                // 验签请求返回原始串
-                if !sign.is_empty() || resp.is_success() {
-                    let body = resp.body.to_owned().unwrap_or_default();
-                    // 对body进行排序
-                    let result = self.verify(&body, &sign)?;
-                    if !result {
-                        return Err(LabraError::InvalidSignature("sign check fail: check Sign and Data Fail!".to_string()))
-                    }
-                }
-                Ok(resp)
-            }
-            Err(err) => Err(err)
-        }
+        let resp = AlipayEnvelope::new(method).verifier(|content, sign| self.verify(content, sign)).open_response(&result)?;
+        // 签名针对密文计算，验签通过后再解密
+        self.decrypt_response(resp)
     }
 
     fn build_form(&self, url: &str, parameters: &BTreeMap<String, String>) -> String {
@@ -666,6 +708,88 @@ impl <T: SessionStore> AlipayClient<T> {
         self.page_excute(http_method.unwrap_or("POST"), req)
     }
 
+    /// # 生成PC网站支付跳转地址
+    /// <pre>
+    /// alipay.trade.page.pay不由服务端调用网关，而是生成签名后的地址供浏览器跳转。
+    /// 所有参数（包括biz_content的JSON内容）均按RFC 3986进行百分号编码，空格编码为%20。
+    /// 仅支持UTF-8字符集。
+    /// </pre>
+    /// [接口地址](https://opendocs.alipay.com/open/028r8t?scene=22)
+    /// # 示例
+    /// ```no_run
+    ///
+    ///  # use labrador::{AlipayClient, AlipayTradePagePayModel, AlipayTradePagePayRequest, SimpleStorage};
+    ///
+    ///   # fn main() {
+    ///         let mut param = AlipayTradePagePayRequest::<AlipayTradePagePayModel>::default();
+    ///         param.biz_model = AlipayTradePagePayModel {
+    ///             out_trade_no: "20150320010101001".to_string(),
+    ///             total_amount: 88.88,
+    ///             subject: "Iphone6 16G".to_string(),
+    ///             product_code: "FAST_INSTANT_TRADE_PAY".to_string(),
+    ///             ..Default::default()
+    ///         }.into();
+    ///         let client = AlipayClient::<SimpleStorage>::new("appKey", false).set_private_key("privateKey").unwrap();
+    ///         match client.build_page_pay_url(param) {
+    ///             Ok(url) => {}
+    ///             Err(err) => {}
+    ///         }
+    ///   # }
+    ///
+    /// ```
+    ///
+    pub fn build_page_pay_url(&self, req: AlipayTradePagePayRequest<AlipayTradePagePayModel>) -> LabradorResult<String> {
+        self.build_page_pay_url_at(req, Local::now().naive_local())
+    }
+
+    /// 生成PC网站支付跳转地址，timestamp为公共参数中的请求时间（北京时间），用于重放或比对签名
+    pub fn build_page_pay_url_at(&self, req: AlipayTradePagePayRequest<AlipayTradePagePayModel>, timestamp: NaiveDateTime) -> LabradorResult<String> {
+        self.check_page_charset()?;
+        let holder = self.get_request_holder_with_sign_at(req, None, None, None, timestamp)?;
+        Ok(self.get_encoded_redirect_url(&holder))
+    }
+
+    /// # 生成手机网站支付自动提交表单
+    /// <pre>
+    /// alipay.trade.wap.pay不由服务端调用网关，而是生成签名后自动提交的HTML表单，直接输出到浏览器即可。
+    /// 公共参数按RFC 3986编码后拼接在表单action中，biz_content放在隐藏域中。
+    /// 仅支持UTF-8字符集。
+    /// </pre>
+    /// [接口地址](https://opendocs.alipay.com/open/02ivbs?scene=21)
+    /// # 示例
+    /// ```no_run
+    ///
+    ///  # use labrador::{AlipayClient, AlipayTradeWapPayModel, AlipayTradeWapPayRequest, SimpleStorage};
+    ///
+    ///   # fn main() {
+    ///         let mut param = AlipayTradeWapPayRequest::<AlipayTradeWapPayModel>::default();
+    ///         param.biz_model = AlipayTradeWapPayModel {
+    ///             out_trade_no: "20150320010101001".to_string(),
+    ///             subject: "Iphone6 16G".to_string(),
+    ///             product_code: "QUICK_WAP_WAY".to_string(),
+    ///             ..Default::default()
+    ///         }.into();
+    ///         let client = AlipayClient::<SimpleStorage>::new("appKey", false).set_private_key("privateKey").unwrap();
+    ///         match client.build_wap_pay_form(param) {
+    ///             Ok(html) => {}
+    ///             Err(err) => {}
+    ///         }
+    ///   # }
+    ///
+    /// ```
+    ///
+    pub fn build_wap_pay_form(&self, req: AlipayTradeWapPayRequest<AlipayTradeWapPayModel>) -> LabradorResult<String> {
+        self.build_wap_pay_form_at(req, Local::now().naive_local())
+    }
+
+    /// 生成手机网站支付自动提交表单，timestamp为公共参数中的请求时间（北京时间），用于重放或比对签名
+    pub fn build_wap_pay_form_at(&self, req: AlipayTradeWapPayRequest<AlipayTradeWapPayModel>, timestamp: NaiveDateTime) -> LabradorResult<String> {
+        self.check_page_charset()?;
+        let holder = self.get_request_holder_with_sign_at(req, None, None, None, timestamp)?;
+        let url = self.get_encoded_request_url(&holder);
+        Ok(self.build_form(&url, &holder.application_params))
+    }
+
     /// # app支付接口2.0
     /// 外部商户APP唤起快捷SDK创建订单并支付
     /// [接口地址](https://opendocs.alipay.com/open/02e7gq?scene=20)
@@ -1018,7 +1142,7 @@ impl <T: SessionStore> AlipayClient<T> {
         let mut data = serde_urlencoded::from_str::<BTreeMap<String, String>>(notify_data)?;
         let sign_type = data.get(constants::SIGN).map(|v| v.to_owned()).unwrap_or_default();
         let sign = data.get(constants::SIGN).map(|v| urlencoding::decode(v).unwrap_or_default().into_owned()).unwrap_or_default();
-        let source = data.into_iter().filter(|(k,v)| !k.is_empty() && !v.is_empty() && k.ne(constants::SIGN) && k.ne(constants::SIGN_TYPE)).map(|(k, v)| format!("{}={}", k, urlencoding::decode(&v).unwrap_or_default().replace("+", " "))).collect::<Vec<String>>().join("&");
+        let source = notify_sign_content(&data);
         let result = self.verify(&source, &sign)?;
         if !result {
             return Err(LabraError::InvalidSignature("回调结果验签失败！".to_string()))
@@ -1046,6 +1170,22 @@ impl <T: SessionStore> AlipayClient<T> {
     }
 }
 
+/// # 解密小程序获取的手机号
+/// <pre>
+/// 小程序调用my.getPhoneNumber获得的response为AES加密的密文，aes_key为开放平台设置的接口内容加密密钥（base64编码）
+/// 解密失败或返回码非10000时返回错误
+/// </pre>
+/// 详见 [文档](https://opendocs.alipay.com/mini/api/getphonenumber)
+pub fn decrypt_alipay_phone_number(response: &str, aes_key: &str) -> LabradorResult<AlipayMobileResponse> {
+    let prp = PrpCrypto::new(base64::decode(aes_key)?);
+    let content = prp.aes_128_cbc_base64_decrypt(response)?;
+    let mobile = serde_json::from_str::<AlipayMobileResponse>(&content)?;
+    if !mobile.code.eq("10000") {
+        return Err(LabraError::ClientError { errcode: mobile.code.to_owned(), errmsg: mobile.sub_msg.to_owned().or(mobile.msg.to_owned()).unwrap_or_default() })
+    }
+    Ok(mobile)
+}
+
 fn iter2string(iter: X509NameEntries) -> LabradorResult<String> {
     let mut string: String = String::from("");
     for value in iter {
@@ -1057,3 +1197,248 @@ fn iter2string(iter: X509NameEntries) -> LabradorResult<String> {
     Ok(string)
 }
 
+
+/// 按RFC 3986进行百分号编码，空格编码为%20
+fn percent_encode(v: &str) -> String {
+    urlencoding::encode(v).into_owned()
+}
+
+
+/// RSA2（SHA256WithRSA）签名，private_key为base64编码的PKCS#1或PKCS#8私钥
+pub(crate) fn rsa2_sign(sign_content: &str, private_key: &str) -> LabradorResult<String> {
+    let content = base64::decode(private_key)?;
+    let pkey = match PKey::private_key_from_der(&content) {
+        Ok(k) => k,
+        Err(_) => {
+            // 用pkcs8进行
+            PKey::private_key_from_pkcs8(&content)?
+        }
+    };
+    let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+    signer.update(sign_content.as_bytes())?;
+    Ok(base64::encode(&signer.sign_to_vec()?))
+}
+
+/// 异步通知的验签串：剔除sign、sign_type与空值参数，按参数名升序拼接URL解码后的值
+pub(crate) fn notify_sign_content(data: &BTreeMap<String, String>) -> String {
+    data.iter().filter(|(k,v)| !k.is_empty() && !v.is_empty() && k.as_str().ne(constants::SIGN) && k.as_str().ne(constants::SIGN_TYPE)).map(|(k, v)| format!("{}={}", k, urlencoding::decode(v).unwrap_or_default().replace("+", " "))).collect::<Vec<String>>().join("&")
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Verifier;
+    use openssl::sign::Signer;
+    use crate::{AlipayClient, AlipayTradePagePayModel, AlipayTradePagePayRequest, AlipayTradeQueryModel, AlipayTradeQueryRequest, AlipayTradeWapPayModel, AlipayTradeWapPayRequest, LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{decrypt_alipay_phone_number, percent_encode};
+
+    /// 以下密文由`openssl enc -aes-128-cbc -iv 00000000000000000000000000000000 -base64`生成
+    const AES_KEY: &str = "4ChT08phkz59hquD795X7w==";
+
+    fn test_client() -> (AlipayClient<SimpleStorage>, PKey<openssl::pkey::Public>) {
+        let rsa = Rsa::generate(2048).unwrap();
+        let private_key = base64::encode(rsa.private_key_to_der().unwrap());
+        let public_key = PKey::public_key_from_der(&rsa.public_key_to_der().unwrap()).unwrap();
+        let client = AlipayClient::<SimpleStorage>::new("2021000000000000", false).set_private_key(&private_key).unwrap();
+        (client, public_key)
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("a b+c/中"), "a%20b%2Bc%2F%E4%B8%AD");
+        assert_eq!(percent_encode("2022-08-02 21:23:00"), "2022-08-02%2021%3A23%3A00");
+        // 只保留RFC 3986的非保留字符
+        assert_eq!(percent_encode("A-z_0.9~=&?%"), "A-z_0.9~%3D%26%3F%25");
+        assert_eq!(percent_encode("支付宝+/=="), "%E6%94%AF%E4%BB%98%E5%AE%9D%2B%2F%3D%3D");
+    }
+
+    #[test]
+    fn test_build_page_pay_url() {
+        let (client, public_key) = test_client();
+        let mut req = AlipayTradePagePayRequest::<AlipayTradePagePayModel>::default();
+        let model = AlipayTradePagePayModel {
+            out_trade_no: "20150320010101001".to_string(),
+            total_amount: 88.88,
+            subject: "Iphone6 16G".to_string(),
+            product_code: "FAST_INSTANT_TRADE_PAY".to_string(),
+            ..Default::default()
+        };
+        let biz_content = serde_json::to_string(&model).unwrap();
+        req.biz_model = model.into();
+        req.return_url = "https://example.com/return?a=1".to_string().into();
+        let url = client.build_page_pay_url(req).unwrap();
+        let (gateway, query) = url.split_once('?').unwrap();
+        assert_eq!(gateway, "https://openapi.alipay.com/gateway.do");
+        assert!(!query.contains('+'));
+        assert!(query.contains("Iphone6%2016G"));
+
+        let mut params = query.split('&').map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap();
+            (urlencoding::decode(k).unwrap().into_owned(), urlencoding::decode(v).unwrap().into_owned())
+        }).collect::<BTreeMap<String, String>>();
+        assert_eq!(params.get("method").unwrap(), "alipay.trade.page.pay");
+        assert_eq!(params.get("biz_content").unwrap(), &biz_content);
+        assert_eq!(params.get("return_url").unwrap(), "https://example.com/return?a=1");
+        let sign = base64::decode(params.remove("sign").unwrap()).unwrap();
+        let content = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<String>>().join("&");
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key).unwrap();
+        verifier.update(content.as_bytes()).unwrap();
+        assert!(verifier.verify(&sign).unwrap());
+    }
+
+    #[test]
+    fn test_build_wap_pay_form() {
+        let (client, _) = test_client();
+        let req = AlipayTradeWapPayRequest::<AlipayTradeWapPayModel> {
+            biz_model: AlipayTradeWapPayModel {
+                out_trade_no: "20150320010101001".to_string(),
+                subject: "Iphone6 16G".to_string(),
+                product_code: "QUICK_WAP_WAY".to_string(),
+                ..Default::default()
+            }.into(),
+            ..Default::default()
+        };
+        let form = client.build_wap_pay_form(req).unwrap();
+        assert!(form.contains("action=\"https://openapi.alipay.com/gateway.do?"));
+        assert!(form.contains("method=alipay.trade.wap.pay"));
+        assert!(form.contains("&sign="));
+        assert!(form.contains("<input type=\"hidden\" name=\"biz_content\" value=\"{&quot;out_trade_no&quot;:&quot;20150320010101001&quot;"));
+        assert!(form.contains("document.forms[0].submit()"));
+    }
+
+    /// <pre>
+    /// 固定私钥（testkit中的测试私钥）、请求时间与biz_content的页面跳转支付
+    /// 签名串按开放平台“自行实现签名”的规则拼接，签名值与`openssl dgst -sha256 -sign`对同一签名串的结果一致
+    /// </pre>
+    /// <pre>
+    /// 固定时间戳的客户端，biz_content取自开放平台电脑网站支付、手机网站支付文档的请求示例
+    /// 期望值不经本库生成：签名串按文档规则拼接后用`openssl dgst -sha256 -sign`签名，
+    /// 各参数值按RFC 3986做百分号编码（与Python的`urllib.parse.quote(v, safe='')`一致）
+    /// </pre>
+    fn golden_client() -> (AlipayClient<SimpleStorage>, chrono::NaiveDateTime) {
+        let client = AlipayClient::<SimpleStorage>::new("2014072300007148", false).set_private_key(crate::testkit::alipay::PRIVATE_KEY).unwrap();
+        let timestamp = chrono::NaiveDate::from_ymd_opt(2022, 8, 2).unwrap().and_hms_opt(21, 23, 0).unwrap();
+        (client, timestamp)
+    }
+
+    #[test]
+    fn test_build_page_pay_url_golden() {
+        let (client, timestamp) = golden_client();
+        let mut req = AlipayTradePagePayRequest::<AlipayTradePagePayModel>::default();
+        req.put_other_text_param("biz_content".to_string(), r#"{"out_trade_no":"20150320010101001","product_code":"FAST_INSTANT_TRADE_PAY","subject":"Iphone6 16G","total_amount":"88.88"}"#.to_string());
+        req.return_url = "https://example.com/return?a=1".to_string().into();
+        req.notify_url = "https://example.com/notify".to_string().into();
+        let url = client.build_page_pay_url_at(req, timestamp).unwrap();
+        assert_eq!(url, concat!(
+            "https://openapi.alipay.com/gateway.do?app_id=2014072300007148",
+            "&biz_content=%7B%22out_trade_no%22%3A%2220150320010101001%22%2C%22product_code%22%3A%22FAST_INSTANT_TRADE_PAY%22%2C%22subject%22%3A%22Iphone6%2016G%22%2C%22total_amount%22%3A%2288.88%22%7D",
+            "&charset=UTF-8&format=json&method=alipay.trade.page.pay&notify_url=https%3A%2F%2Fexample.com%2Fnotify&return_url=https%3A%2F%2Fexample.com%2Freturn%3Fa%3D1",
+            "&sign=ZJ%2BX9xt%2B%2BmKwxguJrKSshz4%2F3knAwCu%2BSVHYdNphd2QspE4l6RepxiqDNAkJcKAqUJJ91%2FC3QZXH5hkRrEFDX%2FazbXaRWmuVgb3vgbmgdkQvthX4ZHIt6GFVYE9vP6ZLk4KuxYhtDcGo5eCjaD2S17APEBwEmdJTqGG%2F6TI7j%2FWrD6YeV4e5y5I0ZjfkosEoQ8WIBLgCtVmPBm1pt%2BHHupzGL%2FC2kHcggCpuvYX7IKqZWuoYqlT3vWhscbutMuj9mFF1StnMHt52afdnFznFyoYxpe0MxYl0G%2BaC6eTvIQLCqr4quKgnd6BsYTU6W0yUIjJFhjQlesxv%2BEngbhxtFA%3D%3D",
+            "&sign_type=RSA2&timestamp=2022-08-02%2021%3A23%3A00&version=1.0",
+        ));
+    }
+
+    #[test]
+    fn test_build_page_pay_url_golden_percent_encode() {
+        let (client, timestamp) = golden_client();
+        let mut req = AlipayTradePagePayRequest::<AlipayTradePagePayModel>::default();
+        req.put_other_text_param("biz_content".to_string(), r#"{"out_trade_no":"20150320010101002","product_code":"FAST_INSTANT_TRADE_PAY","subject":"测试商品+/配件","total_amount":"0.01"}"#.to_string());
+        req.return_url = "https://example.com/return?a=1+2&b=/c".to_string().into();
+        let url = client.build_page_pay_url_at(req, timestamp).unwrap();
+        // 非ASCII字符按UTF-8编码，`+`、`/`、`&`均需编码，签名中的`+`、`/`、`=`同样编码
+        assert_eq!(url, concat!(
+            "https://openapi.alipay.com/gateway.do?app_id=2014072300007148",
+            "&biz_content=%7B%22out_trade_no%22%3A%2220150320010101002%22%2C%22product_code%22%3A%22FAST_INSTANT_TRADE_PAY%22%2C%22subject%22%3A%22%E6%B5%8B%E8%AF%95%E5%95%86%E5%93%81%2B%2F%E9%85%8D%E4%BB%B6%22%2C%22total_amount%22%3A%220.01%22%7D",
+            "&charset=UTF-8&format=json&method=alipay.trade.page.pay&return_url=https%3A%2F%2Fexample.com%2Freturn%3Fa%3D1%2B2%26b%3D%2Fc",
+            "&sign=HfPtw9yuYQ%2FxpzpFuYk9%2FXcjQDIJSyR0tLgy045mMCmtuk0DEnaChKyx6EH8fnsOYzY0%2Bh7FmzrjpoJ7OfCB0Ahl83mYay0N3lF4CtLGs32o0uk3gMiDm2RLjS56SRG6dqE7ad%2FlycvKYDMOi%2F1yz4fH6bDcurhjCnFonFIremXw%2FPw1ua9zG2sdvHcBtQXNyoX9rIDp75zIAFuCL%2FrHUXgxHIOEqOxtRx0xcadHzTvqiGXBvZGkPPA13riWw4AMuvYCAUG%2BgVurfsIAoKmbSu5ZW%2FVYp91SfTqiBhnUotWcWhI0Ji8ZJM7fTyS%2Blk2kqCw4VuY2rzVK1UV0Igyh4w%3D%3D",
+            "&sign_type=RSA2&timestamp=2022-08-02%2021%3A23%3A00&version=1.0",
+        ));
+    }
+
+    #[test]
+    fn test_build_wap_pay_form_golden() {
+        let (client, timestamp) = golden_client();
+        let mut req = AlipayTradeWapPayRequest::<AlipayTradeWapPayModel>::default();
+        req.put_other_text_param("biz_content".to_string(), r#"{"out_trade_no":"20150320010101001","product_code":"QUICK_WAP_WAY","subject":"Iphone6 16G","total_amount":"88.88"}"#.to_string());
+        req.return_url = "https://example.com/return?a=1".to_string().into();
+        req.notify_url = "https://example.com/notify".to_string().into();
+        let form = client.build_wap_pay_form_at(req, timestamp).unwrap();
+        assert_eq!(form, concat!(
+            "<form name=\"punchout_form\" method=\"post\" action=\"https://openapi.alipay.com/gateway.do?app_id=2014072300007148&charset=UTF-8&method=alipay.trade.wap.pay",
+            "&notify_url=https%3A%2F%2Fexample.com%2Fnotify&return_url=https%3A%2F%2Fexample.com%2Freturn%3Fa%3D1",
+            "&sign=KbAcfpEGduH%2BuWHHQ%2Bh3Ny%2FZ3%2FlF%2BwRSvxVsSRJBenpIyUPLA86cRm9nHpoPAIkjHt19ldgT2YoOB5EAyhit9QaZQ0fDmqIFT6vGBWLlMtV%2FCh0m98JLcMOd1TxeDA%2FFH6dAhoKvLvejieKKw4TxwqMRUMCL2LJ4zS5A3GtPqQqEKe5M2TM%2Bu238c71WVJFQs9nD7x1%2FgnWUUyAoEtjrZdoukBL3q6%2Fbu4AYYHiZu5WXV%2Fa0tU5jxl2RYNTw0EFmVozsA1AF%2FZYBWP8B9ccsZob%2FsISQQCcCD5o6J7tmGMKF73JC96MeLKHFd5Ee0Cp6XAzrrnkg6f7E3selpjcrnw%3D%3D",
+            "&sign_type=RSA2&timestamp=2022-08-02%2021%3A23%3A00&version=1.0&format=json\">\n",
+            "<input type=\"hidden\" name=\"biz_content\" value=\"{&quot;out_trade_no&quot;:&quot;20150320010101001&quot;,&quot;product_code&quot;:&quot;QUICK_WAP_WAY&quot;,&quot;subject&quot;:&quot;Iphone6 16G&quot;,&quot;total_amount&quot;:&quot;88.88&quot;}\">\n",
+            "<input type=\"submit\" value=\"立即支付\" style=\"display:none\" >\n",
+            "</form>\n",
+            "<script>document.forms[0].submit();</script>",
+        ));
+    }
+
+    #[test]
+    fn test_build_page_pay_url_gbk() {
+        let (client, _) = test_client();
+        let client = client.set_charset("GBK");
+        assert!(client.build_page_pay_url(AlipayTradePagePayRequest::default()).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_alipay_phone_number() {
+        let mobile = decrypt_alipay_phone_number("vnDdZDJPOfucbFtb+ibKH9g5M3RuAzg8iHVMcl2bWESg71Hk/JNyAXNEUnGQdrd2zV71WLAFRRXolXhV8ZOdVA==", AES_KEY).unwrap();
+        assert_eq!(mobile.code, "10000");
+        assert_eq!(mobile.mobile.unwrap(), "13800138000");
+        let err = decrypt_alipay_phone_number("5GAdf7bfTmGGiDd71X6OqJgPICrbnkgpkT2HDnIs+gLPX0mlls8q040j8TNrnKj4HMghq1FAUFugFEb5d9l4WxLkkT5oPUl8hqSvYfWlvjai7CTYK788mgyI64fsuPWQEjk/+KzQb3XX3dYERo4vXiq97eCw7/Vco+8cIHgb5pk=", AES_KEY).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "40004" && errmsg == "无效的授权关系"));
+        assert!(decrypt_alipay_phone_number("vnDdZDJPOfucbFtb+ibKH9g5M3RuAzg8iHVMcl2bWESg71Hk/JNyAXNEUnGQdrd2zV71WLAFRRXolXhV8ZOdVA==", "AAAAAAAAAAAAAAAAAAAAAA==").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_excute_with_encrypt() {
+        // 支付宝公钥与应用私钥使用同一对密钥，便于在测试中对返回结果签名
+        let rsa = Rsa::generate(2048).unwrap();
+        let pkey = PKey::from_rsa(rsa.clone()).unwrap();
+        let content = r#"{"code":"10000","msg":"Success","out_trade_no":"20150320010101001"}"#;
+        let ciphertext = "\"".to_string() + &crate::prp::PrpCrypto::new(base64::decode(AES_KEY).unwrap()).aes_128_cbc_base64_encrypt(content).unwrap() + "\"";
+        // 签名针对密文（含引号）计算
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        signer.update(ciphertext.as_bytes()).unwrap();
+        let sign = base64::encode(signer.sign_to_vec().unwrap());
+        let body = format!(r#"{{"alipay_trade_query_response":{},"sign":"{}"}}"#, ciphertext, sign);
+        let server = MockServer::start(vec![MockResponse::json(&body), MockResponse::json(&body)]).await;
+
+        let mut client = AlipayClient::<SimpleStorage>::new("2021000000000000", false)
+            .set_private_key(&base64::encode(rsa.private_key_to_der().unwrap())).unwrap()
+            .set_alipay_public_key(&base64::encode(rsa.public_key_to_der().unwrap()))
+            .set_encrypt_key(AES_KEY);
+        client.api_client.api_path = server.url.to_owned();
+        let mut req = AlipayTradeQueryRequest::<AlipayTradeQueryModel>::new();
+        let model = AlipayTradeQueryModel { out_trade_no: "20150320010101001".to_string().into(), ..Default::default() };
+        let biz_content = serde_json::to_string(&model).unwrap();
+        req.need_encrypt = true;
+        req.biz_model = model.into();
+        let resp = client.excute(req, None, None, None).await.unwrap();
+        assert!(resp.is_success());
+        assert_eq!(resp.body.unwrap(), content);
+
+        let request = &server.requests()[0];
+        assert!(request.contains("encrypt_type=AES"));
+        let (_, form) = request.split_once("\r\n\r\n").unwrap();
+        let form = serde_urlencoded::from_str::<BTreeMap<String, String>>(form).unwrap();
+        let encrypted = form.get("biz_content").unwrap();
+        assert_ne!(encrypted, &biz_content);
+        assert_eq!(crate::prp::PrpCrypto::new(base64::decode(AES_KEY).unwrap()).aes_128_cbc_base64_decrypt(encrypted).unwrap(), biz_content);
+
+        // 未设置加密密钥时无法解密返回结果
+        let mut client = AlipayClient::<SimpleStorage>::new("2021000000000000", false)
+            .set_private_key(&base64::encode(rsa.private_key_to_der().unwrap())).unwrap()
+            .set_alipay_public_key(&base64::encode(rsa.public_key_to_der().unwrap()));
+        client.api_client.api_path = server.url.to_owned();
+        let err = client.excute(AlipayTradeQueryRequest::<AlipayTradeQueryModel>::new(), None, None, None).await.unwrap_err();
+        assert!(matches!(err, LabraError::ApiError(_)));
+    }
+}
@@ -0,0 +1,255 @@
+//!
+//! 开放平台消息
+//!
+//! 支付宝以表单POST的方式将授权变更、服务市场订单等消息推送到应用网关，商户验签后需返回`success`，否则支付宝会重试。
+//! 交易状态的异步通知（trade_status_sync）见[`AlipayClient::parse_order_notify`](crate::AlipayClient::parse_order_notify)。
+//!
+use std::collections::BTreeMap;
+
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Verifier;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::{LabraError, LabradorResult};
+use crate::alipay::constants::{SIGN, SIGN_TYPE, SIGN_TYPE_RSA};
+
+/// 用户签约（如周期扣款协议）
+const NOTIFY_TYPE_USER_SIGN: &str = "dut_user_sign";
+/// 用户解约
+const NOTIFY_TYPE_USER_UNSIGN: &str = "dut_user_unsign";
+/// 服务市场订购
+const NOTIFY_TYPE_SERVICEMARKET_ORDER: &str = "servicemarket_order_notify";
+/// 应答内容，返回其它内容时支付宝视为处理失败并重试
+const ACK_SUCCESS: &str = "success";
+
+/// 应用网关收到的消息
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlipayOpenMessage {
+    /// 用户签约
+    UserSign(AlipayUserAgreementNotify),
+    /// 用户解约
+    UserUnsign(AlipayUserAgreementNotify),
+    /// 服务市场订购
+    ServiceMarketOrder(AlipayServiceMarketOrderNotify),
+    /// 其它消息，notify_type取自notify_type参数，没有时取msg_method
+    Unknown {
+        notify_type: String,
+        params: BTreeMap<String, String>,
+    },
+}
+
+impl AlipayOpenMessage {
+    /// <pre>
+    /// 验签并解析应用网关收到的消息
+    /// form_params为URL解码后的表单参数，alipay_public_key为支付宝公钥（base64，不含PEM头尾）
+    /// 验签串按参数名排序拼接，剔除sign与空值；异步通知不含sign_type，开放平台消息（msg_method）则包含sign_type，这里依次尝试
+    /// </pre>
+    pub fn parse_and_verify(form_params: &BTreeMap<String, String>, alipay_public_key: &str) -> LabradorResult<AlipayOpenMessage> {
+        let sign = form_params.get(SIGN).filter(|sign| !sign.is_empty())
+            .ok_or_else(|| LabraError::InvalidSignature("消息缺少sign参数！".to_string()))?;
+        let sign_type = form_params.get(SIGN_TYPE).map(String::as_str).unwrap_or_default();
+        let digest = if sign_type == SIGN_TYPE_RSA { MessageDigest::sha1() } else { MessageDigest::sha256() };
+        let verified = [false, true].iter().any(|with_sign_type| {
+            let content = sign_content(form_params, *with_sign_type);
+            verify(&content, sign, alipay_public_key, digest).unwrap_or(false)
+        });
+        if !verified {
+            return Err(LabraError::InvalidSignature("开放平台消息验签失败！".to_string()));
+        }
+        let notify_type = form_params.get("notify_type").or_else(|| form_params.get("msg_method")).cloned().unwrap_or_default();
+        let message = match notify_type.as_str() {
+            NOTIFY_TYPE_USER_SIGN => AlipayOpenMessage::UserSign(from_params(form_params)?),
+            NOTIFY_TYPE_USER_UNSIGN => AlipayOpenMessage::UserUnsign(from_params(form_params)?),
+            NOTIFY_TYPE_SERVICEMARKET_ORDER => AlipayOpenMessage::ServiceMarketOrder(from_params(form_params)?),
+            _ => AlipayOpenMessage::Unknown { notify_type, params: form_params.to_owned() },
+        };
+        Ok(message)
+    }
+
+    /// 处理成功后返回给支付宝的应答
+    pub fn render_ack() -> String {
+        ACK_SUCCESS.to_string()
+    }
+}
+
+/// 用户签约、解约通知
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlipayUserAgreementNotify {
+    /// 通知校验ID
+    pub notify_id: String,
+    /// 通知的发送时间，格式为yyyy-MM-dd HH:mm:ss
+    pub notify_time: String,
+    /// 支付宝分配给开发者的应用ID
+    pub app_id: Option<String>,
+    /// 支付宝系统中用以唯一标识用户签约记录的编号
+    pub agreement_no: String,
+    /// 商户签约号
+    pub external_agreement_no: Option<String>,
+    /// 用户的支付宝账号对应的支付宝唯一用户号，以2088开头的16位纯数字
+    pub alipay_user_id: Option<String>,
+    /// 用户的支付宝登录账号（脱敏）
+    pub alipay_logon_id: Option<String>,
+    /// 协议产品码
+    pub personal_product_code: Option<String>,
+    /// 签约场景
+    pub sign_scene: Option<String>,
+    /// 协议当前状态：TEMP 暂存，NORMAL 正常，STOP 暂停，UNSIGN 解约
+    pub status: Option<String>,
+    /// 签约时间
+    pub sign_time: Option<String>,
+    /// 协议生效时间
+    pub valid_time: Option<String>,
+    /// 协议失效时间
+    pub invalid_time: Option<String>,
+    /// 解约时间
+    pub unsign_time: Option<String>,
+    /// 用户在商户网站的登录账号
+    pub external_logon_id: Option<String>,
+    /// 签约商户的支付宝用户号
+    pub partner_id: Option<String>,
+}
+
+/// 服务市场订购通知
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlipayServiceMarketOrderNotify {
+    /// 通知校验ID
+    pub notify_id: String,
+    /// 通知的发送时间，格式为yyyy-MM-dd HH:mm:ss
+    pub notify_time: String,
+    /// 订购服务的订单号
+    pub commodity_order_id: String,
+    /// 订购的服务商品ID
+    pub service_code: Option<String>,
+    /// 订购服务的商户支付宝用户号
+    pub merchant_pid: Option<String>,
+    /// 订购时间
+    pub order_time: Option<String>,
+    /// 订单明细数量
+    pub order_item_num: Option<String>,
+    /// 订单总价，单位为元
+    pub total_price: Option<String>,
+    /// 商品名称
+    pub name: Option<String>,
+    /// 订购的规格名称
+    pub title: Option<String>,
+    /// 联系人
+    pub contactor: Option<String>,
+    /// 联系电话
+    pub phone: Option<String>,
+    /// 业务类型
+    pub biz_type: Option<String>,
+}
+
+fn sign_content(params: &BTreeMap<String, String>, with_sign_type: bool) -> String {
+    params.iter()
+        .filter(|(k, v)| !k.is_empty() && !v.is_empty() && k.as_str() != SIGN && (with_sign_type || k.as_str() != SIGN_TYPE))
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>().join("&")
+}
+
+fn verify(content: &str, sign: &str, public_key: &str, digest: MessageDigest) -> LabradorResult<bool> {
+    let pkey = PKey::public_key_from_der(&base64::decode(public_key.trim())?)?;
+    let mut verifier = Verifier::new(digest, &pkey)?;
+    verifier.update(content.as_bytes())?;
+    Ok(verifier.verify(&base64::decode(sign)?)?)
+}
+
+fn from_params<T: DeserializeOwned>(params: &BTreeMap<String, String>) -> LabradorResult<T> {
+    Ok(serde_json::from_value(serde_json::to_value(params)?)?)
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::LabraError;
+    use crate::alipay::rsa2_sign;
+    use crate::testkit::alipay::{PRIVATE_KEY, PUBLIC_KEY};
+    use super::{sign_content, AlipayOpenMessage};
+
+    fn signed(params: &[(&str, &str)], with_sign_type: bool) -> BTreeMap<String, String> {
+        let mut params = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<String, String>>();
+        let sign = rsa2_sign(&sign_content(&params, with_sign_type), PRIVATE_KEY).unwrap();
+        params.insert("sign".to_string(), sign);
+        params
+    }
+
+    fn user_sign() -> BTreeMap<String, String> {
+        signed(&[
+            ("notify_id", "4ff76eb4ff9e5bf6b3f3c0d1d8b04dbk2u"), ("notify_time", "2017-04-26 15:44:30"), ("notify_type", "dut_user_sign"),
+            ("sign_type", "RSA2"), ("charset", "UTF-8"), ("app_id", "2015101400446982"), ("agreement_no", "20170322450983769228"),
+            ("external_agreement_no", "test"), ("alipay_user_id", "2088101122675263"), ("alipay_logon_id", "hel***@alitest.com"),
+            ("personal_product_code", "GENERAL_WITHHOLDING_P"), ("sign_scene", "INDUSTRY|DEFAULT_SCENE"), ("status", "NORMAL"),
+            ("sign_time", "2017-04-26 15:44:30"), ("valid_time", "2017-04-26 15:44:30"), ("invalid_time", "2115-02-01 00:00:00"),
+            ("partner_id", "2088101122675263"),
+        ], false)
+    }
+
+    #[test]
+    fn test_parse_user_sign() {
+        match AlipayOpenMessage::parse_and_verify(&user_sign(), PUBLIC_KEY).unwrap() {
+            AlipayOpenMessage::UserSign(notify) => {
+                assert_eq!(notify.agreement_no, "20170322450983769228");
+                assert_eq!(notify.external_agreement_no.as_deref(), Some("test"));
+                assert_eq!(notify.status.as_deref(), Some("NORMAL"));
+                assert_eq!(notify.unsign_time, None);
+            }
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn test_parse_unsign_and_order() {
+        let params = signed(&[
+            ("notify_id", "8c18b2d8e0b1ba3f86bb2e24d60b9a5k2u"), ("notify_time", "2017-05-02 10:01:12"), ("notify_type", "dut_user_unsign"),
+            ("sign_type", "RSA2"), ("agreement_no", "20170322450983769228"), ("status", "UNSIGN"), ("unsign_time", "2017-05-02 10:01:12"),
+        ], false);
+        assert!(matches!(AlipayOpenMessage::parse_and_verify(&params, PUBLIC_KEY).unwrap(),
+            AlipayOpenMessage::UserUnsign(notify) if notify.unsign_time.as_deref() == Some("2017-05-02 10:01:12")));
+
+        let params = signed(&[
+            ("notify_id", "2017050200222153011054210522588419"), ("notify_time", "2017-05-02 15:04:05"), ("notify_type", "servicemarket_order_notify"),
+            ("sign_type", "RSA2"), ("commodity_order_id", "20170502000000000000000000289471"), ("merchant_pid", "2088102169952386"),
+            ("order_time", "2017-05-02 15:04:03"), ("order_item_num", "1"), ("total_price", "0.00"), ("service_code", "APPEX20170502000001"),
+            ("name", "测试服务"), ("title", "基础版"), ("contactor", "张三"), ("phone", "13800000000"),
+        ], false);
+        match AlipayOpenMessage::parse_and_verify(&params, PUBLIC_KEY).unwrap() {
+            AlipayOpenMessage::ServiceMarketOrder(notify) => {
+                assert_eq!(notify.commodity_order_id, "20170502000000000000000000289471");
+                assert_eq!(notify.total_price.as_deref(), Some("0.00"));
+                assert_eq!(notify.name.as_deref(), Some("测试服务"));
+            }
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn test_parse_open_message() {
+        // 开放平台消息的sign_type参与签名
+        let params = signed(&[
+            ("msg_method", "alipay.open.auth.userauth.cancelled"), ("app_id", "2021000000000000"), ("version", "1.1"), ("charset", "UTF-8"),
+            ("notify_id", "2022080100222153011054210522588419"), ("utc_timestamp", "1659336000000"), ("sign_type", "RSA2"),
+            ("biz_content", r#"{"cancel_time":"2022-08-01 15:00:00","user_id":"2088102169952386"}"#),
+        ], true);
+        match AlipayOpenMessage::parse_and_verify(&params, PUBLIC_KEY).unwrap() {
+            AlipayOpenMessage::Unknown { notify_type, params } => {
+                assert_eq!(notify_type, "alipay.open.auth.userauth.cancelled");
+                assert_eq!(params["utc_timestamp"], "1659336000000");
+            }
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+        assert_eq!(AlipayOpenMessage::render_ack(), "success");
+    }
+
+    #[test]
+    fn test_bad_signature() {
+        let mut params = user_sign();
+        params.insert("agreement_no".to_string(), "20170322450983769229".to_string());
+        assert!(matches!(AlipayOpenMessage::parse_and_verify(&params, PUBLIC_KEY), Err(LabraError::InvalidSignature(_))));
+        params.remove("sign");
+        assert!(matches!(AlipayOpenMessage::parse_and_verify(&params, PUBLIC_KEY), Err(LabraError::InvalidSignature(_))));
+    }
+}
@@ -0,0 +1,424 @@
+//!
+//! 支付宝卡券（会员卡、票券等）
+//!
+//! 先通过`add_template`创建模板，再按模板发放卡券实例（`add_instance`），核销、关闭时更新实例状态（`update_instance`）。
+//! 卡券接口的业务结果放在返回的`result`字段中，且该字段本身是JSON字符串，这里统一做二次解析。
+//!
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{session::SessionStore, AlipayClient, AlipayRequest, LabraError, LabradorResult};
+use crate::alipay::constants::BIZ_CONTENT_KEY;
+use crate::alipay::method::AlipayMethod;
+
+/// 支付宝卡券
+#[derive(Debug, Clone)]
+pub struct AlipayPass<T: SessionStore> {
+    client: AlipayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> AlipayPass<T> {
+
+    #[inline]
+    pub fn from_client(client: AlipayClient<T>) -> AlipayPass<T> {
+        AlipayPass {
+            client,
+        }
+    }
+
+    /// # 卡券模板创建
+    /// <pre>
+    /// tpl_content为模板内容（含logo、strip等file_info，style样式与fields字段定义），原样作为JSON字符串提交
+    /// </pre>
+    /// 详见 [文档](https://opendocs.alipay.com/open/01fxh2)
+    pub async fn add_template(&self, unique_id: &str, tpl_content: &Value) -> LabradorResult<AlipayPassTemplate> {
+        let model = AlipayPassTemplateAddModel {
+            unique_id: unique_id.to_string(),
+            tpl_content: serde_json::to_string(tpl_content)?,
+        };
+        self.excute(AlipayMethod::PassTemplateAdd, model).await
+    }
+
+    /// # 卡券实例发放
+    /// <pre>
+    /// 按模板发放卡券，tpl_params为模板中定义的变量，recognition_info为用户识别信息，需与recognition_type对应
+    /// </pre>
+    /// 详见 [文档](https://opendocs.alipay.com/open/01fxh3)
+    pub async fn add_instance(&self, tpl_id: &str, tpl_params: &Value, recognition_info: AlipayPassRecognition) -> LabradorResult<AlipayPassInstance> {
+        let model = AlipayPassInstanceAddModel {
+            tpl_id: tpl_id.to_string(),
+            tpl_params: serde_json::to_string(tpl_params)?,
+            recognition_type: recognition_info.recognition_type().to_string(),
+            recognition_info: recognition_info.to_json()?,
+        };
+        self.excute(AlipayMethod::PassInstanceAdd, model).await
+    }
+
+    /// # 卡券实例更新
+    /// <pre>
+    /// 更新卡券的模板变量或状态（核销USED、关闭CLOSED），serial_number为发放时的商户券号，channel_id为商户的appid
+    /// </pre>
+    /// 详见 [文档](https://opendocs.alipay.com/open/01fxh4)
+    pub async fn update_instance(&self, req: AlipayPassInstanceUpdateModel) -> LabradorResult<AlipayPassInstance> {
+        self.excute(AlipayMethod::PassInstanceUpdate, req).await
+    }
+
+    async fn excute<M: Serialize, D: DeserializeOwned>(&self, method: AlipayMethod, model: M) -> LabradorResult<D> {
+        let resp = self.client.excute(AlipayPassRequest::new(method, model), None, None, None).await?;
+        resp.get_biz_model::<AlipayPassResponse>()?.parse_result::<D>()
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 卡券接口的请求，三个接口只有方法名与业务参数不同，其他同样只需业务参数的接口（如应用授权令牌查询）也可使用
+#[derive(Debug)]
+pub struct AlipayPassRequest<T: Serialize> {
+    /// API版本
+    pub api_version: String,
+    /// 接口方法
+    pub method: AlipayMethod,
+    /// 参数
+    pub udf_params: BTreeMap<String, String>,
+    /// 业务实体
+    pub biz_model: Option<T>
+}
+
+impl <T> AlipayPassRequest<T> where T: Serialize {
+    pub fn new(method: AlipayMethod, biz_model: T) -> Self {
+        Self {
+            api_version: "1.0".to_string(),
+            method,
+            udf_params: BTreeMap::new(),
+            biz_model: biz_model.into(),
+        }
+    }
+
+    pub fn put_other_text_param(&mut self, key: String, value: String) {
+        self.udf_params.insert(key, value);
+    }
+}
+
+impl <T> AlipayRequest<T> for AlipayPassRequest<T> where T: Serialize {
+    fn get_api_method_name(&self) -> AlipayMethod {
+        self.method.to_owned()
+    }
+
+    fn get_text_params(&self) -> BTreeMap<String, String> {
+        let mut txt_params = BTreeMap::new();
+        txt_params.insert(BIZ_CONTENT_KEY.to_string(), serde_json::to_string(&self.get_biz_model()).unwrap_or_default());
+        for (k, v) in &self.udf_params {
+            txt_params.insert(k.to_string(), v.to_string());
+        }
+        txt_params
+    }
+
+    fn get_api_version(&self) -> String {
+        if self.api_version.is_empty() {
+            "1.0".to_string()
+        } else {
+            self.api_version.to_string()
+        }
+    }
+
+    fn get_terminal_type(&self) -> String {
+        String::default()
+    }
+
+    fn get_terminal_info(&self) -> String {
+        String::default()
+    }
+
+    fn get_prod_code(&self) -> String {
+        String::default()
+    }
+
+    fn get_notify_url(&self) -> String {
+        String::default()
+    }
+
+    fn get_return_url(&self) -> String {
+        String::default()
+    }
+
+    fn is_need_encrypt(&self) -> bool {
+        false
+    }
+
+    fn get_biz_content(self) -> String {
+        String::default()
+    }
+
+    fn get_biz_model(&self) -> Option<&T> {
+        self.biz_model.as_ref()
+    }
+}
+
+/// 卡券模板创建
+#[derive(Debug, Serialize, Default, Deserialize)]
+pub struct AlipayPassTemplateAddModel {
+    /// 商户用于控制模板的唯一性，可以使用时间戳保证唯一性
+    pub unique_id: String,
+    /// 模板内容信息（JSON字符串）
+    pub tpl_content: String,
+}
+
+/// 卡券实例发放
+#[derive(Debug, Serialize, Default, Deserialize)]
+pub struct AlipayPassInstanceAddModel {
+    /// 支付宝卡券模板id
+    pub tpl_id: String,
+    /// 模板变量（JSON字符串）
+    pub tpl_params: String,
+    /// 用户识别方式
+    pub recognition_type: String,
+    /// 用户识别信息（JSON字符串）
+    pub recognition_info: String,
+}
+
+/// 卡券实例更新
+#[derive(Debug, Serialize, Default, Deserialize)]
+pub struct AlipayPassInstanceUpdateModel {
+    /// 商户指定卡券唯一值，即发放时模板变量中的serialNumber
+    pub serial_number: String,
+    /// 代理商代替商户发放卡券后，再代替商户更新卡券时，此值为商户的pid/appid
+    pub channel_id: String,
+    /// 需要修改的模板变量（JSON字符串）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tpl_params: Option<String>,
+    /// 卡券状态，支持更新为USED（已使用）、CLOSED（关闭）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<AlipayPassStatus>,
+    /// 核销码串值，status为USED时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_code: Option<String>,
+    /// 核销方式，status为USED时必填，目前支持wave（声波）、qrcode（二维码）、barcode（条码）、input（文本）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_type: Option<String>,
+}
+
+impl AlipayPassInstanceUpdateModel {
+
+    pub fn new(serial_number: &str, channel_id: &str) -> Self {
+        AlipayPassInstanceUpdateModel {
+            serial_number: serial_number.to_string(),
+            channel_id: channel_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 修改模板变量
+    pub fn tpl_params(mut self, tpl_params: &Value) -> LabradorResult<Self> {
+        self.tpl_params = serde_json::to_string(tpl_params)?.into();
+        Ok(self)
+    }
+
+    /// 核销卡券
+    pub fn used(mut self, verify_code: &str, verify_type: &str) -> Self {
+        self.status = AlipayPassStatus::Used.into();
+        self.verify_code = verify_code.to_string().into();
+        self.verify_type = verify_type.to_string().into();
+        self
+    }
+
+    /// 关闭卡券
+    pub fn closed(mut self) -> Self {
+        self.status = AlipayPassStatus::Closed.into();
+        self
+    }
+}
+
+/// 卡券实例的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AlipayPassStatus {
+    /// 已使用
+    Used,
+    /// 关闭
+    Closed,
+}
+
+/// 卡券发放的用户识别信息
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlipayPassRecognition {
+    /// 基于交易识别（recognition_type为1），partner_id为商户的pid，out_trade_no为支付宝交易的商户订单号
+    Trade { partner_id: String, out_trade_no: String },
+    /// 基于支付宝用户id识别（recognition_type为3）
+    UserId(String),
+    /// 其他识别方式，原样提交
+    Other { recognition_type: String, recognition_info: Value },
+}
+
+impl AlipayPassRecognition {
+
+    pub fn recognition_type(&self) -> &str {
+        match self {
+            AlipayPassRecognition::Trade { .. } => "1",
+            AlipayPassRecognition::UserId(_) => "3",
+            AlipayPassRecognition::Other { recognition_type, .. } => recognition_type,
+        }
+    }
+
+    fn to_json(&self) -> LabradorResult<String> {
+        let v = match self {
+            AlipayPassRecognition::Trade { partner_id, out_trade_no } => serde_json::json!({"partner_id": partner_id, "out_trade_no": out_trade_no}),
+            AlipayPassRecognition::UserId(user_id) => serde_json::json!({"user_id": user_id}),
+            AlipayPassRecognition::Other { recognition_info, .. } => recognition_info.to_owned(),
+        };
+        serde_json::to_string(&v).map_err(LabraError::from)
+    }
+}
+
+/// <pre>
+/// 卡券接口的返回
+/// success可能为布尔值或"true"/"false"字符串，业务结果result（部分文档中为biz_result）为JSON字符串
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlipayPassResponse {
+    /// 操作是否成功
+    pub success: Option<Value>,
+    /// 错误码
+    #[serde(alias = "errorCode")]
+    pub error_code: Option<String>,
+    /// 业务结果（JSON字符串）
+    #[serde(alias = "biz_result")]
+    pub result: Option<String>,
+}
+
+impl AlipayPassResponse {
+
+    pub fn is_success(&self) -> bool {
+        match &self.success {
+            Some(Value::Bool(v)) => *v,
+            Some(Value::String(v)) => v.eq_ignore_ascii_case("true") || v.eq_ignore_ascii_case("T"),
+            _ => false,
+        }
+    }
+
+    /// 二次解析业务结果，失败时以result中的errorCode/errorMsg（缺失时为外层的error_code）作为错误
+    pub fn parse_result<D: DeserializeOwned>(&self) -> LabradorResult<D> {
+        let result = self.result.to_owned().unwrap_or_default();
+        let v = if result.trim().is_empty() { Value::Null } else {
+            serde_json::from_str::<Value>(&result).unwrap_or(Value::String(result.to_owned()))
+        };
+        let error_code = v["errorCode"].as_str().filter(|c| !c.is_empty()).map(str::to_string)
+            .or_else(|| self.error_code.to_owned().filter(|c| !c.is_empty()));
+        if !self.is_success() || error_code.is_some() {
+            let errmsg = v["errorMsg"].as_str().map(str::to_string).unwrap_or_else(|| if v.is_string() { result } else { String::default() });
+            return Err(LabraError::ClientError { errcode: error_code.unwrap_or_default(), errmsg });
+        }
+        serde_json::from_value::<D>(v).map_err(LabraError::from)
+    }
+}
+
+/// 创建的卡券模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlipayPassTemplate {
+    /// 支付宝卡券模板id
+    pub tpl_id: String,
+    /// 模板中定义的变量
+    #[serde(default)]
+    pub tpl_params: Vec<String>,
+}
+
+/// 发放、更新的卡券实例
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlipayPassInstance {
+    /// 支付宝卡券id
+    pub pass_id: Option<String>,
+    /// 商户指定的卡券唯一值
+    pub serial_number: Option<String>,
+    /// 本次操作，如ADD、UPDATE
+    pub operation: Option<String>,
+    pub error_code: Option<String>,
+    pub error_msg: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+    use serde_json::{json, Value};
+
+    use crate::{AlipayClient, LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{AlipayPassInstance, AlipayPassInstanceUpdateModel, AlipayPassRecognition, AlipayPassResponse, AlipayPassTemplate};
+
+    #[test]
+    fn test_parse_result_success() {
+        let resp = serde_json::from_str::<AlipayPassResponse>(r#"{"code":"10000","msg":"Success","success":"true","result":"{\"tpl_id\":\"2015060500\",\"tpl_params\":[\"title\",\"validDate\"]}"}"#).unwrap();
+        let tpl = resp.parse_result::<AlipayPassTemplate>().unwrap();
+        assert_eq!(tpl.tpl_id, "2015060500");
+        assert_eq!(tpl.tpl_params, vec!["title".to_string(), "validDate".to_string()]);
+
+        let resp = serde_json::from_str::<AlipayPassResponse>(r#"{"success":true,"biz_result":"{\"passId\":\"PASS_ID\",\"serialNumber\":\"SN001\",\"operation\":\"ADD\",\"errorCode\":\"\",\"errorMsg\":\"\"}"}"#).unwrap();
+        let instance = resp.parse_result::<AlipayPassInstance>().unwrap();
+        assert_eq!(instance.pass_id.as_deref(), Some("PASS_ID"));
+        assert_eq!(instance.serial_number.as_deref(), Some("SN001"));
+        assert_eq!(instance.operation.as_deref(), Some("ADD"));
+    }
+
+    #[test]
+    fn test_parse_result_failure() {
+        // 失败原因在result的JSON字符串中
+        let resp = serde_json::from_str::<AlipayPassResponse>(r#"{"success":"false","result":"{\"errorCode\":\"PASS_TPL_NOT_EXIST\",\"errorMsg\":\"模板不存在\"}"}"#).unwrap();
+        assert!(matches!(resp.parse_result::<AlipayPassInstance>(), Err(LabraError::ClientError { errcode, errmsg }) if errcode == "PASS_TPL_NOT_EXIST" && errmsg == "模板不存在"));
+        // success为true但result中带有错误码
+        let resp = serde_json::from_str::<AlipayPassResponse>(r#"{"success":true,"result":"{\"serialNumber\":\"SN001\",\"errorCode\":\"SERIAL_NUMBER_EXIST\",\"errorMsg\":\"券号已存在\"}"}"#).unwrap();
+        assert!(matches!(resp.parse_result::<AlipayPassInstance>(), Err(LabraError::ClientError { errcode, .. }) if errcode == "SERIAL_NUMBER_EXIST"));
+        // result不是JSON时原样作为错误信息
+        let resp = serde_json::from_str::<AlipayPassResponse>(r#"{"success":"F","error_code":"SYSTEM_ERROR","result":"system busy"}"#).unwrap();
+        assert!(matches!(resp.parse_result::<AlipayPassInstance>(), Err(LabraError::ClientError { errcode, errmsg }) if errcode == "SYSTEM_ERROR" && errmsg == "system busy"));
+        let resp = serde_json::from_str::<AlipayPassResponse>(r#"{"success":"false"}"#).unwrap();
+        assert!(matches!(resp.parse_result::<AlipayPassInstance>(), Err(LabraError::ClientError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_add_instance() {
+        // 支付宝公钥与应用私钥使用同一对密钥，便于在测试中对返回结果签名
+        let rsa = Rsa::generate(2048).unwrap();
+        let pkey = PKey::from_rsa(rsa.clone()).unwrap();
+        let sign = |content: &str| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+            signer.update(content.as_bytes()).unwrap();
+            base64::encode(signer.sign_to_vec().unwrap())
+        };
+        let add = r#"{"code":"10000","msg":"Success","success":"true","result":"{\"passId\":\"PASS_ID\",\"serialNumber\":\"SN001\",\"operation\":\"ADD\"}"}"#;
+        let update = r#"{"code":"10000","msg":"Success","success":"true","result":"{\"serialNumber\":\"SN001\",\"operation\":\"UPDATE\"}"}"#;
+        let server = MockServer::start(vec![
+            MockResponse::json(&format!(r#"{{"alipay_pass_instance_add_response":{},"sign":"{}"}}"#, add, sign(add))),
+            MockResponse::json(&format!(r#"{{"alipay_pass_instance_update_response":{},"sign":"{}"}}"#, update, sign(update))),
+        ]).await;
+        let mut client = AlipayClient::<SimpleStorage>::new("2021000000000000", false)
+            .set_private_key(&base64::encode(rsa.private_key_to_der().unwrap())).unwrap()
+            .set_alipay_public_key(&base64::encode(rsa.public_key_to_der().unwrap()));
+        client.api_client.api_path = server.url.to_owned();
+
+        let recognition = AlipayPassRecognition::Trade { partner_id: "2088000000000000".to_string(), out_trade_no: "20150320010101001".to_string() };
+        let instance = client.pass().add_instance("2015060500", &json!({"serialNumber": "SN001", "channelID": "2021000000000000"}), recognition).await.unwrap();
+        assert_eq!(instance.pass_id.as_deref(), Some("PASS_ID"));
+        let instance = client.pass().update_instance(AlipayPassInstanceUpdateModel::new("SN001", "2021000000000000").used("8612231273", "qrcode")).await.unwrap();
+        assert_eq!(instance.operation.as_deref(), Some("UPDATE"));
+
+        let requests = server.requests();
+        let form = |request: &str| serde_urlencoded::from_str::<BTreeMap<String, String>>(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+        let biz_content = serde_json::from_str::<Value>(form(&requests[0]).get("biz_content").unwrap()).unwrap();
+        assert_eq!(biz_content["tpl_id"], json!("2015060500"));
+        assert_eq!(biz_content["recognition_type"], json!("1"));
+        assert_eq!(serde_json::from_str::<Value>(biz_content["recognition_info"].as_str().unwrap()).unwrap(), json!({"partner_id": "2088000000000000", "out_trade_no": "20150320010101001"}));
+        assert_eq!(serde_json::from_str::<Value>(biz_content["tpl_params"].as_str().unwrap()).unwrap()["serialNumber"], json!("SN001"));
+        assert!(requests[1].contains("method=alipay.pass.instance.update"));
+        let biz_content = serde_json::from_str::<Value>(form(&requests[1]).get("biz_content").unwrap()).unwrap();
+        assert_eq!(biz_content, json!({"serial_number": "SN001", "channel_id": "2021000000000000", "status": "USED", "verify_code": "8612231273", "verify_type": "qrcode"}));
+    }
+}
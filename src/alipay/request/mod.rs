@@ -105,6 +105,11 @@ impl <T> AlipayRequest<T> for AlipayTradeWapPayRequest<T> where T: Serialize {
     fn get_text_params(&self) -> BTreeMap<String, String> {
         let mut txt_params = BTreeMap::new();
         txt_params.insert(BIZ_CONTENT_KEY.to_string(), serde_json::to_string(&self.get_biz_model()).unwrap_or_default());
+        if !self.udf_params.is_empty() {
+            for (k, v) in &self.udf_params {
+                txt_params.insert(k.to_string(), v.to_string());
+            }
+        }
         txt_params
     }
 
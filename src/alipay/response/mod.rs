@@ -1,10 +1,9 @@
 use std::collections::{BTreeMap};
-use json::JsonValue;
 use serde::{Deserialize, Serialize};
 use serde::de::{DeserializeOwned};
 
 use crate::{errors::LabraError, AlipayResponse, LabradorResult, RequestMethod};
-use crate::alipay::constants::{ERROR_RESPONSE_KEY, SIGN};
+use crate::alipay::envelope::{client_error, AlipayEnvelope};
 
 //----------------------------------------------------------------------------------------------------------------------------
 #[derive(Debug, Deserialize,Serialize)]
@@ -62,29 +61,9 @@ impl AlipayBaseResponse {
         }
     }
 
+    /// 解析返回结果，不验签，见[`AlipayEnvelope`]
     pub fn parse(str: &str,method: impl RequestMethod) -> LabradorResult<Self> {
-        let v= json::parse(str).unwrap_or(JsonValue::Null);
-        let sign = v[SIGN].as_str().unwrap_or_default();
-        // 判断是否异常
-        let err= &v[ERROR_RESPONSE_KEY];
-        if !err.is_empty() && !err.is_null() {
-            let resp = serde_json::from_str::<Self>(&err.to_string()).unwrap_or(AlipayBaseResponse::new());
-            Err(LabraError::ClientError {errcode: resp.code.to_owned().unwrap_or_default(), errmsg: resp.sub_msg.to_owned().unwrap_or_default()})
-        } else {
-            let response = &v[&method.get_response_key()];
-            if !response.is_empty() && !response.is_null() {
-                let mut resp = serde_json::from_str::<Self>(&response.to_string()).unwrap_or(AlipayBaseResponse::new());
-                if resp.code.is_none() {
-                    resp.code = "10000".to_string().into();
-                }
-                resp.sign = sign.to_string().into();
-                resp.body = response.to_string().into();
-                Ok(resp)
-            } else {
-                Err(LabraError::MissingField(format!("无法获取解析返回结果：【{}】", str)))
-            }
-        }
-
+        AlipayEnvelope::new(method).open_response(str)
     }
 
     pub fn is_success(&self) -> bool {
@@ -95,7 +74,7 @@ impl AlipayBaseResponse {
         if self.is_success() {
             serde_json::from_str::<T>(&self.body.to_owned().unwrap_or_default()).map_err(LabraError::from)
         } else {
-            Err(LabraError::ClientError { errcode: self.code.to_owned().unwrap_or_default(), errmsg: self.sub_msg.to_owned().unwrap_or_default() })
+            Err(client_error(self))
         }
     }
 
@@ -851,3 +830,19 @@ pub struct AppTokenExchangeSubElement {
 }
 
 //----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize,Serialize)]
+pub struct AlipayMobileResponse {
+    /// 网关返回码
+    pub code: String,
+    /// 网关返回码描述
+    pub msg: Option<String>,
+    /// 业务返回码
+    pub sub_code: Option<String>,
+    /// 业务返回码描述
+    pub sub_msg: Option<String>,
+    /// 用户绑定的手机号
+    pub mobile: Option<String>,
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use crate::{LabradorResult, RequestParametersHolder, SignDebugReport, SignProblem};
+use crate::alipay::{constants, notify_sign_content, rsa2_sign};
+use crate::util::check_value;
+
+/// <pre>
+/// 排查支付宝请求的签名
+/// params为待签名的全部参数（含公共参数），按传入的顺序检查，签名串与RSA2签名均由正式请求使用的逻辑生成。
+/// 注意：值为空的参数同样会拼接到签名串中，而支付宝服务端会剔除空值参数，请求前应去掉这类参数。
+/// </pre>
+pub fn alipay_sign_debug<I, K, V>(params: I, private_key: &str) -> LabradorResult<SignDebugReport>
+    where I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: AsRef<[u8]> {
+    let mut problems = Vec::new();
+    let mut keys = Vec::new();
+    let mut application_params = BTreeMap::new();
+    for (key, value) in params {
+        let key = key.as_ref();
+        if key == constants::SIGN {
+            problems.push(SignProblem::SignIncluded);
+            continue;
+        }
+        let value = check_value(key, value.as_ref(), &mut problems);
+        keys.push(key.to_string());
+        application_params.insert(key.to_string(), value);
+    }
+    if keys.windows(2).any(|w| w[0] > w[1]) {
+        problems.insert(0, SignProblem::UnsortedKeys);
+    }
+    let mut holder = RequestParametersHolder::new();
+    holder.set_application_params(application_params);
+    let sign_content = holder.get_signature_content();
+    let signature = rsa2_sign(&sign_content, private_key)?;
+    Ok(SignDebugReport { sign_content, signature, problems })
+}
+
+/// <pre>
+/// 排查支付宝异步通知的验签
+/// notify_data为通知的原始请求体（application/x-www-form-urlencoded），验签串由解析通知时使用的逻辑生成，
+/// 报告中的签名为通知携带的sign。验签时需剔除sign与sign_type参数。
+/// </pre>
+pub fn alipay_notify_sign_debug(notify_data: &str) -> LabradorResult<SignDebugReport> {
+    let data = serde_urlencoded::from_str::<BTreeMap<String, String>>(notify_data)?;
+    let signature = data.get(constants::SIGN).map(|v| urlencoding::decode(v).unwrap_or_default().into_owned()).unwrap_or_default();
+    let sign_content = notify_sign_content(&data);
+    let mut problems = Vec::new();
+    for pair in notify_data.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = urlencoding::decode(key).map(|v| v.into_owned()).unwrap_or_else(|_| key.to_string());
+        if key == constants::SIGN_TYPE {
+            problems.push(SignProblem::SignTypeIncluded);
+        } else if key != constants::SIGN && !value.is_empty() {
+            check_value(&key, &urlencoding::decode_binary(value.replace('+', " ").as_bytes()), &mut problems);
+        }
+    }
+    Ok(SignDebugReport { sign_content, signature, problems })
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use openssl::rsa::Rsa;
+
+    use crate::prp::PrpCrypto;
+    use crate::SignProblem;
+    use crate::testkit::alipay;
+    use super::{alipay_notify_sign_debug, alipay_sign_debug};
+
+    fn private_key() -> (String, String) {
+        let rsa = Rsa::generate(2048).unwrap();
+        let public_key = String::from_utf8(rsa.public_key_to_pem().unwrap()).unwrap();
+        (base64::encode(rsa.private_key_to_der().unwrap()), public_key)
+    }
+
+    #[test]
+    fn test_sign_debug() {
+        let (private_key, public_key) = private_key();
+        let params = vec![("app_id", "2014072300007148"), ("biz_content", r#"{"out_trade_no":"20150320010101001"}"#), ("charset", "utf-8"), ("method", "alipay.trade.query"), ("sign_type", "RSA2")];
+        let report = alipay_sign_debug(params, &private_key).unwrap();
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!(report.sign_content, r#"app_id=2014072300007148&biz_content={"out_trade_no":"20150320010101001"}&charset=utf-8&method=alipay.trade.query&sign_type=RSA2"#);
+        assert!(PrpCrypto::rsa_sha256_verify(&public_key, &report.sign_content, &report.signature).unwrap());
+
+        // 官方示例参数
+        alipay::assert_rsa2_key_pair(alipay::PRIVATE_KEY, alipay::PUBLIC_KEY);
+    }
+
+    #[test]
+    fn test_sign_debug_problems() {
+        let (private_key, _) = private_key();
+        let params: Vec<(&str, &[u8])> = vec![
+            ("method", b"alipay.trade.query"),
+            ("app_id", b"2014072300007148"),
+            ("sign", b"ERITJKEIJKJHKKKKKKKHJEREEEEEEEEEEE"),
+            ("notify_url", b"https://example.com/notify "),
+            ("return_url", b""),
+            ("subject", b"\xb2\xe2\xca\xd4"),
+        ];
+        let report = alipay_sign_debug(params, &private_key).unwrap();
+        assert_eq!(report.problems, vec![
+            SignProblem::UnsortedKeys,
+            SignProblem::SignIncluded,
+            SignProblem::Whitespace("notify_url".to_string()),
+            SignProblem::EmptyValue("return_url".to_string()),
+            SignProblem::NonUtf8("subject".to_string()),
+        ]);
+        // 签名串按排序拼接，不含sign
+        assert_eq!(report.sign_content, "app_id=2014072300007148&method=alipay.trade.query&notify_url=https://example.com/notify &return_url=&subject=\u{fffd}\u{fffd}\u{fffd}\u{fffd}");
+        assert!(report.to_string().contains("参数notify_url的值首尾含空白字符"));
+    }
+
+    #[test]
+    fn test_notify_sign_debug() {
+        let notify = "gmt_create=2015-06-11+22%3A51%3A30&notify_id=ac05099524730693a8b330c5ecf72da9786&subject=%E6%B5%8B%E8%AF%95&sign_type=RSA2&sign=ERITJKEIJKJHKKKKKKKHJEREEEEEEEEEEE&trade_status=TRADE_SUCCESS";
+        let report = alipay_notify_sign_debug(notify).unwrap();
+        assert_eq!(report.sign_content, "gmt_create=2015-06-11 22:51:30&notify_id=ac05099524730693a8b330c5ecf72da9786&subject=测试&trade_status=TRADE_SUCCESS");
+        assert_eq!(report.signature, "ERITJKEIJKJHKKKKKKKHJEREEEEEEEEEEE");
+        assert_eq!(report.problems, vec![SignProblem::SignTypeIncluded]);
+
+        let report = alipay_notify_sign_debug("subject=%B2%E2%CA%D4&body=abc%20&sign=SIGN").unwrap();
+        assert_eq!(report.problems, vec![SignProblem::NonUtf8("subject".to_string()), SignProblem::Whitespace("body".to_string())]);
+    }
+}
@@ -0,0 +1,268 @@
+//!
+//! 响应缓存
+//!
+//! 用户详情、部门列表等读多写少的GET接口可以按接口路径配置[`CachePolicy`]，命中后不再请求第三方接口。
+//! 缓存写入客户端的会话存储，使用Redis等共享存储时多个实例共用同一份缓存；只有GET请求会读写缓存，
+//! POST等非幂等请求总是直接发送。
+//!
+//! 缓存键由接口路径与排序后的查询参数组成（不含access_token），如`/cgi-bin/user/get?userid=zhangsan`。
+//! 会话存储无法按前缀列出或删除键，[`ResponseCache::invalidate_prefix`]记录前缀的失效时间，
+//! 读取时缓存早于失效时间的视为未命中。
+//!
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{session::SessionStore, LabradorResult};
+
+/// 缓存键中忽略的凭证参数
+const CREDENTIAL_PARAMS: [&str; 4] = ["access_token", "suite_access_token", "provider_access_token", "component_access_token"];
+
+/// <pre>
+/// 接口的缓存策略
+/// pattern为接口路径，以`*`结尾时按前缀匹配，如`/cgi-bin/department/*`
+/// 多个策略都匹配时使用最先添加的
+/// </pre>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub pattern: String,
+    pub ttl: Duration,
+    /// 最多缓存的条目数，超出后淘汰最早写入的，只统计当前进程写入的条目
+    pub max_entries: Option<usize>,
+}
+
+impl CachePolicy {
+    pub fn new<S: Into<String>>(pattern: S, ttl: Duration) -> Self {
+        CachePolicy {
+            pattern: pattern.into(),
+            ttl,
+            max_entries: None,
+        }
+    }
+
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.into();
+        self
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self.pattern.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => path == self.pattern,
+        }
+    }
+}
+
+/// 写入会话存储的缓存条目
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// 写入时间（纳秒）
+    stored_at: i64,
+    /// 过期时间（纳秒），不依赖存储本身的过期精度
+    expires_at: i64,
+    body: String,
+}
+
+/// 按策略缓存GET接口的响应
+#[derive(Debug, Clone, Default)]
+pub struct ResponseCache {
+    policies: Vec<CachePolicy>,
+    /// 各策略当前进程写入的缓存键，按写入顺序，用于淘汰超出`max_entries`的条目
+    recent: Arc<Mutex<HashMap<usize, VecDeque<String>>>>,
+}
+
+#[allow(unused)]
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn policy(mut self, policy: CachePolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    pub fn policies(&self) -> &[CachePolicy] {
+        &self.policies
+    }
+
+    /// <pre>
+    /// 缓存键：接口路径与排序后的查询参数，不含access_token等凭证
+    /// 路径中自带的查询参数（如`/cgi-bin/user/get?userid=zhangsan`）与params合并
+    /// </pre>
+    pub fn cache_key(url: &str, params: &[(String, String)]) -> String {
+        let (path, query) = match url.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (url, ""),
+        };
+        let mut pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(query).unwrap_or_default();
+        pairs.extend(params.iter().cloned());
+        pairs.retain(|(k, _)| !CREDENTIAL_PARAMS.contains(&k.as_str()));
+        if pairs.is_empty() {
+            return path.to_string();
+        }
+        pairs.sort();
+        format!("{}?{}", path, serde_urlencoded::to_string(&pairs).unwrap_or_default())
+    }
+
+    /// <pre>
+    /// 使前缀下的缓存失效
+    /// 前缀需在路径分隔处截断，如`/cgi-bin/user`、`/cgi-bin/user/get`，
+    /// 也可以是路径加一个查询参数，如`/cgi-bin/user/get?userid=zhangsan`（与参数顺序无关）
+    /// </pre>
+    pub fn invalidate_prefix<T: SessionStore>(&self, session: &T, namespace: &str, key_prefix: &str) -> LabradorResult<()> {
+        let max_ttl = self.policies.iter().map(|policy| policy.ttl).max().unwrap_or_default();
+        let prefix = key_prefix.trim_end_matches(['/', '?', '&']);
+        // 查询参数按缓存键的规则重新编码
+        let prefix = if prefix.contains('?') { Self::cache_key(prefix, &[]) } else { prefix.to_string() };
+        session.set(invalidated_key(namespace, &prefix), now_nanos(), Some(ttl_seconds(max_ttl)))
+    }
+
+    /// 读取缓存，未配置策略、已过期或已失效时返回None
+    pub(crate) fn load<T: SessionStore>(&self, session: &T, namespace: &str, url: &str, params: &[(String, String)]) -> Option<String> {
+        let path = url.split('?').next().unwrap_or_default();
+        self.find_policy(path)?;
+        let key = Self::cache_key(url, params);
+        let entry = session.get::<_, String>(entry_key(namespace, &key), None).ok()??;
+        let entry = serde_json::from_str::<CacheEntry>(&entry).ok()?;
+        if entry.expires_at <= now_nanos() {
+            return None;
+        }
+        for prefix in invalidation_prefixes(&key) {
+            match session.get::<_, i64>(invalidated_key(namespace, &prefix), None) {
+                Ok(Some(invalidated_at)) if entry.stored_at <= invalidated_at => return None,
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!("[响应缓存] 读取{}的失效时间失败:{}", prefix, err);
+                    return None;
+                }
+            }
+        }
+        Some(entry.body)
+    }
+
+    /// 写入缓存，未配置策略的接口忽略
+    pub(crate) fn store<T: SessionStore>(&self, session: &T, namespace: &str, url: &str, params: &[(String, String)], body: String) {
+        let path = url.split('?').next().unwrap_or_default();
+        let (index, policy) = match self.find_policy(path) {
+            Some(v) => v,
+            None => return,
+        };
+        let key = Self::cache_key(url, params);
+        let now = now_nanos();
+        let entry = CacheEntry {
+            stored_at: now,
+            expires_at: now.saturating_add(policy.ttl.as_nanos() as i64),
+            body,
+        };
+        let entry = serde_json::to_string(&entry).unwrap_or_default();
+        if let Err(err) = session.set(entry_key(namespace, &key), entry, Some(ttl_seconds(policy.ttl))) {
+            tracing::warn!("[响应缓存] 写入{}失败:{}", key, err);
+            return;
+        }
+        let max_entries = match policy.max_entries {
+            Some(v) => v,
+            None => return,
+        };
+        let evicted = {
+            let mut recent = self.recent.lock().unwrap_or_else(|err| err.into_inner());
+            let keys = recent.entry(index).or_default();
+            keys.retain(|v| v != &key);
+            keys.push_back(key);
+            let count = keys.len().saturating_sub(max_entries);
+            keys.drain(..count).collect::<Vec<_>>()
+        };
+        for key in evicted {
+            // 无法删除键，写入空值使其不再命中
+            let _ = session.set(entry_key(namespace, &key), String::default(), Some(1));
+        }
+    }
+
+    fn find_policy(&self, path: &str) -> Option<(usize, &CachePolicy)> {
+        self.policies.iter().enumerate().find(|(_, policy)| policy.matches(path))
+    }
+}
+
+fn entry_key(namespace: &str, key: &str) -> String {
+    format!("labrador_cache:{}:{}", namespace, key)
+}
+
+fn invalidated_key(namespace: &str, prefix: &str) -> String {
+    format!("labrador_cache_invalidated:{}:{}", namespace, prefix)
+}
+
+/// 缓存键可能被失效的前缀：各级路径、路径加单个查询参数，以及完整的键
+fn invalidation_prefixes(key: &str) -> Vec<String> {
+    let (path, query) = match key.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (key, None),
+    };
+    let mut prefixes = vec![String::default()];
+    prefixes.extend(path.match_indices('/').skip(1).map(|(i, _)| path[..i].to_string()));
+    prefixes.push(path.to_string());
+    if let Some(query) = query {
+        prefixes.extend(query.split('&').map(|pair| format!("{}?{}", path, pair)));
+        prefixes.push(key.to_string());
+    }
+    prefixes.dedup();
+    prefixes
+}
+
+fn now_nanos() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|v| v.as_nanos() as i64).unwrap_or_default()
+}
+
+/// 存储的过期时间，按秒向上取整
+fn ttl_seconds(ttl: Duration) -> usize {
+    let secs = ttl.as_secs() as usize;
+    if ttl.subsec_nanos() > 0 { secs + 1 } else { secs.max(1) }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::SimpleStorage;
+    use super::{invalidation_prefixes, CachePolicy, ResponseCache};
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(ResponseCache::cache_key("/cgi-bin/user/get?userid=zhangsan", &params(&[("access_token", "TOKEN")])), "/cgi-bin/user/get?userid=zhangsan");
+        assert_eq!(ResponseCache::cache_key("/cgi-bin/user/info", &params(&[("openid", "o1"), ("lang", "zh_CN"), ("access_token", "TOKEN")])), "/cgi-bin/user/info?lang=zh_CN&openid=o1");
+        assert_eq!(ResponseCache::cache_key("/cgi-bin/department/list", &[]), "/cgi-bin/department/list");
+        assert_eq!(invalidation_prefixes("/cgi-bin/user/info?lang=zh_CN&openid=o1"), vec![
+            "", "/cgi-bin", "/cgi-bin/user", "/cgi-bin/user/info", "/cgi-bin/user/info?lang=zh_CN", "/cgi-bin/user/info?openid=o1", "/cgi-bin/user/info?lang=zh_CN&openid=o1",
+        ]);
+    }
+
+    #[test]
+    fn test_policy_and_eviction() {
+        let session = SimpleStorage::new();
+        let cache = ResponseCache::new()
+            .policy(CachePolicy::new("/cgi-bin/department/*", Duration::from_secs(60)))
+            .policy(CachePolicy::new("/cgi-bin/user/get", Duration::from_secs(60)).max_entries(2));
+        cache.store(&session, "cache_policy", "/cgi-bin/user/list", &[], "list".to_string());
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/user/list", &[]), None);
+        cache.store(&session, "cache_policy", "/cgi-bin/department/list", &params(&[("id", "1")]), "department".to_string());
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/department/list", &params(&[("id", "1")])).as_deref(), Some("department"));
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/department/list", &params(&[("id", "2")])), None);
+
+        for userid in ["a", "b", "c"].iter() {
+            cache.store(&session, "cache_policy", &format!("/cgi-bin/user/get?userid={}", userid), &[], userid.to_string());
+        }
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/user/get?userid=a", &[]), None);
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/user/get?userid=b", &[]).as_deref(), Some("b"));
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/user/get?userid=c", &[]).as_deref(), Some("c"));
+
+        cache.invalidate_prefix(&session, "cache_policy", "/cgi-bin/user/").unwrap();
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/user/get?userid=c", &[]), None);
+        assert_eq!(cache.load(&session, "cache_policy", "/cgi-bin/department/list", &params(&[("id", "1")])).as_deref(), Some("department"));
+    }
+}
@@ -1,6 +1,13 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use reqwest::Url;
 use serde::Serialize;
 
-use crate::{request::{LabraResponse, LabraRequest}, session::{SessionStore, SimpleStorage}, LabradorResult, RequestMethod, RequestType, Method};
+use crate::{cache::{CachePolicy, ResponseCache}, dns::{DnsConfig, DnsOverrides}, debug::{self, DebugRecord, DebugRecorder}, metrics::{MetricsRecorder, NoopMetricsRecorder, Outcome}, request::{LabraResponse, LabraRequest, LabraStreamResponse, APP_USER_AGENT}, session::{SessionStore, SimpleStorage}, LabradorResult, LabraError, RequestMethod, RequestType, Method, Resolve};
 
 /// API請求
 #[derive(Debug, Clone)]
@@ -8,7 +15,21 @@ pub struct APIClient<T: SessionStore> {
     pub app_key: String,
     pub secret: String,
     pub api_path: String,
+    /// 备用域名，主域名连接失败时按顺序重试
+    pub fallback_paths: Vec<String>,
     pub session: T,
+    /// 同一客户端（及其克隆）的请求共用连接池
+    http_client: reqwest::Client,
+    /// 请求指标记录，未设置时不记录，也不会为统计结果解析响应体
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    /// 请求调试记录，默认不记录
+    debug: Option<Arc<DebugRecorder>>,
+    /// GET接口的响应缓存，默认不缓存
+    cache: Option<ResponseCache>,
+    /// 跳过读取缓存，见[`APIClient::no_cache`]
+    no_cache: bool,
+    /// 自定义的域名解析，默认使用系统DNS
+    dns: Option<DnsConfig>,
 }
 
 /// APIClient
@@ -40,7 +61,14 @@ impl<T: SessionStore> APIClient<T> {
             app_key: app_key.into(),
             secret: secret.into(),
             api_path: api_path.into(),
-            session: SimpleStorage::new()
+            fallback_paths: Vec::new(),
+            session: SimpleStorage::new(),
+            http_client: http_client(),
+            metrics: None,
+            debug: None,
+            cache: None,
+            no_cache: false,
+            dns: None,
         }
     }
 
@@ -50,7 +78,83 @@ impl<T: SessionStore> APIClient<T> {
             app_key: app_key.into(),
             secret: secret.into(),
             api_path: api_path.into(),
-            session: session,
+            fallback_paths: Vec::new(),
+            session,
+            http_client: http_client(),
+            metrics: None,
+            debug: None,
+            cache: None,
+            no_cache: false,
+            dns: None,
+        }
+    }
+
+    /// 替换接口域名，如私有网关、就近接入域名
+    pub fn api_path<S: Into<String>>(mut self, api_path: S) -> Self {
+        self.api_path = api_path.into();
+        self
+    }
+
+    /// 设置备用域名，主域名连接失败（非业务错误）时按顺序切换
+    pub fn fallback_paths<S: Into<String>>(mut self, fallback_paths: Vec<S>) -> Self {
+        self.fallback_paths = fallback_paths.into_iter().map(|path| path.into()).collect();
+        self
+    }
+
+    /// <pre>
+    /// 为域名指定固定IP，如出口只允许访问白名单IP时
+    /// 会重新创建连接池，TLS校验与Host请求头仍使用原域名
+    /// </pre>
+    pub fn dns_overrides(mut self, overrides: DnsOverrides) -> Self {
+        self.dns = self.dns.take().unwrap_or_default().overrides(overrides).into();
+        self
+    }
+
+    /// 使用自定义的域名解析器，会重新创建连接池；同时配置了固定IP时，固定IP的域名优先
+    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> Self {
+        self.dns = self.dns.take().unwrap_or_default().resolver(resolver).into();
+        self
+    }
+
+    /// 通过[`APIClient::dns_overrides`]配置的域名映射
+    pub fn get_dns_overrides(&self) -> Option<&DnsOverrides> {
+        self.dns.as_ref()?.get_overrides()
+    }
+
+    /// 设置请求指标记录
+    pub fn metrics_recorder(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        self.metrics = metrics.into();
+        self
+    }
+
+    /// 开启请求调试记录，保留最近的请求/响应报文
+    pub fn debug_recorder(mut self, debug: Arc<DebugRecorder>) -> Self {
+        self.debug = debug.into();
+        self
+    }
+
+    /// 当前保留的请求调试记录，未开启时为空
+    pub fn debug_records(&self) -> Vec<DebugRecord> {
+        self.debug.as_ref().map(|debug| debug.records()).unwrap_or_default()
+    }
+
+    /// 添加GET接口的缓存策略，缓存写入会话存储
+    pub fn cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.cache = self.cache.take().unwrap_or_default().policy(policy).into();
+        self
+    }
+
+    /// 本次起不读取缓存，请求结果仍会写入缓存
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// 使前缀下的缓存失效，见[`ResponseCache::invalidate_prefix`]，未配置缓存时忽略
+    pub fn invalidate_cache_prefix(&self, key_prefix: &str) -> LabradorResult<()> {
+        match &self.cache {
+            Some(cache) => cache.invalidate_prefix(&self.session, &self.app_key, key_prefix),
+            None => Ok(()),
         }
     }
 
@@ -58,6 +162,11 @@ impl<T: SessionStore> APIClient<T> {
         &self.session
     }
 
+    /// 未设置时返回不记录的实现
+    pub(crate) fn metrics(&self) -> &Arc<dyn MetricsRecorder> {
+        self.metrics.as_ref().unwrap_or(&NOOP_METRICS)
+    }
+
     /// Request Http/Https
     ///
     /// # Examples
@@ -80,28 +189,340 @@ impl<T: SessionStore> APIClient<T> {
     ///
     #[inline]
     pub async fn request<D: Serialize>(&self, mut req: LabraRequest<D>) -> LabradorResult<LabraResponse> {
-        let mut api_path = self.api_path.to_owned();
-        let LabraRequest { url, ..} = req;
-        if url.starts_with("http") {
-            req.url = url;
-        } else {
-            req.url = api_path + &url;
+        if req.http_client.is_none() {
+            let http_client = match &self.dns {
+                Some(dns) => dns.http_client(),
+                None => self.http_client.clone(),
+            };
+            req = req.http_client(http_client);
+        }
+        let method = metrics_method(&req.url);
+        if req.url.starts_with("http") {
+            let record = self.debug_record(&req);
+            return self.attempt(&method, 0, record, req.request()).await;
+        }
+        if self.fallback_paths.is_empty() {
+            req.url = join_url(&self.api_path, &req.url);
+            let record = self.debug_record(&req);
+            return self.attempt(&method, 0, record, req.request()).await;
+        }
+        let req = match req.into_replayable() {
+            Ok(req) => req,
+            Err(mut req) => {
+                // Multipart请求体无法重复发送，只请求主域名；请求体序列化失败时由request()返回错误
+                req.url = join_url(&self.api_path, &req.url);
+                let record = self.debug_record(&req);
+                return self.attempt(&method, 0, record, req.request()).await;
+            }
+        };
+        let mut last_error = LabraError::Unknown;
+        for (attempt, api_path) in std::iter::once(&self.api_path).chain(self.fallback_paths.iter()).enumerate() {
+            let replay = req.replay(join_url(api_path, &req.url));
+            let record = self.debug_record(&replay);
+            match self.attempt(&method, attempt, record, replay.request()).await {
+                Err(LabraError::ConnectError(err)) => {
+                    tracing::warn!("[请求第三方接口] 连接{}失败:{}，尝试切换备用域名", api_path, err);
+                    last_error = LabraError::ConnectError(err);
+                }
+                result => return result,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// <pre>
+    /// 发送请求，收到响应头后立即返回，响应体由调用方逐块读取（如逐条解析大列表）
+    /// 只请求主域名；指标按HTTP状态码记录，不解析errcode，也不写入调试记录与缓存
+    /// </pre>
+    pub async fn request_stream<D: Serialize>(&self, mut req: LabraRequest<D>) -> LabradorResult<LabraStreamResponse> {
+        if req.http_client.is_none() {
+            let http_client = match &self.dns {
+                Some(dns) => dns.http_client(),
+                None => self.http_client.clone(),
+            };
+            req = req.http_client(http_client);
+        }
+        let method = metrics_method(&req.url);
+        if !req.url.starts_with("http") {
+            req.url = join_url(&self.api_path, &req.url);
         }
-        req.request().await
+        let start = Instant::now();
+        let result = req.request_stream().await;
+        if let Some(metrics) = &self.metrics {
+            let outcome = match &result {
+                Ok(response) if response.status().is_success() => Outcome::Success,
+                _ => Outcome::HttpError,
+            };
+            metrics.record(&self.app_key, &method, outcome, start.elapsed(), 0);
+        }
+        result
+    }
+
+    /// 开启调试记录时，在发送前记下请求报文
+    fn debug_record<D: Serialize>(&self, req: &LabraRequest<D>) -> Option<DebugRecord> {
+        self.debug.as_ref()?;
+        Some(DebugRecord {
+            method: req.method.to_string(),
+            url: debug::redact_url(&req.url, req.params.as_deref().unwrap_or_default()),
+            request_body: debug::redact_body(req.body.to_string()),
+            status: None,
+            response_body: String::default(),
+            requested_at: Local::now(),
+            responded_at: Local::now(),
+        })
+    }
+
+    /// 发送一次请求并记录指标
+    async fn attempt<F: Future<Output = LabradorResult<LabraResponse>>>(&self, method: &str, attempt: usize, record: Option<DebugRecord>, request: F) -> LabradorResult<LabraResponse> {
+        let start = Instant::now();
+        let result = request.await;
+        if let Some(metrics) = &self.metrics {
+            let outcome = match &result {
+                Ok(response) => Outcome::from_response(response),
+                Err(_) => Outcome::HttpError,
+            };
+            metrics.record(&self.app_key, method, outcome, start.elapsed(), attempt);
+        }
+        if let (Some(debug), Some(mut record)) = (&self.debug, record) {
+            match &result {
+                Ok(response) => {
+                    record.status = Some(response.status().as_u16());
+                    record.response_body = response.bytes().map(|bytes| String::from_utf8_lossy(&bytes).to_string()).unwrap_or_default();
+                }
+                Err(err) => record.response_body = err.to_string(),
+            }
+            record.responded_at = Local::now();
+            debug.push(record);
+        }
+        result
     }
 
     /// 发送POST请求
-    pub async fn post<D: Serialize, R: RequestMethod>(&self, method: R, mut querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let req = LabraRequest::new().url(method.get_method()).params(querys).method(Method::Post).json(data).req_type(request_type);
+    pub async fn post<D: Serialize, R: RequestMethod>(&self, method: R, querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
+        self.post_with_headers(method, querys, vec![], data, request_type).await
+    }
+
+    /// 发送POST请求，并附加请求头（如`AuthStyle::BearerHeader`的凭证）
+    pub async fn post_with_headers<D: Serialize, R: RequestMethod>(&self, method: R, querys: Vec<(String, String)>, headers: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
+        let mut req = LabraRequest::new().url(method.get_method()).params(querys).method(Method::Post).json(data).req_type(request_type);
+        if !headers.is_empty() {
+            req = req.headers(headers);
+        }
         self.request(req).await
     }
 
-    /// 发送GET请求
+    /// 发送GET请求，匹配缓存策略时优先读取缓存
     pub async fn get<R: RequestMethod>(&self, method: R, params: Vec<(String, String)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let req = LabraRequest::<String>::new().url(method.get_method()).params(params).method(Method::Get).req_type(request_type);
-        self.request(req).await
+        self.get_with_headers(method, params, vec![], request_type).await
+    }
+
+    /// 发送GET请求，并附加请求头（如`AuthStyle::BearerHeader`的凭证），缓存键不含请求头
+    pub async fn get_with_headers<R: RequestMethod>(&self, method: R, params: Vec<(String, String)>, headers: Vec<(String, String)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
+        let url = method.get_method();
+        let build = |url: String, params: Vec<(String, String)>| {
+            let req = LabraRequest::<String>::new().url(url).params(params).method(Method::Get).req_type(request_type);
+            if headers.is_empty() { req } else { req.headers(headers.to_owned()) }
+        };
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return self.request(build(url, params)).await,
+        };
+        if !self.no_cache {
+            if let Some(body) = cache.load(&self.session, &self.app_key, &url, &params) {
+                if let Ok(response_url) = Url::parse(&join_url(&self.api_path, &url)) {
+                    return Ok(LabraResponse::cached(response_url, body));
+                }
+            }
+        }
+        let response = self.request(build(url.to_owned(), params.to_owned())).await?;
+        // 只缓存成功的响应
+        if Outcome::from_response(&response) == Outcome::Success {
+            if let Some(body) = response.bytes().ok().and_then(|bytes| String::from_utf8(bytes.to_vec()).ok()) {
+                cache.store(&self.session, &self.app_key, &url, &params, body);
+            }
+        }
+        Ok(response)
+    }
+}
+
+static NOOP_METRICS: Lazy<Arc<dyn MetricsRecorder>> = Lazy::new(|| Arc::new(NoopMetricsRecorder));
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder().user_agent(APP_USER_AGENT).build().unwrap_or_default()
+}
+
+/// 指标中的接口名称：去掉域名和查询参数的路径
+fn metrics_method(url: &str) -> String {
+    let path = url.split('?').next().unwrap_or_default();
+    match Url::parse(path) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// 拼接域名与接口路径，兼容域名末尾带`/`的写法
+fn join_url(api_path: &str, url: &str) -> String {
+    if url.starts_with('/') {
+        format!("{}{}", api_path.trim_end_matches('/'), url)
+    } else {
+        format!("{}{}", api_path, url)
     }
 }
 
 
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{LabraError, LabraRequest, Method, RequestType, SimpleStorage};
+    use crate::util::mock::{closed_url, MockResponse, MockServer};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::{DebugRecorder, MetricsRecorder, Outcome};
+    use super::{join_url, APIClient};
+
+    fn token_request() -> LabraRequest<String> {
+        LabraRequest::<String>::new().url("/cgi-bin/token".to_string()).params(vec![("grant_type".to_string(), "client_credential".to_string())]).method(Method::Get).req_type(RequestType::Json)
+    }
+
+    #[test]
+    fn test_join_url() {
+        assert_eq!(join_url("https://api.weixin.qq.com", "/cgi-bin/token"), "https://api.weixin.qq.com/cgi-bin/token");
+        assert_eq!(join_url("http://gateway.local/wechat/", "/cgi-bin/token"), "http://gateway.local/wechat/cgi-bin/token");
+        assert_eq!(join_url("https://api.jd.com/routerjson", ""), "https://api.jd.com/routerjson");
+    }
+
+    #[tokio::test]
+    async fn test_request_to_base_url() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"errcode":0}"#)]).await;
+        let client = APIClient::<SimpleStorage>::new("appid", "secret", "https://api.weixin.qq.com").api_path(format!("{}/", server.url));
+        let response = client.request(token_request()).await.unwrap();
+        assert_eq!(response.text().unwrap(), r#"{"errcode":0}"#);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("GET /cgi-bin/token?grant_type=client_credential HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_after_connect_failure() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"errcode":0}"#)]).await;
+        let client = APIClient::<SimpleStorage>::new("appid", "secret", closed_url().await).fallback_paths(vec![server.url.to_owned()]);
+        let response = client.request(LabraRequest::new().url("/cgi-bin/message/send".to_string()).method(Method::Post).json(serde_json::json!({"touser": "openid"}))).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /cgi-bin/message/send HTTP/1.1"));
+        assert!(requests[0].ends_with(r#"{"touser":"openid"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_no_fallback_on_http_error() {
+        let primary = MockServer::start(vec![MockResponse::json("system busy").status(503)]).await;
+        let backup = MockServer::start(vec![MockResponse::json(r#"{"errcode":0}"#)]).await;
+        let client = APIClient::<SimpleStorage>::new("appid", "secret", primary.url.to_owned()).fallback_paths(vec![backup.url.to_owned()]);
+        let response = client.request(token_request()).await.unwrap();
+        assert_eq!(response.status().as_u16(), 503);
+        assert!(backup.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unserializable_body_not_replayed() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"errcode":0}"#)]).await;
+        let client = APIClient::<SimpleStorage>::new("appid", "secret", server.url.to_owned()).fallback_paths(vec![server.url.to_owned()]);
+        // 键不是字符串，无法序列化为JSON
+        let body = std::collections::HashMap::from([((1, 2), "value")]);
+        assert!(client.request(LabraRequest::new().url("/cgi-bin/message/send".to_string()).method(Method::Post).json(body)).await.is_err());
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_all_hosts_unreachable() {
+        let client = APIClient::<SimpleStorage>::new("appid", "secret", closed_url().await).fallback_paths(vec![closed_url().await]);
+        assert!(matches!(client.request(token_request()).await, Err(LabraError::ConnectError(_))));
+    }
+
+    /// 记录每次回调
+    #[derive(Default)]
+    struct CountingRecorder {
+        records: Mutex<Vec<(String, String, Outcome, usize)>>,
+    }
+
+    impl MetricsRecorder for CountingRecorder {
+        fn record(&self, appid: &str, method: &str, status: Outcome, _elapsed: Duration, attempt: usize) {
+            self.records.lock().unwrap().push((appid.to_string(), method.to_string(), status, attempt));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_per_attempt() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"errcode":40001,"errmsg":"invalid credential"}"#), MockResponse::json("system busy").status(503), MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let recorder = Arc::new(CountingRecorder::default());
+        let client = APIClient::<SimpleStorage>::new("metrics_appid", "secret", closed_url().await).fallback_paths(vec![server.url.to_owned()]).metrics_recorder(recorder.clone());
+        client.request(token_request()).await.unwrap();
+        assert_eq!(*recorder.records.lock().unwrap(), vec![
+            ("metrics_appid".to_string(), "/cgi-bin/token".to_string(), Outcome::HttpError, 0),
+            ("metrics_appid".to_string(), "/cgi-bin/token".to_string(), Outcome::ErrCode(40001), 1),
+        ]);
+
+        let client = client.api_path(server.url.to_owned()).fallback_paths(Vec::<String>::new());
+        client.request(token_request()).await.unwrap();
+        client.request(LabraRequest::<String>::new().url(format!("{}/cgi-bin/message/send?access_token=TOKEN", server.url)).method(Method::Get)).await.unwrap();
+        let records = recorder.records.lock().unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!((records[2].2, records[2].3), (Outcome::HttpError, 0));
+        assert_eq!((records[3].1.as_str(), records[3].2), ("/cgi-bin/message/send", Outcome::Success));
+        assert_eq!(Outcome::ErrCode(40001).label(), "40001");
+    }
+
+    #[tokio::test]
+    async fn test_metrics_outcome_content_type() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"errcode":"45009","errmsg":"reach max api daily quota limit","items":[1,2,3]}"#),
+            MockResponse::bytes("text/plain", br#"{"errcode":40001}"#),
+            // 素材等二进制内容不解析，即使恰好以JSON开头
+            MockResponse::bytes("image/jpeg", br#"{"errcode":40001}"#),
+            MockResponse::bytes("application/json", b"not json"),
+        ]).await;
+        let recorder = Arc::new(CountingRecorder::default());
+        let client = APIClient::<SimpleStorage>::new("metrics_appid", "secret", server.url.to_owned()).metrics_recorder(recorder.clone());
+        for _ in 0..4 {
+            client.request(token_request()).await.unwrap();
+        }
+        let outcomes = recorder.records.lock().unwrap().iter().map(|record| record.2).collect::<Vec<_>>();
+        assert_eq!(outcomes, vec![Outcome::ErrCode(45009), Outcome::ErrCode(40001), Outcome::Success, Outcome::Success]);
+    }
 
+    #[tokio::test]
+    async fn test_debug_records() {
+        let client = APIClient::<SimpleStorage>::new("debug_appid", "secret", closed_url().await);
+        client.request(token_request()).await.ok();
+        assert!(client.debug_records().is_empty());
+
+        let server = MockServer::start(vec![MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let client = client.fallback_paths(vec![server.url.to_owned()]).debug_recorder(Arc::new(DebugRecorder::new(10)));
+        let req = LabraRequest::new().url("/cgi-bin/message/send".to_string())
+            .params(vec![("access_token".to_string(), "TOKEN".to_string())])
+            .method(Method::Post).json(serde_json::json!({"touser": "OPENID"})).req_type(RequestType::Json);
+        client.request(req).await.unwrap();
+        let records = client.debug_records();
+        assert_eq!(records.len(), 2);
+        // 主域名连接失败，记录错误信息
+        assert_eq!(records[0].status, None);
+        assert!(!records[0].response_body.is_empty());
+        assert_eq!(records[1].method, "POST");
+        assert_eq!(records[1].url, format!("{}/cgi-bin/message/send?access_token=***", server.url));
+        assert_eq!(records[1].request_body, r#"{"touser":"OPENID"}"#);
+        assert_eq!((records[1].status, records[1].response_body.as_str()), (Some(200), r#"{"errcode":0,"errmsg":"ok"}"#));
+        assert!(records[1].requested_at <= records[1].responded_at);
+        assert!(!server.requests()[0].contains("***"));
+    }
+
+    #[cfg(feature = "wechat-mp")]
+    #[tokio::test]
+    async fn test_wechat_client_base_url() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#)]).await;
+        let client = crate::WechatMpClient::<SimpleStorage>::new("appid", "secret").base_url(&server.url);
+        assert_eq!(client.access_token(true).await.unwrap(), "ACCESS_TOKEN");
+        assert!(server.requests()[0].starts_with("GET /cgi-bin/token?"));
+    }
+}
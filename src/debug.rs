@@ -0,0 +1,236 @@
+//!
+//! 请求调试记录
+//!
+//! 签名校验失败、解密失败等问题往往难以复现，开启[`DebugRecorder`]后客户端会在内存中保留最近N次请求的
+//! 请求/响应报文（URL中的access_token等凭证会被隐去），出现问题时可以通过`client.debug_records()`或
+//! [`DebugRecorder::dump_json`]导出排查。未开启时不做任何记录。
+//!
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::Value;
+
+/// 默认保留的记录数
+const DEFAULT_CAPACITY: usize = 50;
+/// 默认的报文长度上限（字节）
+const DEFAULT_MAX_BODY_SIZE: usize = 4096;
+/// 凭证被隐去后的取值
+const REDACTED: &str = "***";
+/// 需要隐去的参数名
+const SENSITIVE_PARAMS: [&str; 9] = [
+    "access_token", "suite_access_token", "provider_access_token", "component_access_token",
+    "secret", "corpsecret", "suite_secret", "provider_secret", "component_appsecret",
+];
+
+/// 一次请求的调试记录
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugRecord {
+    /// 请求方法，如`POST`
+    pub method: String,
+    /// 完整的请求URL，凭证参数已隐去
+    pub url: String,
+    /// 请求报文，超过长度上限时截断
+    pub request_body: String,
+    /// HTTP状态码，连接失败时为空
+    pub status: Option<u16>,
+    /// 响应报文，超过长度上限时截断；连接失败时为错误信息
+    pub response_body: String,
+    pub requested_at: DateTime<Local>,
+    pub responded_at: DateTime<Local>,
+}
+
+/// <pre>
+/// 请求调试记录器
+/// 以环形缓冲区保留最近`capacity`次请求，超出后丢弃最早的记录
+/// </pre>
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use labrador::{DebugRecorder, WechatMpClient, SimpleStorage};
+///
+/// let client = WechatMpClient::<SimpleStorage>::new("appid", "secret")
+///     .debug_recorder(Arc::new(DebugRecorder::new(20).max_body_size(1024)));
+/// for record in client.debug_records() {
+///     println!("{} {} {:?}", record.method, record.url, record.status);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DebugRecorder {
+    capacity: usize,
+    max_body_size: usize,
+    records: Mutex<VecDeque<DebugRecord>>,
+}
+
+impl Default for DebugRecorder {
+    fn default() -> Self {
+        DebugRecorder::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl DebugRecorder {
+    /// `capacity` 为保留的记录数，至少为1
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        DebugRecorder {
+            capacity,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// 请求/响应报文的长度上限（字节），默认4096
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// 当前保留的记录，按请求先后排列
+    pub fn records(&self) -> Vec<DebugRecord> {
+        self.records.lock().map(|records| records.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// 以JSON数组导出当前保留的记录
+    pub fn dump_json(&self) -> String {
+        serde_json::to_string_pretty(&self.records()).unwrap_or_default()
+    }
+
+    /// 清空记录
+    pub fn clear(&self) {
+        if let Ok(mut records) = self.records.lock() {
+            records.clear();
+        }
+    }
+
+    pub(crate) fn push(&self, mut record: DebugRecord) {
+        record.request_body = self.truncate(record.request_body);
+        record.response_body = self.truncate(record.response_body);
+        if let Ok(mut records) = self.records.lock() {
+            while records.len() >= self.capacity {
+                records.pop_front();
+            }
+            records.push_back(record);
+        }
+    }
+
+    /// 超过长度上限的报文按字符边界截断，并在末尾标注截掉的字节数
+    fn truncate(&self, mut body: String) -> String {
+        if body.len() <= self.max_body_size {
+            return body;
+        }
+        let mut end = self.max_body_size;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let omitted = body.len() - end;
+        body.truncate(end);
+        body.push_str(&format!("...[truncated {} bytes]", omitted));
+        body
+    }
+}
+
+fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_PARAMS.iter().any(|param| param.eq_ignore_ascii_case(name))
+}
+
+/// 拼接请求参数并隐去URL中的凭证，URL本身带有的查询参数同样处理
+pub(crate) fn redact_url(url: &str, params: &[(String, String)]) -> String {
+    let mut http_url = match Url::parse(url) {
+        Ok(http_url) => http_url,
+        Err(_) => return url.to_string(),
+    };
+    let pairs = http_url.query_pairs().map(|(k, v)| (k.to_string(), v.to_string()))
+        .chain(params.iter().cloned())
+        .map(|(k, v)| if is_sensitive(&k) { (k, REDACTED.to_string()) } else { (k, v) })
+        .collect::<Vec<(String, String)>>();
+    if pairs.is_empty() {
+        http_url.set_query(None);
+    } else {
+        http_url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    http_url.to_string()
+}
+
+/// 隐去JSON请求报文第一层中的凭证（如获取suite_access_token时提交的suite_secret），非JSON报文原样返回
+pub(crate) fn redact_body(body: String) -> String {
+    match serde_json::from_str::<Value>(&body) {
+        Ok(Value::Object(mut map)) => {
+            let mut redacted = false;
+            for (k, v) in map.iter_mut() {
+                if is_sensitive(k) {
+                    *v = Value::String(REDACTED.to_string());
+                    redacted = true;
+                }
+            }
+            if redacted { Value::Object(map).to_string() } else { body }
+        }
+        _ => body,
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::Local;
+
+    use super::{redact_body, redact_url, DebugRecord, DebugRecorder};
+
+    fn record(url: &str, request_body: &str) -> DebugRecord {
+        DebugRecord {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            request_body: request_body.to_string(),
+            status: Some(200),
+            response_body: "{\"errcode\":0}".to_string(),
+            requested_at: Local::now(),
+            responded_at: Local::now(),
+        }
+    }
+
+    #[test]
+    fn test_redact_url() {
+        let params = vec![("access_token".to_string(), "TOKEN".to_string()), ("openid".to_string(), "OPENID".to_string())];
+        assert_eq!(redact_url("https://api.weixin.qq.com/cgi-bin/user/info", &params), "https://api.weixin.qq.com/cgi-bin/user/info?access_token=***&openid=OPENID");
+        assert_eq!(redact_url("https://api.weixin.qq.com/cgi-bin/token?grant_type=client_credential&secret=SECRET", &[]), "https://api.weixin.qq.com/cgi-bin/token?grant_type=client_credential&secret=***");
+        assert_eq!(redact_url("https://qyapi.weixin.qq.com/cgi-bin/service/get_auth_info?suite_access_token=SUITE", &[]), "https://qyapi.weixin.qq.com/cgi-bin/service/get_auth_info?suite_access_token=***");
+        assert_eq!(redact_url("https://api.mch.weixin.qq.com/v3/certificates", &[]), "https://api.mch.weixin.qq.com/v3/certificates");
+        assert_eq!(redact_body("{\"suite_id\":\"ID\",\"suite_secret\":\"SECRET\"}".to_string()), "{\"suite_id\":\"ID\",\"suite_secret\":\"***\"}");
+        assert_eq!(redact_body("<xml><sign>SIGN</sign></xml>".to_string()), "<xml><sign>SIGN</sign></xml>");
+    }
+
+    #[test]
+    fn test_truncate_body() {
+        let recorder = DebugRecorder::new(10).max_body_size(8);
+        recorder.push(record("https://api.weixin.qq.com/a", "0123456789"));
+        // 按字符边界截断：第三个汉字跨越第8个字节
+        recorder.push(record("https://api.weixin.qq.com/b", "一二三四"));
+        let records = recorder.records();
+        assert_eq!(records[0].request_body, "01234567...[truncated 2 bytes]");
+        assert_eq!(records[0].response_body, "{\"errcod...[truncated 5 bytes]");
+        assert_eq!(records[1].request_body, "一二...[truncated 6 bytes]");
+    }
+
+    #[test]
+    fn test_ring_wrap_around() {
+        let recorder = DebugRecorder::new(3);
+        for i in 0..5 {
+            recorder.push(record(&format!("https://api.weixin.qq.com/{}", i), ""));
+        }
+        let urls = recorder.records().into_iter().map(|record| record.url).collect::<Vec<String>>();
+        assert_eq!(urls, vec!["https://api.weixin.qq.com/2", "https://api.weixin.qq.com/3", "https://api.weixin.qq.com/4"]);
+        let dump = serde_json::from_str::<serde_json::Value>(&recorder.dump_json()).unwrap();
+        assert_eq!(dump.as_array().unwrap().len(), 3);
+        assert_eq!(dump[0]["url"], "https://api.weixin.qq.com/2");
+        recorder.clear();
+        assert!(recorder.records().is_empty());
+        // 容量至少为1
+        let recorder = DebugRecorder::new(0);
+        recorder.push(record("https://api.weixin.qq.com/a", ""));
+        recorder.push(record("https://api.weixin.qq.com/b", ""));
+        assert_eq!(recorder.records().len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+//!
+//! 自定义域名解析
+//!
+//! 出口需要限制到白名单IP时，可以为接口域名指定固定的IP，而不依赖公共DNS。
+//! 解析只决定连接的地址，TLS的SNI、证书校验与Host请求头仍使用URL中的域名。
+//! DNS本身没有端口的概念，连接的端口始终取自URL（如https为443）。
+//!
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::dns::Resolve;
+
+use crate::request::APP_USER_AGENT;
+
+/// <pre>
+/// 域名到固定IP的映射，未配置的域名仍按系统DNS解析
+/// 克隆的实例共用同一份映射，客户端创建后仍可通过`insert`更新（如定期刷新官方IP），下次请求时生效
+/// </pre>
+#[derive(Clone, Default)]
+pub struct DnsOverrides {
+    hosts: Arc<RwLock<HashMap<String, Vec<IpAddr>>>>,
+    /// 每次修改加一，客户端据此重建连接池
+    generation: Arc<AtomicU64>,
+}
+
+impl DnsOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 指定域名的IP，多个IP时按顺序尝试连接；ips为空时移除该域名的配置
+    pub fn insert(&self, host: &str, ips: Vec<IpAddr>) {
+        if let Ok(mut hosts) = self.hosts.write() {
+            if ips.is_empty() {
+                hosts.remove(&host.to_lowercase());
+            } else {
+                hosts.insert(host.to_lowercase(), ips);
+            }
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// 添加域名的IP后返回自身，便于链式配置
+    pub fn with(self, host: &str, ips: Vec<IpAddr>) -> Self {
+        self.insert(host, ips);
+        self
+    }
+
+    /// 域名当前配置的IP
+    pub fn get(&self, host: &str) -> Option<Vec<IpAddr>> {
+        self.hosts.read().ok()?.get(&host.to_lowercase()).cloned()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    fn install(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Ok(hosts) = self.hosts.read() {
+            for (host, ips) in hosts.iter() {
+                let addrs = ips.iter().map(|ip| SocketAddr::new(*ip, 0)).collect::<Vec<_>>();
+                builder = builder.resolve_to_addrs(host, &addrs);
+            }
+        }
+        builder
+    }
+}
+
+impl fmt::Debug for DnsOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hosts.read() {
+            Ok(hosts) => f.debug_map().entries(hosts.iter()).finish(),
+            Err(_) => f.write_str("DnsOverrides(poisoned)"),
+        }
+    }
+}
+
+type InstallResolver = Arc<dyn Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + Sync>;
+
+/// <pre>
+/// 客户端的域名解析配置
+/// 固定IP优先于自定义解析器；映射变化后，下次请求时按新的映射重建连接池（克隆的客户端共用）
+/// </pre>
+#[derive(Clone, Default)]
+pub(crate) struct DnsConfig {
+    overrides: Option<DnsOverrides>,
+    resolver: Option<InstallResolver>,
+    client: Arc<Mutex<Option<(u64, reqwest::Client)>>>,
+}
+
+impl fmt::Debug for DnsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsConfig").field("overrides", &self.overrides).field("resolver", &self.resolver.is_some()).finish()
+    }
+}
+
+impl DnsConfig {
+    pub(crate) fn overrides(&self, overrides: DnsOverrides) -> Self {
+        DnsConfig {
+            overrides: overrides.into(),
+            resolver: self.resolver.to_owned(),
+            client: Default::default(),
+        }
+    }
+
+    pub(crate) fn resolver<R: Resolve + 'static>(&self, resolver: Arc<R>) -> Self {
+        DnsConfig {
+            overrides: self.overrides.to_owned(),
+            resolver: Some(Arc::new(move |builder: reqwest::ClientBuilder| builder.dns_resolver(resolver.to_owned()))),
+            client: Default::default(),
+        }
+    }
+
+    pub(crate) fn get_overrides(&self) -> Option<&DnsOverrides> {
+        self.overrides.as_ref()
+    }
+
+    /// 按当前配置的连接池，映射未变化时复用
+    pub(crate) fn http_client(&self) -> reqwest::Client {
+        let generation = self.overrides.as_ref().map(|overrides| overrides.generation()).unwrap_or_default();
+        let mut client = match self.client.lock() {
+            Ok(client) => client,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match client.as_ref() {
+            Some((built, http_client)) if *built == generation => http_client.to_owned(),
+            _ => {
+                let mut builder = reqwest::Client::builder().user_agent(APP_USER_AGENT);
+                if let Some(install) = &self.resolver {
+                    builder = install(builder);
+                }
+                if let Some(overrides) = &self.overrides {
+                    builder = overrides.install(builder);
+                }
+                let http_client = builder.build().unwrap_or_default();
+                *client = Some((generation, http_client.to_owned()));
+                http_client
+            }
+        }
+    }
+}
+
+/// 解析接口返回的IP列表，忽略无法解析的项
+#[cfg(feature = "wechat-mp")]
+pub(crate) fn parse_ips<S: AsRef<str>>(ips: &[S]) -> Vec<IpAddr> {
+    ips.iter().filter_map(|ip| ip.as_ref().trim().parse::<IpAddr>().ok()).collect()
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::net::IpAddr;
+
+    use crate::{APIClient, LabraRequest, Method, RequestType, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::DnsOverrides;
+
+    #[test]
+    fn test_overrides() {
+        let overrides = DnsOverrides::new().with("API.weixin.qq.com", vec!["101.226.212.27".parse().unwrap()]);
+        let shared = overrides.clone();
+        assert_eq!(shared.get("api.weixin.qq.com"), Some(vec!["101.226.212.27".parse::<IpAddr>().unwrap()]));
+        overrides.insert("api.weixin.qq.com", vec![]);
+        assert_eq!(shared.get("api.weixin.qq.com"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "wechat-mp")]
+    fn test_parse_ips() {
+        assert_eq!(super::parse_ips(&["101.226.212.27", " 240e:e1:a800:120::36 ", "101.226.212.0/24", ""]).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_to_pinned_ip() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"errcode":0}"#)]).await;
+        let port = server.url.rsplit(':').next().unwrap().to_string();
+        let overrides = DnsOverrides::new().with("api.weixin.qq.com", vec!["127.0.0.1".parse().unwrap()]);
+        let client = APIClient::<SimpleStorage>::new("appid", "secret", format!("http://api.weixin.qq.com:{}", port)).dns_overrides(overrides);
+        let req = LabraRequest::<String>::new().url("/cgi-bin/token".to_string()).method(Method::Get).req_type(RequestType::Json);
+        let response = client.request(req).await.unwrap();
+        assert_eq!(response.text().unwrap(), r#"{"errcode":0}"#);
+        // 连接到固定的IP，Host仍为原域名
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].to_lowercase().contains(&format!("\r\nhost: api.weixin.qq.com:{}\r\n", port)), "{}", requests[0]);
+    }
+}
@@ -87,10 +87,9 @@ impl <T: SessionStore> JDClient<T> {
     }
 
     /// 签名
-    fn sign(&self, sign_content: &str) -> String {
+    fn sign(&self, sign_content: &str) -> LabradorResult<String> {
         let content = format!("{}{}{}", self.api_client.secret.to_string(), sign_content, self.api_client.secret.to_string());
-        let sign = md5::md5(content).to_uppercase();
-        sign
+        Ok(md5::md5(content)?.to_uppercase())
     }
 
     fn get_request_url(&self, holder: &RequestParametersHolder) -> LabradorResult<String> {
@@ -126,7 +125,7 @@ impl <T: SessionStore> JDClient<T> {
         holder.set_protocal_must_params(protocal_must_params.to_owned());
         let pairs = holder.get_sorted_map();
         let sign_content = pairs.iter().filter(|(k, v)| !k.is_empty() && !v.is_empty()).map(|(k, v)| format!("{}{}", k, v)).collect::<Vec<String>>().join("");
-        protocal_must_params.insert(constants::SIGN.to_string(), self.sign(&sign_content));
+        protocal_must_params.insert(constants::SIGN.to_string(), self.sign(&sign_content)?);
         holder.set_protocal_must_params(protocal_must_params);
         Ok(holder)
     }
@@ -428,7 +427,6 @@ mod tests {
     use reqwest::Url;
     use serde::{Deserializer, Deserialize, Serialize};
     use serde_json::{json, Value};
-    use crate::ResponseType::Text;
     use crate::{SimpleStorage, JDClient, JdPromotionUrlGenerateRequest, JdPromotionUrlGenerateParam, JdOrderRecentQueryParam, JdOrderRawQueryParam};
     use crate::jd::request::{JdGoodsInfoQueryRequest, JdJFGoodsParam};
 
@@ -18,6 +18,13 @@
 //! *   ```pdd``` - Pinduoduo related services
 //! *   ```jd``` - Jingdong related services
 //! *   ```wechat``` - Wechat related services
+//! *   ```server``` - Wechat message server (signature, decrypt, dispatch, reply)
+//! *   ```region``` - Region code table and Chinese address normalization (not in ```full```)
+//! *   ```refresher``` - Background access_token refreshing for wechat mp/miniapp clients (not in ```full```)
+//! *   ```outbox``` - Retrying outbound message queue persisted through the session store (not in ```full```)
+//! *   ```upload-stream``` - Streaming wechat mp material uploads from an AsyncRead with progress callbacks (not in ```full```)
+//! *   ```tokio-runtime``` - Run on tokio (default)
+//! *   ```async-std-runtime``` - Run on async-std, use with `default-features = false`; ```refresher``` and ```upload-stream``` still require tokio
 //!
 //! ## Installation
 //!
@@ -32,10 +39,13 @@
 //!
 //! ## Example
 //!
+//! Common clients and types can be imported at once with `use labrador::prelude::*;`,
+//! see [`prelude`] for more examples.
+//!
 //! ### With Wechat（微信开放平台、包含微信支付）
 //!
 //!  ```rust
-//! use labrador::{WechatPayClient, SimpleStorage, TradeType, WechatPayRequestV3, Amount, Payer};
+//! use labrador::{WechatPayClient, SimpleStorage, TradeType, TradeNo, WechatPayRequestV3, Amount, Payer};
 //! use chrono::{Local, SecondsFormat};
 //!
 //!  #[tokio::main]
@@ -47,7 +57,7 @@
 //!          appid: "appid".to_string().into(),
 //!          mch_id: "mchid".to_string(),
 //!          description: "测试商品支付".to_string(),
-//!          out_trade_no: "1602920235sdfsdfas32234234".to_string(),
+//!          out_trade_no: TradeNo::try_new("1602920235sdfsdfas32234234").unwrap(),
 //!          time_expire: date,
 //!          attach: None,
 //!          notify_url: "https:xxx.cn/trade/notify".to_string(),
@@ -158,12 +168,25 @@
 //! We will gradually improve the corresponding API
 //!
 //!
-#![feature(doc_cfg)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
 mod session;
 mod request;
 mod errors;
 mod client;
+mod metrics;
+mod debug;
+mod cache;
+mod dns;
+mod runtime;
+#[cfg(feature = "outbox")]
+#[cfg_attr(docsrs, doc(cfg(feature = "outbox")))]
+mod outbox;
 mod util;
+pub mod prelude;
+pub mod config;
+#[cfg(any(test, feature = "testkit"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testkit")))]
+pub mod testkit;
 #[cfg(feature = "jd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "jd")))]
 mod jd;
@@ -179,10 +202,10 @@ pub use taobao::*;
 mod pdd;
 #[cfg(feature = "pdd")]
 pub use pdd::*;
-#[cfg(feature = "wechat")]
-#[cfg_attr(docsrs, doc(cfg(feature = "wechat")))]
+#[cfg(feature = "wechat-core")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wechat-core")))]
 mod wechat;
-#[cfg(feature = "wechat")]
+#[cfg(feature = "wechat-core")]
 pub use wechat::*;
 
 pub type LabradorResult<T, E = LabraError> = Result<T, E>;
@@ -197,8 +220,15 @@ pub use errors::LabraError;
 pub use session::*;
 pub use util::*;
 pub use client::APIClient;
+pub use metrics::*;
+pub use debug::*;
+pub use cache::*;
+pub use dns::DnsOverrides;
+#[cfg(feature = "outbox")]
+pub use outbox::*;
 pub use request::*;
 pub use reqwest::multipart::{Form, Part};
+pub use reqwest::dns::{Addrs, Resolve, Resolving};
 
 pub use bytes;
 pub use serde_urlencoded;
@@ -0,0 +1,129 @@
+//!
+//! 请求指标
+//!
+//! 每次对外请求（含切换备用域名的重试，每次尝试各记录一次）结束后回调[`MetricsRecorder`]，
+//! 可以据此对接Prometheus等监控系统，统计各appid的请求数、耗时和错误码分布。
+//!
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::request::LabraResponse;
+use crate::util::serde_helper::option_string_or_number;
+
+/// 只读取`errcode`，其余字段由serde跳过，不会把整个响应体解析为`Value`
+#[derive(Deserialize)]
+struct ErrCode {
+    #[serde(default, with = "option_string_or_number")]
+    errcode: Option<i64>,
+}
+
+/// 请求结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// 连接失败、超时或HTTP状态码非2xx
+    HttpError,
+    /// 接口返回了非0的errcode
+    ErrCode(i64),
+    Success,
+}
+
+impl Outcome {
+    /// 非JSON的响应（如下载的素材、账单）不解析响应体，视为成功
+    pub(crate) fn from_response(response: &LabraResponse) -> Self {
+        if !response.status().is_success() {
+            return Outcome::HttpError;
+        }
+        let is_json = match response.header_value(reqwest::header::CONTENT_TYPE.as_str()) {
+            // 微信部分接口以text/plain返回JSON
+            Some(content_type) => {
+                let content_type = content_type.to_ascii_lowercase();
+                content_type.contains("json") || content_type.starts_with("text/plain")
+            }
+            None => true,
+        };
+        if !is_json {
+            return Outcome::Success;
+        }
+        let errcode = response.json::<ErrCode>().ok().and_then(|v| v.errcode);
+        match errcode {
+            Some(errcode) if errcode != 0 => Outcome::ErrCode(errcode),
+            _ => Outcome::Success,
+        }
+    }
+
+    /// 指标标签
+    pub fn label(&self) -> String {
+        match self {
+            Outcome::HttpError => "http_error".to_string(),
+            Outcome::ErrCode(errcode) => errcode.to_string(),
+            Outcome::Success => "success".to_string(),
+        }
+    }
+}
+
+/// 当前使用的凭证（secret）
+///
+/// 配置了备用secret时，主secret换取access_token失败（40001、40125）会改用备用secret。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecretGeneration {
+    /// 主secret
+    Primary,
+    /// 备用secret
+    Secondary,
+}
+
+impl SecretGeneration {
+    /// 指标标签
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecretGeneration::Primary => "primary",
+            SecretGeneration::Secondary => "secondary",
+        }
+    }
+}
+
+/// 请求指标记录
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use labrador::{MetricsRecorder, Outcome, WechatMpClient, SimpleStorage};
+///
+/// struct LogRecorder;
+///
+/// impl MetricsRecorder for LogRecorder {
+///     fn record(&self, appid: &str, method: &str, status: Outcome, elapsed: Duration, attempt: usize) {
+///         println!("{} {} {:?} {:?} #{}", appid, method, status, elapsed, attempt);
+///     }
+/// }
+///
+/// let client = WechatMpClient::<SimpleStorage>::new("appid", "secret").metrics_recorder(Arc::new(LogRecorder));
+/// ```
+pub trait MetricsRecorder: Send + Sync {
+    /// `method` 为接口路径（如`/cgi-bin/token`），`attempt` 为本次调用的第几次尝试，从0开始
+    fn record(&self, appid: &str, method: &str, status: Outcome, elapsed: Duration, attempt: usize);
+
+    /// 换取access_token成功时回调，`generation` 为本次换取成功所用的secret，默认不记录
+    fn record_secret_generation(&self, _appid: &str, _generation: SecretGeneration) {}
+
+    /// 配置了`StoreFallbackPolicy`时，每次读写会话存储中的access_token后回调，可作为会话存储健康状态的gauge，默认不记录
+    fn store_healthy(&self, _appid: &str, _healthy: bool) {}
+}
+
+impl fmt::Debug for dyn MetricsRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsRecorder")
+    }
+}
+
+/// 默认不记录
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetricsRecorder;
+
+impl MetricsRecorder for NoopMetricsRecorder {
+    fn record(&self, _appid: &str, _method: &str, _status: Outcome, _elapsed: Duration, _attempt: usize) {}
+}
@@ -0,0 +1,370 @@
+//!
+//! 基于会话存储的可靠发送队列
+//!
+//! 模板消息、客服消息等接口会因频率限制（如45047）或网络原因临时失败，进程在重试期间重启会导致消息丢失。
+//! [`OutboxQueue`]把待发送的消息、已尝试次数和下次执行时间持久化到[`SessionStore`]中，重启后用同一存储和队列名
+//! 重新创建队列即可继续发送；超过最大尝试次数的消息进入死信列表，可通过[`OutboxQueue::dead_letters`]取出处理。
+//!
+//! 会话存储只有get/set，队列以索引key记录待发送与死信的ID列表，每条消息单独存放。索引的更新在进程内加锁，
+//! 多个进程共用同一队列时请保证只有一个进程调用`drain`/`run_once`。
+//!
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::{get_timestamp, rand_string, LabraError, LabradorResult, SessionStore};
+
+/// 默认的首次重试间隔
+const DEFAULT_BASE_BACKOFF: Duration = Duration::from_secs(1);
+/// 默认的重试间隔上限
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// 队列中的一条消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxItem<P> {
+    pub id: String,
+    pub payload: P,
+    /// 已尝试次数，调用处理函数前即已加1，进程在处理中途退出同样计为一次尝试
+    pub attempts: u32,
+    pub max_attempts: u32,
+    /// 下次执行时间（毫秒级时间戳）
+    pub next_run_at: i64,
+    /// 最近一次失败的错误信息
+    pub last_error: Option<String>,
+    /// 入队时间（毫秒级时间戳）
+    pub created_at: i64,
+}
+
+/// <pre>
+/// 可靠发送队列
+/// 消息须可序列化，处理函数返回错误时按指数退避重试，达到最大尝试次数后进入死信列表
+/// </pre>
+///
+/// # Examples
+///
+/// ```no_run
+/// use labrador::{OutboxQueue, SimpleStorage, TemplateMessage, WechatMpClient};
+///
+/// # async fn run(client: WechatMpClient<SimpleStorage>, message: TemplateMessage) -> labrador::LabradorResult<()> {
+/// let queue = OutboxQueue::<TemplateMessage, _>::new("template", SimpleStorage::new());
+/// queue.enqueue(message, 5)?;
+/// queue.drain(|message| {
+///     let client = client.clone();
+///     async move { client.template_msg().send_mp_message(message).await.map(|_| ()) }
+/// }).await?;
+/// for item in queue.dead_letters()? {
+///     println!("{} failed after {} attempts: {:?}", item.id, item.attempts, item.last_error);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutboxQueue<P, S: SessionStore> {
+    name: String,
+    store: S,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    /// 索引的读改写在进程内串行执行
+    lock: Arc<Mutex<()>>,
+    _payload: PhantomData<fn() -> P>,
+}
+
+impl<P: Serialize + DeserializeOwned + Clone, S: SessionStore> OutboxQueue<P, S> {
+    /// `name`区分同一存储中的不同队列
+    pub fn new<N: Into<String>>(name: N, store: S) -> Self {
+        OutboxQueue {
+            name: name.into(),
+            store,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            lock: Arc::new(Mutex::new(())),
+            _payload: PhantomData,
+        }
+    }
+
+    /// 重试间隔，第n次失败后等待`base * 2^(n-1)`，最长`max`，默认1秒起、最长10分钟
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_backoff = base;
+        self.max_backoff = max;
+        self
+    }
+
+    /// 入队，返回消息ID；`max_attempts`至少为1
+    pub fn enqueue(&self, payload: P, max_attempts: u32) -> LabradorResult<String> {
+        let now = get_timestamp();
+        let item = OutboxItem {
+            id: format!("{}{}", now, rand_string(8)),
+            payload,
+            attempts: 0,
+            max_attempts: max_attempts.max(1),
+            next_run_at: now,
+            last_error: None,
+            created_at: now,
+        };
+        self.save_item(&item)?;
+        let _guard = self.lock()?;
+        let mut index = self.load_index(&self.pending_key())?;
+        index.push(item.id.to_owned());
+        self.save_index(&self.pending_key(), &index)?;
+        Ok(item.id)
+    }
+
+    /// 待发送的消息，按入队先后排列
+    pub fn pending(&self) -> LabradorResult<Vec<OutboxItem<P>>> {
+        self.load_items(&self.pending_key())
+    }
+
+    /// 死信列表，即达到最大尝试次数仍失败的消息
+    pub fn dead_letters(&self) -> LabradorResult<Vec<OutboxItem<P>>> {
+        self.load_items(&self.dead_key())
+    }
+
+    /// 从死信列表中移除，返回被移除的消息
+    pub fn remove_dead_letter(&self, id: &str) -> LabradorResult<Option<OutboxItem<P>>> {
+        let _guard = self.lock()?;
+        let item = self.load_item(id)?;
+        let mut index = self.load_index(&self.dead_key())?;
+        index.retain(|v| v != id);
+        self.save_index(&self.dead_key(), &index)?;
+        self.remove_item(id)?;
+        Ok(item)
+    }
+
+    /// 将死信重新放回待发送队列，尝试次数清零
+    pub fn retry_dead_letter(&self, id: &str) -> LabradorResult<bool> {
+        let _guard = self.lock()?;
+        let mut item = match self.load_item(id)? {
+            Some(item) => item,
+            None => return Ok(false),
+        };
+        item.attempts = 0;
+        item.next_run_at = get_timestamp();
+        self.save_item(&item)?;
+        self.move_id(id, &self.dead_key(), &self.pending_key())?;
+        Ok(true)
+    }
+
+    /// <pre>
+    /// 处理一轮已到执行时间的消息，返回本轮处理的条数
+    /// 成功的消息出队，失败的按退避时间推迟，达到最大尝试次数的进入死信列表
+    /// </pre>
+    pub async fn run_once<F, Fut>(&self, handler: &F) -> LabradorResult<usize>
+        where F: Fn(P) -> Fut, Fut: Future<Output = LabradorResult<()>> {
+        let now = get_timestamp();
+        let due = self.pending()?.into_iter().filter(|item| item.next_run_at <= now).collect::<Vec<_>>();
+        for mut item in due.iter().cloned() {
+            item.attempts += 1;
+            item.next_run_at = now + self.backoff_for(item.attempts).as_millis() as i64;
+            self.save_item(&item)?;
+            match handler(item.payload.clone()).await {
+                Ok(_) => {
+                    let _guard = self.lock()?;
+                    let mut index = self.load_index(&self.pending_key())?;
+                    index.retain(|v| v != &item.id);
+                    self.save_index(&self.pending_key(), &index)?;
+                    self.remove_item(&item.id)?;
+                }
+                Err(err) => {
+                    item.last_error = Some(err.to_string());
+                    self.save_item(&item)?;
+                    if item.attempts >= item.max_attempts {
+                        let _guard = self.lock()?;
+                        self.move_id(&item.id, &self.pending_key(), &self.dead_key())?;
+                    }
+                }
+            }
+        }
+        Ok(due.len())
+    }
+
+    /// <pre>
+    /// 持续处理，直到待发送队列为空（全部成功或进入死信列表）
+    /// 没有到期的消息时等待到最近一条的执行时间
+    /// </pre>
+    pub async fn drain<F, Fut>(&self, handler: F) -> LabradorResult<()>
+        where F: Fn(P) -> Fut, Fut: Future<Output = LabradorResult<()>> {
+        loop {
+            self.run_once(&handler).await?;
+            let next_run_at = match self.pending()?.iter().map(|item| item.next_run_at).min() {
+                Some(next_run_at) => next_run_at,
+                None => return Ok(()),
+            };
+            let wait = (next_run_at - get_timestamp()).max(0) as u64;
+            if wait > 0 {
+                crate::runtime::sleep(Duration::from_millis(wait)).await;
+            }
+        }
+    }
+
+    fn backoff_for(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.base_backoff.checked_mul(factor).unwrap_or(self.max_backoff).min(self.max_backoff)
+    }
+
+    fn lock(&self) -> LabradorResult<std::sync::MutexGuard<'_, ()>> {
+        self.lock.lock().map_err(|_| LabraError::RequestError("outbox lock poisoned".to_string()))
+    }
+
+    fn pending_key(&self) -> String {
+        format!("{}_outbox_pending", self.name)
+    }
+
+    fn dead_key(&self) -> String {
+        format!("{}_outbox_dead", self.name)
+    }
+
+    fn item_key(&self, id: &str) -> String {
+        format!("{}_outbox_item_{}", self.name, id)
+    }
+
+    fn move_id(&self, id: &str, from: &str, to: &str) -> LabradorResult<()> {
+        let mut source = self.load_index(from)?;
+        source.retain(|v| v != id);
+        self.save_index(from, &source)?;
+        let mut target = self.load_index(to)?;
+        if !target.iter().any(|v| v == id) {
+            target.push(id.to_string());
+        }
+        self.save_index(to, &target)
+    }
+
+    fn load_index(&self, key: &str) -> LabradorResult<Vec<String>> {
+        match self.store.get::<_, String>(key, None)? {
+            Some(v) if !v.is_empty() => serde_json::from_str(&v).map_err(LabraError::from),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn save_index(&self, key: &str, index: &[String]) -> LabradorResult<()> {
+        self.store.set(key, serde_json::to_string(index)?, None)
+    }
+
+    fn load_items(&self, key: &str) -> LabradorResult<Vec<OutboxItem<P>>> {
+        let mut items = Vec::new();
+        for id in self.load_index(key)? {
+            if let Some(item) = self.load_item(&id)? {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    fn load_item(&self, id: &str) -> LabradorResult<Option<OutboxItem<P>>> {
+        match self.store.get::<_, String>(self.item_key(id), None)? {
+            Some(v) if !v.is_empty() => serde_json::from_str(&v).map(Some).map_err(LabraError::from),
+            _ => Ok(None),
+        }
+    }
+
+    fn save_item(&self, item: &OutboxItem<P>) -> LabradorResult<()> {
+        self.store.set(self.item_key(&item.id), serde_json::to_string(item)?, None)
+    }
+
+    /// 会话存储没有删除操作，以空字符串表示已删除
+    fn remove_item(&self, id: &str) -> LabradorResult<()> {
+        self.store.set(self.item_key(id), String::new(), None)
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde::{Serialize, Deserialize};
+
+    use crate::{LabraError, SimpleStorage};
+    use super::OutboxQueue;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Envelope {
+        openid: String,
+        content: String,
+    }
+
+    fn envelope(openid: &str) -> Envelope {
+        Envelope { openid: openid.to_string(), content: "hello".to_string() }
+    }
+
+    fn queue(name: &str) -> OutboxQueue<Envelope, SimpleStorage> {
+        OutboxQueue::new(name, SimpleStorage::new()).backoff(Duration::from_millis(20), Duration::from_millis(100))
+    }
+
+    #[tokio::test]
+    async fn test_survives_restart() {
+        let outbox = queue("test_outbox_restart");
+        let first = outbox.enqueue(envelope("OPENID1"), 5).unwrap();
+        outbox.enqueue(envelope("OPENID2"), 5).unwrap();
+        // 第一轮全部失败（如45047），随后进程退出
+        let processed = outbox.run_once(&|_| async { Err(LabraError::ClientError { errcode: "45047".to_string(), errmsg: "out of response count limit".to_string() }) }).await.unwrap();
+        assert_eq!(processed, 2);
+        drop(outbox);
+
+        // 用同一存储重新创建队列
+        let outbox = queue("test_outbox_restart");
+        let pending = outbox.pending().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].id, first);
+        assert_eq!(pending[0].payload, envelope("OPENID1"));
+        assert_eq!(pending[0].attempts, 1);
+        assert!(pending[0].last_error.as_ref().unwrap().contains("45047"));
+        // 未到重试时间
+        assert_eq!(outbox.run_once(&|_| async { Ok(()) }).await.unwrap(), 0);
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        outbox.drain(|message: Envelope| {
+            let sent = sent.clone();
+            async move {
+                sent.lock().unwrap().push(message.openid);
+                Ok(())
+            }
+        }).await.unwrap();
+        assert_eq!(*sent.lock().unwrap(), vec!["OPENID1", "OPENID2"]);
+        assert!(outbox.pending().unwrap().is_empty());
+        assert!(outbox.dead_letters().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters() {
+        let outbox = queue("test_outbox_dead");
+        let id = outbox.enqueue(envelope("OPENID"), 3).unwrap();
+        let attempts = Arc::new(Mutex::new(0));
+        outbox.drain(|_| {
+            let attempts = attempts.clone();
+            async move {
+                *attempts.lock().unwrap() += 1;
+                Err(LabraError::RequestError("network".to_string()))
+            }
+        }).await.unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        assert!(outbox.pending().unwrap().is_empty());
+
+        let dead = queue("test_outbox_dead").dead_letters().unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].id, id);
+        assert_eq!(dead[0].attempts, 3);
+
+        // 重新放回队列后成功发送
+        assert!(outbox.retry_dead_letter(&id).unwrap());
+        assert_eq!(outbox.pending().unwrap()[0].attempts, 0);
+        outbox.drain(|_| async { Ok(()) }).await.unwrap();
+        assert!(outbox.dead_letters().unwrap().is_empty());
+        assert!(outbox.pending().unwrap().is_empty());
+        assert!(!outbox.retry_dead_letter(&id).unwrap());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let outbox = queue("test_outbox_backoff");
+        assert_eq!(outbox.backoff_for(1), Duration::from_millis(20));
+        assert_eq!(outbox.backoff_for(2), Duration::from_millis(40));
+        assert_eq!(outbox.backoff_for(3), Duration::from_millis(80));
+        assert_eq!(outbox.backoff_for(4), Duration::from_millis(100));
+        assert_eq!(outbox.backoff_for(40), Duration::from_millis(100));
+    }
+}
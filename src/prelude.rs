@@ -0,0 +1,102 @@
+//!
+//! 常用类型的统一导出
+//!
+//! 通过`use labrador::prelude::*;`即可引入各平台的客户端、会话存储、返回结果及消息/回复等常用类型，
+//! 各接口服务通过客户端上的方法获取，如`client.user()`、`client.code_session()`、`client.wxpay()`。
+//!
+//! ## 公众号：获取用户信息并发送模板消息
+//!
+//! ```no_run
+//! use labrador::prelude::*;
+//! use serde_json::json;
+//!
+//! # async fn example() -> LabradorResult<()> {
+//! let client = WechatMpClient::<SimpleStorage>::new("appid", "secret");
+//! let user = client.user().get("openid").await?;
+//! client.template_msg().send_mp_message(TemplateMessage {
+//!     touser: user.openid.into(),
+//!     template_id: "template_id".to_string(),
+//!     url: None,
+//!     miniprogram: None,
+//!     data: json!({"first": {"value": "您好"}}),
+//! }).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## 小程序：登录凭证校验
+//!
+//! ```no_run
+//! use labrador::prelude::*;
+//!
+//! # async fn example() -> LabradorResult<()> {
+//! let client = WechatMaClient::<SimpleStorage>::new("appid", "secret");
+//! let session = client.code_session().jscode_2_session("code").await?;
+//! println!("openid: {}", session.openid);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## 微信支付：JSAPI下单并解析支付通知
+//!
+//! ```no_run
+//! use labrador::prelude::*;
+//!
+//! # async fn example(notify_body: &str) -> LabradorResult<()> {
+//! let client = WechatPayClient::<SimpleStorage>::new("appid", "secret")
+//!     .mch_id("mch_id".to_string())
+//!     .key_v3("key_v3".to_string())
+//!     .serial_no("serial_no".to_string())
+//!     .private_key("private_key".to_string());
+//! let res = client.wxpay().unified_order_v3(TradeType::Jsapi, WechatPayRequestV3 {
+//!     appid: None,
+//!     mch_id: "mch_id".to_string(),
+//!     description: "测试商品支付".to_string(),
+//!     out_trade_no: TradeNo::try_new(out_trade_no())?,
+//!     time_expire: "2022-10-01T00:00:00+08:00".to_string(),
+//!     attach: None,
+//!     notify_url: "https://example.com/notify".to_string(),
+//!     amount: Amount { total: 1, currency: None, payer_total: None, payer_currency: None },
+//!     payer: Payer { openid: "openid".to_string() }.into(),
+//!     detail: None,
+//!     scene_info: None,
+//!     settle_info: None,
+//! }).await?;
+//! println!("prepay_id: {:?}", res.prepay_id);
+//! let notify = client.wxpay().parse_order_notify_v3(notify_body, None).await?;
+//! println!("trade_state: {}", notify.decrypt_result().trade_state);
+//! # Ok(())
+//! # }
+//! ```
+//!
+pub use crate::{LabradorResult, LabraError, SessionStore, SimpleStorage, RequestType, Method, Page, PagedStream, nonce_str, out_trade_no};
+pub use crate::redis_store::RedisStorage;
+
+#[cfg(feature = "wechat-core")]
+pub use crate::{WechatCommonResponse, WechatCrypto, JsapiSignature};
+
+#[cfg(feature = "wechat-mp")]
+pub use crate::{WechatMpClient, TemplateMessage, messages::Message, replies::Reply, parse_message};
+#[cfg(feature = "server")]
+pub use crate::WechatMpServer;
+
+#[cfg(feature = "wechat-cp")]
+pub use crate::{WechatCpClient, WechatCpTpClient};
+
+#[cfg(feature = "wechat-ma")]
+pub use crate::miniapp::WechatMaClient;
+
+#[cfg(feature = "wechat-pay")]
+pub use crate::{WechatPayClient, TradeType, TradeNo, WechatPayRequestV3, WechatPayResponseV3, WechatPayNotifyResponseV3, Amount, Payer};
+
+#[cfg(feature = "alipay")]
+pub use crate::AlipayClient;
+
+#[cfg(feature = "taobao")]
+pub use crate::TaobaoClient;
+
+#[cfg(feature = "jd")]
+pub use crate::JDClient;
+
+#[cfg(feature = "pdd")]
+pub use crate::PDDClient;
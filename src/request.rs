@@ -1,19 +1,27 @@
 use std::net::SocketAddr;
+use std::time::Duration;
 use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
 use openssl::x509::X509;
 use reqwest::{self, multipart, StatusCode, Url};
 use reqwest::header::{HeaderMap, HeaderValue};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::Value;
 use crate::errors::LabraError;
 use crate::LabradorResult;
+use crate::util::inflate;
+use crate::{json_pointer, JsonArrayIter, JsonArrayStream};
 
 /// Parse Data For Response
 pub trait Response <T> where T: Serialize {
     fn parse_result(&self) -> LabradorResult<T>;
 }
 
-const APP_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; WOW64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/63.0.3239.132 Safari/537.36";
+/// 解压后响应体的默认上限（64MB），防止压缩炸弹耗尽内存
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+pub(crate) const APP_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; WOW64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/63.0.3239.132 Safari/537.36";
 
 
 /// Common Params Format
@@ -32,6 +40,47 @@ pub trait RequestMethod {
     fn get_response_key(&self) -> String {
         String::default()
     }
+
+    /// 接口调用凭证的传递方式，默认作为查询参数
+    fn auth_style(&self) -> AuthStyle {
+        AuthStyle::QueryParam
+    }
+}
+
+/// <pre>
+/// 接口调用凭证的传递方式
+/// 传统接口要求在URL中携带`?access_token=`，部分新接口支持（或要求）通过请求头传递，避免凭证出现在日志记录的URL中
+/// </pre>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthStyle {
+    /// 作为查询参数传递
+    #[default]
+    QueryParam,
+    /// 不需要凭证，如换取access_token的接口
+    None,
+    /// 通过`Authorization: Bearer <token>`请求头传递
+    BearerHeader,
+}
+
+impl AuthStyle {
+    /// <pre>
+    /// 按传递方式附加凭证，`key`为查询参数名（如`access_token`），返回需要附加的请求头
+    /// 调用方传入的同名查询参数会被移除，凭证不会同时出现在URL与请求头中
+    /// </pre>
+    pub fn attach(&self, key: &str, token: &str, params: &mut Vec<(String, String)>) -> Vec<(String, String)> {
+        if *self == AuthStyle::None || token.is_empty() {
+            return vec![];
+        }
+        params.retain(|(k, _)| k != key);
+        match self {
+            AuthStyle::QueryParam => {
+                params.push((key.to_string(), token.to_string()));
+                vec![]
+            }
+            AuthStyle::BearerHeader => vec![(reqwest::header::AUTHORIZATION.to_string(), format!("Bearer {}", token))],
+            AuthStyle::None => vec![],
+        }
+    }
 }
 
 #[allow(unused)]
@@ -64,19 +113,19 @@ impl From<Method> for reqwest::Method {
     }
 }
 
-impl ToString for Method {
-    fn to_string(&self) -> String {
-        match self {
-            Method::Options => String::from("OPTIONS"),
-            Method::Get => String::from("GET"),
-            Method::Post => String::from("POST"),
-            Method::Put => String::from("PUT"),
-            Method::Delete => String::from("DELETE"),
-            Method::Head => String::from("HEAD"),
-            Method::Trace => String::from("TRACE"),
-            Method::Connect => String::from("CONNECT"),
-            Method::Patch => String::from("PATCH"),
-        }
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Method::Options => "OPTIONS",
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+            Method::Trace => "TRACE",
+            Method::Connect => "CONNECT",
+            Method::Patch => "PATCH",
+        })
     }
 }
 
@@ -91,10 +140,9 @@ pub enum RequestBody<T: Serialize> {
     Null
 }
 
-impl <T: Serialize> RequestBody<T> {
-
-    pub fn to_string(&self) -> String {
-        match self {
+impl <T: Serialize> std::fmt::Display for RequestBody<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let body = match self {
             RequestBody::Json(v) => serde_json::to_string(&v).unwrap_or_default(),
             RequestBody::Form(v) => serde_json::to_string(&v).unwrap_or_default(),
             RequestBody::Multipart(v) => {
@@ -104,7 +152,8 @@ impl <T: Serialize> RequestBody<T> {
             RequestBody::Text(v) => v.to_string(),
             RequestBody::Raw(_v) => String::from("bytes"),
             RequestBody::Null => String::default(),
-        }
+        };
+        f.write_str(&body)
     }
 }
 
@@ -184,7 +233,15 @@ pub struct LabraRequest <T> where T: Serialize {
     pub cert: Option<LabraCertificate>,
     pub params: Option<Vec<(String, String)>>,
     pub headers: Option<Vec<(String, String)>>,
-    pub body: RequestBody<T>
+    pub body: RequestBody<T>,
+    /// 复用的HTTP客户端，未设置或携带证书时单独创建
+    pub(crate) http_client: Option<reqwest::Client>,
+    /// 是否自动解压gzip/deflate响应，默认开启
+    pub decompress: bool,
+    /// 解压后响应体的最大字节数，超过时返回`LabraError::DecodeError`
+    pub max_decompressed_size: usize,
+    /// 本次请求的超时时间（含读取响应体），未设置时不限制
+    pub timeout: Option<Duration>,
 }
 
 #[allow(unused)]
@@ -193,17 +250,52 @@ pub struct LabraResponse {
     status: StatusCode,
     headers: HeaderMap,
     remote_addr: Option<SocketAddr>,
-    body: Bytes
+    body: Bytes,
+    /// 解压GZIP格式的文件时使用，未开启解压时为`None`
+    max_decompressed_size: Option<usize>,
 }
 
 impl LabraResponse {
-    fn new(url: Url, status: StatusCode, remote_addr: Option<SocketAddr>, headers: HeaderMap, body: Bytes) -> LabraResponse {
-        LabraResponse {
+    /// 按`Content-Encoding`解压响应体，解压后只保留解压结果；`max_decompressed_size`为`None`时原样保留
+    fn new(url: Url, status: StatusCode, remote_addr: Option<SocketAddr>, mut headers: HeaderMap, body: Bytes, max_decompressed_size: Option<usize>) -> LabradorResult<LabraResponse> {
+        let encoding = headers.get(reqwest::header::CONTENT_ENCODING).and_then(|v| v.to_str().ok()).map(|v| v.trim().to_lowercase());
+        let decoded = match (encoding.as_deref(), max_decompressed_size) {
+            (Some("gzip"), Some(limit)) | (Some("x-gzip"), Some(limit)) => Some(inflate::gunzip(&body, limit)?),
+            (Some("deflate"), Some(limit)) => Some(inflate::zlib_decompress(&body, limit)?),
+            _ => None,
+        };
+        // 解压后原有的编码与长度已不再适用
+        let body = match decoded {
+            Some(decoded) => {
+                tracing::debug!("[解压响应] encoding: {:?}, {} -> {} bytes", encoding, body.len(), decoded.len());
+                headers.remove(reqwest::header::CONTENT_ENCODING);
+                headers.remove(reqwest::header::CONTENT_LENGTH);
+                Bytes::from(decoded)
+            }
+            None => body,
+        };
+        Ok(LabraResponse {
             url,
             headers,
             remote_addr,
             status,
-            body
+            body,
+            max_decompressed_size,
+        })
+    }
+
+    /// 由缓存的响应体构造
+    pub(crate) fn cached(url: Url, body: String) -> LabraResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert(reqwest::header::CONTENT_TYPE, reqwest::header::HeaderValue::from_static("application/json"));
+        let body = Bytes::from(body);
+        LabraResponse {
+            url,
+            status: StatusCode::OK,
+            headers,
+            remote_addr: None,
+            body,
+            max_decompressed_size: None,
         }
     }
 
@@ -223,10 +315,34 @@ impl LabraResponse {
         &self.headers
     }
 
+    /// 获取指定响应头，不存在或非可见字符时返回None
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// 请求ID（如微信支付V3的Request-ID），排查问题时提供给官方
+    pub fn request_id(&self) -> Option<&str> {
+        self.header_value("Request-ID")
+    }
+
     pub fn json<T: DeserializeOwned>(&self) -> LabradorResult<T> {
         serde_json::from_slice(&self.body).map_err(LabraError::from)
     }
 
+    /// <pre>
+    /// 逐条解析`pointer`（如`/external_contact_list`）指向的数组，用于数十MB的大列表返回
+    /// 不会先解析为`Value`，同一时刻只持有响应体与当前元素；字段不存在或为null时为空
+    /// 响应体已完整读取，不需要缓存整个响应时使用[`LabraRequest::request_stream`]
+    /// </pre>
+    pub fn json_stream<T: DeserializeOwned>(&self, pointer: &str) -> LabradorResult<impl Stream<Item = LabradorResult<T>> + Unpin> {
+        JsonArrayIter::new(self.body.clone(), pointer).map(stream::iter)
+    }
+
+    /// 只解析`pointer`（如`/next_cursor`）指向的值，字段不存在时返回`None`
+    pub fn json_pointer<T: DeserializeOwned>(&self, pointer: &str) -> LabradorResult<Option<T>> {
+        json_pointer(&self.body, pointer)
+    }
+
     pub fn text(&self) -> LabradorResult<String> {
         unsafe {
             // decoding returned Cow::Borrowed, meaning these bytes
@@ -235,15 +351,85 @@ impl LabraResponse {
         }
     }
 
+    /// 响应体，开启解压时GZIP格式的文件（如账单）会一并解压，同样受`max_decompressed_size`限制
     pub fn bytes(&self) -> LabradorResult<Bytes> {
-        Ok(self.body.clone())
+        match self.max_decompressed_size {
+            Some(limit) if inflate::is_gzip(&self.body) => inflate::gunzip(&self.body, limit).map(Bytes::from),
+            _ => Ok(self.body.clone()),
+        }
+    }
+
+    /// <pre>
+    /// 接收到的响应体，不解压其中的GZIP文件，用于校验需要按压缩数据计算的摘要（如GZIP账单的hash_value）
+    /// 传输层的`Content-Encoding`此时已经解码，不再单独保留
+    /// </pre>
+    pub fn raw_bytes(&self) -> Bytes {
+        self.body.clone()
+    }
+}
+
+impl <T> LabraRequest <T> where T: Serialize {
+    /// 转换为可重复发送的请求（请求体序列化为`Value`），Multipart请求体无法复制、请求体序列化失败时原样返回
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn into_replayable(self) -> Result<LabraRequest<Value>, Self> {
+        let replayable = match &self.body {
+            RequestBody::Json(v) | RequestBody::Form(v) => match serde_json::to_value(v) {
+                Ok(v) => Some(v),
+                Err(_) => return Err(self),
+            },
+            RequestBody::Multipart(_) => return Err(self),
+            _ => None,
+        };
+        let LabraRequest { url, method, req_type, identity, cert, params, headers, body, http_client, decompress, max_decompressed_size, timeout } = self;
+        let body = match (body, replayable) {
+            (RequestBody::Json(_), Some(v)) => RequestBody::Json(v),
+            (RequestBody::Form(_), Some(v)) => RequestBody::Form(v),
+            (RequestBody::Xml(v), _) => RequestBody::Xml(v),
+            (RequestBody::Text(v), _) => RequestBody::Text(v),
+            (RequestBody::Raw(v), _) => RequestBody::Raw(v),
+            _ => RequestBody::Null,
+        };
+        Ok(LabraRequest { url, method, req_type, identity, cert, params, headers, body, http_client, decompress, max_decompressed_size, timeout })
+    }
+}
+
+impl LabraRequest<Value> {
+    pub(crate) fn replay(&self, url: String) -> Self {
+        let body = match &self.body {
+            RequestBody::Json(v) => RequestBody::Json(v.clone()),
+            RequestBody::Form(v) => RequestBody::Form(v.clone()),
+            RequestBody::Xml(v) => RequestBody::Xml(v.clone()),
+            RequestBody::Text(v) => RequestBody::Text(v.clone()),
+            RequestBody::Raw(v) => RequestBody::Raw(v.clone()),
+            RequestBody::Multipart(_) | RequestBody::Null => RequestBody::Null,
+        };
+        LabraRequest {
+            url,
+            method: self.method.clone(),
+            req_type: self.req_type.clone(),
+            identity: self.identity.clone(),
+            cert: self.cert.clone(),
+            params: self.params.clone(),
+            headers: self.headers.clone(),
+            body,
+            http_client: self.http_client.clone(),
+            decompress: self.decompress,
+            max_decompressed_size: self.max_decompressed_size,
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl <T> Default for LabraRequest <T> where T: Serialize {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[allow(unused)]
 impl <T> LabraRequest <T> where T: Serialize {
     pub fn new() -> Self {
-        LabraRequest { url: String::default(), method: Method::Post, req_type: RequestType::Json, identity: None, cert: None, params: None, headers: None, body: RequestBody::Null }
+        LabraRequest { url: String::default(), method: Method::Post, req_type: RequestType::Json, identity: None, cert: None, params: None, headers: None, body: RequestBody::Null, http_client: None, decompress: true, max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE, timeout: None }
     }
 
     pub fn url(mut self, url: String) -> Self {
@@ -281,8 +467,34 @@ impl <T> LabraRequest <T> where T: Serialize {
         self
     }
 
+    pub(crate) fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client.into();
+        self
+    }
+
+    /// 是否自动解压响应，关闭后不再发送`Accept-Encoding`，响应体原样返回
+    pub fn decompress(mut self, decompress: bool) -> Self {
+        self.decompress = decompress;
+        self
+    }
+
+    /// 解压后响应体的最大字节数，默认`DEFAULT_MAX_DECOMPRESSED_SIZE`，超过时返回`LabraError::DecodeError`
+    pub fn max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+
+    /// <pre>
+    /// 设置本次请求的超时时间，覆盖客户端的默认设置
+    /// 从建立连接到读取完响应体整体计时，超时后丢弃连接并返回`LabraError::Timeout`
+    /// </pre>
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
     pub fn body(mut self, body: RequestBody<T>) -> Self {
-        self.body = body.into();
+        self.body = body;
         self
     }
 
@@ -313,23 +525,62 @@ impl <T> LabraRequest <T> where T: Serialize {
 
     #[inline]
     pub async fn request(self) -> LabradorResult<LabraResponse> {
-        let mut http_url = Url::parse(&self.url).unwrap();
-        if let Some(params) = &self.params {
-            http_url.query_pairs_mut().extend_pairs(params.into_iter());
+        match self.timeout {
+            // 超时后send的future被丢弃，连接随之关闭，不会归还到连接池
+            Some(timeout) => crate::runtime::timeout(timeout, self.send()).await.ok_or(LabraError::Timeout(timeout))?,
+            None => self.send().await,
         }
-        let mut client = reqwest::Client::builder().user_agent(APP_USER_AGENT);
-        if let Some(identity) = &self.identity {
-            client = client.identity(identity.identity());
+    }
+
+    /// <pre>
+    /// 发送请求，收到响应头后立即返回，响应体在读取时才从连接中接收
+    /// 不会自动解压（不发送`Accept-Encoding`），超时只作用于收到响应头之前
+    /// </pre>
+    pub async fn request_stream(mut self) -> LabradorResult<LabraStreamResponse> {
+        self.decompress = false;
+        let response = match self.timeout {
+            Some(timeout) => crate::runtime::timeout(timeout, self.send_raw()).await.ok_or(LabraError::Timeout(timeout))?,
+            None => self.send_raw().await,
+        }?;
+        Ok(LabraStreamResponse { response })
+    }
+
+    async fn send(self) -> LabradorResult<LabraResponse> {
+        let max_decompressed_size = if self.decompress { Some(self.max_decompressed_size) } else { None };
+        let result = self.send_raw().await?;
+        let status = result.status();
+        let remote_addr = result.remote_addr();
+        let headers = result.headers();
+        let response = LabraResponse::new(result.url().clone(), status, remote_addr, headers.clone(), result.bytes().await?, max_decompressed_size)?;
+        tracing::info!("[请求第三方接口响应] data:{}", &response.text().unwrap_or_default());
+        Ok(response)
+    }
+
+    async fn send_raw(self) -> LabradorResult<reqwest::Response> {
+        let mut http_url = Url::parse(&self.url).map_err(|err| LabraError::RequestError(format!("invalid url {}: {}", self.url, err)))?;
+        // 空参数不追加'?'，否则实际请求的URL与V3签名时使用的URL不一致
+        if let Some(params) = self.params.as_ref().filter(|params| !params.is_empty()) {
+            http_url.query_pairs_mut().extend_pairs(params);
         }
-        if let Some(cert) = &self.cert {
-            client = client.add_root_certificate(cert.reqwest_cert()?);
+        let client = match &self.http_client {
+            Some(client) if self.identity.is_none() && self.cert.is_none() => client.clone(),
+            _ => {
+                let mut client = reqwest::Client::builder().user_agent(APP_USER_AGENT);
+                if let Some(identity) = &self.identity {
+                    client = client.identity(identity.identity());
+                }
+                if let Some(cert) = &self.cert {
+                    client = client.add_root_certificate(cert.reqwest_cert()?);
+                }
+                client.build()?
+            }
+        };
+        let mut request = client.request(self.method.clone().into(), http_url.to_owned());
+        // Multipart由reqwest设置带boundary的Content-Type，重复设置会导致服务端无法解析
+        if !matches!(self.body, RequestBody::Multipart(_)) {
+            request = request.header(reqwest::header::CONTENT_TYPE, self.req_type.get_content_type());
         }
-        let client = client.build()?;
-        let mut request = client.request(self.method.clone().into(), http_url.to_owned()).header(
-            reqwest::header::CONTENT_TYPE,
-            self.req_type.get_content_type(),
-        );
-        let mut data = &self.body.to_string();
+        let data = &self.body.to_string();
         match self.body {
             RequestBody::Json(v) => {
                 request = request.json(&v);
@@ -373,19 +624,73 @@ impl <T> LabraRequest <T> where T: Serialize {
         //         }
         //     }
         // }
+        let mut accept_encoding = false;
         if let Some(headers) = &self.headers {
-            for (k, v) in headers.into_iter() {
+            for (k, v) in headers.iter() {
+                accept_encoding |= k.eq_ignore_ascii_case("accept-encoding");
                 request = request.header(k, HeaderValue::from_str(v)?);
             }
         }
+        if self.decompress && !accept_encoding {
+            request = request.header(reqwest::header::ACCEPT_ENCODING, "gzip, deflate");
+        }
         tracing::info!("[请求第三方接口参数] url: {}, data:{}", http_url.as_str(), data);
-        let result = request.send().await?;
-        let status = result.status();
-        let remote_addr = result.remote_addr();
-        let headers = result.headers();
-        let response = LabraResponse::new(result.url().clone(), status, remote_addr, headers.clone(), result.bytes().await?);
-        tracing::info!("[请求第三方接口响应] data:{}", &response.text().unwrap_or_default());
-        Ok(response)
+        Ok(request.send().await?)
+    }
+}
+
+/// 响应体的字节流
+pub type LabraByteStream = BoxStream<'static, Result<Bytes, reqwest::Error>>;
+
+/// <pre>
+/// 尚未读取响应体的响应，见[`LabraRequest::request_stream`]
+/// 用于逐条解析数十MB的大列表，响应体边接收边解析，不会整体缓存
+/// </pre>
+pub struct LabraStreamResponse {
+    response: reqwest::Response,
+}
+
+impl LabraStreamResponse {
+    pub fn status(&self) -> StatusCode {
+        self.response.status()
+    }
+
+    pub fn url(&self) -> &Url {
+        self.response.url()
+    }
+
+    pub fn header(&self) -> &HeaderMap {
+        self.response.headers()
+    }
+
+    /// 获取指定响应头，不存在或非可见字符时返回None
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.response.headers().get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// 响应体的字节流，按接收顺序逐块返回
+    pub fn bytes_stream(self) -> LabraByteStream {
+        stream::unfold(self.response, |mut response| async move {
+            response.chunk().await.transpose().map(|chunk| (chunk, response))
+        }).boxed()
+    }
+
+    /// <pre>
+    /// 逐条解析`pointer`（如`/external_contact_list`）指向的数组，字段不存在或为null时为空
+    /// 只缓存尚未解析完的数据，同一时刻只持有当前元素，内存占用与响应总大小无关
+    /// </pre>
+    pub fn json_stream<T: DeserializeOwned>(self, pointer: &str) -> LabradorResult<JsonArrayStream<LabraByteStream, T>> {
+        if let Some(encoding) = self.header_value(reqwest::header::CONTENT_ENCODING.as_str()).filter(|v| !v.eq_ignore_ascii_case("identity")) {
+            return Err(LabraError::Unsupported(format!("逐条解析不支持压缩的响应：{}", encoding)));
+        }
+        JsonArrayStream::new(self.bytes_stream(), pointer)
+    }
+
+    /// 读取完整的响应体，如响应为错误信息而不是列表时
+    pub async fn into_response(self) -> LabradorResult<LabraResponse> {
+        let response = self.response;
+        let (url, status, remote_addr, headers) = (response.url().clone(), response.status(), response.remote_addr(), response.headers().clone());
+        LabraResponse::new(url, status, remote_addr, headers, response.bytes().await?, None)
     }
 }
 
@@ -426,20 +731,34 @@ impl LabraIdentity {
         })
     }
 
+    /// PEM格式的证书与PKCS#8私钥，如微信支付的`apiclient_cert.pem`与`apiclient_key.pem`
+    pub fn from_pkcs8_pem(cert: &[u8], key: &[u8]) -> LabradorResult<Self> {
+        let identity = reqwest::Identity::from_pkcs8_pem(cert, key)?;
+        Ok(Self {
+            identity,
+        })
+    }
+
     pub fn identity(&self) -> reqwest::Identity {
         self.identity.clone()
     }
 
+    /// 携带该客户端证书的HTTP客户端，可复用连接
+    #[cfg(feature = "wechat-pay")]
+    pub(crate) fn http_client(&self) -> LabradorResult<reqwest::Client> {
+        Ok(reqwest::Client::builder().user_agent(APP_USER_AGENT).identity(self.identity()).build()?)
+    }
+
 }
 
 impl LabraCertificate {
 
 
     pub fn from_pem(pem: Vec<u8>) -> LabradorResult<Self> {
-        let x509 = X509::from_pem(&pem).unwrap();
-        let pk = x509.public_key().unwrap();
-        let rpk = pk.public_key_to_pem().unwrap();
-        let sn = x509.serial_number().to_bn().unwrap().to_string();
+        let x509 = X509::from_pem(&pem)?;
+        let pk = x509.public_key()?;
+        let rpk = pk.public_key_to_pem()?;
+        let sn = x509.serial_number().to_bn()?.to_string();
         Ok(Self {
             serial_no: sn.to_string(),
             effective_time: "".to_string(),
@@ -451,9 +770,9 @@ impl LabraCertificate {
 
     pub fn from(pem: &str) -> LabradorResult<Self> {
         let content = pem.as_bytes();
-        let x509 = X509::from_pem(content).unwrap();
-        let pk = x509.public_key().unwrap();
-        let rpk = pk.public_key_to_pem().unwrap();
+        let x509 = X509::from_pem(content)?;
+        let pk = x509.public_key()?;
+        let rpk = pk.public_key_to_pem()?;
         Ok(Self {
             serial_no: "".to_string(),
             effective_time: "".to_string(),
@@ -488,7 +807,7 @@ where
     F: Fn(reqwest::Client) -> reqwest::RequestBuilder,
 {
     let result = f(reqwest::Client::new()).send().await?;
-    Ok(LabraResponse::new(result.url().clone(), result.status(), result.remote_addr(), result.headers().clone(), result.bytes().await?))
+    LabraResponse::new(result.url().clone(), result.status(), result.remote_addr(), result.headers().clone(), result.bytes().await?, Some(DEFAULT_MAX_DECOMPRESSED_SIZE))
 }
 
 #[allow(unused)]
@@ -497,5 +816,154 @@ where
     F: Fn(reqwest::blocking::Client) -> reqwest::blocking::RequestBuilder,
 {
     let result = f(reqwest::blocking::Client::new()).send()?;
-    Ok(LabraResponse::new(result.url().clone(), result.status(), result.remote_addr(), result.headers().clone(), result.bytes()?))
-}
\ No newline at end of file
+    LabraResponse::new(result.url().clone(), result.status(), result.remote_addr(), result.headers().clone(), result.bytes()?, Some(DEFAULT_MAX_DECOMPRESSED_SIZE))
+}
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::LabraError;
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{AuthStyle, LabraRequest, Method};
+
+    /// python: gzip.compress(b'{"errcode":0,"errmsg":"ok","download_url":"https://api.mch.weixin.qq.com/v3/billdownload/file"}', mtime=0)
+    const JSON_GZIP: &str = "H4sIAAAAAAACAzXLQQqAIBBA0bvMOpygnZcJU8uhsSm1DKK7V4t2nwf/Ap+SFedBt83XMU+gQWZowEldWIzr98SvhVLWrBHNSiraoKqnkxa1bcpKxKPDgZj/B0diD/cD4x2eMV8AAAA=";
+    /// zlib.compress 同一段JSON
+    const JSON_DEFLATE: &str = "eJw1y0EKgCAQQNG7zDqcoJ2XCVPLobEptQyiu1eLdp8H/wKfkhXnQbfN1zFPoEFmaMBJXViM6/fEr4VS1qwRzUoq2qCqp5MWtW3KSsSjw4GY/wdHYg/3AxcYIVY=";
+    /// GZIP格式的交易账单
+    const BILL_GZIP: &str = "H4sIAAAAAAACA3uya8mzGZOfTd/2cvoWnaeta57smf5iy7Kn/ds9XXSeTm171rEdyNZ5um/dk/0LX6xb9LR3KpgPlkHwn4BNed617VlDo87TXVOe754MkXzZPvHlorlcCUYGRia6BoZApGBoYGUAQjoJ5RUWaEAnwdDSAAQMDSx1EkyMDHACnYQQkJlAhYYQDToJwaHOzq7BwToJBnoGhlzPGnZDXAV0xbOpG1BcBZSDOcwQqhwAa8hjxQgBAAA=";
+
+    fn sha1_hex(data: &[u8]) -> String {
+        openssl::sha::sha1(data).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_gzip_json_response() {
+        let server = MockServer::start(vec![
+            MockResponse::bytes("application/json", &base64::decode(JSON_GZIP).unwrap()).header("Content-Encoding", "gzip"),
+            MockResponse::bytes("application/json", &base64::decode(JSON_DEFLATE).unwrap()).header("Content-Encoding", "deflate"),
+        ]).await;
+        for _ in 0..2 {
+            let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).request().await.unwrap();
+            let v = response.json::<serde_json::Value>().unwrap();
+            assert_eq!(v["download_url"], "https://api.mch.weixin.qq.com/v3/billdownload/file");
+            assert!(response.header_value("Content-Encoding").is_none());
+        }
+        let requests = server.requests();
+        assert!(requests[0].to_lowercase().contains("accept-encoding: gzip, deflate"));
+    }
+
+    #[tokio::test]
+    async fn test_gzip_bill_hash() {
+        let bill = base64::decode(BILL_GZIP).unwrap();
+        let server = MockServer::start(vec![
+            MockResponse::bytes("application/octet-stream", &bill),
+            MockResponse::bytes("application/octet-stream", &bill),
+        ]).await;
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).request().await.unwrap();
+        // 账单的hash_value按压缩后的文件计算
+        assert_eq!(sha1_hex(&response.raw_bytes()), "6e456c996706a1b48993cf4b74ebb81077cf1f45");
+        let csv = String::from_utf8(response.bytes().unwrap().to_vec()).unwrap();
+        assert!(csv.starts_with("交易时间,公众账号ID"));
+        assert!(csv.contains("`T20240101000001"));
+
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).decompress(false).request().await.unwrap();
+        assert_eq!(response.bytes().unwrap().to_vec(), bill);
+        assert!(!server.requests()[1].to_lowercase().contains("accept-encoding: gzip"));
+    }
+
+    #[tokio::test]
+    async fn test_max_decompressed_size() {
+        let bill = base64::decode(BILL_GZIP).unwrap();
+        let server = MockServer::start(vec![
+            MockResponse::bytes("application/json", &base64::decode(JSON_GZIP).unwrap()).header("Content-Encoding", "gzip"),
+            MockResponse::bytes("application/octet-stream", &bill),
+        ]).await;
+        // 传输层解压超限
+        let result = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).max_decompressed_size(16).request().await;
+        assert!(matches!(result, Err(LabraError::DecodeError(err)) if err.to_string().contains("exceeds limit of 16 bytes")));
+        // GZIP文件解压超限，压缩数据本身仍可读取
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).max_decompressed_size(16).request().await.unwrap();
+        assert!(matches!(response.bytes(), Err(LabraError::DecodeError(_))));
+        assert_eq!(response.raw_bytes().to_vec(), bill);
+    }
+
+    #[tokio::test]
+    async fn test_request_stream_json() {
+        use futures_util::StreamExt;
+
+        let padding = "x".repeat(1000);
+        let list = (0..4000).map(|id| format!(r#"{{"id":{},"padding":"{}"}}"#, id, padding)).collect::<Vec<_>>().join(",");
+        let body = format!(r#"{{"errcode":0,"errmsg":"ok","list":[{}],"next_cursor":"END"}}"#, list);
+        let server = MockServer::start(vec![
+            MockResponse::json(&body),
+            MockResponse::json(&body).header("Content-Encoding", "gzip"),
+        ]).await;
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).request_stream().await.unwrap();
+        assert!(response.status().is_success());
+        let mut items = response.json_stream::<serde_json::Value>("/list").unwrap();
+        let (mut total, mut peak_buffered) = (0, 0);
+        while let Some(item) = items.next().await {
+            assert_eq!(item.unwrap()["id"], total);
+            total += 1;
+            peak_buffered = peak_buffered.max(items.buffered_len());
+        }
+        assert_eq!(total, 4000);
+        // 边接收边解析，缓存的只是未解析完的一块
+        assert!(peak_buffered < body.len() / 4, "peak buffered: {} of {}", peak_buffered, body.len());
+        assert!(!server.requests()[0].to_lowercase().contains("accept-encoding: gzip"));
+
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).request_stream().await.unwrap();
+        assert!(matches!(response.json_stream::<serde_json::Value>("/list"), Err(LabraError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"errcode":0}"#).delay(Duration::from_millis(500)),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let http_client = reqwest::Client::new();
+        let start = Instant::now();
+        let result = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).http_client(http_client.clone()).timeout(Duration::from_millis(50)).request().await;
+        assert!(matches!(result, Err(LabraError::Timeout(timeout)) if timeout == Duration::from_millis(50)));
+        assert!(start.elapsed() < Duration::from_millis(400));
+        // 超时的请求不影响共用的HTTP客户端
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).http_client(http_client).timeout(Duration::from_secs(5)).request().await.unwrap();
+        assert_eq!(response.json::<serde_json::Value>().unwrap()["errmsg"], "ok");
+    }
+
+    #[cfg(feature = "async-std-runtime")]
+    #[async_std::test]
+    async fn test_request_timeout_async_std() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"errcode":0}"#).delay(Duration::from_millis(500)),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let start = Instant::now();
+        let result = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).timeout(Duration::from_millis(50)).request().await;
+        assert!(matches!(result, Err(LabraError::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_millis(400));
+        let response = LabraRequest::<String>::new().url(server.url.clone()).method(Method::Get).timeout(Duration::from_secs(5)).request().await.unwrap();
+        assert_eq!(response.json::<serde_json::Value>().unwrap()["errmsg"], "ok");
+    }
+
+    #[test]
+    fn test_auth_style_attach() {
+        let params = || vec![("openid".to_string(), "o1".to_string()), ("access_token".to_string(), "STALE".to_string())];
+        // 查询参数：替换调用方传入的同名参数
+        let mut query = params();
+        assert!(AuthStyle::QueryParam.attach("access_token", "TOKEN", &mut query).is_empty());
+        assert_eq!(query, vec![("openid".to_string(), "o1".to_string()), ("access_token".to_string(), "TOKEN".to_string())]);
+        // 请求头：URL中不再出现凭证
+        let mut query = params();
+        assert_eq!(AuthStyle::BearerHeader.attach("access_token", "TOKEN", &mut query), vec![("authorization".to_string(), "Bearer TOKEN".to_string())]);
+        assert_eq!(query, vec![("openid".to_string(), "o1".to_string())]);
+        // 不需要凭证或凭证为空时原样保留
+        let mut query = params();
+        assert!(AuthStyle::None.attach("access_token", "TOKEN", &mut query).is_empty());
+        assert!(AuthStyle::BearerHeader.attach("access_token", "", &mut query).is_empty());
+        assert_eq!(query, params());
+        assert_eq!(AuthStyle::default(), AuthStyle::QueryParam);
+    }
+}
@@ -0,0 +1,159 @@
+//!
+//! 异步运行时适配
+//!
+//! 请求路径上只使用这里的定时、超时、锁与停止信号，不直接依赖tokio的类型，可以运行在tokio、async-std或smol上。
+//! 开启`tokio-runtime`（默认）时定时器使用`tokio::time`，否则使用与运行时无关的`futures-timer`；
+//! 后台刷新（`refresher`）与流式上传（`upload-stream`）仍需要tokio。
+//!
+use std::future::Future;
+#[cfg(feature = "wechat-cp")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "wechat-cp")]
+use std::sync::Mutex as StdMutex;
+#[cfg(feature = "wechat-cp")]
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+use futures_util::future::{self, Either};
+
+/// 异步锁，不依赖具体的运行时
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma", feature = "wechat-cp"))]
+pub(crate) use futures_util::lock::Mutex;
+
+/// 等待指定时间
+#[cfg(feature = "tokio-runtime")]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// 等待指定时间
+#[cfg(not(feature = "tokio-runtime"))]
+pub(crate) async fn sleep(duration: Duration) {
+    futures_timer::Delay::new(duration).await
+}
+
+/// 在指定时间内等待future完成，超时返回None
+pub(crate) async fn timeout<F: Future>(duration: Duration, fut: F) -> Option<F::Output> {
+    let delay = sleep(duration);
+    futures_util::pin_mut!(fut, delay);
+    match future::select(fut, delay).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+/// <pre>
+/// 停止信号，触发后所有等待者立即返回
+/// 触发是一次性的，之后`wait`总是立即完成
+/// </pre>
+#[cfg(feature = "wechat-cp")]
+#[derive(Debug, Default)]
+pub(crate) struct Signal {
+    fired: AtomicBool,
+    wakers: StdMutex<Vec<Waker>>,
+}
+
+#[cfg(feature = "wechat-cp")]
+impl Signal {
+    pub(crate) fn fire(&self) {
+        self.fired.store(true, Ordering::SeqCst);
+        let wakers = self.wakers.lock().map(|mut wakers| std::mem::take(&mut *wakers)).unwrap_or_default();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    pub(crate) fn is_fired(&self) -> bool {
+        self.fired.load(Ordering::SeqCst)
+    }
+
+    /// 等待信号触发
+    pub(crate) fn wait(&self) -> impl Future<Output = ()> + '_ {
+        future::poll_fn(move |cx| {
+            if self.is_fired() {
+                return Poll::Ready(());
+            }
+            if let Ok(mut wakers) = self.wakers.lock() {
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+            }
+            // 注册期间可能已经触发
+            if self.is_fired() { Poll::Ready(()) } else { Poll::Pending }
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use super::{sleep, timeout};
+    #[cfg(feature = "wechat-cp")]
+    use super::Signal;
+
+    #[tokio::test]
+    async fn test_timeout() {
+        assert_eq!(timeout(Duration::from_secs(1), async { 1 }).await, Some(1));
+        assert_eq!(timeout(Duration::from_millis(20), sleep(Duration::from_secs(1))).await, None);
+    }
+
+    #[cfg(feature = "wechat-cp")]
+    #[tokio::test]
+    async fn test_signal() {
+        let signal = Arc::new(Signal::default());
+        let fire = signal.clone();
+        let start = Instant::now();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(30)).await;
+            fire.fire();
+        });
+        assert!(timeout(Duration::from_secs(5), signal.wait()).await.is_some());
+        assert!(signal.is_fired());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // 触发后再次等待立即完成
+        assert!(timeout(Duration::from_millis(10), signal.wait()).await.is_some());
+    }
+
+    #[cfg(feature = "async-std-runtime")]
+    #[async_std::test]
+    async fn test_timeout_async_std() {
+        assert_eq!(timeout(Duration::from_secs(1), async { 1 }).await, Some(1));
+        let start = Instant::now();
+        assert_eq!(timeout(Duration::from_millis(20), sleep(Duration::from_secs(1))).await, None);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[cfg(all(feature = "async-std-runtime", feature = "wechat-cp"))]
+    #[async_std::test]
+    async fn test_signal_async_std() {
+        let signal = Arc::new(Signal::default());
+        let fire = signal.clone();
+        async_std::task::spawn(async move {
+            sleep(Duration::from_millis(30)).await;
+            fire.fire();
+        });
+        assert!(timeout(Duration::from_secs(5), signal.wait()).await.is_some());
+        assert!(signal.is_fired());
+        assert!(timeout(Duration::from_millis(10), signal.wait()).await.is_some());
+    }
+
+    #[cfg(all(feature = "async-std-runtime", any(feature = "wechat-mp", feature = "wechat-ma", feature = "wechat-cp")))]
+    #[async_std::test]
+    async fn test_mutex_async_std() {
+        let lock = Arc::new(super::Mutex::new(0));
+        let tasks = (0..8).map(|_| {
+            let lock = lock.clone();
+            async_std::task::spawn(async move {
+                let mut guard = lock.lock().await;
+                let value = *guard;
+                sleep(Duration::from_millis(1)).await;
+                *guard = value + 1;
+            })
+        }).collect::<Vec<_>>();
+        for task in tasks {
+            task.await;
+        }
+        assert_eq!(*lock.lock().await, 8);
+    }
+}
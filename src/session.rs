@@ -1,16 +1,64 @@
-use std::{collections::BTreeMap, any::type_name, fmt, error, time::{SystemTime, UNIX_EPOCH}};
+use std::{collections::BTreeMap, any::type_name, fmt, error, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 
 use redis::{FromRedisValue, ToRedisArgs};
 use serde::{Deserialize, Serialize};
-use crate::{get_timestamp, LabradorResult};
+use crate::{get_timestamp, LabraError, LabradorResult};
 
+// 方法上未使用的生命周期参数保留，移除后已有的实现无法编译
+#[allow(clippy::extra_unused_lifetimes)]
 pub trait SessionStore: Clone {
     fn get<'a, K: AsRef<str>, T: FromStore>(&self, key: K, default: Option<T>) -> LabradorResult<Option<T>>;
     fn set<'a, K: AsRef<str>, T: ToStore>(&self, key: K, value: T, ttl: Option<usize>) -> LabradorResult<()>;
 }
 
+/// <pre>
+/// 对象安全的会话存储
+/// `SessionStore`的方法带有泛型参数且要求`Clone`，无法作为trait对象使用；本trait以`Store`读写，
+/// 所有`SessionStore + Send + Sync`都自动实现，用于在运行时选择存储（如开发环境用内存、生产环境用Redis）
+/// </pre>
+pub trait ObjectSessionStore {
+    fn get_store(&self, key: &str) -> LabradorResult<Option<Store>>;
+    fn set_store(&self, key: &str, value: Store, ttl: Option<usize>) -> LabradorResult<()>;
+}
+
+impl<S: SessionStore + Send + Sync> ObjectSessionStore for S {
+    fn get_store(&self, key: &str) -> LabradorResult<Option<Store>> {
+        self.get::<_, Store>(key, None)
+    }
+
+    fn set_store(&self, key: &str, value: Store, ttl: Option<usize>) -> LabradorResult<()> {
+        self.set(key, value, ttl)
+    }
+}
+
+/// <pre>
+/// 运行时选择的会话存储，可直接作为客户端的存储类型，如`WechatCpClient<DynSessionStore>`
+/// 只需要固定存储的场景仍可使用具体类型，没有额外开销
+/// </pre>
+pub type DynSessionStore = Arc<dyn ObjectSessionStore + Send + Sync>;
+
+impl fmt::Debug for dyn ObjectSessionStore + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("DynSessionStore")
+    }
+}
+
+impl SessionStore for DynSessionStore {
+    fn get<'a, K: AsRef<str>, T: FromStore>(&self, key: K, default: Option<T>) -> LabradorResult<Option<T>> {
+        // 需解引用到trait对象，否则会调用到`DynSessionStore`自身通过blanket impl获得的实现
+        match (**self).get_store(key.as_ref())? {
+            Some(v) => T::from_store_opt(&v).map(Some).map_err(LabraError::from),
+            None => Ok(default),
+        }
+    }
+
+    fn set<'a, K: AsRef<str>, T: ToStore>(&self, key: K, value: T, ttl: Option<usize>) -> LabradorResult<()> {
+        (**self).set_store(key.as_ref(), value.to_store(), ttl)
+    }
+}
+
 pub trait ToStore {
     fn to_store(&self) -> Store;
 }
@@ -95,7 +143,7 @@ impl Number {
     #[inline]
     pub fn is_i64(&self) -> bool {
         match self.n {
-            N::PosInt(v) => v <= i64::max_value() as u64,
+            N::PosInt(v) => v <= i64::MAX as u64,
             N::NegInt(_) => true,
             N::Float(_) => false,
         }
@@ -121,7 +169,7 @@ impl Number {
     pub fn as_i64(&self) -> Option<i64> {
         match self.n {
             N::PosInt(n) => {
-                if n <= i64::max_value() as u64 {
+                if n <= i64::MAX as u64 {
                     Some(n as i64)
                 } else {
                     None
@@ -216,7 +264,7 @@ where T: ToStore {
 impl <T> ToStore for &T 
 where T: ToStore {
     fn to_store(&self) -> Store {
-        T::to_store(&self)
+        T::to_store(self)
     }
 }
 impl ToStore for &str {
@@ -351,6 +399,12 @@ pub static SIMPLE_STORAGE: Lazy<DashMap<String, (Option<usize>, Store)>> = Lazy:
 pub struct SimpleStorage {
 }
 
+impl Default for SimpleStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SimpleStorage {
     pub fn new() -> SimpleStorage {
         SimpleStorage {  }
@@ -370,10 +424,10 @@ impl SessionStore for SimpleStorage {
                     is_expire = true;
                     None
                 } else {
-                    Some(T::from_store(&value))
+                    Some(T::from_store(value))
                 }
             } else {
-                Some(T::from_store(&value))
+                Some(T::from_store(value))
             }
         } else {
             default
@@ -387,8 +441,9 @@ impl SessionStore for SimpleStorage {
     fn set<'a, K: AsRef<str>, T: ToStore>(&self, key: K, value: T, ttl: Option<usize>) -> LabradorResult<()> {
         let key = key.as_ref();
         let ttl = if let Some(ttl) = ttl {
+            // ttl为秒，过期时间按毫秒记录
             let current_stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
-            let ttl = current_stamp as usize + ttl;
+            let ttl = current_stamp as usize + ttl * 1000;
             Some(ttl)
         } else {
             None
@@ -438,8 +493,7 @@ pub mod redis_store {
         }
 
         fn get_connect(&self) -> RedisPool {
-            let pool = self.client_pool.to_owned();
-            pool
+            self.client_pool.to_owned()
         }
 
        
@@ -449,7 +503,7 @@ pub mod redis_store {
             if !client.check_connection() {
                 return Err(LabraError::ApiError("error to get redis connection".to_string()))
             }
-            let s = client.del(key.as_ref())?;
+            let _: () = client.del(key.as_ref())?;
             Ok(())
         }
 
@@ -483,10 +537,7 @@ pub mod redis_store {
                 return Ok(default);
             }
             let v = if let Ok(value) = data {
-                match T::from_store_opt(&value) {
-                    Ok(store) =>Some(store),
-                    Err(_err) => None
-                }
+                T::from_store_opt(&value).ok()
             } else {
                 default
             };
@@ -500,9 +551,9 @@ pub mod redis_store {
                 return Err(LabraError::ApiError("error to get redis connection".to_string()))
             }
             if let Some(seconds) = ttl {
-                let _ = client.set_ex(key, value.to_store(), seconds)?;
+                let _: () = client.set_ex(key, value.to_store(), seconds)?;
             } else {
-                let _ = client.set(key, value.to_store())?;
+                let _: () = client.set(key, value.to_store())?;
             }
 
             Ok(())
@@ -524,4 +575,44 @@ fn test_simple() {
     // let v = session.get::<&str, String>("a", None).unwrap();
     //
     // println!("v:{}" , v.unwrap_or_default());
-}
\ No newline at end of file
+}
+#[test]
+fn test_simple_storage_ttl() {
+    let session = SimpleStorage::new();
+    // ttl为秒
+    session.set("test_simple_storage_ttl", "value", Some(1)).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(session.get::<_, String>("test_simple_storage_ttl", None).unwrap(), Some("value".to_string()));
+    session.set("test_simple_storage_ttl", "value", Some(0)).unwrap();
+    assert_eq!(session.get::<_, String>("test_simple_storage_ttl", None).unwrap(), None);
+}
+
+#[test]
+fn test_dyn_session_store() {
+    let session: DynSessionStore = Arc::new(SimpleStorage::new());
+    session.set("test_dyn_session_store", "value", None).unwrap();
+    assert_eq!(session.get::<_, String>("test_dyn_session_store", None).unwrap(), Some("value".to_string()));
+    // 与具体类型读写同一份数据
+    assert_eq!(SimpleStorage::new().get::<_, String>("test_dyn_session_store", None).unwrap(), Some("value".to_string()));
+    assert_eq!(session.get::<_, String>("test_dyn_session_store_missing", Some("default".to_string())).unwrap(), Some("default".to_string()));
+    assert_eq!(format!("{:?}", session), "DynSessionStore");
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::LabraError;
+    use super::{DynSessionStore, SessionStore, SimpleStorage};
+
+    #[test]
+    fn test_dyn_session_type_mismatch() {
+        let session: DynSessionStore = Arc::new(SimpleStorage::new());
+        session.set("dyn_session_mismatch", "not a number".to_string(), None).unwrap();
+        assert_eq!(session.get::<_, String>("dyn_session_mismatch", None).unwrap().as_deref(), Some("not a number"));
+        // 类型不匹配时返回错误，而不是当作未命中
+        assert!(matches!(session.get::<_, i64>("dyn_session_mismatch", Some(1)), Err(LabraError::StoreError(_))));
+        assert_eq!(session.get::<_, i64>("dyn_session_missing", Some(1)).unwrap(), Some(1));
+    }
+}
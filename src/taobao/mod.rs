@@ -98,7 +98,7 @@ impl <T: SessionStore> TaobaoClient<T> {
         match self.sign_method.as_str() {
             constants::SIGN_TYPE_MD5 => {
                 let content = format!("{}{}{}", self.api_client.secret.to_string(), sign_content, self.api_client.secret.to_string());
-                let sign = md5::md5(content).to_uppercase();
+                let sign = md5::md5(content)?.to_uppercase();
                 Ok(sign)
             }
             // constants::SIGN_TYPE_RSA => {
@@ -405,7 +405,6 @@ mod tests {
     use reqwest::Url;
     use serde::{Deserializer, Deserialize, Serialize};
     use serde_json::{json, Value};
-    use crate::ResponseType::Text;
     use crate::{SimpleStorage, TaobaoClient};
     use crate::taobao::request::{TbItemDetailRequest, TbJhsSearchRequest, TbMaterialSearchRequest, TbMaterialSelectRequest};
 
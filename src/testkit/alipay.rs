@@ -0,0 +1,55 @@
+//!
+//! 支付宝RSA2签名测试向量
+//!
+//! 来自开放平台文档“自行实现签名”中的示例：剔除sign，其余参数按参数名ASCII码排序后以`&`拼接，值不做URL编码，
+//! 使用[`PRIVATE_KEY`]做SHA256withRSA签名
+//!
+use openssl::pkey::PKey;
+
+use crate::alipay_sign_debug;
+use crate::prp::PrpCrypto;
+
+/// 测试私钥，与[`RSA_PRIVATE_KEY`](super::RSA_PRIVATE_KEY)相同，按支付宝的格式去掉了PEM头尾与换行
+pub const PRIVATE_KEY: &str = "MIIEowIBAAKCAQEAr9T7JMfsxOY7kHtMM3q1xRQ8TBUoB1FjSes+5ddCQOJgrYLmz6HWlX991IUh2zqH9H1T5/mh0hCe4trkd3Ckw7vAwKd2TwtSvdO5bsE0tD62I5snS4Hiyw39RJTR2HfH2garnHVMx4LiiVkDy50hltjcIM+i+cla/LdHPOMOt8D6YDQ944SvMztOlcaryyRwac+SslvUL16COYaC4FLNhUWpT8TAKdfi7l1Ps5z1B8M/xWR9im6dfjxE4olj1KVx11sOxPO2ErN8r9aAyXutFP/5MLbEJ3hRexoeWgESTpXUFXiPMd8aTe/ADIQ2iHYxCkkCNPKTLFfTyH/hH2KqEwIDAQABAoIBAANbklYv+S4DZYTLTfe3sqvqSIJ9bu/A1qANPXy/IRVDHWyC0OyLHyFot1rtFentUrZRBF6sLjJoPdiALJe5T3QrbDFhoUk+IpzcY7ChQOPmRK0mY9g1yUJuaHU8UEuNcgQQV4ub09UFg1TzHbbwL7mFN6LqxCZ3+WRVDae0gE3y+ceaGAtpbNCaeIZ9Kf2eRkoq6zkZd+TGDX5MKsDDbBdIKC1ef8GPFXBwuCrNSisMfmX58QtKOmGEYqtnY0gqP2I10igvSQHWLd8hXZdPmuCq/AzXUILUDogFkE0Qg8jdP6izBW4DcXWRhobkUiejLsAwpfn1vIvhyVHIUX3CB7kCgYEA8uwIpZTYHgtWd6o3qQxH9ng5D8m0Y/hQZK7l0YiB6bA8SBKUa5Dunx6OkiRuDCTOvLcQPpl4jAAB8GEfNg/oh3jP2g92Tlp3yML/K32N9XUK7MJAZmwhfuza8iS9n4ZPRexO/v9xq5r1Jf7L9Fd/EvmNRdTkskZtxY25ar2Jn1sCgYEAuUxO2cN7HugDs3Asa2tzdNd27hd1OLs1VdMcB19ZQV/GtBQJirCsGnyfq8I6eSFp1bO+gfzfOb17paeNbStrFW/Jauosq0zN0H/eCcek23fkiY1gtq8NUFczwP7JkJHbd10VFNfwJfpE8BXu3XTpINpXrLRjsBZtiv5nSeBCFakCgYBj8S4hiZpcygdaFkLuHupggdfgIflAhAH3WmmdCk+AqIw0jshXdPpnpre7cpeAEYio5ZweVR/eYYZqslqX8HWXfuHs8h2XYOsVQvyelPpZuTy0abr7KJOjV6lApFMLlcN5nh5mFQxcMNWZOzoIHhNe6CLyTwOEQSuacpONUcuULwKBgHEZ1J8ypDnvICMsetBuW6Cby2n0Q4aHLjzeiKMvXcl7bkuIV3Kh98qK+uLZwNsiu/jGp6er84zIzWoFZVuXPttsyiJKIU3FqXJ0OdD6XPPgsEwNUVTwcE3zjBFm8x0dCjpHRJDeZSSpJ97rZEskCxAUdaNiz94xMle+hMkg6vVRAoGBAKlYRk26JKSoM34nTJtq6ACtDsStMT3F9jEsEn3UyW79RgvgOK9phGBzX3u2erZOgzhy3EP33qnNKf79L879HPElA2H3KrVTeNsM9HGuHrvEj8FfOLym0sVtFTL69/XSUUkoft9PTqthlfCtDuPM8QWSfi/xA1RgEHybYLVeMv/v";
+/// 测试公钥，与[`RSA_PUBLIC_KEY`](super::RSA_PUBLIC_KEY)相同，按支付宝的格式去掉了PEM头尾与换行
+pub const PUBLIC_KEY: &str = "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAr9T7JMfsxOY7kHtMM3q1xRQ8TBUoB1FjSes+5ddCQOJgrYLmz6HWlX991IUh2zqH9H1T5/mh0hCe4trkd3Ckw7vAwKd2TwtSvdO5bsE0tD62I5snS4Hiyw39RJTR2HfH2garnHVMx4LiiVkDy50hltjcIM+i+cla/LdHPOMOt8D6YDQ944SvMztOlcaryyRwac+SslvUL16COYaC4FLNhUWpT8TAKdfi7l1Ps5z1B8M/xWR9im6dfjxE4olj1KVx11sOxPO2ErN8r9aAyXutFP/5MLbEJ3hRexoeWgESTpXUFXiPMd8aTe/ADIQ2iHYxCkkCNPKTLFfTyH/hH2KqEwIDAQAB";
+
+/// 示例请求参数，已按参数名排序
+pub const SIGN_PARAMS: [(&str, &str); 7] = [
+    ("app_id", "2014072300007148"),
+    ("biz_content", r#"{"button":[{"actionParam":"ZFB_HFCZ","actionType":"out","name":"话费充值"},{"name":"查询","subButton":[{"actionParam":"ZFB_YECX","actionType":"out","name":"余额查询"},{"actionParam":"ZFB_LLCX","actionType":"out","name":"流量查询"},{"actionParam":"ZFB_HFCX","actionType":"out","name":"话费查询"}]},{"actionParam":"http://m.alipay.com","actionType":"link","name":"最新优惠"}]}"#),
+    ("charset", "GBK"),
+    ("method", "alipay.mobile.public.menu.add"),
+    ("sign_type", "RSA2"),
+    ("timestamp", "2014-07-24 03:07:50"),
+    ("version", "1.0"),
+];
+/// 示例参数的签名串
+pub const SIGN_CONTENT: &str = r#"app_id=2014072300007148&biz_content={"button":[{"actionParam":"ZFB_HFCZ","actionType":"out","name":"话费充值"},{"name":"查询","subButton":[{"actionParam":"ZFB_YECX","actionType":"out","name":"余额查询"},{"actionParam":"ZFB_LLCX","actionType":"out","name":"流量查询"},{"actionParam":"ZFB_HFCX","actionType":"out","name":"话费查询"}]},{"actionParam":"http://m.alipay.com","actionType":"link","name":"最新优惠"}]}&charset=GBK&method=alipay.mobile.public.menu.add&sign_type=RSA2&timestamp=2014-07-24 03:07:50&version=1.0"#;
+/// 使用测试私钥对[`SIGN_CONTENT`]的签名
+pub const SIGNATURE: &str = "RV7vqbqcqPJqk/liZXLGkb9blx75NNDQ5lvu8wXljg4Oyjnz+xu0sJAqh36g5Rgvmu+Q8V/zufcZ6vSZ9db0z/d3XVYSiPfalhkFnYAsSbwLZEy1xFdkncWl4aAULqoKf55D1iZkH150PyyCsUO9Mga1OCYoLI0HsYI4HcUbhzO36vE0EXGEoN73u+cPm/7fsZKE3Kbcco67xwCa6lgOlDXgxZL7qQNzIA9pqgFv/1EjkJP70IGAE86PfkpHl5ZGJqdKmlZE8BY536B6USdUuCDU41oA5sFjAw+GU/RguxXZrvQyYphVZN/eaidA75JgcAcy2zgOw8lfLa3INefDVw==";
+
+/// <pre>
+/// 应用私钥与应用公钥应配对：按示例参数生成签名串并签名后，能用公钥验签
+/// 两者均为支付宝开放平台密钥工具生成的格式（base64，不含PEM头尾），私钥支持PKCS#1与PKCS#8
+/// 使用测试私钥时签名应与[`SIGNATURE`]一致
+/// </pre>
+pub fn assert_rsa2_key_pair(private_key: &str, public_key: &str) {
+    let report = alipay_sign_debug(SIGN_PARAMS.iter().copied(), private_key).expect("RSA2签名失败，请检查应用私钥格式");
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+    assert_eq!(report.sign_content, SIGN_CONTENT);
+    assert!(verify(public_key, &report.sign_content, &report.signature), "应用私钥与公钥不匹配");
+    assert!(!verify(public_key, &SIGN_CONTENT.replace("GBK", "utf-8"), &report.signature), "签名串不同时不应验签通过");
+    if private_key.trim() == PRIVATE_KEY {
+        assert_eq!(report.signature, SIGNATURE);
+    }
+}
+
+fn verify(public_key: &str, content: &str, signature: &str) -> bool {
+    let pem = base64::decode(public_key.trim()).ok()
+        .and_then(|der| PKey::public_key_from_der(&der).ok())
+        .and_then(|pkey| pkey.public_key_to_pem().ok())
+        .and_then(|pem| String::from_utf8(pem).ok())
+        .expect("读取公钥失败，请检查是否为base64格式");
+    PrpCrypto::rsa_sha256_verify(&pem, content, signature).unwrap_or(false)
+}
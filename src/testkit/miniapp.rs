@@ -0,0 +1,24 @@
+//!
+//! 小程序开放数据解密测试向量
+//!
+//! 来自小程序官方文档的encryptedData示例：AES-128-CBC，密钥为session_key，IV由接口返回，PKCS#7补位，
+//! 明文中的watermark.appid应与小程序的appid一致。
+//!
+use crate::LabradorResult;
+
+pub const APPID: &str = "wx4f4bc4dec97d474b";
+pub const SESSION_KEY: &str = "tiihtNczf5v6AKRyjwEUhQ==";
+pub const ENCRYPTED_DATA: &str = "CiyLU1Aw2KjvrjMdj8YKliAjtP4gsMZMQmRzooG2xrDcvSnxIMXFufNstNGTyaGS9uT5geRa0W4oTOb1WT7fJlAC+oNPdbB+3hVbJSRgv+4lGOETKUQz6OYStslQ142dNCuabNPGBzlooOmB231qMM85d2/fV6ChevvXvQP8Hkue1poOFtnEtpyxVLW1zAo6/1Xx1COxFvrc2d7UL/lmHInNlxuacJXwu0fjpXfz/YqYzBIBzD6WUfTIF9GRHpOn/Hz7saL8xz+W//FRAUid1OksQaQx4CMs8LOddcQhULW4ucetDf96JcR3g0gfRK4PC7E/r7Z6xNrXd2UIeorGj5Ef7b1pJAYB6Y5anaHqZ9J6nKEBvB4DnNLIVWSgARns/8wR2SiRS7MNACwTyrGvt9ts8p12PKFdlqYTopNHR1Vf7XjfhQlVsAJdNiKdYmYVoKlaRv85IfVunYzO0IKXsyl7JCUjCpoG20f0a04COwfneQAGGwd5oa+T8yO5hzuyDb/XcxxmK01EpqOyuxINew==";
+pub const IV: &str = "r7BXXKkLb8qrSNn05n0qiA==";
+/// 解密后的明文
+pub const DECRYPTED_DATA: &str = r#"{"openId":"oGZUI0egBJY1zhBYw2KhdUfwVJJE","nickName":"Band","gender":1,"language":"zh_CN","city":"Guangzhou","province":"Guangdong","country":"CN","avatarUrl":"http://wx.qlogo.cn/mmopen/vi_32/aSKcBBPpibyKNicHNTMM0qJVh8Kjgiak2AHWr8MHM4WgMEm7GFhsf8OYrySdbvAMvTsw3mo8ibKicsnfN5pRjl1p8HQ/0","unionId":"ocMvos6NjeKLIBqg5Mr9QjxrP1FA","watermark":{"timestamp":1477314187,"appid":"wx4f4bc4dec97d474b"}}"#;
+
+/// <pre>
+/// 解密函数（参数依次为session_key、encryptedData、iv）应能解密官方示例，并在IV或session_key错误时返回错误或不同的明文
+/// 如`assert_decrypt_data(WechatCrypto::decrypt_data)`
+/// </pre>
+pub fn assert_decrypt_data<F: Fn(&str, &str, &str) -> LabradorResult<String>>(decrypt: F) {
+    assert_eq!(decrypt(SESSION_KEY, ENCRYPTED_DATA, IV).expect("解密encryptedData示例失败"), DECRYPTED_DATA);
+    assert!(decrypt(SESSION_KEY, ENCRYPTED_DATA, "AAAAAAAAAAAAAAAAAAAAAA==").map_or(true, |v| v != DECRYPTED_DATA), "IV错误时不应得到原文");
+    assert!(decrypt("AAAAAAAAAAAAAAAAAAAAAA==", ENCRYPTED_DATA, IV).map_or(true, |v| v != DECRYPTED_DATA), "session_key错误时不应得到原文");
+}
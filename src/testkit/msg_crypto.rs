@@ -0,0 +1,135 @@
+//!
+//! 消息加解密测试向量
+//!
+//! 来自微信官方加解密示例，公众号、小程序与企业微信使用相同的方案：
+//! AES-256-CBC，密钥为EncodingAESKey补`=`后base64解码，IV取密钥前16字节，明文按32字节PKCS#7补位，
+//! 明文结构为`16字节随机串 + 4字节网络序长度 + 消息 + appid（企业微信为corpid）`；
+//! 安全模式的msg_signature为token、timestamp、nonce与密文字典序排序后拼接的SHA1。
+//!
+use crate::{LabradorResult, WechatCrypto};
+use crate::util::xmlutil;
+
+pub const TOKEN: &str = "123456";
+pub const ENCODING_AES_KEY: &str = "kWxPEV2UEDyxWpmPdKC3F4dgPDmOvfKX1HGnEUDS1aR";
+pub const APPID: &str = "wx49f0ab532d5d035a";
+pub const TIMESTAMP: i64 = 1411525903;
+pub const NONCE: &str = "461056294";
+
+/// 微信推送的加密消息
+pub const ENCRYPTED_XML: &str = "<xml><ToUserName><![CDATA[wx49f0ab532d5d035a]]></ToUserName>\n\
+    <Encrypt><![CDATA[RgqEoJj5A4EMYlLvWO1F86ioRjZfaex/gePD0gOXTxpsq5Yj4GNglrBb8I2BAJVODGajiFnXBu7mCPatfjsu6IHCrsTyeDXzF6Bv283dGymzxh6ydJRvZsryDyZbLTE7rhnus50qGPMfp2wASFlzEgMW9z1ef/RD8XzaFYgm7iTdaXpXaG4+BiYyolBug/gYNx410cvkKR2/nPwBiT+P4hIiOAQqGp/TywZBtDh1yCF2KOd0gpiMZ5jSw3e29mTvmUHzkVQiMS6td7vXUaWOMZnYZlF3So2SjHnwh4jYFxdgpkHHqIrH/54SNdshoQgWYEvccTKe7FS709/5t6NMxuGhcUGAPOQipvWTT4dShyqio7mlsl5noTrb++x6En749zCpQVhDpbV6GDnTbcX2e8K9QaNWHp91eBdCRxthuL0=]]></Encrypt>\n\
+    <AgentID><![CDATA[1]]></AgentID>\n\
+    </xml>";
+/// 推送消息查询参数中的msg_signature
+pub const MSG_SIGNATURE: &str = "74d92dfeb87ba7c714f89d98870ae5eb62dff26d";
+/// 推送消息解密后的明文
+pub const DECRYPTED_XML: &str = "<xml><ToUserName><![CDATA[wx49f0ab532d5d035a]]></ToUserName>\n\
+    <FromUserName><![CDATA[messense]]></FromUserName>\n\
+    <CreateTime>1411525903</CreateTime>\n\
+    <MsgType><![CDATA[text]]></MsgType>\n\
+    <Content><![CDATA[test]]></Content>\n\
+    <MsgId>4363689963896700987</MsgId>\n\
+    <AgentID>1</AgentID>\n\
+    </xml>";
+
+/// 被动回复的明文
+pub const REPLY_XML: &str = "<xml>\n\
+    <MsgType><![CDATA[text]]></MsgType>\n\
+    <Content><![CDATA[test]]></Content>\n\
+    <FromUserName><![CDATA[wx49f0ab532d5d035a]]></FromUserName>\n\
+    <ToUserName><![CDATA[messense]]></ToUserName>\n\
+    <AgentID>1</AgentID>\n\
+    <CreateTime>1411525903</CreateTime>\n\
+    </xml>";
+/// 被动回复加密后的报文，随机串为`1234567890123456`
+pub const ENCRYPTED_REPLY_XML: &str = "<xml>\n\
+    <Encrypt><![CDATA[9s4gMv99m88kKTh/H8IdkOiMg6bisoy3ypwy9H4hvSPe9nsGaqyw5hhSjdYbcrKk+j3nba4HMOTzHrluLBYqxgNcBqGsL8GqxlhZgURnAtObvesEl5nZ+uBE8bviY0LWke8Zy9V/QYKxNV2FqllNXcfmstttyIkMKCCmVbCFM2JTF5wY0nFhHZSjPUL2Q1qvSUCUld+/WIXrx0oyKQmpB6o8NRrrNrsDf03oxI1p9FxUgMnwKKZeOA/uu+2IEvEBtb7muXsVbwbgX05UPPJvFurDXafG0RQyPR+mf1nDnAtQmmNOuiR5MIkdQ39xn1vWwi1O5oazPoQJz0nTYjxxEE8kv3kFxtAGVRe3ypD3WeK2XeFYFMNMpatF9XiKzHo3]]></Encrypt>\n\
+    <MsgSignature><![CDATA[407518b7649e86ef23978113f92d27afa9296533]]></MsgSignature>\n\
+    <TimeStamp>1411525903</TimeStamp>\n\
+    <Nonce><![CDATA[461056294]]></Nonce>\n\
+    </xml>";
+/// 被动回复的msg_signature
+pub const REPLY_MSG_SIGNATURE: &str = "407518b7649e86ef23978113f92d27afa9296533";
+
+pub const ECHO_TIMESTAMP: i64 = 1411443780;
+pub const ECHO_NONCE: &str = "437374425";
+/// 企业微信验证URL时的加密echostr
+pub const ECHO_STR: &str = "4ByGGj+sVCYcvGeQYhaKIk1o0pQRNbRjxybjTGblXrBaXlTXeOo1+bXFXDQQb1o6co6Yh9Bv41n7hOchLF6p+Q==";
+/// 验证URL时的msg_signature，echostr参与签名
+pub const ECHO_MSG_SIGNATURE: &str = "dd6b9c95b495b3f7e2901bfbc76c664930ffdb96";
+/// echostr解密后的明文
+pub const ECHO_DECRYPTED: &str = "5927782489442352469";
+/// 明文模式的signature，只有token、timestamp、nonce参与签名
+pub const PLAIN_SIGNATURE: &str = "97f44b51ccbee5533bf61e753557d165ea0f4566";
+
+/// 消息加解密的实现，如本库的[`WechatMsgCrypto`]或下游自行封装的版本
+pub trait MsgCrypto {
+    /// 加密回复，返回包含Encrypt、MsgSignature、TimeStamp、Nonce的XML
+    fn encrypt_message(&self, msg: &str, timestamp: i64, nonce: &str) -> LabradorResult<String>;
+
+    /// 校验msg_signature并解密推送的XML
+    fn decrypt_message(&self, xml: &str, msg_signature: &str, timestamp: i64, nonce: &str) -> LabradorResult<String>;
+}
+
+/// 本库的消息加解密，token与appid（企业微信为corpid）参与签名与加密
+#[derive(Debug)]
+pub struct WechatMsgCrypto {
+    crypto: WechatCrypto,
+    token: String,
+    id: String,
+}
+
+impl WechatMsgCrypto {
+    pub fn new(encoding_aes_key: &str, token: &str, id: &str) -> Self {
+        WechatMsgCrypto {
+            crypto: WechatCrypto::new(encoding_aes_key),
+            token: token.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    /// 使用示例配置
+    pub fn sample() -> Self {
+        Self::new(ENCODING_AES_KEY, TOKEN, APPID)
+    }
+}
+
+impl MsgCrypto for WechatMsgCrypto {
+    fn encrypt_message(&self, msg: &str, timestamp: i64, nonce: &str) -> LabradorResult<String> {
+        self.crypto.encrypt_message(msg, timestamp, nonce, &self.token, &self.id)
+    }
+
+    fn decrypt_message(&self, xml: &str, msg_signature: &str, timestamp: i64, nonce: &str) -> LabradorResult<String> {
+        self.crypto.decrypt_message(xml, msg_signature, timestamp, nonce, &self.token, &self.id)
+    }
+}
+
+/// <pre>
+/// 使用示例配置（[`TOKEN`]、[`ENCODING_AES_KEY`]、[`APPID`]）的实现应能解密官方示例报文
+/// 并拒绝签名不匹配的报文
+/// </pre>
+pub fn assert_msg_crypto_sample(crypto: &impl MsgCrypto) {
+    assert_eq!(crypto.decrypt_message(ENCRYPTED_XML, MSG_SIGNATURE, TIMESTAMP, NONCE).expect("解密推送消息示例失败"), DECRYPTED_XML);
+    assert_eq!(crypto.decrypt_message(ENCRYPTED_REPLY_XML, REPLY_MSG_SIGNATURE, TIMESTAMP, NONCE).expect("解密回复示例失败"), REPLY_XML);
+    assert!(crypto.decrypt_message(ENCRYPTED_XML, REPLY_MSG_SIGNATURE, TIMESTAMP, NONCE).is_err(), "签名不匹配时应返回错误");
+    assert!(crypto.decrypt_message(ENCRYPTED_XML, MSG_SIGNATURE, TIMESTAMP, "461056295").is_err(), "nonce不匹配时应返回错误");
+    assert_msg_crypto_roundtrip(crypto);
+}
+
+/// <pre>
+/// 任意配置的实现，加密后应能解密还原，且输出的签名、时间戳与随机串与请求一致
+/// 用于确认下游加载的EncodingAESKey、token可用
+/// </pre>
+pub fn assert_msg_crypto_roundtrip(crypto: &impl MsgCrypto) {
+    for msg in [REPLY_XML, "<xml><Content><![CDATA[你好，😀]]></Content></xml>", ""].iter() {
+        let encrypted = crypto.encrypt_message(msg, TIMESTAMP, NONCE).expect("加密失败，请检查EncodingAESKey是否为43位");
+        let package = xmlutil::parse(&encrypted);
+        let doc = package.as_document();
+        let signature = xmlutil::evaluate(&doc, "//xml/MsgSignature/text()").string();
+        assert_eq!(xmlutil::evaluate(&doc, "//xml/TimeStamp/text()").string(), TIMESTAMP.to_string());
+        assert_eq!(xmlutil::evaluate(&doc, "//xml/Nonce/text()").string(), NONCE);
+        assert_eq!(signature.len(), 40, "msg_signature应为40位SHA1：{}", signature);
+        assert_eq!(&crypto.decrypt_message(&encrypted, &signature, TIMESTAMP, NONCE).expect("解密失败"), msg);
+        assert!(crypto.decrypt_message(&encrypted, &signature, TIMESTAMP + 1, NONCE).is_err(), "timestamp不匹配时应返回错误");
+    }
+}
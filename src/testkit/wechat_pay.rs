@@ -0,0 +1,66 @@
+//!
+//! 微信支付签名测试向量
+//!
+//! *   V2：文档“安全规范”中的签名示例，参数按ASCII码排序拼接并追加`&key=`后做MD5或HMAC-SHA256，结果转大写
+//! *   V3：文档“签名生成”中的签名串示例`HTTP请求方法\nURL\n请求时间戳\n请求随机串\n请求报文主体\n`，
+//!     使用[`RSA_PRIVATE_KEY`](super::RSA_PRIVATE_KEY)做SHA256withRSA签名
+//!
+use std::collections::BTreeMap;
+
+use openssl::x509::X509;
+
+use crate::{LabradorResult, WechatCryptoV3};
+use super::RSA_PRIVATE_KEY;
+
+/// V2的API密钥
+pub const V2_KEY: &str = "192006250b4c09247ec02edce69f6a2d";
+/// V2参数按ASCII码排序拼接后的签名串
+pub const V2_SIGN_CONTENT: &str = "appid=wxd930ea5d5a258f4f&body=test&device_info=1000&mch_id=10000100&nonce_str=ibuaiVcKdpRxkhJA&key=192006250b4c09247ec02edce69f6a2d";
+pub const V2_MD5_SIGN: &str = "9A0A8659F005D6984697E2CA0A9CF3B7";
+pub const V2_HMAC_SHA256_SIGN: &str = "6A9AE1657590FD6257D693A078E1C3E4BB6BA4DC30B23E0EE2496E54170DACD6";
+
+pub const V3_METHOD: &str = "GET";
+pub const V3_URL: &str = "/v3/certificates";
+pub const V3_TIMESTAMP: i64 = 1554208460;
+pub const V3_NONCE: &str = "593BEC0C930BF1AFEB40B4A08C8FB242";
+/// GET请求的报文主体为空
+pub const V3_BODY: &str = "";
+pub const V3_SIGN_CONTENT: &str = "GET\n/v3/certificates\n1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n\n";
+/// 使用测试私钥对[`V3_SIGN_CONTENT`]的签名
+pub const V3_SIGNATURE: &str = "OrM+jsgxPKcr+ROPwQVNIlMTOp1MWLzvc9d6gCTmtDF/bWl+NUqpSRHftLHIeCPkAhj43loRaIUAf4JBeuZXdQWSnSuU2y0DVC/dRDj+cRUY6nZSP4xbisJtttY4Onhx9eM09i+CNZWR/y4w965z8K6gQpzwoy7Ck69yLRuDj7HLacf4U57bWo21yOo4QIPOiKtY/Qt3nFhmeT/jR2R1/SdMSsOUYvo0IJbjiOWhsMpTbSB86mWEShJtYxmx3euAbe3hPWfOBDM0P++vE/bWL5Ka3kN6ZcQLLi6120CmQZSGJbI9SmGP5WuWFnB77RdP9VWc3v9oAJOl/z8alzfKSg==";
+
+/// V2签名示例的参数
+pub fn v2_params() -> BTreeMap<String, String> {
+    vec![("appid", "wxd930ea5d5a258f4f"), ("mch_id", "10000100"), ("device_info", "1000"), ("body", "test"), ("nonce_str", "ibuaiVcKdpRxkhJA")]
+        .into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// <pre>
+/// 商户私钥与证书（或公钥）应配对：使用私钥对V3签名串示例签名后，能用证书中的公钥验签
+/// private_key为PEM格式的商户私钥（apiclient_key.pem），certificate为商户证书（apiclient_cert.pem）或PEM格式的公钥
+/// 使用测试私钥时签名应与[`V3_SIGNATURE`]一致
+/// </pre>
+pub fn assert_v3_key_pair(private_key: &str, certificate: &str) {
+    let signature = sign_v3(private_key).expect("V3签名失败，请检查商户私钥是否为PEM格式");
+    let public_key = public_key_pem(certificate).expect("读取证书公钥失败");
+    assert!(WechatCryptoV3::verify(V3_SIGN_CONTENT, &signature, &public_key).unwrap_or(false), "商户私钥与证书不匹配");
+    assert!(!WechatCryptoV3::verify(&V3_SIGN_CONTENT.replace("GET", "POST"), &signature, &public_key).unwrap_or(false), "签名串不同时不应验签通过");
+    if private_key.trim() == RSA_PRIVATE_KEY.trim() {
+        assert_eq!(signature, V3_SIGNATURE);
+    }
+}
+
+fn sign_v3(private_key: &str) -> LabradorResult<String> {
+    let sign_content = WechatCryptoV3::signature_message_v3(V3_METHOD, V3_URL, V3_TIMESTAMP, V3_NONCE, V3_BODY);
+    assert_eq!(sign_content, V3_SIGN_CONTENT);
+    WechatCryptoV3::sign(&sign_content, &private_key.to_string())
+}
+
+/// 证书转换为PEM格式的公钥，公钥原样返回
+fn public_key_pem(certificate: &str) -> LabradorResult<String> {
+    if !certificate.contains("CERTIFICATE") {
+        return Ok(certificate.to_string());
+    }
+    let public_key = X509::from_pem(certificate.as_bytes())?.public_key()?.public_key_to_pem()?;
+    Ok(String::from_utf8(public_key)?)
+}
@@ -0,0 +1,89 @@
+//!
+//! 日期区间
+//!
+//! 数据统计类接口（如公众号datacube）对单次查询的时间跨度有限制，
+//! 这里提供按天数拆分区间的工具，由调用方逐段请求后合并结果。
+//!
+use chrono::{Duration, NaiveDate};
+
+use crate::{LabraError, LabradorResult};
+
+/// 闭区间 [begin, end]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub begin: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    pub fn new(begin: NaiveDate, end: NaiveDate) -> LabradorResult<Self> {
+        if begin > end {
+            return Err(LabraError::RequestError(format!("开始日期{}不能晚于结束日期{}", begin, end)));
+        }
+        Ok(Self {
+            begin,
+            end,
+        })
+    }
+
+    /// 区间包含的天数
+    pub fn num_days(&self) -> i64 {
+        (self.end - self.begin).num_days() + 1
+    }
+
+    /// 按最多`n`天一段拆分区间
+    pub fn days(&self, n: i64) -> Vec<DateRange> {
+        let n = n.max(1);
+        let mut ranges = Vec::new();
+        let mut begin = self.begin;
+        while begin <= self.end {
+            let end = (begin + Duration::days(n - 1)).min(self.end);
+            ranges.push(DateRange {
+                begin,
+                end,
+            });
+            begin = end + Duration::days(1);
+        }
+        ranges
+    }
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::NaiveDate;
+    use super::DateRange;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_split_days() {
+        let range = DateRange::new(date("2022-12-28"), date("2023-01-12")).unwrap();
+        assert_eq!(range.num_days(), 16);
+        let ranges = range.days(7);
+        assert_eq!(ranges, vec![
+            DateRange { begin: date("2022-12-28"), end: date("2023-01-03") },
+            DateRange { begin: date("2023-01-04"), end: date("2023-01-10") },
+            DateRange { begin: date("2023-01-11"), end: date("2023-01-12") },
+        ]);
+        assert_eq!(ranges.iter().map(|r| r.num_days()).sum::<i64>(), range.num_days());
+    }
+
+    #[test]
+    fn test_split_single_day() {
+        let range = DateRange::new(date("2023-02-27"), date("2023-03-01")).unwrap();
+        let ranges = range.days(1);
+        assert_eq!(ranges.len(), 3);
+        assert!(ranges.iter().all(|r| r.begin == r.end));
+        assert_eq!(ranges[1].begin, date("2023-02-28"));
+        assert_eq!(DateRange::new(date("2023-03-01"), date("2023-03-01")).unwrap().days(7).len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_range() {
+        assert!(DateRange::new(date("2023-03-02"), date("2023-03-01")).is_err());
+    }
+}
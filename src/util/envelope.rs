@@ -0,0 +1,23 @@
+//!
+//! 通用返回解析
+//!
+//! 各平台的返回都在业务数据外包了一层公共结构：微信为errcode/errmsg，支付宝为`xxx_response`、`sign`与`error_response`。
+//! 实现[`ResponseEnvelope`]后，各接口只需声明业务数据的类型，错误码的识别、签名的校验统一在这里完成。
+//!
+use std::borrow::Cow;
+
+use serde::de::DeserializeOwned;
+
+use crate::{LabraError, LabradorResult};
+
+/// 平台的公共返回结构
+pub trait ResponseEnvelope {
+    /// 校验原始返回（错误码、签名等），成功时返回业务数据所在的原始JSON
+    fn open<'a>(&self, raw: &'a str) -> LabradorResult<Cow<'a, str>>;
+
+    /// 校验原始返回并将业务数据反序列化为`T`
+    fn extract<T: DeserializeOwned>(&self, raw: &str) -> LabradorResult<T> {
+        let content = self.open(raw)?;
+        serde_json::from_str::<T>(&content).map_err(LabraError::from)
+    }
+}
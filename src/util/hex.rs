@@ -0,0 +1,101 @@
+//!
+//! 十六进制编解码
+//!
+use std::fmt;
+
+const LOWER_CHARS: &[u8; 16] = b"0123456789abcdef";
+const UPPER_CHARS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// 十六进制解码出错
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FromHexError {
+    /// 包含非十六进制字符
+    InvalidHexCharacter(char, usize),
+    /// 长度不是偶数
+    InvalidHexLength,
+}
+
+impl fmt::Display for FromHexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromHexError::InvalidHexCharacter(ch, idx) => write!(f, "Invalid character '{}' at position {}", ch, idx),
+            FromHexError::InvalidHexLength => write!(f, "Invalid input length"),
+        }
+    }
+}
+
+impl std::error::Error for FromHexError {}
+
+/// 编码为小写十六进制字符串
+pub fn encode<T: AsRef<[u8]>>(data: T) -> String {
+    encode_with(data.as_ref(), LOWER_CHARS)
+}
+
+/// 编码为大写十六进制字符串
+pub fn encode_upper<T: AsRef<[u8]>>(data: T) -> String {
+    encode_with(data.as_ref(), UPPER_CHARS)
+}
+
+fn encode_with(data: &[u8], chars: &[u8; 16]) -> String {
+    let mut s = String::with_capacity(data.len() * 2);
+    for b in data {
+        s.push(chars[(b >> 4) as usize] as char);
+        s.push(chars[(b & 0xf) as usize] as char);
+    }
+    s
+}
+
+/// 解码十六进制字符串，大小写均可，忽略空白字符
+pub fn decode<T: AsRef<[u8]>>(data: T) -> Result<Vec<u8>, FromHexError> {
+    let mut out = Vec::with_capacity(data.as_ref().len() / 2);
+    let mut buf = 0u8;
+    let mut modulus = 0;
+    for (idx, byte) in data.as_ref().iter().enumerate() {
+        buf <<= 4;
+        match byte {
+            b'A'..=b'F' => buf |= byte - b'A' + 10,
+            b'a'..=b'f' => buf |= byte - b'a' + 10,
+            b'0'..=b'9' => buf |= byte - b'0',
+            b' ' | b'\r' | b'\n' | b'\t' => {
+                buf >>= 4;
+                continue
+            }
+            _ => {
+                let ch = String::from_utf8_lossy(&data.as_ref()[idx..]).chars().next().unwrap_or_default();
+                return Err(FromHexError::InvalidHexCharacter(ch, idx))
+            }
+        }
+        modulus += 1;
+        if modulus == 2 {
+            modulus = 0;
+            out.push(buf);
+        }
+    }
+    match modulus {
+        0 => Ok(out),
+        _ => Err(FromHexError::InvalidHexLength),
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use super::{decode, encode, encode_upper, FromHexError};
+
+    #[test]
+    fn test_hex_encode_decode() {
+        assert_eq!(encode(b"foobar"), "666f6f626172");
+        assert_eq!(encode_upper([0x00, 0x0f, 0xab, 0xff]), "000FABFF");
+        assert_eq!(encode([]), "");
+        assert_eq!(decode("666f6f626172").unwrap(), b"foobar");
+        assert_eq!(decode("666F6F626172").unwrap(), b"foobar");
+        assert_eq!(decode("66 6f\n6f").unwrap(), b"foo");
+        assert_eq!(decode("").unwrap(), Vec::<u8>::new());
+        assert_eq!(decode("666"), Err(FromHexError::InvalidHexLength));
+        assert_eq!(decode("66g6"), Err(FromHexError::InvalidHexCharacter('g', 2)));
+        assert_eq!(decode("66中"), Err(FromHexError::InvalidHexCharacter('中', 2)));
+        let data = (0..=255u8).collect::<Vec<u8>>();
+        assert_eq!(decode(encode(&data)).unwrap(), data);
+        assert_eq!(decode(encode_upper(&data)).unwrap(), data);
+    }
+}
@@ -0,0 +1,356 @@
+//!
+//! gzip / zlib / deflate 解压
+//!
+//! 用于响应的`Content-Encoding`以及GZIP格式的账单文件，仅解压不压缩，实现参照RFC 1950/1951/1952。
+//! 解压后的大小超过`limit`时立即返回错误，不会为压缩炸弹分配内存。
+//!
+use crate::{LabraError, LabradorResult};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// 最大码长
+const MAX_BITS: usize = 15;
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// 动态Huffman编码中码长表的顺序
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn error(msg: &str) -> LabraError {
+    LabraError::DecodeError(msg.into())
+}
+
+fn check_limit(len: usize, limit: usize) -> LabradorResult<()> {
+    if len > limit {
+        return Err(LabraError::DecodeError(format!("decompressed size exceeds limit of {} bytes", limit).into()));
+    }
+    Ok(())
+}
+
+/// 是否为gzip数据
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// 解压gzip数据，支持多个member拼接，解压后最多`limit`字节
+pub fn gunzip(data: &[u8], limit: usize) -> LabradorResult<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let member = &data[pos..];
+        if member.len() < 18 || !is_gzip(member) || member[2] != 8 {
+            return Err(error("invalid gzip header"));
+        }
+        let flags = member[3];
+        let mut offset = 10;
+        if flags & 0x04 != 0 {
+            let xlen = *member.get(offset).ok_or_else(|| error("invalid gzip header"))? as usize
+                | (*member.get(offset + 1).ok_or_else(|| error("invalid gzip header"))? as usize) << 8;
+            offset += 2 + xlen;
+        }
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                let end = member.get(offset..).and_then(|v| v.iter().position(|b| *b == 0)).ok_or_else(|| error("invalid gzip header"))?;
+                offset += end + 1;
+            }
+        }
+        if flags & 0x02 != 0 {
+            offset += 2;
+        }
+        let body = member.get(offset..).ok_or_else(|| error("invalid gzip header"))?;
+        let start = out.len();
+        let consumed = inflate_into(body, &mut out, limit)?;
+        let trailer = body.get(consumed..consumed + 8).ok_or_else(|| error("truncated gzip trailer"))?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc32(&out[start..]) != crc || (out.len() - start) as u32 != size {
+            return Err(error("gzip checksum mismatch"));
+        }
+        pos += offset + consumed + 8;
+    }
+    Ok(out)
+}
+
+/// 解压HTTP的deflate编码：按规范为zlib格式，兼容部分服务端直接返回的原始deflate数据，解压后最多`limit`字节
+pub fn zlib_decompress(data: &[u8], limit: usize) -> LabradorResult<Vec<u8>> {
+    let is_zlib = data.len() >= 2 && data[0] & 0x0f == 8 && (data[0] as u16 * 256 + data[1] as u16).is_multiple_of(31);
+    let mut out = Vec::new();
+    if is_zlib {
+        if data[1] & 0x20 != 0 {
+            return Err(error("zlib preset dictionary is not supported"));
+        }
+        inflate_into(&data[2..], &mut out, limit)?;
+    } else {
+        inflate_into(data, &mut out, limit)?;
+    }
+    Ok(out)
+}
+
+/// 解压原始deflate数据，返回读取的字节数
+fn inflate_into(data: &[u8], out: &mut Vec<u8>, limit: usize) -> LabradorResult<usize> {
+    let mut reader = BitReader::new(data);
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored(&mut reader, out, limit)?,
+            1 => {
+                let (lit, dist) = fixed_tables();
+                codes(&mut reader, out, &lit, &dist, limit)?
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut reader)?;
+                codes(&mut reader, out, &lit, &dist, limit)?
+            }
+            _ => return Err(error("invalid deflate block type")),
+        }
+        if last {
+            return Ok(reader.consumed());
+        }
+    }
+}
+
+fn stored(reader: &mut BitReader, out: &mut Vec<u8>, limit: usize) -> LabradorResult<()> {
+    reader.align();
+    let header = reader.take(4)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err(error("invalid stored block length"));
+    }
+    check_limit(out.len() + len as usize, limit)?;
+    out.extend_from_slice(reader.take(len as usize)?);
+    Ok(())
+}
+
+fn codes(reader: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman, limit: usize) -> LabradorResult<()> {
+    loop {
+        let symbol = lit.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                check_limit(out.len() + 1, limit)?;
+                out.push(symbol as u8)
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let len = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let index = dist.decode(reader)? as usize;
+                if index >= DIST_BASE.len() {
+                    return Err(error("invalid deflate distance code"));
+                }
+                let distance = DIST_BASE[index] as usize + reader.bits(DIST_EXTRA[index] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(error("invalid deflate distance"));
+                }
+                check_limit(out.len() + len, limit)?;
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+            _ => return Err(error("invalid deflate literal/length code")),
+        }
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5u8; 30]))
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> LabradorResult<(Huffman, Huffman)> {
+    let nlen = reader.bits(5)? as usize + 257;
+    let ndist = reader.bits(5)? as usize + 1;
+    let ncode = reader.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(error("invalid deflate code lengths"));
+    }
+    let mut lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(ncode) {
+        lengths[*index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&lengths);
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut index = 0;
+    while index < nlen + ndist {
+        let symbol = code_lengths.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths.get(index.wrapping_sub(1)).ok_or_else(|| error("invalid deflate repeat"))?;
+                (prev, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if index + repeat > nlen + ndist {
+            return Err(error("invalid deflate code lengths"));
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(error("missing deflate end-of-block code"));
+    }
+    Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+/// 规范Huffman编码：各码长的数量及按码排序的符号
+struct Huffman {
+    count: [u16; MAX_BITS + 1],
+    symbol: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut count = [0u16; MAX_BITS + 1];
+        for len in lengths {
+            count[*len as usize] += 1;
+        }
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + count[len];
+        }
+        let mut symbol = vec![0u16; lengths.len()];
+        for (sym, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                symbol[offsets[*len as usize] as usize] = sym as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Self {
+            count,
+            symbol,
+        }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> LabradorResult<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.count[len] as i32;
+            if code - count < first {
+                return Ok(self.symbol[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(error("invalid deflate huffman code"))
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> LabradorResult<u32> {
+        while self.bit_count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| error("unexpected end of deflate data"))?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u64 << n) - 1) as u32;
+        self.bit_buf >>= n;
+        self.bit_count -= n;
+        Ok(value)
+    }
+
+    /// 丢弃当前字节剩余的位
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    fn take(&mut self, n: usize) -> LabradorResult<&'a [u8]> {
+        let data = self.data.get(self.pos..self.pos + n).ok_or_else(|| error("unexpected end of deflate data"))?;
+        self.pos += n;
+        Ok(data)
+    }
+
+    /// 已读取的完整字节数
+    fn consumed(&self) -> usize {
+        self.pos - (self.bit_count / 8) as usize
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use super::{gunzip, is_gzip, zlib_decompress};
+
+    /// python: gzip.compress(b"hello hello hello hello\n", mtime=0)
+    const HELLO_GZIP: &str = "H4sIAAAAAAACA8tIzcnJV8hAJ7kAAIhZCxgAAAA=";
+
+    const LIMIT: usize = 1024;
+
+    #[test]
+    fn test_gunzip() {
+        let data = base64::decode(HELLO_GZIP).unwrap();
+        assert!(is_gzip(&data));
+        assert_eq!(gunzip(&data, LIMIT).unwrap(), b"hello hello hello hello\n");
+        // 多个member拼接
+        let mut twice = data.clone();
+        twice.extend_from_slice(&data);
+        assert_eq!(gunzip(&twice, LIMIT).unwrap(), b"hello hello hello hello\nhello hello hello hello\n");
+        // 校验失败
+        let mut broken = data.clone();
+        let n = broken.len();
+        broken[n - 5] ^= 0xff;
+        assert!(gunzip(&broken, LIMIT).is_err());
+        assert!(gunzip(b"not gzip", LIMIT).is_err());
+    }
+
+    #[test]
+    fn test_zlib_and_raw_deflate() {
+        // python: zlib.compress(b"hello hello hello hello\n")
+        let zlib = base64::decode("eJzLSM3JyVfIQCe5AHC+CLs=").unwrap();
+        assert_eq!(zlib_decompress(&zlib, LIMIT).unwrap(), b"hello hello hello hello\n");
+        // 去掉zlib头尾的原始deflate数据
+        assert_eq!(zlib_decompress(&zlib[2..zlib.len() - 4], LIMIT).unwrap(), b"hello hello hello hello\n");
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let zlib = base64::decode("eJzLSM3JyVfIQCe5AHC+CLs=").unwrap();
+        // 恰好等于上限可以解压，超过一个字节即失败
+        assert_eq!(zlib_decompress(&zlib, 24).unwrap().len(), 24);
+        assert!(zlib_decompress(&zlib, 23).is_err());
+        // 多个member拼接的总大小同样受限，超限时不会继续解压后续member
+        let data = base64::decode(HELLO_GZIP).unwrap();
+        let bomb = data.repeat(1000);
+        let err = gunzip(&bomb, LIMIT).unwrap_err();
+        assert!(err.to_string().contains("exceeds limit"));
+        assert_eq!(gunzip(&bomb, 24 * 1000).unwrap().len(), 24 * 1000);
+    }
+}
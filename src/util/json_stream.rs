@@ -0,0 +1,554 @@
+//!
+//! 大列表返回的逐条解析
+//!
+//! 部分接口（批量获取客户详情、导出结果等）的返回可达数十MB，先解析为`serde_json::Value`再转换会使内存翻倍。
+//! 这里按JSON Pointer（如`/external_contact_list`）定位到数组，跳过其余字段时不分配内存，
+//! 数组中的元素在迭代时才逐条反序列化。
+//!
+//! 解析是增量的：[`JsonArrayStream`]直接读取响应的字节流，数据不足时等待下一块，
+//! 已解析的部分随即丢弃，同一时刻只持有一个元素和未解析完的数据，不会缓存整个响应。
+//!
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::stream::Stream;
+use serde::de::{DeserializeOwned, Error, IgnoredAny};
+use serde_json::Deserializer;
+
+use crate::{LabradorResult, LabraError};
+
+/// <pre>
+/// 逐条解析`pointer`指向的数组，响应已完整读取时使用
+/// 字段不存在或为null时视为空数组；元素解析失败时返回该错误并结束
+/// </pre>
+pub struct JsonArrayIter<T> {
+    body: Bytes,
+    parser: ArrayParser,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> JsonArrayIter<T> {
+    pub fn new(body: Bytes, pointer: &str) -> LabradorResult<Self> {
+        let mut parser = ArrayParser::new(pointer)?;
+        // 完整的数据不会等待，定位失败时立即返回错误
+        parser.enter(&body, true)?;
+        Ok(JsonArrayIter { body, parser, _marker: PhantomData })
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for JsonArrayIter<T> {
+    type Item = LabradorResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parser.step::<T>(&self.body, true) {
+            Ok(Progress::Item(item)) => Some(Ok(item)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+/// <pre>
+/// 逐条解析字节流中`pointer`指向的数组，如[`LabraStreamResponse::json_stream`](crate::LabraStreamResponse::json_stream)
+/// 只缓存尚未解析完的数据，内存占用取决于单个元素（及数组之前被跳过的单个字段）的大小，与响应总大小无关
+/// 字段不存在或为null时视为空数组；读取或解析失败时返回该错误并结束
+/// </pre>
+pub struct JsonArrayStream<S, T> {
+    source: S,
+    buffer: Vec<u8>,
+    eof: bool,
+    parser: ArrayParser,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<S, E, T> JsonArrayStream<S, T>
+    where S: Stream<Item = Result<Bytes, E>> + Unpin, E: Into<LabraError>, T: DeserializeOwned {
+    pub fn new(source: S, pointer: &str) -> LabradorResult<Self> {
+        Ok(JsonArrayStream { source, buffer: Vec::new(), eof: false, parser: ArrayParser::new(pointer)?, _marker: PhantomData })
+    }
+
+    /// 当前缓存的未解析字节数
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+impl<S, E, T> Stream for JsonArrayStream<S, T>
+    where S: Stream<Item = Result<Bytes, E>> + Unpin, E: Into<LabraError>, T: DeserializeOwned {
+    type Item = LabradorResult<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let progress = this.parser.step::<T>(&this.buffer, this.eof);
+            // 已解析的部分不再需要
+            let consumed = this.parser.rebase();
+            this.buffer.drain(..consumed);
+            match progress {
+                Ok(Progress::Item(item)) => return Poll::Ready(Some(Ok(item))),
+                Ok(Progress::Done) => return Poll::Ready(None),
+                Err(err) => return Poll::Ready(Some(Err(err.into()))),
+                Ok(Progress::Pending) => {}
+            }
+            match Pin::new(&mut this.source).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.buffer.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(err))) => {
+                    this.parser.phase = Phase::Done;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Ready(None) => this.eof = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// 只解析`pointer`指向的值，字段不存在时返回`None`，其余字段跳过而不分配内存
+pub fn json_pointer<T: DeserializeOwned>(body: &[u8], pointer: &str) -> LabradorResult<Option<T>> {
+    let mut parser = ArrayParser::new(pointer)?;
+    if !parser.navigate(body, true)? {
+        return Ok(None);
+    }
+    let mut offset = parser.offset;
+    Ok(parse_value(body, &mut offset, true)?)
+}
+
+enum Progress<T> {
+    Item(T),
+    /// 数据不足，需要继续读取
+    Pending,
+    Done,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    /// 进入当前层的值
+    Enter,
+    /// 在对象中查找字段
+    Key,
+    /// 在数组中查找下标，值为还需跳过的元素个数
+    Index(usize),
+    /// 跳过不匹配的值，`usize`为数组中还需跳过的元素个数
+    Skip(bool, usize),
+    /// 跳过值之后的`,`或结束符
+    AfterSkip(bool, usize),
+    /// 已定位到目标值
+    Target,
+    /// 数组元素之前，`true`表示第一个元素
+    Items(bool),
+    /// 数组元素
+    Item,
+    Done,
+}
+
+/// <pre>
+/// 可恢复的定位与解析状态
+/// 每一步只在完整读取一个值（或分隔符）后前进，数据不足时保持不变，追加数据后从同一位置重试
+/// </pre>
+struct ArrayParser {
+    tokens: Vec<String>,
+    level: usize,
+    phase: Phase,
+    /// 当前缓存中的位置
+    offset: usize,
+    /// 已丢弃的字节数，用于错误信息中的位置
+    discarded: usize,
+}
+
+impl ArrayParser {
+    fn new(pointer: &str) -> Result<Self, serde_json::Error> {
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(custom(format!("JSON Pointer{}必须以`/`开头", pointer)));
+        }
+        let tokens = pointer.split('/').skip(1).map(|token| token.replace("~1", "/").replace("~0", "~")).collect();
+        Ok(ArrayParser { tokens, level: 0, phase: Phase::Enter, offset: 0, discarded: 0 })
+    }
+
+    /// 返回可以丢弃的字节数，之后的位置从0开始
+    fn rebase(&mut self) -> usize {
+        let consumed = self.offset;
+        self.discarded += consumed;
+        self.offset = 0;
+        consumed
+    }
+
+    fn position(&self, offset: usize) -> usize {
+        self.discarded + offset
+    }
+
+    /// 跳过空白后的下一个字节，数据不足时返回`None`
+    fn peek(&self, buf: &[u8], offset: &mut usize, eof: bool) -> Result<Option<u8>, serde_json::Error> {
+        skip_whitespace(buf, offset);
+        match buf.get(*offset) {
+            Some(c) => Ok(Some(*c)),
+            None if eof => Err(custom(format!("位置{}处数据不完整", self.position(*offset)))),
+            None => Ok(None),
+        }
+    }
+
+    /// 定位到`pointer`指向的值，返回是否存在；数据不足时返回`Ok(false)`且`phase`不是`Done`
+    fn navigate(&mut self, buf: &[u8], eof: bool) -> Result<bool, serde_json::Error> {
+        loop {
+            let mut offset = self.offset;
+            let c = match self.phase {
+                Phase::Target => return Ok(true),
+                Phase::Done => return Ok(false),
+                _ => match self.peek(buf, &mut offset, eof)? {
+                    Some(c) => c,
+                    None => return Ok(false),
+                },
+            };
+            match self.phase {
+                Phase::Enter if self.level == self.tokens.len() => self.phase = Phase::Target,
+                Phase::Enter => match (c, self.tokens[self.level].parse::<usize>()) {
+                    (b'{', _) => {
+                        offset += 1;
+                        self.phase = Phase::Key;
+                    }
+                    (b'[', Ok(index)) => {
+                        offset += 1;
+                        self.phase = Phase::Index(index);
+                    }
+                    _ => self.phase = Phase::Done,
+                },
+                Phase::Key if c == b'}' => self.phase = Phase::Done,
+                Phase::Key => {
+                    let key = match parse_value::<String>(buf, &mut offset, eof)? {
+                        Some(key) => key,
+                        None => return Ok(false),
+                    };
+                    match self.peek(buf, &mut offset, eof)? {
+                        Some(b':') => offset += 1,
+                        Some(_) => return Err(custom(format!("位置{}处应为`:`", self.position(offset)))),
+                        None => return Ok(false),
+                    }
+                    if key == self.tokens[self.level] {
+                        self.level += 1;
+                        self.phase = Phase::Enter;
+                    } else {
+                        self.phase = Phase::Skip(true, 0);
+                    }
+                }
+                Phase::Index(_) if c == b']' => self.phase = Phase::Done,
+                Phase::Index(0) => {
+                    self.level += 1;
+                    self.phase = Phase::Enter;
+                }
+                Phase::Index(remaining) => self.phase = Phase::Skip(false, remaining - 1),
+                Phase::Skip(object, remaining) => {
+                    if parse_value::<IgnoredAny>(buf, &mut offset, eof)?.is_none() {
+                        return Ok(false);
+                    }
+                    self.phase = Phase::AfterSkip(object, remaining);
+                }
+                Phase::AfterSkip(object, _) if c == if object { b'}' } else { b']' } => self.phase = Phase::Done,
+                Phase::AfterSkip(object, remaining) if c == b',' => {
+                    offset += 1;
+                    self.phase = if object { Phase::Key } else { Phase::Index(remaining) };
+                }
+                Phase::AfterSkip(..) => return Err(custom(format!("位置{}处应为`,`", self.position(offset)))),
+                _ => unreachable!(),
+            }
+            self.offset = offset;
+        }
+    }
+
+    /// 定位到数组的起始位置，目标不存在或为null时结束
+    fn enter(&mut self, buf: &[u8], eof: bool) -> Result<bool, serde_json::Error> {
+        if !self.navigate(buf, eof)? {
+            return Ok(self.phase == Phase::Done);
+        }
+        let mut offset = self.offset;
+        match self.peek(buf, &mut offset, eof)? {
+            Some(b'[') => {
+                self.offset = offset + 1;
+                self.phase = Phase::Items(true);
+                Ok(true)
+            }
+            Some(b'n') if buf.len() >= offset + 4 || eof => {
+                if !buf[offset..].starts_with(b"null") {
+                    return Err(custom(format!("位置{}处不是数组", self.position(offset))));
+                }
+                self.phase = Phase::Done;
+                Ok(true)
+            }
+            Some(b'n') | None => Ok(false),
+            Some(_) => {
+                self.phase = Phase::Done;
+                Err(custom(format!("{}不是数组，位置{}", self.pointer(), self.position(offset))))
+            }
+        }
+    }
+
+    fn pointer(&self) -> String {
+        self.tokens.iter().map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1"))).collect()
+    }
+
+    /// 解析下一个数组元素
+    fn step<T: DeserializeOwned>(&mut self, buf: &[u8], eof: bool) -> Result<Progress<T>, serde_json::Error> {
+        let result = self.next_item(buf, eof);
+        // 出错后结束
+        if result.is_err() {
+            self.phase = Phase::Done;
+        }
+        result
+    }
+
+    fn next_item<T: DeserializeOwned>(&mut self, buf: &[u8], eof: bool) -> Result<Progress<T>, serde_json::Error> {
+        if !matches!(self.phase, Phase::Items(_) | Phase::Item | Phase::Done) && !self.enter(buf, eof)? {
+            return Ok(Progress::Pending);
+        }
+        loop {
+            let mut offset = self.offset;
+            match self.phase {
+                Phase::Done => return Ok(Progress::Done),
+                Phase::Items(first) => {
+                    match self.peek(buf, &mut offset, eof)? {
+                        Some(b']') => {
+                            offset += 1;
+                            self.phase = Phase::Done;
+                        }
+                        Some(b',') if !first => {
+                            offset += 1;
+                            self.phase = Phase::Item;
+                        }
+                        Some(_) if !first => return Err(custom(format!("数组元素之间缺少`,`，位置{}", self.position(offset)))),
+                        Some(_) => self.phase = Phase::Item,
+                        None => return Ok(Progress::Pending),
+                    }
+                    self.offset = offset;
+                }
+                Phase::Item => {
+                    return match parse_value::<T>(buf, &mut offset, eof)? {
+                        Some(item) => {
+                            self.offset = offset;
+                            self.phase = Phase::Items(false);
+                            Ok(Progress::Item(item))
+                        }
+                        None => Ok(Progress::Pending),
+                    };
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// <pre>
+/// 从`offset`处解析一个值，并移动到该值之后
+/// 未读取完时（`eof`为false）数据不足返回`None`；数字恰好在数据末尾时同样返回`None`，后面可能还有数字
+/// </pre>
+fn parse_value<T: DeserializeOwned>(body: &[u8], offset: &mut usize, eof: bool) -> Result<Option<T>, serde_json::Error> {
+    let rest = &body[*offset..];
+    let mut values = Deserializer::from_slice(rest).into_iter::<T>();
+    match values.next() {
+        Some(Ok(_)) if !eof && values.byte_offset() == rest.len() && rest.last().is_some_and(u8::is_ascii_digit) => Ok(None),
+        Some(Ok(value)) => {
+            *offset += values.byte_offset();
+            Ok(Some(value))
+        }
+        // 数据在数字（如`-`、`1.`）或字符串中间截断时，错误位置在数据末尾
+        Some(Err(err)) if !eof && (err.is_eof() || error_offset(rest, &err) >= rest.len()) => Ok(None),
+        Some(Err(err)) => Err(err),
+        None if !eof => Ok(None),
+        None => Err(custom(format!("位置{}处缺少值", offset))),
+    }
+}
+
+/// 错误在数据中的字节位置
+fn error_offset(body: &[u8], err: &serde_json::Error) -> usize {
+    let line_start = match err.line() {
+        0 | 1 => 0,
+        line => body.iter().enumerate().filter(|(_, c)| **c == b'\n').nth(line - 2).map_or(body.len(), |(i, _)| i + 1),
+    };
+    line_start + err.column()
+}
+
+fn skip_whitespace(body: &[u8], offset: &mut usize) {
+    while let Some(b' ' | b'\n' | b'\r' | b'\t') = body.get(*offset) {
+        *offset += 1;
+    }
+}
+
+fn custom(msg: String) -> serde_json::Error {
+    serde_json::Error::custom(msg)
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Poll;
+
+    use bytes::Bytes;
+    use futures_util::stream::{self, Stream, StreamExt};
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    use crate::{LabradorResult, LabraError};
+    use super::{json_pointer, JsonArrayIter, JsonArrayStream};
+
+    fn stream<T: serde::de::DeserializeOwned>(body: &str, pointer: &str) -> LabradorResult<Vec<T>> {
+        JsonArrayIter::<T>::new(Bytes::from(body.to_string()), pointer)?.collect()
+    }
+
+    #[test]
+    fn test_same_as_value() {
+        let body = json!({
+            "errcode": 0,
+            "errmsg": "ok",
+            "skipped": {"list": [1, 2], "nested": [{"a": "]}\"["}], "escaped\"key": null},
+            "list": [{"name": "张三", "tags": ["a", "b"]}, {"name": "\u{1F600}\n", "tags": []}, {"name": "", "tags": [null]}],
+            "next_cursor": "CURSOR"
+        });
+        for text in [body.to_string(), serde_json::to_string_pretty(&body).unwrap()].iter() {
+            let items = stream::<Value>(text, "/list").unwrap();
+            assert_eq!(items, serde_json::from_str::<Value>(text).unwrap()["list"].as_array().unwrap().to_owned());
+            assert_eq!(json_pointer::<String>(text.as_bytes(), "/next_cursor").unwrap().as_deref(), Some("CURSOR"));
+            assert_eq!(json_pointer::<String>(text.as_bytes(), "/list/1/tags/0").unwrap(), None);
+            assert_eq!(json_pointer::<String>(text.as_bytes(), "/skipped/nested/0/a").unwrap().as_deref(), Some("]}\"["));
+            assert_eq!(json_pointer::<Value>(text.as_bytes(), "/skipped/escaped\"key").unwrap(), Some(Value::Null));
+            assert_eq!(stream::<String>(text, "/list/0/tags").unwrap(), vec!["a", "b"]);
+        }
+
+        // 字段不存在或为null时视为空数组
+        assert!(stream::<Value>(r#"{"errcode":0}"#, "/list").unwrap().is_empty());
+        assert!(stream::<Value>(r#"{"list":null}"#, "/list").unwrap().is_empty());
+        assert!(stream::<Value>(r#"{"list":[ ]}"#, "/list").unwrap().is_empty());
+        assert!(stream::<Value>(r#"[1,[2]]"#, "/1").unwrap() == vec![json!(2)]);
+        assert!(matches!(stream::<Value>(r#"{"list":{}}"#, "/list"), Err(LabraError::JsonError(_))));
+        assert!(matches!(stream::<Value>(r#"{"list":[1,]}"#, "/list"), Err(LabraError::JsonError(_))));
+        assert!(matches!(stream::<Value>(r#"{"list":[1 2]}"#, "/list"), Err(LabraError::JsonError(_))));
+        assert!(matches!(stream::<i32>(r#"{"list":[1,"2"]}"#, "/list"), Err(LabraError::JsonError(_))));
+        // 出错后结束
+        let mut iter = JsonArrayIter::<i32>::new(Bytes::from_static(b"{\"list\":[1,\"2\",3]}"), "/list").unwrap();
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    thread_local! {
+        static LIVE: Cell<usize> = const { Cell::new(0) };
+        static MAX_LIVE: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// 统计同时存活的元素个数
+    #[derive(Deserialize)]
+    struct Tracked {
+        id: usize,
+        #[serde(skip)]
+        tracked: bool,
+    }
+
+    impl Tracked {
+        fn track(mut self) -> Self {
+            LIVE.with(|live| {
+                live.set(live.get() + 1);
+                MAX_LIVE.with(|max| max.set(max.get().max(live.get())));
+            });
+            self.tracked = true;
+            self
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            if self.tracked {
+                LIVE.with(|live| live.set(live.get() - 1));
+            }
+        }
+    }
+
+    /// 按`chunk_size`切分的字节流
+    fn chunked(body: &str, chunk_size: usize) -> impl Stream<Item = Result<Bytes, LabraError>> + Unpin {
+        let chunks = body.as_bytes().chunks(chunk_size).map(|chunk| Ok(Bytes::copy_from_slice(chunk))).collect::<Vec<_>>();
+        stream::iter(chunks)
+    }
+
+    #[tokio::test]
+    async fn test_chunked_same_as_buffered() {
+        let body = json!({
+            "errcode": 0,
+            "skipped": {"list": [1, 2], "nested": [{"a": "]}\"["}]},
+            "list": [12345, -1.5e10, "张三", {"tags": ["a", null]}, true, null, [], {}],
+            "next_cursor": "CURSOR"
+        });
+        for text in [body.to_string(), serde_json::to_string_pretty(&body).unwrap()].iter() {
+            let expected = stream::<Value>(text, "/list").unwrap();
+            // 任意位置切分（含数字、多字节字符中间）结果都一致
+            for chunk_size in [1, 2, 3, 7, 64, text.len()] {
+                let items = JsonArrayStream::<_, Value>::new(chunked(text, chunk_size), "/list").unwrap().collect::<Vec<_>>().await;
+                assert_eq!(items.into_iter().collect::<LabradorResult<Vec<_>>>().unwrap(), expected, "chunk_size: {}", chunk_size);
+                let tags = JsonArrayStream::<_, Option<String>>::new(chunked(text, chunk_size), "/list/3/tags").unwrap().collect::<Vec<_>>().await;
+                assert_eq!(tags.into_iter().collect::<LabradorResult<Vec<_>>>().unwrap(), vec![Some("a".to_string()), None]);
+            }
+        }
+        for chunk_size in [1, 5] {
+            let empty = JsonArrayStream::<_, Value>::new(chunked(r#"{"errcode":0,"list":null}"#, chunk_size), "/list").unwrap().collect::<Vec<_>>().await;
+            assert!(empty.is_empty());
+            // 数据不完整
+            let truncated = JsonArrayStream::<_, i32>::new(chunked(r#"{"list":[1,2"#, chunk_size), "/list").unwrap().collect::<Vec<_>>().await;
+            assert!(matches!(truncated.as_slice(), [Ok(1), Ok(2), Err(LabraError::JsonError(_))]));
+            let not_array = JsonArrayStream::<_, Value>::new(chunked(r#"{"list":{"a":1}}"#, chunk_size), "/list").unwrap().collect::<Vec<_>>().await;
+            assert!(matches!(not_array.as_slice(), [Err(LabraError::JsonError(_))]));
+        }
+        // 读取出错时返回该错误并结束
+        let source = stream::iter(vec![Ok(Bytes::from_static(b"{\"list\":[1,")), Err(LabraError::Unknown), Ok(Bytes::from_static(b"2]}"))]);
+        let items = JsonArrayStream::<_, i32>::new(source, "/list").unwrap().collect::<Vec<_>>().await;
+        assert!(matches!(items.as_slice(), [Ok(1), Err(LabraError::Unknown)]));
+    }
+
+    /// <pre>
+    /// 逐块生成约50MB的返回，每条约1KB，生成的数据不会整体保存
+    /// 块大小与元素大小不对齐，元素会跨块
+    /// </pre>
+    fn generated_body(count: usize, chunk_size: usize, generated: Arc<AtomicUsize>) -> impl Stream<Item = Result<Bytes, LabraError>> + Unpin {
+        let padding = "x".repeat(1000);
+        let mut pending = br#"{"errcode":0,"errmsg":"ok","list":["#.to_vec();
+        let (mut next, mut finished) = (0, false);
+        stream::poll_fn(move |_| {
+            while pending.len() < chunk_size && !finished {
+                if next < count {
+                    if next > 0 {
+                        pending.push(b',');
+                    }
+                    pending.extend_from_slice(format!(r#"{{"id":{},"padding":"{}"}}"#, next, padding).as_bytes());
+                    next += 1;
+                } else {
+                    pending.extend_from_slice(br#"],"next_cursor":"END"}"#);
+                    finished = true;
+                }
+            }
+            if pending.is_empty() {
+                return Poll::Ready(None);
+            }
+            let chunk = pending.drain(..chunk_size.min(pending.len())).collect::<Vec<_>>();
+            generated.fetch_add(chunk.len(), Ordering::SeqCst);
+            Poll::Ready(Some(Ok(Bytes::from(chunk))))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_large_payload_bounded_memory() {
+        let (count, chunk_size) = (50_000, 7919);
+        let generated = Arc::new(AtomicUsize::new(0));
+        let mut items = JsonArrayStream::<_, Tracked>::new(generated_body(count, chunk_size, generated.clone()), "/list").unwrap();
+        let (mut total, mut peak_buffered) = (0, 0);
+        while let Some(item) = items.next().await {
+            let item = item.unwrap().track();
+            assert_eq!(item.id, total);
+            total += 1;
+            peak_buffered = peak_buffered.max(items.buffered_len());
+        }
+        assert_eq!(total, count);
+        assert!(generated.load(Ordering::SeqCst) > 50_000_000);
+        // 只缓存未解析完的数据：不超过一个元素加一块
+        assert!(peak_buffered < chunk_size + 1100, "peak buffered: {}", peak_buffered);
+        // 逐条消费时同一时刻只有一条元素存活
+        assert_eq!(MAX_LIVE.with(|max| max.get()), 1);
+    }
+}
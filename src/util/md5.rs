@@ -3,71 +3,61 @@
 //! MD5加密类
 //!
 use openssl::hash::{Hasher, MessageDigest};
-use rustc_serialize::hex::{ToHex};
+use crate::LabradorResult;
+use crate::util::hex;
 
 #[allow(unused)]
-static SALT: &'static str = "labrador";
+static SALT: &str = "labrador";
+
+/// 依次写入各段数据计算MD5，openssl出错时返回错误，避免空字符串被当作签名使用
+fn digest(parts: &[&[u8]]) -> LabradorResult<String> {
+    let mut h = Hasher::new(MessageDigest::md5())?;
+    for part in parts {
+        h.update(part)?;
+    }
+    Ok(hex::encode(h.finish()?))
+}
 
 ///
 /// MD5 encry
 /// 
 #[allow(unused)]
-pub fn md5_with_salt_default<S:Into<String>>(input: S) -> String {
+pub fn md5_with_salt_default<S:Into<String>>(input: S) -> LabradorResult<String> {
     let input: String = input.into();
-    let mut result = String::default();
-    if let Ok(mut h) = Hasher::new(MessageDigest::md5()) {
-        h.update(SALT.as_bytes()).unwrap();
-        h.update(input.as_bytes()).unwrap();
-        let res = h.finish().unwrap();
-        result = res.to_hex();
-    }
-    result
+    digest(&[SALT.as_bytes(), input.as_bytes()])
 }
 
 ///
 /// MD5 encry
 ///
 #[allow(unused)]
-pub fn md5<S:Into<String>>(input: S) -> String {
+pub fn md5<S:Into<String>>(input: S) -> LabradorResult<String> {
     let input: String = input.into();
-    let mut result = String::default();
-
-    if let Ok(mut h) = Hasher::new(MessageDigest::md5()) {
-        h.update(input.as_bytes()).unwrap();
-        let res = h.finish().unwrap();
-        result = res.to_hex();
-    }
-    result
+    digest(&[input.as_bytes()])
 }
 ///
 /// MD5 encry
 ///
 #[allow(unused)]
-pub fn md5_salt<S:Into<String>>(input: S, salt: S) -> String {
+pub fn md5_salt<S:Into<String>>(input: S, salt: S) -> LabradorResult<String> {
     let input: String = input.into();
     let salt: String = salt.into();
-    let mut result = String::default();
-    if let Ok(mut h) = Hasher::new(MessageDigest::md5()) {
-        h.update(input.as_bytes()).unwrap();
-        h.update(salt.as_bytes()).unwrap();
-        let res = h.finish().unwrap();
-        result = res.to_hex();
-    }
-    result
+    digest(&[input.as_bytes(), salt.as_bytes()])
 }
 
 ///
 /// 校验加密串是否匹配
 /// 
 #[allow(unused)]
-pub fn validate<S: Into<String>>(input_source: S, input_target: S) -> bool {
+pub fn validate<S: Into<String>>(input_source: S, input_target: S) -> LabradorResult<bool> {
     let source = input_source.into();
-    let target = md5_with_salt_default(input_target);
-    source.eq(&target)
+    let target = md5_with_salt_default(input_target)?;
+    Ok(source.eq(&target))
 }
 
 #[test]
 fn test_md5() {
-    let s = md5("sdfsdfasdfasf");
-    println!("md5:{}", s);
+    assert_eq!(md5("sdfsdfasdfasf").unwrap().len(), 32);
+    assert_eq!(md5("").unwrap(), "d41d8cd98f00b204e9800998ecf8427e");
+    assert!(validate(md5_with_salt_default("abc").unwrap(), "abc".to_string()).unwrap());
 }
\ No newline at end of file
@@ -0,0 +1,128 @@
+//!
+//! 测试用的HTTP模拟服务
+//!
+//! 按顺序为每个连接返回一个预设应答，并记录收到的请求报文
+//!
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// 读取请求后延迟应答，用于模拟慢接口
+    pub delay: Option<Duration>,
+}
+
+impl MockResponse {
+    pub fn json(body: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.as_bytes().to_vec(),
+            delay: None,
+        }
+    }
+
+    pub fn bytes(content_type: &str, body: &[u8]) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.to_vec(),
+            delay: None,
+        }
+    }
+
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay.into();
+        self
+    }
+}
+
+pub(crate) struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub async fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut socket).await;
+                received.lock().unwrap().push(request);
+                if let Some(delay) = response.delay {
+                    tokio::time::sleep(delay).await;
+                }
+                let mut reply = format!("HTTP/1.1 {} MOCK\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+                for (k, v) in response.headers {
+                    reply.push_str(&format!("{}: {}\r\n", k, v));
+                }
+                reply.push_str("\r\n");
+                let mut reply = reply.into_bytes();
+                reply.extend_from_slice(&response.body);
+                // 客户端超时后可能已关闭连接
+                if socket.write_all(&reply).await.is_err() {
+                    continue;
+                }
+                socket.shutdown().await.ok();
+            }
+        });
+        Self {
+            url,
+            requests,
+        }
+    }
+
+    /// 收到的请求报文
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// 一个未监听的本地地址，连接会被拒绝
+pub(crate) async fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    url
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await.unwrap_or_default();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request).to_lowercase();
+        if let Some(pos) = text.find("\r\n\r\n") {
+            let length = text.lines()
+                .find_map(|line| line.strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap_or_default()))
+                .unwrap_or_default();
+            if request.len() >= pos + 4 + length {
+                break;
+            }
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&request).to_string()
+}
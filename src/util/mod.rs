@@ -1,9 +1,33 @@
 use std::{collections::BTreeMap, time::{SystemTime, UNIX_EPOCH}};
-use uuid::Uuid;
 use crate::prp::PrpCrypto;
+use crate::LabradorResult;
 
 pub mod md5;
+pub mod hex;
 pub mod prp;
+mod page;
+mod json_stream;
+mod date_range;
+mod random;
+mod sign_debug;
+mod envelope;
+pub mod text;
+pub mod plate;
+pub mod serde_helper;
+pub(crate) mod inflate;
+#[cfg(feature = "region")]
+pub mod region;
+#[cfg(feature = "upload-stream")]
+pub mod upload;
+#[cfg(test)]
+pub(crate) mod mock;
+
+pub use page::*;
+pub use json_stream::*;
+pub use date_range::*;
+pub use random::*;
+pub use sign_debug::*;
+pub use envelope::*;
 
 
 /// 请求参数
@@ -14,6 +38,12 @@ pub struct RequestParametersHolder {
     pub application_params: BTreeMap<String, String>,
 }
 
+impl Default for RequestParametersHolder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RequestParametersHolder {
     pub fn new() -> Self {
         Self {
@@ -37,17 +67,17 @@ impl RequestParametersHolder {
 
     pub fn get_sorted_map(&self) -> BTreeMap<&String, &String> {
         let mut sorted_params = BTreeMap::new();
-        if self.application_params.len() > 0 {
+        if !self.application_params.is_empty() {
             for (k, v) in self.application_params.iter() {
                 sorted_params.insert(k, v);
             }
         }
-        if self.protocal_must_params.len() > 0 {
+        if !self.protocal_must_params.is_empty() {
             for (k, v) in self.protocal_must_params.iter() {
                 sorted_params.insert(k, v);
             }
         }
-        if self.protocal_opt_params.len() > 0 {
+        if !self.protocal_opt_params.is_empty() {
             for (k, v) in self.protocal_opt_params.iter() {
                 sorted_params.insert(k, v);
             }
@@ -75,7 +105,7 @@ impl <T: Sized + std::fmt::Display> StringExtern<T> for String {
 
     fn add_form_params(&mut self, field_name: &str, field_value: T) {
         if !self.is_empty() {
-            self.push_str("&");
+            self.push('&');
         }
         self.push_str(format!("{}={}", field_name, field_value).as_str());
     }
@@ -85,7 +115,7 @@ impl <T: Sized + std::fmt::Display> StringExtern<T> for String {
 ///
 /// See: ThirdPlatform(Wechat/Alibaba/PDD) > Security
 #[allow(unused)]
-pub fn get_sign(pairs: &BTreeMap<String, String>, secret: &str) -> String {
+pub fn get_sign(pairs: &BTreeMap<String, String>, secret: &str) -> LabradorResult<String> {
     // filter null params
     let keys = pairs
         .iter()
@@ -100,12 +130,12 @@ pub fn get_sign(pairs: &BTreeMap<String, String>, secret: &str) -> String {
     }
     params = format!("{}{}{}",secret,params,secret);
     // generate MD5 string
-    md5::md5(params).to_uppercase()
+    Ok(md5::md5(params)?.to_uppercase())
 }
 
 /// Sign Method (build request paramaters sign format)
 #[allow(unused)]
-pub fn get_sign_with_rsa(pairs: &BTreeMap<String, String>, private_key: &str) -> String {
+pub fn get_sign_with_rsa(pairs: &BTreeMap<String, String>, private_key: &str) -> LabradorResult<String> {
     // filter null params
     let keys = pairs
         .iter()
@@ -118,8 +148,7 @@ pub fn get_sign_with_rsa(pairs: &BTreeMap<String, String>, private_key: &str) ->
         params.add_form_params(key.as_str(), &pairs[&key].as_str());
     }
     // generate RSA string
-    let sign = PrpCrypto::rsa_sha256_sign_pkcs8(&params, base64::decode(private_key).unwrap()).unwrap();
-    sign
+    PrpCrypto::rsa_sha256_sign_pkcs8(&params, base64::decode(private_key)?)
 }
 
 /// Sign Method (build request paramaters sign format)
@@ -140,15 +169,14 @@ pub fn get_sign_params(pairs: &BTreeMap<String, String>, private_key: &str) -> S
     params
 }
 
-/// Get TimeStamp
+/// 毫秒级时间戳，微信接口中的时间戳均为秒，请使用[`current_timestamp`]
 #[allow(unused)]
 pub fn get_timestamp() -> i64 {
     let start = SystemTime::now();
     let since_the_epoch = start
         .duration_since(UNIX_EPOCH)
         .expect("Time went backwards");
-    let ms = since_the_epoch.as_secs() as i64 * 1000i64 + (since_the_epoch.subsec_nanos() as f64 / 1_000_000.0) as i64;
-    ms
+    since_the_epoch.as_secs() as i64 * 1000i64 + (since_the_epoch.subsec_nanos() as f64 / 1_000_000.0) as i64
 }
 
 pub fn merge_properties(mut source: serde_json::Value, target: serde_json::Value) -> serde_json::Value {
@@ -174,9 +202,10 @@ pub fn remove_properties(mut source: serde_json::Value, props: Vec<&str>) -> ser
 /// 微信支付API接口协议中包含字段nonce_str，主要保证签名不可预测。
 #[allow(unused)]
 pub fn get_nonce_str() -> String {
-    Uuid::new_v4().to_simple().to_string()
+    nonce_str()
 }
 
+/// 秒级时间戳
 #[allow(unused)]
 pub fn current_timestamp() -> i64 {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
@@ -265,7 +294,7 @@ macro_rules! cfg_if {
 }
 
 
-cfg_if! {if #[cfg(feature = "wechat")]{
+cfg_if! {if #[cfg(feature = "wechat-core")]{
     pub mod xmlutil;
 }}
 
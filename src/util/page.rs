@@ -0,0 +1,260 @@
+//!
+//! 分页拉取工具
+//!
+//! 微信很多列表接口（用户列表的next_openid、客户联系的cursor、素材列表的offset）都是
+//! “带上游标继续请求，直到游标为空”的模式，这里统一封装为惰性的 [`Stream`]。
+//!
+use std::future::Future;
+
+use futures_util::stream::{self, Stream, StreamExt};
+
+use crate::LabradorResult;
+
+/// 单页结果
+///
+/// `next` 为下一页的游标，为`None`时表示没有更多数据。
+#[derive(Debug, Clone)]
+pub struct Page<T, C = String> {
+    pub items: Vec<T>,
+    pub next: Option<C>,
+}
+
+impl<T, C> Page<T, C> {
+    pub fn new(items: Vec<T>, next: Option<C>) -> Self {
+        Self {
+            items,
+            next,
+        }
+    }
+
+    /// 最后一页
+    pub fn last(items: Vec<T>) -> Self {
+        Self {
+            items,
+            next: None,
+        }
+    }
+}
+
+impl<T> Page<T, String> {
+    /// 字符串游标，微信接口在没有下一页时通常返回空字符串
+    pub fn with_cursor(items: Vec<T>, next: Option<String>) -> Self {
+        Self {
+            items,
+            next: next.filter(|cursor| !cursor.is_empty()),
+        }
+    }
+}
+
+/// <pre>
+/// 逐条解析的单页结果
+/// `items` 为该页元素的流（如[`LabraResponse::json_stream`](crate::LabraResponse::json_stream)），消费时才会解析，适用于单页很大的返回
+/// </pre>
+pub struct LazyPage<S, C = String> {
+    pub items: S,
+    pub next: Option<C>,
+}
+
+impl<S, C> LazyPage<S, C> {
+    pub fn new(items: S, next: Option<C>) -> Self {
+        Self {
+            items,
+            next,
+        }
+    }
+}
+
+impl<S> LazyPage<S, String> {
+    /// 字符串游标，空字符串表示没有下一页
+    pub fn with_cursor(items: S, next: Option<String>) -> Self {
+        Self {
+            items,
+            next: next.filter(|cursor| !cursor.is_empty()),
+        }
+    }
+}
+
+///
+/// 分页流
+///
+/// # 示例
+///
+/// ```no_run
+/// # use labrador::{PagedStream, Page, LabradorResult};
+/// # use futures_util::StreamExt;
+/// # async fn fetch(cursor: Option<String>) -> LabradorResult<Page<String>> { Ok(Page::last(vec![])) }
+/// # async fn example() {
+/// let stream = PagedStream::new(|cursor: Option<String>| fetch(cursor)).max_items(100).into_stream();
+/// futures_util::pin_mut!(stream);
+/// while let Some(item) = stream.next().await {
+///     let _openid = item.unwrap();
+/// }
+/// # }
+/// ```
+///
+pub struct PagedStream<F, C> {
+    fetch: F,
+    cursor: Option<C>,
+    max_items: Option<usize>,
+}
+
+struct PagedState<F, S, C> {
+    fetch: F,
+    /// 当前页尚未消费的元素
+    items: Option<S>,
+    /// 当前页是否已有元素
+    yielded: bool,
+    cursor: Option<C>,
+    remaining: Option<usize>,
+    finished: bool,
+}
+
+impl<F, C> PagedStream<F, C> {
+    pub fn new(fetch: F) -> Self {
+        Self {
+            fetch,
+            cursor: None,
+            max_items: None,
+        }
+    }
+
+    /// 从指定游标开始拉取
+    pub fn start_from(mut self, cursor: C) -> Self {
+        self.cursor = cursor.into();
+        self
+    }
+
+    /// 最多返回的条数
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items.into();
+        self
+    }
+
+    /// 转换为`Stream`，只有在消费时才会发起请求；出现错误时返回该错误并结束
+    pub fn into_stream<T, Fut>(self) -> impl Stream<Item = LabradorResult<T>>
+        where F: FnMut(Option<C>) -> Fut,
+              Fut: Future<Output = LabradorResult<Page<T, C>>> {
+        let mut fetch = self.fetch;
+        PagedStream {
+            fetch: move |cursor| {
+                let page = fetch(cursor);
+                async move {
+                    page.await.map(|page| LazyPage::new(stream::iter(page.items.into_iter().map(Ok)), page.next))
+                }
+            },
+            cursor: self.cursor,
+            max_items: self.max_items,
+        }.into_lazy_stream()
+    }
+
+    /// 转换为`Stream`，每页的元素逐条消费，不会整页缓存；页内元素出错时同样返回该错误并结束
+    pub fn into_lazy_stream<T, S, Fut>(self) -> impl Stream<Item = LabradorResult<T>>
+        where F: FnMut(Option<C>) -> Fut,
+              Fut: Future<Output = LabradorResult<LazyPage<S, C>>>,
+              S: Stream<Item = LabradorResult<T>> + Unpin {
+        let state: PagedState<F, S, C> = PagedState {
+            fetch: self.fetch,
+            items: None,
+            yielded: false,
+            cursor: self.cursor,
+            remaining: self.max_items,
+            finished: false,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if state.remaining == Some(0) {
+                    return None;
+                }
+                if let Some(items) = state.items.as_mut() {
+                    match items.next().await {
+                        Some(Ok(item)) => {
+                            state.yielded = true;
+                            state.remaining = state.remaining.map(|n| n - 1);
+                            return Some((Ok(item), state));
+                        }
+                        Some(Err(err)) => {
+                            state.items = None;
+                            state.finished = true;
+                            return Some((Err(err), state));
+                        }
+                        None => {
+                            state.items = None;
+                            // 空页同样视为结束，避免接口一直返回同一游标导致死循环
+                            state.finished |= !state.yielded;
+                        }
+                    }
+                }
+                if state.finished {
+                    return None;
+                }
+                match (state.fetch)(state.cursor.take()).await {
+                    Ok(page) => {
+                        state.finished = page.next.is_none();
+                        state.cursor = page.next;
+                        state.items = Some(page.items);
+                        state.yielded = false;
+                    }
+                    Err(err) => {
+                        state.finished = true;
+                        return Some((Err(err), state));
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::cell::Cell;
+    use futures_util::StreamExt;
+    use crate::{LabraError, LabradorResult};
+    use super::{Page, PagedStream};
+
+    fn fake_page(cursor: Option<String>) -> LabradorResult<Page<i32>> {
+        match cursor.as_deref() {
+            None => Ok(Page::with_cursor(vec![1, 2], Some("2".to_string()))),
+            Some("2") => Ok(Page::with_cursor(vec![3, 4], Some("4".to_string()))),
+            Some("4") => Ok(Page::with_cursor(vec![5], Some("".to_string()))),
+            _ => Err(LabraError::RequestError("unexpected cursor".to_string())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_three_pages() {
+        let calls = Cell::new(0);
+        let items = PagedStream::new(|cursor| {
+            calls.set(calls.get() + 1);
+            async move { fake_page(cursor) }
+        }).into_stream().collect::<Vec<_>>().await;
+        let items = items.into_iter().collect::<LabradorResult<Vec<i32>>>().unwrap();
+        assert_eq!(items, vec![1, 2, 3, 4, 5]);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_max_items() {
+        let calls = Cell::new(0);
+        let items = PagedStream::new(|cursor| {
+            calls.set(calls.get() + 1);
+            async move { fake_page(cursor) }
+        }).max_items(3).into_stream().collect::<Vec<_>>().await;
+        let items = items.into_iter().collect::<LabradorResult<Vec<i32>>>().unwrap();
+        assert_eq!(items, vec![1, 2, 3]);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_error_mid_stream() {
+        let items = PagedStream::new(|cursor: Option<String>| async move {
+            match cursor {
+                None => Ok(Page::with_cursor(vec![1, 2], Some("next".to_string()))),
+                Some(_) => Err(LabraError::ApiError("system error".to_string())),
+            }
+        }).into_stream().collect::<Vec<LabradorResult<i32>>>().await;
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_ok() && items[1].is_ok());
+        assert!(items[2].is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//!
+//! 车牌号
+//!
+//! 停车、加油、ETC等行业接口都需要传入车牌号，这里统一规范化（去除`·`、空格，全角转半角，字母转大写）、
+//! 校验格式，并提供日志中使用的脱敏输出。
+//!
+//! 支持的格式：
+//! *   普通车牌：省份简称 + 发牌机关代号 + 5位字母或数字，如`沪A12345`
+//! *   新能源车牌：省份简称 + 发牌机关代号 + 6位，小型车第一位为字母（如`沪AD12345`），大型车最后一位为字母（如`沪A12345D`）
+//! *   警车及教练车、挂车、港澳车辆等专用车牌：最后一位为`警`、`学`、`挂`、`港`、`澳`等，如`沪A1234警`
+//!
+use crate::{LabradorResult, LabraError};
+
+/// 省、自治区、直辖市简称
+const PROVINCES: &str = "京津沪渝冀豫云辽黑湘皖鲁新苏浙赣鄂桂甘晋蒙陕吉闽贵粤青藏川宁琼";
+/// 专用车牌的最后一位
+const SPECIAL_SUFFIXES: &str = "警学挂港澳领使试超";
+/// 新能源车牌中表示能源类型的字母，D、A、B、C、E为纯电动，F、G、H、J、K为非纯电动
+const NEW_ENERGY_LETTERS: &str = "ABCDEFGHJK";
+
+/// 车牌类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlateKind {
+    /// 普通车牌
+    Regular,
+    /// 新能源车牌
+    NewEnergy,
+    /// 警车
+    Police,
+    /// 其他专用车牌，如教练车（学）、挂车（挂）、港澳入境车辆（港、澳）
+    Special(char),
+}
+
+/// 规范化车牌号：去除`·`、空格等分隔符，全角字母数字转半角，字母转大写
+pub fn normalize_plate_number(plate_number: &str) -> String {
+    plate_number.chars()
+        .filter(|c| !matches!(c, '·' | '•' | '.' | '-' | '\u{30FB}') && !c.is_whitespace())
+        .map(|c| match c {
+            '\u{FF10}'..='\u{FF19}' | '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}' => std::char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            c => c,
+        })
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// 校验并规范化车牌号，返回规范化后的车牌号与车牌类型
+pub fn validate_plate_number(plate_number: &str) -> LabradorResult<(String, PlateKind)> {
+    let normalized = normalize_plate_number(plate_number);
+    match plate_kind(&normalized) {
+        Some(kind) => Ok((normalized, kind)),
+        None => Err(LabraError::RequestError(format!("车牌号{}格式有误", mask_plate_number(plate_number)))),
+    }
+}
+
+/// 车牌号格式是否正确
+pub fn is_valid_plate_number(plate_number: &str) -> bool {
+    plate_kind(&normalize_plate_number(plate_number)).is_some()
+}
+
+/// <pre>
+/// 日志中使用的脱敏车牌号，保留前两位与后两位，其余替换为`*`，如`沪A***45`
+/// 不足5位时只保留第一位
+/// </pre>
+pub fn mask_plate_number(plate_number: &str) -> String {
+    let chars = normalize_plate_number(plate_number).chars().collect::<Vec<_>>();
+    let (head, tail) = if chars.len() >= 5 { (2, 2) } else { (1.min(chars.len()), 0) };
+    let mut masked = chars[..head].iter().collect::<String>();
+    masked.push_str(&"*".repeat(chars.len() - head - tail));
+    masked.extend(&chars[chars.len() - tail..]);
+    masked
+}
+
+fn plate_kind(plate_number: &str) -> Option<PlateKind> {
+    let chars = plate_number.chars().collect::<Vec<_>>();
+    if chars.len() < 7 || !PROVINCES.contains(chars[0]) || !is_plate_letter(chars[1]) {
+        return None;
+    }
+    let serial = &chars[2..];
+    match serial.len() {
+        5 if serial.iter().all(|c| is_plate_alnum(*c)) => Some(PlateKind::Regular),
+        5 if serial[..4].iter().all(|c| is_plate_alnum(*c)) && SPECIAL_SUFFIXES.contains(serial[4]) => match serial[4] {
+            '警' => Some(PlateKind::Police),
+            c => Some(PlateKind::Special(c)),
+        },
+        // 小型新能源汽车：能源类型字母 + 1位字母或数字 + 4位数字
+        6 if NEW_ENERGY_LETTERS.contains(serial[0]) && is_plate_alnum(serial[1]) && serial[2..].iter().all(char::is_ascii_digit) => Some(PlateKind::NewEnergy),
+        // 大型新能源汽车：5位数字 + 能源类型字母
+        6 if serial[..5].iter().all(char::is_ascii_digit) && NEW_ENERGY_LETTERS.contains(serial[5]) => Some(PlateKind::NewEnergy),
+        _ => None,
+    }
+}
+
+/// 车牌中使用的字母，不含易与数字混淆的I、O
+fn is_plate_letter(c: char) -> bool {
+    c.is_ascii_uppercase() && c != 'I' && c != 'O'
+}
+
+fn is_plate_alnum(c: char) -> bool {
+    c.is_ascii_digit() || is_plate_letter(c)
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use super::{is_valid_plate_number, mask_plate_number, normalize_plate_number, validate_plate_number, PlateKind};
+
+    #[test]
+    fn test_validate_plate_number() {
+        assert_eq!(validate_plate_number("沪A·12345").unwrap(), ("沪A12345".to_string(), PlateKind::Regular));
+        assert_eq!(validate_plate_number(" 粤b dc123 ").unwrap(), ("粤BDC123".to_string(), PlateKind::Regular));
+        assert_eq!(validate_plate_number("京Ａ１２３４５").unwrap().0, "京A12345");
+        // 新能源
+        assert_eq!(validate_plate_number("沪AD12345").unwrap(), ("沪AD12345".to_string(), PlateKind::NewEnergy));
+        assert_eq!(validate_plate_number("浙AFA1234").unwrap().1, PlateKind::NewEnergy);
+        assert_eq!(validate_plate_number("沪A12345D").unwrap().1, PlateKind::NewEnergy);
+        // 警车及其他专用车牌
+        assert_eq!(validate_plate_number("沪A1234警").unwrap().1, PlateKind::Police);
+        assert_eq!(validate_plate_number("粤B1234学").unwrap().1, PlateKind::Special('学'));
+        assert_eq!(validate_plate_number("粤Z1234港").unwrap().1, PlateKind::Special('港'));
+
+        for invalid in ["", "沪A1234", "沪A123456", "沪AI2345", "沪A1234O", "沪1A2345", "XA12345", "沪AZ12345", "沪AD1234A", "沪A1234警5", "沪A12警"].iter() {
+            assert!(!is_valid_plate_number(invalid), "{}", invalid);
+        }
+        let err = validate_plate_number("沪A12").unwrap_err();
+        assert!(err.to_string().contains("沪***"), "{}", err);
+    }
+
+    #[test]
+    fn test_mask_plate_number() {
+        assert_eq!(normalize_plate_number("沪a·12345"), "沪A12345");
+        assert_eq!(mask_plate_number("沪A·12345"), "沪A***45");
+        assert_eq!(mask_plate_number("沪AD12345"), "沪A****45");
+        assert_eq!(mask_plate_number("沪A12"), "沪***");
+        assert_eq!(mask_plate_number(""), "");
+    }
+}
@@ -1,19 +1,16 @@
 use std::io::{Cursor};
 
-use rand::thread_rng;
-use rand::{Rng, distributions::Alphanumeric};
 use base64;
 use byteorder::{NativeEndian, WriteBytesExt, ReadBytesExt};
 use crate::errors::LabraError;
 
-use std::iter::repeat;
 use openssl::{symm};
 use openssl::hash::{MessageDigest};
 use openssl::pkey::PKey;
 use openssl::rsa::{Padding, Rsa};
 use openssl::sign::{Signer, Verifier};
-use rustc_serialize::hex::{ToHex, FromHex};
-use crate::LabradorResult;
+use crate::{LabradorResult, rand_string};
+use crate::util::hex;
 
 #[allow(unused)]
 pub enum HashType {
@@ -21,6 +18,15 @@ pub enum HashType {
     Sha256
 }
 
+/// 消息加解密的补位块大小
+const MSG_BLOCK_SIZE: usize = 32;
+/// CBC模式的IV长度
+const AES_IV_SIZE: usize = 16;
+const AES_128_KEY_SIZE: usize = 16;
+const AES_256_KEY_SIZE: usize = 32;
+/// GCM模式的认证标签长度
+const GCM_TAG_SIZE: usize = 16;
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct PrpCrypto {
     key: Vec<u8>,
@@ -41,50 +47,186 @@ impl PrpCrypto {
         if cfg!(test) {
             "1234567890123456".to_owned()
         } else {
-            thread_rng().sample_iter(&Alphanumeric).take(16).collect::<String>()
+            rand_string(16)
         }
     }
 
-    /// # 加密消息(aes_128_cbc)
-    pub fn aes_128_cbc_encrypt_msg(&self, plaintext: &str, _id: &str) -> LabradorResult<String> {
+    /// <pre>
+    /// # 加密消息(aes_256_cbc)，IV取key前16位，与微信官方加解密方案一致
+    /// 32位EncodingAESKey使用AES-256-CBC（16位key为AES-128-CBC），消息体按32字节做PKCS#7补位
+    /// </pre>
+    pub fn aes_256_cbc_encrypt_msg(&self, plaintext: &str, _id: &str) -> LabradorResult<String> {
+        let iv = self.key_iv()?.to_vec();
+        self.aes_256_cbc_encrypt_msg_with_iv(plaintext, _id, &iv)
+    }
+
+    /// # 加密消息(aes_256_cbc，32字节PKCS#7补位)，使用指定的16字节IV
+    pub fn aes_256_cbc_encrypt_msg_with_iv(&self, plaintext: &str, _id: &str, iv: &[u8]) -> LabradorResult<String> {
         let mut wtr = PrpCrypto::get_random_string().into_bytes();
-        wtr.write_u32::<NativeEndian>((plaintext.len() as u32).to_be()).unwrap_or_default();
+        wtr.write_u32::<NativeEndian>((plaintext.len() as u32).to_be())?;
         wtr.extend(plaintext.bytes());
         wtr.extend(_id.bytes());
-        let encrypted = symm::encrypt(symm::Cipher::aes_128_cbc(), &self.key, Some(&self.key[..16]), &wtr)?;
+        // 消息体按32字节做PKCS#7补位
+        let pad = MSG_BLOCK_SIZE - wtr.len() % MSG_BLOCK_SIZE;
+        wtr.resize(wtr.len() + pad, pad as u8);
+        let encrypted = self.msg_cipher(symm::Mode::Encrypt, &wtr, iv)?;
         let b64encoded = base64::encode(&encrypted);
         Ok(b64encoded)
     }
 
-    /// # 解密消息(aes_128_cbc)
-    pub fn aes_128_cbc_decrypt_msg(&self, ciphertext: &str, _id: &str) -> LabradorResult<String> {
+    /// <pre>
+    /// # 解密消息(aes_256_cbc)，IV取key前16位，与微信官方加解密方案一致
+    /// 32位EncodingAESKey使用AES-256-CBC（16位key为AES-128-CBC），去除32字节PKCS#7补位
+    /// </pre>
+    pub fn aes_256_cbc_decrypt_msg(&self, ciphertext: &str, _id: &str) -> LabradorResult<String> {
+        let iv = self.key_iv()?.to_vec();
+        self.aes_256_cbc_decrypt_msg_with_iv(ciphertext, _id, &iv)
+    }
+
+    /// # 解密消息(aes_256_cbc，32字节PKCS#7补位)，使用指定的16字节IV
+    pub fn aes_256_cbc_decrypt_msg_with_iv(&self, ciphertext: &str, _id: &str, iv: &[u8]) -> LabradorResult<String> {
         let b64decoded = base64::decode(ciphertext)?;
-        let text = symm::decrypt(symm::Cipher::aes_128_cbc(), &self.key, Some(&self.key[..16]), &b64decoded)?;
+        let text = self.aes_cbc_decrypt_pkcs7_with_iv(&b64decoded, iv)?;
+        if text.len() < 20 {
+            return Err(LabraError::InvalidSignature("invalid message length.".to_string()));
+        }
         let mut rdr = Cursor::new(text[16..20].to_vec());
-        let content_length = u32::from_be(rdr.read_u32::<NativeEndian>().unwrap_or_default()) as usize;
+        let content_length = u32::from_be(rdr.read_u32::<NativeEndian>()?) as usize;
+        if text.len() - 20 < content_length {
+            return Err(LabraError::InvalidSignature("invalid message length.".to_string()));
+        }
         let content = &text[20 .. content_length + 20];
         let from_id = &text[content_length + 20 ..];
         if from_id != _id.as_bytes() {
             return Err(LabraError::InvalidAppId);
         }
-        let content_string = String::from_utf8(content.to_vec()).unwrap_or_default();
-        Ok(content_string)
+        Ok(String::from_utf8(content.to_vec())?)
+    }
+
+    /// # 加密消息，实际为AES-256-CBC，见`aes_256_cbc_encrypt_msg`
+    #[deprecated(note = "请使用`aes_256_cbc_encrypt_msg`")]
+    pub fn aes_128_cbc_encrypt_msg(&self, plaintext: &str, _id: &str) -> LabradorResult<String> {
+        self.aes_256_cbc_encrypt_msg(plaintext, _id)
+    }
+
+    /// # 加密消息，实际为AES-256-CBC，见`aes_256_cbc_encrypt_msg_with_iv`
+    #[deprecated(note = "请使用`aes_256_cbc_encrypt_msg_with_iv`")]
+    pub fn aes_128_cbc_encrypt_msg_with_iv(&self, plaintext: &str, _id: &str, iv: &[u8]) -> LabradorResult<String> {
+        self.aes_256_cbc_encrypt_msg_with_iv(plaintext, _id, iv)
+    }
+
+    /// # 解密消息，实际为AES-256-CBC，见`aes_256_cbc_decrypt_msg`
+    #[deprecated(note = "请使用`aes_256_cbc_decrypt_msg`")]
+    pub fn aes_128_cbc_decrypt_msg(&self, ciphertext: &str, _id: &str) -> LabradorResult<String> {
+        self.aes_256_cbc_decrypt_msg(ciphertext, _id)
+    }
+
+    /// # 解密消息，实际为AES-256-CBC，见`aes_256_cbc_decrypt_msg_with_iv`
+    #[deprecated(note = "请使用`aes_256_cbc_decrypt_msg_with_iv`")]
+    pub fn aes_128_cbc_decrypt_msg_with_iv(&self, ciphertext: &str, _id: &str, iv: &[u8]) -> LabradorResult<String> {
+        self.aes_256_cbc_decrypt_msg_with_iv(ciphertext, _id, iv)
+    }
+
+    /// # 解密数据(aes_cbc)，去除32字节PKCS#7补位
+    ///
+    /// 用于企业微信通讯录导出文件等使用EncodingAESKey加密的数据
+    pub fn aes_cbc_decrypt_pkcs7(&self, ciphertext: &[u8]) -> LabradorResult<Vec<u8>> {
+        let iv = self.key_iv()?.to_vec();
+        self.aes_cbc_decrypt_pkcs7_with_iv(ciphertext, &iv)
+    }
+
+    fn aes_cbc_decrypt_pkcs7_with_iv(&self, ciphertext: &[u8], iv: &[u8]) -> LabradorResult<Vec<u8>> {
+        let mut text = self.msg_cipher(symm::Mode::Decrypt, ciphertext, iv)?;
+        let pad = text.last().map(|v| *v as usize).unwrap_or_default();
+        if !(1..=MSG_BLOCK_SIZE).contains(&pad) || text.len() < pad {
+            return Err(LabraError::InvalidSignature("invalid message padding.".to_string()));
+        }
+        text.truncate(text.len() - pad);
+        Ok(text)
     }
 
+    /// openssl在key、IV长度不足时会直接panic，调用前先校验
+    fn check_key_iv(&self, key_size: usize, iv: &[u8], iv_size: Option<usize>) -> LabradorResult<()> {
+        if self.key.len() != key_size {
+            return Err(LabraError::InvalidSignature("invalid aes key.".to_string()));
+        }
+        if iv.is_empty() || iv_size.map(|size| iv.len() != size).unwrap_or(false) {
+            return Err(LabraError::InvalidSignature("invalid aes iv.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 微信官方方案的IV：key前16位
+    fn key_iv(&self) -> LabradorResult<&[u8]> {
+        self.key.get(..AES_IV_SIZE).ok_or_else(|| LabraError::InvalidSignature("invalid aes key.".to_string()))
+    }
+
+    /// 消息加解密：32位EncodingAESKey为AES-256-CBC，16位为AES-128-CBC，补位由调用方处理
+    fn msg_cipher(&self, mode: symm::Mode, data: &[u8], iv: &[u8]) -> LabradorResult<Vec<u8>> {
+        let cipher = match self.key.len() {
+            32 => symm::Cipher::aes_256_cbc(),
+            16 => symm::Cipher::aes_128_cbc(),
+            _ => return Err(LabraError::InvalidSignature("invalid aes key.".to_string())),
+        };
+        if iv.len() != AES_IV_SIZE {
+            return Err(LabraError::InvalidSignature("invalid aes iv.".to_string()));
+        }
+        let mut crypter = symm::Crypter::new(cipher, mode, &self.key, Some(iv))?;
+        crypter.pad(false);
+        let mut out = vec![0; data.len() + cipher.block_size()];
+        let count = crypter.update(data, &mut out)?;
+        let rest = crypter.finalize(&mut out[count..])?;
+        out.truncate(count + rest);
+        Ok(out)
+    }
 
     /// # 解密数据(aes_128_cbc)
+    ///
+    /// ciphertext为十六进制的密文，解密结果按utf8转为字符串
+    #[deprecated(note = "请使用`aes_128_cbc_decrypt_data_bytes`")]
     pub fn aes_128_cbc_decrypt_data(&self, ciphertext: &str, iv: &str) -> LabradorResult<String> {
-        let data = ciphertext.from_hex()?;
-        let text = symm::decrypt(symm::Cipher::aes_128_cbc(), &self.key, Some(iv.as_bytes()), &data)?;
-        let content_string = String::from_utf8(text).unwrap_or_default();
-        Ok(content_string)
+        let data = hex::decode(ciphertext)?;
+        let text = self.aes_128_cbc_decrypt_data_bytes(&data, iv.as_bytes())?;
+        Ok(String::from_utf8(text)?)
     }
 
+    /// # 解密数据(aes_128_cbc，PKCS#7补位)
+    pub fn aes_128_cbc_decrypt_data_bytes(&self, ciphertext: &[u8], iv: &[u8]) -> LabradorResult<Vec<u8>> {
+        self.check_key_iv(AES_128_KEY_SIZE, iv, Some(AES_IV_SIZE))?;
+        let text = symm::decrypt(symm::Cipher::aes_128_cbc(), &self.key, Some(iv), ciphertext)?;
+        Ok(text)
+    }
 
     /// # 加密数据(aes_128_cbc)
+    ///
+    /// 返回十六进制的密文
+    #[deprecated(note = "请使用`aes_128_cbc_encrypt_data_bytes`")]
     pub fn aes_128_cbc_encrypt_data(&self, plaintext: &str, iv: &str) -> LabradorResult<String> {
-        let text = symm::encrypt(symm::Cipher::aes_128_cbc(), &self.key, Some(iv.as_bytes()), plaintext.as_bytes())?;
-        Ok(text.to_hex())
+        let text = self.aes_128_cbc_encrypt_data_bytes(plaintext.as_bytes(), iv.as_bytes())?;
+        Ok(hex::encode(text))
+    }
+
+    /// # 加密数据(aes_128_cbc，PKCS#7补位)
+    pub fn aes_128_cbc_encrypt_data_bytes(&self, plaintext: &[u8], iv: &[u8]) -> LabradorResult<Vec<u8>> {
+        self.check_key_iv(AES_128_KEY_SIZE, iv, Some(AES_IV_SIZE))?;
+        let text = symm::encrypt(symm::Cipher::aes_128_cbc(), &self.key, Some(iv), plaintext)?;
+        Ok(text)
+    }
+
+    /// # 加密数据(aes_128_cbc，全零IV，PKCS#7补位)
+    ///
+    /// 支付宝内容加密（encrypt_type=AES）使用的方案，返回base64编码的密文
+    pub fn aes_128_cbc_base64_encrypt(&self, data: &str) -> LabradorResult<String> {
+        let text = self.aes_128_cbc_encrypt_data_bytes(data.as_bytes(), &[0u8; AES_IV_SIZE])?;
+        Ok(base64::encode(text))
+    }
+
+    /// # 解密数据(aes_128_cbc，全零IV，PKCS#7补位)
+    ///
+    /// data为base64编码的密文，解密结果按utf8转为字符串
+    pub fn aes_128_cbc_base64_decrypt(&self, data: &str) -> LabradorResult<String> {
+        let text = self.aes_128_cbc_decrypt_data_bytes(&base64::decode(data)?, &[0u8; AES_IV_SIZE])?;
+        Ok(String::from_utf8(text)?)
     }
 
     /// RSA签名
@@ -104,14 +246,20 @@ impl PrpCrypto {
     /// ```
     /// return: 返回base64字符串
     pub fn rsa_sha256_sign(content: &str, private_key: &str) -> LabradorResult<String> {
-        let private_key = openssl::rsa::Rsa::private_key_from_pem(private_key.as_bytes())?;
+        let result = PrpCrypto::rsa_sha256_sign_bytes(content.as_bytes(), private_key.as_bytes())?;
+        // 签名结果转化为base64
+        Ok(base64::encode(&result))
+    }
+
+    /// RSA签名，private_key为PEM格式的私钥，返回签名的原始字节
+    pub fn rsa_sha256_sign_bytes(content: &[u8], private_key: &[u8]) -> LabradorResult<Vec<u8>> {
+        let private_key = openssl::rsa::Rsa::private_key_from_pem(private_key)?;
         let pkey = PKey::from_rsa(private_key)?;
-        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
         signer.set_rsa_padding(Padding::PKCS1)?;
-        signer.update(content.as_bytes())?;
+        signer.update(content)?;
         let result = signer.sign_to_vec()?;
-        // 签名结果转化为base64
-        Ok(base64::encode(&result))
+        Ok(result)
     }
 
     pub fn rsa_sha256_sign_pkcs1(content: &str, private_key: Vec<u8>) -> LabradorResult<String> {
@@ -151,36 +299,69 @@ impl PrpCrypto {
     /// ```
     pub fn rsa_sha256_verify(public_key: &str, content: &str, sign: &str) -> LabradorResult<bool> {
         let sig = base64::decode(sign)?;
-        let sig = sig.to_hex();
-        let sig = sig.from_hex()?;
+        PrpCrypto::rsa_sha256_verify_bytes(public_key.as_bytes(), content.as_bytes(), &sig)
+    }
+
+    /// RSA签名验证，public_key为PEM格式的公钥，sign为签名的原始字节
+    pub fn rsa_sha256_verify_bytes(public_key: &[u8], content: &[u8], sign: &[u8]) -> LabradorResult<bool> {
         // 获取公钥对象
-        let pk = Rsa::public_key_from_pem(public_key.as_bytes())?;
+        let pk = Rsa::public_key_from_pem(public_key)?;
         let pkey = PKey::from_rsa(pk)?;
         // 对摘要进行签名
         let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)?;
-        verifier.update(content.as_bytes())?;
-        let ver = verifier.verify(&sig)?;
+        verifier.update(content)?;
+        let ver = verifier.verify(sign)?;
         Ok(ver)
     }
 
+    /// RSA公钥加密，填充方式为OAEP（RSAES-OAEP，SHA-1），public_key为PEM格式的公钥
+    pub fn rsa_oaep_encrypt(public_key: &[u8], content: &[u8]) -> LabradorResult<Vec<u8>> {
+        let rsa = Rsa::public_key_from_pem(public_key)?;
+        let mut buf = vec![0u8; rsa.size() as usize];
+        let len = rsa.public_encrypt(content, &mut buf, Padding::PKCS1_OAEP)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// RSA私钥解密，填充方式为OAEP（RSAES-OAEP，SHA-1），private_key为PEM格式的私钥
+    pub fn rsa_oaep_decrypt(private_key: &[u8], content: &[u8]) -> LabradorResult<Vec<u8>> {
+        let rsa = Rsa::private_key_from_pem(private_key)?;
+        let mut buf = vec![0u8; rsa.size() as usize];
+        let len = rsa.private_decrypt(content, &mut buf, Padding::PKCS1_OAEP)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// HMAC-SHA256签名，返回小写十六进制字符串
     pub fn hmac_sha256_sign(key: &str, message: &str) -> LabradorResult<String> {
-        let pkey = PKey::hmac(key.as_bytes())?;
-        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
-        signer.update(message.as_bytes())?;
+        let result = PrpCrypto::hmac_sha256_sign_bytes(key.as_bytes(), message.as_bytes())?;
+        Ok(hex::encode(result))
+    }
+
+    /// HMAC-SHA256签名，返回签名的原始字节
+    pub fn hmac_sha256_sign_bytes(key: &[u8], message: &[u8]) -> LabradorResult<Vec<u8>> {
+        let pkey = PKey::hmac(key)?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+        signer.update(message)?;
         let result = signer.sign_to_vec()?;
-        Ok(result.to_hex())
+        Ok(result)
     }
 
     /// # 加密(aes_256_gcm)
     pub fn aes_256_gcm_encrypt(&self, associated_data: &[u8], nonce: &[u8], plain_text: &[u8]) -> LabradorResult<Vec<u8>> {
-        let mut out_tag: Vec<u8> = repeat(0).take(16).collect();
-        let encrypted = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), &self.key, Some(&nonce), associated_data, plain_text, &mut out_tag)?;
+        self.check_key_iv(AES_256_KEY_SIZE, nonce, None)?;
+        let mut out_tag = vec![0u8; GCM_TAG_SIZE];
+        let encrypted = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), &self.key, Some(nonce), associated_data, plain_text, &mut out_tag)?;
         Ok(encrypted)
     }
 
     /// # 解密(aes_256_gcm)
     pub fn aes_256_gcm_decrypt(&self, associated_data: &[u8], nonce: &[u8], ciphertext: &[u8], tag: &[u8]) -> LabradorResult<Vec<u8>> {
-        let decrypted = symm::decrypt_aead(symm::Cipher::aes_256_gcm(), &self.key, Some(&nonce), associated_data, ciphertext, tag)?;
+        self.check_key_iv(AES_256_KEY_SIZE, nonce, None)?;
+        if tag.len() != GCM_TAG_SIZE {
+            return Err(LabraError::InvalidSignature("invalid aes gcm tag.".to_string()));
+        }
+        let decrypted = symm::decrypt_aead(symm::Cipher::aes_256_gcm(), &self.key, Some(nonce), associated_data, ciphertext, tag)?;
         Ok(decrypted)
     }
 }
@@ -191,7 +372,7 @@ mod tests {
     use std::iter::repeat;
     use base64;
     use super::PrpCrypto;
-    use rustc_serialize::hex::{FromHex, ToHex};
+    use crate::util::hex;
 
 
     #[test]
@@ -206,14 +387,117 @@ mod tests {
     #[test]
     fn test_prpcrypto_decrypt() {
         let encoding_aes_key = "kWxPEV2UEDyxWpmPdKC3F4dgPDmOvfKX1HGnEUDS1aR=";
-        let key = base64::decode(encoding_aes_key).unwrap();
+        let key = base64::decode_config(encoding_aes_key, base64::STANDARD.decode_allow_trailing_bits(true)).unwrap();
         let prp = PrpCrypto::new(key);
         // let decrypted = prp.decrypt("9s4gMv99m88kKTh/H8IdkNiFGeG9pd7vNWl50fGRWXY=", "rust").unwrap();
         // assert_eq!("test", &decrypted);
     }
 
+    #[test]
+    fn test_aes_cbc_msg() {
+        let key = base64::decode_config("kWxPEV2UEDyxWpmPdKC3F4dgPDmOvfKX1HGnEUDS1aR=", base64::STANDARD.decode_allow_trailing_bits(true)).unwrap();
+        let prp = PrpCrypto::new(key);
+        let msg = "<xml>\n\
+            <MsgType><![CDATA[text]]></MsgType>\n\
+            <Content><![CDATA[test]]></Content>\n\
+            <FromUserName><![CDATA[wx49f0ab532d5d035a]]></FromUserName>\n\
+            <ToUserName><![CDATA[messense]]></ToUserName>\n\
+            <AgentID>1</AgentID>\n\
+            <CreateTime>1411525903</CreateTime>\n\
+            </xml>";
+        let encrypted = prp.aes_256_cbc_encrypt_msg(msg, "wx49f0ab532d5d035a").unwrap();
+        assert_eq!("9s4gMv99m88kKTh/H8IdkOiMg6bisoy3ypwy9H4hvSPe9nsGaqyw5hhSjdYbcrKk+j3nba4HMOTzHrluLBYqxgNcBqGsL8GqxlhZgURnAtObvesEl5nZ+uBE8bviY0LWke8Zy9V/QYKxNV2FqllNXcfmstttyIkMKCCmVbCFM2JTF5wY0nFhHZSjPUL2Q1qvSUCUld+/WIXrx0oyKQmpB6o8NRrrNrsDf03oxI1p9FxUgMnwKKZeOA/uu+2IEvEBtb7muXsVbwbgX05UPPJvFurDXafG0RQyPR+mf1nDnAtQmmNOuiR5MIkdQ39xn1vWwi1O5oazPoQJz0nTYjxxEE8kv3kFxtAGVRe3ypD3WeK2XeFYFMNMpatF9XiKzHo3", &encrypted);
+        assert_eq!(msg, prp.aes_256_cbc_decrypt_msg(&encrypted, "wx49f0ab532d5d035a").unwrap());
+        assert!(prp.aes_256_cbc_decrypt_msg(&encrypted, "wx0000000000000000").is_err());
+        // 旧方法名保留为别名
+        #[allow(deprecated)]
+        {
+            assert_eq!(encrypted, prp.aes_128_cbc_encrypt_msg(msg, "wx49f0ab532d5d035a").unwrap());
+            assert_eq!(msg, prp.aes_128_cbc_decrypt_msg(&encrypted, "wx49f0ab532d5d035a").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_aes_cbc_msg_with_iv() {
+        let key = base64::decode_config("kWxPEV2UEDyxWpmPdKC3F4dgPDmOvfKX1HGnEUDS1aR=", base64::STANDARD.decode_allow_trailing_bits(true)).unwrap();
+        let prp = PrpCrypto::new(key.clone());
+        let msg = "<xml><Content><![CDATA[test]]></Content></xml>";
+        // 默认IV为key前16位
+        let encrypted = prp.aes_256_cbc_encrypt_msg(msg, "wx49f0ab532d5d035a").unwrap();
+        assert_eq!(encrypted, prp.aes_256_cbc_encrypt_msg_with_iv(msg, "wx49f0ab532d5d035a", &key[..16]).unwrap());
+        assert_eq!(msg, prp.aes_256_cbc_decrypt_msg_with_iv(&encrypted, "wx49f0ab532d5d035a", &key[..16]).unwrap());
+        // 指定IV
+        let iv = b"fedcba9876543210";
+        let encrypted_with_iv = prp.aes_256_cbc_encrypt_msg_with_iv(msg, "wx49f0ab532d5d035a", iv).unwrap();
+        assert_ne!(encrypted, encrypted_with_iv);
+        assert_eq!(msg, prp.aes_256_cbc_decrypt_msg_with_iv(&encrypted_with_iv, "wx49f0ab532d5d035a", iv).unwrap());
+        // IV长度
+        assert!(prp.aes_256_cbc_encrypt_msg_with_iv(msg, "wx49f0ab532d5d035a", b"short").is_err());
+        assert!(prp.aes_256_cbc_decrypt_msg_with_iv(&encrypted, "wx49f0ab532d5d035a", &key).is_err());
+        // 16位key为AES-128-CBC
+        let prp = PrpCrypto::new(b"0123456789abcdef".to_vec());
+        let encrypted = prp.aes_256_cbc_encrypt_msg(msg, "rust").unwrap();
+        assert_eq!(msg, prp.aes_256_cbc_decrypt_msg(&encrypted, "rust").unwrap());
+    }
+
+    #[test]
+    fn test_aes_cbc_msg_short_key() {
+        let prp = PrpCrypto::new(b"0123456789".to_vec());
+        assert!(prp.aes_256_cbc_encrypt_msg("test", "rust").is_err());
+        assert!(prp.aes_256_cbc_decrypt_msg("9s4gMv99m88kKTh/H8IdkNiFGeG9pd7vNWl50fGRWXY=", "rust").is_err());
+        assert!(prp.aes_256_cbc_encrypt_msg_with_iv("test", "rust", b"fedcba9876543210").is_err());
+        assert!(prp.aes_cbc_decrypt_pkcs7(&[0u8; 32]).is_err());
+        assert!(PrpCrypto::new(Vec::new()).aes_256_cbc_encrypt_msg("test", "rust").is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_aes_128_cbc_data_bytes_and_hex() {
+        let prp = PrpCrypto::new(b"0123456789abcdef".to_vec());
+        let iv = "fedcba9876543210";
+        for plaintext in ["", "labrador", "{\"openId\":\"oGZUI0egBJY1zhBYw2KhdUfwVJJE\",\"nickName\":\"Band\"}"].iter() {
+            let encrypted = prp.aes_128_cbc_encrypt_data_bytes(plaintext.as_bytes(), iv.as_bytes()).unwrap();
+            let encrypted_hex = prp.aes_128_cbc_encrypt_data(plaintext, iv).unwrap();
+            assert_eq!(hex::encode(&encrypted), encrypted_hex);
+            assert_eq!(encrypted.len() % 16, 0);
+            assert_eq!(prp.aes_128_cbc_decrypt_data_bytes(&encrypted, iv.as_bytes()).unwrap(), plaintext.as_bytes());
+            assert_eq!(&prp.aes_128_cbc_decrypt_data(&encrypted_hex, iv).unwrap(), plaintext);
+        }
+        assert!(prp.aes_128_cbc_decrypt_data("not hex", iv).is_err());
+    }
+
+    #[test]
+    fn test_aes_128_cbc_decrypt_data_bytes_sample() {
+        // 小程序开放数据解密官方示例
+        let key = base64::decode("tiihtNczf5v6AKRyjwEUhQ==").unwrap();
+        let encrypted = base64::decode("CiyLU1Aw2KjvrjMdj8YKliAjtP4gsMZMQmRzooG2xrDcvSnxIMXFufNstNGTyaGS9uT5geRa0W4oTOb1WT7fJlAC+oNPdbB+3hVbJSRgv+4lGOETKUQz6OYStslQ142dNCuabNPGBzlooOmB231qMM85d2/fV6ChevvXvQP8Hkue1poOFtnEtpyxVLW1zAo6/1Xx1COxFvrc2d7UL/lmHInNlxuacJXwu0fjpXfz/YqYzBIBzD6WUfTIF9GRHpOn/Hz7saL8xz+W//FRAUid1OksQaQx4CMs8LOddcQhULW4ucetDf96JcR3g0gfRK4PC7E/r7Z6xNrXd2UIeorGj5Ef7b1pJAYB6Y5anaHqZ9J6nKEBvB4DnNLIVWSgARns/8wR2SiRS7MNACwTyrGvt9ts8p12PKFdlqYTopNHR1Vf7XjfhQlVsAJdNiKdYmYVoKlaRv85IfVunYzO0IKXsyl7JCUjCpoG20f0a04COwfneQAGGwd5oa+T8yO5hzuyDb/XcxxmK01EpqOyuxINew==").unwrap();
+        let iv = base64::decode("r7BXXKkLb8qrSNn05n0qiA==").unwrap();
+        let prp = PrpCrypto::new(key);
+        let data = String::from_utf8(prp.aes_128_cbc_decrypt_data_bytes(&encrypted, &iv).unwrap()).unwrap();
+        let v = serde_json::from_str::<serde_json::Value>(&data).unwrap();
+        assert_eq!(v["openId"], "oGZUI0egBJY1zhBYw2KhdUfwVJJE");
+        assert_eq!(v["watermark"]["appid"], "wx4f4bc4dec97d474b");
+    }
+
+    #[test]
+    fn test_sign_bytes_and_string() {
+        let sign = PrpCrypto::hmac_sha256_sign("key", "The quick brown fox jumps over the lazy dog").unwrap();
+        assert_eq!(sign, "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+        assert_eq!(hex::encode(PrpCrypto::hmac_sha256_sign_bytes(b"key", b"The quick brown fox jumps over the lazy dog").unwrap()), sign);
+
+        let rsa = openssl::rsa::Rsa::generate(2048).unwrap();
+        let private_key = String::from_utf8(rsa.private_key_to_pem().unwrap()).unwrap();
+        let public_key = String::from_utf8(rsa.public_key_to_pem().unwrap()).unwrap();
+        let sign = PrpCrypto::rsa_sha256_sign("labrador", &private_key).unwrap();
+        let sign_bytes = PrpCrypto::rsa_sha256_sign_bytes(b"labrador", private_key.as_bytes()).unwrap();
+        assert_eq!(base64::encode(&sign_bytes), sign);
+        assert!(PrpCrypto::rsa_sha256_verify(&public_key, "labrador", &sign).unwrap());
+        assert!(PrpCrypto::rsa_sha256_verify_bytes(public_key.as_bytes(), b"labrador", &sign_bytes).unwrap());
+        assert!(!PrpCrypto::rsa_sha256_verify_bytes(public_key.as_bytes(), b"labrador!", &sign_bytes).unwrap());
+    }
+
     fn hex_to_bytes(raw_hex: &str) -> Vec<u8> {
-        raw_hex.from_hex().ok().unwrap()
+        hex::decode(raw_hex).ok().unwrap()
     }
 
     #[test]
@@ -242,7 +526,7 @@ mod tests {
         let iv= b"bb9ee5e44da1";
         // let plain_text= hex_to_bytes("d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39");
         let cipher_text_base64=base64::decode("WZnvm4CnxNuPUYLIAh3Kv2WJFivwhLA2/xGxhwNHh5j2XmhUn2ibLm1I/pU3XKw6YWYLY8RfHsRHVcY4ln0NUUsiqsmgUxELKjqPKY0dWZSwXtbVAMlK+rGQbrgoopn/gNurM6Sx0jOjzorg091J0GGkxn2hHSaJ6EUtbHAGB3Nx/PTLr2o1rzNvF/QWLGE+5bcGe5Yg85qshvoGATJSwNAlVmdCOV4fg583irGzg6u7MYAytZpBoyzA4yf+9AKrO3K5lQwF5G6ULPWXtTNuW4rrC8wPI5xdnLqKopo9gNDUqg+19DYDSYsUvztRU7wORNh0SVkZLTwhOmKzFM8oqDHDuvcRCrUjw52NT85BQIFtsJMHciiFL+pefsz1llxlDnjroRyqNAyXw0RvKJfff40M8Fw7mAWK5eINQLPZAi4f9Ws7vC3WZ9/WGjrPOQInn8oLxzb8c+Wn0HSAxfEBRBmGx8FQ0+MdAP5bHTn3KCVxBM8gdx5vfeNqzcnRPG6qTMwuf/NE4BdnqNsDk5o3ZyhMGxnDfoJ+9PophG5KtdaPYHDVj/18PzT0w4GttSdw/1pisSPeOKcQqpI3/sC3ndDO7uqieUUAhMCtLxFCn1spndDLr+ciUs3CWJYlBgATE8vOFzPjVN8ECV+UeGULjkjWGBm0yPG3znbBpkX5Zvei4eZml16/JZHTWVgAKHpaaoBNH6qLKqS4UdpAXZJEQLAXflRw+4RjyD8ZsERcOTutnycozb/sPxB8N3qWhTGb8EJ8DTYSCILYemSIDmefmPU+ChzdM1FDbePMpHv8wCC/+zfRSwl0VtWXCauazZ3+1J9dW8ThvTOwlXPuRvOXFwCX/bq8BI3DX619TnahNBKU3+EfcvGGDO6bI5LvPSPLAaf1MgPc31Ab4jP+s73y4vc5IYNuwMC+aKuPmaxrqPA6Lr7PAUEicem4mYiTOAeG4hQh2C9XSOKrocsNDaOgLRiUU53bNY9sBTEkxoOc5prYVV7azwPfR506fSec0fv5c7v58srSK9zpTKNNVKbLL76WCpQ453dwmyaYeJNVqYoslzEL+kcb6UZVwr/Kj9TJka5bYHQOBmTRJT7FUeawvu4kHWzWnlRUShNFkuoymJEA8SXYyPliJgBWl36HAWse3PNr63K+RoYe8VdtviQQ02Js2Bg2RcTAlaxSoKuQdFfraGh35gVeJYEbrIp3N5goxLc6oc+bE/uoQI+pgv6oNsNznotp7bPCY1hIOEdtgvxMAUnpiU5ZsiPGt/N5KVAvSZJMzbuql3p2LBZjY3aGsNsT+xfgMj9K1fsORHP8/zt+RoF3AasSnn66zWRlxGlptkH+HtNxfEefaHtZ3NwYNPwaKwn9hIF5EotIhgLRsbEL9PWJLBVDuaWcmoaYDTNzAUlpGAKvyh2e4U7j3VuxPDiwNmPC+ZG/2CSMuD3+GPJodA3wbkhiNP4TAitKgYC03i94HDj8i2Th5HvNuA+dap7LaZerV7A34DwCK4rwk2C6z8+TAhdqagv2q1rnvzVT/dUXkIz3YMNkowboTpc/VgENPgUGBM4TtUpdk+hSxx/L5q/C+uWt8U1rIxbu5JrN3dHlvF/WfaCHQZP8e2QC8bz/TSX/tzFIQ6o/QtFWlF8OGbbndoNgTe5xyS5AwlprmR9FWFzjim8JAKNKMTKTrW3U6TKSUxSD9m7sl08rD3pCk+1kkKiVEgcuVHPd985n1xr4Ex9Hr8pJBTDcbkzis+dvh+CajqgsrYas+Eq8NTM8pz004PcPfZZzuaLgjl0Z+l7ZschSCkzq54BRxfIcvwywqJUhtRmB6xccpCtln6AsC/FS+kcJdAYEnnuU5uoPmNCcf3n+jDL9UGbcNg5Nj/w92tyF5A==").unwrap();
-        let base64_cipher = cipher_text_base64.to_hex();
+        let base64_cipher = hex::encode(&cipher_text_base64);
         println!("cipher_text:{}", &base64_cipher);
         let cipher_text = hex_to_bytes(&base64_cipher);
         let aad= b"certificate";
@@ -290,12 +574,26 @@ mod tests {
     
     }
 
+    #[test]
+    fn test_aes_128_cbc_base64() {
+        // 密文由`openssl enc -aes-128-cbc -iv 00000000000000000000000000000000 -base64`生成
+        let prp = PrpCrypto::new(base64::decode("4ChT08phkz59hquD795X7w==").unwrap());
+        let plaintext = r#"{"out_trade_no":"20150320010101001"}"#;
+        let ciphertext = "FwtPJot+E8m98OG/+ai5lerXv/YucpwEPINXwp37d9Frn15nVkaH+8aAkxNr3bKI";
+        assert_eq!(prp.aes_128_cbc_base64_encrypt(plaintext).unwrap(), ciphertext);
+        assert_eq!(prp.aes_128_cbc_base64_decrypt(ciphertext).unwrap(), plaintext);
+        assert!(prp.aes_128_cbc_base64_decrypt("not base64!").is_err());
+        let other = PrpCrypto::new(base64::decode("AAAAAAAAAAAAAAAAAAAAAA==").unwrap());
+        assert!(other.aes_128_cbc_base64_decrypt(ciphertext).is_err());
+    }
+
     #[test]
     fn test_aes_128_ecb() {
         let appId = "1ebc3d10ce15cf8cc601f60d3e84385c4d7acc9cc70fcd56dbbd969300c8f6082625cdd2cf66738f4635406a4c796bf7e1769d7ccfb468537ba211bdbf8fb13e09c343f52b1f5a47cab44126b61e338acc93b4cc12939a131f7b15a1af54be699dbb7ce3770aa8261af253d2aeac41c1c2db333d0052b48de4e58541bab56d98";
         let key = base64::decode("4ChT08phkz59hquD795X7w==").unwrap();
         let prp = PrpCrypto::new(key);
-        println!("result:{}", prp.aes_128_cbc_decrypt_data(appId, "dsd2bb9ee5e44da1").unwrap());
+        let data = prp.aes_128_cbc_decrypt_data_bytes(&hex::decode(appId).unwrap(), b"dsd2bb9ee5e44da1").unwrap();
+        println!("result:{}", String::from_utf8_lossy(&data));
         // match prp.decrypt_data(encryptedData, iv) {
         //     Ok(data) => {
         //         println!("data:{}",data);
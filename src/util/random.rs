@@ -0,0 +1,134 @@
+use chrono::Utc;
+use rand::{thread_rng, Rng};
+
+use crate::{LabraError, LabradorResult};
+use crate::serde_helper::beijing;
+
+/// 商户订单号最大长度
+pub const OUT_TRADE_NO_MAX_LEN: usize = 32;
+/// 商户订单号中时间前缀（yyyyMMddHHmmss）的长度
+const OUT_TRADE_NO_TIME_LEN: usize = 14;
+/// 商户订单号中随机后缀的最小长度
+const OUT_TRADE_NO_MIN_RANDOM_LEN: usize = 6;
+
+/// 随机字符串的字符集
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RandCharset {
+    /// 大小写字母及数字
+    Alphanumeric,
+    /// 小写十六进制字符
+    Hex,
+    /// 数字
+    Digits,
+}
+
+impl RandCharset {
+    pub fn chars(&self) -> &'static [u8] {
+        match self {
+            RandCharset::Alphanumeric => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+            RandCharset::Hex => b"0123456789abcdef",
+            RandCharset::Digits => b"0123456789",
+        }
+    }
+}
+
+/// 指定长度的随机字符串（大小写字母及数字）
+pub fn rand_string(len: usize) -> String {
+    rand_string_with(len, RandCharset::Alphanumeric)
+}
+
+/// 指定长度、字符集的随机字符串
+///
+/// 使用线程本地的随机数生成器，可在多线程中直接调用。
+pub fn rand_string_with(len: usize, charset: RandCharset) -> String {
+    let chars = charset.chars();
+    let mut rng = thread_rng();
+    (0..len).map(|_| chars[rng.gen_range(0, chars.len())] as char).collect()
+}
+
+/// 32位的随机字符串，用于签名中的nonce_str、noncestr等字段
+pub fn nonce_str() -> String {
+    rand_string(32)
+}
+
+/// <pre>
+/// 生成商户订单号
+/// 格式为北京时间yyyyMMddHHmmss加上18位随机数字，共32位
+/// </pre>
+pub fn out_trade_no() -> String {
+    gen_out_trade_no("")
+}
+
+/// <pre>
+/// 生成带前缀的商户订单号
+/// 格式为前缀加北京时间yyyyMMddHHmmss，剩余位数以随机数字补足，总长度为32位
+/// 前缀只能包含数字、大小写字母及_-|*，长度不超过12
+/// </pre>
+pub fn out_trade_no_with_prefix(prefix: &str) -> LabradorResult<String> {
+    let max_prefix_len = OUT_TRADE_NO_MAX_LEN - OUT_TRADE_NO_TIME_LEN - OUT_TRADE_NO_MIN_RANDOM_LEN;
+    if prefix.len() > max_prefix_len {
+        return Err(LabraError::RequestError(format!("商户订单号前缀长度不能超过{}，当前长度：{}", max_prefix_len, prefix.len())));
+    }
+    if !prefix.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'|' | b'*')) {
+        return Err(LabraError::RequestError(format!("商户订单号前缀只能包含数字、大小写字母及_-|*，当前前缀：{}", prefix)));
+    }
+    Ok(gen_out_trade_no(prefix))
+}
+
+fn gen_out_trade_no(prefix: &str) -> String {
+    let time = Utc::now().with_timezone(&beijing()).format("%Y%m%d%H%M%S").to_string();
+    let random = rand_string_with(OUT_TRADE_NO_MAX_LEN - prefix.len() - time.len(), RandCharset::Digits);
+    format!("{}{}{}", prefix, time, random)
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{nonce_str, out_trade_no, out_trade_no_with_prefix, rand_string, rand_string_with, RandCharset, OUT_TRADE_NO_MAX_LEN};
+
+    #[test]
+    fn test_rand_string_length_and_charset() {
+        for len in [0usize, 1, 16, 32, 100].iter() {
+            assert_eq!(rand_string(*len).len(), *len);
+            for charset in [RandCharset::Alphanumeric, RandCharset::Hex, RandCharset::Digits].iter() {
+                let s = rand_string_with(*len, *charset);
+                assert_eq!(s.len(), *len);
+                assert!(s.bytes().all(|b| charset.chars().contains(&b)), "{:?} {}", charset, s);
+            }
+        }
+        assert!(rand_string_with(1000, RandCharset::Hex).bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+        assert!(rand_string_with(1000, RandCharset::Digits).bytes().all(|b| b.is_ascii_digit()));
+        assert!(rand_string(1000).bytes().all(|b| b.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_rand_string_uniqueness() {
+        let samples = (0..10000).map(|_| nonce_str()).collect::<HashSet<String>>();
+        assert_eq!(samples.len(), 10000);
+        assert!(samples.iter().all(|v| v.len() == 32));
+        // 多线程下同样不重复
+        let handles = (0..4).map(|_| std::thread::spawn(|| (0..2000).map(|_| rand_string(16)).collect::<Vec<String>>())).collect::<Vec<_>>();
+        let all = handles.into_iter().flat_map(|h| h.join().unwrap()).collect::<HashSet<String>>();
+        assert_eq!(all.len(), 8000);
+    }
+
+    #[test]
+    fn test_out_trade_no() {
+        let samples = (0..10000).map(|_| out_trade_no()).collect::<HashSet<String>>();
+        assert_eq!(samples.len(), 10000);
+        for no in samples.iter() {
+            assert_eq!(no.len(), OUT_TRADE_NO_MAX_LEN);
+            assert!(no.bytes().all(|b| b.is_ascii_digit()));
+            assert!(no.starts_with("20"));
+        }
+        let no = out_trade_no_with_prefix("REFUND_").unwrap();
+        assert!(no.starts_with("REFUND_"));
+        assert_eq!(no.len(), OUT_TRADE_NO_MAX_LEN);
+        assert_eq!(out_trade_no_with_prefix("ABCDEFGHIJKL").unwrap().len(), OUT_TRADE_NO_MAX_LEN);
+        assert!(out_trade_no_with_prefix("ABCDEFGHIJKLM").is_err());
+        assert!(out_trade_no_with_prefix("订单").is_err());
+        assert!(out_trade_no_with_prefix("a b").is_err());
+    }
+}
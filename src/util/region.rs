@@ -0,0 +1,379 @@
+//!
+//! 行政区划代码
+//!
+//! 物流、发票、商户进件等接口要求传入行政区划代码或规范的省/市/区县名称，名称写法不一致（如“广西”与“广西壮族自治区”、
+//! 直辖市的“市辖区”、省直辖县级市）会导致请求被拒绝。这里提供按名称查询代码、按代码查询名称，以及将自由填写的地址
+//! 拆分为省、市、区县与详细地址的工具。
+//!
+//! 内置的区划表（`region.txt`）仅包含省级行政区及常用的地级、县级行政区，完整的区划表可通过[`RegionTable::parse`]
+//! 加载国家统计局发布的数据。
+//!
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+
+use crate::{LabraError, LabradorResult};
+
+static BUILTIN: Lazy<RegionTable> = Lazy::new(|| RegionTable::parse(include_str!("region.txt")).unwrap_or_default());
+
+/// 直辖市与省直辖县级行政区的虚拟地级区划名称
+const VIRTUAL_CITIES: [&str; 4] = ["市辖区", "县", "省直辖县级行政区划", "自治区直辖县级行政区划"];
+/// 去掉后可作为简称的后缀，按长度从长到短排列
+const SUFFIXES: [&str; 14] = ["特别行政区", "维吾尔自治区", "壮族自治区", "回族自治区", "自治区", "自治州", "自治县", "地区", "省", "市", "盟", "州", "区", "县"];
+/// 自治州、自治县名称中的民族，简称为民族之前的部分，如“延边朝鲜族自治州”简称“延边”
+const ETHNIC_GROUPS: [&str; 20] = [
+    "朝鲜族", "土家族", "苗族", "白族", "藏族", "羌族", "彝族", "傣族", "回族", "哈萨克", "蒙古族", "布依族",
+    "侗族", "壮族", "哈尼族", "景颇族", "傈僳族", "柯尔克孜", "黎族", "瑶族",
+];
+
+/// 解析后的地址
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedAddress {
+    /// 省级名称，如“北京市”、“广东省”
+    pub province: String,
+    /// 地级名称，直辖市与省级名称相同，省直辖县级市为该县级市的名称
+    pub city: String,
+    /// 县级名称，不设区的地级市、省直辖县级市为空
+    pub district: String,
+    /// 区县之后的详细地址
+    pub detail: String,
+    /// 识别到的最小一级行政区划代码
+    pub code: Option<u32>,
+}
+
+/// 行政区划表
+#[derive(Debug, Clone, Default)]
+pub struct RegionTable {
+    regions: BTreeMap<u32, String>,
+}
+
+impl RegionTable {
+    /// 内置的区划表
+    pub fn builtin() -> &'static RegionTable {
+        &BUILTIN
+    }
+
+    /// <pre>
+    /// 加载区划表
+    /// 每行为6位代码与名称，以空白或逗号分隔；空行与`#`开头的行忽略
+    /// </pre>
+    pub fn parse(data: &str) -> LabradorResult<Self> {
+        let mut regions = BTreeMap::new();
+        for line in data.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let mut parts = line.splitn(2, |c: char| c.is_whitespace() || c == ',');
+            let code = parts.next().and_then(|code| code.parse::<u32>().ok()).filter(|code| (100000..1000000).contains(code));
+            let name = parts.next().map(|name| name.trim_matches(|c: char| c.is_whitespace() || c == ',')).filter(|name| !name.is_empty());
+            match (code, name) {
+                (Some(code), Some(name)) => { regions.insert(code, name.to_string()); }
+                _ => return Err(LabraError::DecodeError(format!("区划表格式有误：{}", line).into())),
+            }
+        }
+        Ok(RegionTable { regions })
+    }
+
+    /// 按代码查询名称
+    pub fn name_for(&self, code: u32) -> Option<&str> {
+        self.regions.get(&code).map(String::as_str)
+    }
+
+    /// <pre>
+    /// 按省、市、区县名称查询代码，返回最小一级的代码
+    /// 名称支持简称（如“广西”、“延边州”、“浦东”），直辖市的市名可填省级名称或“市辖区”，
+    /// 省直辖县级市可直接填在市一级；末尾的层级可以为空，如不设区的地级市只需填写省、市
+    /// </pre>
+    pub fn code_for(&self, province: &str, city: &str, district: &str) -> Option<u32> {
+        let province = self.provinces().find(|(_, name)| name_matches(province, name))?.0;
+        let (city, district) = (city.trim(), district.trim());
+        let cities = if city.is_empty() || is_virtual_city(city) || self.is_municipality(province) && name_matches(city, self.name_for(province)?) {
+            self.virtual_cities(province)
+        } else {
+            match self.cities(province).find(|(_, name)| name_matches(city, name)) {
+                Some((code, _)) => vec![code],
+                None => {
+                    // 省直辖县级市
+                    let county = self.virtual_cities(province).into_iter().flat_map(|code| self.districts(code))
+                        .find(|(_, name)| name_matches(city, name))?.0;
+                    return if district.is_empty() { Some(county) } else { None };
+                }
+            }
+        };
+        if district.is_empty() {
+            return if city.is_empty() || cities.len() != 1 || self.is_municipality(province) { Some(province) } else { cities.first().copied() };
+        }
+        cities.into_iter().flat_map(|code| self.districts(code)).find(|(_, name)| name_matches(district, name)).map(|(code, _)| code)
+    }
+
+    /// <pre>
+    /// 拆分自由填写的地址
+    /// 依次识别省、市、区县，省级缺失时按市级推断，市级缺失时按区县推断，其余部分作为详细地址
+    /// </pre>
+    pub fn normalize_address(&self, raw: &str) -> ParsedAddress {
+        let mut rest = trim_separators(raw);
+        let mut address = ParsedAddress::default();
+        let mut province = longest_prefix(rest, self.provinces(), province_variants);
+        let mut city = None;
+        if let Some((_, len)) = province {
+            rest = trim_separators(&rest[len..]);
+        } else if let Some((code, len)) = longest_prefix(rest, self.all_cities(), city_variants) {
+            // 省级缺失
+            province = Some((code / 10000 * 10000, 0));
+            city = Some(code);
+            rest = trim_separators(&rest[len..]);
+        }
+        let province = match province {
+            Some((code, _)) => code,
+            None => {
+                address.detail = rest.to_string();
+                return address;
+            }
+        };
+        address.province = self.name_for(province).unwrap_or_default().to_string();
+        address.code = Some(province);
+
+        let mut cities = match city {
+            Some(code) => vec![code],
+            None if self.is_municipality(province) => {
+                // 直辖市：跳过重复的市名或“市辖区”
+                let name = address.province.to_string();
+                for prefix in province_variants(&name).iter().map(String::as_str).chain(VIRTUAL_CITIES.iter().copied()) {
+                    if let Some(stripped) = rest.strip_prefix(prefix) {
+                        rest = trim_separators(stripped);
+                        break;
+                    }
+                }
+                self.virtual_cities(province)
+            }
+            None => match longest_prefix(rest, self.cities(province), city_variants) {
+                Some((code, len)) => {
+                    rest = trim_separators(&rest[len..]);
+                    vec![code]
+                }
+                None => Vec::new(),
+            },
+        };
+        if cities.is_empty() {
+            // 省直辖县级市
+            let counties = self.virtual_cities(province).into_iter().flat_map(|code| self.districts(code)).collect::<Vec<_>>();
+            if let Some((code, len)) = longest_prefix(rest, counties.into_iter(), district_variants) {
+                address.city = self.name_for(code).unwrap_or_default().to_string();
+                address.code = Some(code);
+                address.detail = trim_separators(&rest[len..]).to_string();
+                return address;
+            }
+            // 市级缺失时按区县推断
+            let districts = self.cities(province).flat_map(|(code, _)| self.districts(code)).collect::<Vec<_>>();
+            match longest_prefix(rest, districts.into_iter(), district_variants) {
+                Some((code, _)) => cities.push(code / 100 * 100),
+                None => {
+                    address.detail = rest.to_string();
+                    return address;
+                }
+            }
+        }
+        address.city = if self.is_municipality(province) { address.province.to_string() } else { self.name_for(cities[0]).unwrap_or_default().to_string() };
+        if !self.is_municipality(province) {
+            address.code = Some(cities[0]);
+        }
+
+        let districts = cities.iter().flat_map(|code| self.districts(*code)).collect::<Vec<_>>();
+        if let Some((code, len)) = longest_prefix(rest, districts.into_iter(), district_variants) {
+            address.district = self.name_for(code).unwrap_or_default().to_string();
+            address.code = Some(code);
+            rest = trim_separators(&rest[len..]);
+        }
+        address.detail = rest.to_string();
+        address
+    }
+
+    fn provinces(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.regions.iter().filter(|(code, _)| *code % 10000 == 0).map(|(code, name)| (*code, name.as_str()))
+    }
+
+    /// 省级下的地级行政区，不含虚拟的地级区划
+    fn cities(&self, province: u32) -> impl Iterator<Item = (u32, &str)> {
+        self.regions.range(province + 1..province + 10000)
+            .filter(|(code, name)| *code % 100 == 0 && !is_virtual_city(name))
+            .map(|(code, name)| (*code, name.as_str()))
+    }
+
+    fn all_cities(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.regions.iter()
+            .filter(|(code, name)| *code % 100 == 0 && *code % 10000 != 0 && !is_virtual_city(name))
+            .map(|(code, name)| (*code, name.as_str()))
+    }
+
+    /// 直辖市的“市辖区”、“县”及省直辖县级行政区划
+    fn virtual_cities(&self, province: u32) -> Vec<u32> {
+        self.regions.range(province + 1..province + 10000)
+            .filter(|(code, name)| *code % 100 == 0 && is_virtual_city(name))
+            .map(|(code, _)| *code)
+            .collect()
+    }
+
+    fn districts(&self, city: u32) -> impl Iterator<Item = (u32, &str)> {
+        self.regions.range(city + 1..city + 100).map(|(code, name)| (*code, name.as_str()))
+    }
+
+    fn is_municipality(&self, province: u32) -> bool {
+        matches!(province, 110000 | 120000 | 310000 | 500000)
+    }
+}
+
+/// 按内置区划表查询代码，见[`RegionTable::code_for`]
+pub fn code_for(province: &str, city: &str, district: &str) -> Option<u32> {
+    RegionTable::builtin().code_for(province, city, district)
+}
+
+/// 按内置区划表查询名称
+pub fn name_for(code: u32) -> Option<&'static str> {
+    RegionTable::builtin().name_for(code)
+}
+
+/// 按内置区划表拆分地址，见[`RegionTable::normalize_address`]
+pub fn normalize_address(raw: &str) -> ParsedAddress {
+    RegionTable::builtin().normalize_address(raw)
+}
+
+fn is_virtual_city(name: &str) -> bool {
+    VIRTUAL_CITIES.contains(&name)
+}
+
+fn trim_separators(s: &str) -> &str {
+    s.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '，' | '-' | '/'))
+}
+
+/// 简称：民族自治地方取民族之前的部分，其余去掉行政区划后缀
+fn short_name(name: &str) -> &str {
+    if let Some(index) = ETHNIC_GROUPS.iter().filter_map(|group| name.find(group)).filter(|index| *index > 0).min() {
+        return &name[..index];
+    }
+    SUFFIXES.iter().find_map(|suffix| name.strip_suffix(suffix)).unwrap_or(name)
+}
+
+fn name_matches(query: &str, name: &str) -> bool {
+    let query = query.trim();
+    if query.is_empty() {
+        return false;
+    }
+    if query == name {
+        return true;
+    }
+    let short = short_name(name);
+    short.chars().count() >= 2 && short_name(query) == short
+}
+
+fn province_variants(name: &str) -> Vec<String> {
+    let mut variants = vec![name.to_string()];
+    let short = short_name(name);
+    if short != name && short.chars().count() >= 2 {
+        variants.push(short.to_string());
+    }
+    variants
+}
+
+fn city_variants(name: &str) -> Vec<String> {
+    let mut variants = vec![name.to_string()];
+    let short = short_name(name);
+    if short != name && short.chars().count() >= 2 {
+        if name.ends_with("自治州") {
+            variants.push(format!("{}州", short));
+        }
+        variants.push(short.to_string());
+    }
+    variants
+}
+
+/// 区县只按全称识别，避免将“朝阳路”识别为朝阳区
+fn district_variants(name: &str) -> Vec<String> {
+    vec![name.to_string()]
+}
+
+/// 在候选中找出与`s`开头匹配的最长名称，返回代码与匹配的字节长度
+fn longest_prefix<'a, I, F>(s: &str, candidates: I, variants: F) -> Option<(u32, usize)>
+    where I: Iterator<Item = (u32, &'a str)>, F: Fn(&str) -> Vec<String> {
+    candidates.filter_map(|(code, name)| {
+        variants(name).into_iter().filter(|variant| s.starts_with(variant.as_str())).map(|variant| (code, variant.len())).max_by_key(|(_, len)| *len)
+    }).max_by_key(|(_, len)| *len)
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use super::{code_for, name_for, normalize_address, ParsedAddress, RegionTable};
+
+    fn parsed(province: &str, city: &str, district: &str, detail: &str, code: u32) -> ParsedAddress {
+        ParsedAddress { province: province.to_string(), city: city.to_string(), district: district.to_string(), detail: detail.to_string(), code: Some(code) }
+    }
+
+    #[test]
+    fn test_code_for() {
+        assert_eq!(code_for("广东省", "深圳市", "南山区"), Some(440305));
+        assert_eq!(code_for("广东", "深圳", "南山"), Some(440305));
+        // 直辖市
+        assert_eq!(code_for("北京市", "北京市", "朝阳区"), Some(110105));
+        assert_eq!(code_for("北京", "市辖区", "朝阳区"), Some(110105));
+        assert_eq!(code_for("上海市", "", "浦东新区"), Some(310115));
+        assert_eq!(code_for("重庆市", "重庆市", "石柱土家族自治县"), Some(500240));
+        assert_eq!(code_for("重庆市", "重庆市", ""), Some(500000));
+        // 同名区县按所属城市区分
+        assert_eq!(code_for("江苏省", "南京市", "鼓楼区"), Some(320106));
+        assert_eq!(code_for("福建省", "福州市", "鼓楼区"), Some(350102));
+        assert_eq!(code_for("天津市", "天津市", "和平区"), Some(120101));
+        // 自治区、自治州、盟
+        assert_eq!(code_for("广西", "南宁市", ""), Some(450100));
+        assert_eq!(code_for("吉林省", "延边州", "延吉市"), Some(222401));
+        assert_eq!(code_for("湖北省", "恩施土家族苗族自治州", "利川市"), Some(422802));
+        assert_eq!(code_for("内蒙古", "锡林郭勒盟", "锡林浩特市"), Some(152502));
+        // 省直辖县级市
+        assert_eq!(code_for("湖北省", "仙桃市", ""), Some(429004));
+        assert_eq!(code_for("湖北省", "省直辖县级行政区划", "仙桃市"), Some(429004));
+        assert_eq!(code_for("新疆", "石河子市", ""), Some(659001));
+        // 不设区的地级市
+        assert_eq!(code_for("广东省", "东莞市", ""), Some(441900));
+        assert_eq!(code_for("广东省", "东莞市", "长安镇"), None);
+        assert_eq!(code_for("广东省", "杭州市", ""), None);
+        assert_eq!(code_for("", "深圳市", "南山区"), None);
+    }
+
+    #[test]
+    fn test_name_for() {
+        assert_eq!(name_for(440305), Some("南山区"));
+        assert_eq!(name_for(110100), Some("市辖区"));
+        assert_eq!(name_for(540000), Some("西藏自治区"));
+        assert_eq!(name_for(999999), None);
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        let cases = vec![
+            ("广东省深圳市南山区科技园科苑路15号", parsed("广东省", "深圳市", "南山区", "科技园科苑路15号", 440305)),
+            ("北京市朝阳区建国路88号", parsed("北京市", "北京市", "朝阳区", "建国路88号", 110105)),
+            ("北京市市辖区东城区东长安街1号", parsed("北京市", "北京市", "东城区", "东长安街1号", 110101)),
+            ("上海浦东新区世纪大道100号", parsed("上海市", "上海市", "浦东新区", "世纪大道100号", 310115)),
+            ("重庆市 渝中区 解放碑步行街", parsed("重庆市", "重庆市", "渝中区", "解放碑步行街", 500103)),
+            ("吉林省延边朝鲜族自治州延吉市人民路1号", parsed("吉林省", "延边朝鲜族自治州", "延吉市", "人民路1号", 222401)),
+            ("云南大理州大理市古城复兴路", parsed("云南省", "大理白族自治州", "大理市", "古城复兴路", 532901)),
+            ("四川省阿坝州九寨沟县漳扎镇", parsed("四川省", "阿坝藏族羌族自治州", "九寨沟县", "漳扎镇", 513225)),
+            ("广西壮族自治区南宁市青秀区民族大道", parsed("广西壮族自治区", "南宁市", "", "青秀区民族大道", 450100)),
+            ("广东省东莞市长安镇长青路", parsed("广东省", "东莞市", "", "长安镇长青路", 441900)),
+            ("湖北省仙桃市沔州大道", parsed("湖北省", "仙桃市", "", "沔州大道", 429004)),
+            ("海南省琼海市嘉积镇", parsed("海南省", "琼海市", "", "嘉积镇", 469002)),
+            ("深圳市福田区深南大道", parsed("广东省", "深圳市", "福田区", "深南大道", 440304)),
+            ("浙江省西湖区文三路", parsed("浙江省", "杭州市", "西湖区", "文三路", 330106)),
+            ("北京朝阳路1号", parsed("北京市", "北京市", "", "朝阳路1号", 110000)),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(normalize_address(raw), expected, "{}", raw);
+        }
+        assert_eq!(normalize_address("火星基地1号"), ParsedAddress { detail: "火星基地1号".to_string(), ..Default::default() });
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let table = RegionTable::parse("# 测试\n440000,广东省\n440300 深圳市\n\n440305\t南山区\n").unwrap();
+        assert_eq!(table.code_for("广东", "深圳", "南山区"), Some(440305));
+        assert!(table.code_for("北京市", "", "").is_none());
+        assert!(RegionTable::parse("44030 深圳市").is_err());
+        assert!(RegionTable::parse("440300").is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//!
+//! serde辅助工具
+//!
+//! 微信部分接口会不定期把数字以字符串形式返回（如`"errcode":"0"`、`"total":"42"`），
+//! 布尔值以0/1返回，列表以逗号分隔的字符串返回，这里统一做兼容处理。
+//!
+//! 时间字段统一使用chrono：公众号、企业微信等接口的秒级时间戳对应`DateTime<Utc>`，
+//! 微信支付V3的RFC3339时间（带+08:00时区）对应`DateTime<FixedOffset>`。
+//!
+//! # 示例
+//!
+//! ```no_run
+//! # use serde::{Serialize, Deserialize};
+//! # use labrador::serde_helper::{string_or_number, option_string_or_number, bool_from_int};
+//! #[derive(Serialize, Deserialize)]
+//! struct Response {
+//!     #[serde(with = "string_or_number")]
+//!     total: i64,
+//!     #[serde(default, with = "option_string_or_number")]
+//!     count: Option<i64>,
+//!     #[serde(with = "bool_from_int")]
+//!     subscribe: bool,
+//! }
+//! ```
+//!
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Deserializer};
+
+use crate::{LabraError, LabradorResult};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrValue<T> {
+    String(String),
+    Value(T),
+}
+
+/// 兼容数字或数字字符串
+pub mod string_or_number {
+    use super::*;
+    use serde::{de, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where D: Deserializer<'de>, T: FromStr + Deserialize<'de>, T::Err: Display {
+        match StringOrValue::<T>::deserialize(deserializer)? {
+            StringOrValue::String(v) => v.trim().parse::<T>().map_err(de::Error::custom),
+            StringOrValue::Value(v) => Ok(v),
+        }
+    }
+}
+
+/// 兼容可选的数字或数字字符串，`null`与空字符串均视为`None`
+///
+/// 字段缺失时需配合`#[serde(default)]`使用
+pub mod option_string_or_number {
+    use super::*;
+    use serde::{de, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where D: Deserializer<'de>, T: FromStr + Deserialize<'de>, T::Err: Display {
+        match Option::<StringOrValue<T>>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(StringOrValue::String(v)) if v.trim().is_empty() => Ok(None),
+            Some(StringOrValue::String(v)) => v.trim().parse::<T>().map(Some).map_err(de::Error::custom),
+            Some(StringOrValue::Value(v)) => Ok(Some(v)),
+        }
+    }
+}
+
+/// 兼容以0/1（或"0"/"1"）表示的布尔值
+pub mod bool_from_int {
+    use super::*;
+    use serde::{de, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrInt {
+        Bool(bool),
+        Int(i64),
+        String(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(*value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        match BoolOrInt::deserialize(deserializer)? {
+            BoolOrInt::Bool(v) => Ok(v),
+            BoolOrInt::Int(v) => Ok(v != 0),
+            BoolOrInt::String(v) => match v.trim() {
+                "1" | "true" => Ok(true),
+                "0" | "false" | "" => Ok(false),
+                other => Err(de::Error::custom(format!("invalid bool value: {}", other))),
+            },
+        }
+    }
+}
+
+/// 兼容逗号分隔的字符串或数组
+pub mod comma_separated_list {
+    use super::*;
+    use serde::{de, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &Vec<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+        where D: Deserializer<'de>, T: FromStr + Deserialize<'de>, T::Err: Display {
+        match StringOrValue::<Vec<T>>::deserialize(deserializer)? {
+            StringOrValue::String(v) => v.split(',')
+                .map(|item| item.trim())
+                .filter(|item| !item.is_empty())
+                .map(|item| item.parse::<T>().map_err(de::Error::custom))
+                .collect(),
+            StringOrValue::Value(v) => Ok(v),
+        }
+    }
+}
+
+/// 以JSON字符串形式嵌套的字段，如附近小程序的`pic_list`：`"{\"list\":[...]}"`
+///
+/// 序列化时先编码为JSON字符串，反序列化时兼容JSON字符串或直接的对象
+pub mod json_string {
+    use super::*;
+    use serde::{de, ser, Serialize, Serializer};
+    use serde::de::DeserializeOwned;
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        let v = serde_json::to_string(value).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&v)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where D: Deserializer<'de>, T: DeserializeOwned {
+        match StringOrValue::<T>::deserialize(deserializer)? {
+            StringOrValue::String(v) => serde_json::from_str::<T>(&v).map_err(de::Error::custom),
+            StringOrValue::Value(v) => Ok(v),
+        }
+    }
+}
+
+/// 可选的以JSON字符串形式嵌套的字段，`null`与空字符串均视为`None`
+///
+/// 字段缺失时需配合`#[serde(default)]`使用，序列化时建议配合`skip_serializing_if = "Option::is_none"`
+pub mod option_json_string {
+    use super::*;
+    use serde::{de, Serialize, Serializer};
+    use serde::de::DeserializeOwned;
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => json_string::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where D: Deserializer<'de>, T: DeserializeOwned {
+        match Option::<StringOrValue<T>>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(StringOrValue::String(v)) if v.trim().is_empty() => Ok(None),
+            Some(StringOrValue::String(v)) => serde_json::from_str::<T>(&v).map(Some).map_err(de::Error::custom),
+            Some(StringOrValue::Value(v)) => Ok(Some(v)),
+        }
+    }
+}
+
+/// 秒级时间戳的上限（公元5138年），超过时基本可以断定误传了毫秒
+const MAX_TIMESTAMP_SECONDS: i64 = 99_999_999_999;
+
+/// 秒级时间戳转为`DateTime<Utc>`
+///
+/// 微信的CreateTime等字段均为秒，误传毫秒级时间戳时返回错误，而不是得到一个几万年后的时间
+pub fn datetime_from_seconds(seconds: i64) -> LabradorResult<DateTime<Utc>> {
+    if !(-MAX_TIMESTAMP_SECONDS..=MAX_TIMESTAMP_SECONDS).contains(&seconds) {
+        return Err(LabraError::DecodeError(format!("timestamp {} is out of range, expected seconds but it looks like milliseconds", seconds).into()));
+    }
+    Utc.timestamp_opt(seconds, 0).single().ok_or_else(|| LabraError::DecodeError(format!("invalid timestamp: {}", seconds).into()))
+}
+
+/// 按微信支付V3的格式（北京时间，如`2018-06-08T10:34:56+08:00`）格式化时间，用于`time_expire`等请求字段
+pub fn format_rfc3339<Tz: TimeZone>(value: &DateTime<Tz>) -> String {
+    value.with_timezone(&beijing()).to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+pub(crate) fn beijing() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+/// 秒级时间戳与`DateTime<Utc>`互转，兼容数字字符串，毫秒级时间戳视为错误
+pub mod timestamp_seconds {
+    use super::*;
+    use serde::{de, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(value.timestamp())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        let seconds = super::string_or_number::deserialize::<i64, D>(deserializer)?;
+        datetime_from_seconds(seconds).map_err(de::Error::custom)
+    }
+}
+
+/// 可选的秒级时间戳，`null`、空字符串与0均视为`None`
+///
+/// 字段缺失时需配合`#[serde(default)]`使用
+pub mod option_timestamp_seconds {
+    use super::*;
+    use serde::{de, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(v) => serializer.serialize_some(&v.timestamp()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match super::option_string_or_number::deserialize::<i64, D>(deserializer)? {
+            None | Some(0) => Ok(None),
+            Some(seconds) => datetime_from_seconds(seconds).map(Some).map_err(de::Error::custom),
+        }
+    }
+}
+
+/// 以秒为单位的时长与`chrono::Duration`互转，兼容数字字符串
+pub mod duration_seconds {
+    use super::*;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(value.num_seconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let seconds = super::string_or_number::deserialize::<i64, D>(deserializer)?;
+        Ok(Duration::seconds(seconds))
+    }
+}
+
+/// 微信支付V3的RFC3339时间（如`2015-05-20T13:29:35+08:00`），保留原时区，序列化时按原格式输出
+pub mod rfc3339 {
+    use super::*;
+    use serde::{de, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &DateTime<FixedOffset>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<FixedOffset>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(value.trim()).map_err(|err| de::Error::custom(format!("invalid rfc3339 time {}: {}", value, err)))
+    }
+}
+
+/// 可选的RFC3339时间，`null`与空字符串均视为`None`
+///
+/// 字段缺失时需配合`#[serde(default)]`使用
+pub mod option_rfc3339 {
+    use super::*;
+    use serde::{de, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<DateTime<FixedOffset>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(v) => serializer.serialize_some(&v.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<FixedOffset>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) if !value.trim().is_empty() => DateTime::parse_from_rfc3339(value.trim()).map(Some).map_err(|err| de::Error::custom(format!("invalid rfc3339 time {}: {}", value, err))),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use serde::{Serialize, Deserialize};
+    use chrono::FixedOffset;
+    use super::{string_or_number, option_string_or_number, bool_from_int, comma_separated_list, timestamp_seconds, option_timestamp_seconds, duration_seconds, rfc3339, option_rfc3339, datetime_from_seconds, format_rfc3339};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Response {
+        #[serde(default, with = "option_string_or_number")]
+        errcode: Option<i64>,
+        #[serde(with = "string_or_number")]
+        total: u64,
+        #[serde(default, with = "option_string_or_number")]
+        count: Option<i32>,
+        #[serde(default, with = "option_string_or_number")]
+        expires_in: Option<i64>,
+        #[serde(with = "bool_from_int")]
+        subscribe: bool,
+        #[serde(with = "comma_separated_list")]
+        scope: Vec<String>,
+    }
+
+    #[test]
+    fn test_all_strings() {
+        let resp = serde_json::from_str::<Response>(r#"{"errcode":"0","total":"42","count":"","expires_in":" 7200 ","subscribe":"1","scope":"snsapi_base,snsapi_userinfo"}"#).unwrap();
+        assert_eq!(resp.errcode, Some(0));
+        assert_eq!(resp.total, 42);
+        assert_eq!(resp.count, None);
+        assert_eq!(resp.expires_in, Some(7200));
+        assert!(resp.subscribe);
+        assert_eq!(resp.scope, vec!["snsapi_base".to_string(), "snsapi_userinfo".to_string()]);
+    }
+
+    #[test]
+    fn test_native_values() {
+        let resp = serde_json::from_str::<Response>(r#"{"total":42,"count":null,"subscribe":0,"scope":["snsapi_base"]}"#).unwrap();
+        assert_eq!(resp.errcode, None);
+        assert_eq!(resp.total, 42);
+        assert_eq!(resp.count, None);
+        assert!(!resp.subscribe);
+        assert_eq!(resp.scope, vec!["snsapi_base".to_string()]);
+        let v = serde_json::to_value(&resp).unwrap();
+        assert_eq!(v["total"], 42);
+        assert_eq!(v["subscribe"], false);
+    }
+
+    #[test]
+    fn test_invalid_number() {
+        assert!(serde_json::from_str::<Response>(r#"{"total":"abc","subscribe":1,"scope":""}"#).is_err());
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "timestamp_seconds")]
+        time: DateTime<Utc>,
+        #[serde(default, with = "option_timestamp_seconds")]
+        sch_time: Option<DateTime<Utc>>,
+        #[serde(default, with = "duration_seconds")]
+        duration: chrono::Duration,
+    }
+
+    #[test]
+    fn test_timestamp_seconds() {
+        let record = serde_json::from_str::<Record>(r#"{"time":"1492617610","sch_time":0}"#).unwrap();
+        assert_eq!(record.time, Utc.timestamp_opt(1492617610, 0).unwrap());
+        assert_eq!(record.sch_time, None);
+        assert_eq!(record.duration, chrono::Duration::zero());
+        let record = serde_json::from_str::<Record>(r#"{"time":1492617610,"sch_time":1492617600,"duration":"43200"}"#).unwrap();
+        assert_eq!(record.sch_time, Some(Utc.timestamp_opt(1492617600, 0).unwrap()));
+        assert_eq!(record.duration, chrono::Duration::hours(12));
+        assert_eq!(serde_json::to_string(&record).unwrap(), r#"{"time":1492617610,"sch_time":1492617600,"duration":43200}"#);
+        assert!(serde_json::from_str::<Record>(r#"{"time":"abc"}"#).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_boundaries() {
+        assert_eq!(datetime_from_seconds(0).unwrap(), Utc.timestamp_opt(0, 0).unwrap());
+        assert_eq!(datetime_from_seconds(-1).unwrap().timestamp(), -1);
+        assert_eq!(datetime_from_seconds(99_999_999_999).unwrap().timestamp(), 99_999_999_999);
+        assert!(datetime_from_seconds(100_000_000_000).is_err());
+        assert!(datetime_from_seconds(i64::MAX).is_err());
+        assert!(datetime_from_seconds(i64::MIN).is_err());
+        // 误传毫秒级时间戳
+        let err = serde_json::from_str::<Record>(r#"{"time":1492617610123}"#).unwrap_err();
+        assert!(err.to_string().contains("milliseconds"));
+        assert!(serde_json::from_str::<Record>(r#"{"time":1492617610,"sch_time":"1492617600000"}"#).is_err());
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct PayRecord {
+        #[serde(with = "rfc3339")]
+        create_time: DateTime<FixedOffset>,
+        #[serde(default, with = "option_rfc3339")]
+        success_time: Option<DateTime<FixedOffset>>,
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let record = serde_json::from_str::<PayRecord>(r#"{"create_time":"2015-05-20T13:29:35+08:00","success_time":""}"#).unwrap();
+        assert_eq!(record.create_time.with_timezone(&Utc), Utc.with_ymd_and_hms(2015, 5, 20, 5, 29, 35).unwrap());
+        assert_eq!(record.create_time.offset().local_minus_utc(), 8 * 3600);
+        assert_eq!(record.success_time, None);
+        let record = serde_json::from_str::<PayRecord>(r#"{"create_time":"2015-05-20T13:29:35+08:00","success_time":"2015-05-20T13:30:00+08:00"}"#).unwrap();
+        assert_eq!(serde_json::to_string(&record).unwrap(), r#"{"create_time":"2015-05-20T13:29:35+08:00","success_time":"2015-05-20T13:30:00+08:00"}"#);
+        assert!(serde_json::from_str::<PayRecord>(r#"{"create_time":"2015-05-20 13:29:35"}"#).is_err());
+        assert!(serde_json::from_str::<PayRecord>(r#"{"create_time":1432099775}"#).is_err());
+        assert_eq!(format_rfc3339(&Utc.with_ymd_and_hms(2018, 6, 8, 2, 34, 56).unwrap()), "2018-06-08T10:34:56+08:00");
+        // 跨日
+        assert_eq!(format_rfc3339(&Utc.with_ymd_and_hms(2018, 12, 31, 16, 0, 0).unwrap()), "2019-01-01T00:00:00+08:00");
+    }
+
+    #[cfg(feature = "wechat-mp")]
+    #[test]
+    fn test_wechat_response_with_string_numbers() {
+        let resp = crate::WechatCommonResponse::from_str(r#"{"errcode":"40001","errmsg":"invalid credential"}"#).unwrap();
+        assert_eq!(resp.errcode, Some(40001));
+        assert!(!resp.is_success());
+        let user = serde_json::from_str::<crate::WechatUser>(r#"{"subscribe":"1","openid":"o6_bmjrPTlm6_2sgVt7hMZOPfL2M","nickname":"Band","sex":"1","language":"zh_CN","city":"广州","province":"广东","country":"中国","headimgurl":"","subscribe_time":"1382694957","unionid":"o6_bmasdasdsad6_2sgVt7hMZOPfL","remark":"","groupid":"0"}"#).unwrap();
+        assert!(user.subscribe);
+        assert_eq!(user.sex, 1);
+        assert_eq!(user.subscribe_time, Some(Utc.timestamp_opt(1382694957, 0).unwrap()));
+        assert_eq!(user.group_id, 0);
+    }
+}
@@ -0,0 +1,95 @@
+//!
+//! 签名排查
+//!
+//! "验签失败"时，对照签名串逐项检查最常见的错误：参数顺序、多余的签名参数、首尾空白、非UTF-8字符等。
+//! 报告中的签名串与签名均由正式请求使用的同一套逻辑生成。
+//!
+use std::fmt;
+
+/// 签名排查中发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignProblem {
+    /// 参数未按参数名ASCII码升序排列，签名串按排序后的顺序拼接
+    UnsortedKeys,
+    /// `sign`参数不参与签名
+    SignIncluded,
+    /// `sign_type`参数在当前场景下不参与签名（如支付宝异步通知验签）
+    SignTypeIncluded,
+    /// 参数值为空
+    EmptyValue(String),
+    /// 参数值首尾含空白字符
+    Whitespace(String),
+    /// 参数值含非UTF-8字节，签名串中已替换为U+FFFD
+    NonUtf8(String),
+    /// HTTP请求方法应为大写，如`POST`
+    LowercaseMethod(String),
+    /// URL应为不含域名的绝对路径（含查询参数），如`/v3/pay/transactions/native`
+    InvalidUrl(String),
+    /// GET请求的签名主体应为空
+    GetWithBody,
+}
+
+impl fmt::Display for SignProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignProblem::UnsortedKeys => write!(f, "参数未按参数名ASCII码升序排列"),
+            SignProblem::SignIncluded => write!(f, "sign参数不应参与签名"),
+            SignProblem::SignTypeIncluded => write!(f, "sign_type参数不应参与签名"),
+            SignProblem::EmptyValue(key) => write!(f, "参数{}的值为空", key),
+            SignProblem::Whitespace(key) => write!(f, "参数{}的值首尾含空白字符", key),
+            SignProblem::NonUtf8(key) => write!(f, "参数{}的值含非UTF-8字节", key),
+            SignProblem::LowercaseMethod(method) => write!(f, "请求方法{}应为大写", method),
+            SignProblem::InvalidUrl(url) => write!(f, "URL{}应为不含域名的绝对路径", url),
+            SignProblem::GetWithBody => write!(f, "GET请求的签名主体应为空"),
+        }
+    }
+}
+
+/// 签名排查报告
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignDebugReport {
+    /// 待签名串
+    pub sign_content: String,
+    /// base64编码的签名，验签场景下为待验证的签名
+    pub signature: String,
+    /// 发现的问题
+    pub problems: Vec<SignProblem>,
+}
+
+impl SignDebugReport {
+    /// 是否存在指定的问题
+    pub fn has(&self, problem: &SignProblem) -> bool {
+        self.problems.contains(problem)
+    }
+}
+
+impl fmt::Display for SignDebugReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "待签名串：{:?}", self.sign_content)?;
+        writeln!(f, "签名：{}", self.signature)?;
+        if self.problems.is_empty() {
+            write!(f, "未发现问题")
+        } else {
+            let problems = self.problems.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+            write!(f, "发现的问题：{}", problems.join("；"))
+        }
+    }
+}
+
+/// 将参数值转换为字符串，并检查值的常见问题
+#[allow(unused)]
+pub(crate) fn check_value(key: &str, value: &[u8], problems: &mut Vec<SignProblem>) -> String {
+    let value = match std::str::from_utf8(value) {
+        Ok(v) => v.to_string(),
+        Err(_) => {
+            problems.push(SignProblem::NonUtf8(key.to_string()));
+            String::from_utf8_lossy(value).to_string()
+        }
+    };
+    if value.is_empty() {
+        problems.push(SignProblem::EmptyValue(key.to_string()));
+    } else if value.trim() != value {
+        problems.push(SignProblem::Whitespace(key.to_string()));
+    }
+    value
+}
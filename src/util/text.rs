@@ -0,0 +1,170 @@
+//!
+//! 消息文本处理
+//!
+//! 微信各接口的文本长度限制多按UTF-8字节数计算（中文3字节、emoji 4字节），而不是字符数，
+//! 且会拒绝包含部分控制字符的内容。[`sanitize`]按[`TextPolicy`]去除控制字符与零宽字符，
+//! 并在超出长度限制时返回错误，或在字符边界截断并追加省略号。
+//!
+use crate::{LabradorResult, LabraError};
+
+/// 客服消息（公众号客服消息、企业微信微信客服）文本的最大字节数
+pub const KF_TEXT_MAX_BYTES: usize = 2048;
+/// 企业微信应用消息文本的最大字节数
+pub const CP_TEXT_MAX_BYTES: usize = 2048;
+
+/// 超出长度限制时的处理方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextOverflow {
+    /// 返回错误
+    Error,
+    /// 在字符边界截断，并追加指定的省略号（计入长度）
+    Truncate(String),
+}
+
+/// <pre>
+/// 文本处理策略
+/// 默认去除控制字符与零宽字符、不限制长度；超出长度限制时默认返回错误，需要截断时使用[`TextPolicy::truncate`]
+/// </pre>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPolicy {
+    /// 最大字节数（UTF-8）
+    pub max_bytes: Option<usize>,
+    pub overflow: TextOverflow,
+    /// 是否去除控制字符（保留换行、回车与制表符）与零宽字符
+    pub strip_control: bool,
+}
+
+impl Default for TextPolicy {
+    fn default() -> Self {
+        TextPolicy {
+            max_bytes: None,
+            overflow: TextOverflow::Error,
+            strip_control: true,
+        }
+    }
+}
+
+impl TextPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 客服消息文本，最多2048字节
+    pub fn kf_text() -> Self {
+        Self::new().max_bytes(KF_TEXT_MAX_BYTES)
+    }
+
+    /// 企业微信应用消息文本，最多2048字节
+    pub fn cp_text() -> Self {
+        Self::new().max_bytes(CP_TEXT_MAX_BYTES)
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes.into();
+        self
+    }
+
+    /// 超出长度时截断并追加省略号，如`…`
+    pub fn truncate<S: Into<String>>(mut self, ellipsis: S) -> Self {
+        self.overflow = TextOverflow::Truncate(ellipsis.into());
+        self
+    }
+
+    pub fn strip_control(mut self, strip_control: bool) -> Self {
+        self.strip_control = strip_control;
+        self
+    }
+}
+
+/// <pre>
+/// 按策略处理文本
+/// 零宽连接符（U+200D）用于组合emoji（如👨‍👩‍👧），不会被去除
+/// </pre>
+pub fn sanitize(content: &str, policy: &TextPolicy) -> LabradorResult<String> {
+    let content = if policy.strip_control {
+        content.chars().filter(|c| !is_stripped(*c)).collect::<String>()
+    } else {
+        content.to_string()
+    };
+    let max_bytes = match policy.max_bytes {
+        Some(v) if content.len() > v => v,
+        _ => return Ok(content),
+    };
+    match &policy.overflow {
+        TextOverflow::Error => Err(LabraError::RequestError(format!("内容长度为{}字节，超过了{}字节的限制", content.len(), max_bytes))),
+        TextOverflow::Truncate(ellipsis) => {
+            // 省略号本身放不下时直接截断
+            let (limit, ellipsis) = if ellipsis.len() <= max_bytes { (max_bytes - ellipsis.len(), ellipsis.as_str()) } else { (max_bytes, "") };
+            let mut truncated = content[..floor_char_boundary(&content, limit)].to_string();
+            // 不以半个组合emoji结尾
+            while truncated.ends_with('\u{200D}') {
+                truncated.pop();
+            }
+            truncated.push_str(ellipsis);
+            Ok(truncated)
+        }
+    }
+}
+
+fn is_stripped(c: char) -> bool {
+    match c {
+        '\n' | '\r' | '\t' => false,
+        '\u{200B}' | '\u{200C}' | '\u{2060}' | '\u{FEFF}' => true,
+        c => c.is_control(),
+    }
+}
+
+/// 不超过index的最大字符边界
+fn floor_char_boundary(content: &str, index: usize) -> usize {
+    let mut index = index.min(content.len());
+    while !content.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::LabraError;
+    use super::{sanitize, TextPolicy};
+
+    #[test]
+    fn test_truncate_multi_byte() {
+        // 每个汉字3字节
+        let policy = TextPolicy::new().max_bytes(10).truncate("…");
+        assert_eq!(sanitize("你好世界", &policy).unwrap(), "你好…");
+        assert_eq!(sanitize("你好世", &policy).unwrap(), "你好世");
+        assert_eq!(sanitize("ab你好世界", &policy).unwrap(), "ab你…");
+        assert!(sanitize("你好世界ab", &policy).unwrap().len() <= 10);
+        // 省略号放不下时直接截断
+        assert_eq!(sanitize("你好世界", &TextPolicy::new().max_bytes(2).truncate("…")).unwrap(), "");
+        assert_eq!(sanitize("ab你好", &TextPolicy::new().max_bytes(4).truncate("...")).unwrap(), "a...");
+
+        let err = sanitize("你好世界", &TextPolicy::new().max_bytes(10)).unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(ref msg) if msg.contains("12字节")), "{}", err);
+        let content = "中".repeat(682);
+        assert_eq!(sanitize(&content, &TextPolicy::kf_text()).unwrap(), content);
+        assert!(sanitize(&format!("{}abc", content), &TextPolicy::kf_text()).is_err());
+    }
+
+    #[test]
+    fn test_truncate_emoji() {
+        let policy = TextPolicy::new().max_bytes(9).truncate("");
+        // 每个emoji 4字节
+        assert_eq!(sanitize("😀😀😀", &policy).unwrap(), "😀😀");
+        assert_eq!(sanitize("a😀😀😀", &policy).unwrap(), "a😀😀");
+        // 截断处在零宽连接符之后时一并去掉
+        assert_eq!(sanitize("👨\u{200D}👩\u{200D}👧", &TextPolicy::new().max_bytes(8).truncate("")).unwrap(), "👨");
+        assert_eq!(sanitize("👨\u{200D}👩\u{200D}👧", &TextPolicy::new()).unwrap(), "👨\u{200D}👩\u{200D}👧");
+    }
+
+    #[test]
+    fn test_strip_control() {
+        let content = "你好\u{0}\u{7}\u{1b}[0m\u{7f}\u{200B}世界\u{FEFF}\n第二行\t\r";
+        assert_eq!(sanitize(content, &TextPolicy::new()).unwrap(), "你好[0m世界\n第二行\t\r");
+        assert_eq!(sanitize(content, &TextPolicy::new().strip_control(false)).unwrap(), content);
+        // 先去除再计算长度
+        assert_eq!(sanitize("\u{200B}\u{200B}你好", &TextPolicy::new().max_bytes(6)).unwrap(), "你好");
+    }
+}
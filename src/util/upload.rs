@@ -0,0 +1,117 @@
+//!
+//! 流式上传
+//!
+//! 大文件（如永久视频素材）不必整个读入内存：从`AsyncRead`边读边写入multipart请求体，
+//! 并可通过进度回调得知已发送的字节数。
+//!
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::BytesMut;
+use futures_util::stream;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+/// 每次从reader读取的块大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 上传进度回调，参数为已发送字节数与总字节数
+pub type UploadProgress = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// <pre>
+/// 统计读取进度的reader
+/// 每次读到数据后以（累计字节数，总字节数）调用回调，累计字节数单调递增
+/// </pre>
+pub struct ProgressReader<R> {
+    inner: R,
+    sent: u64,
+    total: u64,
+    progress: UploadProgress,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, total: u64, progress: UploadProgress) -> Self {
+        ProgressReader {
+            inner,
+            sent: 0,
+            total,
+            progress,
+        }
+    }
+
+    /// 已读取的字节数
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+}
+
+impl<R> fmt::Debug for ProgressReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressReader").field("sent", &self.sent).field("total", &self.total).finish()
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+        if read > 0 {
+            self.sent += read;
+            (self.progress)(self.sent, self.total);
+        }
+        poll
+    }
+}
+
+/// <pre>
+/// 将reader转换为长度已知的请求体
+/// 读到的数据超过`length`时截断，不足时请求体以实际读到的为准（服务端会因长度不符而拒绝）
+/// </pre>
+pub(crate) fn reader_body<R: AsyncRead + Send + Unpin + 'static>(reader: R, length: u64) -> reqwest::Body {
+    let reader = reader.take(length);
+    let chunks = stream::unfold(reader, |mut reader| async move {
+        let mut buf = BytesMut::with_capacity(CHUNK_SIZE);
+        match reader.read_buf(&mut buf).await {
+            Ok(0) => None,
+            Ok(_) => Some((Ok::<_, io::Error>(buf.freeze()), reader)),
+            Err(err) => Some((Err(err), reader)),
+        }
+    });
+    hyper::Body::wrap_stream(chunks).into()
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::AsyncReadExt;
+
+    use super::ProgressReader;
+
+    #[tokio::test]
+    async fn test_progress_reader() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let received = calls.clone();
+        let mut reader = ProgressReader::new(Cursor::new(vec![1u8; 100_000]), 100_000, Arc::new(move |sent, total| received.lock().unwrap().push((sent, total))));
+        let mut buf = [0u8; 4096];
+        let mut read = 0;
+        loop {
+            let n = reader.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+        assert_eq!(read, 100_000);
+        assert_eq!(reader.sent(), 100_000);
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 25);
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(calls.last(), Some(&(100_000, 100_000)));
+    }
+}
@@ -0,0 +1,234 @@
+//!
+//! 类目树
+//!
+//! 门店、附近的小程序等接口需要填写类目id，获取类目的接口返回的是按父节点id（`father`）关联的扁平列表，
+//! [`CategoryTree`]据此在本地建树并提供按名称、按id的查询。类目变化很少，可以通过会话存储缓存24小时。
+//!
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{session::SessionStore, LabradorResult};
+
+/// 类目树的缓存时间（秒）
+pub(crate) const CATEGORY_TREE_CACHE_SECONDS: usize = 24 * 60 * 60;
+
+/// 类目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCategory {
+    /// 类目id
+    pub id: i64,
+    /// 类目名称
+    pub name: String,
+    /// 父类目id，顶级类目为空
+    #[serde(default, alias = "father", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
+    /// 类目层级，从1开始
+    #[serde(default)]
+    pub level: i32,
+}
+
+/// <pre>
+/// 由扁平的类目列表构建的类目树
+/// 父类目不存在的类目作为顶级类目；存在环时，环中id最小的类目作为顶级类目，其余类目的父子关系保持不变
+/// </pre>
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CategoryTree {
+    categories: BTreeMap<i64, WechatCategory>,
+    /// 实际采用的父类目，已去除孤立与成环的关联
+    parents: BTreeMap<i64, i64>,
+    children: BTreeMap<i64, Vec<i64>>,
+    roots: Vec<i64>,
+}
+
+impl CategoryTree {
+    /// 由类目列表构建，id重复时保留第一个
+    pub fn from_categories<I: IntoIterator<Item = WechatCategory>>(categories: I) -> Self {
+        let mut tree = CategoryTree::default();
+        for category in categories {
+            tree.categories.entry(category.id).or_insert(category);
+        }
+        let mut parents = tree.categories.values()
+            .filter_map(|v| v.parent_id.filter(|parent_id| *parent_id != v.id && tree.categories.contains_key(parent_id)).map(|parent_id| (v.id, parent_id)))
+            .collect::<BTreeMap<_, _>>();
+        // 沿父类目向上查找，遇到本轮已经过的类目即为环
+        let mut checked = BTreeSet::new();
+        for id in tree.categories.keys() {
+            let mut path = Vec::new();
+            let mut current = Some(*id);
+            while let Some(v) = current {
+                if checked.contains(&v) {
+                    break;
+                }
+                if let Some(start) = path.iter().position(|p| *p == v) {
+                    if let Some(min) = path[start..].iter().min() {
+                        parents.remove(min);
+                    }
+                    break;
+                }
+                path.push(v);
+                current = parents.get(&v).copied();
+            }
+            checked.extend(path);
+        }
+        for id in tree.categories.keys() {
+            match parents.get(id) {
+                Some(parent_id) => tree.children.entry(*parent_id).or_default().push(*id),
+                None => tree.roots.push(*id),
+            }
+        }
+        tree.parents = parents;
+        tree
+    }
+
+    /// <pre>
+    /// 由逗号分隔的类目路径构建，如公众号门店类目`美食,江浙菜,上海菜`
+    /// 这类接口不返回类目id，id按类目首次出现的顺序从1开始编号
+    /// </pre>
+    pub fn from_paths<I: IntoIterator<Item = S>, S: AsRef<str>>(paths: I) -> Self {
+        let mut ids = BTreeMap::<Vec<String>, i64>::new();
+        let mut categories = Vec::new();
+        for path in paths {
+            let names = path.as_ref().split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect::<Vec<_>>();
+            let mut parent_id = None;
+            for level in 1..=names.len() {
+                let key = names[..level].to_vec();
+                let id = match ids.get(&key) {
+                    Some(id) => *id,
+                    None => {
+                        let id = ids.len() as i64 + 1;
+                        ids.insert(key, id);
+                        categories.push(WechatCategory { id, name: names[level - 1].to_string(), parent_id, level: level as i32 });
+                        id
+                    }
+                };
+                parent_id = Some(id);
+            }
+        }
+        Self::from_categories(categories)
+    }
+
+    /// 全部类目，按id排序
+    pub fn categories(&self) -> impl Iterator<Item = &WechatCategory> {
+        self.categories.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.categories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+
+    pub fn get(&self, id: i64) -> Option<&WechatCategory> {
+        self.categories.get(&id)
+    }
+
+    /// 顶级类目
+    pub fn roots(&self) -> Vec<&WechatCategory> {
+        self.roots.iter().filter_map(|id| self.categories.get(id)).collect()
+    }
+
+    /// 直接子类目
+    pub fn children(&self, id: i64) -> Vec<&WechatCategory> {
+        self.children.get(&id).map(|ids| ids.iter().filter_map(|id| self.categories.get(id)).collect()).unwrap_or_default()
+    }
+
+    /// 按名称查找，不同父类目下可能有同名类目（如“其他”），因此返回全部匹配的类目
+    pub fn find_by_name(&self, name: &str) -> Vec<&WechatCategory> {
+        self.categories.values().filter(|v| v.name == name).collect()
+    }
+
+    /// 从顶级类目到该类目的路径，类目不存在时为空
+    pub fn path_for(&self, id: i64) -> Vec<&WechatCategory> {
+        let mut path = Vec::new();
+        let mut current = self.categories.get(&id);
+        while let Some(category) = current {
+            path.push(category);
+            current = self.parents.get(&category.id).and_then(|parent_id| self.categories.get(parent_id));
+        }
+        path.reverse();
+        path
+    }
+
+    /// 没有子类目的类目，通常只有末级类目可以提交
+    pub fn leaf_categories(&self) -> Vec<&WechatCategory> {
+        self.categories.values().filter(|v| !self.children.contains_key(&v.id)).collect()
+    }
+
+    /// 读取缓存的类目树
+    pub(crate) fn load<T: SessionStore>(session: &T, key: &str) -> LabradorResult<Option<Self>> {
+        let cached = session.get::<_, String>(key, None)?.unwrap_or_default();
+        if cached.is_empty() {
+            return Ok(None);
+        }
+        Ok(serde_json::from_str::<Vec<WechatCategory>>(&cached).ok().map(Self::from_categories))
+    }
+
+    /// 缓存类目树，保存原始的类目列表，读取时重新建树
+    pub(crate) fn store<T: SessionStore>(&self, session: &T, key: &str) -> LabradorResult<()> {
+        let categories = serde_json::to_string(&self.categories().collect::<Vec<_>>())?;
+        session.set(key, categories, Some(CATEGORY_TREE_CACHE_SECONDS))
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::SimpleStorage;
+    use super::{CategoryTree, WechatCategory};
+
+    fn names(categories: Vec<&WechatCategory>) -> Vec<&str> {
+        categories.iter().map(|v| v.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tree_with_orphans_and_cycles() {
+        let categories = serde_json::from_value::<Vec<WechatCategory>>(json!([
+            {"id": 1, "name": "美食", "level": 1},
+            {"id": 2, "name": "江浙菜", "level": 2, "father": 1},
+            {"id": 3, "name": "上海菜", "level": 3, "father": 2},
+            {"id": 4, "name": "其他", "level": 3, "father": 2},
+            {"id": 5, "name": "其他", "level": 2, "father": 1},
+            // 父类目不存在
+            {"id": 6, "name": "孤立类目", "level": 2, "father": 99},
+            // 8 -> 9 -> 10 -> 8 成环，11挂在环上
+            {"id": 8, "name": "环A", "level": 1, "father": 10},
+            {"id": 9, "name": "环B", "level": 2, "father": 8},
+            {"id": 10, "name": "环C", "level": 3, "father": 9},
+            {"id": 11, "name": "环下", "level": 4, "father": 10},
+            // 自己是自己的父类目
+            {"id": 12, "name": "自环", "level": 1, "father": 12},
+            // id重复时保留第一个
+            {"id": 3, "name": "重复", "level": 3, "father": 1}
+        ])).unwrap();
+        let tree = CategoryTree::from_categories(categories);
+        assert_eq!(tree.len(), 11);
+        assert_eq!(names(tree.roots()), vec!["美食", "孤立类目", "环A", "自环"]);
+        assert_eq!(names(tree.path_for(3)), vec!["美食", "江浙菜", "上海菜"]);
+        assert_eq!(names(tree.path_for(11)), vec!["环A", "环B", "环C", "环下"]);
+        assert_eq!(names(tree.path_for(6)), vec!["孤立类目"]);
+        assert!(tree.path_for(100).is_empty());
+        assert_eq!(tree.find_by_name("其他").iter().map(|v| v.id).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(names(tree.children(1)), vec!["江浙菜", "其他"]);
+        assert_eq!(tree.leaf_categories().iter().map(|v| v.id).collect::<Vec<_>>(), vec![3, 4, 5, 6, 11, 12]);
+    }
+
+    #[test]
+    fn test_tree_from_paths_and_cache() {
+        let tree = CategoryTree::from_paths(["美食,江浙菜,上海菜", "美食,江浙菜,淮扬菜", "美食,粤菜", "购物"]);
+        assert_eq!(names(tree.roots()), vec!["美食", "购物"]);
+        let huaiyang = tree.find_by_name("淮扬菜")[0];
+        assert_eq!(names(tree.path_for(huaiyang.id)), vec!["美食", "江浙菜", "淮扬菜"]);
+        assert_eq!(huaiyang.level, 3);
+        assert_eq!(names(tree.leaf_categories()), vec!["上海菜", "淮扬菜", "粤菜", "购物"]);
+
+        let session = SimpleStorage::new();
+        assert_eq!(CategoryTree::load(&session, "category_tree_test").unwrap(), None);
+        tree.store(&session, "category_tree_test").unwrap();
+        assert_eq!(CategoryTree::load(&session, "category_tree_test").unwrap(), Some(tree));
+    }
+}
@@ -6,20 +6,26 @@ use crate::wechat::cp::method::{CpAgentMethod, WechatCpMethod};
 
 /// 管理企业号应用
 #[derive(Debug, Clone)]
-pub struct WechatCpAgent<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpAgent<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpAgent<'a, T> {
+impl<T: SessionStore> WechatCpAgent<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpAgent<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpAgent<T> {
         WechatCpAgent {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.agent()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpAgent<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 获取企业号应用信息
     /// 该API用于获取企业号某个应用的基本信息，包括头像、昵称、帐号类型、认证类型、可见范围等信息
@@ -0,0 +1,284 @@
+use serde::{Serialize, Deserialize, Serializer};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient, LabraError};
+use crate::wechat::cp::api::kf::serialize_tagged;
+use crate::wechat::cp::method::{CpAppChatMethod, WechatCpMethod};
+
+/// 群聊成员数下限
+const MIN_CHAT_MEMBERS: usize = 2;
+/// 群聊成员数上限
+const MAX_CHAT_MEMBERS: usize = 2000;
+
+/// 群聊会话
+///
+/// 应用创建的群聊只能由该应用推送消息，群成员须在应用的可见范围内。
+#[derive(Debug, Clone)]
+pub struct WechatCpAppChat<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpAppChat<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpAppChat<T> {
+        WechatCpAppChat {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.app_chat()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpAppChat<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 创建群聊会话.
+    /// <pre>
+    /// userlist为群成员id列表，至少2人，至多2000人；owner不填时从userlist中随机选一人为群主
+    /// chatid不填时由系统随机生成，返回群聊的chatid
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90245">文档</a>
+    /// </pre>
+    pub async fn create(&self, name: Option<&str>, owner: Option<&str>, userlist: &[String], chatid: Option<&str>) -> LabradorResult<String> {
+        if userlist.len() < MIN_CHAT_MEMBERS || userlist.len() > MAX_CHAT_MEMBERS {
+            return Err(LabraError::RequestError(format!("群成员数必须为{}~{}人，当前为{}人", MIN_CHAT_MEMBERS, MAX_CHAT_MEMBERS, userlist.len())));
+        }
+        let req = WechatCpAppChatCreateRequest {
+            name: name.map(|v| v.to_string()),
+            owner: owner.map(|v| v.to_string()),
+            userlist: userlist.to_vec(),
+            chatid: chatid.map(|v| v.to_string()),
+        };
+        let v = self.client.post(WechatCpMethod::AppChat(CpAppChatMethod::Create), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["chatid"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// 修改群聊会话.
+    /// <pre>
+    /// 各参数为None时不修改对应项
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90246">文档</a>
+    /// </pre>
+    pub async fn update(&self, chatid: &str, add_user_list: Option<Vec<String>>, del_user_list: Option<Vec<String>>, name: Option<&str>, owner: Option<&str>) -> LabradorResult<WechatCommonResponse> {
+        let req = WechatCpAppChatUpdateRequest {
+            chatid: chatid.to_string(),
+            name: name.map(|v| v.to_string()),
+            owner: owner.map(|v| v.to_string()),
+            add_user_list,
+            del_user_list,
+        };
+        let v = self.client.post(WechatCpMethod::AppChat(CpAppChatMethod::Update), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 获取群聊会话.
+    /// <pre>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90247">文档</a>
+    /// </pre>
+    pub async fn get(&self, chatid: &str) -> LabradorResult<WechatCpAppChatInfo> {
+        let v = self.client.get(WechatCpMethod::AppChat(CpAppChatMethod::Get), vec![
+            ("chatid".to_string(), chatid.to_string()),
+        ], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<WechatCpAppChatInfo>(v, "chat_info")
+    }
+
+    /// 应用推送消息.
+    /// <pre>
+    /// 与应用消息不同，群聊消息以chatid指定会话，不需要touser、agentid
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90248">文档</a>
+    /// </pre>
+    pub async fn send(&self, chatid: &str, message: WechatCpAppChatMessage) -> LabradorResult<WechatCommonResponse> {
+        let req = WechatCpAppChatSendRequest {
+            chatid: chatid.to_string(),
+            message,
+            safe: None,
+        };
+        let v = self.client.post(WechatCpMethod::AppChat(CpAppChatMethod::Send), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpAppChatCreateRequest {
+    /// 群聊名，最多50个utf8字符，超过将截断
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 指定群主的id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// 群成员id列表。至少2人，至多2000人
+    pub userlist: Vec<String>,
+    /// 群聊的唯一标志，不能与已有的群重复；字符串类型，最长32个字符。只允许字符0-9及字母a-zA-Z
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chatid: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpAppChatUpdateRequest {
+    /// 群聊id
+    pub chatid: String,
+    /// 新的群聊名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 新群主的id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// 添加成员的id列表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_user_list: Option<Vec<String>>,
+    /// 踢出成员的id列表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub del_user_list: Option<Vec<String>>,
+}
+
+/// 群聊信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpAppChatInfo {
+    /// 群聊唯一标志
+    pub chatid: String,
+    /// 群聊名
+    pub name: Option<String>,
+    /// 群主id
+    pub owner: Option<String>,
+    /// 群成员id列表
+    #[serde(default)]
+    pub userlist: Vec<String>,
+    /// 群聊类型。0：普通群；1：家校群
+    pub chat_type: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WechatCpAppChatSendRequest {
+    /// 群聊id
+    pub chatid: String,
+    /// 消息内容，按msgtype区分
+    #[serde(flatten)]
+    pub message: WechatCpAppChatMessage,
+    /// 表示是否是保密消息，0表示否，1表示是，默认0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe: Option<u8>,
+}
+
+/// 群聊消息内容
+#[derive(Debug, Clone)]
+pub enum WechatCpAppChatMessage {
+    /// 文本消息，content最长不超过2048个字节
+    Text(String),
+    /// markdown消息，content最长不超过2048个字节
+    Markdown(String),
+    /// 图片消息，为图片媒体文件id
+    Image(String),
+    /// 文件消息，为文件媒体文件id
+    File(String),
+    /// 文本卡片消息
+    TextCard(WechatCpAppChatTextCard),
+}
+
+/// 文本卡片消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpAppChatTextCard {
+    /// 标题，不超过128个字节
+    pub title: String,
+    /// 描述，不超过512个字节
+    pub description: String,
+    /// 点击后跳转的链接
+    pub url: String,
+    /// 按钮文字，默认为“详情”
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub btntxt: Option<String>,
+}
+
+impl WechatCpAppChatMessage {
+    pub fn get_msgtype(&self) -> String {
+        match self {
+            WechatCpAppChatMessage::Text(_) => "text".to_string(),
+            WechatCpAppChatMessage::Markdown(_) => "markdown".to_string(),
+            WechatCpAppChatMessage::Image(_) => "image".to_string(),
+            WechatCpAppChatMessage::File(_) => "file".to_string(),
+            WechatCpAppChatMessage::TextCard(_) => "textcard".to_string(),
+        }
+    }
+}
+
+impl Serialize for WechatCpAppChatMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let msgtype = self.get_msgtype();
+        match self {
+            WechatCpAppChatMessage::Text(content)
+            | WechatCpAppChatMessage::Markdown(content) => serialize_tagged(serializer, "msgtype", &msgtype, &json!({ "content": content })),
+            WechatCpAppChatMessage::Image(media_id)
+            | WechatCpAppChatMessage::File(media_id) => serialize_tagged(serializer, "msgtype", &msgtype, &json!({ "media_id": media_id })),
+            WechatCpAppChatMessage::TextCard(v) => serialize_tagged(serializer, "msgtype", &msgtype, v),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpAppChatInfo, WechatCpAppChatMessage, WechatCpAppChatSendRequest, WechatCpAppChatTextCard};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_send_envelope() {
+        let req = WechatCpAppChatSendRequest { chatid: "CHATID".to_string(), message: WechatCpAppChatMessage::Text("你的快递已到".to_string()), safe: Some(0) };
+        let v = serde_json::to_value(&req).unwrap();
+        assert_eq!(v, json!({"chatid": "CHATID", "msgtype": "text", "text": {"content": "你的快递已到"}, "safe": 0}));
+        // 不同于应用消息，群聊消息没有touser、agentid
+        assert!(v.get("touser").is_none() && v.get("agentid").is_none());
+
+        let req = WechatCpAppChatSendRequest { chatid: "CHATID".to_string(), message: WechatCpAppChatMessage::File("MEDIA_ID".to_string()), safe: None };
+        assert_eq!(serde_json::to_value(&req).unwrap(), json!({"chatid": "CHATID", "msgtype": "file", "file": {"media_id": "MEDIA_ID"}}));
+
+        let card = WechatCpAppChatMessage::TextCard(WechatCpAppChatTextCard {
+            title: "领奖通知".to_string(),
+            description: "<div class=\"gray\">2016年9月26日</div>".to_string(),
+            url: "https://work.weixin.qq.com".to_string(),
+            btntxt: None,
+        });
+        let req = WechatCpAppChatSendRequest { chatid: "CHATID".to_string(), message: card, safe: None };
+        assert_eq!(serde_json::to_value(&req).unwrap(), json!({"chatid": "CHATID", "msgtype": "textcard", "textcard": {"title": "领奖通知", "description": "<div class=\"gray\">2016年9月26日</div>", "url": "https://work.weixin.qq.com"}}));
+    }
+
+    #[tokio::test]
+    async fn test_create_member_count() {
+        let client = WechatCpClient::<SimpleStorage>::new("appchat_member_count_corp", "secret").base_url("http://127.0.0.1:1");
+        let err = client.app_chat().create(Some("NAME"), None, &["zhangsan".to_string()], None).await.unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(_)));
+        let userlist = (0..2001).map(|i| format!("user{}", i)).collect::<Vec<String>>();
+        let err = client.app_chat().create(Some("NAME"), None, &userlist, None).await.unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(_)));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_send() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","chatid":"CHATID"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","chat_info":{"chatid":"CHATID","name":"NAME","owner":"userid2","userlist":["userid1","userid2","userid3"],"chat_type":0}}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("appchat_create_corp", "secret").base_url(&server.url);
+        let chatid = client.app_chat().create(Some("NAME"), Some("userid1"), &["userid1".to_string(), "userid2".to_string()], None).await.unwrap();
+        assert_eq!(chatid, "CHATID");
+        client.app_chat().send(&chatid, WechatCpAppChatMessage::Markdown("**告警**".to_string())).await.unwrap();
+        let info = client.app_chat().get(&chatid).await.unwrap();
+        assert_eq!(info.owner.as_deref(), Some("userid2"));
+        assert_eq!(info.userlist.len(), 3);
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/appchat/create?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"name":"NAME","owner":"userid1","userlist":["userid1","userid2"]}"#));
+        assert!(requests[2].starts_with("POST /cgi-bin/appchat/send?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[2].ends_with(r#"{"chatid":"CHATID","msgtype":"markdown","markdown":{"content":"**告警**"}}"#));
+        assert!(requests[3].starts_with("GET /cgi-bin/appchat/get?"));
+        assert!(requests[3].contains("chatid=CHATID"));
+    }
+}
@@ -0,0 +1,716 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient};
+use crate::wechat::cp::method::{CpApprovalMethod, WechatCpMethod};
+
+/// 审批
+///
+/// 获取审批模板详情，并按模板的控件校验后提交审批申请。
+#[derive(Debug, Clone)]
+pub struct WechatCpApproval<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpApproval<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpApproval<T> {
+        WechatCpApproval {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.approval()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpApproval<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取审批模板详情.
+    /// <pre>
+    /// 返回模板的名称与控件，可通过[`WechatCpApplyDataBuilder::for_template`]按控件填写审批申请数据
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/oa/gettemplatedetail?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/91982">文档</a>
+    /// </pre>
+    pub async fn get_template_detail(&self, template_id: &str) -> LabradorResult<WechatCpApprovalTemplate> {
+        let v = self.client.post(WechatCpMethod::Approval(CpApprovalMethod::GetTemplateDetail), vec![], json!({ "template_id": template_id }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpApprovalTemplate>(v)
+    }
+
+    /// 提交审批申请.
+    /// <pre>
+    /// 返回审批单号sp_no
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/oa/applyevent?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/91853">文档</a>
+    /// </pre>
+    pub async fn apply_event(&self, req: &WechatCpApplyEventRequest) -> LabradorResult<String> {
+        let v = self.client.post(WechatCpMethod::Approval(CpApprovalMethod::ApplyEvent), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["sp_no"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+/// 多语言文本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpApprovalText {
+    pub text: String,
+    pub lang: String,
+}
+
+/// 审批模板详情
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalTemplate {
+    /// 模板名称，若配置了多语言则会包含中英文的模板名称
+    #[serde(default = "Vec::new")]
+    pub template_names: Vec<WechatCpApprovalText>,
+    pub template_content: WechatCpApprovalTemplateContent,
+}
+
+impl WechatCpApprovalTemplate {
+    /// 模板的控件
+    pub fn controls(&self) -> &[WechatCpApprovalControl] {
+        &self.template_content.controls
+    }
+
+    /// 按id查找控件，不查找明细控件中的子控件
+    pub fn control(&self, id: &str) -> Option<&WechatCpApprovalControl> {
+        self.controls().iter().find(|control| control.property.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalTemplateContent {
+    #[serde(default = "Vec::new")]
+    pub controls: Vec<WechatCpApprovalControl>,
+}
+
+/// 控件类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum WechatCpApprovalControlType {
+    /// 文本
+    Text,
+    /// 多行文本
+    Textarea,
+    /// 数字
+    Number,
+    /// 金额
+    Money,
+    /// 日期/日期+时间
+    Date,
+    /// 单选/多选
+    Selector,
+    /// 成员/部门
+    Contact,
+    /// 说明文字，不需要填写
+    Tips,
+    /// 附件
+    File,
+    /// 明细
+    Table,
+    Other(String),
+}
+
+impl From<String> for WechatCpApprovalControlType {
+    fn from(v: String) -> Self {
+        match v.as_str() {
+            "Text" => WechatCpApprovalControlType::Text,
+            "Textarea" => WechatCpApprovalControlType::Textarea,
+            "Number" => WechatCpApprovalControlType::Number,
+            "Money" => WechatCpApprovalControlType::Money,
+            "Date" => WechatCpApprovalControlType::Date,
+            "Selector" => WechatCpApprovalControlType::Selector,
+            "Contact" => WechatCpApprovalControlType::Contact,
+            "Tips" => WechatCpApprovalControlType::Tips,
+            "File" => WechatCpApprovalControlType::File,
+            "Table" => WechatCpApprovalControlType::Table,
+            _ => WechatCpApprovalControlType::Other(v),
+        }
+    }
+}
+
+impl From<WechatCpApprovalControlType> for String {
+    fn from(v: WechatCpApprovalControlType) -> Self {
+        v.to_string()
+    }
+}
+
+impl fmt::Display for WechatCpApprovalControlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WechatCpApprovalControlType::Text => "Text",
+            WechatCpApprovalControlType::Textarea => "Textarea",
+            WechatCpApprovalControlType::Number => "Number",
+            WechatCpApprovalControlType::Money => "Money",
+            WechatCpApprovalControlType::Date => "Date",
+            WechatCpApprovalControlType::Selector => "Selector",
+            WechatCpApprovalControlType::Contact => "Contact",
+            WechatCpApprovalControlType::Tips => "Tips",
+            WechatCpApprovalControlType::File => "File",
+            WechatCpApprovalControlType::Table => "Table",
+            WechatCpApprovalControlType::Other(v) => v,
+        };
+        f.write_str(name)
+    }
+}
+
+/// 模板控件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalControl {
+    pub property: WechatCpApprovalControlProperty,
+    /// 控件的配置，仅部分控件有
+    #[serde(default)]
+    pub config: WechatCpApprovalControlConfig,
+}
+
+impl WechatCpApprovalControl {
+    /// 明细控件的子控件
+    pub fn children(&self) -> &[WechatCpApprovalControl] {
+        self.config.table.as_ref().map(|table| table.children.as_slice()).unwrap_or_default()
+    }
+}
+
+/// 控件属性
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalControlProperty {
+    /// 控件类型
+    pub control: WechatCpApprovalControlType,
+    /// 控件id
+    pub id: String,
+    /// 控件名称，若配置了多语言则会包含中英文的控件名称
+    #[serde(default = "Vec::new")]
+    pub title: Vec<WechatCpApprovalText>,
+    /// 控件说明，向申请者展示的控件填写说明
+    #[serde(default = "Vec::new")]
+    pub placeholder: Vec<WechatCpApprovalText>,
+    /// 是否必填：1-必填；0-非必填
+    #[serde(default)]
+    pub require: i32,
+    /// 是否参与打印：1-不参与打印；0-参与打印
+    #[serde(default)]
+    pub un_print: i32,
+}
+
+impl WechatCpApprovalControlProperty {
+    pub fn is_required(&self) -> bool {
+        self.require == 1
+    }
+}
+
+/// 控件配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WechatCpApprovalControlConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<WechatCpApprovalSelectorConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<WechatCpApprovalDateConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<WechatCpApprovalContactConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<WechatCpApprovalTableConfig>,
+}
+
+/// 单选/多选控件的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalSelectorConfig {
+    /// single-单选；multi-多选
+    #[serde(rename = "type")]
+    pub r#type: String,
+    #[serde(default = "Vec::new")]
+    pub options: Vec<WechatCpApprovalSelectorOption>,
+}
+
+/// 选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalSelectorOption {
+    /// 选项id，填写审批申请时使用
+    pub key: String,
+    /// 选项值，若配置了多语言则会包含中英文的选项值
+    #[serde(default = "Vec::new")]
+    pub value: Vec<WechatCpApprovalText>,
+}
+
+/// 日期控件的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalDateConfig {
+    /// day-日期；hour-日期+时间
+    #[serde(rename = "type")]
+    pub r#type: String,
+}
+
+/// 成员/部门控件的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalContactConfig {
+    /// single-单选；multi-多选
+    #[serde(rename = "type")]
+    pub r#type: String,
+    /// user-成员；department-部门
+    pub mode: String,
+}
+
+/// 明细控件的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApprovalTableConfig {
+    /// 明细中的子控件
+    #[serde(default = "Vec::new")]
+    pub children: Vec<WechatCpApprovalControl>,
+}
+
+/// 审批申请中的成员
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpApplyMember {
+    pub userid: String,
+    pub name: String,
+}
+
+/// 审批申请中的部门
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpApplyDepartment {
+    pub openapi_id: String,
+    pub name: String,
+}
+
+/// <pre>
+/// 控件的取值
+/// Text可用于文本与多行文本控件，Number与Money为数字的字符串形式（如"700.5"），Selector为选项的key
+/// Raw按原样作为value提交，不做校验，用于SDK尚未支持的控件
+/// </pre>
+#[derive(Debug, Clone, PartialEq)]
+pub enum WechatCpApplyValue {
+    Text(String),
+    Number(String),
+    Money(String),
+    Date(DateTime<Utc>),
+    Selector(Vec<String>),
+    Members(Vec<WechatCpApplyMember>),
+    Departments(Vec<WechatCpApplyDepartment>),
+    /// 附件的文件id
+    Files(Vec<String>),
+    /// 明细，每行填写子控件的取值
+    Table(Vec<WechatCpApplyTableRow>),
+    Raw(Value),
+}
+
+impl WechatCpApplyValue {
+    /// 取值对应的控件类型，用于错误信息
+    fn kind(&self) -> &'static str {
+        match self {
+            WechatCpApplyValue::Text(_) => "Text",
+            WechatCpApplyValue::Number(_) => "Number",
+            WechatCpApplyValue::Money(_) => "Money",
+            WechatCpApplyValue::Date(_) => "Date",
+            WechatCpApplyValue::Selector(_) => "Selector",
+            WechatCpApplyValue::Members(_) | WechatCpApplyValue::Departments(_) => "Contact",
+            WechatCpApplyValue::Files(_) => "File",
+            WechatCpApplyValue::Table(_) => "Table",
+            WechatCpApplyValue::Raw(_) => "Raw",
+        }
+    }
+}
+
+/// 明细中的一行
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WechatCpApplyTableRow {
+    values: Vec<(String, WechatCpApplyValue)>,
+}
+
+impl WechatCpApplyTableRow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 填写子控件的取值，同一控件重复填写时以最后一次为准
+    pub fn value(mut self, id: &str, value: WechatCpApplyValue) -> Self {
+        set_value(&mut self.values, id, value);
+        self
+    }
+}
+
+fn set_value(values: &mut Vec<(String, WechatCpApplyValue)>, id: &str, value: WechatCpApplyValue) {
+    values.retain(|(key, _)| key != id);
+    values.push((id.to_string(), value));
+}
+
+/// 审批申请数据的字段级问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatCpApplyDataIssue {
+    /// 控件id，明细中的子控件为`Table-1[0].Text-2`的形式
+    pub control_id: String,
+    pub message: String,
+}
+
+impl fmt::Display for WechatCpApplyDataIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "control {} {}", self.control_id, self.message)
+    }
+}
+
+/// 审批申请数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WechatCpApplyData {
+    pub contents: Vec<WechatCpApplyContent>,
+}
+
+/// 审批申请中单个控件的内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WechatCpApplyContent {
+    pub control: WechatCpApprovalControlType,
+    pub id: String,
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub title: Vec<WechatCpApprovalText>,
+    pub value: Value,
+}
+
+/// <pre>
+/// 按审批模板填写申请数据
+/// build时按控件类型与是否必填校验所有取值，有问题时返回全部字段级的问题，如`control Money-1 expects Money, got Text`
+/// 生成的contents按模板中控件的顺序排列，未填写的非必填控件不提交
+/// </pre>
+#[derive(Debug, Clone)]
+pub struct WechatCpApplyDataBuilder<'a> {
+    template: &'a WechatCpApprovalTemplate,
+    values: Vec<(String, WechatCpApplyValue)>,
+}
+
+impl<'a> WechatCpApplyDataBuilder<'a> {
+    pub fn for_template(template: &'a WechatCpApprovalTemplate) -> Self {
+        WechatCpApplyDataBuilder {
+            template,
+            values: vec![],
+        }
+    }
+
+    /// 填写控件的取值，同一控件重复填写时以最后一次为准
+    pub fn value(mut self, id: &str, value: WechatCpApplyValue) -> Self {
+        set_value(&mut self.values, id, value);
+        self
+    }
+
+    /// 校验所有取值，返回字段级的问题，为空表示校验通过
+    pub fn validate(&self) -> Vec<WechatCpApplyDataIssue> {
+        let mut issues = vec![];
+        build_contents(self.template.controls(), &self.values, "", &mut issues);
+        issues
+    }
+
+    pub fn build(&self) -> LabradorResult<WechatCpApplyData> {
+        let mut issues = vec![];
+        let contents = build_contents(self.template.controls(), &self.values, "", &mut issues);
+        if issues.is_empty() {
+            Ok(WechatCpApplyData { contents })
+        } else {
+            Err(LabraError::RequestError(issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>().join("; ")))
+        }
+    }
+}
+
+/// 按控件顺序生成内容，prefix为明细行的路径
+fn build_contents(controls: &[WechatCpApprovalControl], values: &[(String, WechatCpApplyValue)], prefix: &str, issues: &mut Vec<WechatCpApplyDataIssue>) -> Vec<WechatCpApplyContent> {
+    let mut issue = |id: &str, message: String| issues.push(WechatCpApplyDataIssue { control_id: format!("{}{}", prefix, id), message });
+    for (id, _) in values {
+        if !controls.iter().any(|control| &control.property.id == id) {
+            issue(id, "is not in the template".to_string());
+        }
+    }
+    let mut contents = vec![];
+    let mut nested = vec![];
+    for control in controls {
+        let property = &control.property;
+        let value = match values.iter().find(|(id, _)| id == &property.id) {
+            Some((_, value)) => value,
+            None => {
+                if property.is_required() && property.control != WechatCpApprovalControlType::Tips {
+                    issue(&property.id, "is required".to_string());
+                }
+                continue;
+            }
+        };
+        let content = match (&property.control, value) {
+            (_, WechatCpApplyValue::Raw(v)) => Ok(v.to_owned()),
+            (WechatCpApprovalControlType::Tips, _) => Err("is a Tips control and takes no value".to_string()),
+            (WechatCpApprovalControlType::Text | WechatCpApprovalControlType::Textarea, WechatCpApplyValue::Text(text)) => {
+                if property.is_required() && text.trim().is_empty() { Err("is required".to_string()) } else { Ok(json!({ "text": text })) }
+            }
+            (WechatCpApprovalControlType::Number, WechatCpApplyValue::Number(number)) => number_value(number).map(|_| json!({ "new_number": number })),
+            (WechatCpApprovalControlType::Money, WechatCpApplyValue::Money(money)) => number_value(money).map(|_| json!({ "new_money": money })),
+            (WechatCpApprovalControlType::Date, WechatCpApplyValue::Date(date)) => {
+                let date_type = control.config.date.as_ref().map(|date| date.r#type.as_str()).unwrap_or("day");
+                Ok(json!({ "date": { "type": date_type, "s_timestamp": date.timestamp().to_string() } }))
+            }
+            (WechatCpApprovalControlType::Selector, WechatCpApplyValue::Selector(keys)) => selector_value(control, keys),
+            (WechatCpApprovalControlType::Contact, WechatCpApplyValue::Members(members)) => contact_value(control, "user", members.len()).map(|_| json!({ "members": members })),
+            (WechatCpApprovalControlType::Contact, WechatCpApplyValue::Departments(departments)) => contact_value(control, "department", departments.len()).map(|_| json!({ "departments": departments })),
+            (WechatCpApprovalControlType::File, WechatCpApplyValue::Files(files)) => {
+                if property.is_required() && files.is_empty() { Err("is required".to_string()) } else { Ok(json!({ "files": files.iter().map(|file_id| json!({ "file_id": file_id })).collect::<Vec<_>>() })) }
+            }
+            (WechatCpApprovalControlType::Table, WechatCpApplyValue::Table(rows)) => {
+                if property.is_required() && rows.is_empty() {
+                    Err("is required".to_string())
+                } else {
+                    let children = rows.iter().enumerate().map(|(i, row)| {
+                        let list = build_contents(control.children(), &row.values, &format!("{}{}[{}].", prefix, property.id, i), &mut nested);
+                        json!({ "list": list })
+                    }).collect::<Vec<_>>();
+                    Ok(json!({ "children": children }))
+                }
+            }
+            (expected, value) => Err(format!("expects {}, got {}", expected, value.kind())),
+        };
+        match content {
+            Ok(value) => contents.push(WechatCpApplyContent {
+                control: property.control.to_owned(),
+                id: property.id.to_owned(),
+                title: property.title.to_owned(),
+                value,
+            }),
+            Err(message) => issue(&property.id, message),
+        }
+    }
+    issues.append(&mut nested);
+    contents
+}
+
+fn number_value(number: &str) -> Result<f64, String> {
+    number.trim().parse::<f64>().map_err(|_| format!("expects a number, got {:?}", number))
+}
+
+fn selector_value(control: &WechatCpApprovalControl, keys: &[String]) -> Result<Value, String> {
+    let selector = control.config.selector.as_ref();
+    let selector_type = selector.map(|selector| selector.r#type.as_str()).unwrap_or("single");
+    if control.property.is_required() && keys.is_empty() {
+        return Err("is required".to_string());
+    }
+    if selector_type == "single" && keys.len() > 1 {
+        return Err(format!("accepts a single option, got {}", keys.len()));
+    }
+    if let Some(key) = keys.iter().find(|key| !selector.map(|selector| selector.options.iter().any(|option| &option.key == *key)).unwrap_or_default()) {
+        return Err(format!("has no option {}", key));
+    }
+    Ok(json!({ "selector": { "type": selector_type, "options": keys.iter().map(|key| json!({ "key": key })).collect::<Vec<_>>() } }))
+}
+
+fn contact_value(control: &WechatCpApprovalControl, mode: &str, count: usize) -> Result<(), String> {
+    let contact = control.config.contact.as_ref();
+    let expected = contact.map(|contact| contact.mode.as_str()).unwrap_or(mode);
+    if expected != mode {
+        return Err(format!("expects {} contacts, got {}", expected, mode));
+    }
+    if control.property.is_required() && count == 0 {
+        return Err("is required".to_string());
+    }
+    if contact.map(|contact| contact.r#type == "single").unwrap_or_default() && count > 1 {
+        return Err(format!("accepts a single {}, got {}", mode, count));
+    }
+    Ok(())
+}
+
+/// 审批节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApplyApprover {
+    /// 节点审批方式：1-或签；2-会签，仅在节点为多人审批时有效
+    pub attr: i32,
+    /// 审批节点审批人userid列表
+    pub userid: Vec<String>,
+}
+
+/// 摘要行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApplySummary {
+    pub summary_info: Vec<WechatCpApprovalText>,
+}
+
+/// 提交审批申请
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpApplyEventRequest {
+    /// 申请人userid
+    pub creator_userid: String,
+    /// 模板id
+    pub template_id: String,
+    /// 审批人模式：0-通过接口指定审批人、抄送人；1-使用此模板在管理后台设置的审批流程
+    pub use_template_approver: i32,
+    /// 审批流程信息，use_template_approver为0时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approver: Option<Vec<WechatCpApplyApprover>>,
+    /// 抄送人userid列表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifyer: Option<Vec<String>>,
+    /// 抄送方式：1-提单时抄送；2-单据通过后抄送；3-提单和单据通过后抄送
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_type: Option<i32>,
+    pub apply_data: WechatCpApplyData,
+    /// 摘要信息，最多3行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_list: Option<Vec<WechatCpApplySummary>>,
+}
+
+impl WechatCpApplyEventRequest {
+    /// 使用模板在管理后台设置的审批流程
+    pub fn with_template_approver(creator_userid: &str, template_id: &str, apply_data: WechatCpApplyData) -> Self {
+        WechatCpApplyEventRequest {
+            creator_userid: creator_userid.to_string(),
+            template_id: template_id.to_string(),
+            use_template_approver: 1,
+            approver: None,
+            notifyer: None,
+            notify_type: None,
+            apply_data,
+            summary_list: None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpApplyDataBuilder, WechatCpApplyEventRequest, WechatCpApplyMember, WechatCpApplyTableRow, WechatCpApplyValue, WechatCpApprovalControlType, WechatCpApprovalTemplate};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    const TEMPLATE_DETAIL: &str = r#"{
+        "errcode": 0,
+        "errmsg": "ok",
+        "template_names": [{"text": "报销", "lang": "zh_CN"}],
+        "template_content": {
+            "controls": [
+                {"property": {"control": "Tips", "id": "Tips-1", "title": [{"text": "说明", "lang": "zh_CN"}], "require": 0, "un_print": 1}, "config": {}},
+                {"property": {"control": "Text", "id": "Text-1", "title": [{"text": "事由", "lang": "zh_CN"}], "placeholder": [{"text": "请输入", "lang": "zh_CN"}], "require": 1, "un_print": 0}},
+                {"property": {"control": "Textarea", "id": "Textarea-1", "title": [{"text": "备注", "lang": "zh_CN"}], "require": 0, "un_print": 0}},
+                {"property": {"control": "Money", "id": "Money-1", "title": [{"text": "金额", "lang": "zh_CN"}], "require": 1, "un_print": 0}},
+                {"property": {"control": "Date", "id": "Date-1", "title": [{"text": "日期", "lang": "zh_CN"}], "require": 0, "un_print": 0}, "config": {"date": {"type": "hour"}}},
+                {"property": {"control": "Selector", "id": "Selector-1", "title": [{"text": "类型", "lang": "zh_CN"}], "require": 1, "un_print": 0}, "config": {"selector": {"type": "single", "options": [{"key": "option-1", "value": [{"text": "差旅", "lang": "zh_CN"}]}, {"key": "option-2", "value": [{"text": "招待", "lang": "zh_CN"}]}]}}},
+                {"property": {"control": "Contact", "id": "Contact-1", "title": [{"text": "同行人", "lang": "zh_CN"}], "require": 0, "un_print": 0}, "config": {"contact": {"type": "multi", "mode": "user"}}},
+                {"property": {"control": "Table", "id": "Table-1", "title": [{"text": "明细", "lang": "zh_CN"}], "require": 1, "un_print": 0}, "config": {"table": {"children": [
+                    {"property": {"control": "Number", "id": "Number-2", "title": [{"text": "数量", "lang": "zh_CN"}], "require": 1, "un_print": 0}},
+                    {"property": {"control": "Text", "id": "Text-2", "title": [{"text": "名称", "lang": "zh_CN"}], "require": 0, "un_print": 0}}
+                ], "stat_field": [], "sum_field": []}}}
+            ]
+        }
+    }"#;
+
+    fn template() -> WechatCpApprovalTemplate {
+        WechatCommonResponse::parse::<WechatCpApprovalTemplate>(serde_json::from_str::<Value>(TEMPLATE_DETAIL).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_template_deserialize() {
+        let template = template();
+        assert_eq!(template.template_names[0].text, "报销");
+        assert_eq!(template.controls().len(), 8);
+        let table = template.control("Table-1").unwrap();
+        assert_eq!(table.property.control, WechatCpApprovalControlType::Table);
+        assert_eq!(table.children().len(), 2);
+        assert!(table.children()[0].property.is_required());
+        let selector = template.control("Selector-1").unwrap().config.selector.as_ref().unwrap();
+        assert_eq!(selector.options[1].key, "option-2");
+        assert_eq!(template.control("Contact-1").unwrap().config.contact.as_ref().unwrap().mode, "user");
+    }
+
+    #[test]
+    fn test_build_apply_data() {
+        let template = template();
+        let data = WechatCpApplyDataBuilder::for_template(&template)
+            .value("Text-1", WechatCpApplyValue::Text("出差".to_string()))
+            .value("Money-1", WechatCpApplyValue::Money("700.5".to_string()))
+            .value("Date-1", WechatCpApplyValue::Date(Utc.timestamp_opt(1569859200, 0).unwrap()))
+            .value("Selector-1", WechatCpApplyValue::Selector(vec!["option-1".to_string()]))
+            .value("Contact-1", WechatCpApplyValue::Members(vec![WechatCpApplyMember { userid: "zhangsan".to_string(), name: "张三".to_string() }]))
+            .value("Table-1", WechatCpApplyValue::Table(vec![
+                WechatCpApplyTableRow::new().value("Number-2", WechatCpApplyValue::Number("2".to_string())).value("Text-2", WechatCpApplyValue::Text("机票".to_string())),
+                WechatCpApplyTableRow::new().value("Number-2", WechatCpApplyValue::Number("1".to_string())),
+            ]))
+            .build().unwrap();
+        let contents = serde_json::to_value(&data).unwrap()["contents"].to_owned();
+        assert_eq!(contents.as_array().unwrap().len(), 6);
+        assert_eq!(contents[0], json!({"control": "Text", "id": "Text-1", "title": [{"text": "事由", "lang": "zh_CN"}], "value": {"text": "出差"}}));
+        assert_eq!(contents[1]["value"], json!({"new_money": "700.5"}));
+        assert_eq!(contents[2]["value"], json!({"date": {"type": "hour", "s_timestamp": "1569859200"}}));
+        assert_eq!(contents[3]["value"], json!({"selector": {"type": "single", "options": [{"key": "option-1"}]}}));
+        assert_eq!(contents[4]["value"], json!({"members": [{"userid": "zhangsan", "name": "张三"}]}));
+        let children = contents[5]["value"]["children"].as_array().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0]["list"][0], json!({"control": "Number", "id": "Number-2", "title": [{"text": "数量", "lang": "zh_CN"}], "value": {"new_number": "2"}}));
+        assert_eq!(children[0]["list"][1]["value"], json!({"text": "机票"}));
+        assert_eq!(children[1]["list"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_validation_failures() {
+        let template = template();
+        let builder = WechatCpApplyDataBuilder::for_template(&template)
+            .value("Money-1", WechatCpApplyValue::Text("700".to_string()))
+            .value("Selector-1", WechatCpApplyValue::Selector(vec!["option-9".to_string()]))
+            .value("Tips-1", WechatCpApplyValue::Text("说明".to_string()))
+            .value("Unknown-1", WechatCpApplyValue::Text("x".to_string()))
+            .value("Table-1", WechatCpApplyValue::Table(vec![
+                WechatCpApplyTableRow::new().value("Number-2", WechatCpApplyValue::Number("abc".to_string())),
+                WechatCpApplyTableRow::new().value("Text-2", WechatCpApplyValue::Text("机票".to_string())),
+            ]));
+        let issues = builder.validate().iter().map(|issue| issue.to_string()).collect::<Vec<_>>();
+        assert_eq!(issues, vec![
+            "control Unknown-1 is not in the template",
+            "control Tips-1 is a Tips control and takes no value",
+            "control Text-1 is required",
+            "control Money-1 expects Money, got Text",
+            "control Selector-1 has no option option-9",
+            "control Table-1[0].Number-2 expects a number, got \"abc\"",
+            "control Table-1[1].Number-2 is required",
+        ]);
+        match builder.build() {
+            Err(LabraError::RequestError(msg)) => assert!(msg.contains("control Money-1 expects Money, got Text"), "{}", msg),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        // 单选控件只能选一个，成员控件不能填部门
+        let issues = WechatCpApplyDataBuilder::for_template(&template)
+            .value("Text-1", WechatCpApplyValue::Text("出差".to_string()))
+            .value("Money-1", WechatCpApplyValue::Money("700".to_string()))
+            .value("Selector-1", WechatCpApplyValue::Selector(vec!["option-1".to_string(), "option-2".to_string()]))
+            .value("Contact-1", WechatCpApplyValue::Departments(vec![]))
+            .value("Table-1", WechatCpApplyValue::Table(vec![]))
+            .validate().iter().map(|issue| issue.to_string()).collect::<Vec<_>>();
+        assert_eq!(issues, vec![
+            "control Selector-1 accepts a single option, got 2",
+            "control Contact-1 expects user contacts, got department",
+            "control Table-1 is required",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_get_template_detail_and_apply() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(TEMPLATE_DETAIL),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","sp_no":"201909270001"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("approval_template_corp", "secret").base_url(&server.url);
+        let approval = client.approval();
+        let template = approval.get_template_detail("TEMPLATE_ID").await.unwrap();
+        let data = WechatCpApplyDataBuilder::for_template(&template)
+            .value("Text-1", WechatCpApplyValue::Text("出差".to_string()))
+            .value("Money-1", WechatCpApplyValue::Money("700".to_string()))
+            .value("Selector-1", WechatCpApplyValue::Selector(vec!["option-2".to_string()]))
+            .value("Table-1", WechatCpApplyValue::Table(vec![WechatCpApplyTableRow::new().value("Number-2", WechatCpApplyValue::Number("1".to_string()))]))
+            .build().unwrap();
+        let sp_no = approval.apply_event(&WechatCpApplyEventRequest::with_template_approver("zhangsan", "TEMPLATE_ID", data)).await.unwrap();
+        assert_eq!(sp_no, "201909270001");
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/oa/gettemplatedetail?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({"template_id": "TEMPLATE_ID"}));
+        assert!(requests[2].starts_with("POST /cgi-bin/oa/applyevent?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<Value>(requests[2].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["use_template_approver"], 1);
+        assert_eq!(body["apply_data"]["contents"].as_array().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,753 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveTime, Timelike, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient};
+use crate::serde_helper::{beijing, timestamp_seconds, option_timestamp_seconds};
+use crate::wechat::cp::method::{CpCheckinMethod, WechatCpMethod};
+
+/// 单次请求的最大用户数
+const MAX_USERS: usize = 100;
+/// 单次请求的最大时间跨度（天）
+const MAX_DAYS: i64 = 30;
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// 打卡相关
+#[derive(Debug, Clone)]
+pub struct WechatCpCheckin<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpCheckin<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpCheckin<T> {
+        WechatCpCheckin {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.checkin()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpCheckin<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取打卡数据.
+    /// <pre>
+    /// 获取记录时间跨度不超过30天，用户列表不超过100个
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/getcheckindata?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90262">文档</a>
+    /// </pre>
+    pub async fn get_checkin_data(&self, data_type: WechatCpCheckinDataType, start_time: DateTime<Utc>, end_time: DateTime<Utc>, user_ids: Vec<String>) -> LabradorResult<Vec<WechatCpCheckinData>> {
+        check_window(start_time, end_time)?;
+        check_users(&user_ids)?;
+        let req = json!({
+            "opencheckindatatype": data_type as i32,
+            "starttime": start_time.timestamp(),
+            "endtime": end_time.timestamp(),
+            "useridlist": user_ids,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinData), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinData>>(v, "checkindata")
+    }
+
+    /// 获取打卡数据，用户列表超过100个时自动分批请求并合并结果.
+    pub async fn get_checkin_data_chunked(&self, data_type: WechatCpCheckinDataType, start_time: DateTime<Utc>, end_time: DateTime<Utc>, user_ids: Vec<String>) -> LabradorResult<Vec<WechatCpCheckinData>> {
+        let mut result = Vec::new();
+        for chunk in user_ids.chunks(MAX_USERS) {
+            let data = self.get_checkin_data(data_type, start_time, end_time, chunk.to_vec()).await?;
+            result.extend(data);
+        }
+        Ok(result)
+    }
+
+    /// 获取员工打卡规则.
+    /// <pre>
+    /// 用户列表不超过100个
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/getcheckinoption?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90263">文档</a>
+    /// </pre>
+    pub async fn get_checkin_option(&self, datetime: DateTime<Utc>, user_ids: Vec<String>) -> LabradorResult<Vec<WechatCpCheckinOption>> {
+        check_users(&user_ids)?;
+        let req = json!({
+            "datetime": datetime.timestamp(),
+            "useridlist": user_ids,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinOption), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinOption>>(v, "info")
+    }
+
+    /// 获取打卡日报数据.
+    /// <pre>
+    /// 获取记录时间跨度不超过30天，用户列表不超过100个
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/getcheckin_daydata?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/96498">文档</a>
+    /// </pre>
+    pub async fn get_checkin_day_data(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>, user_ids: Vec<String>) -> LabradorResult<Vec<WechatCpCheckinDayData>> {
+        check_window(start_time, end_time)?;
+        check_users(&user_ids)?;
+        let req = json!({
+            "starttime": start_time.timestamp(),
+            "endtime": end_time.timestamp(),
+            "useridlist": user_ids,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinDayData), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinDayData>>(v, "datas")
+    }
+
+    /// 获取打卡人员排班信息.
+    /// <pre>
+    /// 获取记录时间跨度不超过30天，用户列表不超过100个
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/getcheckinschedulist?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93380">文档</a>
+    /// </pre>
+    pub async fn get_checkin_schedule_list(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>, user_ids: Vec<String>) -> LabradorResult<Vec<WechatCpCheckinSchedule>> {
+        check_window(start_time, end_time)?;
+        check_users(&user_ids)?;
+        let req = json!({
+            "starttime": start_time.timestamp(),
+            "endtime": end_time.timestamp(),
+            "useridlist": user_ids,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinScheduleList), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinSchedule>>(v, "schedule_list")
+    }
+
+    /// 录入打卡人员人脸信息.
+    /// <pre>
+    /// user_face为base64编码的人脸图片
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/addcheckinuserface?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93378">文档</a>
+    /// </pre>
+    pub async fn add_checkin_user_face(&self, user_id: &str, user_face: &str) -> LabradorResult<WechatCommonResponse> {
+        let req = json!({
+            "userid": user_id,
+            "userface": user_face,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::AddCheckinUserFace), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 获取企业所有打卡规则.
+    /// <pre>
+    /// 返回的schedulelist为按班次上下班规则的班次列表，排班时使用其中的schedule_id
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/getcorpcheckinoption?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93384">文档</a>
+    /// </pre>
+    pub async fn get_corp_checkin_option(&self) -> LabradorResult<Vec<WechatCpCorpCheckinOption>> {
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::GetCorpCheckinOption), vec![], json!({}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpCorpCheckinOption>>(v, "group")
+    }
+
+    /// 为打卡人员排班.
+    /// <pre>
+    /// 同一次请求的排班日期须在同一个月，且只能是当月或下个月（按北京时间）
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/setcheckinschedulist?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93385">文档</a>
+    /// </pre>
+    pub async fn set_checkin_schedule_list(&self, group_id: i64, items: Vec<WechatCpCheckinScheduleItem>) -> LabradorResult<WechatCommonResponse> {
+        let today = Utc::now().with_timezone(&beijing()).date_naive();
+        let yearmonth = check_schedule_items(&items, today)?;
+        let req = json!({
+            "groupid": group_id,
+            "items": items,
+            "yearmonth": yearmonth,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::SetCheckinScheduleList), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 添加打卡记录.
+    /// <pre>
+    /// 用于同步门禁、考勤机等第三方设备的打卡记录
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/add_checkin_record?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/99647">文档</a>
+    /// </pre>
+    pub async fn add_checkin_record(&self, records: Vec<WechatCpCheckinRecord>) -> LabradorResult<WechatCommonResponse> {
+        if records.is_empty() {
+            return Err(LabraError::RequestError("打卡记录不能为空".to_string()));
+        }
+        let req = json!({
+            "records": records,
+        });
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::AddCheckinRecord), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 为打卡人员补卡.
+    /// <pre>
+    /// 补卡审批通过后调用，schedule_date为应打卡日期，schedule_checkin_time为该日应打卡的时间点，checkin_time为实际补卡时间
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/checkin/punch_correction?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/98920">文档</a>
+    /// </pre>
+    pub async fn punch_correction(&self, user_id: &str, schedule_date: NaiveDate, schedule_checkin_time: NaiveTime, checkin_time: DateTime<Utc>, remark: Option<&str>) -> LabradorResult<WechatCommonResponse> {
+        let date = schedule_date.and_hms_opt(0, 0, 0).and_then(|v| v.and_local_timezone(beijing()).single())
+            .ok_or_else(|| LabraError::RequestError(format!("应打卡日期{}有误", schedule_date)))?;
+        let mut req = json!({
+            "userid": user_id,
+            "schedule_date": date.timestamp(),
+            "schedule_checkin_time": checkin_seconds_from_time(schedule_checkin_time),
+            "checkin_time": checkin_time.timestamp(),
+        });
+        if let Some(remark) = remark {
+            req["remark"] = remark.into();
+        }
+        let v = self.client.post(WechatCpMethod::Checkin(CpCheckinMethod::PunchCorrection), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+}
+
+/// <pre>
+/// 距当天0点的秒数转换为时间，用于班次的work_sec、off_work_sec等字段
+/// 跨天班次的下班时间会超过86400秒，此时返回次日的时间，是否跨天见[`WechatCpCheckinTimeSection::is_overnight`]
+/// </pre>
+pub fn checkin_time_from_seconds(seconds: i64) -> LabradorResult<NaiveTime> {
+    if !(0..2 * SECONDS_PER_DAY).contains(&seconds) {
+        return Err(LabraError::DecodeError(format!("距0点的秒数{}超出范围，应在0到{}之间", seconds, 2 * SECONDS_PER_DAY).into()));
+    }
+    NaiveTime::from_num_seconds_from_midnight_opt((seconds % SECONDS_PER_DAY) as u32, 0)
+        .ok_or_else(|| LabraError::DecodeError(format!("距0点的秒数{}有误", seconds).into()))
+}
+
+/// 时间转换为距当天0点的秒数，不足一秒的部分舍去
+pub fn checkin_seconds_from_time(time: NaiveTime) -> i64 {
+    time.num_seconds_from_midnight() as i64
+}
+
+fn check_window(start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> LabradorResult<()> {
+    if start_time > end_time {
+        return Err(LabraError::RequestError(format!("开始时间{}不能晚于结束时间{}", start_time, end_time)));
+    }
+    if end_time - start_time > Duration::days(MAX_DAYS) {
+        return Err(LabraError::RequestError(format!("打卡数据时间跨度不能超过{}天", MAX_DAYS)));
+    }
+    Ok(())
+}
+
+/// 排班日期须在同一个月，且只能是`today`所在月或下个月，返回排班月份，如`202011`
+fn check_schedule_items(items: &[WechatCpCheckinScheduleItem], today: NaiveDate) -> LabradorResult<i32> {
+    let first = items.first().ok_or_else(|| LabraError::RequestError("排班列表不能为空".to_string()))?;
+    let yearmonth = to_yearmonth(first.date);
+    if let Some(item) = items.iter().find(|v| to_yearmonth(v.date) != yearmonth) {
+        return Err(LabraError::RequestError(format!("排班日期{}与{}不在同一个月", item.date, first.date)));
+    }
+    let this_month = today.with_day(1).unwrap_or(today);
+    let next_month = this_month + Months::new(1);
+    if yearmonth != to_yearmonth(this_month) && yearmonth != to_yearmonth(next_month) {
+        let last_day = next_month + Months::new(1) - Duration::days(1);
+        return Err(LabraError::RequestError(format!("排班日期{}不在{}至{}之间，只能设置当月或下个月的排班", first.date, this_month, last_day)));
+    }
+    Ok(yearmonth)
+}
+
+fn to_yearmonth(date: NaiveDate) -> i32 {
+    date.year() * 100 + date.month() as i32
+}
+
+fn check_users(user_ids: &[String]) -> LabradorResult<()> {
+    if user_ids.is_empty() {
+        return Err(LabraError::RequestError("用户列表不能为空".to_string()));
+    }
+    if user_ids.len() > MAX_USERS {
+        return Err(LabraError::RequestError(format!("用户列表不能超过{}个，当前{}个", MAX_USERS, user_ids.len())));
+    }
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 打卡类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatCpCheckinDataType {
+    /// 上下班打卡
+    OnOffDuty = 1,
+    /// 外出打卡
+    Outside = 2,
+    /// 全部打卡
+    All = 3,
+}
+
+/// 打卡记录类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WechatCpCheckinType {
+    #[serde(rename = "上班打卡")]
+    OnDuty,
+    #[serde(rename = "下班打卡")]
+    OffDuty,
+    #[serde(rename = "外出打卡")]
+    Outside,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 打卡数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinData {
+    pub userid: String,
+    pub groupname: Option<String>,
+    pub checkin_type: WechatCpCheckinType,
+    /// 异常类型，多个以分号分隔，正常打卡时为空
+    pub exception_type: Option<String>,
+    #[serde(with = "timestamp_seconds")]
+    pub checkin_time: DateTime<Utc>,
+    pub location_title: Option<String>,
+    pub location_detail: Option<String>,
+    pub wifiname: Option<String>,
+    pub wifimac: Option<String>,
+    pub notes: Option<String>,
+    pub mediaids: Option<Vec<String>>,
+    /// 标准打卡时间
+    #[serde(default, with = "option_timestamp_seconds")]
+    pub sch_checkin_time: Option<DateTime<Utc>>,
+    pub groupid: Option<i64>,
+    pub schedule_id: Option<i64>,
+    pub timeline_id: Option<i64>,
+    /// 纬度，实际值乘1000000
+    pub lat: Option<i64>,
+    /// 经度，实际值乘1000000
+    pub lng: Option<i64>,
+    pub deviceid: Option<String>,
+}
+
+impl WechatCpCheckinData {
+    /// 打卡位置(纬度, 经度)
+    pub fn location(&self) -> Option<(f64, f64)> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => Some((lat as f64 / 1_000_000.0, lng as f64 / 1_000_000.0)),
+            _ => None,
+        }
+    }
+
+    /// 异常类型列表
+    pub fn exceptions(&self) -> Vec<&str> {
+        self.exception_type.as_deref().unwrap_or_default().split(';').map(|v| v.trim()).filter(|v| !v.is_empty()).collect()
+    }
+}
+
+/// 员工打卡规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinOption {
+    pub userid: String,
+    /// 打卡规则
+    pub group: Option<Value>,
+}
+
+/// 打卡日报数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinDayData {
+    pub base_info: WechatCpCheckinDayBaseInfo,
+    pub summary_info: Option<WechatCpCheckinDaySummaryInfo>,
+    pub holiday_infos: Option<Vec<Value>>,
+    pub exception_infos: Option<Vec<Value>>,
+    pub ot_info: Option<Value>,
+    pub sp_items: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinDayBaseInfo {
+    #[serde(with = "timestamp_seconds")]
+    pub date: DateTime<Utc>,
+    /// 记录类型：1-固定上下班；2-外出；3-按班次上下班；4-自由签到；5-加班；7-无规则
+    pub record_type: Option<i32>,
+    pub name: Option<String>,
+    pub name_ex: Option<String>,
+    pub departs_name: Option<String>,
+    pub acctid: Option<String>,
+    pub rule_info: Option<Value>,
+    /// 日报类型：0-工作日日报；1-休息日日报
+    pub day_type: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinDaySummaryInfo {
+    pub checkin_count: Option<i32>,
+    pub regular_work_sec: Option<i64>,
+    pub standard_work_sec: Option<i64>,
+    #[serde(default, with = "option_timestamp_seconds")]
+    pub earliest_time: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_timestamp_seconds")]
+    pub lastest_time: Option<DateTime<Utc>>,
+}
+
+/// 排班信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinSchedule {
+    pub userid: String,
+    pub yearmonth: Option<i32>,
+    pub groupid: Option<i64>,
+    pub groupname: Option<String>,
+    pub schedule: Option<WechatCpCheckinUserSchedule>,
+}
+
+/// 人员当月的排班
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinUserSchedule {
+    #[serde(rename = "scheduleList", default)]
+    pub schedule_list: Vec<WechatCpCheckinDaySchedule>,
+}
+
+/// 某一天的排班
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinDaySchedule {
+    /// 排班日期，为当月的第几天
+    pub day: u32,
+    pub schedule_info: WechatCpCheckinScheduleInfo,
+}
+
+/// 班次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinScheduleInfo {
+    /// 班次id，0表示休息
+    pub schedule_id: i64,
+    pub schedule_name: Option<String>,
+    #[serde(default)]
+    pub time_section: Vec<WechatCpCheckinTimeSection>,
+    /// 允许提前打卡的时间（秒）
+    pub limit_aheadtime: Option<i64>,
+    /// 下班是否不需要打卡
+    pub noneed_offwork: Option<bool>,
+    /// 允许延后打卡的时间（秒）
+    pub limit_offtime: Option<i64>,
+    /// 允许迟到的时间（秒）
+    pub flex_on_duty_time: Option<i64>,
+    /// 允许早退的时间（秒）
+    pub flex_off_duty_time: Option<i64>,
+    /// 是否允许弹性时间
+    pub allow_flex: Option<bool>,
+    pub max_allow_arrive_early: Option<i64>,
+    pub max_allow_arrive_late: Option<i64>,
+}
+
+/// 班次的上下班时段，时间均为距当天0点的秒数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinTimeSection {
+    /// 时段id，获取排班信息时返回的字段名为id
+    #[serde(alias = "id")]
+    pub time_id: Option<i64>,
+    pub work_sec: i64,
+    pub off_work_sec: i64,
+    pub remind_work_sec: Option<i64>,
+    pub remind_off_work_sec: Option<i64>,
+    /// 休息开始时间
+    pub rest_begin_time: Option<i64>,
+    /// 休息结束时间
+    pub rest_end_time: Option<i64>,
+    pub allow_rest: Option<bool>,
+}
+
+impl WechatCpCheckinTimeSection {
+    /// 上班时间
+    pub fn work_time(&self) -> LabradorResult<NaiveTime> {
+        checkin_time_from_seconds(self.work_sec)
+    }
+
+    /// 下班时间，跨天班次为次日的时间
+    pub fn off_work_time(&self) -> LabradorResult<NaiveTime> {
+        checkin_time_from_seconds(self.off_work_sec)
+    }
+
+    /// 下班时间是否在次日
+    pub fn is_overnight(&self) -> bool {
+        self.off_work_sec >= SECONDS_PER_DAY
+    }
+}
+
+/// 企业打卡规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCorpCheckinOption {
+    /// 打卡规则类型：1-固定时间上下班；2-按班次上下班；3-自由上下班
+    pub grouptype: Option<i32>,
+    pub groupid: i64,
+    pub groupname: Option<String>,
+    /// 固定时间上下班时的打卡日期与时间
+    pub checkindate: Option<Vec<Value>>,
+    /// 特殊日期
+    pub spe_workdays: Option<Vec<Value>>,
+    pub spe_offdays: Option<Vec<Value>>,
+    /// 是否同步法定节假日
+    pub sync_holidays: Option<bool>,
+    pub need_photo: Option<bool>,
+    pub wifimac_infos: Option<Vec<Value>>,
+    pub loc_infos: Option<Vec<Value>>,
+    /// 打卡人员
+    pub range: Option<Value>,
+    #[serde(default, with = "option_timestamp_seconds")]
+    pub create_time: Option<DateTime<Utc>>,
+    pub white_users: Option<Vec<String>>,
+    /// 按班次上下班时的班次列表
+    #[serde(default)]
+    pub schedulelist: Vec<WechatCpCheckinScheduleInfo>,
+    pub ot_info: Option<Value>,
+}
+
+impl WechatCpCorpCheckinOption {
+    /// 按班次id查找班次
+    pub fn schedule(&self, schedule_id: i64) -> Option<&WechatCpCheckinScheduleInfo> {
+        self.schedulelist.iter().find(|v| v.schedule_id == schedule_id)
+    }
+}
+
+/// 排班
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WechatCpCheckinScheduleItem {
+    pub userid: String,
+    /// 排班日期，请求时转换为当月的第几天
+    #[serde(rename = "day", serialize_with = "serialize_day")]
+    pub date: NaiveDate,
+    /// 班次id，0表示休息
+    pub schedule_id: i64,
+}
+
+impl WechatCpCheckinScheduleItem {
+    pub fn new(userid: &str, date: NaiveDate, schedule_id: i64) -> Self {
+        WechatCpCheckinScheduleItem {
+            userid: userid.to_string(),
+            date,
+            schedule_id,
+        }
+    }
+}
+
+fn serialize_day<S: serde::Serializer>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32(date.day())
+}
+
+/// 打卡设备类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatCpCheckinDeviceType {
+    /// 门禁
+    AccessControl = 1,
+    /// 考勤机
+    AttendanceMachine = 2,
+    /// 其他
+    Other = 3,
+}
+
+/// 第三方设备的打卡记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCheckinRecord {
+    pub userid: String,
+    #[serde(with = "timestamp_seconds")]
+    pub checkin_time: DateTime<Utc>,
+    /// 打卡设备类型，见[`WechatCpCheckinDeviceType`]
+    pub device_type: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mediaids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 纬度，实际值乘1000000
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lat: Option<i64>,
+    /// 经度，实际值乘1000000
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lng: Option<i64>,
+    /// 设备名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wifiname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wifimac: Option<String>,
+}
+
+impl WechatCpCheckinRecord {
+    pub fn new(userid: &str, checkin_time: DateTime<Utc>, device_type: WechatCpCheckinDeviceType) -> Self {
+        WechatCpCheckinRecord {
+            userid: userid.to_string(),
+            checkin_time,
+            device_type: device_type as i32,
+            location_title: None,
+            location_detail: None,
+            mediaids: None,
+            notes: None,
+            lat: None,
+            lng: None,
+            device_detail: None,
+            wifiname: None,
+            wifimac: None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::Value;
+
+    use crate::{SimpleStorage, WechatCpClient, WechatCommonResponse, LabraError};
+    use crate::util::mock::{MockResponse, MockServer};
+
+    use super::*;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn user_ids(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("user{}", i)).collect()
+    }
+
+    #[test]
+    fn test_checkin_data_deserialize() {
+        let json = r#"{
+            "errcode": 0,
+            "errmsg": "ok",
+            "checkindata": [
+                {"userid": "james", "groupname": "打卡一组", "checkin_type": "上班打卡", "exception_type": "地点异常;时间异常", "checkin_time": 1492617610, "location_title": "依澜府", "location_detail": "四川省成都市武侯区益州大道中段784号附近", "wifiname": "办公一区", "notes": "路上堵车，迟到了5分钟", "wifimac": "3c:46:d8:0c:7a:70", "mediaids": ["WWCISP_G8PYgRaOVHjXWUWFqchpBqqqUpGj0OyR9z6WTwhnMZGCPHxyviVstiv_2fTG8YOJq8L8zJT2T2OvTebANV-2MQ"], "sch_checkin_time": 1492617600, "groupid": 1, "schedule_id": 0, "timeline_id": 2, "lat": 30547645, "lng": 104063236, "deviceid": "E5FA89F6-3926-4972-BE4F-4A7ACF4701E2"},
+                {"userid": "paul", "groupname": "打卡二组", "checkin_type": "外出打卡", "exception_type": "", "checkin_time": "1492617620", "location_title": "", "sch_checkin_time": 0},
+                {"userid": "bob", "checkin_type": "补卡", "checkin_time": 1492617630}
+            ]
+        }"#;
+        let v = serde_json::from_str::<Value>(json).unwrap();
+        let data = WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinData>>(v, "checkindata").unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0].checkin_type, WechatCpCheckinType::OnDuty);
+        assert_eq!(data[0].checkin_time, Utc.timestamp_opt(1492617610, 0).unwrap());
+        assert_eq!(data[0].sch_checkin_time, Some(Utc.timestamp_opt(1492617600, 0).unwrap()));
+        assert_eq!(data[0].exceptions(), vec!["地点异常", "时间异常"]);
+        assert_eq!(data[0].location(), Some((30.547645, 104.063236)));
+        assert_eq!(data[1].checkin_type, WechatCpCheckinType::Outside);
+        assert_eq!(data[1].checkin_time.timestamp(), 1492617620);
+        assert_eq!(data[1].sch_checkin_time, None);
+        assert!(data[1].exceptions().is_empty());
+        assert_eq!(data[1].location(), None);
+        assert_eq!(data[2].checkin_type, WechatCpCheckinType::Unknown);
+    }
+
+    #[test]
+    fn test_day_data_deserialize() {
+        let json = r#"{
+            "errcode": 0,
+            "errmsg": "ok",
+            "datas": [{
+                "base_info": {"date": 1599062400, "record_type": 1, "name": "张三", "name_ex": "Zhangsan", "departs_name": "有家企业/realempty;有家企业;有家企业/部门A4", "acctid": "ZhangSan", "rule_info": {"groupid": 10, "groupname": "规则测试", "scheduleid": 0, "schedulename": "", "checkintime": [{"work_sec": 38760, "off_work_sec": 38880}]}, "day_type": 0},
+                "summary_info": {"checkin_count": 2, "regular_work_sec": 31, "standard_work_sec": 120, "earliest_time": 38827, "lastest_time": 38858},
+                "holiday_infos": [],
+                "exception_infos": [{"count": 1, "duration": 60, "exception": 1}],
+                "ot_info": {"ot_status": 1, "ot_duration": 0, "exception_duration": []},
+                "sp_items": [{"count": 1, "duration": 0, "time_type": 0, "type": 1, "vacation_id": 2, "name": "年假"}]
+            }]
+        }"#;
+        let v = serde_json::from_str::<Value>(json).unwrap();
+        let data = WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinDayData>>(v, "datas").unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].base_info.date, Utc.timestamp_opt(1599062400, 0).unwrap());
+        assert_eq!(data[0].base_info.acctid.as_deref(), Some("ZhangSan"));
+        let summary = data[0].summary_info.as_ref().unwrap();
+        assert_eq!(summary.checkin_count, Some(2));
+        assert_eq!(summary.standard_work_sec, Some(120));
+    }
+
+    #[test]
+    fn test_limits() {
+        let start = Utc.timestamp_opt(1492617600, 0).unwrap();
+        assert!(check_window(start, start + Duration::days(30)).is_ok());
+        assert!(matches!(check_window(start, start + Duration::days(31)), Err(LabraError::RequestError(msg)) if msg.contains("30天")));
+        assert!(check_window(start + Duration::days(1), start).is_err());
+        assert!(check_users(&user_ids(100)).is_ok());
+        assert!(matches!(check_users(&user_ids(101)), Err(LabraError::RequestError(msg)) if msg.contains("101")));
+        assert!(check_users(&[]).is_err());
+    }
+
+    #[test]
+    fn test_checkin_time_seconds() {
+        let time = |h, m, s| NaiveTime::from_hms_opt(h, m, s).unwrap();
+        assert_eq!(checkin_time_from_seconds(0).unwrap(), time(0, 0, 0));
+        assert_eq!(checkin_time_from_seconds(32400).unwrap(), time(9, 0, 0));
+        assert_eq!(checkin_time_from_seconds(86399).unwrap(), time(23, 59, 59));
+        // 跨天班次取次日的时间
+        assert_eq!(checkin_time_from_seconds(86400 + 7200).unwrap(), time(2, 0, 0));
+        assert!(checkin_time_from_seconds(-1).is_err());
+        assert!(checkin_time_from_seconds(2 * 86400).is_err());
+        for seconds in [0, 1, 32400, 64800, 86399].iter() {
+            assert_eq!(checkin_seconds_from_time(checkin_time_from_seconds(*seconds).unwrap()), *seconds);
+        }
+        assert_eq!(checkin_seconds_from_time(NaiveTime::from_hms_milli_opt(18, 30, 0, 999).unwrap()), 66600);
+    }
+
+    #[test]
+    fn test_schedule_deserialize() {
+        let json = r#"{
+            "errcode": 0,
+            "errmsg": "ok",
+            "group": [{
+                "grouptype": 2, "groupid": 69, "groupname": "按班次上下班", "checkindate": [], "create_time": 1606204343,
+                "schedulelist": [
+                    {"schedule_id": 221, "schedule_name": "早班", "time_section": [{"time_id": 1, "work_sec": 32400, "off_work_sec": 64800, "remind_work_sec": 31800, "remind_off_work_sec": 64800, "rest_begin_time": 43200, "rest_end_time": 46800, "allow_rest": true}], "limit_aheadtime": 14400000, "noneed_offwork": false, "limit_offtime": 14400, "flex_on_duty_time": 0, "flex_off_duty_time": 0, "allow_flex": false},
+                    {"schedule_id": 222, "schedule_name": "夜班", "time_section": [{"time_id": 1, "work_sec": 79200, "off_work_sec": 108000}]}
+                ]
+            }]
+        }"#;
+        let v = serde_json::from_str::<Value>(json).unwrap();
+        let options = WechatCommonResponse::parse_with_key::<Vec<WechatCpCorpCheckinOption>>(v, "group").unwrap();
+        let night = options[0].schedule(222).unwrap();
+        assert_eq!(night.time_section[0].work_time().unwrap(), NaiveTime::from_hms_opt(22, 0, 0).unwrap());
+        assert_eq!(night.time_section[0].off_work_time().unwrap(), NaiveTime::from_hms_opt(6, 0, 0).unwrap());
+        assert!(night.time_section[0].is_overnight());
+        assert!(!options[0].schedule(221).unwrap().time_section[0].is_overnight());
+        assert!(options[0].schedule(0).is_none());
+
+        let json = r#"{
+            "errcode": 0,
+            "errmsg": "ok",
+            "schedule_list": [{
+                "userid": "zhangsan", "yearmonth": 202011, "groupid": 69, "groupname": "按班次上下班",
+                "schedule": {"scheduleList": [{"day": 1, "schedule_info": {"schedule_id": 221, "schedule_name": "早班", "time_section": [{"id": 1, "work_sec": 32400, "off_work_sec": 64800, "remind_work_sec": 31800, "remind_off_work_sec": 64800}]}}]}
+            }]
+        }"#;
+        let v = serde_json::from_str::<Value>(json).unwrap();
+        let schedules = WechatCommonResponse::parse_with_key::<Vec<WechatCpCheckinSchedule>>(v, "schedule_list").unwrap();
+        let day = &schedules[0].schedule.as_ref().unwrap().schedule_list[0];
+        assert_eq!(day.day, 1);
+        assert_eq!(day.schedule_info.time_section[0].time_id, Some(1));
+        assert_eq!(day.schedule_info.time_section[0].work_time().unwrap(), NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_schedule_window() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let items = |dates: &[&str]| dates.iter().map(|v| WechatCpCheckinScheduleItem::new("zhangsan", date(v), 221)).collect::<Vec<_>>();
+        let today = date("2020-11-15");
+        assert_eq!(check_schedule_items(&items(&["2020-11-01", "2020-11-30"]), today).unwrap(), 202011);
+        assert_eq!(check_schedule_items(&items(&["2020-12-31"]), today).unwrap(), 202012);
+        // 跨年
+        assert_eq!(check_schedule_items(&items(&["2021-01-01"]), date("2020-12-31")).unwrap(), 202101);
+        // 月末时下个月不会被跳过
+        assert_eq!(check_schedule_items(&items(&["2021-02-28"]), date("2021-01-31")).unwrap(), 202102);
+        assert!(matches!(check_schedule_items(&items(&["2021-01-01"]), today), Err(LabraError::RequestError(msg)) if msg.contains("2020-11-01至2020-12-31")));
+        assert!(check_schedule_items(&items(&["2020-10-31"]), today).is_err());
+        assert!(matches!(check_schedule_items(&items(&["2020-11-30", "2020-12-01"]), today), Err(LabraError::RequestError(msg)) if msg.contains("同一个月")));
+        assert!(check_schedule_items(&[], today).is_err());
+
+        let body = serde_json::to_value(&items(&["2020-11-05"])).unwrap();
+        assert_eq!(body, serde_json::json!([{"userid": "zhangsan", "day": 5, "schedule_id": 221}]));
+    }
+
+    #[tokio::test]
+    async fn test_get_checkin_data_chunked() {
+        let first = r#"{"errcode":0,"errmsg":"ok","checkindata":[{"userid":"user0","checkin_type":"上班打卡","checkin_time":1492617610}]}"#;
+        let second = r#"{"errcode":0,"errmsg":"ok","checkindata":[{"userid":"user100","checkin_type":"下班打卡","checkin_time":1492650010},{"userid":"user149","checkin_type":"下班打卡","checkin_time":1492650020}]}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(first), MockResponse::json(second)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("checkin_chunked_corp", "secret").base_url(&server.url);
+        let start = Utc.timestamp_opt(1492617600, 0).unwrap();
+        let data = client.checkin().get_checkin_data_chunked(WechatCpCheckinDataType::All, start, start + Duration::days(1), user_ids(150)).await.unwrap();
+        assert_eq!(data.iter().map(|v| v.userid.as_str()).collect::<Vec<_>>(), vec!["user0", "user100", "user149"]);
+        assert_eq!(data[1].checkin_type, WechatCpCheckinType::OffDuty);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        let bodies = requests[1..].iter().map(|r| serde_json::from_str::<Value>(r.split("\r\n\r\n").nth(1).unwrap()).unwrap()).collect::<Vec<_>>();
+        assert!(requests[1].starts_with("POST /cgi-bin/checkin/getcheckindata?access_token=ACCESS_TOKEN"));
+        assert_eq!(bodies[0]["opencheckindatatype"], 3);
+        assert_eq!(bodies[0]["starttime"], 1492617600);
+        assert_eq!(bodies[0]["useridlist"].as_array().unwrap().len(), 100);
+        assert_eq!(bodies[1]["useridlist"].as_array().unwrap().len(), 50);
+        assert_eq!(bodies[1]["useridlist"][0], "user100");
+    }
+}
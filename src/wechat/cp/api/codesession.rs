@@ -6,20 +6,26 @@ use crate::wechat::cp::method::WechatCpMethod;
 
 
 #[derive(Debug, Clone)]
-pub struct WechatCpCodeSession<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpCodeSession<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpCodeSession<'a, T> {
+impl<T: SessionStore> WechatCpCodeSession<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpCodeSession<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpCodeSession<T> {
         WechatCpCodeSession {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.code_session()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpCodeSession<T> {
+        Self::from_client(client.clone())
+    }
+
     /// # 小程序登录凭证校验
     pub async fn jscode_2_session(&self, code: &str) -> LabradorResult<WechatCpJsCodeSession> {
         let v = self.client.get(WechatCpMethod::JsCode2Session, vec![
@@ -0,0 +1,190 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient};
+use crate::serde_helper::string_or_number;
+use crate::wechat::cp::method::{CpCorpGroupMethod, WechatCpMethod};
+use crate::wechat::token_cache::TokenRequest;
+
+/// 上下游（企业互联）
+///
+/// 上游企业把自建应用共享给下游企业后，可获取下游企业的access_token，
+/// 再通过[`WechatCpClient::for_linked_corp`]以下游企业身份调用接口。
+#[derive(Debug, Clone)]
+pub struct WechatCpCorpGroup<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpCorpGroup<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpCorpGroup<T> {
+        WechatCpCorpGroup {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.corp_group()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpCorpGroup<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取应用共享信息.
+    /// <pre>
+    /// 上级企业通过该接口获取某个应用分享给的所有企业列表
+    /// business_type：0-企业互联/局校互联，1-上下游企业，默认为0
+    /// corpid：下级企业corpid，填写时只返回该企业的共享信息
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93403">文档</a>
+    /// </pre>
+    pub async fn list_app_share_info(&self, agentid: i64, business_type: Option<i32>, corpid: Option<&str>, limit: Option<i32>, cursor: Option<&str>) -> LabradorResult<WechatCpCorpGroupAppShareInfo> {
+        let mut req = json!({
+            "agentid": agentid,
+        });
+        if let Some(business_type) = business_type {
+            req["business_type"] = business_type.into();
+        }
+        if let Some(corpid) = corpid {
+            req["corpid"] = corpid.into();
+        }
+        if let Some(limit) = limit {
+            req["limit"] = limit.into();
+        }
+        if let Some(cursor) = cursor {
+            req["cursor"] = cursor.into();
+        }
+        let v = self.client.post(WechatCpMethod::CorpGroup(CpCorpGroupMethod::ListAppShareInfo), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpCorpGroupAppShareInfo>(v)
+    }
+
+    /// 获取下级企业的access_token.
+    /// <pre>
+    /// 获取应用可见范围内下级企业的access_token，该access_token可用于调用下级企业通讯录的只读接口
+    /// 结果按上级企业、下级企业与应用缓存在会话存储中，过期前重复调用直接返回缓存
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93359">文档</a>
+    /// </pre>
+    pub async fn get_corp_token(&self, corpid: &str, business_type: i32, agentid: i64) -> LabradorResult<String> {
+        let inner = &self.client.inner;
+        let token_key = format!("{}_corpgroup_{}_{}_access_token_cp", inner.corp_id, corpid, agentid);
+        let expires_key = format!("{}_corpgroup_{}_{}_expires_at_cp", inner.corp_id, corpid, agentid);
+        let token_req = TokenRequest { session: inner.client.session(), token_key: &token_key, expires_key: &expires_key, appid: corpid, metrics: inner.client.metrics() };
+        let (info, _) = inner.tokens.token_info(&token_req, false, || async {
+            let v = self.client.post(WechatCpMethod::CorpGroup(CpCorpGroupMethod::GetToken), vec![], json!({
+                "corpid": corpid,
+                "business_type": business_type,
+                "agentid": agentid,
+            }), RequestType::Json).await?.json::<Value>()?;
+            let res = WechatCommonResponse::parse::<WechatCpCorpGroupToken>(v)?;
+            Ok((res.access_token, res.expires_in))
+        }).await?;
+        Ok(info.token)
+    }
+
+    /// 获取下级企业的access_token，并构造以下级企业身份调用接口的客户端
+    pub async fn linked_corp_client(&self, corpid: &str, business_type: i32, agentid: i64) -> LabradorResult<WechatCpClient<T>> {
+        let access_token = self.get_corp_token(corpid, business_type, agentid).await?;
+        Ok(self.client.for_linked_corp(corpid, &access_token))
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCorpGroupAppShareInfo {
+    /// 1表示拉取完毕，0表示还有更多数据
+    #[serde(default)]
+    pub ending: i32,
+    #[serde(default)]
+    pub corp_list: Vec<WechatCpCorpGroupCorp>,
+    /// 分页游标，再下次请求时填写以获取之后分页的记录
+    pub next_cursor: Option<String>,
+}
+
+impl WechatCpCorpGroupAppShareInfo {
+    pub fn has_more(&self) -> bool {
+        self.ending == 0 && self.next_cursor.as_deref().map(|v| !v.is_empty()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCorpGroupCorp {
+    /// 下级企业corpid
+    pub corpid: String,
+    /// 下级企业名称
+    pub corp_name: Option<String>,
+    /// 下级企业应用id
+    pub agentid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCorpGroupToken {
+    pub access_token: String,
+    #[serde(with = "string_or_number")]
+    pub expires_in: i64,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{SessionStore, SimpleStorage, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_list_app_share_info() {
+        let list = r#"{"errcode":0,"errmsg":"ok","ending":0,"corp_list":[{"corpid":"wwcorp1","corp_name":"测试企业1","agentid":1111}],"next_cursor":"CURSOR"}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(list)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("corpgroup_list_corp", "secret").base_url(&server.url);
+        let info = client.corp_group().list_app_share_info(1000001, Some(1), None, Some(100), None).await.unwrap();
+        assert!(info.has_more());
+        assert_eq!(info.corp_list[0].corpid, "wwcorp1");
+        assert_eq!(info.corp_list[0].agentid, 1111);
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/corpgroup/corp/list_app_share_info?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"agentid":1000001,"business_type":1,"limit":100}"#));
+    }
+
+    #[tokio::test]
+    async fn test_get_corp_token_cached() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","access_token":"CORP1_TOKEN","expires_in":7200}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","access_token":"CORP2_TOKEN","expires_in":7200}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("corpgroup_token_corp", "secret").base_url(&server.url);
+        assert_eq!(client.corp_group().get_corp_token("wwcorp1", 1, 1111).await.unwrap(), "CORP1_TOKEN");
+        // 再次获取命中缓存
+        assert_eq!(client.corp_group().get_corp_token("wwcorp1", 1, 1111).await.unwrap(), "CORP1_TOKEN");
+        // 不同的下级企业各自缓存
+        assert_eq!(client.corp_group().get_corp_token("wwcorp2", 1, 2222).await.unwrap(), "CORP2_TOKEN");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].ends_with(r#"{"agentid":1111,"business_type":1,"corpid":"wwcorp1"}"#));
+
+        let session = client.inner.client.session();
+        let cached: Option<String> = session.get("corpgroup_token_corp_corpgroup_wwcorp1_1111_access_token_cp", None).unwrap();
+        assert_eq!(cached.as_deref(), Some("CORP1_TOKEN"));
+        // 上级企业自身的token不受影响
+        let own: Option<String> = session.get("corpgroup_token_corp_access_token_cp", None).unwrap();
+        assert_eq!(own.as_deref(), Some("ACCESS_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_linked_corp_client() {
+        let list = r#"{"errcode":0,"errmsg":"ok","department":[{"id":2,"name":"下级企业部门","parentid":1,"order":10}]}"#;
+        let server = MockServer::start(vec![MockResponse::json(list)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("corpgroup_parent_corp", "secret").base_url(&server.url);
+        let linked = client.for_linked_corp("wwcorp1", "CORP1_TOKEN");
+        assert_eq!(linked.access_token(false).await.unwrap(), "CORP1_TOKEN");
+        let departments = linked.department().list(None).await.unwrap();
+        assert_eq!(departments.department.len(), 1);
+        // 不会去获取上级企业的token
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("GET /cgi-bin/department/list?access_token=CORP1_TOKEN HTTP/1.1"));
+        assert!(client.inner.access_token.is_none());
+    }
+}
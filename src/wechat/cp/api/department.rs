@@ -6,20 +6,26 @@ use crate::wechat::cp::method::{CpDepartmentMethod, WechatCpMethod};
 
 /// 部门管理
 #[derive(Debug, Clone)]
-pub struct WechatCpDepartment<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpDepartment<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpDepartment<'a, T> {
+impl<T: SessionStore> WechatCpDepartment<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpDepartment<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpDepartment<T> {
         WechatCpDepartment {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.department()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpDepartment<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 部门管理接口 - 创建部门.
     /// 最多支持创建500个部门
@@ -28,6 +34,7 @@ impl<'a, T: SessionStore> WechatCpDepartment<'a, T> {
     pub async fn create(&self, req: WechatCpDepartInfo) -> LabradorResult<i64> {
 
         let v = self.client.post(WechatCpMethod::Department(CpDepartmentMethod::Create), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        self.invalidate_cache();
         let v = WechatCommonResponse::parse::<Value>(v)?;
         let tag_id = v["id"].as_i64().unwrap_or_default();
         Ok(tag_id)
@@ -66,7 +73,9 @@ impl<'a, T: SessionStore> WechatCpDepartment<'a, T> {
     /// 如果id为0(未部门),1(黑名单),2(星标组)，或者不存在的id，微信会返回系统繁忙的错误
     /// </pre>
     pub async fn update(&self, req: WechatCpDepartInfo) -> LabradorResult<WechatCommonResponse> {
-        self.client.post(WechatCpMethod::Department(CpDepartmentMethod::Update), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        let res = self.client.post(WechatCpMethod::Department(CpDepartmentMethod::Update), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>();
+        self.invalidate_cache();
+        res
     }
 
     /// <pre>
@@ -75,7 +84,16 @@ impl<'a, T: SessionStore> WechatCpDepartment<'a, T> {
     /// 应用须拥有指定部门的管理权限
     /// </pre>
     pub async fn delete(&self, depart_id: i64) -> LabradorResult<WechatCommonResponse> {
-        self.client.get(WechatCpMethod::Department(CpDepartmentMethod::Delete(depart_id)), vec![], RequestType::Json).await?.json::<WechatCommonResponse>()
+        let res = self.client.get(WechatCpMethod::Department(CpDepartmentMethod::Delete(depart_id)), vec![], RequestType::Json).await?.json::<WechatCommonResponse>();
+        self.invalidate_cache();
+        res
+    }
+
+    /// 部门变更后使部门列表的缓存失效
+    fn invalidate_cache(&self) {
+        if let Err(err) = self.client.invalidate_cache_prefix("/cgi-bin/department") {
+            tracing::warn!("[响应缓存] 使部门列表失效失败:{}", err);
+        }
     }
 }
 
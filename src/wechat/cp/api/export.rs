@@ -0,0 +1,284 @@
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType, LabraRequest, Method}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient, WechatCrypto};
+use crate::wechat::cp::method::{CpExportMethod, WechatCpMethod};
+
+/// 每块数据的最小人员/部门数
+const MIN_BLOCK_SIZE: i64 = 10_000;
+/// 每块数据的最大人员/部门数
+const MAX_BLOCK_SIZE: i64 = 1_000_000;
+
+/// 异步导出接口
+///
+/// 导出任务完成后通过[`WechatCpExport::get_result`]获取数据文件的下载地址，
+/// 文件使用任务提交时的encoding_aeskey加密（AES-256-CBC，PKCS#7填充至32字节的倍数，IV取AESKey前16字节），
+/// 可以使用[`WechatCpExport::download_and_decrypt`]下载并解密。
+#[derive(Debug, Clone)]
+pub struct WechatCpExport<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpExport<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpExport<T> {
+        WechatCpExport {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.export()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpExport<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 导出成员.
+    /// <pre>
+    /// 导出的字段仅包含userid，name，department，open_userid，返回jobid
+    /// block_size为每块数据的人员数，支持范围[10^4,10^6]，默认值为10^6
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/export/simple_user?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94849">文档</a>
+    /// </pre>
+    pub async fn export_simple_user(&self, encoding_aeskey: &str, block_size: Option<i64>) -> LabradorResult<String> {
+        self.export(CpExportMethod::SimpleUser, encoding_aeskey, block_size).await
+    }
+
+    /// 导出成员详情.
+    /// <pre>
+    /// 导出的字段请参考“读取成员”接口，返回jobid
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94851">文档</a>
+    /// </pre>
+    pub async fn export_user(&self, encoding_aeskey: &str, block_size: Option<i64>) -> LabradorResult<String> {
+        self.export(CpExportMethod::User, encoding_aeskey, block_size).await
+    }
+
+    /// 导出部门.
+    /// <pre>
+    /// 导出的字段包含id，name，parentid，order，返回jobid
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94852">文档</a>
+    /// </pre>
+    pub async fn export_department(&self, encoding_aeskey: &str, block_size: Option<i64>) -> LabradorResult<String> {
+        self.export(CpExportMethod::Department, encoding_aeskey, block_size).await
+    }
+
+    async fn export(&self, method: CpExportMethod, encoding_aeskey: &str, block_size: Option<i64>) -> LabradorResult<String> {
+        if encoding_aeskey.len() != 43 {
+            return Err(LabraError::RequestError("encoding_aeskey长度必须为43位".to_string()));
+        }
+        if let Some(block_size) = block_size {
+            if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) {
+                return Err(LabraError::RequestError(format!("block_size必须在{}到{}之间", MIN_BLOCK_SIZE, MAX_BLOCK_SIZE)));
+            }
+        }
+        let mut req = json!({
+            "encoding_aeskey": encoding_aeskey,
+        });
+        if let Some(block_size) = block_size {
+            req["block_size"] = block_size.into();
+        }
+        let v = self.client.post(WechatCpMethod::Export(method), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["jobid"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// 获取导出结果.
+    /// <pre>
+    /// 获取任务结果的调用身份需要与提交任务的一致，任务完成前需轮询
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/export/get_result?access_token=ACCESS_TOKEN&jobid=jobid_xxxxxxxxxxxxxxx">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94854">文档</a>
+    /// </pre>
+    pub async fn get_result(&self, jobid: &str) -> LabradorResult<WechatCpExportResult> {
+        let v = self.client.get(WechatCpMethod::Export(CpExportMethod::GetResult), vec![("jobid".to_string(), jobid.to_string())], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpExportResult>(v)
+    }
+
+    /// 下载导出的数据文件并解密.
+    /// <pre>
+    /// url为get_result返回的下载地址，aeskey为提交任务时的encoding_aeskey
+    /// 成员数据可解析为[`WechatCpExportUserData`]，部门数据可解析为[`WechatCpExportDepartmentData`]
+    /// </pre>
+    pub async fn download_and_decrypt<D: DeserializeOwned>(&self, url: &str, aeskey: &str) -> LabradorResult<D> {
+        let data = LabraRequest::<String>::new().url(url.to_string()).method(Method::Get).req_type(RequestType::Json).request().await?.bytes()?;
+        decrypt_export_data(&data, aeskey)
+    }
+}
+
+/// 解密导出的数据文件
+pub fn decrypt_export_data<D: DeserializeOwned>(data: &[u8], aeskey: &str) -> LabradorResult<D> {
+    let data = WechatCrypto::new(aeskey).decrypt_file(data)?;
+    serde_json::from_slice::<D>(&data).map_err(LabraError::from)
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 导出任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatCpExportStatus {
+    /// 未处理
+    Pending,
+    /// 处理中
+    Processing,
+    /// 完成
+    Finished,
+    /// 异常失败
+    Failed,
+    Unknown(i32),
+}
+
+impl From<i32> for WechatCpExportStatus {
+    fn from(v: i32) -> Self {
+        match v {
+            0 => WechatCpExportStatus::Pending,
+            1 => WechatCpExportStatus::Processing,
+            2 => WechatCpExportStatus::Finished,
+            3 => WechatCpExportStatus::Failed,
+            v => WechatCpExportStatus::Unknown(v),
+        }
+    }
+}
+
+impl From<WechatCpExportStatus> for i32 {
+    fn from(v: WechatCpExportStatus) -> Self {
+        match v {
+            WechatCpExportStatus::Pending => 0,
+            WechatCpExportStatus::Processing => 1,
+            WechatCpExportStatus::Finished => 2,
+            WechatCpExportStatus::Failed => 3,
+            WechatCpExportStatus::Unknown(v) => v,
+        }
+    }
+}
+
+impl WechatCpExportStatus {
+    /// 是否需要继续轮询
+    pub fn is_pending(&self) -> bool {
+        matches!(self, WechatCpExportStatus::Pending | WechatCpExportStatus::Processing)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpExportResult {
+    pub status: WechatCpExportStatus,
+    #[serde(default)]
+    pub data_list: Vec<WechatCpExportFile>,
+}
+
+impl WechatCpExportResult {
+    /// 数据文件的下载地址
+    pub fn urls(&self) -> Vec<&str> {
+        self.data_list.iter().map(|v| v.url.as_str()).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpExportFile {
+    /// 数据下载链接,支持指定Range头部分段下载。有效期2个小时
+    pub url: String,
+    /// 密文数据大小
+    pub size: Option<i64>,
+    /// 密文数据md5
+    pub md5: Option<String>,
+}
+
+/// 导出的成员数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpExportUserData {
+    #[serde(default)]
+    pub userlist: Vec<WechatCpExportUser>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpExportUser {
+    pub userid: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub department: Vec<i64>,
+    pub open_userid: Option<String>,
+}
+
+/// 导出的部门数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpExportDepartmentData {
+    #[serde(default)]
+    pub department: Vec<WechatCpExportDepartment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpExportDepartment {
+    pub id: i64,
+    pub name: Option<String>,
+    pub parentid: Option<i64>,
+    pub order: Option<i64>,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use openssl::symm::{Cipher, Crypter, Mode};
+
+    use crate::{SimpleStorage, WechatCpClient, WechatCommonResponse, LabraError};
+
+    use super::*;
+
+    const AES_KEY: &str = "kWxPEV2UEDyxWpmPdKC3F4dgPDmOvfKX1HGnEUDS1aR";
+
+    /// 按企业微信的方式加密：AES-256-CBC，PKCS#7填充至32字节的倍数，IV取key前16字节
+    fn encrypt(plaintext: &[u8]) -> Vec<u8> {
+        let key = base64::decode_config(format!("{}=", AES_KEY), base64::STANDARD.decode_allow_trailing_bits(true)).unwrap();
+        let pad = 32 - plaintext.len() % 32;
+        let mut data = plaintext.to_vec();
+        data.extend(vec![pad as u8; pad]);
+        let mut crypter = Crypter::new(Cipher::aes_256_cbc(), Mode::Encrypt, &key, Some(&key[..16])).unwrap();
+        crypter.pad(false);
+        let mut out = vec![0; data.len() + 32];
+        let mut count = crypter.update(&data, &mut out).unwrap();
+        count += crypter.finalize(&mut out[count..]).unwrap();
+        out.truncate(count);
+        out
+    }
+
+    #[test]
+    fn test_decrypt_export_data() {
+        let chunk = encrypt(r#"{"userlist":[{"userid":"zhangsan","name":"张三","department":[1,2],"open_userid":"xxxxx"},{"userid":"lisi","department":[1]}]}"#.as_bytes());
+        assert_eq!(chunk.len() % 32, 0);
+        let data = decrypt_export_data::<WechatCpExportUserData>(&chunk, AES_KEY).unwrap();
+        assert_eq!(data.userlist.len(), 2);
+        assert_eq!(data.userlist[0].name.as_deref(), Some("张三"));
+        assert_eq!(data.userlist[0].department, vec![1, 2]);
+        assert!(data.userlist[1].open_userid.is_none());
+
+        let chunk = encrypt(r#"{"department":[{"id":1,"name":"总公司","parentid":0,"order":100000000},{"id":2,"name":"研发部","parentid":1,"order":99999000}]}"#.as_bytes());
+        let data = decrypt_export_data::<WechatCpExportDepartmentData>(&chunk, AES_KEY).unwrap();
+        assert_eq!(data.department[1].parentid, Some(1));
+
+        assert!(decrypt_export_data::<WechatCpExportUserData>(&chunk[..chunk.len() - 1], AES_KEY).is_err());
+    }
+
+    #[test]
+    fn test_export_result_status() {
+        let statuses = (0..5).map(|v| serde_json::from_value::<WechatCpExportStatus>(v.into()).unwrap()).collect::<Vec<_>>();
+        assert_eq!(statuses, vec![WechatCpExportStatus::Pending, WechatCpExportStatus::Processing, WechatCpExportStatus::Finished, WechatCpExportStatus::Failed, WechatCpExportStatus::Unknown(4)]);
+        assert!(statuses[0].is_pending() && statuses[1].is_pending() && !statuses[2].is_pending());
+        assert_eq!(serde_json::to_value(WechatCpExportStatus::Finished).unwrap(), 2);
+
+        let json = r#"{"errcode":0,"errmsg":"ok","status":2,"data_list":[{"url":"https://xxxx","size":1,"md5":"xxxxxxxxx"},{"url":"https://yyyy","size":1,"md5":"yyyyyyyyy"}]}"#;
+        let result = WechatCommonResponse::parse::<WechatCpExportResult>(serde_json::from_str(json).unwrap()).unwrap();
+        assert_eq!(result.status, WechatCpExportStatus::Finished);
+        assert_eq!(result.urls(), vec!["https://xxxx", "https://yyyy"]);
+
+        let result = WechatCommonResponse::parse::<WechatCpExportResult>(serde_json::from_str(r#"{"errcode":0,"errmsg":"ok","status":1}"#).unwrap()).unwrap();
+        assert!(result.status.is_pending() && result.data_list.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_params() {
+        let client = WechatCpClient::<SimpleStorage>::new("export_params_corp", "secret");
+        assert!(matches!(client.export().export_simple_user("short", None).await, Err(LabraError::RequestError(_))));
+        assert!(matches!(client.export().export_user(AES_KEY, Some(100)).await, Err(LabraError::RequestError(_))));
+    }
+}
@@ -1,26 +1,34 @@
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
+use futures_util::Stream;
 
-use crate::{session::SessionStore, LabradorResult, RequestType, WechatCpClient, LabraError, WechatCommonResponse};
+use crate::{session::SessionStore, LabradorResult, RequestType, WechatCpClient, LabraError, WechatCommonResponse, WechatEnvelope, LazyPage, PagedStream};
 use crate::wechat::cp::constants::{CURSOR, EXTERNAL_USERID, USERID, WELCOME_MSG_TYPE_FILE, WELCOME_MSG_TYPE_IMAGE, WELCOME_MSG_TYPE_LINK, WELCOME_MSG_TYPE_MINIPROGRAM, WELCOME_MSG_TYPE_VIDEO};
 use crate::wechat::cp::method::{CpExternalContactMethod, WechatCpMethod};
+use crate::serde_helper::{string_or_number, option_string_or_number};
 
 
 /// 外部联系人管理接口
 #[derive(Debug, Clone)]
-pub struct WechatCpExternalContact<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpExternalContact<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpExternalContact<'a, T> {
+impl<T: SessionStore> WechatCpExternalContact<T> {
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpExternalContact<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpExternalContact<T> {
         WechatCpExternalContact {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.external_contact()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpExternalContact<T> {
+        Self::from_client(client.clone())
+    }
+
     /// 配置客户联系「联系我」方式
     /// <pre>
     /// 企业可以在管理后台-客户联系中配置成员的「联系我」的二维码或者小程序按钮，客户通过扫描二维码或点击小程序上的按钮，即可获取成员联系方式，主动联系到成员。
@@ -222,6 +230,39 @@ impl<'a, T: SessionStore> WechatCpExternalContact<'a, T> {
         WechatCommonResponse::parse::<WechatCpExternalContactBatchInfoResponse>(v)
     }
 
+    /// 批量获取全部客户详情
+    /// <pre>
+    /// 基于get_contact_detail_batch按next_cursor自动翻页，消费时才会发起请求。
+    /// 每页的external_contact_list逐条解析，不会整页反序列化，适合跟进记录很多的成员。
+    /// `limit` 每页返回的条数，`max_items` 最多返回的条数
+    /// </pre>
+    pub fn list_all_contact_detail(&self, userid_list: Vec<String>, limit: Option<i32>, max_items: Option<usize>) -> impl Stream<Item = LabradorResult<ExternalContactInfo>> + '_ {
+        let client = self.client.to_owned();
+        let mut pager = PagedStream::new(move |cursor: Option<String>| {
+            let userid_list = userid_list.to_owned();
+            let client = client.to_owned();
+            async move {
+                let mut req = json!({
+                    "userid_list": userid_list,
+                });
+                if let Some(cursor) = cursor {
+                    req["cursor"] = cursor.into();
+                }
+                if let Some(limit) = limit {
+                    req["limit"] = limit.into();
+                }
+                let res = client.post(WechatCpMethod::ExternalContact(CpExternalContactMethod::BatchGetByUser), vec![], req, RequestType::Json).await?;
+                WechatEnvelope::new().check_slice(&res.bytes()?)?;
+                let next_cursor = res.json_pointer::<Option<String>>("/next_cursor")?.flatten();
+                Ok(LazyPage::with_cursor(res.json_stream::<ExternalContactInfo>("/external_contact_list")?, next_cursor))
+            }
+        });
+        if let Some(max_items) = max_items {
+            pager = pager.max_items(max_items);
+        }
+        pager.into_lazy_stream()
+    }
+
     /// 修改客户备注信息.
     /// <pre>
     /// 企业可通过此接口修改指定用户添加的客户的备注信息。
@@ -861,7 +902,7 @@ pub struct WechatCpGroupJoinWayResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatCpExternalContactBatchInfoResponse {
-    pub external_contact_list: Option<ExternalContactInfo>,
+    pub external_contact_list: Option<Vec<ExternalContactInfo>>,
     pub next_cursor: Option<String>,
 }
 
@@ -960,6 +1001,7 @@ pub struct WechatCpUserTransferCustomerResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferCustomer {
     /// 对此客户进行分配的结果, 0表示成功发起接替,待24小时后自动接替,并不代表最终接替成功
+    #[serde(with = "string_or_number")]
     pub errcode: u8,
     /// 客户的external_userid
     pub external_userid: Option<String>,
@@ -1013,6 +1055,7 @@ pub struct GroupChat {
     pub name: Option<String>,
     pub owner: Option<String>,
     pub notice: Option<String>,
+    #[serde(default, with = "option_string_or_number")]
     pub create_time: Option<u64>,
     pub member_list: Option<Vec<GroupMember>>,
     pub admin_list: Option<Vec<GroupAdmin>>,
@@ -1273,6 +1316,7 @@ pub struct WechatCpUserExternalTagGroupInfo {
 pub struct TagGroup {
     pub group_id: Option<String>,
     pub group_name: Option<String>,
+    #[serde(default, with = "option_string_or_number")]
     pub create_time: Option<u64>,
     pub order: Option<u64>,
     pub deleted: Option<bool>,
@@ -1284,6 +1328,7 @@ pub struct CustomerTag {
     /// 客户群ID
     pub id: Option<String>,
     pub name: Option<String>,
+    #[serde(default, with = "option_string_or_number")]
     pub create_time: Option<u64>,
     pub order: Option<u64>,
     pub deleted: Option<bool>,
@@ -1309,6 +1354,7 @@ pub struct ExternalContactGroupMsgInfo {
     pub msgid: Option<String>,
     pub creator: Option<String>,
     pub create_type: Option<u8>,
+    #[serde(default, with = "option_string_or_number")]
     pub create_time: Option<u64>,
     pub text: Option<WechatCpTextMsg>,
     pub attachments: Option<Vec<WechatCpAttachment>>,
@@ -1384,3 +1430,75 @@ pub struct WechatCpGroupWelcomeTemplateInfo {
     /// 是否通知成员将这条入群欢迎语应用到客户群中，0-不通知，1-通知， 不填则通知
     pub notify: Option<u8>,
 }
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use futures_util::StreamExt;
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, LabradorResult, SimpleStorage, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::*;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn contact(external_userid: &str) -> Value {
+        json!({
+            "external_contact": {"external_userid": external_userid, "name": "李四", "type": 1, "gender": 1},
+            "follow_info": {"userid": "rocky", "remark": "李部长", "createtime": 1525779812, "tag_id": ["etAJ2GCAAAXtWyujaWJHDDGi0mACHAAA"], "remark_mobiles": ["10000000003"], "oper_userid": "rocky"}
+        })
+    }
+
+    fn page(ids: &[&str], next_cursor: &str) -> MockResponse {
+        let list = ids.iter().map(|id| contact(id)).collect::<Vec<_>>();
+        MockResponse::json(&json!({"errcode": 0, "errmsg": "ok", "external_contact_list": list, "next_cursor": next_cursor}).to_string())
+    }
+
+    fn ids(items: &[ExternalContactInfo]) -> Vec<String> {
+        items.iter().map(|v| v.external_contact.as_ref().and_then(|v| v.external_userid.to_owned()).unwrap_or_default()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_all_contact_detail() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            page(&["wm_01", "wm_02"], "CURSOR_1"),
+            page(&["wm_03"], ""),
+            page(&["wm_01", "wm_02"], "CURSOR_1"),
+            page(&["wm_03"], ""),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("ww_cp_contact_list_all", "secret").base_url(&server.url);
+        let contact = client.external_contact();
+        let streamed = contact.list_all_contact_detail(vec!["rocky".to_string()], Some(2), None).collect::<Vec<_>>().await
+            .into_iter().collect::<LabradorResult<Vec<_>>>().unwrap();
+        // 与整页解析的结果一致
+        let first = contact.get_contact_detail_batch(vec!["rocky".to_string()], None, Some(2)).await.unwrap();
+        let second = contact.get_contact_detail_batch(vec!["rocky".to_string()], first.next_cursor.as_deref(), Some(2)).await.unwrap();
+        let mut parsed = first.external_contact_list.unwrap();
+        parsed.extend(second.external_contact_list.unwrap());
+        assert_eq!(ids(&streamed), vec!["wm_01", "wm_02", "wm_03"]);
+        assert_eq!(serde_json::to_value(&streamed).unwrap(), serde_json::to_value(&parsed).unwrap());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 5);
+        assert!(requests[1].starts_with("POST /cgi-bin/externalcontact/batch/get_by_user?"), "{}", requests[1]);
+        let body = |i: usize| serde_json::from_str::<Value>(requests[i].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body(1), json!({"userid_list": ["rocky"], "limit": 2}));
+        assert_eq!(body(2), json!({"userid_list": ["rocky"], "cursor": "CURSOR_1", "limit": 2}));
+    }
+
+    #[tokio::test]
+    async fn test_list_all_contact_detail_error() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            page(&["wm_01"], "CURSOR_1"),
+            MockResponse::json(r#"{"errcode":84061,"errmsg":"not exist external contact"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("ww_cp_contact_list_all_error", "secret").base_url(&server.url);
+        let items = client.external_contact().list_all_contact_detail(vec!["rocky".to_string()], None, None).collect::<Vec<_>>().await;
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_ok());
+        assert!(matches!(&items[1], Err(LabraError::ClientError { errcode, .. }) if errcode == "84061"));
+    }
+}
@@ -1,28 +1,34 @@
 use serde::{Serialize, Deserialize};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient, LabraError};
+use crate::{session::SessionStore, request::{Method, RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient, LabraError};
 use crate::wechat::cp::constants::{ GROUP_ROBOT_MSG_IMAGE, GROUP_ROBOT_MSG_MARKDOWN, GROUP_ROBOT_MSG_NEWS, GROUP_ROBOT_MSG_TEXT};
 use crate::wechat::cp::method::{WechatCpMethod};
 
 /// 微信群机器人消息发送api
 /// 文档地址：<a href="https://work.weixin.qq.com/help?doc_id=13376">文档</a>
 #[derive(Debug, Clone)]
-pub struct WechatCpGroupRobot<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpGroupRobot<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpGroupRobot<'a, T> {
+impl<T: SessionStore> WechatCpGroupRobot<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpGroupRobot<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpGroupRobot<T> {
         WechatCpGroupRobot {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.group_robot()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpGroupRobot<T> {
+        Self::from_client(client.clone())
+    }
+
     fn get_webhook_url(&self) -> LabradorResult<String> {
-        if let Some(webhook_url) = &self.client.webhook_url {
+        if let Some(webhook_url) = &self.client.inner.webhook_url {
             Ok(webhook_url.to_string())
         } else {
             return Err(LabraError::ApiError("请先设置WebhookKey".to_string()))
@@ -51,7 +57,7 @@ impl<'a, T: SessionStore> WechatCpGroupRobot<'a, T> {
             articles: None,
             media_id: None
         };
-        self.client.post(WechatCpMethod::Custom {need_token: false, method_url: webhook_url.to_string()}, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        self.client.post(WechatCpMethod::Custom { path: webhook_url.to_string(), request_method: Method::Post }, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
     /// <pre>
@@ -76,7 +82,7 @@ impl<'a, T: SessionStore> WechatCpGroupRobot<'a, T> {
             articles: None,
             media_id: None
         };
-        self.client.post(WechatCpMethod::Custom {need_token: false, method_url: webhook_url.to_string()}, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        self.client.post(WechatCpMethod::Custom { path: webhook_url.to_string(), request_method: Method::Post }, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
     /// <pre>
@@ -101,7 +107,7 @@ impl<'a, T: SessionStore> WechatCpGroupRobot<'a, T> {
             articles: None,
             media_id: None
         };
-        self.client.post(WechatCpMethod::Custom {need_token: false, method_url: webhook_url.to_string()}, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        self.client.post(WechatCpMethod::Custom { path: webhook_url.to_string(), request_method: Method::Post }, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
     /// <pre>
@@ -126,7 +132,7 @@ impl<'a, T: SessionStore> WechatCpGroupRobot<'a, T> {
             articles: articles.into(),
             media_id: None
         };
-        self.client.post(WechatCpMethod::Custom {need_token: false, method_url: webhook_url.to_string()}, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        self.client.post(WechatCpMethod::Custom { path: webhook_url.to_string(), request_method: Method::Post }, vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 }
 
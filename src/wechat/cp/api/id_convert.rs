@@ -0,0 +1,316 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient};
+use crate::wechat::cp::method::{CpExternalContactMethod, CpIdConvertMethod, CpUserMethod, WechatCpMethod};
+
+/// 批量转换接口每次最多传入的id数量
+const BATCH_CONVERT_LIMIT: usize = 1000;
+
+/// ID转换
+///
+/// 汇总企业成员userid、openid、open_userid与客户external_userid之间的转换，
+/// 批量转换按接口上限（每次1000个）分批调用，结果与传入顺序一致，单个id转换失败不影响其他id。
+#[derive(Debug, Clone)]
+pub struct WechatCpIdConvert<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpIdConvert<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpIdConvert<T> {
+        WechatCpIdConvert {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.id_convert()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpIdConvert<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// userid转openid.
+    /// <pre>
+    /// 用于微信支付、微信红包和企业转账，需要成员使用微信登录企业微信或者关注微信插件才能转成openid
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90202">文档</a>
+    /// </pre>
+    pub async fn convert_to_openid(&self, userid: &str) -> LabradorResult<String> {
+        let v = self.client.post(WechatCpMethod::User(CpUserMethod::ConvertToOpenid), vec![], json!({"userid": userid}), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        v["openid"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("openid".to_string()))
+    }
+
+    /// openid转userid.
+    /// <pre>
+    /// 用于微信支付、微信红包和企业转账之后的结果查询，管理组需对openid对应的企业微信成员有查看权限
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90202">文档</a>
+    /// </pre>
+    pub async fn convert_to_userid(&self, openid: &str) -> LabradorResult<String> {
+        let v = self.client.post(WechatCpMethod::User(CpUserMethod::ConvertToUserid), vec![], json!({"openid": openid}), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        v["userid"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("userid".to_string()))
+    }
+
+    /// 批量userid转openid，接口只支持单个转换，逐个调用
+    pub async fn batch_convert_to_openid(&self, userids: &[&str]) -> Vec<WechatCpIdConvertItem> {
+        let mut items = Vec::with_capacity(userids.len());
+        for userid in userids {
+            let target = self.convert_to_openid(userid).await.map_err(WechatCpIdConvertFailure::from);
+            items.push(WechatCpIdConvertItem { source: userid.to_string(), target });
+        }
+        items
+    }
+
+    /// 批量openid转userid，接口只支持单个转换，逐个调用
+    pub async fn batch_convert_to_userid(&self, openids: &[&str]) -> Vec<WechatCpIdConvertItem> {
+        let mut items = Vec::with_capacity(openids.len());
+        for openid in openids {
+            let target = self.convert_to_userid(openid).await.map_err(WechatCpIdConvertFailure::from);
+            items.push(WechatCpIdConvertItem { source: openid.to_string(), target });
+        }
+        items
+    }
+
+    /// userid转open_userid.
+    /// <pre>
+    /// 将企业主体下的明文userid转换为服务商主体下的密文open_userid，每次最多1000个，超出时分批调用
+    /// 不在可见范围或不存在的userid为`WechatCpIdConvertFailure::Invalid`
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/95884">文档</a>
+    /// </pre>
+    pub async fn userid_to_open_userid(&self, userids: &[&str]) -> Vec<WechatCpIdConvertItem> {
+        self.batch(BatchConvert::OpenUserid, userids).await
+    }
+
+    /// external_userid转换为新的external_userid.
+    /// <pre>
+    /// 代开发应用迁移、服务商主体变更时，将旧的external_userid转换为新的external_userid，每次最多1000个，超出时分批调用
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/95327">文档</a>
+    /// </pre>
+    pub async fn get_new_external_userid(&self, external_userids: &[&str]) -> Vec<WechatCpIdConvertItem> {
+        self.batch(BatchConvert::NewExternalUserid, external_userids).await
+    }
+
+    /// 上下游企业的unionid转external_userid.
+    /// <pre>
+    /// 上游企业通过客户的unionid与openid，获取该客户在各个下游企业中的external_userid
+    /// corpid为空时返回全部下游企业
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/95342">文档</a>
+    /// </pre>
+    pub async fn corp_group_unionid_to_external_userid(&self, unionid: &str, openid: &str, corpid: Option<&str>) -> LabradorResult<Vec<WechatCpCorpExternalUserid>> {
+        let mut req = json!({
+            "unionid": unionid,
+            "openid": openid,
+        });
+        if let Some(corpid) = corpid {
+            req["corpid"] = corpid.into();
+        }
+        let v = self.client.post(WechatCpMethod::IdConvert(CpIdConvertMethod::CorpGroupUnionidToExternalUserid), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(serde_json::from_value::<Option<Vec<WechatCpCorpExternalUserid>>>(v["external_userid_info"].to_owned())?.unwrap_or_default())
+    }
+
+    /// 外部联系人external_userid转openid，见[`crate::WechatCpExternalContact::convert_openid`]
+    pub async fn external_userid_to_openid(&self, external_userid: &str) -> LabradorResult<String> {
+        let v = self.client.post(WechatCpMethod::ExternalContact(CpExternalContactMethod::ConvertToOpenid), vec![], json!({"external_userid": external_userid}), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        v["openid"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("openid".to_string()))
+    }
+
+    /// 分批转换并按传入顺序汇总结果，某一批调用失败时该批的id都记为失败
+    async fn batch(&self, kind: BatchConvert, ids: &[&str]) -> Vec<WechatCpIdConvertItem> {
+        let mut items = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(BATCH_CONVERT_LIMIT) {
+            match self.convert_chunk(&kind, chunk).await {
+                Ok((converted, invalid)) => {
+                    items.extend(chunk.iter().map(|id| {
+                        let target = match converted.get(*id) {
+                            Some(target) => Ok(target.to_string()),
+                            None if invalid.contains(*id) => Err(WechatCpIdConvertFailure::Invalid),
+                            None => Err(WechatCpIdConvertFailure::NotFound),
+                        };
+                        WechatCpIdConvertItem { source: id.to_string(), target }
+                    }));
+                }
+                Err(err) => {
+                    let failure = WechatCpIdConvertFailure::from(err);
+                    items.extend(chunk.iter().map(|id| WechatCpIdConvertItem { source: id.to_string(), target: Err(failure.clone()) }));
+                }
+            }
+        }
+        items
+    }
+
+    /// 返回转换成功的id映射与接口标记为无效的id
+    async fn convert_chunk(&self, kind: &BatchConvert, ids: &[&str]) -> LabradorResult<(HashMap<String, String>, HashSet<String>)> {
+        let (method, req) = match kind {
+            BatchConvert::OpenUserid => (CpIdConvertMethod::UseridToOpenUserid, json!({"userid_list": ids})),
+            BatchConvert::NewExternalUserid => (CpIdConvertMethod::GetNewExternalUserid, json!({"external_userid_list": ids})),
+        };
+        let v = self.client.post(WechatCpMethod::IdConvert(method), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        let (list, source, target, invalid) = match kind {
+            BatchConvert::OpenUserid => ("open_userid_list", "userid", "open_userid", "invalid_userid_list"),
+            BatchConvert::NewExternalUserid => ("items", "external_userid", "new_external_userid", "invalid_external_userid_list"),
+        };
+        let converted = v[list].as_array().map(|list| list.iter().filter_map(|item| {
+            Some((item[source].as_str()?.to_string(), item[target].as_str().filter(|v| !v.is_empty())?.to_string()))
+        }).collect()).unwrap_or_default();
+        let invalid = v[invalid].as_array().map(|list| list.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()).unwrap_or_default();
+        Ok((converted, invalid))
+    }
+}
+
+enum BatchConvert {
+    OpenUserid,
+    NewExternalUserid,
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 单个id的转换结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatCpIdConvertItem {
+    /// 传入的id
+    pub source: String,
+    /// 转换后的id或失败原因
+    pub target: Result<String, WechatCpIdConvertFailure>,
+}
+
+impl WechatCpIdConvertItem {
+    pub fn is_success(&self) -> bool {
+        self.target.is_ok()
+    }
+}
+
+/// 单个id转换失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatCpIdConvertFailure {
+    /// 接口返回的无效id（不存在、不在可见范围等）
+    Invalid,
+    /// 接口未返回该id的转换结果
+    NotFound,
+    /// 所在批次的接口调用失败
+    Failed { errcode: String, errmsg: String },
+}
+
+impl From<LabraError> for WechatCpIdConvertFailure {
+    fn from(err: LabraError) -> Self {
+        match err {
+            LabraError::ClientError { errcode, errmsg } => WechatCpIdConvertFailure::Failed { errcode, errmsg },
+            err => WechatCpIdConvertFailure::Failed { errcode: String::default(), errmsg: err.to_string() },
+        }
+    }
+}
+
+/// 客户在下游企业中的external_userid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpCorpExternalUserid {
+    pub corpid: String,
+    pub external_userid: String,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{SimpleStorage, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpIdConvertFailure, WechatCpIdConvertItem};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    fn items(list: &[String]) -> String {
+        json!({"errcode": 0, "errmsg": "ok", "items": list.iter().map(|v| json!({"external_userid": v, "new_external_userid": format!("new_{}", v)})).collect::<Vec<_>>()}).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_batch_chunks_and_order() {
+        let ids = (0..2001).map(|i| format!("wm{:04}", i)).collect::<Vec<_>>();
+        // 返回结果的顺序与传入顺序不同
+        let mut first = ids[..1000].to_vec();
+        first.reverse();
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(&items(&first)),
+            MockResponse::json(&items(&ids[1000..2000])),
+            MockResponse::json(&items(&ids[2000..])),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("ww_cp_id_convert_chunks", "secret").base_url(&server.url);
+        let refs = ids.iter().map(String::as_str).collect::<Vec<_>>();
+        let result = client.id_convert().get_new_external_userid(&refs).await;
+        assert_eq!(result.len(), 2001);
+        for (item, id) in result.iter().zip(ids.iter()) {
+            assert_eq!(&item.source, id);
+            assert_eq!(item.target, Ok(format!("new_{}", id)));
+        }
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].starts_with("POST /cgi-bin/externalcontact/get_new_external_userid?access_token=ACCESS_TOKEN"));
+        let sizes = requests[1..].iter().map(|r| body(r)["external_userid_list"].as_array().unwrap().len()).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![1000, 1000, 1]);
+        assert_eq!(body(&requests[3]), json!({"external_userid_list": ["wm2000"]}));
+    }
+
+    #[tokio::test]
+    async fn test_batch_partial_failure() {
+        let ids = (0..1001).map(|i| format!("user{}", i)).collect::<Vec<_>>();
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(&json!({
+                "errcode": 0,
+                "errmsg": "ok",
+                "open_userid_list": ids[2..1000].iter().map(|v| json!({"userid": v, "open_userid": format!("open_{}", v)})).collect::<Vec<_>>(),
+                "invalid_userid_list": ["user0"]
+            }).to_string()),
+            MockResponse::json(r#"{"errcode":48002,"errmsg":"api forbidden"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("ww_cp_id_convert_partial", "secret").base_url(&server.url);
+        let refs = ids.iter().map(String::as_str).collect::<Vec<_>>();
+        let result = client.id_convert().userid_to_open_userid(&refs).await;
+        assert_eq!(result.len(), 1001);
+        assert_eq!(result[0], WechatCpIdConvertItem { source: "user0".to_string(), target: Err(WechatCpIdConvertFailure::Invalid) });
+        assert_eq!(result[1].target, Err(WechatCpIdConvertFailure::NotFound));
+        assert_eq!(result[2].target, Ok("open_user2".to_string()));
+        assert_eq!(result.iter().filter(|v| v.is_success()).count(), 998);
+        assert_eq!(result[1000].source, "user1000");
+        assert_eq!(result[1000].target, Err(WechatCpIdConvertFailure::Failed { errcode: "48002".to_string(), errmsg: "api forbidden".to_string() }));
+        assert!(server.requests()[1].starts_with("POST /cgi-bin/batch/userid_to_openuserid?access_token=ACCESS_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_single_conversions() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","openid":"oDjGHs-1yCnGrRovBj2yHij5JAAA"}"#),
+            MockResponse::json(r#"{"errcode":60111,"errmsg":"userid not found"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","userid":"zhangsan"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","external_userid_info":[{"corpid":"wwxxxx1","external_userid":"wmxxxx1"},{"corpid":"wwxxxx2","external_userid":"wmxxxx2"}]}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("ww_cp_id_convert_single", "secret").base_url(&server.url);
+        let id_convert = client.id_convert();
+        let result = id_convert.batch_convert_to_openid(&["zhangsan", "lisi"]).await;
+        assert_eq!(result[0].target, Ok("oDjGHs-1yCnGrRovBj2yHij5JAAA".to_string()));
+        assert!(matches!(&result[1].target, Err(WechatCpIdConvertFailure::Failed { errcode, .. }) if errcode == "60111"));
+        assert_eq!(id_convert.convert_to_userid("oDjGHs-1yCnGrRovBj2yHij5JAAA").await.unwrap(), "zhangsan");
+        let info = id_convert.corp_group_unionid_to_external_userid("oAAAAAAA", "oBBBBB", None).await.unwrap();
+        assert_eq!(info.len(), 2);
+        assert_eq!(info[1].external_userid, "wmxxxx2");
+
+        let requests = server.requests();
+        assert_eq!(body(&requests[1]), json!({"userid": "zhangsan"}));
+        assert!(requests[3].starts_with("POST /cgi-bin/user/convert_to_userid?access_token=ACCESS_TOKEN"));
+        assert!(requests[4].starts_with("POST /cgi-bin/corpgroup/unionid_to_external_userid?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[4]), json!({"unionid": "oAAAAAAA", "openid": "oBBBBB"}));
+    }
+}
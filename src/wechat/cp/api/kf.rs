@@ -0,0 +1,1048 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::{self, Either};
+use futures_util::stream::{self, Stream};
+use rand::Rng;
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use serde::de;
+use serde::ser::SerializeMap;
+use serde_json::{json, Value};
+
+use crate::runtime::Signal;
+use crate::{session::SessionStore, LabradorResult, LabraError, RequestType, WechatCpClient, WechatCommonResponse};
+use crate::text::{self, TextPolicy};
+use crate::wechat::cp::method::{CpKfMethod, WechatCpMethod};
+use crate::serde_helper::{bool_from_int, option_string_or_number};
+
+/// 微信客服
+#[derive(Debug, Clone)]
+pub struct WechatCpKf<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpKf<T> {
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpKf<T> {
+        WechatCpKf {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.kf()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpKf<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 添加客服帐号
+    /// <pre>
+    /// 添加客服帐号，并可设置客服名称和头像。目前一家企业最多可添加5000个客服帐号
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/account/add?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94662">地址</a>
+    /// </pre>
+    pub async fn add_account(&self, name: &str, media_id: &str) -> LabradorResult<String> {
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::AddAccount), vec![], json!({
+            "name": name,
+            "media_id": media_id,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["open_kfid"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// 删除客服帐号
+    /// <pre>
+    /// 删除已有的客服帐号
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/account/del?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94663">地址</a>
+    /// </pre>
+    pub async fn delete_account(&self, open_kfid: &str) -> LabradorResult<WechatCommonResponse> {
+        self.client.post(WechatCpMethod::Kf(CpKfMethod::DeleteAccount), vec![], json!({
+            "open_kfid": open_kfid,
+        }), RequestType::Json).await?.json::<WechatCommonResponse>()
+    }
+
+    /// 修改客服帐号
+    /// <pre>
+    /// 修改已有的客服帐号，可修改客服名称和头像
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/account/update?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94664">地址</a>
+    /// </pre>
+    pub async fn update_account(&self, open_kfid: &str, name: Option<&str>, media_id: Option<&str>) -> LabradorResult<WechatCommonResponse> {
+        let mut req = json!({
+            "open_kfid": open_kfid,
+        });
+        if let Some(name) = name {
+            req["name"] = name.into();
+        }
+        if let Some(media_id) = media_id {
+            req["media_id"] = media_id.into();
+        }
+        self.client.post(WechatCpMethod::Kf(CpKfMethod::UpdateAccount), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+    }
+
+    /// 获取客服帐号列表
+    /// <pre>
+    /// 获取客服帐号列表，包括所有的客服帐号的客服ID、名称和头像
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/account/list?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94661">地址</a>
+    /// </pre>
+    pub async fn list_account(&self, offset: Option<u32>, limit: Option<u32>) -> LabradorResult<Vec<WechatCpKfAccount>> {
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::ListAccount), vec![], json!({
+            "offset": offset.unwrap_or(0),
+            "limit": limit.unwrap_or(100),
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpKfAccount>>(v, "account_list")
+    }
+
+    /// 获取客服帐号链接
+    /// <pre>
+    /// 企业可通过此接口获取带有不同参数的客服链接，不同客服帐号对应不同的客服链接。
+    /// `scene` 场景值，字符串类型，由开发者自定义。不多于32字节，字符串取值范围(正则表达式)：[0-9a-zA-Z_-]*
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/add_contact_way?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94665">地址</a>
+    /// </pre>
+    pub async fn add_contact_way(&self, open_kfid: &str, scene: Option<&str>) -> LabradorResult<String> {
+        let mut req = json!({
+            "open_kfid": open_kfid,
+        });
+        if let Some(scene) = scene {
+            req["scene"] = scene.into();
+        }
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::AddContactWay), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["url"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// 添加接待人员
+    /// <pre>
+    /// 添加指定客服帐号的接待人员，每个客服帐号目前最多可添加500个接待人员。
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/servicer/add?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94646">地址</a>
+    /// </pre>
+    pub async fn add_servicer(&self, open_kfid: &str, userid_list: Vec<String>) -> LabradorResult<Vec<WechatCpKfServicerResult>> {
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::AddServicer), vec![], json!({
+            "open_kfid": open_kfid,
+            "userid_list": userid_list,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpKfServicerResult>>(v, "result_list")
+    }
+
+    /// 删除接待人员
+    /// <pre>
+    /// 从客服帐号删除接待人员
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/servicer/del?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94647">地址</a>
+    /// </pre>
+    pub async fn delete_servicer(&self, open_kfid: &str, userid_list: Vec<String>) -> LabradorResult<Vec<WechatCpKfServicerResult>> {
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::DeleteServicer), vec![], json!({
+            "open_kfid": open_kfid,
+            "userid_list": userid_list,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpKfServicerResult>>(v, "result_list")
+    }
+
+    /// 获取接待人员列表
+    /// <pre>
+    /// 获取某个客服帐号的接待人员列表
+    /// 请求方式: GET(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/servicer/list?access_token=ACCESS_TOKEN&open_kfid=XXX">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94645">地址</a>
+    /// </pre>
+    pub async fn list_servicer(&self, open_kfid: &str) -> LabradorResult<Vec<WechatCpKfServicer>> {
+        let v = self.client.get(WechatCpMethod::Kf(CpKfMethod::ListServicer), vec![("open_kfid".to_string(), open_kfid.to_string())], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpKfServicer>>(v, "servicer_list")
+    }
+
+    /// 读取消息
+    /// <pre>
+    /// 微信客户发送的消息、接待人员在企业微信回复的消息、发送消息接口发送失败事件（如被用户拒收）、客户点击菜单消息的回复消息，
+    /// 可以通过该接口获取最近10天具体的消息内容和事件。不支持读取通过发送消息接口发送的消息。
+    /// `cursor` 上一次调用时返回的next_cursor，第一次拉取可以不填
+    /// `token` 回调事件返回的token字段，10分钟内有效；可不填，如果不填接口有严格的频率限制
+    /// `limit` 期望请求的数据量，默认值和最大值都为1000
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/sync_msg?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94670">地址</a>
+    /// </pre>
+    pub async fn sync_msg(&self, cursor: Option<&str>, token: Option<&str>, limit: Option<u32>, open_kfid: Option<&str>) -> LabradorResult<WechatCpKfSyncMsgResponse> {
+        let mut req = json!({});
+        if let Some(cursor) = cursor {
+            req["cursor"] = cursor.into();
+        }
+        if let Some(token) = token {
+            req["token"] = token.into();
+        }
+        if let Some(limit) = limit {
+            req["limit"] = limit.into();
+        }
+        if let Some(open_kfid) = open_kfid {
+            req["open_kfid"] = open_kfid.into();
+        }
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::SyncMsg), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpKfSyncMsgResponse>(v)
+    }
+
+    /// 从已保存的游标继续读取消息
+    /// <pre>
+    /// 游标按open_kfid保存在SessionStore中，本方法不会更新游标：调用方处理完这批消息后，
+    /// 再用返回的next_cursor调用`save_cursor`，处理中途进程退出时会重新收到整批消息，
+    /// 即至少一次（at-least-once）投递，与`message_stream`一致，消费方需按msgid去重。
+    /// </pre>
+    pub async fn sync_msg_from_saved_cursor(&self, open_kfid: &str, token: Option<&str>, limit: Option<u32>) -> LabradorResult<WechatCpKfSyncMsgResponse> {
+        let cursor = self.get_saved_cursor(open_kfid)?;
+        self.sync_msg(cursor.as_deref(), token, limit, open_kfid.into()).await
+    }
+
+    /// 获取已保存的消息游标
+    pub fn get_saved_cursor(&self, open_kfid: &str) -> LabradorResult<Option<String>> {
+        let cursor: Option<String> = self.client.inner.client.session().get(self.cursor_key(open_kfid), None)?;
+        Ok(cursor.filter(|v| !v.is_empty()))
+    }
+
+    /// 保存消息游标
+    pub fn save_cursor(&self, open_kfid: &str, cursor: &str) -> LabradorResult<()> {
+        self.client.inner.client.session().set(self.cursor_key(open_kfid), cursor.to_string(), None)
+    }
+
+    fn cursor_key(&self, open_kfid: &str) -> String {
+        format!("{}_kf_cursor_{}", self.client.inner.corp_id, open_kfid)
+    }
+
+    /// 持续读取消息
+    /// <pre>
+    /// 从已保存的游标开始循环调用sync_msg：has_more为true时立即继续拉取，否则等待轮询间隔（加随机抖动）后再拉取。
+    /// 一批消息全部被取走后才保存该批的next_cursor，处理中途进程退出时会重新收到整批消息，
+    /// 即至少一次（at-least-once）投递，消费方需按msgid去重。
+    /// 网络错误、HTTP错误应答、系统繁忙（-1）和频率限制（45009）按退避重试，游标保持不变；其他错误返回后结束。
+    /// 默认参数见[`WechatCpKfStreamOptions`]，需要停止轮询时使用`message_stream_with_options`
+    /// </pre>
+    pub fn message_stream(&self, open_kfid: &str) -> impl Stream<Item = LabradorResult<WechatCpKfMessage>> {
+        self.message_stream_with_options(open_kfid, WechatCpKfStreamOptions::default())
+    }
+
+    /// 按指定参数持续读取消息，可通过[`WechatCpKfStreamOptions::shutdown_handle`]停止
+    pub fn message_stream_with_options(&self, open_kfid: &str, options: WechatCpKfStreamOptions) -> impl Stream<Item = LabradorResult<WechatCpKfMessage>> {
+        let state = KfMessageStreamState {
+            kf: self.clone(),
+            open_kfid: open_kfid.to_string(),
+            options,
+            buffer: VecDeque::new(),
+            cursor: None,
+            cursor_loaded: false,
+            pending_cursor: None,
+            wait: None,
+            failures: 0,
+            finished: false,
+        };
+        stream::unfold(state, |mut state| async move {
+            state.next().await.map(|item| (item, state))
+        })
+    }
+
+    /// 发送消息
+    /// <pre>
+    /// 当微信客户处于“新接入待处理”或“由智能助手接待”状态下，可调用该接口给用户发送消息。
+    /// 注意仅当微信客户在主动发送消息给客服后的48小时内，企业可发送消息给客户，最多可发送5条消息；若用户继续发送消息，企业可再次下发消息。
+    /// 支持发送消息类型：文本、图片、语音、视频、文件、图文、小程序、菜单消息、地理位置。
+    /// 请求方式: POST(HTTPS)
+    /// 请求地址: <a href="https://qyapi.weixin.qq.com/cgi-bin/kf/send_msg?access_token=ACCESS_TOKEN">地址</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/94677">地址</a>
+    /// </pre>
+    pub async fn send_msg(&self, req: WechatCpKfSendMsgRequest) -> LabradorResult<String> {
+        let req = req.sanitize(&TextPolicy::kf_text())?;
+        let v = self.client.post(WechatCpMethod::Kf(CpKfMethod::SendMsg), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["msgid"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// <pre>
+/// 持续读取消息的参数
+/// 默认轮询间隔5秒、抖动1秒，失败后第n次重试等待`base * 2^(n-1)`，默认1秒起、最长1分钟
+/// </pre>
+#[derive(Debug, Clone)]
+pub struct WechatCpKfStreamOptions {
+    interval: Duration,
+    jitter: Duration,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    limit: Option<u32>,
+    token: Option<String>,
+    handle: WechatCpKfStreamHandle,
+}
+
+impl Default for WechatCpKfStreamOptions {
+    fn default() -> Self {
+        WechatCpKfStreamOptions {
+            interval: Duration::from_secs(5),
+            jitter: Duration::from_secs(1),
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            limit: None,
+            token: None,
+            handle: WechatCpKfStreamHandle::default(),
+        }
+    }
+}
+
+impl WechatCpKfStreamOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 没有更多消息时的轮询间隔
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 轮询间隔上随机增加的最大时长，避免多个实例同时请求
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 失败重试的退避间隔
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_backoff = base;
+        self.max_backoff = max;
+        self
+    }
+
+    /// 每次拉取的数据量
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit.into();
+        self
+    }
+
+    /// 回调事件返回的token，10分钟内有效，不填时接口有严格的频率限制
+    pub fn token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// 停止读取的句柄
+    pub fn shutdown_handle(&self) -> WechatCpKfStreamHandle {
+        self.handle.clone()
+    }
+
+    fn poll_interval(&self) -> Duration {
+        let jitter = self.jitter.as_millis() as u64;
+        if jitter == 0 {
+            return self.interval;
+        }
+        self.interval + Duration::from_millis(rand::thread_rng().gen_range(0, jitter + 1))
+    }
+
+    fn backoff_for(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.base_backoff.checked_mul(factor).unwrap_or(self.max_backoff).min(self.max_backoff)
+    }
+}
+
+/// <pre>
+/// 停止持续读取消息的句柄
+/// 停止后不再发起新的拉取，正在等待的轮询或重试立即结束；已拉取的消息仍会返回，取完后保存游标并结束
+/// </pre>
+#[derive(Debug, Clone)]
+pub struct WechatCpKfStreamHandle {
+    signal: Arc<Signal>,
+}
+
+impl Default for WechatCpKfStreamHandle {
+    fn default() -> Self {
+        WechatCpKfStreamHandle {
+            signal: Arc::new(Signal::default()),
+        }
+    }
+}
+
+impl WechatCpKfStreamHandle {
+    pub fn shutdown(&self) {
+        self.signal.fire();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.signal.is_fired()
+    }
+}
+
+struct KfMessageStreamState<T: SessionStore> {
+    kf: WechatCpKf<T>,
+    open_kfid: String,
+    options: WechatCpKfStreamOptions,
+    buffer: VecDeque<WechatCpKfMessage>,
+    /// 下次拉取使用的游标
+    cursor: Option<String>,
+    cursor_loaded: bool,
+    /// 当前批次的next_cursor，整批取完后保存
+    pending_cursor: Option<String>,
+    /// 下次拉取前的等待时间
+    wait: Option<Duration>,
+    /// 连续失败的次数
+    failures: u32,
+    finished: bool,
+}
+
+impl<T: SessionStore> KfMessageStreamState<T> {
+    async fn next(&mut self) -> Option<LabradorResult<WechatCpKfMessage>> {
+        loop {
+            if let Some(message) = self.buffer.pop_front() {
+                return Some(Ok(message));
+            }
+            if let Some(cursor) = self.pending_cursor.take() {
+                if let Err(err) = self.kf.save_cursor(&self.open_kfid, &cursor) {
+                    // 内存中的游标不受影响，下一批取完后再次保存
+                    tracing::warn!("[微信客服] 保存{}的消息游标失败:{}", self.open_kfid, err);
+                    self.pending_cursor = cursor.into();
+                }
+            }
+            if self.finished || self.options.handle.is_shutdown() {
+                return None;
+            }
+            if !self.cursor_loaded {
+                match self.kf.get_saved_cursor(&self.open_kfid) {
+                    Ok(cursor) => {
+                        self.cursor = cursor;
+                        self.cursor_loaded = true;
+                    }
+                    Err(err) => {
+                        self.finished = true;
+                        return Some(Err(err));
+                    }
+                }
+            }
+            if let Some(wait) = self.wait.take() {
+                if !self.sleep(wait).await {
+                    return None;
+                }
+            }
+            match self.kf.sync_msg(self.cursor.as_deref(), self.options.token.as_deref(), self.options.limit, self.open_kfid.as_str().into()).await {
+                Ok(res) => {
+                    self.failures = 0;
+                    if let Some(next_cursor) = res.next_cursor.filter(|v| !v.is_empty()) {
+                        self.cursor = next_cursor.to_owned().into();
+                        self.pending_cursor = next_cursor.into();
+                    }
+                    if !res.has_more {
+                        self.wait = self.options.poll_interval().into();
+                    }
+                    self.buffer.extend(res.msg_list);
+                }
+                Err(err) if is_transient_error(&err) => {
+                    self.failures += 1;
+                    let backoff = self.options.backoff_for(self.failures);
+                    tracing::warn!("[微信客服] 读取{}的消息失败，{:?}后第{}次重试:{}", self.open_kfid, backoff, self.failures, err);
+                    self.wait = backoff.into();
+                }
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+
+    /// 等待指定时间，期间停止时返回false
+    async fn sleep(&mut self, duration: Duration) -> bool {
+        let sleep = crate::runtime::sleep(duration);
+        let shutdown = self.options.handle.signal.wait();
+        futures_util::pin_mut!(sleep, shutdown);
+        matches!(future::select(sleep, shutdown).await, Either::Left(_))
+    }
+}
+
+/// 可重试的错误：网络错误、HTTP错误应答（应答体不是JSON）、系统繁忙、频率限制
+fn is_transient_error(err: &LabraError) -> bool {
+    match err {
+        LabraError::ConnectError(_) | LabraError::HttpError(_) | LabraError::Timeout(_) | LabraError::IOError(_) | LabraError::JsonError(_) => true,
+        LabraError::ClientError { errcode, .. } => errcode == "-1" || errcode == "45009",
+        _ => false,
+    }
+}
+
+/// 客服帐号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfAccount {
+    /// 客服帐号ID
+    pub open_kfid: Option<String>,
+    /// 客服名称
+    pub name: Option<String>,
+    /// 客服头像URL
+    pub avatar: Option<String>,
+    /// 当前调用接口的应用身份，是否有该客服帐号的管理权限（编辑客服帐号信息、分配会话和收发消息）
+    pub manage_privilege: Option<bool>,
+}
+
+/// 接待人员操作结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfServicerResult {
+    pub userid: Option<String>,
+    pub department_id: Option<i64>,
+    pub errcode: Option<i64>,
+    pub errmsg: Option<String>,
+}
+
+/// 接待人员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfServicer {
+    pub userid: Option<String>,
+    pub department_id: Option<i64>,
+    /// 接待人员的接待状态。0:接待中,1:停止接待
+    pub status: Option<u8>,
+    /// 接待人员的接待状态为「停止接待」的子类型。0:停止接待,1:暂时挂起
+    pub stop_type: Option<u8>,
+}
+
+/// 读取消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfSyncMsgResponse {
+    /// 下次调用带上该值，则从当前的位置继续往后拉，以实现增量拉取
+    pub next_cursor: Option<String>,
+    /// 是否还有更多数据
+    #[serde(default, with = "bool_from_int")]
+    pub has_more: bool,
+    /// 消息列表
+    #[serde(default)]
+    pub msg_list: Vec<WechatCpKfMessage>,
+}
+
+/// 客服消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMessage {
+    /// 消息ID
+    pub msgid: Option<String>,
+    /// 客服帐号ID（msgtype为event，该字段不返回）
+    pub open_kfid: Option<String>,
+    /// 客户UserID（msgtype为event，该字段不返回）
+    pub external_userid: Option<String>,
+    /// 消息发送时间
+    #[serde(default, with = "option_string_or_number")]
+    pub send_time: Option<i64>,
+    /// 消息来源。3-微信客户发送的消息 4-系统推送的事件消息 5-接待人员在企业微信客户端发送的消息
+    pub origin: Option<u8>,
+    /// 从企业微信给客户发消息的接待人员userid（即仅origin为5才返回）
+    pub servicer_userid: Option<String>,
+    /// 消息内容，按msgtype区分
+    #[serde(flatten)]
+    pub content: WechatCpKfMsgContent,
+}
+
+/// 客服消息内容
+///
+/// 按`msgtype`区分，未识别的类型保存为`Unknown`，内容为除公共字段外的原始JSON（包括msgtype）
+#[derive(Debug, Clone)]
+pub enum WechatCpKfMsgContent {
+    Text(WechatCpKfText),
+    Image(WechatCpKfMedia),
+    Voice(WechatCpKfMedia),
+    Video(WechatCpKfMedia),
+    File(WechatCpKfMedia),
+    Location(WechatCpKfLocation),
+    Link(WechatCpKfLink),
+    Event(WechatCpKfEvent),
+    Unknown(Value),
+}
+
+impl WechatCpKfMsgContent {
+    pub fn get_msgtype(&self) -> String {
+        match self {
+            WechatCpKfMsgContent::Text(_) => "text".to_string(),
+            WechatCpKfMsgContent::Image(_) => "image".to_string(),
+            WechatCpKfMsgContent::Voice(_) => "voice".to_string(),
+            WechatCpKfMsgContent::Video(_) => "video".to_string(),
+            WechatCpKfMsgContent::File(_) => "file".to_string(),
+            WechatCpKfMsgContent::Location(_) => "location".to_string(),
+            WechatCpKfMsgContent::Link(_) => "link".to_string(),
+            WechatCpKfMsgContent::Event(_) => "event".to_string(),
+            WechatCpKfMsgContent::Unknown(v) => v["msgtype"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+pub(crate) fn serialize_tagged<S: Serializer, V: Serialize>(serializer: S, tag: &str, tag_value: &str, v: &V) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(2))?;
+    map.serialize_entry(tag, tag_value)?;
+    map.serialize_entry(tag_value, v)?;
+    map.end()
+}
+
+impl Serialize for WechatCpKfMsgContent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let msgtype = self.get_msgtype();
+        match self {
+            WechatCpKfMsgContent::Text(v) => serialize_tagged(serializer, "msgtype", &msgtype, v),
+            WechatCpKfMsgContent::Image(v)
+            | WechatCpKfMsgContent::Voice(v)
+            | WechatCpKfMsgContent::Video(v)
+            | WechatCpKfMsgContent::File(v) => serialize_tagged(serializer, "msgtype", &msgtype, v),
+            WechatCpKfMsgContent::Location(v) => serialize_tagged(serializer, "msgtype", &msgtype, v),
+            WechatCpKfMsgContent::Link(v) => serialize_tagged(serializer, "msgtype", &msgtype, v),
+            WechatCpKfMsgContent::Event(v) => serialize_tagged(serializer, "msgtype", &msgtype, v),
+            WechatCpKfMsgContent::Unknown(v) => v.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WechatCpKfMsgContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let v = Value::deserialize(deserializer)?;
+        let content = match v["msgtype"].as_str().unwrap_or_default() {
+            "text" => serde_json::from_value(v["text"].to_owned()).map(WechatCpKfMsgContent::Text),
+            "image" => serde_json::from_value(v["image"].to_owned()).map(WechatCpKfMsgContent::Image),
+            "voice" => serde_json::from_value(v["voice"].to_owned()).map(WechatCpKfMsgContent::Voice),
+            "video" => serde_json::from_value(v["video"].to_owned()).map(WechatCpKfMsgContent::Video),
+            "file" => serde_json::from_value(v["file"].to_owned()).map(WechatCpKfMsgContent::File),
+            "location" => serde_json::from_value(v["location"].to_owned()).map(WechatCpKfMsgContent::Location),
+            "link" => serde_json::from_value(v["link"].to_owned()).map(WechatCpKfMsgContent::Link),
+            "event" => serde_json::from_value(v["event"].to_owned()).map(WechatCpKfMsgContent::Event),
+            _ => Ok(WechatCpKfMsgContent::Unknown(v)),
+        };
+        content.map_err(de::Error::custom)
+    }
+}
+
+/// 文本消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfText {
+    /// 文本内容
+    pub content: String,
+    /// 客户点击菜单消息，触发的回复消息中附带的菜单ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub menu_id: Option<String>,
+}
+
+/// 图片、语音、视频、文件消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMedia {
+    /// 媒体文件ID
+    pub media_id: String,
+}
+
+/// 地理位置消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfLocation {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub name: Option<String>,
+    pub address: Option<String>,
+}
+
+/// 图文链接消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfLink {
+    /// 标题
+    pub title: String,
+    /// 描述
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desc: Option<String>,
+    /// 点击后跳转的链接
+    pub url: String,
+    /// 缩略图链接（读取消息时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pic_url: Option<String>,
+    /// 缩略图的media_id（发送消息时使用）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumb_media_id: Option<String>,
+}
+
+/// 客服事件，按`event_type`区分，未识别的事件保存为`Unknown`原始JSON
+#[derive(Debug, Clone)]
+pub enum WechatCpKfEvent {
+    /// 用户进入会话事件
+    EnterSession(WechatCpKfEnterSessionEvent),
+    /// 消息发送失败事件
+    MsgSendFail(WechatCpKfMsgSendFailEvent),
+    Unknown(Value),
+}
+
+impl Serialize for WechatCpKfEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            WechatCpKfEvent::EnterSession(v) => {
+                let mut v = serde_json::to_value(v).map_err(serde::ser::Error::custom)?;
+                v["event_type"] = "enter_session".into();
+                v.serialize(serializer)
+            }
+            WechatCpKfEvent::MsgSendFail(v) => {
+                let mut v = serde_json::to_value(v).map_err(serde::ser::Error::custom)?;
+                v["event_type"] = "msg_send_fail".into();
+                v.serialize(serializer)
+            }
+            WechatCpKfEvent::Unknown(v) => v.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WechatCpKfEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let v = Value::deserialize(deserializer)?;
+        let event = match v["event_type"].as_str().unwrap_or_default() {
+            "enter_session" => serde_json::from_value(v).map(WechatCpKfEvent::EnterSession),
+            "msg_send_fail" => serde_json::from_value(v).map(WechatCpKfEvent::MsgSendFail),
+            _ => Ok(WechatCpKfEvent::Unknown(v)),
+        };
+        event.map_err(de::Error::custom)
+    }
+}
+
+/// 用户进入会话事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfEnterSessionEvent {
+    /// 客服帐号ID
+    pub open_kfid: Option<String>,
+    /// 客户UserID
+    pub external_userid: Option<String>,
+    /// 进入会话的场景值，获取客服帐号链接开发者自定义的场景值
+    pub scene: Option<String>,
+    /// 进入会话的自定义参数，获取客服帐号链接返回的url，开发者按规范拼接的scene_param参数
+    pub scene_param: Option<String>,
+    /// 用于发送欢迎语的code，仅在用户首次进入会话的48小时内有效
+    pub welcome_code: Option<String>,
+    /// 用于标识用户是通过哪个视频号进入会话的
+    pub wechat_channels: Option<Value>,
+}
+
+/// 消息发送失败事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMsgSendFailEvent {
+    /// 客服帐号ID
+    pub open_kfid: Option<String>,
+    /// 客户UserID
+    pub external_userid: Option<String>,
+    /// 发送失败的消息msgid
+    pub fail_msgid: Option<String>,
+    /// 失败类型。0-未知原因 1-客服帐号已删除 2-应用已关闭 4-会话已过期，超过48小时 5-会话已关闭 6-超过5条限制
+    /// 8-主体未验证 10-用户拒收 11-企业未有成员登录企业微信App（排查方法：企业至少一个成员通过手机号验证/微信授权登录企业微信App即可）
+    pub fail_type: Option<u8>,
+}
+
+/// 发送消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfSendMsgRequest {
+    /// 指定接收消息的客户UserID
+    pub touser: String,
+    /// 指定发送消息的客服帐号ID
+    pub open_kfid: String,
+    /// 指定消息ID，不多于32字节，字符串取值范围(正则表达式)：[0-9a-zA-Z_-]*
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msgid: Option<String>,
+    pub msgtype: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<WechatCpKfText>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<WechatCpKfMedia>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<WechatCpKfLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msgmenu: Option<WechatCpKfMsgMenu>,
+}
+
+impl WechatCpKfSendMsgRequest {
+    fn new(touser: &str, open_kfid: &str, msgtype: &str) -> Self {
+        Self {
+            touser: touser.to_string(),
+            open_kfid: open_kfid.to_string(),
+            msgid: None,
+            msgtype: msgtype.to_string(),
+            text: None,
+            image: None,
+            link: None,
+            msgmenu: None,
+        }
+    }
+
+    /// 文本消息
+    pub fn text(touser: &str, open_kfid: &str, content: &str) -> Self {
+        let mut req = Self::new(touser, open_kfid, "text");
+        req.text = WechatCpKfText { content: content.to_string(), menu_id: None }.into();
+        req
+    }
+
+    /// 图片消息
+    pub fn image(touser: &str, open_kfid: &str, media_id: &str) -> Self {
+        let mut req = Self::new(touser, open_kfid, "image");
+        req.image = WechatCpKfMedia { media_id: media_id.to_string() }.into();
+        req
+    }
+
+    /// 图文链接消息
+    pub fn link(touser: &str, open_kfid: &str, link: WechatCpKfLink) -> Self {
+        let mut req = Self::new(touser, open_kfid, "link");
+        req.link = link.into();
+        req
+    }
+
+    /// 菜单消息
+    pub fn menu(touser: &str, open_kfid: &str, menu: WechatCpKfMsgMenu) -> Self {
+        let mut req = Self::new(touser, open_kfid, "msgmenu");
+        req.msgmenu = menu.into();
+        req
+    }
+
+    /// 指定消息ID
+    pub fn msgid(mut self, msgid: &str) -> Self {
+        self.msgid = msgid.to_string().into();
+        self
+    }
+
+    /// <pre>
+    /// 按策略处理文本消息的内容
+    /// 发送时默认去除控制字符并校验2048字节的限制，需要截断时可先调用：`req.sanitize(&TextPolicy::kf_text().truncate("…"))`
+    /// </pre>
+    pub fn sanitize(mut self, policy: &TextPolicy) -> LabradorResult<Self> {
+        if let Some(text) = self.text.as_mut() {
+            text.content = text::sanitize(&text.content, policy)?;
+        }
+        Ok(self)
+    }
+}
+
+/// 菜单消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMsgMenu {
+    /// 起始文本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_content: Option<String>,
+    /// 菜单项配置
+    pub list: Vec<WechatCpKfMenuItem>,
+    /// 结束文本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tail_content: Option<String>,
+}
+
+/// 菜单项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WechatCpKfMenuItem {
+    /// 回复菜单
+    Click { click: WechatCpKfMenuClick },
+    /// 超链接菜单
+    View { view: WechatCpKfMenuView },
+    /// 小程序菜单
+    Miniprogram { miniprogram: WechatCpKfMenuMiniprogram },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMenuClick {
+    /// 菜单ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// 菜单显示内容
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMenuView {
+    /// 点击后跳转的链接
+    pub url: String,
+    /// 菜单显示内容
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpKfMenuMiniprogram {
+    /// 小程序appid
+    pub appid: String,
+    /// 点击后进入的小程序页面
+    pub pagepath: String,
+    /// 菜单显示内容
+    pub content: String,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use crate::SimpleStorage;
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::*;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn batch(next_cursor: &str, has_more: bool, msgids: &[&str]) -> MockResponse {
+        let msg_list = msgids.iter().map(|msgid| json!({"msgid": msgid, "open_kfid": "wk_stream", "external_userid": "wm_user", "send_time": 1615478585, "origin": 3, "msgtype": "text", "text": {"content": msgid}})).collect::<Vec<_>>();
+        MockResponse::json(&json!({"errcode": 0, "errmsg": "ok", "next_cursor": next_cursor, "has_more": has_more as i32, "msg_list": msg_list}).to_string())
+    }
+
+    fn request_cursor(request: &str) -> Option<String> {
+        let body = serde_json::from_str::<Value>(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        body["cursor"].as_str().map(|v| v.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_sync_msg_from_saved_cursor() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), batch("cursor_1", false, &["msg_1"])]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("kf_saved_cursor_corp", "secret").base_url(&server.url);
+        let kf = client.kf();
+        kf.save_cursor("wk_stream", "cursor_0").unwrap();
+        let res = kf.sync_msg_from_saved_cursor("wk_stream", None, None).await.unwrap();
+        assert_eq!(request_cursor(&server.requests()[1]).as_deref(), Some("cursor_0"));
+        // 处理完成前游标保持不变，由调用方保存
+        assert_eq!(kf.get_saved_cursor("wk_stream").unwrap().as_deref(), Some("cursor_0"));
+        kf.save_cursor("wk_stream", res.next_cursor.as_deref().unwrap()).unwrap();
+        assert_eq!(kf.get_saved_cursor("wk_stream").unwrap().as_deref(), Some("cursor_1"));
+    }
+
+    #[tokio::test]
+    async fn test_message_stream_batches() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            batch("cursor_1", true, &["msg_1", "msg_2"]),
+            batch("cursor_2", false, &["msg_3"]),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("kf_stream_batches_corp", "secret").base_url(&server.url);
+        let kf = client.kf();
+        kf.save_cursor("wk_stream", "cursor_0").unwrap();
+        let options = WechatCpKfStreamOptions::new().interval(Duration::from_secs(60)).jitter(Duration::from_millis(0));
+        let handle = options.shutdown_handle();
+        let stream = kf.message_stream_with_options("wk_stream", options);
+        futures_util::pin_mut!(stream);
+
+        assert_eq!(stream.next().await.unwrap().unwrap().msgid.as_deref(), Some("msg_1"));
+        assert_eq!(stream.next().await.unwrap().unwrap().msgid.as_deref(), Some("msg_2"));
+        assert_eq!(kf.get_saved_cursor("wk_stream").unwrap().as_deref(), Some("cursor_0"));
+        // has_more为true时立即拉取下一批，拉取前保存上一批的游标
+        assert_eq!(stream.next().await.unwrap().unwrap().msgid.as_deref(), Some("msg_3"));
+        assert_eq!(kf.get_saved_cursor("wk_stream").unwrap().as_deref(), Some("cursor_1"));
+
+        // 没有更多消息时等待轮询间隔，停止后立即结束并保存最后一批的游标
+        let stop = handle.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stop.shutdown();
+        });
+        let next = tokio::time::timeout(Duration::from_secs(5), stream.next()).await.unwrap();
+        assert!(next.is_none());
+        assert!(handle.is_shutdown());
+        assert_eq!(kf.get_saved_cursor("wk_stream").unwrap().as_deref(), Some("cursor_2"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].starts_with("POST /cgi-bin/kf/sync_msg?access_token=ACCESS_TOKEN"));
+        assert_eq!(request_cursor(&requests[1]).as_deref(), Some("cursor_0"));
+        assert_eq!(request_cursor(&requests[2]).as_deref(), Some("cursor_1"));
+    }
+
+    #[tokio::test]
+    async fn test_message_stream_retry() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            batch("cursor_1", true, &["msg_1"]),
+            MockResponse::bytes("text/html", b"<html>Internal Server Error</html>").status(500),
+            MockResponse::json(r#"{"errcode":-1,"errmsg":"system busy"}"#),
+            batch("cursor_2", true, &["msg_2"]),
+            MockResponse::json(r#"{"errcode":95000,"errmsg":"invalid open_kfid"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("kf_stream_retry_corp", "secret").base_url(&server.url);
+        let kf = client.kf();
+        let options = WechatCpKfStreamOptions::new().backoff(Duration::from_millis(10), Duration::from_millis(50)).limit(100);
+        let items = kf.message_stream_with_options("wk_stream", options).collect::<Vec<_>>().await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().msgid.as_deref(), Some("msg_1"));
+        assert_eq!(items[1].as_ref().unwrap().msgid.as_deref(), Some("msg_2"));
+        assert!(matches!(&items[2], Err(LabraError::ClientError { errcode, .. }) if errcode == "95000"));
+        // 出错后游标不变，不可重试的错误不影响已保存的游标
+        assert_eq!(kf.get_saved_cursor("wk_stream").unwrap().as_deref(), Some("cursor_2"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 6);
+        assert_eq!(request_cursor(&requests[1]), None);
+        for request in &requests[2..5] {
+            assert_eq!(request_cursor(request).as_deref(), Some("cursor_1"));
+        }
+        assert_eq!(request_cursor(&requests[5]).as_deref(), Some("cursor_2"));
+        assert!(requests[1].contains(r#""limit":100"#));
+    }
+
+    #[test]
+    fn test_sync_msg_deserialize() {
+        let json = r#"{
+            "errcode": 0,
+            "errmsg": "ok",
+            "next_cursor": "4gw7MepFLfgF2VC5npN",
+            "has_more": 1,
+            "msg_list": [
+                {"msgid": "from_msgid_1", "open_kfid": "wkAJ2GCAAASSm4_FhToWMFea0xAFfd3Q", "external_userid": "wmAJ2GCAAAme1XQRC-NI-q0_ZM9ukoAw", "send_time": 1615478585, "origin": 3, "msgtype": "text", "text": {"content": "你好世界", "menu_id": "101"}},
+                {"msgid": "from_msgid_2", "open_kfid": "wkAJ2GCAAASSm4_FhToWMFea0xAFfd3Q", "external_userid": "wmAJ2GCAAAme1XQRC-NI-q0_ZM9ukoAw", "send_time": "1615478585", "origin": 3, "msgtype": "image", "image": {"media_id": "2iSLeVyqzk4eX0IB5kTi9Ljfa2rt9dwfq5WKRQ4Nvvgw"}},
+                {"msgid": "from_msgid_3", "send_time": 1615478585, "origin": 4, "msgtype": "event", "event": {"event_type": "enter_session", "open_kfid": "wkAJ2GCAAASSm4_FhToWMFea0xAFfd3Q", "external_userid": "wmAJ2GCAAAme1XQRC-NI-q0_ZM9ukoAw", "scene": "123", "scene_param": "abc", "welcome_code": "aaaaaa"}},
+                {"msgid": "from_msgid_4", "send_time": 1615478585, "origin": 4, "msgtype": "event", "event": {"event_type": "msg_send_fail", "open_kfid": "wkAJ2GCAAASSm4_FhToWMFea0xAFfd3Q", "external_userid": "wmAJ2GCAAAme1XQRC-NI-q0_ZM9ukoAw", "fail_msgid": "FAIL_MSGID", "fail_type": 4}},
+                {"msgid": "from_msgid_5", "send_time": 1615478585, "origin": 4, "msgtype": "event", "event": {"event_type": "servicer_status_change", "servicer_userid": "zhangsan"}},
+                {"msgid": "from_msgid_6", "send_time": 1615478585, "origin": 3, "msgtype": "channels_shop_product", "channels_shop_product": {"product_id": "10000042"}}
+            ]
+        }"#;
+        let v = serde_json::from_str::<Value>(json).unwrap();
+        let res = WechatCommonResponse::parse::<WechatCpKfSyncMsgResponse>(v).unwrap();
+        assert!(res.has_more);
+        assert_eq!(res.msg_list.len(), 6);
+        match &res.msg_list[0].content {
+            WechatCpKfMsgContent::Text(v) => {
+                assert_eq!(v.content, "你好世界");
+                assert_eq!(v.menu_id.as_deref(), Some("101"));
+            }
+            _ => panic!("expect text"),
+        }
+        assert_eq!(res.msg_list[1].send_time, Some(1615478585));
+        assert!(matches!(&res.msg_list[1].content, WechatCpKfMsgContent::Image(v) if v.media_id == "2iSLeVyqzk4eX0IB5kTi9Ljfa2rt9dwfq5WKRQ4Nvvgw"));
+        match &res.msg_list[2].content {
+            WechatCpKfMsgContent::Event(WechatCpKfEvent::EnterSession(v)) => assert_eq!(v.welcome_code.as_deref(), Some("aaaaaa")),
+            _ => panic!("expect enter_session"),
+        }
+        match &res.msg_list[3].content {
+            WechatCpKfMsgContent::Event(WechatCpKfEvent::MsgSendFail(v)) => assert_eq!(v.fail_type, Some(4)),
+            _ => panic!("expect msg_send_fail"),
+        }
+        assert!(matches!(&res.msg_list[4].content, WechatCpKfMsgContent::Event(WechatCpKfEvent::Unknown(v)) if v["servicer_userid"] == "zhangsan"));
+        match &res.msg_list[5].content {
+            WechatCpKfMsgContent::Unknown(v) => {
+                assert_eq!(v["msgtype"], "channels_shop_product");
+                assert_eq!(v["channels_shop_product"]["product_id"], "10000042");
+            }
+            _ => panic!("expect unknown"),
+        }
+        assert_eq!(res.msg_list[5].content.get_msgtype(), "channels_shop_product");
+        assert_eq!(res.msg_list[5].msgid.as_deref(), Some("from_msgid_6"));
+    }
+
+    #[test]
+    fn test_send_msg_sanitize() {
+        let content = format!("\u{7}{}", "客".repeat(700));
+        assert!(WechatCpKfSendMsgRequest::text("EXTERNAL_USERID", "OPEN_KFID", &content).sanitize(&TextPolicy::kf_text()).is_err());
+        let req = WechatCpKfSendMsgRequest::text("EXTERNAL_USERID", "OPEN_KFID", &content).sanitize(&TextPolicy::kf_text().truncate("…")).unwrap();
+        let text = req.text.unwrap().content;
+        assert_eq!(text.len(), 2046);
+        assert!(text.starts_with('客') && text.ends_with('…'));
+    }
+
+    #[test]
+    fn test_message_serialize() {
+        let msg = serde_json::from_str::<WechatCpKfMessage>(r#"{"msgid": "1", "msgtype": "text", "text": {"content": "hello"}}"#).unwrap();
+        let v = serde_json::to_value(&msg).unwrap();
+        assert_eq!(v["msgtype"], "text");
+        assert_eq!(v["text"]["content"], "hello");
+        let req = WechatCpKfSendMsgRequest::menu("EXTERNAL_USERID", "OPEN_KFID", WechatCpKfMsgMenu {
+            head_content: "您对本次服务是否满意呢?".to_string().into(),
+            list: vec![WechatCpKfMenuItem::Click { click: WechatCpKfMenuClick { id: "101".to_string().into(), content: "满意".to_string() } }],
+            tail_content: None,
+        });
+        let v = serde_json::to_value(&req).unwrap();
+        assert_eq!(v["msgtype"], "msgmenu");
+        assert_eq!(v["msgmenu"]["list"][0]["type"], "click");
+        assert_eq!(v["msgmenu"]["list"][0]["click"]["id"], "101");
+        assert!(v.get("text").is_none());
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient};
+use crate::wechat::cp::method::{CpLinkedCorpMethod, WechatCpMethod};
+
+/// 互联企业
+#[derive(Debug, Clone)]
+pub struct WechatCpLinkedCorp<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpLinkedCorp<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpLinkedCorp<T> {
+        WechatCpLinkedCorp {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.linked_corp()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpLinkedCorp<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取应用的可见范围.
+    /// <pre>
+    /// 本接口只返回互联企业中非本企业内的成员和部门的信息
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/linkedcorp/agent/get_perm_list?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93172">文档</a>
+    /// </pre>
+    pub async fn get_perm_list(&self) -> LabradorResult<WechatCpLinkedCorpPermList> {
+        let v = self.client.post(WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::GetPermList), vec![], json!({}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpLinkedCorpPermList>(v)
+    }
+
+    /// 获取互联企业成员详细信息.
+    /// <pre>
+    /// userid格式为CorpId/userid
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93171">文档</a>
+    /// </pre>
+    pub async fn get_user(&self, userid: &str) -> LabradorResult<WechatCpLinkedCorpUser> {
+        let v = self.client.post(WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::GetUser), vec![], json!({"userid": userid}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<WechatCpLinkedCorpUser>(v, "user_info")
+    }
+
+    /// 获取互联企业部门成员.
+    /// <pre>
+    /// department_id格式为LinkedId/DepartmentId
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93168">文档</a>
+    /// </pre>
+    pub async fn get_user_simple_list(&self, department_id: &str) -> LabradorResult<Vec<WechatCpLinkedCorpUser>> {
+        let v = self.client.post(WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::UserSimpleList), vec![], json!({"department_id": department_id}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpLinkedCorpUser>>(v, "userlist")
+    }
+
+    /// 获取互联企业部门成员详情.
+    /// <pre>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93169">文档</a>
+    /// </pre>
+    pub async fn get_user_list(&self, department_id: &str) -> LabradorResult<Vec<WechatCpLinkedCorpUser>> {
+        let v = self.client.post(WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::UserList), vec![], json!({"department_id": department_id}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpLinkedCorpUser>>(v, "userlist")
+    }
+
+    /// 获取互联企业部门列表.
+    /// <pre>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93170">文档</a>
+    /// </pre>
+    pub async fn get_department_list(&self, department_id: &str) -> LabradorResult<Vec<WechatCpLinkedCorpDepartment>> {
+        let v = self.client.post(WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::DepartmentList), vec![], json!({"department_id": department_id}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpLinkedCorpDepartment>>(v, "department_list")
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLinkedCorpPermList {
+    /// 可见的userid列表，格式为CorpId/userid
+    #[serde(default)]
+    pub userids: Vec<String>,
+    /// 可见的部门id列表，格式为LinkedId/DepartmentId
+    #[serde(default)]
+    pub department_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLinkedCorpUser {
+    pub userid: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub department: Vec<String>,
+    pub mobile: Option<String>,
+    pub telephone: Option<String>,
+    pub email: Option<String>,
+    pub position: Option<String>,
+    pub corpid: Option<String>,
+    pub extattr: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLinkedCorpDepartment {
+    pub department_id: String,
+    pub department_name: Option<String>,
+    pub parentid: Option<String>,
+    pub order: Option<i64>,
+}
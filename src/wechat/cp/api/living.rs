@@ -0,0 +1,400 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use futures_util::Stream;
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient, Page, PagedStream};
+use crate::wechat::cp::method::{CpLivingMethod, WechatCpMethod};
+
+/// 直播
+///
+/// 调用时需使用直播应用的Secret，或已配置到“可调用应用”列表中的自建应用Secret构造客户端。
+#[derive(Debug, Clone)]
+pub struct WechatCpLiving<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpLiving<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpLiving<T> {
+        WechatCpLiving {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.living()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpLiving<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 创建预约直播.
+    /// <pre>
+    /// 返回直播id
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93637">文档</a>
+    /// </pre>
+    pub async fn create(&self, req: WechatCpLivingCreateRequest) -> LabradorResult<String> {
+        let v = self.client.post(WechatCpMethod::Living(CpLivingMethod::Create), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(v["livingid"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// 修改预约直播.
+    /// <pre>
+    /// 只能修改“预约中”的直播
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93640">文档</a>
+    /// </pre>
+    pub async fn modify(&self, req: WechatCpLivingModifyRequest) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatCpMethod::Living(CpLivingMethod::Modify), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 取消预约直播.
+    /// <pre>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93638">文档</a>
+    /// </pre>
+    pub async fn cancel(&self, livingid: &str) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatCpMethod::Living(CpLivingMethod::Cancel), vec![], json!({
+            "livingid": livingid,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 获取直播详情.
+    /// <pre>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93635">文档</a>
+    /// </pre>
+    pub async fn get_living_info(&self, livingid: &str) -> LabradorResult<WechatCpLivingInfo> {
+        let v = self.client.get(WechatCpMethod::Living(CpLivingMethod::GetLivingInfo), vec![
+            ("livingid".to_string(), livingid.to_string()),
+        ], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<WechatCpLivingInfo>(v, "living_info")
+    }
+
+    /// 获取直播观看明细.
+    /// <pre>
+    /// 通过该接口可以获取所有观看直播的人员统计，分为企业成员与外部用户两部分
+    /// next_key：上一次调用时返回的next_key，初次调用传None（即按"0"拉取）
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93636">文档</a>
+    /// </pre>
+    pub async fn get_watch_stat(&self, livingid: &str, next_key: Option<&str>) -> LabradorResult<WechatCpLivingWatchStatResponse> {
+        let v = self.client.post(WechatCpMethod::Living(CpLivingMethod::GetWatchStat), vec![], json!({
+            "livingid": livingid,
+            "next_key": next_key.unwrap_or("0"),
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpLivingWatchStatResponse>(v)
+    }
+
+    /// 获取全部直播观看明细
+    /// <pre>
+    /// 基于get_watch_stat按next_key自动翻页，消费时才会发起请求。
+    /// `max_items` 最多返回的人数
+    /// </pre>
+    pub fn list_all_watch_stat(&self, livingid: &str, max_items: Option<usize>) -> impl Stream<Item = LabradorResult<WechatCpLivingViewer>> + '_ {
+        let livingid = livingid.to_string();
+        let mut pager = PagedStream::new(move |next_key: Option<String>| {
+            let livingid = livingid.to_owned();
+            async move {
+                let res = self.get_watch_stat(&livingid, next_key.as_deref()).await?;
+                let next_key = if res.is_ending() { None } else { res.next_key.to_owned() };
+                Ok(Page::with_cursor(res.stat_info.into_viewers(), next_key))
+            }
+        });
+        if let Some(max_items) = max_items {
+            pager = pager.max_items(max_items);
+        }
+        pager.into_stream()
+    }
+
+    /// 获取成员直播ID列表.
+    /// <pre>
+    /// 通过此接口可以获取指定成员的所有直播ID
+    /// cursor：上一次调用时返回的next_cursor，第一次拉取可以不填
+    /// limit：每次拉取的数据量，默认值和最大值都为100
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93634">文档</a>
+    /// </pre>
+    pub async fn get_user_all_livingid(&self, userid: &str, cursor: Option<&str>, limit: Option<i32>) -> LabradorResult<WechatCpLivingIdResponse> {
+        let mut req = json!({
+            "userid": userid,
+        });
+        if let Some(cursor) = cursor {
+            req["cursor"] = cursor.into();
+        }
+        if let Some(limit) = limit {
+            req["limit"] = limit.into();
+        }
+        let v = self.client.post(WechatCpMethod::Living(CpLivingMethod::GetUserAllLivingId), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpLivingIdResponse>(v)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingCreateRequest {
+    /// 直播发起者的userid
+    pub anchor_userid: String,
+    /// 直播的标题，最多支持60个字节
+    pub theme: String,
+    /// 直播开始时间的unix时间戳
+    pub living_start: i64,
+    /// 直播持续时长，单位秒
+    pub living_duration: i64,
+    /// 直播的类型，0：通用直播，1：小班课，2：大班课，3：企业培训，4：活动直播，默认0
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<i32>,
+    /// 直播的简介，最多支持300个字节
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 授权方安装的应用agentid，仅旧的第三方多应用套件需要填此参数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agentid: Option<i32>,
+    /// 指定直播开始前多久提醒用户，单位秒，默认60
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_time: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingModifyRequest {
+    /// 直播id，仅允许修改预约状态下的直播id
+    pub livingid: String,
+    /// 直播的标题，最多支持60个字节
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// 直播开始时间的unix时间戳
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub living_start: Option<i64>,
+    /// 直播持续时长，单位秒
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub living_duration: Option<i64>,
+    /// 直播的类型
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<i32>,
+    /// 直播的简介，最多支持300个字节
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 指定直播开始前多久提醒用户，单位秒
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_time: Option<i64>,
+}
+
+/// 直播详情
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingInfo {
+    /// 直播的标题
+    pub theme: String,
+    /// 直播开始时间戳
+    pub living_start: i64,
+    /// 直播时长，单位为秒
+    pub living_duration: i64,
+    /// 直播的状态，0：预约中，1：直播中，2：已结束，3：已过期，4：已取消
+    pub status: i32,
+    /// 直播预约的开始时间戳
+    pub reserve_start: Option<i64>,
+    /// 直播预约时长，单位为秒
+    pub reserve_living_duration: Option<i64>,
+    /// 直播的描述
+    pub description: Option<String>,
+    /// 主播的userid
+    pub anchor_userid: String,
+    /// 主播所在主部门id
+    pub main_department: Option<i64>,
+    /// 观看直播总人数
+    pub viewer_num: Option<i64>,
+    /// 评论数
+    pub comment_num: Option<i64>,
+    /// 连麦发言人数
+    pub mic_num: Option<i64>,
+    /// 是否开启回放，1表示开启，0表示关闭
+    pub open_replay: Option<i32>,
+    /// 回放状态，0：生成成功，1：生成中，2：已删除，3：生成失败
+    pub replay_status: Option<i32>,
+    /// 直播的类型
+    #[serde(rename = "type")]
+    pub r#type: Option<i32>,
+    /// 推流地址，仅直播类型为活动直播并且直播状态是待开播返回
+    pub push_stream_url: Option<String>,
+    /// 当前在线观看人数
+    pub online_count: Option<i64>,
+    /// 直播预约人数
+    pub subscribe_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingWatchStatResponse {
+    /// 是否结束，0表示还有更多数据，需要继续拉取，1表示已经拉取完所有数据
+    pub ending: i32,
+    /// 当前数据最后一个key值，如果有下一页，下一页请求时传入
+    pub next_key: Option<String>,
+    /// 观看直播的统计
+    #[serde(default)]
+    pub stat_info: WechatCpLivingStatInfo,
+}
+
+impl WechatCpLivingWatchStatResponse {
+    /// 是否已拉取完所有数据
+    pub fn is_ending(&self) -> bool {
+        self.ending == 1
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WechatCpLivingStatInfo {
+    /// 企业成员的观看列表
+    #[serde(default)]
+    pub users: Vec<WechatCpLivingWatchUser>,
+    /// 外部用户的观看列表
+    #[serde(default)]
+    pub external_users: Vec<WechatCpLivingWatchExternalUser>,
+}
+
+impl WechatCpLivingStatInfo {
+    /// 合并为观看者列表，企业成员在前
+    pub fn into_viewers(self) -> Vec<WechatCpLivingViewer> {
+        self.users.into_iter().map(WechatCpLivingViewer::User)
+            .chain(self.external_users.into_iter().map(WechatCpLivingViewer::ExternalUser))
+            .collect()
+    }
+}
+
+/// 观看直播的企业成员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingWatchUser {
+    /// 企业成员的userid
+    pub userid: String,
+    /// 观看时长，单位为秒
+    pub watch_time: i64,
+    /// 是否评论，0-否，1-是
+    pub is_comment: Option<i32>,
+    /// 是否连麦发言，0-否，1-是
+    pub is_mic: Option<i32>,
+}
+
+/// 观看直播的外部用户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingWatchExternalUser {
+    /// 外部成员的userid
+    pub external_userid: String,
+    /// 外部用户类型，1表示该外部成员是微信用户，2表示该外部成员是企业微信用户
+    #[serde(rename = "type")]
+    pub r#type: i32,
+    /// 外部成员的名称
+    pub name: Option<String>,
+    /// 观看时长，单位为秒
+    pub watch_time: i64,
+    /// 是否评论，0-否，1-是
+    pub is_comment: Option<i32>,
+    /// 是否连麦发言，0-否，1-是
+    pub is_mic: Option<i32>,
+}
+
+/// 观看直播的人员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WechatCpLivingViewer {
+    /// 企业成员
+    User(WechatCpLivingWatchUser),
+    /// 外部用户
+    ExternalUser(WechatCpLivingWatchExternalUser),
+}
+
+impl WechatCpLivingViewer {
+    /// 观看时长，单位为秒
+    pub fn watch_time(&self) -> i64 {
+        match self {
+            WechatCpLivingViewer::User(v) => v.watch_time,
+            WechatCpLivingViewer::ExternalUser(v) => v.watch_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpLivingIdResponse {
+    /// 当前数据最后一个key值，如果有下一页，下一页请求时传入
+    pub next_cursor: Option<String>,
+    /// 直播ID列表
+    #[serde(default)]
+    pub livingid_list: Vec<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use futures_util::StreamExt;
+    use serde_json::Value;
+
+    use crate::{LabradorResult, SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpLivingCreateRequest, WechatCpLivingInfo, WechatCpLivingViewer, WechatCpLivingWatchStatResponse};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_watch_stat_deserialize() {
+        let data = r#"{"errcode":0,"errmsg":"ok","ending":1,"next_key":"NEXT_KEY","stat_info":{"users":[{"userid":"userid","watch_time":30,"is_comment":1,"is_mic":1}],"external_users":[{"external_userid":"external_userid1","type":1,"name":"user name","watch_time":30,"is_comment":1,"is_mic":1},{"external_userid":"external_userid2","type":2,"name":"user_name","watch_time":30,"is_comment":1,"is_mic":1}]}}"#;
+        let v = serde_json::from_str::<Value>(data).unwrap();
+        let res = WechatCommonResponse::parse::<WechatCpLivingWatchStatResponse>(v).unwrap();
+        assert!(res.is_ending());
+        assert_eq!(res.next_key.as_deref(), Some("NEXT_KEY"));
+        assert_eq!(res.stat_info.users.len(), 1);
+        assert_eq!(res.stat_info.users[0].userid, "userid");
+        assert_eq!(res.stat_info.external_users.len(), 2);
+        assert_eq!(res.stat_info.external_users[1].r#type, 2);
+        let viewers = res.stat_info.into_viewers();
+        assert!(matches!(&viewers[0], WechatCpLivingViewer::User(u) if u.userid == "userid"));
+        assert!(matches!(&viewers[2], WechatCpLivingViewer::ExternalUser(u) if u.external_userid == "external_userid2"));
+        assert_eq!(viewers.iter().map(|v| v.watch_time()).sum::<i64>(), 90);
+
+        // 只有外部用户
+        let data = r#"{"errcode":0,"errmsg":"ok","ending":0,"next_key":"1","stat_info":{"external_users":[{"external_userid":"wm1","type":1,"watch_time":10}]}}"#;
+        let res = WechatCommonResponse::parse::<WechatCpLivingWatchStatResponse>(serde_json::from_str::<Value>(data).unwrap()).unwrap();
+        assert!(!res.is_ending());
+        assert!(res.stat_info.users.is_empty());
+        assert!(res.stat_info.external_users[0].name.is_none());
+    }
+
+    #[test]
+    fn test_living_info_deserialize() {
+        let data = r#"{"errcode":0,"errmsg":"ok","living_info":{"theme":"直角三角形讲解","living_start":1586405229,"living_duration":1800,"status":3,"reserve_start":1586405239,"reserve_living_duration":1600,"description":"小学数学精选课程，欢迎观看","anchor_userid":"zhangsan","main_department":1,"viewer_num":100,"comment_num":110,"mic_num":120,"open_replay":1,"replay_status":2,"type":1,"push_stream_url":"https://www.qq.test.com","online_count":1,"subscribe_count":1}}"#;
+        let info = WechatCommonResponse::parse_with_key::<WechatCpLivingInfo>(serde_json::from_str::<Value>(data).unwrap(), "living_info").unwrap();
+        assert_eq!(info.theme, "直角三角形讲解");
+        assert_eq!(info.status, 3);
+        assert_eq!(info.anchor_userid, "zhangsan");
+        assert_eq!(info.viewer_num, Some(100));
+        assert_eq!(info.r#type, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_list_all_watch_stat() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","livingid":"XXXXXXXXX"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","ending":0,"next_key":"KEY1","stat_info":{"users":[{"userid":"u1","watch_time":10}],"external_users":[{"external_userid":"wm1","type":1,"watch_time":20}]}}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","ending":1,"next_key":"KEY2","stat_info":{"users":[{"userid":"u2","watch_time":30}],"external_users":[]}}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("living_watch_stat_corp", "secret").base_url(&server.url);
+        let livingid = client.living().create(WechatCpLivingCreateRequest {
+            anchor_userid: "zhangsan".to_string(),
+            theme: "theme1".to_string(),
+            living_start: 1600000000,
+            living_duration: 3600,
+            r#type: Some(3),
+            description: None,
+            agentid: None,
+            remind_time: None,
+        }).await.unwrap();
+        assert_eq!(livingid, "XXXXXXXXX");
+        let living = client.living();
+        let viewers = living.list_all_watch_stat(&livingid, None).collect::<Vec<_>>().await
+            .into_iter().collect::<LabradorResult<Vec<WechatCpLivingViewer>>>().unwrap();
+        assert_eq!(viewers.len(), 3);
+        assert!(matches!(&viewers[1], WechatCpLivingViewer::ExternalUser(u) if u.external_userid == "wm1"));
+        assert!(matches!(&viewers[2], WechatCpLivingViewer::User(u) if u.userid == "u2"));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].starts_with("POST /cgi-bin/living/create?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"anchor_userid":"zhangsan","theme":"theme1","living_start":1600000000,"living_duration":3600,"type":3}"#));
+        assert!(requests[2].ends_with(r#"{"livingid":"XXXXXXXXX","next_key":"0"}"#));
+        assert!(requests[3].ends_with(r#"{"livingid":"XXXXXXXXX","next_key":"KEY1"}"#));
+    }
+}
@@ -2,30 +2,41 @@ use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::time::{Duration, Instant};
 use bytes::Bytes;
 use serde::{Serialize, Deserialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use crate::{session::SessionStore, LabradorResult, RequestBody, RequestType, WechatCpClient, WechatRequest, WechatCommonResponse, request, get_nonce_str};
+use crate::{session::SessionStore, LabradorResult, LabraError, RequestBody, RequestType, WechatCpClient, WechatRequest, WechatCommonResponse, request, get_nonce_str};
 use crate::wechat::cp::constants::{ATTACHMENT_TYPE, MEDIA_TYPE};
+use crate::serde_helper::option_string_or_number;
 use crate::wechat::cp::method::{CpMediaMethod, WechatCpMethod};
 
+/// 轮询异步上传结果时，两次查询的最大间隔为初始间隔的倍数
+const UPLOAD_BY_URL_MAX_BACKOFF: u32 = 8;
+
 
 #[derive(Debug, Clone)]
-pub struct WechatCpMedia<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpMedia<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpMedia<'a, T> {
+impl<T: SessionStore> WechatCpMedia<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpMedia<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpMedia<T> {
         WechatCpMedia {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.media()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpMedia<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 上传多媒体文件.
     /// 上传的多媒体文件有格式和大小限制，如下：
@@ -156,6 +167,79 @@ impl<'a, T: SessionStore> WechatCpMedia<'a, T> {
         let response = self.client.post(WechatCpMethod::Media(CpMediaMethod::GetMediaJssdk), vec![("media_id".to_string(), media_id.to_string())], serde_json::Value::Null, RequestType::Json).await?;
         response.bytes()
     }
+
+    /// <pre>
+    /// 生成异步上传任务
+    /// 企业微信从url下载文件（支持Range分块下载）并校验md5，适用于超过20M的视频、文件，返回查询结果用的jobid
+    /// scene目前仅支持1（客户联系入群欢迎语素材），media_type为video或file
+    /// 文档地址：https://developer.work.weixin.qq.com/document/path/96219
+    /// </pre>
+    pub async fn upload_by_url(&self, scene: i32, media_type: &str, filename: &str, url: &str, md5: &str) -> LabradorResult<String> {
+        if media_type != "video" && media_type != "file" {
+            return Err(LabraError::RequestError(format!("异步上传仅支持video与file类型，当前为{}", media_type)));
+        }
+        if md5.len() != 32 || !md5.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(LabraError::RequestError(format!("md5应为32位十六进制字符串，当前为{}", md5)));
+        }
+        let v = self.client.post(WechatCpMethod::Media(CpMediaMethod::UploadByUrl), vec![], json!({
+            "scene": scene,
+            "type": media_type,
+            "filename": filename,
+            "url": url,
+            "md5": md5,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        v["jobid"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("jobid".to_string()))
+    }
+
+    /// <pre>
+    /// 查询异步上传任务结果
+    /// 文档地址：https://developer.work.weixin.qq.com/document/path/96219
+    /// </pre>
+    pub async fn get_upload_by_url_result(&self, jobid: &str) -> LabradorResult<WechatCpUploadByUrlResult> {
+        let v = self.client.post(WechatCpMethod::Media(CpMediaMethod::GetUploadByUrlResult), vec![], json!({"jobid": jobid}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpUploadByUrlResult>(v)
+    }
+
+    /// <pre>
+    /// 轮询异步上传任务直到完成
+    /// 首次查询前等待interval，之后每次的间隔翻倍，最长为interval的8倍；系统繁忙（-1）与网络错误不中断轮询。
+    /// 超过max_wait仍在处理中时返回`Pending`，可保存jobid之后再次调用继续等待；
+    /// 丢弃返回的Future即可取消轮询，不会留下任何状态
+    /// </pre>
+    pub async fn poll_upload_result(&self, jobid: &str, interval: Duration, max_wait: Duration) -> LabradorResult<WechatCpUploadByUrlOutcome> {
+        let start = Instant::now();
+        let mut delay = interval;
+        let mut polls = 0;
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= max_wait {
+                return Ok(WechatCpUploadByUrlOutcome::Pending { jobid: jobid.to_string(), polls, elapsed });
+            }
+            crate::runtime::sleep(delay.min(max_wait - elapsed)).await;
+            delay = (delay * 2).min(interval * UPLOAD_BY_URL_MAX_BACKOFF);
+            polls += 1;
+            let result = match self.get_upload_by_url_result(jobid).await {
+                Ok(result) => result,
+                Err(LabraError::ClientError { errcode, .. }) if errcode == "-1" => continue,
+                Err(LabraError::ConnectError(_)) | Err(LabraError::Timeout(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            match result.status {
+                WechatCpUploadByUrlStatus::Processing => continue,
+                WechatCpUploadByUrlStatus::Finished => {
+                    let detail = result.detail.unwrap_or_default();
+                    let media_id = detail.media_id.ok_or_else(|| LabraError::MissingField("media_id".to_string()))?;
+                    return Ok(WechatCpUploadByUrlOutcome::Finished { media_id, created_at: detail.created_at });
+                }
+                WechatCpUploadByUrlStatus::Failed | WechatCpUploadByUrlStatus::Unknown(_) => {
+                    let detail = result.detail.unwrap_or_default();
+                    let errcode = detail.errcode.unwrap_or_default();
+                    return Ok(WechatCpUploadByUrlOutcome::Failed { error: WechatCpUploadByUrlError::from(errcode), errcode, errmsg: detail.errmsg.unwrap_or_default() });
+                }
+            }
+        }
+    }
 }
 
 //----------------------------------------------------------------------------------------------------------------------------
@@ -181,6 +265,102 @@ impl WechatRequest for WechatCpMediaRequest {
 }
 
 
+/// 异步上传任务的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatCpUploadByUrlStatus {
+    /// 处理中
+    Processing,
+    /// 完成
+    Finished,
+    /// 异常失败
+    Failed,
+    Unknown(i32),
+}
+
+impl From<i32> for WechatCpUploadByUrlStatus {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => WechatCpUploadByUrlStatus::Processing,
+            2 => WechatCpUploadByUrlStatus::Finished,
+            3 => WechatCpUploadByUrlStatus::Failed,
+            v => WechatCpUploadByUrlStatus::Unknown(v),
+        }
+    }
+}
+
+impl From<WechatCpUploadByUrlStatus> for i32 {
+    fn from(v: WechatCpUploadByUrlStatus) -> Self {
+        match v {
+            WechatCpUploadByUrlStatus::Processing => 1,
+            WechatCpUploadByUrlStatus::Finished => 2,
+            WechatCpUploadByUrlStatus::Failed => 3,
+            WechatCpUploadByUrlStatus::Unknown(v) => v,
+        }
+    }
+}
+
+/// 异步上传任务结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpUploadByUrlResult {
+    pub status: WechatCpUploadByUrlStatus,
+    pub detail: Option<WechatCpUploadByUrlDetail>,
+}
+
+/// 异步上传任务结果详情
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WechatCpUploadByUrlDetail {
+    /// 任务失败的错误码
+    pub errcode: Option<i64>,
+    /// 任务失败的错误信息
+    pub errmsg: Option<String>,
+    /// 媒体文件上传后获取的唯一标识，3天内有效，仅任务完成时返回
+    pub media_id: Option<String>,
+    /// 媒体文件创建的时间戳，仅任务完成时返回
+    #[serde(default, with = "option_string_or_number")]
+    pub created_at: Option<i64>,
+}
+
+/// 异步上传任务失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatCpUploadByUrlError {
+    /// url非法，需确认url是否支持Range分块下载
+    InvalidUrl,
+    /// url下载数据失败，需确认url本身能否正常下载
+    DownloadFailed,
+    /// 腾讯云存储错误
+    StorageError,
+    /// md5与下载到的文件不一致
+    Md5Mismatch,
+    /// 文件大小超过限制
+    FileTooLarge,
+    Other(i64),
+}
+
+impl From<i64> for WechatCpUploadByUrlError {
+    fn from(errcode: i64) -> Self {
+        match errcode {
+            830001 => WechatCpUploadByUrlError::InvalidUrl,
+            830003 => WechatCpUploadByUrlError::DownloadFailed,
+            830004 => WechatCpUploadByUrlError::StorageError,
+            830005 => WechatCpUploadByUrlError::Md5Mismatch,
+            830006 => WechatCpUploadByUrlError::FileTooLarge,
+            v => WechatCpUploadByUrlError::Other(v),
+        }
+    }
+}
+
+/// 轮询异步上传任务的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatCpUploadByUrlOutcome {
+    /// 上传完成
+    Finished { media_id: String, created_at: Option<i64> },
+    /// 任务失败
+    Failed { error: WechatCpUploadByUrlError, errcode: i64, errmsg: String },
+    /// 等待超过max_wait时仍在处理中
+    Pending { jobid: String, polls: u32, elapsed: Duration },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatCpMediaResponse {
     pub url: Option<String>,
@@ -215,3 +395,87 @@ impl WechatRequest for WechatCpAttachmentRequest {
         form.into()
     }
 }
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer, closed_url};
+    use super::{WechatCpUploadByUrlError, WechatCpUploadByUrlOutcome};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+    const PROCESSING: &str = r#"{"errcode":0,"errmsg":"ok","status":1}"#;
+    const MD5: &str = "7b8a2e9c1f5d4e6a3b0c9d8e7f6a5b4c";
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_by_url() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","jobid":"JOBID"}"#),
+            MockResponse::json(PROCESSING),
+            MockResponse::json(r#"{"errcode":-1,"errmsg":"system busy"}"#),
+            MockResponse::json(PROCESSING),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","status":2,"detail":{"errcode":0,"errmsg":"ok","media_id":"MEDIA_ID","created_at":"1380000000"}}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("upload_by_url_corp", "secret").base_url(&server.url);
+        let media = client.media();
+        let jobid = media.upload_by_url(1, "video", "welcome.mp4", "https://example.com/welcome.mp4", MD5).await.unwrap();
+        assert_eq!(jobid, "JOBID");
+        let outcome = media.poll_upload_result(&jobid, Duration::from_millis(10), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(outcome, WechatCpUploadByUrlOutcome::Finished { media_id: "MEDIA_ID".to_string(), created_at: Some(1380000000) });
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 6);
+        assert!(requests[1].starts_with("POST /cgi-bin/media/upload_by_url?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[1]), json!({"scene": 1, "type": "video", "filename": "welcome.mp4", "url": "https://example.com/welcome.mp4", "md5": MD5}));
+        assert!(requests[2].starts_with("POST /cgi-bin/media/get_upload_by_url_result?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[5]), json!({"jobid": "JOBID"}));
+    }
+
+    #[tokio::test]
+    async fn test_poll_upload_result_failed() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","status":3,"detail":{"errcode":830005,"errmsg":"md5 not match"}}"#),
+            MockResponse::json(r#"{"errcode":40001,"errmsg":"invalid credential"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("upload_by_url_failed_corp", "secret").base_url(&server.url);
+        let outcome = client.media().poll_upload_result("JOBID", Duration::from_millis(10), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(outcome, WechatCpUploadByUrlOutcome::Failed { error: WechatCpUploadByUrlError::Md5Mismatch, errcode: 830005, errmsg: "md5 not match".to_string() });
+        // 接口本身的错误直接返回
+        assert!(matches!(client.media().poll_upload_result("JOBID", Duration::from_millis(10), Duration::from_secs(5)).await, Err(LabraError::ClientError { errcode, .. }) if errcode == "40001"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_upload_result_pending() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(PROCESSING), MockResponse::json(PROCESSING), MockResponse::json(PROCESSING)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("upload_by_url_pending_corp", "secret").base_url(&server.url);
+        // 间隔依次为20、40ms，之后剩余时间不足时只等到max_wait
+        match client.media().poll_upload_result("JOBID", Duration::from_millis(20), Duration::from_millis(100)).await.unwrap() {
+            WechatCpUploadByUrlOutcome::Pending { jobid, polls, elapsed } => {
+                assert_eq!(jobid, "JOBID");
+                assert_eq!(polls, 3);
+                assert!(elapsed >= Duration::from_millis(100));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // 丢弃Future即取消轮询
+        assert!(tokio::time::timeout(Duration::from_millis(30), client.media().poll_upload_result("JOBID", Duration::from_secs(1), Duration::from_secs(10))).await.is_err());
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_upload_by_url_params() {
+        let client = WechatCpClient::<SimpleStorage>::new("upload_by_url_params_corp", "secret").base_url(&closed_url().await);
+        assert!(matches!(client.media().upload_by_url(1, "image", "a.png", "https://example.com/a.png", MD5).await, Err(LabraError::RequestError(_))));
+        assert!(matches!(client.media().upload_by_url(1, "file", "a.zip", "https://example.com/a.zip", "md5").await, Err(LabraError::RequestError(_))));
+    }
+}
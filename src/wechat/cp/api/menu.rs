@@ -6,20 +6,26 @@ use crate::wechat::cp::method::{CpMenuMethod, WechatCpMethod};
 
 /// 菜单管理相关接口
 #[derive(Debug, Clone)]
-pub struct WechatCpMenu<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpMenu<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpMenu<'a, T> {
+impl<T: SessionStore> WechatCpMenu<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpMenu<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpMenu<T> {
         WechatCpMenu {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.menu()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpMenu<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 自定义菜单创建接口
     /// 详情请见: <a href="http://mp.weixin.qq.com/wiki/index.php?title=自定义菜单创建接口">文档</a>
@@ -27,7 +33,7 @@ impl<'a, T: SessionStore> WechatCpMenu<'a, T> {
     /// 注意: 这个方法使用配置里的agentId
     /// </pre>
     pub async fn create(&self, menu: WechatCpMenuInfo) -> LabradorResult<WechatCommonResponse> {
-        self.create_with_agentid(self.client.agent_id.to_owned().unwrap_or_default(), menu).await
+        self.create_with_agentid(self.client.inner.agent_id.to_owned().unwrap_or_default(), menu).await
     }
 
     /// <pre>
@@ -45,7 +51,7 @@ impl<'a, T: SessionStore> WechatCpMenu<'a, T> {
     /// 注意: 这个方法使用配置里的agentId
     /// </pre>
     pub async fn delete(&self) -> LabradorResult<WechatCommonResponse> {
-        self.delete_with_agentid(self.client.agent_id.to_owned().unwrap_or_default()).await
+        self.delete_with_agentid(self.client.inner.agent_id.to_owned().unwrap_or_default()).await
     }
 
     /// <pre>
@@ -63,7 +69,7 @@ impl<'a, T: SessionStore> WechatCpMenu<'a, T> {
     /// 注意: 这个方法使用配置里的agentId
     /// </pre>
     pub async fn get(&self) -> LabradorResult<WechatCommonResponse> {
-        self.delete_with_agentid(self.client.agent_id.to_owned().unwrap_or_default()).await
+        self.delete_with_agentid(self.client.inner.agent_id.to_owned().unwrap_or_default()).await
     }
 
     /// <pre>
@@ -1,25 +1,42 @@
 use serde::{Serialize, Deserialize};
 use serde_json::{Value};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient, WechatCpNewArticle, WechatMpNewsArticle};
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient, WechatCpNewArticle, WechatMpNewsArticle};
 use crate::wechat::cp::method::{CpMessageMethod, WechatCpMethod};
+use crate::serde_helper::option_string_or_number;
+use crate::text::{self, TextPolicy};
+
+/// 单次发送的成员上限
+const MAX_USERS: usize = 1000;
+/// 单次发送的部门上限
+const MAX_PARTIES: usize = 100;
+/// 单次发送的标签上限
+const MAX_TAGS: usize = 100;
+/// 发送给应用可见范围内的全部成员
+const TO_ALL: &str = "@all";
 
 /// 菜单管理相关接口
 #[derive(Debug, Clone)]
-pub struct WechatCpMessage<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpMessage<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpMessage<'a, T> {
+impl<T: SessionStore> WechatCpMessage<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpMessage<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpMessage<T> {
         WechatCpMessage {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.message()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpMessage<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 发送消息
     /// 详情请见: <a href="https://work.weixin.qq.com/api/doc/90000/90135/90236">文档</a>
@@ -27,12 +44,30 @@ impl<'a, T: SessionStore> WechatCpMessage<'a, T> {
     pub async fn send(&self, mut req: WechatCpMessageRequest) -> LabradorResult<WechatCpMessageResponse> {
         let agent_id = req.agent_id.unwrap_or_default();
         if agent_id == 0 {
-            req.agent_id = self.client.agent_id;
+            req.agent_id = self.client.inner.agent_id;
         }
+        let req = req.sanitize(&TextPolicy::cp_text())?;
        let v= self.client.post(WechatCpMethod::Message(CpMessageMethod::Send), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpMessageResponse>(v)
     }
 
+    /// <pre>
+    /// 按接收人分批发送消息
+    /// 成员超过1000个时每1000个为一批，部门与标签随第一批发送；设置了to_all时只发送一次@all
+    /// 各批次的无效接收人合并后返回；某一批发送失败时返回该错误，之前的批次已发送
+    /// </pre>
+    pub async fn send_chunked(&self, req: WechatCpMessageRequest, recipients: WechatCpRecipients) -> LabradorResult<WechatCpChunkedMessageResponse> {
+        let mut result = WechatCpChunkedMessageResponse::default();
+        for (to_user, to_party, to_tag) in recipients.chunks()? {
+            let mut req = req.clone();
+            req.to_user = to_user;
+            req.to_party = to_party;
+            req.to_tag = to_tag;
+            result.merge(self.send(req).await?);
+        }
+        Ok(result)
+    }
+
     /// <pre>
     /// 互联企业的应用支持推送文本、图片、视频、文件、图文等类型。
     /// 详情请见: <a href="https://qyapi.weixin.qq.com/cgi-bin/linkedcorp/message/send?access_token=ACCESS_TOKEN">文档</a>
@@ -40,7 +75,7 @@ impl<'a, T: SessionStore> WechatCpMessage<'a, T> {
     pub async fn send_linked_corp_message(&self, mut req: WechatCpLinkedCorpMessage) -> LabradorResult<WechatCpLinkedCorpMessageResponse> {
         let agent_id = req.agent_id.unwrap_or_default();
         if agent_id == 0 {
-            req.agent_id = self.client.agent_id;
+            req.agent_id = self.client.inner.agent_id;
         }
         let v = self.client.post(WechatCpMethod::Message(CpMessageMethod::LinkedCorpSend), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpLinkedCorpMessageResponse>(v)
@@ -56,7 +91,7 @@ impl<'a, T: SessionStore> WechatCpMessage<'a, T> {
     pub async fn send_school_contact_message(&self, mut req: WechatCpSchoolContactMessage) -> LabradorResult<WechatCpSchoolContactMessageResponse> {
         let agent_id = req.agentid.unwrap_or_default();
         if agent_id == 0 {
-            req.agentid = self.client.agent_id;
+            req.agentid = self.client.inner.agent_id;
         }
        let v = self.client.post(WechatCpMethod::Message(CpMessageMethod::LinkedCorpSend), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpSchoolContactMessageResponse>(v)
@@ -179,6 +214,114 @@ pub struct WechatCpMessageRequest {
     pub card_image_aspect_ratio: Option<f64>,
 }
 
+impl WechatCpMessageRequest {
+    /// <pre>
+    /// 按策略处理文本消息的内容
+    /// 发送时默认去除控制字符并校验2048字节的限制，需要截断时可先调用：`req.sanitize(&TextPolicy::cp_text().truncate("…"))`
+    /// </pre>
+    pub fn sanitize(mut self, policy: &TextPolicy) -> LabradorResult<Self> {
+        if self.msg_type == "text" {
+            self.content = text::sanitize(&self.content, policy)?;
+        }
+        Ok(self)
+    }
+}
+
+/// <pre>
+/// 消息接收人
+/// 成员、部门、标签以`|`分隔拼接，单次发送最多1000个成员、100个部门、100个标签，
+/// 成员超过上限时由[`WechatCpMessage::send_chunked`]分批发送；设置to_all时忽略其余接收人
+/// </pre>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WechatCpRecipients {
+    users: Vec<String>,
+    parties: Vec<String>,
+    tags: Vec<String>,
+    to_all: bool,
+}
+
+impl WechatCpRecipients {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 成员ID列表，重复的成员只发送一次
+    pub fn users<I: IntoIterator<Item = S>, S: Into<String>>(mut self, users: I) -> Self {
+        extend_unique(&mut self.users, users);
+        self
+    }
+
+    /// 部门ID列表
+    pub fn parties<I: IntoIterator<Item = S>, S: Into<String>>(mut self, parties: I) -> Self {
+        extend_unique(&mut self.parties, parties);
+        self
+    }
+
+    /// 标签ID列表
+    pub fn tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        extend_unique(&mut self.tags, tags);
+        self
+    }
+
+    /// 发送给应用可见范围内的全部成员
+    pub fn to_all(mut self) -> Self {
+        self.to_all = true;
+        self
+    }
+
+    /// <pre>
+    /// 校验单次发送的上限
+    /// 部门、标签超过100个或未指定任何接收人时返回错误；成员超过1000个时需分批发送，也返回错误
+    /// </pre>
+    pub fn validate(&self) -> LabradorResult<()> {
+        self.validate_parties_and_tags()?;
+        if !self.to_all && self.users.len() > MAX_USERS {
+            return Err(LabraError::RequestError(format!("单次发送的成员不能超过{}个，当前{}个，请使用send_chunked分批发送", MAX_USERS, self.users.len())));
+        }
+        Ok(())
+    }
+
+    fn validate_parties_and_tags(&self) -> LabradorResult<()> {
+        if self.to_all {
+            return Ok(());
+        }
+        if self.users.is_empty() && self.parties.is_empty() && self.tags.is_empty() {
+            return Err(LabraError::RequestError("未指定消息接收人".to_string()));
+        }
+        if self.parties.len() > MAX_PARTIES {
+            return Err(LabraError::RequestError(format!("单次发送的部门不能超过{}个，当前{}个", MAX_PARTIES, self.parties.len())));
+        }
+        if self.tags.len() > MAX_TAGS {
+            return Err(LabraError::RequestError(format!("单次发送的标签不能超过{}个，当前{}个", MAX_TAGS, self.tags.len())));
+        }
+        Ok(())
+    }
+
+    /// 每一批的(touser, toparty, totag)
+    fn chunks(&self) -> LabradorResult<Vec<(String, Option<String>, Option<String>)>> {
+        self.validate_parties_and_tags()?;
+        if self.to_all {
+            return Ok(vec![(TO_ALL.to_string(), None, None)]);
+        }
+        let join = |ids: &[String]| if ids.is_empty() { None } else { Some(ids.join("|")) };
+        let mut chunks = self.users.chunks(MAX_USERS).map(|users| (users.join("|"), None, None)).collect::<Vec<_>>();
+        if chunks.is_empty() {
+            chunks.push((String::default(), None, None));
+        }
+        chunks[0].1 = join(&self.parties);
+        chunks[0].2 = join(&self.tags);
+        Ok(chunks)
+    }
+}
+
+fn extend_unique<I: IntoIterator<Item = S>, S: Into<String>>(ids: &mut Vec<String>, values: I) {
+    for id in values.into_iter().map(Into::into) {
+        if !id.is_empty() && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+}
+
 /// 引用文献样式
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuoteArea {
@@ -308,6 +451,7 @@ pub struct StatisticItem {
     /// 应用id
     pub agentid: Option<i32>,
     /// 发消息成功人次
+    #[serde(default, with = "option_string_or_number")]
     pub count: Option<i32>,
 }
 
@@ -413,3 +557,117 @@ pub struct WechatCpMessageResponse {
     pub invalidtag: Option<String>,
     pub msgid: Option<String>,
 }
+
+/// 分批发送的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpChunkedMessageResponse {
+    /// 各批次的无效成员
+    pub invaliduser: Vec<String>,
+    pub invalidparty: Vec<String>,
+    pub invalidtag: Vec<String>,
+    /// 各批次的消息id，可用于撤回
+    pub msgids: Vec<String>,
+}
+
+impl WechatCpChunkedMessageResponse {
+    fn merge(&mut self, response: WechatCpMessageResponse) {
+        let split = |ids: Option<String>| ids.unwrap_or_default().split('|').filter(|v| !v.is_empty()).map(String::from).collect::<Vec<_>>();
+        self.invaliduser.extend(split(response.invaliduser));
+        self.invalidparty.extend(split(response.invalidparty));
+        self.invalidtag.extend(split(response.invalidtag));
+        self.msgids.extend(response.msgid.filter(|v| !v.is_empty()));
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::*;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn user_ids(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("user{}", i)).collect()
+    }
+
+    fn text_message() -> WechatCpMessageRequest {
+        serde_json::from_value(json!({"to_user": "", "agent_id": 1000002, "msg_type": "text", "content": "你的快递已到"})).unwrap()
+    }
+
+    /// 请求体
+    fn bodies(requests: &[String]) -> Vec<Value> {
+        requests.iter().map(|r| serde_json::from_str::<Value>(r.split("\r\n\r\n").nth(1).unwrap()).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_chunks() {
+        let chunks = WechatCpRecipients::new().users(user_ids(1001)).parties(vec!["1", "2"]).tags(vec!["3"]).chunks().unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0.split('|').count(), 1000);
+        assert_eq!((chunks[0].1.as_deref(), chunks[0].2.as_deref()), (Some("1|2"), Some("3")));
+        assert_eq!(chunks[1], ("user1000".to_string(), None, None));
+        assert_eq!(WechatCpRecipients::new().users(user_ids(2000)).chunks().unwrap().len(), 2);
+        assert_eq!(WechatCpRecipients::new().users(user_ids(1000)).chunks().unwrap().len(), 1);
+        // 重复的成员只发送一次
+        assert_eq!(WechatCpRecipients::new().users(user_ids(1000)).users(user_ids(1000)).chunks().unwrap().len(), 1);
+        // 只有部门与标签
+        assert_eq!(WechatCpRecipients::new().tags(vec!["3"]).chunks().unwrap(), vec![(String::default(), None, Some("3".to_string()))]);
+        // @all忽略其余接收人
+        assert_eq!(WechatCpRecipients::new().users(user_ids(1001)).parties(vec!["1"]).to_all().chunks().unwrap(), vec![("@all".to_string(), None, None)]);
+
+        assert!(WechatCpRecipients::new().users(user_ids(1000)).validate().is_ok());
+        assert!(matches!(WechatCpRecipients::new().users(user_ids(1001)).validate(), Err(LabraError::RequestError(msg)) if msg.contains("1001")));
+        assert!(WechatCpRecipients::new().users(user_ids(1001)).to_all().validate().is_ok());
+        assert!(matches!(WechatCpRecipients::new().parties(user_ids(101)).chunks(), Err(LabraError::RequestError(msg)) if msg.contains("部门")));
+        assert!(matches!(WechatCpRecipients::new().tags(user_ids(101)).chunks(), Err(LabraError::RequestError(msg)) if msg.contains("标签")));
+        assert!(WechatCpRecipients::new().users(vec![""]).chunks().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_chunked() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","invaliduser":"user1|user2","invalidparty":"2","invalidtag":"","msgid":"MSGID1"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","invaliduser":"user1000","msgid":"MSGID2"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("message_chunked_corp", "secret").base_url(&server.url);
+        let recipients = WechatCpRecipients::new().users(user_ids(1001)).parties(vec!["1", "2"]);
+        let result = client.message().send_chunked(text_message(), recipients).await.unwrap();
+        assert_eq!(result.invaliduser, vec!["user1", "user2", "user1000"]);
+        assert_eq!(result.invalidparty, vec!["2"]);
+        assert!(result.invalidtag.is_empty());
+        assert_eq!(result.msgids, vec!["MSGID1", "MSGID2"]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].starts_with("POST /cgi-bin/message/send?access_token=ACCESS_TOKEN"));
+        let bodies = bodies(&requests[1..]);
+        assert_eq!(bodies[0]["to_user"].as_str().unwrap().split('|').count(), 1000);
+        assert_eq!(bodies[0]["to_party"], "1|2");
+        assert_eq!(bodies[1]["to_user"], "user1000");
+        assert_eq!(bodies[1]["to_party"], Value::Null);
+        assert_eq!(bodies[1]["content"], "你的快递已到");
+    }
+
+    #[tokio::test]
+    async fn test_send_chunked_to_all() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","msgid":"MSGID1"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("message_to_all_corp", "secret").base_url(&server.url);
+        let recipients = WechatCpRecipients::new().users(user_ids(1001)).tags(vec!["3"]).to_all();
+        let result = client.message().send_chunked(text_message(), recipients).await.unwrap();
+        assert_eq!(result.msgids, vec!["MSGID1"]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let body = &bodies(&requests[1..])[0];
+        assert_eq!(body["to_user"], "@all");
+        assert_eq!(body["to_party"], Value::Null);
+        assert_eq!(body["to_tag"], Value::Null);
+    }
+}
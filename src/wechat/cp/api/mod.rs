@@ -9,6 +9,19 @@ mod department;
 mod agent;
 mod tag;
 mod user;
+mod kf;
+mod checkin;
+mod export;
+mod linkedcorp;
+mod corpgroup;
+mod msgaudit;
+mod living;
+mod appchat;
+mod vacation;
+mod id_convert;
+mod school;
+mod security;
+mod approval;
 
 // 企业微信
 
@@ -23,3 +36,16 @@ pub use self::department::*;
 pub use self::agent::*;
 pub use self::tag::*;
 pub use self::user::*;
+pub use self::kf::*;
+pub use self::checkin::*;
+pub use self::export::*;
+pub use self::linkedcorp::*;
+pub use self::corpgroup::*;
+pub use self::msgaudit::*;
+pub use self::living::*;
+pub use self::appchat::*;
+pub use self::vacation::*;
+pub use self::id_convert::*;
+pub use self::school::*;
+pub use self::security::*;
+pub use self::approval::*;
@@ -0,0 +1,216 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient};
+use crate::wechat::cp::method::{CpMsgAuditMethod, WechatCpMethod};
+
+/// 会话内容存档
+///
+/// 不依赖会话存档SDK的接口，调用时需使用会话内容存档的Secret构造客户端。
+#[derive(Debug, Clone)]
+pub struct WechatCpMsgAudit<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpMsgAudit<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpMsgAudit<T> {
+        WechatCpMsgAudit {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.msg_audit()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpMsgAudit<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取会话内容存档开启成员列表.
+    /// <pre>
+    /// 企业可通过此接口，获取企业开启会话内容存档的成员列表
+    /// type：1表示办公版，2表示服务版，3表示企业版，不填时返回全部
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/91614">文档</a>
+    /// </pre>
+    pub async fn get_permit_user_list(&self, r#type: Option<i32>) -> LabradorResult<Vec<String>> {
+        let mut req = json!({});
+        if let Some(r#type) = r#type {
+            req["type"] = r#type.into();
+        }
+        let v = self.client.post(WechatCpMethod::MsgAudit(CpMsgAuditMethod::GetPermitUserList), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<WechatCpMsgAuditPermitUserList>(v)?;
+        Ok(v.ids)
+    }
+
+    /// 获取单聊会话同意情况.
+    /// <pre>
+    /// 企业可通过下述接口，获取会话中外部成员的同意情况
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/91782">文档</a>
+    /// </pre>
+    pub async fn check_single_agree(&self, info: Vec<WechatCpMsgAuditSingleAgreeInfo>) -> LabradorResult<Vec<WechatCpMsgAuditAgreeInfo>> {
+        let v = self.client.post(WechatCpMethod::MsgAudit(CpMsgAuditMethod::CheckSingleAgree), vec![], json!({
+            "info": info,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<WechatCpMsgAuditAgreeResponse>(v)?;
+        Ok(v.agreeinfo)
+    }
+
+    /// 获取群聊会话同意情况.
+    /// <pre>
+    /// 返回群内各外部成员的同意情况
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/91782">文档</a>
+    /// </pre>
+    pub async fn check_room_agree(&self, roomid: &str) -> LabradorResult<Vec<WechatCpMsgAuditAgreeInfo>> {
+        let v = self.client.post(WechatCpMethod::MsgAudit(CpMsgAuditMethod::CheckRoomAgree), vec![], json!({
+            "roomid": roomid,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<WechatCpMsgAuditAgreeResponse>(v)?;
+        Ok(v.agreeinfo)
+    }
+
+    /// 获取会话内容存档内部群信息.
+    /// <pre>
+    /// 企业可通过此接口，获取会话内容存档本企业的内部群信息，包括群名称、群主id、公告、群创建时间以及所有群成员的id与加入时间
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92951">文档</a>
+    /// </pre>
+    pub async fn get_group_chat(&self, roomid: &str) -> LabradorResult<WechatCpMsgAuditGroupChat> {
+        let v = self.client.post(WechatCpMethod::MsgAudit(CpMsgAuditMethod::GetGroupChat), vec![], json!({
+            "roomid": roomid,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpMsgAuditGroupChat>(v)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpMsgAuditPermitUserList {
+    #[serde(default)]
+    pub ids: Vec<String>,
+}
+
+/// 待查询的单聊会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpMsgAuditSingleAgreeInfo {
+    /// 内部成员的userid
+    pub userid: String,
+    /// 外部成员的externalopenid（微信接口字段名为exteranalopenid）
+    #[serde(rename = "exteranalopenid")]
+    pub external_openid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpMsgAuditAgreeResponse {
+    #[serde(default)]
+    pub agreeinfo: Vec<WechatCpMsgAuditAgreeInfo>,
+}
+
+/// 同意情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpMsgAuditAgreeInfo {
+    /// 同意状态改变的具体时间，utc时间
+    pub status_change_time: Option<i64>,
+    /// 内部成员的userid，群聊会话中不返回
+    pub userid: Option<String>,
+    /// 外部成员的externalopenid（微信接口字段名为exteranalopenid）
+    #[serde(rename = "exteranalopenid")]
+    pub external_openid: String,
+    /// 同意:"Agree"，不同意:"Disagree"，默认同意:"Default_Agree"
+    pub agree_status: String,
+}
+
+impl WechatCpMsgAuditAgreeInfo {
+    /// 是否同意存档，默认同意也视为同意
+    pub fn is_agreed(&self) -> bool {
+        self.agree_status == "Agree" || self.agree_status == "Default_Agree"
+    }
+}
+
+/// 内部群信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpMsgAuditGroupChat {
+    /// 群名称
+    pub roomname: Option<String>,
+    /// 群主id
+    pub creator: Option<String>,
+    /// 群创建时间
+    pub room_create_time: Option<i64>,
+    /// 群公告
+    pub notice: Option<String>,
+    #[serde(default)]
+    pub members: Vec<WechatCpMsgAuditGroupChatMember>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpMsgAuditGroupChatMember {
+    /// 群成员的id，userid
+    pub memberid: String,
+    /// 群成员的入群时间
+    pub jointime: Option<i64>,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::{SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpMsgAuditAgreeResponse, WechatCpMsgAuditGroupChat, WechatCpMsgAuditSingleAgreeInfo};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_agree_info_deserialize() {
+        let data = r#"{"errcode":0,"errmsg":"ok","agreeinfo":[{"status_change_time":1562766651,"userid":"XuJinSheng","exteranalopenid":"wmeDKaCPAAGdvxciQWxVsAKwV2HxNAAA","agree_status":"Agree"},{"status_change_time":1562766652,"exteranalopenid":"wmeDKaCQAAIQ_p7ACn_jpLVBJSGocAAA","agree_status":"Disagree"}]}"#;
+        let v = serde_json::from_str::<Value>(data).unwrap();
+        let res = WechatCommonResponse::parse::<WechatCpMsgAuditAgreeResponse>(v).unwrap();
+        assert_eq!(res.agreeinfo.len(), 2);
+        assert_eq!(res.agreeinfo[0].userid.as_deref(), Some("XuJinSheng"));
+        assert_eq!(res.agreeinfo[0].external_openid, "wmeDKaCPAAGdvxciQWxVsAKwV2HxNAAA");
+        assert!(res.agreeinfo[0].is_agreed());
+        assert!(res.agreeinfo[1].userid.is_none());
+        assert!(!res.agreeinfo[1].is_agreed());
+    }
+
+    #[test]
+    fn test_group_chat_deserialize() {
+        let data = r#"{"roomname":"蓦然回首","creator":"ZhangWenChao","room_create_time":1592361604,"notice":"","members":[{"memberid":"ZhangWenChao","jointime":1592361605},{"memberid":"xujinsheng","jointime":1592377076}],"errcode":0,"errmsg":"ok"}"#;
+        let v = serde_json::from_str::<Value>(data).unwrap();
+        let room = WechatCommonResponse::parse::<WechatCpMsgAuditGroupChat>(v).unwrap();
+        assert_eq!(room.roomname.as_deref(), Some("蓦然回首"));
+        assert_eq!(room.creator.as_deref(), Some("ZhangWenChao"));
+        assert_eq!(room.room_create_time, Some(1592361604));
+        assert_eq!(room.members.len(), 2);
+        assert_eq!(room.members[1].memberid, "xujinsheng");
+        assert_eq!(room.members[1].jointime, Some(1592377076));
+    }
+
+    #[tokio::test]
+    async fn test_check_single_agree() {
+        let agree = r#"{"errcode":0,"errmsg":"ok","agreeinfo":[{"status_change_time":1562766651,"userid":"XuJinSheng","exteranalopenid":"wmeDKaCQAAGd9oGiQWxVsAKwV2HxNAAA","agree_status":"Default_Agree"}]}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(agree)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("msgaudit_single_corp", "secret").base_url(&server.url);
+        let info = vec![WechatCpMsgAuditSingleAgreeInfo { userid: "XuJinSheng".to_string(), external_openid: "wmeDKaCQAAGd9oGiQWxVsAKwV2HxNAAA".to_string() }];
+        let res = client.msg_audit().check_single_agree(info).await.unwrap();
+        assert!(res[0].is_agreed());
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/msgaudit/check_single_agree?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"info":[{"exteranalopenid":"wmeDKaCQAAGd9oGiQWxVsAKwV2HxNAAA","userid":"XuJinSheng"}]}"#));
+    }
+
+    #[tokio::test]
+    async fn test_get_permit_user_list() {
+        let list = r#"{"errcode":0,"errmsg":"ok","ids":["userid_111","userid_222","userid_333"]}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(list)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("msgaudit_permit_corp", "secret").base_url(&server.url);
+        let ids = client.msg_audit().get_permit_user_list(Some(1)).await.unwrap();
+        assert_eq!(ids, vec!["userid_111", "userid_222", "userid_333"]);
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/msgaudit/get_permit_user_list?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"type":1}"#));
+    }
+}
@@ -7,30 +7,36 @@ use crate::wechat::cp::method::{CpOauth2Method, WechatCpMethod};
 
 
 #[derive(Debug, Clone)]
-pub struct WechatCpOauth2<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpOauth2<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpOauth2<'a, T> {
+impl<T: SessionStore> WechatCpOauth2<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpOauth2<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpOauth2<T> {
         WechatCpOauth2 {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.oauth2()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpOauth2<T> {
+        Self::from_client(client.clone())
+    }
+
 
     /// <pre>
     /// 构造oauth2授权的url连接
     /// 详情请见:  <a href="http://qydev.weixin.qq.com/wiki/index.php?title=企业获取code">文档</a>
     /// </pre>
     pub fn build_authorization_url(&self, redirect_uri: &str, scope: &str, state: Option<&str>) -> String {
-        let mut url = format!("{}?appid={}&redirect_uri={}&response_type=code&scope={}", CpOauth2Method::Oauth2Authorize.get_method(), &self.client.corp_id, urlencoding::encode(redirect_uri), scope);
+        let mut url = format!("{}?appid={}&redirect_uri={}&response_type=code&scope={}", CpOauth2Method::Oauth2Authorize.get_method(), &self.client.inner.corp_id, urlencoding::encode(redirect_uri), scope);
         if SNSAPI_PRIVATEINFO.eq(scope) || SNSAPI_USERINFO.eq(scope) {
             url.push_str("&agentid=");
-            url.push_str(&self.client.agent_id.to_owned().unwrap_or_default().to_string())
+            url.push_str(&self.client.inner.agent_id.to_owned().unwrap_or_default().to_string())
         }
 
         if let Some(state) = state {
@@ -47,7 +53,7 @@ impl<'a, T: SessionStore> WechatCpOauth2<'a, T> {
     /// 详情请见: <a href="http://qydev.weixin.qq.com/wiki/index.php?title=企业获取code">文档</a>
     /// </pre>
     pub fn build_authorization_url_with_state(&self, state: &str) -> String {
-        self.build_authorization_with_url(&self.client.oauth2_redirect_uri.to_owned().unwrap_or_default(), state.into())
+        self.build_authorization_with_url(&self.client.inner.oauth2_redirect_uri.to_owned().unwrap_or_default(), state.into())
     }
 
     /// <pre>
@@ -67,7 +73,7 @@ impl<'a, T: SessionStore> WechatCpOauth2<'a, T> {
     /// 注意: 这个方法使用client里的agentId
     /// </pre>
     pub async fn get_user_info(&self, code: &str) -> LabradorResult<WechatCpOauth2UserInfo> {
-        self.get_user_info_with_agent(code, self.client.agent_id.to_owned().unwrap_or_default()).await
+        self.get_user_info_with_agent(code, self.client.inner.agent_id.to_owned().unwrap_or_default()).await
     }
 
     /// <pre>
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+
+use futures_util::Stream;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient, Page, PagedStream};
+use crate::wechat::cp::method::{CpSchoolMethod, WechatCpMethod};
+use crate::wechat::cp::WechatCpIdConvert;
+
+/// 家校沟通
+///
+/// 管理家校通讯录中的学生、家长与班级等部门，学生与家长通过学生的userid关联。
+#[derive(Debug, Clone)]
+pub struct WechatCpSchool<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpSchool<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpSchool<T> {
+        WechatCpSchool {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.school()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpSchool<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 创建学生.
+    /// <pre>
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/school/user/create_student?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92325">文档</a>
+    /// </pre>
+    pub async fn create_student(&self, student: &WechatCpSchoolStudent) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatCpMethod::School(CpSchoolMethod::CreateStudent), vec![], json!({
+            "student_userid": student.student_userid,
+            "name": student.name,
+            "department": student.department,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 创建家长.
+    /// <pre>
+    /// 家长的children中需填写已创建的学生userid及与学生的关系
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/school/user/create_parent?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92333">文档</a>
+    /// </pre>
+    pub async fn create_parent(&self, parent: &WechatCpSchoolParent) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatCpMethod::School(CpSchoolMethod::CreateParent), vec![], parent, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// 批量创建家长.
+    /// <pre>
+    /// 每次最多100个，返回结果与传入顺序一致，单个家长创建失败不影响其他家长
+    /// 接口只在result_list中返回失败的家长，未返回的视为创建成功
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/school/user/batch_create_parent?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92334">文档</a>
+    /// </pre>
+    pub async fn batch_create_parent(&self, parents: &[WechatCpSchoolParent]) -> LabradorResult<Vec<WechatCpSchoolBatchItem>> {
+        let v = self.client.post(WechatCpMethod::School(CpSchoolMethod::BatchCreateParent), vec![], json!({
+            "parents": parents,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let userids = parents.iter().map(|v| v.parent_userid.as_str()).collect::<Vec<_>>();
+        WechatCpSchoolBatchItem::from_response(v, &userids, "parent_userid")
+    }
+
+    /// 获取部门成员详情.
+    /// <pre>
+    /// 返回学生与家长的混合列表，`fetch_child`为true时递归获取子部门的成员
+    /// `cursor` 上一页返回的next_cursor，第一页不填；`limit` 每页返回的条数
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/school/user/list?access_token=ACCESS_TOKEN&department_id=DEPARTMENT_ID&fetch_child=FETCH_CHILD">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92338">文档</a>
+    /// </pre>
+    pub async fn list_user(&self, department_id: i64, fetch_child: bool, cursor: Option<&str>, limit: Option<i32>) -> LabradorResult<WechatCpSchoolUserList> {
+        let mut params = vec![
+            ("department_id".to_string(), department_id.to_string()),
+            ("fetch_child".to_string(), (fetch_child as i32).to_string()),
+        ];
+        if let Some(cursor) = cursor {
+            params.push(("cursor".to_string(), cursor.to_string()));
+        }
+        if let Some(limit) = limit {
+            params.push(("limit".to_string(), limit.to_string()));
+        }
+        let v = self.client.get(WechatCpMethod::School(CpSchoolMethod::ListUser), params, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpSchoolUserList>(v)
+    }
+
+    /// 获取部门全部成员详情
+    /// <pre>
+    /// 基于list_user按next_cursor自动翻页，消费时才会发起请求。
+    /// </pre>
+    pub fn list_all_user(&self, department_id: i64, fetch_child: bool, limit: Option<i32>) -> impl Stream<Item = LabradorResult<WechatCpSchoolUser>> + '_ {
+        let client = self.client.to_owned();
+        PagedStream::new(move |cursor: Option<String>| {
+            let school = WechatCpSchool::from_client(client.to_owned());
+            async move {
+                let res = school.list_user(department_id, fetch_child, cursor.as_deref(), limit).await?;
+                Ok(Page::with_cursor(res.users, res.next_cursor))
+            }
+        }).into_stream()
+    }
+
+    /// 创建部门.
+    /// <pre>
+    /// 创建班级、年级、学段、校区等部门，返回部门id
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/school/department/create?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92340">文档</a>
+    /// </pre>
+    pub async fn create_department(&self, department: &WechatCpSchoolDepartment) -> LabradorResult<i64> {
+        let v = self.client.post(WechatCpMethod::School(CpSchoolMethod::CreateDepartment), vec![], department, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        v["id"].as_i64().ok_or_else(|| LabraError::MissingField("id".to_string()))
+    }
+
+    /// 获取部门列表.
+    /// <pre>
+    /// `id` 部门id，获取指定部门及其下的子部门；不填时获取全量组织架构
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/school/department/list?access_token=ACCESS_TOKEN&id=ID">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/92343">文档</a>
+    /// </pre>
+    pub async fn list_department(&self, id: Option<i64>) -> LabradorResult<Vec<WechatCpSchoolDepartment>> {
+        let params = id.map(|id| vec![("id".to_string(), id.to_string())]).unwrap_or_default();
+        let v = self.client.get(WechatCpMethod::School(CpSchoolMethod::ListDepartment), params, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpSchoolDepartment>>(v, "departments")
+    }
+
+    /// 家长userid转openid.
+    /// <pre>
+    /// 用于向家长发起微信支付、企业付款等，家长需已关注家校通知
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/90202">文档</a>
+    /// </pre>
+    pub async fn convert_to_openid(&self, parent_userid: &str) -> LabradorResult<String> {
+        WechatCpIdConvert::from_client(self.client.to_owned()).convert_to_openid(parent_userid).await
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 学生
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpSchoolStudent {
+    /// 学生userid
+    pub student_userid: String,
+    /// 学生姓名
+    #[serde(default)]
+    pub name: String,
+    /// 学生所在的班级id列表
+    #[serde(default)]
+    pub department: Vec<i64>,
+    /// 学生的家长，仅在获取成员详情时返回
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<WechatCpSchoolStudentParent>,
+}
+
+impl WechatCpSchoolStudent {
+    pub fn new<S: Into<String>>(student_userid: S, name: S, department: Vec<i64>) -> Self {
+        WechatCpSchoolStudent {
+            student_userid: student_userid.into(),
+            name: name.into(),
+            department,
+            parents: vec![],
+        }
+    }
+}
+
+/// 学生详情中的家长
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpSchoolStudentParent {
+    /// 家长userid
+    pub parent_userid: String,
+    /// 家长与学生的关系
+    pub relation: String,
+    /// 家长手机号
+    pub mobile: Option<String>,
+    /// 家长是否关注了家校通知，0-未关注，1-已关注
+    pub is_subscribe: Option<i32>,
+    /// 家长的external_userid
+    pub external_userid: Option<String>,
+}
+
+/// 家长
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpSchoolParent {
+    /// 家长userid
+    pub parent_userid: String,
+    /// 家长手机号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mobile: Option<String>,
+    /// 是否发送邀请短信，创建时有效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_invite: Option<bool>,
+    /// 家长的孩子
+    #[serde(default)]
+    pub children: Vec<WechatCpSchoolChild>,
+    /// 家长是否关注了家校通知，0-未关注，1-已关注，仅在获取成员详情时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_subscribe: Option<i32>,
+    /// 家长的external_userid，仅在获取成员详情时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_userid: Option<String>,
+}
+
+impl WechatCpSchoolParent {
+    pub fn new<S: Into<String>>(parent_userid: S, mobile: S) -> Self {
+        WechatCpSchoolParent {
+            parent_userid: parent_userid.into(),
+            mobile: Some(mobile.into()),
+            to_invite: None,
+            children: vec![],
+            is_subscribe: None,
+            external_userid: None,
+        }
+    }
+
+    /// 添加孩子，`relation`为家长与学生的关系，如“爸爸”、“妈妈”
+    pub fn child<S: Into<String>>(mut self, student_userid: S, relation: S) -> Self {
+        self.children.push(WechatCpSchoolChild {
+            student_userid: student_userid.into(),
+            relation: relation.into(),
+            name: None,
+        });
+        self
+    }
+}
+
+/// 家长的孩子
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpSchoolChild {
+    /// 学生userid
+    pub student_userid: String,
+    /// 家长与学生的关系
+    pub relation: String,
+    /// 学生姓名，仅在获取成员详情时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// 家校通讯录成员，按是否有student_userid区分学生与家长
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WechatCpSchoolUser {
+    Student(WechatCpSchoolStudent),
+    Parent(WechatCpSchoolParent),
+}
+
+impl WechatCpSchoolUser {
+    pub fn userid(&self) -> &str {
+        match self {
+            WechatCpSchoolUser::Student(v) => &v.student_userid,
+            WechatCpSchoolUser::Parent(v) => &v.parent_userid,
+        }
+    }
+}
+
+/// <pre>
+/// 部门成员详情
+/// 学生在students，家长在parents，混合列表在user_list中返回，这里统一合并到users
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SchoolUserListRaw")]
+pub struct WechatCpSchoolUserList {
+    pub users: Vec<WechatCpSchoolUser>,
+    /// 下一页的游标，没有更多时为空
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SchoolUserListRaw {
+    #[serde(default)]
+    students: Vec<WechatCpSchoolStudent>,
+    #[serde(default)]
+    parents: Vec<WechatCpSchoolParent>,
+    #[serde(default)]
+    user_list: Vec<WechatCpSchoolUser>,
+    next_cursor: Option<String>,
+}
+
+impl From<SchoolUserListRaw> for WechatCpSchoolUserList {
+    fn from(v: SchoolUserListRaw) -> Self {
+        let mut users = v.user_list;
+        users.extend(v.students.into_iter().map(WechatCpSchoolUser::Student));
+        users.extend(v.parents.into_iter().map(WechatCpSchoolUser::Parent));
+        WechatCpSchoolUserList {
+            users,
+            next_cursor: v.next_cursor,
+        }
+    }
+}
+
+/// 批量接口中单个成员的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatCpSchoolBatchItem {
+    /// 传入的userid
+    pub userid: String,
+    /// 失败时为接口返回的错误码与错误信息
+    pub result: Result<(), WechatCpSchoolBatchFailure>,
+}
+
+/// 批量接口中单个成员失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatCpSchoolBatchFailure {
+    pub errcode: i64,
+    pub errmsg: String,
+}
+
+impl WechatCpSchoolBatchItem {
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// <pre>
+    /// 按result_list合并每个成员的结果
+    /// 部分失败时接口的errcode可能不为0，只要返回了result_list就按成员区分结果，否则按接口错误返回
+    /// </pre>
+    fn from_response(v: Value, userids: &[&str], key: &str) -> LabradorResult<Vec<Self>> {
+        let failures = match v["result_list"].as_array() {
+            Some(list) => list.iter().filter_map(|item| {
+                let errcode = item["errcode"].as_i64().unwrap_or_default();
+                let userid = item[key].as_str()?;
+                (errcode != 0).then(|| (userid.to_string(), WechatCpSchoolBatchFailure {
+                    errcode,
+                    errmsg: item["errmsg"].as_str().unwrap_or_default().to_string(),
+                }))
+            }).collect::<HashMap<_, _>>(),
+            None => {
+                WechatCommonResponse::parse::<Value>(v)?;
+                HashMap::new()
+            }
+        };
+        Ok(userids.iter().map(|userid| WechatCpSchoolBatchItem {
+            userid: userid.to_string(),
+            result: failures.get(*userid).cloned().map_or(Ok(()), Err),
+        }).collect())
+    }
+}
+
+/// 家校通讯录部门
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpSchoolDepartment {
+    /// 部门id，创建时不填则自动生成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    /// 部门名称
+    pub name: String,
+    /// 父部门id
+    #[serde(alias = "parentid")]
+    pub parent_id: i64,
+    /// 部门类型，1-班级，2-年级，3-学段，4-校区，5-学校
+    #[serde(rename = "type")]
+    pub r#type: i32,
+    /// 入学年份，班级与年级必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub register_year: Option<i32>,
+    /// 标准年级，班级与年级必填，1-6为小学，7-9为初中，10-12为高中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub standard_grade: Option<i32>,
+    /// 在父部门中的次序值，值大的排序靠前
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    /// 部门管理员（班主任、任课老师等）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub department_admins: Vec<WechatCpSchoolDepartmentAdmin>,
+    /// 是否已毕业，仅获取部门列表时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_graduated: Option<i32>,
+}
+
+impl WechatCpSchoolDepartment {
+    /// 班级
+    pub fn class<S: Into<String>>(name: S, parent_id: i64, register_year: i32, standard_grade: i32) -> Self {
+        WechatCpSchoolDepartment {
+            id: None,
+            name: name.into(),
+            parent_id,
+            r#type: 1,
+            register_year: register_year.into(),
+            standard_grade: standard_grade.into(),
+            order: None,
+            department_admins: vec![],
+            is_graduated: None,
+        }
+    }
+}
+
+/// 部门管理员
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpSchoolDepartmentAdmin {
+    /// 管理员userid
+    pub userid: String,
+    /// 管理员类型，1-班主任，2-任课老师，3-年级长等
+    #[serde(rename = "type")]
+    pub r#type: i32,
+    /// 任教科目
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::*;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_user_list_deserialize() {
+        let v = json!({
+            "errcode": 0,
+            "errmsg": "ok",
+            "next_cursor": "CURSOR",
+            "user_list": [
+                {"student_userid": "zhangsan", "name": "张三", "department": [1, 2], "parents": [
+                    {"parent_userid": "zhangsan_parent1", "relation": "爸爸", "mobile": "18000000000", "is_subscribe": 1, "external_userid": "wmxxx"}
+                ]},
+                {"parent_userid": "lisi_parent", "mobile": "18000000001", "is_subscribe": 0, "children": [
+                    {"student_userid": "lisi", "relation": "妈妈", "name": "李四"}
+                ]}
+            ],
+            "students": [{"student_userid": "wangwu", "name": "王五", "department": [3]}]
+        });
+        let res = WechatCommonResponse::parse::<WechatCpSchoolUserList>(v).unwrap();
+        assert_eq!(res.next_cursor.as_deref(), Some("CURSOR"));
+        assert_eq!(res.users.iter().map(|v| v.userid()).collect::<Vec<_>>(), vec!["zhangsan", "lisi_parent", "wangwu"]);
+        match &res.users[0] {
+            WechatCpSchoolUser::Student(v) => {
+                assert_eq!(v.department, vec![1, 2]);
+                assert_eq!(v.parents[0].relation, "爸爸");
+            }
+            _ => panic!("expect student"),
+        }
+        match &res.users[1] {
+            WechatCpSchoolUser::Parent(v) => {
+                assert_eq!(v.children[0].student_userid, "lisi");
+                assert_eq!(v.children[0].name.as_deref(), Some("李四"));
+            }
+            _ => panic!("expect parent"),
+        }
+    }
+
+    #[test]
+    fn test_batch_partial_failure() {
+        let v = json!({
+            "errcode": 0,
+            "errmsg": "ok",
+            "result_list": [
+                {"parent_userid": "lisi_parent", "errcode": 60136, "errmsg": "student not exist"},
+                {"parent_userid": "zhangsan_parent", "errcode": 0, "errmsg": "ok"}
+            ]
+        });
+        let items = WechatCpSchoolBatchItem::from_response(v, &["zhangsan_parent", "lisi_parent", "wangwu_parent"], "parent_userid").unwrap();
+        assert_eq!(items.iter().map(|v| v.is_success()).collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(items[1].result, Err(WechatCpSchoolBatchFailure { errcode: 60136, errmsg: "student not exist".to_string() }));
+
+        // 没有result_list时按接口错误返回
+        let err = WechatCpSchoolBatchItem::from_response(json!({"errcode": 40001, "errmsg": "invalid credential"}), &["zhangsan_parent"], "parent_userid").unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "40001"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_create_parent() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":1,"errmsg":"partial failure","result_list":[{"parent_userid":"lisi_parent","errcode":60136,"errmsg":"student not exist"}]}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("ww_cp_school_batch_parent", "secret").base_url(&server.url);
+        let parents = vec![
+            WechatCpSchoolParent::new("zhangsan_parent", "18000000000").child("zhangsan", "爸爸"),
+            WechatCpSchoolParent::new("lisi_parent", "18000000001").child("lisi", "妈妈"),
+        ];
+        let items = client.school().batch_create_parent(&parents).await.unwrap();
+        assert!(items[0].is_success());
+        assert_eq!(items[1].result.as_ref().unwrap_err().errcode, 60136);
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/school/user/batch_create_parent?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["parents"][0], json!({"parent_userid": "zhangsan_parent", "mobile": "18000000000", "children": [{"student_userid": "zhangsan", "relation": "爸爸"}]}));
+    }
+}
@@ -0,0 +1,535 @@
+use chrono::{DateTime, Utc};
+use futures_util::Stream;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpClient, Page, PagedStream};
+use crate::serde_helper::{timestamp_seconds, option_timestamp_seconds};
+use crate::wechat::cp::method::{CpSecurityMethod, WechatCpMethod};
+
+/// 安全管理
+///
+/// 设备管理中的可信设备，以及文件防泄漏、截屏/录屏的操作记录，记录均按cursor分页。
+#[derive(Debug, Clone)]
+pub struct WechatCpSecurity<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpSecurity<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpSecurity<T> {
+        WechatCpSecurity {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.security()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpSecurity<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取设备信息.
+    /// <pre>
+    /// 按设备类型获取可信企业设备、未知设备或可信个人设备
+    /// `cursor` 上一页返回的next_cursor，第一页不填；`limit` 每页返回的条数，最大100
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/security/trustdevice/list?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/98920">文档</a>
+    /// </pre>
+    pub async fn list_trust_device(&self, device_type: WechatCpTrustDeviceType, cursor: Option<&str>, limit: Option<i32>) -> LabradorResult<WechatCpTrustDeviceList> {
+        let mut req = json!({ "type": device_type as i32 });
+        if let Some(cursor) = cursor {
+            req["cursor"] = cursor.into();
+        }
+        if let Some(limit) = limit {
+            req["limit"] = limit.into();
+        }
+        let v = self.client.post(WechatCpMethod::Security(CpSecurityMethod::TrustDeviceList), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpTrustDeviceList>(v)
+    }
+
+    /// 获取全部设备信息
+    /// <pre>
+    /// 基于list_trust_device按next_cursor自动翻页，消费时才会发起请求。
+    /// </pre>
+    pub fn list_all_trust_device(&self, device_type: WechatCpTrustDeviceType, limit: Option<i32>) -> impl Stream<Item = LabradorResult<WechatCpTrustDevice>> + '_ {
+        let client = self.client.to_owned();
+        PagedStream::new(move |cursor: Option<String>| {
+            let security = WechatCpSecurity::from_client(client.to_owned());
+            async move {
+                let res = security.list_trust_device(device_type, cursor.as_deref(), limit).await?;
+                Ok(Page::with_cursor(res.device_list, res.next_cursor))
+            }
+        }).into_stream()
+    }
+
+    /// 获取文件防泄漏操作记录.
+    /// <pre>
+    /// 按时间范围获取成员的文件操作记录，可按成员与操作类型过滤
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/security/get_file_oper_record?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/98079">文档</a>
+    /// </pre>
+    pub async fn get_file_oper_record(&self, req: &WechatCpFileOperRecordRequest, cursor: Option<&str>) -> LabradorResult<WechatCpRecordList<WechatCpFileOperRecord>> {
+        let mut req = serde_json::to_value(req)?;
+        if let Some(cursor) = cursor {
+            req["cursor"] = cursor.into();
+        }
+        let v = self.client.post(WechatCpMethod::Security(CpSecurityMethod::GetFileOperRecord), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpRecordList<WechatCpFileOperRecord>>(v)
+    }
+
+    /// 获取全部文件防泄漏操作记录
+    /// <pre>
+    /// 基于get_file_oper_record按next_cursor自动翻页，消费时才会发起请求。
+    /// </pre>
+    pub fn get_all_file_oper_record(&self, req: WechatCpFileOperRecordRequest) -> impl Stream<Item = LabradorResult<WechatCpFileOperRecord>> + '_ {
+        let client = self.client.to_owned();
+        PagedStream::new(move |cursor: Option<String>| {
+            let (security, req) = (WechatCpSecurity::from_client(client.to_owned()), req.to_owned());
+            async move {
+                let res = security.get_file_oper_record(&req, cursor.as_deref()).await?;
+                Ok(res.into_page())
+            }
+        }).into_stream()
+    }
+
+    /// 获取截屏/录屏记录.
+    /// <pre>
+    /// 按时间范围获取成员的截屏与录屏记录，可按成员、部门与类型过滤
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/security/get_screen_oper_record?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/100128">文档</a>
+    /// </pre>
+    pub async fn get_screen_oper_record(&self, req: &WechatCpScreenOperRecordRequest, cursor: Option<&str>) -> LabradorResult<WechatCpRecordList<WechatCpScreenOperRecord>> {
+        let mut req = serde_json::to_value(req)?;
+        if let Some(cursor) = cursor {
+            req["cursor"] = cursor.into();
+        }
+        let v = self.client.post(WechatCpMethod::Security(CpSecurityMethod::GetScreenOperRecord), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCpRecordList<WechatCpScreenOperRecord>>(v)
+    }
+
+    /// 获取全部截屏/录屏记录
+    /// <pre>
+    /// 基于get_screen_oper_record按next_cursor自动翻页，消费时才会发起请求。
+    /// </pre>
+    pub fn get_all_screen_oper_record(&self, req: WechatCpScreenOperRecordRequest) -> impl Stream<Item = LabradorResult<WechatCpScreenOperRecord>> + '_ {
+        let client = self.client.to_owned();
+        PagedStream::new(move |cursor: Option<String>| {
+            let (security, req) = (WechatCpSecurity::from_client(client.to_owned()), req.to_owned());
+            async move {
+                let res = security.get_screen_oper_record(&req, cursor.as_deref()).await?;
+                Ok(res.into_page())
+            }
+        }).into_stream()
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 设备类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatCpTrustDeviceType {
+    /// 可信企业设备
+    Company = 1,
+    /// 未知设备
+    Unknown = 2,
+    /// 可信个人设备
+    Personal = 3,
+}
+
+/// 设备列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpTrustDeviceList {
+    #[serde(default)]
+    pub device_list: Vec<WechatCpTrustDevice>,
+    /// 下一页的游标，没有更多时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 设备信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpTrustDevice {
+    /// 设备的唯一标识
+    pub device_code: String,
+    /// 设备的系统，如Windows、Mac
+    pub system: Option<String>,
+    /// 设备的mac地址
+    #[serde(default)]
+    pub mac_addr: Vec<String>,
+    pub motherboard_uuid: Option<String>,
+    #[serde(default)]
+    pub harddisk_uuid: Vec<String>,
+    /// windows域
+    pub domain: Option<String>,
+    /// 计算机名
+    pub pc_name: Option<String>,
+    /// Mac的序列号
+    pub seq_no: Option<String>,
+    /// 设备最后登录时间
+    #[serde(default, with = "option_timestamp_seconds")]
+    pub last_login_time: Option<DateTime<Utc>>,
+    /// 设备最后登录的成员
+    pub last_login_userid: Option<String>,
+    /// 设备归属时间，可信企业设备与可信个人设备返回
+    #[serde(default, with = "option_timestamp_seconds")]
+    pub confirm_timestamp: Option<DateTime<Utc>>,
+    /// 设备归属的成员，可信个人设备返回
+    pub confirm_userid: Option<String>,
+    /// 通过申报的管理员，可信个人设备返回
+    pub approved_userid: Option<String>,
+    /// 设备来源，0-未知，1-成员确认，2-管理员导入，3-成员自主申报
+    pub source: Option<i32>,
+    /// 设备状态，1-已导入未登录，2-已登录，3-已解绑，4-禁用
+    pub status: Option<i32>,
+}
+
+/// <pre>
+/// 操作记录的分页结果
+/// has_more为false时没有下一页，next_cursor可能仍不为空
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpRecordList<R> {
+    #[serde(default = "Vec::new")]
+    pub record_list: Vec<R>,
+    #[serde(default)]
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+}
+
+impl<R> WechatCpRecordList<R> {
+    fn into_page(self) -> Page<R> {
+        let next_cursor = if self.has_more { self.next_cursor } else { None };
+        Page::with_cursor(self.record_list, next_cursor)
+    }
+}
+
+/// 文件操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatCpFileOperType {
+    /// 下载
+    Download,
+    /// 上传
+    Upload,
+    /// 分享到企业外部
+    ShareExternal,
+    /// 打印
+    Print,
+    /// 申请外发
+    ApplySend,
+    Other(i32),
+}
+
+impl From<i32> for WechatCpFileOperType {
+    fn from(v: i32) -> Self {
+        match v {
+            101 => WechatCpFileOperType::Download,
+            102 => WechatCpFileOperType::Upload,
+            103 => WechatCpFileOperType::ShareExternal,
+            104 => WechatCpFileOperType::Print,
+            105 => WechatCpFileOperType::ApplySend,
+            v => WechatCpFileOperType::Other(v),
+        }
+    }
+}
+
+impl From<WechatCpFileOperType> for i32 {
+    fn from(v: WechatCpFileOperType) -> Self {
+        match v {
+            WechatCpFileOperType::Download => 101,
+            WechatCpFileOperType::Upload => 102,
+            WechatCpFileOperType::ShareExternal => 103,
+            WechatCpFileOperType::Print => 104,
+            WechatCpFileOperType::ApplySend => 105,
+            WechatCpFileOperType::Other(v) => v,
+        }
+    }
+}
+
+/// 文件操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpFileOperation {
+    /// 操作类型
+    #[serde(rename = "type")]
+    pub r#type: WechatCpFileOperType,
+    /// 操作来源，如企业微信、微盘、邮件
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<i32>,
+}
+
+/// 获取文件防泄漏操作记录的条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpFileOperRecordRequest {
+    /// 开始时间
+    #[serde(with = "timestamp_seconds")]
+    pub start_time: DateTime<Utc>,
+    /// 结束时间，开始与结束时间间隔不能超过14天
+    #[serde(with = "timestamp_seconds")]
+    pub end_time: DateTime<Utc>,
+    /// 需要查询的成员，不填时查询全部成员
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userid_list: Option<Vec<String>>,
+    /// 参与筛选的操作类型，不填时返回全部类型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<WechatCpFileOperation>,
+    /// 每页返回的条数，最大100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i32>,
+}
+
+impl WechatCpFileOperRecordRequest {
+    pub fn new(start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Self {
+        WechatCpFileOperRecordRequest {
+            start_time,
+            end_time,
+            userid_list: None,
+            operation: None,
+            limit: None,
+        }
+    }
+}
+
+/// <pre>
+/// 文件操作记录
+/// 不同操作类型返回的详情字段不同，按操作类型解析到detail，不认识的类型或解析失败时保留原始内容
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "FileOperRecordRaw")]
+pub struct WechatCpFileOperRecord {
+    /// 操作时间
+    pub time: DateTime<Utc>,
+    /// 企业用户账号id，外部用户为空
+    pub userid: Option<String>,
+    /// 外部用户的名称
+    pub external_user: Option<String>,
+    pub operation: WechatCpFileOperation,
+    /// 设备类型，1-企业可信设备，2-个人可信设备，3-未知设备
+    pub device_type: Option<i32>,
+    pub device_code: Option<String>,
+    pub detail: WechatCpFileOperDetail,
+}
+
+/// 文件操作的详情
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WechatCpFileOperDetail {
+    /// 下载、上传、分享、打印等直接操作文件
+    File(WechatCpFileInfo),
+    /// 申请外发
+    ApplySend(WechatCpFileApplySend),
+    /// 其它类型的原始字段（已去掉time、userid等公共字段）
+    Raw(Value),
+}
+
+/// 被操作的文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpFileInfo {
+    /// 文件名
+    pub file_info: String,
+    pub file_md5: Option<String>,
+    /// 文件大小，单位为字节
+    pub file_size: Option<i64>,
+}
+
+/// 申请外发的文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpFileApplySend {
+    /// 文件名
+    pub file_info: String,
+    /// 申请人的名称
+    pub applicant_name: String,
+    /// 外发的审批单号
+    pub sp_no: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FileOperRecordRaw {
+    #[serde(with = "timestamp_seconds")]
+    time: DateTime<Utc>,
+    userid: Option<String>,
+    external_user: Option<String>,
+    operation: WechatCpFileOperation,
+    device_type: Option<i32>,
+    device_code: Option<String>,
+    #[serde(flatten)]
+    detail: Map<String, Value>,
+}
+
+impl From<FileOperRecordRaw> for WechatCpFileOperRecord {
+    fn from(v: FileOperRecordRaw) -> Self {
+        let raw = Value::Object(v.detail);
+        let detail = match v.operation.r#type {
+            WechatCpFileOperType::ApplySend => serde_json::from_value(raw.to_owned()).map(WechatCpFileOperDetail::ApplySend),
+            WechatCpFileOperType::Other(_) => Ok(WechatCpFileOperDetail::Raw(raw.to_owned())),
+            _ => serde_json::from_value(raw.to_owned()).map(WechatCpFileOperDetail::File),
+        }.unwrap_or(WechatCpFileOperDetail::Raw(raw));
+        WechatCpFileOperRecord {
+            time: v.time,
+            userid: v.userid,
+            external_user: v.external_user,
+            operation: v.operation,
+            device_type: v.device_type,
+            device_code: v.device_code,
+            detail,
+        }
+    }
+}
+
+/// 获取截屏/录屏记录的条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpScreenOperRecordRequest {
+    /// 开始时间
+    #[serde(with = "timestamp_seconds")]
+    pub start_time: DateTime<Utc>,
+    /// 结束时间，开始与结束时间间隔不能超过14天
+    #[serde(with = "timestamp_seconds")]
+    pub end_time: DateTime<Utc>,
+    /// 需要查询的成员
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userid_list: Option<Vec<String>>,
+    /// 需要查询的部门
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department_list: Option<Vec<i64>>,
+    /// 筛选的类型，1-截屏，2-录屏，不填时返回全部
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_shot_type: Option<i32>,
+    /// 每页返回的条数，最大100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i32>,
+}
+
+impl WechatCpScreenOperRecordRequest {
+    pub fn new(start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Self {
+        WechatCpScreenOperRecordRequest {
+            start_time,
+            end_time,
+            userid_list: None,
+            department_list: None,
+            screen_shot_type: None,
+            limit: None,
+        }
+    }
+}
+
+/// 截屏/录屏记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpScreenOperRecord {
+    /// 操作时间
+    #[serde(with = "timestamp_seconds")]
+    pub time: DateTime<Utc>,
+    pub userid: String,
+    /// 成员所在的部门
+    pub department_id: Option<i64>,
+    /// 类型，1-截屏，2-录屏
+    pub screen_shot_type: i32,
+    /// 截屏/录屏时的内容，如聊天窗口的名称
+    pub screen_shot_content: Option<String>,
+    /// 操作系统
+    pub system: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use futures_util::StreamExt;
+    use serde_json::{json, Value};
+
+    use crate::{SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpFileOperDetail, WechatCpFileOperRecord, WechatCpFileOperRecordRequest, WechatCpFileOperType, WechatCpFileOperation, WechatCpRecordList, WechatCpTrustDeviceType};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    const FILE_OPER_RECORD: &str = r#"{
+        "errcode": 0,
+        "errmsg": "ok",
+        "has_more": true,
+        "next_cursor": "CURSOR",
+        "record_list": [
+            {"time": 1665536312, "userid": "zhangsan", "operation": {"type": 101, "source": 200}, "file_info": "产品方案.docx", "file_md5": "c0c8d5a1e5e8ef5a7c2dbd4dfa24b9b4", "file_size": 20480, "device_type": 1, "device_code": "DEVICE_CODE"},
+            {"time": 1665536400, "userid": "lisi", "operation": {"type": 105, "source": 200}, "file_info": "报价单.xlsx", "applicant_name": "李四", "sp_no": "202210120001"},
+            {"time": 1665536500, "external_user": "王五", "operation": {"type": 199}, "file_info": "合同.pdf", "new_field": 1}
+        ]
+    }"#;
+
+    #[test]
+    fn test_file_oper_record_deserialize() {
+        let v = serde_json::from_str::<Value>(FILE_OPER_RECORD).unwrap();
+        let res = WechatCommonResponse::parse::<WechatCpRecordList<WechatCpFileOperRecord>>(v).unwrap();
+        assert!(res.has_more);
+        assert_eq!(res.record_list.len(), 3);
+
+        let download = &res.record_list[0];
+        assert_eq!(download.time, Utc.timestamp_opt(1665536312, 0).unwrap());
+        assert_eq!(download.operation, WechatCpFileOperation { r#type: WechatCpFileOperType::Download, source: Some(200) });
+        assert_eq!(download.device_code.as_deref(), Some("DEVICE_CODE"));
+        match download.detail {
+            WechatCpFileOperDetail::File(ref file) => {
+                assert_eq!(file.file_info, "产品方案.docx");
+                assert_eq!(file.file_size, Some(20480));
+            }
+            ref detail => panic!("unexpected detail: {:?}", detail),
+        }
+
+        let apply = &res.record_list[1];
+        assert_eq!(apply.userid.as_deref(), Some("lisi"));
+        match apply.detail {
+            WechatCpFileOperDetail::ApplySend(ref send) => {
+                assert_eq!(send.applicant_name, "李四");
+                assert_eq!(send.sp_no.as_deref(), Some("202210120001"));
+            }
+            ref detail => panic!("unexpected detail: {:?}", detail),
+        }
+
+        // 不认识的操作类型保留原始字段
+        let other = &res.record_list[2];
+        assert_eq!(other.operation.r#type, WechatCpFileOperType::Other(199));
+        assert_eq!(other.external_user.as_deref(), Some("王五"));
+        assert_eq!(other.detail, WechatCpFileOperDetail::Raw(json!({"file_info": "合同.pdf", "new_field": 1})));
+    }
+
+    #[tokio::test]
+    async fn test_get_all_file_oper_record() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(FILE_OPER_RECORD),
+            // 最后一页仍返回next_cursor
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","has_more":false,"next_cursor":"END","record_list":[{"time":1665536600,"userid":"zhangsan","operation":{"type":102},"file_info":"a.txt"}]}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("security_file_oper_corp", "secret").base_url(&server.url);
+        let mut req = WechatCpFileOperRecordRequest::new(Utc.timestamp_opt(1665504000, 0).unwrap(), Utc.timestamp_opt(1665590400, 0).unwrap());
+        req.userid_list = vec!["zhangsan".to_string()].into();
+        req.operation = WechatCpFileOperation { r#type: WechatCpFileOperType::Download, source: None }.into();
+        let security = client.security();
+        let records = security.get_all_file_oper_record(req).collect::<Vec<_>>().await;
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3].as_ref().unwrap().operation.r#type, WechatCpFileOperType::Upload);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].starts_with("POST /cgi-bin/security/get_file_oper_record?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({"start_time": 1665504000, "end_time": 1665590400, "userid_list": ["zhangsan"], "operation": {"type": 101}}));
+        let body = serde_json::from_str::<Value>(requests[2].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["cursor"], "CURSOR");
+    }
+
+    #[tokio::test]
+    async fn test_list_trust_device() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","device_list":[{"device_code":"DEVICE_CODE","system":"Windows","mac_addr":["50:81:40:29:33:CF"],"motherboard_uuid":"MB_UUID","harddisk_uuid":["HD_UUID"],"domain":"WORKGROUP","pc_name":"PC-001","last_login_time":1654041600,"last_login_userid":"zhangsan","confirm_timestamp":1654041600,"confirm_userid":"zhangsan","approved_userid":"admin","source":2,"status":2}],"next_cursor":""}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("security_trust_device_corp", "secret").base_url(&server.url);
+        let res = client.security().list_trust_device(WechatCpTrustDeviceType::Personal, None, Some(10)).await.unwrap();
+        assert_eq!(res.device_list[0].mac_addr, vec!["50:81:40:29:33:CF".to_string()]);
+        assert_eq!(res.device_list[0].last_login_time, Some(Utc.timestamp_opt(1654041600, 0).unwrap()));
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/security/trustdevice/list?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({"type": 3, "limit": 10}));
+    }
+}
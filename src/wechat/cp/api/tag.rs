@@ -6,20 +6,26 @@ use crate::wechat::cp::method::{CpTagMethod, WechatCpMethod};
 
 /// 标签相关
 #[derive(Debug, Clone)]
-pub struct WechatCpTag<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpTag<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTag<'a, T> {
+impl<T: SessionStore> WechatCpTag<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpTag<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpTag<T> {
         WechatCpTag {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.tag()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpTag<T> {
+        Self::from_client(client.clone())
+    }
+
     /// 创建标签.
     /// <pre>
     /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/tag/create?access_token=ACCESS_TOKEN">文档</a>
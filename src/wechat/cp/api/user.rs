@@ -6,20 +6,26 @@ use crate::wechat::cp::method::{CpUserMethod, WechatCpMethod};
 
 /// 部门管理
 #[derive(Debug, Clone)]
-pub struct WechatCpUser<'a, T: SessionStore> {
-    client: &'a WechatCpClient<T>,
+pub struct WechatCpUser<T: SessionStore> {
+    client: WechatCpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpUser<'a, T> {
+impl<T: SessionStore> WechatCpUser<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpClient<T>) -> WechatCpUser<T> {
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpUser<T> {
         WechatCpUser {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.user()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpUser<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     ///   用在二次验证的时候.
     ///   企业在员工验证成功后，调用本方法告诉企业号平台该员工关注成功。
@@ -73,14 +79,19 @@ impl<'a, T: SessionStore> WechatCpUser<'a, T> {
     /// 新建用户
     /// </pre>
     pub async fn create(&self, req: WechatCpUserInfo) -> LabradorResult<WechatCommonResponse> {
-        self.client.post(WechatCpMethod::User(CpUserMethod::Create), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        let res = self.client.post(WechatCpMethod::User(CpUserMethod::Create), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>();
+        self.invalidate_cache(&[]);
+        res
     }
 
     /// <pre>
     /// 更新用户
     /// </pre>
     pub async fn update(&self, req: WechatCpUserInfo) -> LabradorResult<WechatCommonResponse> {
-        self.client.post(WechatCpMethod::User(CpUserMethod::Update), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        let userid = req.userid.to_owned().unwrap_or_default();
+        let res = self.client.post(WechatCpMethod::User(CpUserMethod::Update), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>();
+        self.invalidate_cache(&[&userid]);
+        res
     }
 
     /// <pre>
@@ -88,12 +99,24 @@ impl<'a, T: SessionStore> WechatCpUser<'a, T> {
     /// http://qydev.weixin.qq.com/wiki/index.php?title=管理成员#.E6.89.B9.E9.87.8F.E5.88.A0.E9.99.A4.E6.88.90.E5.91.98
     /// </pre>
     pub async fn delete(&self, user_ids: Vec<&str>) -> LabradorResult<WechatCommonResponse> {
-        if user_ids.len() == 1 {
+        let res = if user_ids.len() == 1 {
             self.client.get(WechatCpMethod::User(CpUserMethod::Delete(user_ids[0].to_string())), vec![], RequestType::Json).await?.json::<WechatCommonResponse>()
         } else {
             self.client.post(WechatCpMethod::User(CpUserMethod::BatchDelete), vec![], json!({"useridlist": user_ids}), RequestType::Json).await?.json::<WechatCommonResponse>()
-        }
+        };
+        self.invalidate_cache(&user_ids);
+        res
+    }
 
+    /// 成员变更后使成员详情与部门成员列表的缓存失效
+    fn invalidate_cache(&self, user_ids: &[&str]) {
+        let prefixes = user_ids.iter().filter(|userid| !userid.is_empty()).map(|userid| format!("/cgi-bin/user/get?userid={}", urlencoding::encode(userid)))
+            .chain(["/cgi-bin/user/list", "/cgi-bin/user/simplelist"].iter().map(|v| v.to_string()));
+        for prefix in prefixes {
+            if let Err(err) = self.client.invalidate_cache_prefix(&prefix) {
+                tracing::warn!("[响应缓存] 使{}失效失败:{}", prefix, err);
+            }
+        }
     }
 
     /// <pre>
@@ -111,14 +134,14 @@ impl<'a, T: SessionStore> WechatCpUser<'a, T> {
     /// 请求地址： https://qyapi.weixin.qq.com/cgi-bin/batch/invite?access_token=ACCESS_TOKEN
     /// 文档地址：https://work.weixin.qq.com/api/doc#12543
     /// </pre>
-    pub async fn invite(&self, userids: Vec<&str>, party_ids: Vec<&str>, tag_ids: Vec<&str>) -> LabradorResult<WxCpInviteResponse> {
+    pub async fn invite(&self, userids: Vec<&str>, party_ids: Vec<&str>, tag_ids: Vec<&str>) -> LabradorResult<WechatCpInviteResponse> {
         let req = json!({
             "user": userids,
             "party": party_ids,
             "tag": tag_ids,
         });
         let v = self.client.post(WechatCpMethod::User(CpUserMethod::Invite), vec![],req, RequestType::Json).await?.json::<Value>()?;
-        WechatCommonResponse::parse::<WxCpInviteResponse>(v)
+        WechatCommonResponse::parse::<WechatCpInviteResponse>(v)
     }
 
     /// <pre>
@@ -131,13 +154,13 @@ impl<'a, T: SessionStore> WechatCpUser<'a, T> {
     ///
     /// 文档地址：https://work.weixin.qq.com/api/doc#11279
     /// </pre>
-    pub async fn userid_2_openid(&self, userid: &str, agent_id: i32) -> LabradorResult<WxCpUseridToOpenidResponse> {
+    pub async fn userid_2_openid(&self, userid: &str, agent_id: i32) -> LabradorResult<WechatCpUseridToOpenidResponse> {
         let req = json!({
             "userid": userid,
             "agentid": agent_id,
         });
         let v = self.client.post(WechatCpMethod::User(CpUserMethod::ConvertToOpenid), vec![],req, RequestType::Json).await?.json::<Value>()?;
-        WechatCommonResponse::parse::<WxCpUseridToOpenidResponse>(v)
+        WechatCommonResponse::parse::<WechatCpUseridToOpenidResponse>(v)
     }
 
     /// <pre>
@@ -224,16 +247,22 @@ impl<'a, T: SessionStore> WechatCpUser<'a, T> {
 //----------------------------------------------------------------------------------------------------------------------------
 /// 邀请成员的结果对象类
 #[derive(Debug, Clone,Serialize, Deserialize)]
-pub struct WxCpInviteResponse {
+pub struct WechatCpInviteResponse {
     pub invaliduser: Option<Vec<String>>,
     pub invalidparty: Option<Vec<String>>,
     pub invalidtag: Option<Vec<String>>,
 }
+
+#[deprecated(note = "请使用`WechatCpInviteResponse`")]
+pub type WxCpInviteResponse = WechatCpInviteResponse;
 #[derive(Debug, Clone,Serialize, Deserialize)]
-pub struct WxCpUseridToOpenidResponse {
+pub struct WechatCpUseridToOpenidResponse {
     pub openid: Option<String>,
     pub appid: Option<String>,
 }
+
+#[deprecated(note = "请使用`WechatCpUseridToOpenidResponse`")]
+pub type WxCpUseridToOpenidResponse = WechatCpUseridToOpenidResponse;
 /// 外部联系人详情
 #[derive(Debug, Clone,Serialize, Deserialize)]
 pub struct WechatCpUserExternalContactInfo {
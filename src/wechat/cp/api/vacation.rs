@@ -0,0 +1,298 @@
+use chrono::Duration;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError, WechatCpClient};
+use crate::serde_helper::duration_seconds;
+use crate::wechat::cp::method::{CpVacationMethod, WechatCpMethod};
+
+/// 一天的秒数，假期未返回每天时长时使用
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// 假期管理
+#[derive(Debug, Clone)]
+pub struct WechatCpVacation<T: SessionStore> {
+    client: WechatCpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatCpVacation<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatCpClient<T>) -> WechatCpVacation<T> {
+        WechatCpVacation {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.vacation()`")]
+    pub fn new(client: &WechatCpClient<T>) -> WechatCpVacation<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 获取企业假期管理配置.
+    /// <pre>
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/oa/vacation/getcorpconf?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93375">文档</a>
+    /// </pre>
+    pub async fn get_corp_conf(&self) -> LabradorResult<Vec<WechatCpVacationConf>> {
+        let v = self.client.get(WechatCpMethod::Vacation(CpVacationMethod::GetCorpConf), vec![], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpVacationConf>>(v, "lists")
+    }
+
+    /// 获取成员假期余额.
+    /// <pre>
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/oa/vacation/getuservacationquota?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93376">文档</a>
+    /// </pre>
+    pub async fn get_user_vacation_quota(&self, user_id: &str) -> LabradorResult<Vec<WechatCpVacationQuota>> {
+        let v = self.client.post(WechatCpMethod::Vacation(CpVacationMethod::GetUserVacationQuota), vec![], json!({ "userid": user_id }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpVacationQuota>>(v, "lists")
+    }
+
+    /// 修改成员假期余额.
+    /// <pre>
+    /// left_duration为设置的假期余额，按天请假的假期可通过[`WechatCpVacationConf::duration_of_days`]换算
+    /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/oa/vacation/setoneuserquota?access_token=ACCESS_TOKEN">文档</a>
+    /// 文档地址：<a href="https://developer.work.weixin.qq.com/document/path/93377">文档</a>
+    /// </pre>
+    pub async fn set_one_user_quota(&self, user_id: &str, vacation_id: i64, left_duration: Duration, time_attr: WechatCpVacationTimeAttr, remarks: Option<&str>) -> LabradorResult<WechatCommonResponse> {
+        if left_duration < Duration::zero() {
+            return Err(LabraError::RequestError(format!("假期余额不能为负数：{}秒", left_duration.num_seconds())));
+        }
+        let mut req = json!({
+            "userid": user_id,
+            "vacation_id": vacation_id,
+            "leftduration": left_duration.num_seconds(),
+            "time_attr": time_attr as i32,
+        });
+        if let Some(remarks) = remarks {
+            req["remarks"] = remarks.into();
+        }
+        let v = self.client.post(WechatCpMethod::Vacation(CpVacationMethod::SetOneUserQuota), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 假期的请假方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatCpVacationTimeAttr {
+    /// 按天请假，最小单位为半天
+    Day = 0,
+    /// 按小时请假
+    Hour = 1,
+}
+
+/// 假期配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpVacationConf {
+    /// 假期id
+    pub id: i64,
+    /// 假期名称
+    pub name: String,
+    /// 请假方式，0-按天，1-按小时
+    pub time_attr: i32,
+    /// 时长计算类型，0-按工作日计算，1-按自然日计算
+    pub duration_type: Option<i32>,
+    /// 余额发放规则
+    pub quota_attr: Option<Value>,
+    /// 按小时请假时每天折算的时长，按天请假时为一天
+    #[serde(default, with = "duration_seconds")]
+    pub perday_duration: Duration,
+    /// 是否关联加班调休，0-不关联，1-关联
+    pub is_newovertime: Option<i32>,
+    /// 入职时间大于n个月可用该假期，单位为月
+    pub enter_comp_time_limit: Option<i32>,
+    /// 假期过期规则
+    pub expire_rule: Option<WechatCpVacationExpireRule>,
+}
+
+impl WechatCpVacationConf {
+
+    /// 请假方式
+    pub fn time_attr(&self) -> WechatCpVacationTimeAttr {
+        if self.time_attr == WechatCpVacationTimeAttr::Hour as i32 {
+            WechatCpVacationTimeAttr::Hour
+        } else {
+            WechatCpVacationTimeAttr::Day
+        }
+    }
+
+    /// 每天折算的秒数，未返回时按一天计算
+    fn seconds_per_day(&self) -> i64 {
+        match self.perday_duration.num_seconds() {
+            seconds if seconds > 0 => seconds,
+            _ => SECONDS_PER_DAY,
+        }
+    }
+
+    /// 将时长按该假期的每天时长折算为天数
+    pub fn days(&self, duration: Duration) -> f64 {
+        duration.num_seconds() as f64 / self.seconds_per_day() as f64
+    }
+
+    /// <pre>
+    /// 将天数换算为该假期的时长
+    /// 按天请假的假期向下取整到半天，按小时请假的假期向下取整到秒
+    /// </pre>
+    pub fn duration_of_days(&self, days: f64) -> Duration {
+        let per_day = self.seconds_per_day();
+        match self.time_attr() {
+            WechatCpVacationTimeAttr::Day => Duration::seconds((days * 2.0).floor() as i64 * per_day / 2),
+            WechatCpVacationTimeAttr::Hour => Duration::seconds((days * per_day as f64).floor() as i64),
+        }
+    }
+}
+
+/// 假期过期规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpVacationExpireRule {
+    /// 过期规则类型
+    #[serde(rename = "type")]
+    pub r#type: Option<i32>,
+    /// 有效期，按年过期时为0
+    #[serde(default, with = "duration_seconds")]
+    pub duration: Duration,
+    /// 失效日期
+    pub date: Option<WechatCpVacationMonthDay>,
+    /// 是否允许延长有效期
+    pub extern_duration_enable: Option<bool>,
+    /// 延长有效期的具体时间
+    pub extern_duration: Option<WechatCpVacationMonthDay>,
+}
+
+/// 月日
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatCpVacationMonthDay {
+    pub month: u32,
+    pub day: u32,
+}
+
+/// 成员假期余额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatCpVacationQuota {
+    /// 假期id
+    pub id: i64,
+    /// 发放时长
+    #[serde(rename = "assignduration", default, with = "duration_seconds")]
+    pub assign_duration: Duration,
+    /// 使用时长
+    #[serde(rename = "usedduration", default, with = "duration_seconds")]
+    pub used_duration: Duration,
+    /// 剩余时长
+    #[serde(rename = "leftduration", default, with = "duration_seconds")]
+    pub left_duration: Duration,
+    /// 假期名称
+    #[serde(rename = "vacationname")]
+    pub vacation_name: String,
+    /// 实际发放时长，通常与发放时长一致
+    #[serde(rename = "real_assignduration", default, with = "duration_seconds")]
+    pub real_assign_duration: Duration,
+}
+
+impl WechatCpVacationQuota {
+
+    /// 按假期配置折算的剩余天数，配置须与该余额的假期id一致
+    pub fn left_days(&self, conf: &WechatCpVacationConf) -> f64 {
+        conf.days(self.left_duration)
+    }
+
+    /// 按假期配置折算的已使用天数
+    pub fn used_days(&self, conf: &WechatCpVacationConf) -> f64 {
+        conf.days(self.used_duration)
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::Duration;
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatCommonResponse, WechatCpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatCpVacationConf, WechatCpVacationQuota, WechatCpVacationTimeAttr, WechatCpVacationMonthDay};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    const CORP_CONF: &str = r#"{
+        "errcode": 0,
+        "errmsg": "ok",
+        "lists": [
+            {"id": 1, "name": "年假", "time_attr": 0, "duration_type": 0, "quota_attr": {"type": 1, "autoreset_time": 1641010352, "autoreset_duration": 432000, "quota_rule_type": 1, "quota_rules": {"list": [{"quota": 432000, "begin": 0, "end": 1, "based_on_actual_work_time": false}], "based_on_actual_work_time": true}, "at_entry_date": true, "auto_reset_month_day": 0}, "perday_duration": 86400, "is_newovertime": 0, "enter_comp_time_limit": 0, "expire_rule": {"type": 1, "duration": 0, "date": {"month": 1, "day": 1}, "extern_duration_enable": true, "extern_duration": {"month": 1, "day": 1}}},
+            {"id": 2, "name": "调休假", "time_attr": 1, "duration_type": 1, "quota_attr": {"type": 3}, "perday_duration": 28800, "is_newovertime": 1, "enter_comp_time_limit": 0}
+        ]
+    }"#;
+
+    const USER_QUOTA: &str = r#"{
+        "errcode": 0,
+        "errmsg": "ok",
+        "lists": [
+            {"id": 1, "assignduration": 432000, "usedduration": 129600, "leftduration": 302400, "vacationname": "年假", "real_assignduration": 432000},
+            {"id": 2, "assignduration": 0, "usedduration": 0, "leftduration": 0, "vacationname": "调休假", "real_assignduration": 0}
+        ]
+    }"#;
+
+    fn corp_conf() -> Vec<WechatCpVacationConf> {
+        let v = serde_json::from_str::<Value>(CORP_CONF).unwrap();
+        WechatCommonResponse::parse_with_key::<Vec<WechatCpVacationConf>>(v, "lists").unwrap()
+    }
+
+    #[test]
+    fn test_corp_conf_deserialize() {
+        let conf = corp_conf();
+        assert_eq!(conf.len(), 2);
+        assert_eq!(conf[0].time_attr(), WechatCpVacationTimeAttr::Day);
+        assert_eq!(conf[0].perday_duration, Duration::days(1));
+        let expire_rule = conf[0].expire_rule.as_ref().unwrap();
+        assert_eq!(expire_rule.r#type, Some(1));
+        assert_eq!(expire_rule.date, Some(WechatCpVacationMonthDay { month: 1, day: 1 }));
+        assert_eq!(expire_rule.extern_duration_enable, Some(true));
+        assert_eq!(conf[1].time_attr(), WechatCpVacationTimeAttr::Hour);
+        assert_eq!(conf[1].perday_duration, Duration::hours(8));
+        assert!(conf[1].expire_rule.is_none());
+    }
+
+    #[test]
+    fn test_user_quota_deserialize() {
+        let conf = corp_conf();
+        let v = serde_json::from_str::<Value>(USER_QUOTA).unwrap();
+        let quota = WechatCommonResponse::parse_with_key::<Vec<WechatCpVacationQuota>>(v, "lists").unwrap();
+        assert_eq!(quota.len(), 2);
+        assert_eq!(quota[0].assign_duration, Duration::days(5));
+        assert_eq!(quota[0].used_duration.num_seconds(), 129600);
+        // 半天的余额
+        assert_eq!(quota[0].used_days(&conf[0]), 1.5);
+        assert_eq!(quota[0].left_days(&conf[0]), 3.5);
+        // 已清零的调休假
+        assert_eq!(quota[1].vacation_name, "调休假");
+        assert_eq!(quota[1].left_duration, Duration::zero());
+        assert_eq!(quota[1].left_days(&conf[1]), 0.0);
+    }
+
+    #[test]
+    fn test_duration_of_days() {
+        let conf = corp_conf();
+        assert_eq!(conf[0].duration_of_days(1.5), Duration::hours(36));
+        assert_eq!(conf[0].duration_of_days(1.7), Duration::hours(36));
+        assert_eq!(conf[1].days(Duration::hours(12)), 1.5);
+        assert_eq!(conf[1].duration_of_days(0.25), Duration::hours(2));
+    }
+
+    #[tokio::test]
+    async fn test_set_one_user_quota() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("vacation_quota_corp", "secret").base_url(&server.url);
+        assert!(matches!(client.vacation().set_one_user_quota("ZhangSan", 1, Duration::seconds(-1), WechatCpVacationTimeAttr::Day, None).await, Err(LabraError::RequestError(_))));
+        client.vacation().set_one_user_quota("ZhangSan", 1, Duration::hours(36), WechatCpVacationTimeAttr::Day, Some("PERSONAL")).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("POST /cgi-bin/oa/vacation/setoneuserquota?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({"userid": "ZhangSan", "vacation_id": 1, "leftduration": 129600, "time_attr": 0, "remarks": "PERSONAL"}));
+    }
+}
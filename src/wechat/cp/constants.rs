@@ -25,6 +25,12 @@ pub static ATTACHMENT_TYPE: &str = "attachment_type";
 pub static AUTH_URL_INSTALL: &str = "https://open.work.weixin.qq.com/3rdapp/install";
 
 pub static ACCESS_TOKEN_KEY: &str = ":accessTokenKey:";
+pub static ACCESS_TOKEN_EXPIRES_KEY: &str = ":accessTokenExpiresAt:";
+pub static PERMANENT_CODE_KEY: &str = ":permanentCode:";
+pub static SUITE_TICKET_KEY: &str = "suiteTicket";
+pub static SUITE_TICKET_EXPIRES_KEY: &str = "suiteTicketExpiresAt";
+pub static SUITE_ACCESS_TOKEN_KEY: &str = "suiteAccessToken";
+pub static SUITE_ACCESS_TOKEN_EXPIRES_KEY: &str = "suiteAccessTokenExpiresAt";
 
 /**
  * 不弹出授权页面，直接跳转，只能获取用户openid.
@@ -1,4 +1,5 @@
-use crate::RequestMethod;
+use crate::{AuthStyle, Method, RequestMethod};
+use crate::wechat::cp::constants::{ACCESS_TOKEN, PROVIDER_ACCESS_TOKEN, SUITE_ACCESS_TOKEN};
 
 #[allow(unused)]
 #[derive(Debug, PartialEq, Clone)]
@@ -27,8 +28,23 @@ pub enum WechatCpMethod {
     Department(CpDepartmentMethod),
     Message(CpMessageMethod),
     ExternalContact(CpExternalContactMethod),
-    /// 自定义方法
-    Custom{ need_token: bool, method_url: String }
+    Kf(CpKfMethod),
+    Checkin(CpCheckinMethod),
+    Export(CpExportMethod),
+    LinkedCorp(CpLinkedCorpMethod),
+    CorpGroup(CpCorpGroupMethod),
+    MsgAudit(CpMsgAuditMethod),
+    Living(CpLivingMethod),
+    AppChat(CpAppChatMethod),
+    Vacation(CpVacationMethod),
+    IdConvert(CpIdConvertMethod),
+    School(CpSchoolMethod),
+    Security(CpSecurityMethod),
+    Approval(CpApprovalMethod),
+    /// 自定义方法，path为完整URL（如群机器人webhook）时不附加access_token
+    Custom { path: String, request_method: Method },
+    /// 自定义方法，并指定接口调用凭证的传递方式
+    CustomWithAuth { path: String, request_method: Method, auth_style: AuthStyle },
 }
 
 impl RequestMethod for WechatCpMethod {
@@ -47,11 +63,12 @@ impl RequestMethod for WechatCpMethod {
             WechatCpMethod::GetSuiteToken => String::from("/cgi-bin/service/get_suite_token"),
             WechatCpMethod::JsCode2Session => String::from("/cgi-bin/miniprogram/jscode2session"),
             WechatCpMethod::GetCallbackIp => String::from("/cgi-bin/getcallbackip"),
-            WechatCpMethod::GetAgentConfigTicket => String::from("/cgi-bin/ticket/get?&type=agent_config"),
+            WechatCpMethod::GetAgentConfigTicket => String::from("/cgi-bin/ticket/get"),
             WechatCpMethod::Media(v) => v.get_method(),
             WechatCpMethod::ExternalContact(v) => v.get_method(),
             WechatCpMethod::Oauth2(v) => v.get_method(),
-            WechatCpMethod::Custom{ method_url, .. } => method_url.to_string(),
+            WechatCpMethod::Custom { path, .. } => path.to_string(),
+            WechatCpMethod::CustomWithAuth { path, .. } => path.to_string(),
             WechatCpMethod::Menu(v) => v.get_method(),
             WechatCpMethod::Message(v) => v.get_method(),
             WechatCpMethod::Tag(v) => v.get_method(),
@@ -59,6 +76,28 @@ impl RequestMethod for WechatCpMethod {
             WechatCpMethod::Department(v) => v.get_method(),
             WechatCpMethod::User(v) => v.get_method(),
             WechatCpMethod::Agent(v) => v.get_method(),
+            WechatCpMethod::Kf(v) => v.get_method(),
+            WechatCpMethod::Checkin(v) => v.get_method(),
+            WechatCpMethod::Export(v) => v.get_method(),
+            WechatCpMethod::LinkedCorp(v) => v.get_method(),
+            WechatCpMethod::CorpGroup(v) => v.get_method(),
+            WechatCpMethod::MsgAudit(v) => v.get_method(),
+            WechatCpMethod::Living(v) => v.get_method(),
+            WechatCpMethod::AppChat(v) => v.get_method(),
+            WechatCpMethod::Vacation(v) => v.get_method(),
+            WechatCpMethod::IdConvert(v) => v.get_method(),
+            WechatCpMethod::School(v) => v.get_method(),
+            WechatCpMethod::Security(v) => v.get_method(),
+            WechatCpMethod::Approval(v) => v.get_method(),
+        }
+    }
+
+    fn auth_style(&self) -> AuthStyle {
+        match self {
+            WechatCpMethod::Custom { path, .. } if path.starts_with("http") => AuthStyle::None,
+            WechatCpMethod::CustomWithAuth { auth_style, .. } => *auth_style,
+            WechatCpMethod::AccessToken | WechatCpMethod::GetProviderToken | WechatCpMethod::GetSuiteToken => AuthStyle::None,
+            _ => AuthStyle::QueryParam,
         }
     }
 }
@@ -67,10 +106,60 @@ impl RequestMethod for WechatCpMethod {
 impl WechatCpMethod {
 
     pub fn need_token(&self) -> bool {
+        self.auth_style() != AuthStyle::None
+    }
+
+    /// 接口调用凭证的参数名
+    pub fn token_param(&self) -> CpTokenParam {
         match self {
-            WechatCpMethod::Custom{ need_token, .. } => *need_token,
-            WechatCpMethod::AccessToken => false,
-            _ => true,
+            WechatCpMethod::GetPreAuthCode
+            | WechatCpMethod::GetPermanentCode
+            | WechatCpMethod::GetAuthInfo
+            | WechatCpMethod::GetCorpToken => CpTokenParam::SuiteAccessToken,
+            WechatCpMethod::GetOrder
+            | WechatCpMethod::GetOrderList
+            | WechatCpMethod::License(_) => CpTokenParam::ProviderAccessToken,
+            _ => CpTokenParam::AccessToken,
+        }
+    }
+
+    /// 自定义方法的请求方式，其余方法由调用方决定
+    pub fn request_method(&self) -> Option<Method> {
+        match self {
+            WechatCpMethod::Custom { request_method, .. } | WechatCpMethod::CustomWithAuth { request_method, .. } => request_method.clone().into(),
+            _ => None,
+        }
+    }
+
+    /// 完整的请求地址，path为完整URL时直接返回
+    pub fn url(&self, base: &str) -> String {
+        let path = self.get_method();
+        if path.starts_with("http") {
+            path
+        } else {
+            format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+        }
+    }
+}
+
+/// 接口调用凭证
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CpTokenParam {
+    /// 企业的access_token
+    AccessToken,
+    /// 第三方应用的suite_access_token
+    SuiteAccessToken,
+    /// 服务商的provider_access_token
+    ProviderAccessToken,
+}
+
+impl CpTokenParam {
+    /// 查询参数名
+    pub fn key(&self) -> &'static str {
+        match self {
+            CpTokenParam::AccessToken => ACCESS_TOKEN,
+            CpTokenParam::SuiteAccessToken => SUITE_ACCESS_TOKEN,
+            CpTokenParam::ProviderAccessToken => PROVIDER_ACCESS_TOKEN,
         }
     }
 }
@@ -89,6 +178,10 @@ pub enum CpMediaMethod {
     GetMedia,
     /// 获取素材JSSDK
     GetMediaJssdk,
+    /// 生成异步上传任务
+    UploadByUrl,
+    /// 查询异步上传任务结果
+    GetUploadByUrlResult,
 }
 
 #[allow(unused)]
@@ -100,6 +193,8 @@ impl CpMediaMethod {
             CpMediaMethod::UploadAttachment => String::from("/cgi-bin/media/upload_attachment"),
             CpMediaMethod::GetMedia => String::from("/cgi-bin/media/get"),
             CpMediaMethod::GetMediaJssdk => String::from("/cgi-bin/media/get/jssdk"),
+            CpMediaMethod::UploadByUrl => String::from("/cgi-bin/media/upload_by_url"),
+            CpMediaMethod::GetUploadByUrlResult => String::from("/cgi-bin/media/get_upload_by_url_result"),
         }
     }
 }
@@ -433,4 +528,543 @@ impl CpExternalContactMethod {
             CpExternalContactMethod::DeleteGroupWelcomeTemplate => String::from("/cgi-bin/externalcontact/group_welcome_template/del"),
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpKfMethod {
+    AddAccount,
+    DeleteAccount,
+    UpdateAccount,
+    ListAccount,
+    AddContactWay,
+    AddServicer,
+    DeleteServicer,
+    ListServicer,
+    SyncMsg,
+    SendMsg,
+}
+
+#[allow(unused)]
+impl CpKfMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpKfMethod::AddAccount => String::from("/cgi-bin/kf/account/add"),
+            CpKfMethod::DeleteAccount => String::from("/cgi-bin/kf/account/del"),
+            CpKfMethod::UpdateAccount => String::from("/cgi-bin/kf/account/update"),
+            CpKfMethod::ListAccount => String::from("/cgi-bin/kf/account/list"),
+            CpKfMethod::AddContactWay => String::from("/cgi-bin/kf/add_contact_way"),
+            CpKfMethod::AddServicer => String::from("/cgi-bin/kf/servicer/add"),
+            CpKfMethod::DeleteServicer => String::from("/cgi-bin/kf/servicer/del"),
+            CpKfMethod::ListServicer => String::from("/cgi-bin/kf/servicer/list"),
+            CpKfMethod::SyncMsg => String::from("/cgi-bin/kf/sync_msg"),
+            CpKfMethod::SendMsg => String::from("/cgi-bin/kf/send_msg"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpCheckinMethod {
+    GetCheckinData,
+    GetCheckinOption,
+    GetCheckinDayData,
+    GetCheckinScheduleList,
+    AddCheckinUserFace,
+    GetCorpCheckinOption,
+    SetCheckinScheduleList,
+    AddCheckinRecord,
+    PunchCorrection,
+}
+
+#[allow(unused)]
+impl CpCheckinMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpCheckinMethod::GetCheckinData => String::from("/cgi-bin/checkin/getcheckindata"),
+            CpCheckinMethod::GetCheckinOption => String::from("/cgi-bin/checkin/getcheckinoption"),
+            CpCheckinMethod::GetCheckinDayData => String::from("/cgi-bin/checkin/getcheckin_daydata"),
+            CpCheckinMethod::GetCheckinScheduleList => String::from("/cgi-bin/checkin/getcheckinschedulist"),
+            CpCheckinMethod::AddCheckinUserFace => String::from("/cgi-bin/checkin/addcheckinuserface"),
+            CpCheckinMethod::GetCorpCheckinOption => String::from("/cgi-bin/checkin/getcorpcheckinoption"),
+            CpCheckinMethod::SetCheckinScheduleList => String::from("/cgi-bin/checkin/setcheckinschedulist"),
+            CpCheckinMethod::AddCheckinRecord => String::from("/cgi-bin/checkin/add_checkin_record"),
+            CpCheckinMethod::PunchCorrection => String::from("/cgi-bin/checkin/punch_correction"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpExportMethod {
+    SimpleUser,
+    User,
+    Department,
+    GetResult,
+}
+
+#[allow(unused)]
+impl CpExportMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpExportMethod::SimpleUser => String::from("/cgi-bin/export/simple_user"),
+            CpExportMethod::User => String::from("/cgi-bin/export/user"),
+            CpExportMethod::Department => String::from("/cgi-bin/export/department"),
+            CpExportMethod::GetResult => String::from("/cgi-bin/export/get_result"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpLinkedCorpMethod {
+    GetPermList,
+    GetUser,
+    UserSimpleList,
+    UserList,
+    DepartmentList,
+}
+
+#[allow(unused)]
+impl CpLinkedCorpMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpLinkedCorpMethod::GetPermList => String::from("/cgi-bin/linkedcorp/agent/get_perm_list"),
+            CpLinkedCorpMethod::GetUser => String::from("/cgi-bin/linkedcorp/user/get"),
+            CpLinkedCorpMethod::UserSimpleList => String::from("/cgi-bin/linkedcorp/user/simplelist"),
+            CpLinkedCorpMethod::UserList => String::from("/cgi-bin/linkedcorp/user/list"),
+            CpLinkedCorpMethod::DepartmentList => String::from("/cgi-bin/linkedcorp/department/list"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpCorpGroupMethod {
+    ListAppShareInfo,
+    GetToken,
+}
+
+#[allow(unused)]
+impl CpCorpGroupMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpCorpGroupMethod::ListAppShareInfo => String::from("/cgi-bin/corpgroup/corp/list_app_share_info"),
+            CpCorpGroupMethod::GetToken => String::from("/cgi-bin/corpgroup/corp/gettoken"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpMsgAuditMethod {
+    GetPermitUserList,
+    CheckSingleAgree,
+    CheckRoomAgree,
+    GetGroupChat,
+}
+
+#[allow(unused)]
+impl CpMsgAuditMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpMsgAuditMethod::GetPermitUserList => String::from("/cgi-bin/msgaudit/get_permit_user_list"),
+            CpMsgAuditMethod::CheckSingleAgree => String::from("/cgi-bin/msgaudit/check_single_agree"),
+            CpMsgAuditMethod::CheckRoomAgree => String::from("/cgi-bin/msgaudit/check_room_agree"),
+            CpMsgAuditMethod::GetGroupChat => String::from("/cgi-bin/msgaudit/groupchat/get"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpLivingMethod {
+    Create,
+    Modify,
+    Cancel,
+    GetLivingInfo,
+    GetWatchStat,
+    GetUserAllLivingId,
+}
+
+#[allow(unused)]
+impl CpLivingMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpLivingMethod::Create => String::from("/cgi-bin/living/create"),
+            CpLivingMethod::Modify => String::from("/cgi-bin/living/modify"),
+            CpLivingMethod::Cancel => String::from("/cgi-bin/living/cancel"),
+            CpLivingMethod::GetLivingInfo => String::from("/cgi-bin/living/get_living_info"),
+            CpLivingMethod::GetWatchStat => String::from("/cgi-bin/living/get_watch_stat"),
+            CpLivingMethod::GetUserAllLivingId => String::from("/cgi-bin/living/get_user_all_livingid"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpAppChatMethod {
+    Create,
+    Update,
+    Get,
+    Send,
+}
+
+#[allow(unused)]
+impl CpAppChatMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpAppChatMethod::Create => String::from("/cgi-bin/appchat/create"),
+            CpAppChatMethod::Update => String::from("/cgi-bin/appchat/update"),
+            CpAppChatMethod::Get => String::from("/cgi-bin/appchat/get"),
+            CpAppChatMethod::Send => String::from("/cgi-bin/appchat/send"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpVacationMethod {
+    GetCorpConf,
+    GetUserVacationQuota,
+    SetOneUserQuota,
+}
+
+#[allow(unused)]
+impl CpVacationMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpVacationMethod::GetCorpConf => String::from("/cgi-bin/oa/vacation/getcorpconf"),
+            CpVacationMethod::GetUserVacationQuota => String::from("/cgi-bin/oa/vacation/getuservacationquota"),
+            CpVacationMethod::SetOneUserQuota => String::from("/cgi-bin/oa/vacation/setoneuserquota"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpIdConvertMethod {
+    /// userid转open_userid
+    UseridToOpenUserid,
+    /// 代开发应用的external_userid转换为新的external_userid
+    GetNewExternalUserid,
+    /// 上下游企业的unionid转external_userid
+    CorpGroupUnionidToExternalUserid,
+}
+
+#[allow(unused)]
+impl CpIdConvertMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpIdConvertMethod::UseridToOpenUserid => String::from("/cgi-bin/batch/userid_to_openuserid"),
+            CpIdConvertMethod::GetNewExternalUserid => String::from("/cgi-bin/externalcontact/get_new_external_userid"),
+            CpIdConvertMethod::CorpGroupUnionidToExternalUserid => String::from("/cgi-bin/corpgroup/unionid_to_external_userid"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpSchoolMethod {
+    /// 创建学生
+    CreateStudent,
+    /// 创建家长
+    CreateParent,
+    /// 批量创建家长
+    BatchCreateParent,
+    /// 获取部门成员详情
+    ListUser,
+    /// 创建部门
+    CreateDepartment,
+    /// 获取部门列表
+    ListDepartment,
+}
+
+#[allow(unused)]
+impl CpSchoolMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpSchoolMethod::CreateStudent => String::from("/cgi-bin/school/user/create_student"),
+            CpSchoolMethod::CreateParent => String::from("/cgi-bin/school/user/create_parent"),
+            CpSchoolMethod::BatchCreateParent => String::from("/cgi-bin/school/user/batch_create_parent"),
+            CpSchoolMethod::ListUser => String::from("/cgi-bin/school/user/list"),
+            CpSchoolMethod::CreateDepartment => String::from("/cgi-bin/school/department/create"),
+            CpSchoolMethod::ListDepartment => String::from("/cgi-bin/school/department/list"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpSecurityMethod {
+    /// 获取设备信息
+    TrustDeviceList,
+    /// 获取文件防泄漏操作记录
+    GetFileOperRecord,
+    /// 获取截屏/录屏记录
+    GetScreenOperRecord,
+}
+
+#[allow(unused)]
+impl CpSecurityMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpSecurityMethod::TrustDeviceList => String::from("/cgi-bin/security/trustdevice/list"),
+            CpSecurityMethod::GetFileOperRecord => String::from("/cgi-bin/security/get_file_oper_record"),
+            CpSecurityMethod::GetScreenOperRecord => String::from("/cgi-bin/security/get_screen_oper_record"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CpApprovalMethod {
+    /// 获取审批模板详情
+    GetTemplateDetail,
+    /// 提交审批申请
+    ApplyEvent,
+}
+
+#[allow(unused)]
+impl CpApprovalMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CpApprovalMethod::GetTemplateDetail => String::from("/cgi-bin/oa/gettemplatedetail"),
+            CpApprovalMethod::ApplyEvent => String::from("/cgi-bin/oa/applyevent"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{Method, RequestMethod};
+    use super::*;
+
+    #[test]
+    fn test_method_urls() {
+        let methods = vec![
+            (WechatCpMethod::AccessToken, "/cgi-bin/gettoken"),
+            (WechatCpMethod::GetJsapiTicket, "/cgi-bin/get_jsapi_ticket"),
+            (WechatCpMethod::GetSuiteJsapiTicket, "/cgi-bin/ticket/get"),
+            (WechatCpMethod::GetOrder, "/cgi-bin/service/get_order"),
+            (WechatCpMethod::GetOrderList, "/cgi-bin/service/get_order_list"),
+            (WechatCpMethod::GetPreAuthCode, "/cgi-bin/service/get_pre_auth_code"),
+            (WechatCpMethod::GetAuthInfo, "/cgi-bin/service/get_auth_info"),
+            (WechatCpMethod::GetPermanentCode, "/cgi-bin/service/get_permanent_code"),
+            (WechatCpMethod::GetProviderToken, "/cgi-bin/service/get_provider_token"),
+            (WechatCpMethod::GetCorpToken, "/cgi-bin/service/get_corp_token"),
+            (WechatCpMethod::GetSuiteToken, "/cgi-bin/service/get_suite_token"),
+            (WechatCpMethod::JsCode2Session, "/cgi-bin/miniprogram/jscode2session"),
+            (WechatCpMethod::GetCallbackIp, "/cgi-bin/getcallbackip"),
+            (WechatCpMethod::GetAgentConfigTicket, "/cgi-bin/ticket/get"),
+            (WechatCpMethod::Media(CpMediaMethod::UploadMedia("1".to_string())), "/cgi-bin/media/upload?type=1"),
+            (WechatCpMethod::Media(CpMediaMethod::UploadImage), "/cgi-bin/media/uploadimg"),
+            (WechatCpMethod::Media(CpMediaMethod::UploadAttachment), "/cgi-bin/media/upload_attachment"),
+            (WechatCpMethod::Media(CpMediaMethod::GetMedia), "/cgi-bin/media/get"),
+            (WechatCpMethod::Media(CpMediaMethod::GetMediaJssdk), "/cgi-bin/media/get/jssdk"),
+            (WechatCpMethod::Media(CpMediaMethod::UploadByUrl), "/cgi-bin/media/upload_by_url"),
+            (WechatCpMethod::Media(CpMediaMethod::GetUploadByUrlResult), "/cgi-bin/media/get_upload_by_url_result"),
+            (WechatCpMethod::Tag(CpTagMethod::Create), "/cgi-bin/tag/create"),
+            (WechatCpMethod::Tag(CpTagMethod::Update), "/cgi-bin/tag/update"),
+            (WechatCpMethod::Tag(CpTagMethod::List), "/cgi-bin/tag/list"),
+            (WechatCpMethod::Tag(CpTagMethod::AddTagUsers), "/cgi-bin/tag/addtagusers"),
+            (WechatCpMethod::Tag(CpTagMethod::DeleteTagUsers), "/cgi-bin/tag/deltagusers"),
+            (WechatCpMethod::Tag(CpTagMethod::Delete("1".to_string())), "/cgi-bin/tag/delete?tagid=1"),
+            (WechatCpMethod::Tag(CpTagMethod::Get("1".to_string())), "/cgi-bin/tag/get?tagid=1"),
+            (WechatCpMethod::Agent(CpAgentMethod::Get(1)), "/cgi-bin/agent/get?agentid=1"),
+            (WechatCpMethod::Agent(CpAgentMethod::Set), "/cgi-bin/agent/set"),
+            (WechatCpMethod::Agent(CpAgentMethod::List), "/cgi-bin/agent/list"),
+            (WechatCpMethod::License(CpLicenseMethod::CreateOrder), "/cgi-bin/license/create_new_order"),
+            (WechatCpMethod::License(CpLicenseMethod::CreateRenewOrderJob), "/cgi-bin/license/create_renew_order_job"),
+            (WechatCpMethod::License(CpLicenseMethod::SubmitOrderJob), "/cgi-bin/license/submit_order_job"),
+            (WechatCpMethod::License(CpLicenseMethod::ListOrder), "/cgi-bin/license/list_order"),
+            (WechatCpMethod::License(CpLicenseMethod::GetOrder), "/cgi-bin/license/get_order"),
+            (WechatCpMethod::License(CpLicenseMethod::ListOrderCount), "/cgi-bin/license/list_order_account"),
+            (WechatCpMethod::License(CpLicenseMethod::CancelOrder), "/cgi-bin/license/cancel_order"),
+            (WechatCpMethod::License(CpLicenseMethod::ActiveAccount), "/cgi-bin/license/active_account"),
+            (WechatCpMethod::License(CpLicenseMethod::BatchActiveAccount), "/cgi-bin/license/batch_active_account"),
+            (WechatCpMethod::License(CpLicenseMethod::GetActiveInfoByCode), "/cgi-bin/license/get_active_info_by_code"),
+            (WechatCpMethod::License(CpLicenseMethod::BatchGetActiveInfoByCode), "/cgi-bin/license/batch_get_active_info_by_code"),
+            (WechatCpMethod::License(CpLicenseMethod::ListActivedAccount), "/cgi-bin/license/list_actived_account"),
+            (WechatCpMethod::License(CpLicenseMethod::GetActiveInfoByUser), "/cgi-bin/license/get_active_info_by_user"),
+            (WechatCpMethod::License(CpLicenseMethod::BatchTransferLicense), "/cgi-bin/license/batch_transfer_license"),
+            (WechatCpMethod::Menu(CpMenuMethod::Create(1)), "/cgi-bin/menu/create?agentid=1"),
+            (WechatCpMethod::Menu(CpMenuMethod::Delete(1)), "/cgi-bin/menu/delete?agentid=1"),
+            (WechatCpMethod::Menu(CpMenuMethod::Get(1)), "/cgi-bin/menu/get?agentid=1"),
+            (WechatCpMethod::User(CpUserMethod::AuthSuccess("1".to_string())), "/cgi-bin/user/authsucc?userid=1"),
+            (WechatCpMethod::User(CpUserMethod::Create), "/cgi-bin/user/create"),
+            (WechatCpMethod::User(CpUserMethod::Update), "/cgi-bin/user/update"),
+            (WechatCpMethod::User(CpUserMethod::BatchDelete), "/cgi-bin/user/batchdelete"),
+            (WechatCpMethod::User(CpUserMethod::ConvertToOpenid), "/cgi-bin/user/convert_to_openid"),
+            (WechatCpMethod::User(CpUserMethod::ConvertToUserid), "/cgi-bin/user/convert_to_userid"),
+            (WechatCpMethod::User(CpUserMethod::GetUserid), "/cgi-bin/user/getuserid"),
+            (WechatCpMethod::User(CpUserMethod::Invite), "/cgi-bin/batch/invite"),
+            (WechatCpMethod::User(CpUserMethod::GetActiveStat), "/cgi-bin/user/get_active_stat"),
+            (WechatCpMethod::User(CpUserMethod::Delete("1".to_string())), "/cgi-bin/user/delete?userid=1"),
+            (WechatCpMethod::User(CpUserMethod::Get("1".to_string())), "/cgi-bin/user/get?userid=1"),
+            (WechatCpMethod::User(CpUserMethod::GetJoinQrcode(1)), "/cgi-bin/corp/get_join_qrcode?size_type=1"),
+            (WechatCpMethod::User(CpUserMethod::GetExternalContact("1".to_string())), "/cgi-bin/crm/get_external_contact?external_userid=1"),
+            (WechatCpMethod::User(CpUserMethod::List(1)), "/cgi-bin/user/list?department_id=1"),
+            (WechatCpMethod::User(CpUserMethod::SimpleList(1)), "/cgi-bin/user/simplelist?department_id=1"),
+            (WechatCpMethod::Department(CpDepartmentMethod::Create), "/cgi-bin/department/create"),
+            (WechatCpMethod::Department(CpDepartmentMethod::Update), "/cgi-bin/department/update"),
+            (WechatCpMethod::Department(CpDepartmentMethod::Get(1)), "/cgi-bin/department/get?id=1"),
+            (WechatCpMethod::Department(CpDepartmentMethod::Delete(1)), "/cgi-bin/department/delete?id=1"),
+            (WechatCpMethod::Department(CpDepartmentMethod::List), "/cgi-bin/department/list"),
+            (WechatCpMethod::Department(CpDepartmentMethod::SimpleList), "/cgi-bin/department/simplelist"),
+            (WechatCpMethod::Message(CpMessageMethod::Send), "/cgi-bin/message/send"),
+            (WechatCpMethod::Message(CpMessageMethod::Statistics), "/cgi-bin/message/get_statistics"),
+            (WechatCpMethod::Message(CpMessageMethod::LinkedCorpSend), "/cgi-bin/linkedcorp/message/send"),
+            (WechatCpMethod::Message(CpMessageMethod::ExternalContactSend), "/cgi-bin/externalcontact/message/send"),
+            (WechatCpMethod::Oauth2(CpOauth2Method::Oauth2Authorize), "https://open.weixin.qq.com/connect/oauth2/authorize"),
+            (WechatCpMethod::Oauth2(CpOauth2Method::GetUserDetail), "/cgi-bin/user/getuserdetail"),
+            (WechatCpMethod::Oauth2(CpOauth2Method::GetUserInfo), "/cgi-bin/user/getuserinfo"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::AddContactWay), "/cgi-bin/externalcontact/add_contact_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetContactWay), "/cgi-bin/externalcontact/get_contact_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetContactWayDetail), "/cgi-bin/externalcontact/get"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::UpdateContactWay), "/cgi-bin/externalcontact/update_contact_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::DeleteContactWay), "/cgi-bin/externalcontact/del_contact_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::CloseTmpChat), "/cgi-bin/externalcontact/close_temp_chat"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::UnionidToExternalUserid), "/cgi-bin/externalcontact/unionid_to_external_userid"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::ConvertToOpenid), "/cgi-bin/externalcontact/convert_to_openid"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::BatchGetByUser), "/cgi-bin/externalcontact/batch/get_by_user"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::Remark), "/cgi-bin/externalcontact/remark"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::List), "/cgi-bin/externalcontact/list"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetFollowUserList), "/cgi-bin/externalcontact/get_follow_user_list"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetUnassignedList), "/cgi-bin/externalcontact/get_unassigned_list"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::TransferCustomer), "/cgi-bin/externalcontact/transfer_customer"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::TransferResult), "/cgi-bin/externalcontact/transfer_result"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::ResignedTransferCustomer), "/cgi-bin/externalcontact/resigned/transfer_customer"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetUserBehaviorData), "/cgi-bin/externalcontact/get_user_behavior_data"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::ResignedTransferResult), "/cgi-bin/externalcontact/resigned/transfer_result"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatAddJoinWay), "/cgi-bin/externalcontact/groupchat/add_join_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatGetJoinWay), "/cgi-bin/externalcontact/groupchat/get_join_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatUpdateJoinWay), "/cgi-bin/externalcontact/groupchat/update_join_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatDeleteJoinWay), "/cgi-bin/externalcontact/groupchat/del_join_way"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatList), "/cgi-bin/externalcontact/groupchat/list"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatGet), "/cgi-bin/externalcontact/groupchat/get"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatTransfer), "/cgi-bin/externalcontact/groupchat/transfer"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GroupChatStatistic), "/cgi-bin/externalcontact/groupchat/statistic"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::AddMsgTemplate), "/cgi-bin/externalcontact/add_msg_template"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::SendWelcomeMsg), "/cgi-bin/externalcontact/send_welcome_msg"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetCorpTagList), "/cgi-bin/externalcontact/get_corp_tag_list"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::AddCorpTag), "/cgi-bin/externalcontact/add_corp_tag"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::EditCorpTag), "/cgi-bin/externalcontact/edit_corp_tag"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::DeleteCorpTag), "/cgi-bin/externalcontact/del_corp_tag"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::MarkTag), "/cgi-bin/externalcontact/mark_tag"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetGroupMsgListV2), "/cgi-bin/externalcontact/get_groupmsg_list_v2"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetGroupMsgSendResult), "/cgi-bin/externalcontact/get_groupmsg_send_result"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetGroupMsgResult), "/cgi-bin/externalcontact/get_group_msg_result"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetGroupMsgTask), "/cgi-bin/externalcontact/get_groupmsg_task"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::AddGroupWelcomeTemplate), "/cgi-bin/externalcontact/group_welcome_template/add"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::EditGroupWelcomeTemplate), "/cgi-bin/externalcontact/group_welcome_template/edit"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::GetGroupWelcomeTemplate), "/cgi-bin/externalcontact/group_welcome_template/get"),
+            (WechatCpMethod::ExternalContact(CpExternalContactMethod::DeleteGroupWelcomeTemplate), "/cgi-bin/externalcontact/group_welcome_template/del"),
+            (WechatCpMethod::Kf(CpKfMethod::AddAccount), "/cgi-bin/kf/account/add"),
+            (WechatCpMethod::Kf(CpKfMethod::DeleteAccount), "/cgi-bin/kf/account/del"),
+            (WechatCpMethod::Kf(CpKfMethod::UpdateAccount), "/cgi-bin/kf/account/update"),
+            (WechatCpMethod::Kf(CpKfMethod::ListAccount), "/cgi-bin/kf/account/list"),
+            (WechatCpMethod::Kf(CpKfMethod::AddContactWay), "/cgi-bin/kf/add_contact_way"),
+            (WechatCpMethod::Kf(CpKfMethod::AddServicer), "/cgi-bin/kf/servicer/add"),
+            (WechatCpMethod::Kf(CpKfMethod::DeleteServicer), "/cgi-bin/kf/servicer/del"),
+            (WechatCpMethod::Kf(CpKfMethod::ListServicer), "/cgi-bin/kf/servicer/list"),
+            (WechatCpMethod::Kf(CpKfMethod::SyncMsg), "/cgi-bin/kf/sync_msg"),
+            (WechatCpMethod::Kf(CpKfMethod::SendMsg), "/cgi-bin/kf/send_msg"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinData), "/cgi-bin/checkin/getcheckindata"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinOption), "/cgi-bin/checkin/getcheckinoption"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinDayData), "/cgi-bin/checkin/getcheckin_daydata"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::GetCheckinScheduleList), "/cgi-bin/checkin/getcheckinschedulist"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::AddCheckinUserFace), "/cgi-bin/checkin/addcheckinuserface"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::GetCorpCheckinOption), "/cgi-bin/checkin/getcorpcheckinoption"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::SetCheckinScheduleList), "/cgi-bin/checkin/setcheckinschedulist"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::AddCheckinRecord), "/cgi-bin/checkin/add_checkin_record"),
+            (WechatCpMethod::Checkin(CpCheckinMethod::PunchCorrection), "/cgi-bin/checkin/punch_correction"),
+            (WechatCpMethod::Export(CpExportMethod::SimpleUser), "/cgi-bin/export/simple_user"),
+            (WechatCpMethod::Export(CpExportMethod::User), "/cgi-bin/export/user"),
+            (WechatCpMethod::Export(CpExportMethod::Department), "/cgi-bin/export/department"),
+            (WechatCpMethod::Export(CpExportMethod::GetResult), "/cgi-bin/export/get_result"),
+            (WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::GetPermList), "/cgi-bin/linkedcorp/agent/get_perm_list"),
+            (WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::GetUser), "/cgi-bin/linkedcorp/user/get"),
+            (WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::UserSimpleList), "/cgi-bin/linkedcorp/user/simplelist"),
+            (WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::UserList), "/cgi-bin/linkedcorp/user/list"),
+            (WechatCpMethod::LinkedCorp(CpLinkedCorpMethod::DepartmentList), "/cgi-bin/linkedcorp/department/list"),
+            (WechatCpMethod::CorpGroup(CpCorpGroupMethod::ListAppShareInfo), "/cgi-bin/corpgroup/corp/list_app_share_info"),
+            (WechatCpMethod::CorpGroup(CpCorpGroupMethod::GetToken), "/cgi-bin/corpgroup/corp/gettoken"),
+            (WechatCpMethod::MsgAudit(CpMsgAuditMethod::GetPermitUserList), "/cgi-bin/msgaudit/get_permit_user_list"),
+            (WechatCpMethod::MsgAudit(CpMsgAuditMethod::CheckSingleAgree), "/cgi-bin/msgaudit/check_single_agree"),
+            (WechatCpMethod::MsgAudit(CpMsgAuditMethod::CheckRoomAgree), "/cgi-bin/msgaudit/check_room_agree"),
+            (WechatCpMethod::MsgAudit(CpMsgAuditMethod::GetGroupChat), "/cgi-bin/msgaudit/groupchat/get"),
+            (WechatCpMethod::Living(CpLivingMethod::Create), "/cgi-bin/living/create"),
+            (WechatCpMethod::Living(CpLivingMethod::Modify), "/cgi-bin/living/modify"),
+            (WechatCpMethod::Living(CpLivingMethod::Cancel), "/cgi-bin/living/cancel"),
+            (WechatCpMethod::Living(CpLivingMethod::GetLivingInfo), "/cgi-bin/living/get_living_info"),
+            (WechatCpMethod::Living(CpLivingMethod::GetWatchStat), "/cgi-bin/living/get_watch_stat"),
+            (WechatCpMethod::Living(CpLivingMethod::GetUserAllLivingId), "/cgi-bin/living/get_user_all_livingid"),
+            (WechatCpMethod::AppChat(CpAppChatMethod::Create), "/cgi-bin/appchat/create"),
+            (WechatCpMethod::AppChat(CpAppChatMethod::Update), "/cgi-bin/appchat/update"),
+            (WechatCpMethod::AppChat(CpAppChatMethod::Get), "/cgi-bin/appchat/get"),
+            (WechatCpMethod::AppChat(CpAppChatMethod::Send), "/cgi-bin/appchat/send"),
+            (WechatCpMethod::Vacation(CpVacationMethod::GetCorpConf), "/cgi-bin/oa/vacation/getcorpconf"),
+            (WechatCpMethod::Vacation(CpVacationMethod::GetUserVacationQuota), "/cgi-bin/oa/vacation/getuservacationquota"),
+            (WechatCpMethod::Vacation(CpVacationMethod::SetOneUserQuota), "/cgi-bin/oa/vacation/setoneuserquota"),
+            (WechatCpMethod::IdConvert(CpIdConvertMethod::UseridToOpenUserid), "/cgi-bin/batch/userid_to_openuserid"),
+            (WechatCpMethod::IdConvert(CpIdConvertMethod::GetNewExternalUserid), "/cgi-bin/externalcontact/get_new_external_userid"),
+            (WechatCpMethod::IdConvert(CpIdConvertMethod::CorpGroupUnionidToExternalUserid), "/cgi-bin/corpgroup/unionid_to_external_userid"),
+            (WechatCpMethod::School(CpSchoolMethod::CreateStudent), "/cgi-bin/school/user/create_student"),
+            (WechatCpMethod::School(CpSchoolMethod::CreateParent), "/cgi-bin/school/user/create_parent"),
+            (WechatCpMethod::School(CpSchoolMethod::BatchCreateParent), "/cgi-bin/school/user/batch_create_parent"),
+            (WechatCpMethod::School(CpSchoolMethod::ListUser), "/cgi-bin/school/user/list"),
+            (WechatCpMethod::School(CpSchoolMethod::CreateDepartment), "/cgi-bin/school/department/create"),
+            (WechatCpMethod::School(CpSchoolMethod::ListDepartment), "/cgi-bin/school/department/list"),
+            (WechatCpMethod::Security(CpSecurityMethod::TrustDeviceList), "/cgi-bin/security/trustdevice/list"),
+            (WechatCpMethod::Security(CpSecurityMethod::GetFileOperRecord), "/cgi-bin/security/get_file_oper_record"),
+            (WechatCpMethod::Security(CpSecurityMethod::GetScreenOperRecord), "/cgi-bin/security/get_screen_oper_record"),
+            (WechatCpMethod::Approval(CpApprovalMethod::GetTemplateDetail), "/cgi-bin/oa/gettemplatedetail"),
+            (WechatCpMethod::Approval(CpApprovalMethod::ApplyEvent), "/cgi-bin/oa/applyevent"),
+        ];
+        for (method, path) in methods {
+            assert_eq!(method.get_method(), path, "{:?}", method);
+            let url = if path.starts_with("http") { path.to_string() } else { format!("https://qyapi.weixin.qq.com{}", path) };
+            assert_eq!(method.url("https://qyapi.weixin.qq.com"), url, "{:?}", method);
+        }
+        assert_eq!(WechatCpMethod::AccessToken.url("https://qyapi.weixin.qq.com/"), "https://qyapi.weixin.qq.com/cgi-bin/gettoken");
+    }
+
+    #[test]
+    fn test_custom_method() {
+        let method = WechatCpMethod::Custom { path: "/cgi-bin/user/get".to_string(), request_method: Method::Get };
+        assert!(method.need_token());
+        assert_eq!(method.request_method(), Some(Method::Get));
+        assert_eq!(method.url("https://qyapi.weixin.qq.com"), "https://qyapi.weixin.qq.com/cgi-bin/user/get");
+        let webhook = WechatCpMethod::Custom { path: "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=KEY".to_string(), request_method: Method::Post };
+        assert!(!webhook.need_token());
+        assert_eq!(webhook.url("https://example.com"), "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=KEY");
+        assert_eq!(WechatCpMethod::AccessToken.request_method(), None);
+        let bearer = WechatCpMethod::CustomWithAuth { path: "/cgi-bin/user/get".to_string(), request_method: Method::Get, auth_style: AuthStyle::BearerHeader };
+        assert!(bearer.need_token());
+        assert_eq!(bearer.auth_style(), AuthStyle::BearerHeader);
+        assert_eq!(webhook.auth_style(), AuthStyle::None);
+        assert_eq!(method.auth_style(), AuthStyle::QueryParam);
+    }
+
+    #[test]
+    fn test_token_param() {
+        assert_eq!(WechatCpMethod::User(CpUserMethod::Get("1".to_string())).token_param().key(), "access_token");
+        assert_eq!(WechatCpMethod::Custom { path: "/cgi-bin/user/get".to_string(), request_method: Method::Get }.token_param().key(), "access_token");
+        assert_eq!(WechatCpMethod::GetPreAuthCode.token_param().key(), "suite_access_token");
+        assert_eq!(WechatCpMethod::GetPermanentCode.token_param().key(), "suite_access_token");
+        assert_eq!(WechatCpMethod::GetAuthInfo.token_param().key(), "suite_access_token");
+        assert_eq!(WechatCpMethod::GetCorpToken.token_param().key(), "suite_access_token");
+        assert_eq!(WechatCpMethod::GetOrderList.token_param().key(), "provider_access_token");
+        assert_eq!(WechatCpMethod::License(CpLicenseMethod::CancelOrder).token_param().key(), "provider_access_token");
+        assert!(!WechatCpMethod::AccessToken.need_token());
+        assert!(!WechatCpMethod::GetSuiteToken.need_token());
+        assert!(!WechatCpMethod::GetProviderToken.need_token());
+    }
+}
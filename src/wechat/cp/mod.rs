@@ -1,4 +1,8 @@
-use crate::{session::SessionStore, client::APIClient, request::{Method, RequestType, LabraResponse, LabraRequest, RequestMethod}, util::current_timestamp, LabradorResult, SimpleStorage, WechatCrypto, WechatRequest, get_timestamp, get_nonce_str, WechatCommonResponse};
+use std::sync::Arc;
+
+use crate::{session::{SessionStore, DynSessionStore}, MetricsRecorder, StoreFallbackPolicy, DebugRecorder, CachePolicy, DebugRecord, client::APIClient, request::{Method, RequestType, LabraResponse, LabraRequest, RequestMethod}, util::current_timestamp, LabradorResult, SimpleStorage, WechatCrypto, WechatRequest, nonce_str, WechatCommonResponse, WechatEnvelope, JsapiTicket, JsapiSignature};
+use crate::wechat::WECHAT_CP_BASE_URL;
+use crate::wechat::token_cache::{TokenCache, TokenRequest};
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 
@@ -10,12 +14,42 @@ mod tp;
 
 pub use api::*;
 pub use tp::*;
-use crate::wechat::cp::constants::{ACCESS_TOKEN, CORPID, CORPSECRET};
+use crate::wechat::cp::constants::{ACCESS_TOKEN, CORPID, CORPSECRET, TYPE};
 use crate::wechat::cp::method::{WechatCpMethod};
-
+use crate::serde_helper::string_or_number;
+
+/// 企业微信客户端
+///
+/// 配置、HTTP连接池和会话存储都放在`Arc`中，克隆只增加引用计数，可以直接放进web框架的状态或转移到`tokio::spawn`的任务中；
+/// 各接口结构体（如[`WechatCpCodeSession`]）持有克隆的客户端，不再带生命周期参数。
+///
+/// ```no_run
+/// use labrador::{WechatCpClient, WechatCpDepartment, SimpleStorage};
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     client: WechatCpClient<SimpleStorage>,
+///     department: WechatCpDepartment<SimpleStorage>,
+/// }
+///
+/// # async fn run() {
+/// let client = WechatCpClient::<SimpleStorage>::new("corp_id", "corp_secret");
+/// let state = AppState { department: client.department(), client };
+/// tokio::spawn(async move {
+///     let _ = state.department.list(None).await;
+///     let _ = state.client.user().authenticate("zhangsan").await;
+/// });
+/// # }
+/// ```
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct WechatCpClient<T: SessionStore> {
+    inner: Arc<WechatCpClientInner<T>>,
+}
+
+/// 客户端配置与会话存储，克隆客户端时共享
+#[derive(Debug, Clone)]
+struct WechatCpClientInner<T: SessionStore> {
     corp_id: String,
     corp_secret: String,
     token: Option<String>,
@@ -23,6 +57,11 @@ pub struct WechatCpClient<T: SessionStore> {
     oauth2_redirect_uri: Option<String>,
     webhook_url: Option<String>,
     agent_id: Option<i32>,
+    /// 固定的access_token（如上下游企业的token），设置后不再通过corpsecret获取
+    access_token: Option<String>,
+    /// 第三方应用授权的企业，access_token通过suite_access_token和永久授权码换取
+    provider: Option<(WechatCpTpClient<T>, String)>,
+    tokens: TokenCache,
     client: APIClient<T>,
 }
 
@@ -30,26 +69,10 @@ pub struct WechatCpClient<T: SessionStore> {
 #[derive(Serialize, Deserialize)]
 pub struct AccessTokenResponse{
     pub access_token: String,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
-#[allow(unused)]
-#[derive(Serialize, Deserialize)]
-pub struct JsapiTicket {
-    pub ticket: String,
-    pub expires_in: i64,
-}
-
-#[allow(unused)]
-#[derive(Serialize, Deserialize)]
-pub struct JsapiSignature {
-    pub app_id: String,
-    #[serde(rename="nonceStr")]
-    pub nonce_str: String,
-    pub url: String,
-    pub signature: String,
-    pub timestamp: i64,
-}
 #[allow(unused)]
 #[derive(Serialize, Deserialize)]
 pub struct AgentJsapiSignature {
@@ -62,6 +85,29 @@ pub struct AgentJsapiSignature {
     pub timestamp: i64,
 }
 
+/// 企业微信jsapi_ticket的类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpTicketType {
+    /// 企业的jsapi_ticket，用于wx.config
+    Jsapi,
+    /// 应用的jsapi_ticket，用于wx.agentConfig
+    AgentConfig,
+}
+
+impl std::fmt::Display for CpTicketType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpTicketType::Jsapi => f.write_str("jsapi"),
+            CpTicketType::AgentConfig => f.write_str("agent_config"),
+        }
+    }
+}
+
+/// jsapi签名：对jsapi_ticket、noncestr、timestamp、url按字段名排序拼接后做sha1
+fn jsapi_sign(jsapi_ticket: &str, noncestr: &str, timestamp: i64, url: &str) -> String {
+    WechatCrypto::get_sha1_sign(&format!("jsapi_ticket={}&noncestr={}&timestamp={}&url={}", jsapi_ticket, noncestr, timestamp, url))
+}
+
 
 #[allow(unused)]
 #[derive(Serialize, Deserialize)]
@@ -69,81 +115,199 @@ pub struct WechatCpProviderToken {
     /// 服务商的access_token，最长为512字节。
     pub provider_access_token: String,
     /// provider_access_token有效期（秒）
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
+impl WechatCpClient<DynSessionStore> {
+    /// <pre>
+    /// 使用运行时选择的会话存储创建客户端
+    /// 例如按配置在内存与Redis之间切换时，客户端类型保持为`WechatCpClient<DynSessionStore>`
+    /// </pre>
+    pub fn new_dyn<S: Into<String>>(crop_id: S, crop_secret: S, session: DynSessionStore) -> WechatCpClient<DynSessionStore> {
+        Self::from_session(crop_id, crop_secret, session)
+    }
+}
+
 #[allow(unused)]
 impl<T: SessionStore> WechatCpClient<T> {
 
     fn from_client(client: APIClient<T>) -> WechatCpClient<T> {
         WechatCpClient {
-            corp_id: client.app_key.to_owned(),
-            corp_secret: client.secret.to_owned(),
-            token: None,
-            aes_key: None,
-            oauth2_redirect_uri: None,
-            webhook_url: None,
-            agent_id: None,
-            client
+            inner: Arc::new(WechatCpClientInner {
+                corp_id: client.app_key.to_owned(),
+                corp_secret: client.secret.to_owned(),
+                token: None,
+                aes_key: None,
+                oauth2_redirect_uri: None,
+                webhook_url: None,
+                agent_id: None,
+                access_token: None,
+                provider: None,
+                tokens: TokenCache::default(),
+                client
+            }),
         }
     }
 
     pub fn aes_key(mut self, aes_key: &str) -> Self {
-        self.aes_key = aes_key.to_string().into();
+        Arc::make_mut(&mut self.inner).aes_key = aes_key.to_string().into();
         self
     }
 
     pub fn token(mut self, token: &str) -> Self {
-        self.token = token.to_string().into();
+        Arc::make_mut(&mut self.inner).token = token.to_string().into();
         self
     }
 
     pub fn oauth2_redirect_uri(mut self, oauth2_redirect_uri: &str) -> Self {
-        self.oauth2_redirect_uri = oauth2_redirect_uri.to_string().into();
+        Arc::make_mut(&mut self.inner).oauth2_redirect_uri = oauth2_redirect_uri.to_string().into();
         self
     }
 
     pub fn webhook_url(mut self, webhook_url: &str) -> Self {
-        self.webhook_url = webhook_url.to_string().into();
+        Arc::make_mut(&mut self.inner).webhook_url = webhook_url.to_string().into();
+        self
+    }
+
+    pub fn agent_id(mut self, agent_id: i32) -> Self {
+        Arc::make_mut(&mut self.inner).agent_id = agent_id.into();
         self
     }
 
     /// get the wechat client
     pub fn new<S: Into<String>>(crop_id: S, crop_secret: S) -> WechatCpClient<SimpleStorage> {
-        let client = APIClient::<SimpleStorage>::from_session(crop_id.into(), crop_secret.into(), "https://qyapi.weixin.qq.com", SimpleStorage::new());
+        let client = APIClient::<SimpleStorage>::from_session(crop_id.into(), crop_secret.into(), WECHAT_CP_BASE_URL, SimpleStorage::new());
         WechatCpClient::<SimpleStorage>::from_client(client)
     }
 
     /// get the wechat client
     pub fn from_session<S: Into<String>>(crop_id: S, crop_secret: S, session: T) -> WechatCpClient<T> {
-        let client = APIClient::from_session(crop_id.into(), crop_secret.into(), "https://qyapi.weixin.qq.com", session);
+        let client = APIClient::from_session(crop_id.into(), crop_secret.into(), WECHAT_CP_BASE_URL, session);
         Self::from_client(client)
     }
 
+    /// 替换接口域名（默认[`WECHAT_CP_BASE_URL`]），如私有网关、就近接入域名
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().api_path(base_url);
+        self
+    }
+
+    /// 备用域名，连接失败时按顺序切换
+    pub fn fallback_urls(mut self, fallback_urls: Vec<&str>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().fallback_paths(fallback_urls);
+        self
+    }
+
+    /// 请求指标记录，每次请求（含切换备用域名的重试）都会回调
+    pub fn metrics_recorder(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().metrics_recorder(metrics);
+        self
+    }
+
+    /// 开启请求调试记录，在内存中保留最近的请求/响应报文，URL中的凭证会被隐去
+    pub fn debug_recorder(mut self, debug: Arc<DebugRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().debug_recorder(debug);
+        self
+    }
+
+    /// 当前保留的请求调试记录，未开启时为空
+    pub fn debug_records(&self) -> Vec<DebugRecord> {
+        self.inner.client.debug_records()
+    }
+
+    /// <pre>
+    /// 添加GET接口的响应缓存策略，如用户详情、部门列表
+    /// 缓存写入会话存储，通过本crate调用对应的修改接口（如更新成员）时自动失效
+    /// </pre>
+    pub fn cache_policy(mut self, policy: CachePolicy) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().cache_policy(policy);
+        self
+    }
+
+    /// 不读取缓存的客户端，请求结果仍会刷新缓存，如`client.no_cache().user().get_by_id(..)`
+    pub fn no_cache(&self) -> Self {
+        let mut client = self.clone();
+        let inner = Arc::make_mut(&mut client.inner);
+        inner.client = inner.client.to_owned().no_cache();
+        client
+    }
+
+    /// 使前缀下的响应缓存失效，如`/cgi-bin/user/get?userid=zhangsan`、`/cgi-bin/department`
+    pub fn invalidate_cache_prefix(&self, key_prefix: &str) -> LabradorResult<()> {
+        self.inner.client.invalidate_cache_prefix(key_prefix)
+    }
+
+    /// 以下游企业身份调用接口的客户端.
+    /// <pre>
+    /// 共用当前客户端的域名、连接池与会话存储，请求时使用传入的access_token（通过[`WechatCpCorpGroup::get_corp_token`]获取）
+    /// token过期后需重新获取并构造
+    /// </pre>
+    pub fn for_linked_corp(&self, corp_id: &str, access_token: &str) -> WechatCpClient<T> {
+        let mut inner = self.inner.as_ref().clone();
+        inner.corp_id = corp_id.to_string();
+        inner.corp_secret = String::default();
+        inner.agent_id = None;
+        inner.access_token = access_token.to_string().into();
+        inner.client.app_key = corp_id.to_string();
+        inner.client.secret = String::default();
+        WechatCpClient {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// 第三方应用授权企业的客户端，见[`WechatCpTpClient::corp_client`]
+    fn from_suite(mut client: APIClient<T>, provider: WechatCpTpClient<T>, auth_corp_id: &str, permanent_code: &str) -> WechatCpClient<T> {
+        client.app_key = auth_corp_id.to_string();
+        client.secret = String::default();
+        let mut inner = Self::from_client(client).inner.as_ref().clone();
+        inner.corp_secret = String::default();
+        inner.provider = (provider, permanent_code.to_string()).into();
+        WechatCpClient {
+            inner: Arc::new(inner),
+        }
+    }
+
     #[inline]
     pub async fn access_token(&self, force_refresh: bool) -> LabradorResult<String> {
-        let mut session = self.client.session();
-        let token_key = format!("{}_access_token_cp", self.corp_id);
-        let expires_key = format!("{}_expires_at_cp", self.corp_id);
-        let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
-        let timestamp = current_timestamp();
-        let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
-        if expires_at <= timestamp || force_refresh {
-            let mut req = LabraRequest::<String>::new().url(WechatCpMethod::AccessToken.get_method()).params(vec![
-                (CORPID.to_string(), self.corp_id.to_string()),
-                (CORPSECRET.to_string(), self.corp_secret.to_string()),
-            ]).method(Method::Get).req_type(RequestType::Json);
-            let res = self.client.request(req).await?.json::<AccessTokenResponse>()?;
-            let token = res.access_token;
-            let expires_in = res.expires_in;
-            // 预留200秒的时间
-            let expires_at = current_timestamp() + expires_in - 200;
-            session.set(&token_key, token.to_owned(), Some(expires_in as usize));
-            session.set(&expires_key, expires_at, Some(expires_in as usize));
-            Ok(token.to_string())
-        } else {
-            Ok(token)
+        if let Some(access_token) = &self.inner.access_token {
+            return Ok(access_token.to_owned());
+        }
+        if let Some((provider, permanent_code)) = &self.inner.provider {
+            return provider.get_corp_token_force(&self.inner.corp_id, permanent_code, force_refresh).await.map(|res| res.access_token);
         }
+        let token_key = format!("{}_access_token_cp", self.inner.corp_id);
+        let expires_key = format!("{}_expires_at_cp", self.inner.corp_id);
+        let token_req = TokenRequest { session: self.inner.client.session(), token_key: &token_key, expires_key: &expires_key, appid: &self.inner.corp_id, metrics: self.inner.client.metrics() };
+        let (info, _) = self.inner.tokens.token_info(&token_req, force_refresh, || async {
+            let req = LabraRequest::<String>::new().url(WechatCpMethod::AccessToken.get_method()).params(vec![
+                (CORPID.to_string(), self.inner.corp_id.to_string()),
+                (CORPSECRET.to_string(), self.inner.corp_secret.to_string()),
+            ]).method(Method::Get).req_type(RequestType::Json);
+            let res = self.inner.client.request(req).await?.json::<AccessTokenResponse>()?;
+            Ok((res.access_token, res.expires_in))
+        }).await?;
+        Ok(info.token)
+    }
+
+    /// <pre>
+    /// 会话存储（如Redis）不可用时降级：直接向企业微信换取access_token并缓存在进程内，定期重试会话存储
+    /// 未配置时读取会话存储出错直接返回错误；固定access_token与第三方应用授权的企业不受影响
+    /// </pre>
+    pub fn store_fallback(mut self, policy: StoreFallbackPolicy) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.tokens = inner.tokens.with_policy(policy);
+        self
+    }
+
+    /// 会话存储是否可用，配置了`store_fallback`且读写出错时为false，重试成功后恢复为true
+    pub fn store_healthy(&self) -> bool {
+        self.inner.tokens.store_healthy()
     }
     
     /// <pre>
@@ -158,7 +322,7 @@ impl<T: SessionStore> WechatCpClient<T> {
             "corpid": corp_id,
             "provider_secret": provider_secret,
         })).method(Method::Post).req_type(RequestType::Json);
-        let res = self.client.request(req).await?.json::<WechatCpProviderToken>()?;
+        let res = self.inner.client.request(req).await?.json::<WechatCpProviderToken>()?;
         Ok(res)
     }
 
@@ -168,26 +332,24 @@ impl<T: SessionStore> WechatCpClient<T> {
     /// [详情](http://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1421135319&token=&lang=zh_CN)
     /// </pre>
     pub fn check_signature(&self, signature: &str, timestamp: i64, nonce: &str, data: &str) -> LabradorResult<bool> {
-        let crp = WechatCrypto::new(&self.aes_key.to_owned().unwrap_or_default());
-        let _ = crp.check_signature(signature, timestamp, nonce, data, &self.token.to_owned().unwrap_or_default())?;
+        let crp = WechatCrypto::new(&self.inner.aes_key.to_owned().unwrap_or_default());
+        let _ = crp.check_signature(signature, timestamp, nonce, data, &self.inner.token.to_owned().unwrap_or_default())?;
         Ok(true)
     }
 
     ///
     /// <pre>
-    /// 创建调用jsapi时所需要的签名
+    /// 创建调用jsapi时所需要的签名，用于wx.config
     ///
     /// 详情[请见](http://qydev.weixin.qq.com/wiki/index.php?title=微信JS接口)
     /// </pre>
     pub async fn create_jsapi_signature(&self, url: &str) -> LabradorResult<JsapiSignature> {
-        let timestamp = get_timestamp() / 1000;
-        let noncestr = get_nonce_str();
+        let timestamp = current_timestamp();
+        let noncestr = nonce_str();
         let jsapi_ticket = self.get_jsapi_ticket(false).await?;
-        let signature = WechatCrypto::get_sha1_sign(&vec!["jsapi_ticket=".to_string() + &jsapi_ticket,
-                                                         "noncestr=".to_string() + &noncestr,
-                                                         "timestamp=".to_string() + &timestamp.to_string(),"url=".to_string() + &url].join("&"));
+        let signature = jsapi_sign(&jsapi_ticket, &noncestr, timestamp, url);
         Ok(JsapiSignature{
-            app_id: self.corp_id.to_string(),
+            app_id: self.inner.corp_id.to_string(),
             nonce_str: noncestr,
             url: url.to_string(),
             signature,
@@ -198,19 +360,19 @@ impl<T: SessionStore> WechatCpClient<T> {
     ///
     /// <pre>
     /// 创建调用wx.agentConfig时所需要的签名
+    /// 签名算法与wx.config相同，但使用应用的jsapi_ticket（见[`get_agent_jsapi_ticket`](Self::get_agent_jsapi_ticket)），
+    /// 客户端需设置agent_id，且access_token需使用该应用的secret获取
     ///
     /// 详情[请见](https://open.work.weixin.qq.com/api/doc/90000/90136/94313)
     /// </pre>
-    pub async fn create_agent_jsapi_signature(&self, url: &str) -> LabradorResult<AgentJsapiSignature> {
-        let timestamp = get_timestamp() / 1000;
-        let noncestr = get_nonce_str();
-        let jsapi_ticket = self.get_jsapi_ticket(false).await?;
-        let signature = WechatCrypto::get_sha1_sign(&vec!["jsapi_ticket=".to_string() + &jsapi_ticket,
-                                                         "noncestr=".to_string() + &noncestr,
-                                                         "timestamp=".to_string() + &timestamp.to_string(),"url=".to_string() + &url].join("&"));
+    pub async fn create_agent_config_signature(&self, url: &str) -> LabradorResult<AgentJsapiSignature> {
+        let timestamp = current_timestamp();
+        let noncestr = nonce_str();
+        let jsapi_ticket = self.get_agent_jsapi_ticket(false).await?;
+        let signature = jsapi_sign(&jsapi_ticket, &noncestr, timestamp, url);
         Ok(AgentJsapiSignature{
-            agentid: self.agent_id.unwrap_or_default().to_string(),
-            corpid: self.corp_id.to_string(),
+            agentid: self.inner.agent_id.unwrap_or_default().to_string(),
+            corpid: self.inner.corp_id.to_string(),
             nonce_str: noncestr,
             url: url.to_string(),
             signature,
@@ -218,32 +380,22 @@ impl<T: SessionStore> WechatCpClient<T> {
         })
     }
 
+    ///
+    /// <pre>
+    /// 创建调用wx.agentConfig时所需要的签名
+    ///
+    /// 详情[请见](https://open.work.weixin.qq.com/api/doc/90000/90136/94313)
+    /// </pre>
+    pub async fn create_agent_jsapi_signature(&self, url: &str) -> LabradorResult<AgentJsapiSignature> {
+        self.create_agent_config_signature(url).await
+    }
+
     ///
     /// <pre>
     /// 获得jsapi_ticket,不强制刷新jsapi_ticket
     /// </pre>
     pub async fn get_jsapi_ticket(&self, force_refresh: bool) -> LabradorResult<String> {
-        let mut session = self.client.session();
-        let token_key = format!("{}_jsapi_ticket_cp", self.corp_id);
-        let expires_key = format!("{}_jsapi_ticket_expires_at_cp", self.corp_id);
-        let ticket: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
-        let timestamp = current_timestamp();
-        let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
-        if expires_at <= timestamp || force_refresh {
-            let mut req = LabraRequest::<String>::new().url(WechatCpMethod::GetJsapiTicket.get_method()).params(vec![]).method(Method::Get).req_type(RequestType::Json);
-            let res = self.client.request(req).await?.json::<JsapiTicket>()?;
-            let ticket = res.ticket;
-            let expires_in = res.expires_in;
-            // 预留200秒的时间
-            let expires_at = current_timestamp() + expires_in - 200;
-            let ticket_key = format!("{}_jsapi_ticket_cp", self.corp_id);
-            let expires_key = format!("{}_jsapi_ticket_expires_at_cp", self.corp_id);
-            session.set(&ticket_key, ticket.to_owned(), Some(expires_in as usize));
-            session.set(&expires_key, expires_at, Some(expires_in as usize));
-            Ok(ticket.to_string())
-        } else {
-            Ok(ticket)
-        }
+        self.get_ticket(CpTicketType::Jsapi, force_refresh).await
     }
 
     ///
@@ -255,29 +407,48 @@ impl<T: SessionStore> WechatCpClient<T> {
     /// 签名用的noncestr和timestamp必须与wx.agentConfig中的nonceStr和timestamp相同。
     /// </pre>
     pub async fn get_agent_jsapi_ticket(&self, force_refresh: bool) -> LabradorResult<String> {
-        let mut session = self.client.session();
-        let token_key = format!("{}_agent_jsapi_ticket_cp", self.corp_id);
-        let expires_key = format!("{}_agent_jsapi_ticket_expires_at_cp", self.corp_id);
-        let ticket: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
+        self.get_ticket(CpTicketType::AgentConfig, force_refresh).await
+    }
+
+    ///
+    /// <pre>
+    /// 获得指定类型的jsapi_ticket
+    /// 企业与应用的ticket分别缓存、各自过期，应用的ticket按agentid区分
+    /// </pre>
+    pub async fn get_ticket(&self, ticket_type: CpTicketType, force_refresh: bool) -> LabradorResult<String> {
+        let session = self.inner.client.session();
+        let (ticket_key, expires_key) = self.ticket_keys(&ticket_type);
+        let ticket: String = session.get(&ticket_key, Some("".to_owned()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
         if expires_at <= timestamp || force_refresh {
-            let mut req = LabraRequest::<String>::new().url(WechatCpMethod::GetAgentConfigTicket.get_method()).params(vec![]).method(Method::Get).req_type(RequestType::Json);
-            let res = self.client.request(req).await?.json::<JsapiTicket>()?;
-            let ticket = res.ticket;
-            let expires_in = res.expires_in;
+            let (method, params) = match ticket_type {
+                CpTicketType::Jsapi => (WechatCpMethod::GetJsapiTicket, vec![]),
+                CpTicketType::AgentConfig => (WechatCpMethod::GetAgentConfigTicket, vec![(TYPE.to_string(), ticket_type.to_string())]),
+            };
+            let res = self.get(method.clone(), params, RequestType::Json).await?.json::<Value>()?;
+            let JsapiTicket { ticket, expires_in } = WechatEnvelope::new().method(method.get_method()).extract_value::<JsapiTicket>(res)?;
             // 预留200秒的时间
             let expires_at = current_timestamp() + expires_in - 200;
-            let ticket_key = format!("{}_agent_jsapi_ticket_cp", self.corp_id);
-            let expires_key = format!("{}_agent_jsapi_ticket_expires_at_cp", self.corp_id);
-            session.set(&ticket_key, ticket.to_owned(), Some(expires_in as usize));
-            session.set(&expires_key, expires_at, Some(expires_in as usize));
-            Ok(ticket.to_string())
+            session.set(&ticket_key, ticket.to_owned(), Some(expires_in as usize))?;
+            session.set(&expires_key, expires_at, Some(expires_in as usize))?;
+            Ok(ticket)
         } else {
             Ok(ticket)
         }
     }
 
+    /// ticket与过期时间的存储键
+    fn ticket_keys(&self, ticket_type: &CpTicketType) -> (String, String) {
+        match ticket_type {
+            CpTicketType::Jsapi => (format!("{}_jsapi_ticket_cp", self.inner.corp_id), format!("{}_jsapi_ticket_expires_at_cp", self.inner.corp_id)),
+            CpTicketType::AgentConfig => {
+                let agent_id = self.inner.agent_id.unwrap_or_default();
+                (format!("{}_{}_agent_jsapi_ticket_cp", self.inner.corp_id, agent_id), format!("{}_{}_agent_jsapi_ticket_expires_at_cp", self.inner.corp_id, agent_id))
+            }
+        }
+    }
+
     ///
     /// <pre>
     /// 获取微信服务器的ip段
@@ -305,80 +476,374 @@ impl<T: SessionStore> WechatCpClient<T> {
         let params = querys.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<(String, String)>>();
         let mut req = LabraRequest::<B>::new().url(request.get_api_method_name())
             .params(params).method(request.get_request_method()).req_type(request.get_request_type()).body(request.get_request_body::<B>());
-        self.client.request(req).await
+        if let Some(timeout) = request.get_timeout() {
+            req = req.timeout(timeout);
+        }
+        self.inner.client.request(req).await
+    }
+
+    /// <pre>
+    /// 调用尚未封装的接口
+    /// path为接口路径（如`/cgi-bin/user/get`）时自动附加access_token，为完整URL时原样请求
+    /// 返回errcode非0时转换为错误
+    /// </pre>
+    pub async fn call<D: Serialize>(&self, path: &str, request_method: Method, params: Vec<(String, String)>, data: Option<D>) -> LabradorResult<Value> {
+        let method = WechatCpMethod::Custom { path: path.to_string(), request_method };
+        let v = match method.request_method() {
+            Some(Method::Get) => self.get(method, params, RequestType::Json).await?,
+            _ => self.post(method, params, data.map(|data| serde_json::to_value(data)).transpose()?.unwrap_or_else(|| json!({})), RequestType::Json).await?,
+        }.json::<Value>()?;
+        WechatCommonResponse::parse::<Value>(v)
     }
 
     /// 发送POST请求
     async fn post<D: Serialize>(&self, method: WechatCpMethod, mut querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let access_token = self.access_token(false).await?;
-        if !access_token.is_empty() && method.need_token() {
-            querys.push((ACCESS_TOKEN.to_string(), access_token));
-        }
-        self.client.post(method, querys, data, request_type).await
+        let headers = self.attach_token(&method, &mut querys).await?;
+        self.inner.client.post_with_headers(method, querys, headers, data, request_type).await
     }
 
     /// 发送GET请求
     async fn get(&self, method: WechatCpMethod, mut params: Vec<(String, String)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let access_token = self.access_token(false).await?;
-        if !access_token.is_empty() && method.need_token() {
-            params.push((ACCESS_TOKEN.to_string(), access_token));
+        let headers = self.attach_token(&method, &mut params).await?;
+        self.inner.client.get_with_headers(method, params, headers, request_type).await
+    }
+
+    /// 按接口的`AuthStyle`附加access_token，返回需要附加的请求头
+    async fn attach_token(&self, method: &WechatCpMethod, params: &mut Vec<(String, String)>) -> LabradorResult<Vec<(String, String)>> {
+        if !method.need_token() {
+            return Ok(vec![]);
         }
-        self.client.get(method, params, request_type).await
+        let access_token = self.access_token(false).await?;
+        Ok(method.auth_style().attach(ACCESS_TOKEN, &access_token, params))
     }
 
     /// codesssion相关服务
     pub fn code_session(&self) -> WechatCpCodeSession<T> {
-        WechatCpCodeSession::new(self)
+        WechatCpCodeSession::from_client(self.clone())
     }
 
     /// 媒体操作接口
     pub fn media(&self) -> WechatCpMedia<T> {
-        WechatCpMedia::new(self)
+        WechatCpMedia::from_client(self.clone())
     }
 
     /// 自建应用
     pub fn agent(&self) -> WechatCpAgent<T> {
-        WechatCpAgent::new(self)
+        WechatCpAgent::from_client(self.clone())
     }
 
     /// 部门
     pub fn department(&self) -> WechatCpDepartment<T> {
-        WechatCpDepartment::new(self)
+        WechatCpDepartment::from_client(self.clone())
     }
 
     /// 外部联系人
     pub fn external_contact(&self) -> WechatCpExternalContact<T> {
-        WechatCpExternalContact::new(self)
+        WechatCpExternalContact::from_client(self.clone())
     }
 
     /// 群机器人
     pub fn group_robot(&self) -> WechatCpGroupRobot<T> {
-        WechatCpGroupRobot::new(self)
+        WechatCpGroupRobot::from_client(self.clone())
     }
 
     /// 菜单
     pub fn menu(&self) -> WechatCpMenu<T> {
-        WechatCpMenu::new(self)
+        WechatCpMenu::from_client(self.clone())
     }
 
     /// 消息
     pub fn message(&self) -> WechatCpMessage<T> {
-        WechatCpMessage::new(self)
+        WechatCpMessage::from_client(self.clone())
     }
 
     /// 认证
     pub fn oauth2(&self) -> WechatCpOauth2<T> {
-        WechatCpOauth2::new(self)
+        WechatCpOauth2::from_client(self.clone())
     }
 
     /// 标签
     pub fn tag(&self) -> WechatCpTag<T> {
-        WechatCpTag::new(self)
+        WechatCpTag::from_client(self.clone())
     }
 
     /// 用户
     pub fn user(&self) -> WechatCpUser<T> {
-        WechatCpUser::new(self)
+        WechatCpUser::from_client(self.clone())
+    }
+
+    /// 微信客服
+    pub fn kf(&self) -> WechatCpKf<T> {
+        WechatCpKf::from_client(self.clone())
+    }
+
+    /// 打卡
+    pub fn checkin(&self) -> WechatCpCheckin<T> {
+        WechatCpCheckin::from_client(self.clone())
+    }
+
+    /// 异步导出
+    pub fn export(&self) -> WechatCpExport<T> {
+        WechatCpExport::from_client(self.clone())
+    }
+
+    /// 互联企业
+    pub fn linked_corp(&self) -> WechatCpLinkedCorp<T> {
+        WechatCpLinkedCorp::from_client(self.clone())
+    }
+
+    /// 上下游
+    pub fn corp_group(&self) -> WechatCpCorpGroup<T> {
+        WechatCpCorpGroup::from_client(self.clone())
+    }
+
+    /// 会话内容存档
+    pub fn msg_audit(&self) -> WechatCpMsgAudit<T> {
+        WechatCpMsgAudit::from_client(self.clone())
+    }
+
+    /// 直播
+    pub fn living(&self) -> WechatCpLiving<T> {
+        WechatCpLiving::from_client(self.clone())
+    }
+
+    /// 群聊会话
+    pub fn app_chat(&self) -> WechatCpAppChat<T> {
+        WechatCpAppChat::from_client(self.clone())
+    }
+
+    /// 假期管理
+    pub fn vacation(&self) -> WechatCpVacation<T> {
+        WechatCpVacation::from_client(self.clone())
+    }
+
+    /// ID转换
+    pub fn id_convert(&self) -> WechatCpIdConvert<T> {
+        WechatCpIdConvert::from_client(self.clone())
+    }
+
+    /// 家校沟通
+    pub fn school(&self) -> WechatCpSchool<T> {
+        WechatCpSchool::from_client(self.clone())
+    }
+
+    /// 安全管理
+    pub fn security(&self) -> WechatCpSecurity<T> {
+        WechatCpSecurity::from_client(self.clone())
+    }
+
+    /// 审批
+    pub fn approval(&self) -> WechatCpApproval<T> {
+        WechatCpApproval::from_client(self.clone())
+    }
+
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::{CachePolicy, DynSessionStore, LabraError, Method, SessionStore, SimpleStorage, WechatCpClient, WechatCrypto, WechatCpDepartment, WechatCpCodeSession, WechatCpUser, WechatCpUserInfo};
+    use crate::redis_store::RedisStorage;
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::CpTicketType;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+    /// 没有生命周期参数，可以作为结构体字段
+    struct AppState {
+        department: WechatCpDepartment<SimpleStorage>,
+    }
+
+    /// 按环境变量选择会话存储
+    fn session_from_env(key: &str) -> DynSessionStore {
+        match std::env::var(key).as_deref() {
+            Ok("redis") => Arc::new(RedisStorage::from_url(std::env::var("LABRADOR_REDIS_URL").unwrap_or_default())),
+            _ => Arc::new(SimpleStorage::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dyn_session_client() {
+        assert_shareable::<WechatCpClient<DynSessionStore>>();
+        std::env::set_var("LABRADOR_TEST_CP_DYN_SESSION", "memory");
+        let session = session_from_env("LABRADOR_TEST_CP_DYN_SESSION");
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","chat_info":{"chatid":"CHATID","userlist":["u1","u2"]}}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","chat_info":{"chatid":"CHATID","userlist":["u1","u2"]}}"#),
+        ]).await;
+        let client = WechatCpClient::new_dyn("dyn_session_corp", "secret", session.clone()).base_url(&server.url);
+        assert_eq!(client.app_chat().get("CHATID").await.unwrap().userlist.len(), 2);
+        // access_token写入运行时选择的存储，第二次请求直接复用
+        client.app_chat().get("CHATID").await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].starts_with("GET /cgi-bin/appchat/get?"));
     }
 
+    #[test]
+    fn test_client_shareable() {
+        assert_shareable::<WechatCpClient<SimpleStorage>>();
+        assert_shareable::<WechatCpCodeSession<SimpleStorage>>();
+        assert_shareable::<WechatCpDepartment<SimpleStorage>>();
+
+        let client = WechatCpClient::<SimpleStorage>::new("shareable_corp", "secret").token("token");
+        let cloned = client.clone();
+        assert!(Arc::ptr_eq(&client.inner, &cloned.inner));
+        // 构建时只修改自己持有的配置
+        let changed = cloned.aes_key("aes_key");
+        assert!(!Arc::ptr_eq(&client.inner, &changed.inner));
+        assert!(client.inner.aes_key.is_none());
+        assert_eq!(changed.inner.token.as_deref(), Some("token"));
+    }
+
+    #[tokio::test]
+    async fn test_owned_api_in_task() {
+        let list = r#"{"errcode":0,"errmsg":"ok","department":[{"id":2,"name":"广州研发中心","parentid":1,"order":10}]}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(list), MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("owned_api_corp", "secret").base_url(&server.url);
+        let state = AppState { department: client.department() };
+        let departments = tokio::spawn(async move { state.department.list(None).await }).await.unwrap().unwrap();
+        assert_eq!(departments.department.len(), 1);
+
+        // 旧的借用构造方式仍然可用
+        #[allow(deprecated)]
+        let user = WechatCpUser::new(&client);
+        assert!(user.authenticate("zhangsan").await.is_ok());
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_call_custom_path() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","userid":"zhangsan"}"#),
+            MockResponse::json(r#"{"errcode":60111,"errmsg":"userid not found"}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("call_custom_corp", "secret").base_url(&server.url);
+        let v = client.call::<()>("/cgi-bin/user/get", Method::Get, vec![("userid".to_string(), "zhangsan".to_string())], None).await.unwrap();
+        assert_eq!(v["userid"], "zhangsan");
+        let err = client.call("/cgi-bin/user/delete", Method::Post, vec![], Some(serde_json::json!({"userid": "lisi"}))).await.unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "60111"));
+        let requests = server.requests();
+        assert!(requests[1].starts_with("GET /cgi-bin/user/get?userid=zhangsan&access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[2].starts_with("POST /cgi-bin/user/delete?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[2].ends_with(r#"{"userid":"lisi"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_hit_and_expiry() {
+        let list = r#"{"errcode":0,"errmsg":"ok","department":[{"id":2,"name":"广州研发中心","parentid":1,"order":10}]}"#;
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(list),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","department":[]}"#),
+            MockResponse::json(r#"{"errcode":60123,"errmsg":"invalid party id"}"#),
+            MockResponse::json(list),
+            MockResponse::json(list),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("response_cache_corp", "secret").base_url(&server.url)
+            .cache_policy(CachePolicy::new("/cgi-bin/department/list", Duration::from_millis(300)));
+        assert_eq!(client.department().list(None).await.unwrap().department.len(), 1);
+        // 命中缓存，不再请求
+        assert_eq!(client.department().list(None).await.unwrap().department.len(), 1);
+        // 查询参数不同的请求单独缓存
+        assert!(client.department().list(Some(3)).await.unwrap().department.is_empty());
+        // 失败的响应不缓存
+        assert!(client.department().list(Some(4)).await.is_err());
+        assert_eq!(server.requests().len(), 4);
+        // 跳过缓存
+        assert_eq!(client.no_cache().department().list(None).await.unwrap().department.len(), 1);
+        assert_eq!(server.requests().len(), 5);
+        assert!(client.department().list(None).await.is_ok());
+        assert_eq!(server.requests().len(), 5);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(client.department().list(None).await.is_ok());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 6);
+        assert!(requests[5].starts_with("GET /cgi-bin/department/list?access_token=ACCESS_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_invalidated_by_update() {
+        let user = r#"{"errcode":0,"errmsg":"ok","userid":"zhangsan","name":"张三"}"#;
+        let ok = r#"{"errcode":0,"errmsg":"ok"}"#;
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(user),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","userid":"lisi","name":"李四"}"#),
+            MockResponse::json(ok),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","userid":"zhangsan","name":"张三丰"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","department":[]}"#),
+            MockResponse::json(ok),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","department":[]}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("response_cache_update_corp", "secret").base_url(&server.url)
+            .cache_policy(CachePolicy::new("/cgi-bin/user/get", Duration::from_secs(60)))
+            .cache_policy(CachePolicy::new("/cgi-bin/department/*", Duration::from_secs(60)));
+        assert_eq!(client.user().get_by_id("zhangsan", "").await.unwrap().name.as_deref(), Some("张三"));
+        assert_eq!(client.user().get_by_id("lisi", "").await.unwrap().name.as_deref(), Some("李四"));
+        let req = serde_json::from_value::<WechatCpUserInfo>(serde_json::json!({"userid": "zhangsan", "name": "张三丰"})).unwrap();
+        client.user().update(req).await.unwrap();
+        // 只有被更新的成员需要重新获取
+        assert_eq!(client.user().get_by_id("zhangsan", "").await.unwrap().name.as_deref(), Some("张三丰"));
+        assert_eq!(client.user().get_by_id("zhangsan", "").await.unwrap().name.as_deref(), Some("张三丰"));
+        assert_eq!(client.user().get_by_id("lisi", "").await.unwrap().name.as_deref(), Some("李四"));
+        assert_eq!(server.requests().len(), 5);
+
+        client.department().list(None).await.unwrap();
+        client.department().delete(3).await.unwrap();
+        client.department().list(None).await.unwrap();
+        client.department().list(None).await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 8);
+        assert!(requests[3].starts_with("POST /cgi-bin/user/update?access_token=ACCESS_TOKEN"));
+        assert!(requests[4].starts_with("GET /cgi-bin/user/get?userid=zhangsan&access_token=ACCESS_TOKEN"));
+        assert!(requests[7].starts_with("GET /cgi-bin/department/list?access_token=ACCESS_TOKEN"));
+
+        client.invalidate_cache_prefix("/cgi-bin/user").unwrap();
+        assert!(client.user().get_by_id("lisi", "").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_jsapi_and_agent_config_signature() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","ticket":"CORP_TICKET","expires_in":7200}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","ticket":"AGENT_TICKET","expires_in":3600}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","ticket":"AGENT_TICKET_2","expires_in":7200}"#),
+        ]).await;
+        let client = WechatCpClient::<SimpleStorage>::new("agent_config_corp", "secret").base_url(&server.url).agent_id(1000002);
+        let url = "https://example.com/page?a=1";
+        let config = client.create_jsapi_signature(url).await.unwrap();
+        assert_eq!(config.app_id, "agent_config_corp");
+        assert_eq!(config.signature, WechatCrypto::get_sha1_sign(&format!("jsapi_ticket=CORP_TICKET&noncestr={}&timestamp={}&url={}", config.nonce_str, config.timestamp, url)));
+        let agent_config = client.create_agent_config_signature(url).await.unwrap();
+        assert_eq!(agent_config.corpid, "agent_config_corp");
+        assert_eq!(agent_config.agentid, "1000002");
+        assert_eq!(agent_config.signature, WechatCrypto::get_sha1_sign(&format!("jsapi_ticket=AGENT_TICKET&noncestr={}&timestamp={}&url={}", agent_config.nonce_str, agent_config.timestamp, url)));
+        let v = serde_json::to_value(&agent_config).unwrap();
+        assert!(v["nonceStr"].is_string());
+
+        // 两种ticket分开缓存
+        let session = client.inner.client.session();
+        assert_eq!(session.get::<_, String>("agent_config_corp_jsapi_ticket_cp", None).unwrap().as_deref(), Some("CORP_TICKET"));
+        assert_eq!(session.get::<_, String>("agent_config_corp_1000002_agent_jsapi_ticket_cp", None).unwrap().as_deref(), Some("AGENT_TICKET"));
+        assert_eq!(client.get_jsapi_ticket(false).await.unwrap(), "CORP_TICKET");
+        assert_eq!(client.get_agent_jsapi_ticket(false).await.unwrap(), "AGENT_TICKET");
+        assert_eq!(server.requests().len(), 3);
+        // 刷新应用的ticket不影响企业的ticket
+        assert_eq!(client.get_ticket(CpTicketType::AgentConfig, true).await.unwrap(), "AGENT_TICKET_2");
+        assert_eq!(client.get_jsapi_ticket(false).await.unwrap(), "CORP_TICKET");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].starts_with("GET /cgi-bin/get_jsapi_ticket?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[2].starts_with("GET /cgi-bin/ticket/get?type=agent_config&access_token=ACCESS_TOKEN HTTP/1.1"));
+    }
 }
@@ -7,20 +7,26 @@ use crate::wechat::cp::method::{CpDepartmentMethod, WechatCpMethod};
 
 /// 部门管理
 #[derive(Debug, Clone)]
-pub struct WechatCpTpDepartment<'a, T: SessionStore> {
-    client: &'a WechatCpTpClient<T>,
+pub struct WechatCpTpDepartment<T: SessionStore> {
+    client: WechatCpTpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTpDepartment<'a, T> {
+impl<T: SessionStore> WechatCpTpDepartment<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpDepartment<T> {
+    pub fn from_client(client: WechatCpTpClient<T>) -> WechatCpTpDepartment<T> {
         WechatCpTpDepartment {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.department()`")]
+    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpDepartment<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 部门管理接口 - 创建部门.
     /// 最多支持创建500个部门
@@ -2,25 +2,30 @@ use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 
 use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCpTpClient};
-use crate::wechat::cp::constants::{PROVIDER_ACCESS_TOKEN};
 use crate::wechat::cp::method::{CpLicenseMethod, WechatCpMethod};
 
 /// 服务商接口调用许可相关
 #[derive(Debug, Clone)]
-pub struct WechatCpTpLicense<'a, T: SessionStore> {
-    client: &'a WechatCpTpClient<T>,
+pub struct WechatCpTpLicense<T: SessionStore> {
+    client: WechatCpTpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
+impl<T: SessionStore> WechatCpTpLicense<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpLicense<T> {
+    pub fn from_client(client: WechatCpTpClient<T>) -> WechatCpTpLicense<T> {
         WechatCpTpLicense {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.license()`")]
+    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpLicense<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 下单购买帐号
     /// 服务商下单为企业购买新的帐号，可以同时购买基础帐号与互通帐号。
@@ -78,8 +83,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
         if let Some(end) = end {
             req["end_time"] = end.into();
         }
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::ListOrder), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::ListOrder), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseOrderListResp>(v)
     }
 
@@ -94,8 +98,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
         let mut req = json!({
             "order_id": order_id,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::GetOrder), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::GetOrder), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseOrderInfoResp>(v)
     }
 
@@ -113,8 +116,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "cursor": cursor,
             "limit": limit,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::ListOrderCount), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::ListOrderCount), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseOrderAccountListResponse>(v)
     }
 
@@ -129,8 +131,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "corpid": corp_id,
             "order_id": order_id,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        self.client.post(WechatCpMethod::License(CpLicenseMethod::CancelOrder), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        self.client.post(WechatCpMethod::License(CpLicenseMethod::CancelOrder), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
 
@@ -146,8 +147,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "corpid": corp_id,
             "userid": user_id,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        self.client.post(WechatCpMethod::License(CpLicenseMethod::ActiveAccount), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<WechatCommonResponse>()
+        self.client.post(WechatCpMethod::License(CpLicenseMethod::ActiveAccount), vec![], req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
     /// <pre>
@@ -161,8 +161,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "corp_id": corp_id,
             "active_list": active_accounts,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::BatchActiveAccount), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::BatchActiveAccount), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseOrderAccountListResponse>(v)
     }
 
@@ -176,8 +175,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "active_code": code,
             "corpid": corp_id,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::GetActiveInfoByCode), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::GetActiveInfoByCode), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseCodeInfoResponse>(v)
     }
 
@@ -192,8 +190,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "active_code_list": codes,
             "corpid": corp_id,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::BatchGetActiveInfoByCode), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::BatchGetActiveInfoByCode), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseBatchCodeInfoResponse>(v)
     }
 
@@ -209,8 +206,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "corpid": corp_id,
             "limit": limit,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::ListActivedAccount), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::ListActivedAccount), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseCorpAccountListResponse>(v)
     }
 
@@ -225,8 +221,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "corpid": corp_id,
             "user_id": user_id,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::GetActiveInfoByUser), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::GetActiveInfoByUser), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseActiveInfoByUserResponse>(v)
     }
 
@@ -241,8 +236,7 @@ impl<'a, T: SessionStore> WechatCpTpLicense<'a, T> {
             "corpid": corp_id,
             "transfer_list": transfers,
         });
-        let access_token = self.client.get_wechat_provider_token().await?;
-        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::BatchTransferLicense), vec![(PROVIDER_ACCESS_TOKEN.to_string(), access_token)], req, RequestType::Json).await?.json::<Value>()?;
+        let v = self.client.post(WechatCpMethod::License(CpLicenseMethod::BatchTransferLicense), vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpLicenseBatchTransferResponse>(v)
     }
 
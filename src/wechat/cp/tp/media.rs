@@ -10,20 +10,26 @@ use crate::wechat::cp::method::{CpMediaMethod, WechatCpMethod};
 
 
 #[derive(Debug, Clone)]
-pub struct WechatCpTpMedia<'a, T: SessionStore> {
-    client: &'a WechatCpTpClient<T>,
+pub struct WechatCpTpMedia<T: SessionStore> {
+    client: WechatCpTpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTpMedia<'a, T> {
+impl<T: SessionStore> WechatCpTpMedia<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpMedia<T> {
+    pub fn from_client(client: WechatCpTpClient<T>) -> WechatCpTpMedia<T> {
         WechatCpTpMedia {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.media()`")]
+    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpMedia<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 上传多媒体文件.
     /// 上传的多媒体文件有格式和大小限制，如下：
@@ -1,11 +1,15 @@
+use std::sync::Arc;
+
 
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, WechatCrypto, current_timestamp, LabraError, JsapiTicket, JsapiSignature, get_timestamp, get_nonce_str, APIClient, WechatRequest, LabraResponse, LabraRequest, SimpleStorage, WechatCpProviderToken};
-use crate::wechat::cp::constants::{ACCESS_TOKEN, ACCESS_TOKEN_KEY, AUTH_URL_INSTALL, SUITE_ACCESS_TOKEN, TYPE};
-use crate::wechat::cp::method::WechatCpMethod;
-use crate::wechat::cp::AccessTokenResponse;
+use crate::{session::SessionStore, MetricsRecorder, DebugRecorder, DebugRecord, request::{RequestType, RequestMethod}, WechatCommonResponse, LabradorResult, WechatCrypto, current_timestamp, LabraError, JsapiTicket, JsapiSignature, nonce_str, APIClient, WechatRequest, LabraResponse, LabraRequest, SimpleStorage, WechatCpProviderToken};
+use crate::wechat::WECHAT_CP_BASE_URL;
+use crate::wechat::cp::constants::{ACCESS_TOKEN, ACCESS_TOKEN_KEY, ACCESS_TOKEN_EXPIRES_KEY, AUTH_URL_INSTALL, PERMANENT_CODE_KEY, SUITE_ACCESS_TOKEN_EXPIRES_KEY, SUITE_ACCESS_TOKEN_KEY, SUITE_TICKET_EXPIRES_KEY, SUITE_TICKET_KEY, TYPE};
+use crate::wechat::cp::method::{CpTokenParam, WechatCpMethod};
+use crate::wechat::cp::{AccessTokenResponse, WechatCpClient};
+use crate::serde_helper::string_or_number;
 
 mod tag;
 mod license;
@@ -23,9 +27,25 @@ pub use order::*;
 
 
 /// 企业微信第三方应用API
+///
+/// <pre>
+/// 以服务商身份管理各级凭证，依赖关系为：
+/// suite_ticket（回调推送，通过`set_suite_ticket`保存） → suite_access_token → 授权企业的access_token（需永久授权码）
+/// provider_access_token（服务商secret）单独获取，用于接口许可、订单等服务商接口
+/// 凭证均缓存在SessionStore中，suite相关的key以suite_id区分，见`key_with_prefix`
+/// </pre>
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct WechatCpTpClient<T: SessionStore> {
+    inner: Arc<WechatCpTpClientInner<T>>,
+}
+
+/// 服务商客户端，即第三方应用客户端
+pub type WechatCpProviderClient<T> = WechatCpTpClient<T>;
+
+/// 客户端配置与会话存储，克隆客户端时共享
+#[derive(Debug, Clone)]
+struct WechatCpTpClientInner<T: SessionStore> {
     token: Option<String>,
     /// 企微服务商企业ID，来自于企微配置
     corp_id: String,
@@ -47,86 +67,155 @@ impl<T: SessionStore> WechatCpTpClient<T> {
 
     fn from_client(client: APIClient<T>) -> WechatCpTpClient<T> {
         WechatCpTpClient {
-            corp_id: client.app_key.to_owned(),
-            corp_secret: client.secret.to_owned(),
-            token: None,
-            aes_key: None,
-            agent_id: None,
-            suite_id: None,
-            suite_secret: None,
-            client,
-            provider_secret: None
+            inner: Arc::new(WechatCpTpClientInner {
+                corp_id: client.app_key.to_owned(),
+                corp_secret: client.secret.to_owned(),
+                token: None,
+                aes_key: None,
+                agent_id: None,
+                suite_id: None,
+                suite_secret: None,
+                client,
+                provider_secret: None
+            }),
         }
     }
 
     pub fn aes_key(mut self, aes_key: &str) -> Self {
-        self.aes_key = aes_key.to_string().into();
+        Arc::make_mut(&mut self.inner).aes_key = aes_key.to_string().into();
         self
     }
 
     pub fn token(mut self, token: &str) -> Self {
-        self.token = token.to_string().into();
+        Arc::make_mut(&mut self.inner).token = token.to_string().into();
         self
     }
 
     pub fn agent_id(mut self, agent_id: i32) -> Self {
-        self.agent_id = agent_id.into();
+        Arc::make_mut(&mut self.inner).agent_id = agent_id.into();
         self
     }
 
     pub fn provider_secret(mut self, provider_secret: &str) -> Self {
-        self.provider_secret = provider_secret.to_string().into();
+        Arc::make_mut(&mut self.inner).provider_secret = provider_secret.to_string().into();
         self
     }
 
     pub fn suite_id(mut self, suite_id: &str) -> Self {
-        self.suite_id = suite_id.to_string().into();
+        Arc::make_mut(&mut self.inner).suite_id = suite_id.to_string().into();
         self
     }
 
     pub fn suite_secret(mut self, suite_secret: &str) -> Self {
-        self.suite_secret = suite_secret.to_string().into();
+        Arc::make_mut(&mut self.inner).suite_secret = suite_secret.to_string().into();
         self
     }
 
+    /// 缓存key，同一服务商下的多个应用互不影响
     fn key_with_prefix(&self, key: &str) -> String {
-        format!("cp:{}:{}", self.suite_id.to_owned().unwrap_or_default(), key)
+        format!("cp:{}:{}", self.inner.suite_id.to_owned().unwrap_or_default(), key)
     }
 
     /// get the wechat client
     pub fn new<S: Into<String>>(crop_id: S, crop_secret: S) -> WechatCpTpClient<SimpleStorage> {
-        let client = APIClient::<SimpleStorage>::from_session(crop_id.into(), crop_secret.into(), "https://qyapi.weixin.qq.com", SimpleStorage::new());
+        let client = APIClient::<SimpleStorage>::from_session(crop_id.into(), crop_secret.into(), WECHAT_CP_BASE_URL, SimpleStorage::new());
         WechatCpTpClient::<SimpleStorage>::from_client(client)
     }
 
     /// get the wechat client
     pub fn from_session<S: Into<String>>(crop_id: S, crop_secret: S, session: T) -> WechatCpTpClient<T> {
-        let client = APIClient::from_session(crop_id.into(), crop_secret.into(), "https://qyapi.weixin.qq.com", session);
+        let client = APIClient::from_session(crop_id.into(), crop_secret.into(), WECHAT_CP_BASE_URL, session);
         Self::from_client(client)
     }
 
+    /// 替换接口域名（默认[`WECHAT_CP_BASE_URL`]），如私有网关、就近接入域名
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().api_path(base_url);
+        self
+    }
+
+    /// 备用域名，连接失败时按顺序切换
+    pub fn fallback_urls(mut self, fallback_urls: Vec<&str>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().fallback_paths(fallback_urls);
+        self
+    }
+
+    /// 请求指标记录，每次请求（含切换备用域名的重试）都会回调
+    pub fn metrics_recorder(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().metrics_recorder(metrics);
+        self
+    }
+
+    /// 开启请求调试记录，在内存中保留最近的请求/响应报文，URL中的凭证会被隐去
+    pub fn debug_recorder(mut self, debug: Arc<DebugRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().debug_recorder(debug);
+        self
+    }
+
+    /// 当前保留的请求调试记录，未开启时为空
+    pub fn debug_records(&self) -> Vec<DebugRecord> {
+        self.inner.client.debug_records()
+    }
+
     /// 授权企业的access token相关
     fn get_access_token(&self, auth_corp_id: &str) -> String {
-        let session = self.client.session();
+        let session = self.inner.client.session();
         session.get::<_,String>(self.key_with_prefix(auth_corp_id) + ACCESS_TOKEN_KEY, None).unwrap_or(None).unwrap_or_default()
     }
 
+    fn corp_token_expires_key(&self, auth_corp_id: &str) -> String {
+        self.key_with_prefix(auth_corp_id) + ACCESS_TOKEN_EXPIRES_KEY
+    }
+
+    fn permanent_code_key(&self, auth_corp_id: &str) -> String {
+        self.key_with_prefix(auth_corp_id) + PERMANENT_CODE_KEY
+    }
+
+    /// <pre>
+    /// 保存授权企业的永久授权码
+    /// 通过`get_permanent_code`获取时会自动保存，已持久化的永久授权码可在启动时通过本方法加载
+    /// </pre>
+    pub fn set_permanent_code(&self, auth_corp_id: &str, permanent_code: &str) -> LabradorResult<()> {
+        self.inner.client.session().set(self.permanent_code_key(auth_corp_id), permanent_code, None)
+    }
+
+    /// 授权企业的永久授权码
+    pub fn permanent_code(&self, auth_corp_id: &str) -> LabradorResult<Option<String>> {
+        let permanent_code = self.inner.client.session().get::<_, String>(self.permanent_code_key(auth_corp_id), None)?;
+        Ok(permanent_code.filter(|code| !code.is_empty()))
+    }
+
+    /// <pre>
+    /// 以授权企业身份调用接口的客户端
+    /// 共用当前客户端的域名、连接池与会话存储，access_token过期后通过suite_access_token和永久授权码自动换取
+    /// 需先通过`get_permanent_code`或`set_permanent_code`保存该企业的永久授权码
+    /// </pre>
+    pub fn corp_client(&self, auth_corp_id: &str) -> LabradorResult<WechatCpClient<T>> {
+        let permanent_code = self.permanent_code(auth_corp_id)?
+            .ok_or_else(|| LabraError::ApiError(format!("permanent code of {} not found", auth_corp_id)))?;
+        Ok(WechatCpClient::from_suite(self.inner.client.to_owned(), self.clone(), auth_corp_id, &permanent_code))
+    }
+
     /// <pre>
     /// 验证推送过来的消息的正确性
     /// 详情请见: <a href="https://work.weixin.qq.com/api/doc#90000/90139/90968/消息体签名校验">文档</a>
     /// </pre>
     pub fn check_signature(&self, signature: &str, timestamp: i64, nonce: &str, data: &str) -> LabradorResult<bool> {
-        let crp = WechatCrypto::new(&self.aes_key.to_owned().unwrap_or_default());
-        let _ = crp.check_signature(signature, timestamp, nonce, data,&self.token.to_owned().unwrap_or_default())?;
+        let crp = WechatCrypto::new(&self.inner.aes_key.to_owned().unwrap_or_default());
+        let _ = crp.check_signature(signature, timestamp, nonce, data,&self.inner.token.to_owned().unwrap_or_default())?;
         Ok(true)
     }
 
     /// 获得suite_ticket,不强制刷新suite_ticket
     /// 由微信服务器推送
     pub fn get_suite_ticket(&self) -> LabradorResult<String> {
-        let session = self.client.session();
-        let token_key = format!("{}_suite_ticket_key_cp", self.corp_id);
-        let expires_key = format!("{}_suite_ticket_expires_at_cp", self.corp_id);
+        let session = self.inner.client.session();
+        let token_key = self.key_with_prefix(SUITE_TICKET_KEY);
+        let expires_key = self.key_with_prefix(SUITE_TICKET_EXPIRES_KEY);
         let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
@@ -140,9 +229,9 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 由微信服务器推送
     pub fn set_suite_ticket_expire(&self, suite_ticket: &str, expire_second: i64) -> LabradorResult<()> {
         let expires_at = current_timestamp() + expire_second;
-        let session = self.client.session();
-        let token_key = format!("{}_suite_ticket_key_cp", self.corp_id);
-        let expires_key = format!("{}_suite_ticket_expires_at_cp", self.corp_id);
+        let session = self.inner.client.session();
+        let token_key = self.key_with_prefix(SUITE_TICKET_KEY);
+        let expires_key = self.key_with_prefix(SUITE_TICKET_EXPIRES_KEY);
         session.set(token_key, suite_ticket, Some(expire_second as usize))?;
         session.set(expires_key, expires_at, Some(expire_second as usize))?;
         Ok(())
@@ -177,20 +266,20 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 详情请见: <a href="https://work.weixin.qq.com/api/doc#90001/90143/90600">文档</a>
     /// </pre>
     pub async fn get_suite_access_token_force(&self, force_refresh: bool) -> LabradorResult<String> {
-        let session = self.client.session();
-        let token_key = format!("{}_suite_access_token_cp", self.corp_id);
-        let expires_key = format!("{}_suite_access_token_expires_at_cp", self.corp_id);
+        let session = self.inner.client.session();
+        let token_key = self.key_with_prefix(SUITE_ACCESS_TOKEN_KEY);
+        let expires_key = self.key_with_prefix(SUITE_ACCESS_TOKEN_EXPIRES_KEY);
         let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
         if expires_at <= timestamp || force_refresh {
             let suite_ticket = self.get_suite_ticket()?;
             let req = json!({
-                "suite_id": self.suite_id,
-                "suite_secret": self.suite_secret,
+                "suite_id": self.inner.suite_id,
+                "suite_secret": self.inner.suite_secret,
                 "suite_ticket": suite_ticket
             });
-            let result = self.client.post(WechatCpMethod::GetSuiteToken, vec![], req, RequestType::Json).await?.json::<WechatCpSuiteAccessTokenResponse>()?;
+            let result = self.inner.client.post(WechatCpMethod::GetSuiteToken, vec![], req, RequestType::Json).await?.json::<WechatCpSuiteAccessTokenResponse>()?;
             let token = result.suite_access_token;
             let expires_in = result.expires_in;
             // 预留200秒的时间
@@ -216,14 +305,14 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 获取应用的 jsapi ticket， 支持强制刷新
     /// </pre>
     pub async fn get_suite_jsapi_ticket_force(&self, auth_corp_id: &str, force_refresh: bool) -> LabradorResult<String> {
-        let mut session = self.client.session();
-        let ticket_key = format!("{}_suite_jsapi_ticket_cp", self.corp_id);
-        let expires_key = format!("{}_suite_jsapi_ticket_expires_at_cp", self.corp_id);
+        let mut session = self.inner.client.session();
+        let ticket_key = format!("{}_suite_jsapi_ticket_cp", self.inner.corp_id);
+        let expires_key = format!("{}_suite_jsapi_ticket_expires_at_cp", self.inner.corp_id);
         let ticket: String = session.get(&ticket_key, Some("".to_string()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
         if expires_at <= timestamp || force_refresh {
-            let res = self.client.get(WechatCpMethod::GetSuiteJsapiTicket, vec![(TYPE.to_string(), "agent_config".to_string()), (ACCESS_TOKEN.to_string(), self.get_access_token(auth_corp_id))], RequestType::Json).await?.json::<JsapiTicket>()?;
+            let res = self.inner.client.get(WechatCpMethod::GetSuiteJsapiTicket, vec![(TYPE.to_string(), "agent_config".to_string()), (ACCESS_TOKEN.to_string(), self.get_access_token(auth_corp_id))], RequestType::Json).await?.json::<JsapiTicket>()?;
             let ticket = res.ticket;
             let expires_in = res.expires_in;
             // 预留200秒的时间
@@ -249,14 +338,14 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 获取授权企业的 jsapi ticket， 支持强制刷新
     /// </pre>
     pub async fn get_auth_corp_jsapi_ticket_force(&self, auth_corp_id: &str, force_refresh: bool) -> LabradorResult<String> {
-        let mut session = self.client.session();
-        let ticket_key = format!("{}_auth_corp_jsapi_ticket_cp", self.corp_id);
-        let expires_key = format!("{}_auth_corp_jsapi_ticket_expires_at_cp", self.corp_id);
+        let mut session = self.inner.client.session();
+        let ticket_key = format!("{}_auth_corp_jsapi_ticket_cp", self.inner.corp_id);
+        let expires_key = format!("{}_auth_corp_jsapi_ticket_expires_at_cp", self.inner.corp_id);
         let ticket: String = session.get(&ticket_key, Some("".to_string()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
         if expires_at <= timestamp || force_refresh {
-            let res = self.client.get(WechatCpMethod::GetJsapiTicket, vec![(ACCESS_TOKEN.to_string(), self.get_access_token(auth_corp_id))], RequestType::Json).await?.json::<JsapiTicket>()?;
+            let res = self.inner.client.get(WechatCpMethod::GetJsapiTicket, vec![(ACCESS_TOKEN.to_string(), self.get_access_token(auth_corp_id))], RequestType::Json).await?.json::<JsapiTicket>()?;
             let ticket = res.ticket;
             let expires_in = res.expires_in;
             // 预留200秒的时间
@@ -282,9 +371,9 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 获取企业凭证, 支持强制刷新
     /// </pre>
     pub async fn get_corp_token_force(&self, auth_corpid: &str, permanent_code: &str, force_refresh: bool) -> LabradorResult<AccessTokenResponse> {
-        let session = self.client.session();
-        let token_key = format!("{}_corp_access_token_cp", auth_corpid);
-        let expires_key = format!("{}_corp_access_token_expires_at_cp", auth_corpid);
+        let session = self.inner.client.session();
+        let token_key = self.key_with_prefix(auth_corpid) + ACCESS_TOKEN_KEY;
+        let expires_key = self.corp_token_expires_key(auth_corpid);
         let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
@@ -294,7 +383,7 @@ impl<T: SessionStore> WechatCpTpClient<T> {
                 "auth_corpid": auth_corpid,
                 "permanent_code": permanent_code,
             });
-            let result = self.client.post(WechatCpMethod::GetCorpToken, vec![], req, RequestType::Json).await?.json::<AccessTokenResponse>()?;
+            let result = self.post(WechatCpMethod::GetCorpToken, vec![], req, RequestType::Json).await?.json::<AccessTokenResponse>()?;
             let token = result.access_token.to_string();
             let expires_in = result.expires_in;
             // 预留200秒的时间
@@ -303,7 +392,7 @@ impl<T: SessionStore> WechatCpTpClient<T> {
             session.set(&expires_key, expires_at, Some(expires_in as usize));
             Ok(result)
         } else {
-            Ok(AccessTokenResponse{ access_token: token.to_string(), expires_in: expires_at })
+            Ok(AccessTokenResponse{ access_token: token.to_string(), expires_in: expires_at - timestamp })
         }
     }
 
@@ -311,19 +400,19 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 获取服务商providerToken
     /// </pre>
     pub async fn get_wechat_provider_token(&self) -> LabradorResult<String> {
-        let session = self.client.session();
-        let token_key = format!("{}_provider_access_token_cp", self.corp_id);
-        let expires_key = format!("{}_provider_access_token_expires_at_cp", self.corp_id);
+        let session = self.inner.client.session();
+        let token_key = format!("{}_provider_access_token_cp", self.inner.corp_id);
+        let expires_key = format!("{}_provider_access_token_expires_at_cp", self.inner.corp_id);
         let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
         if expires_at <= timestamp {
             let suite_ticket = self.get_suite_ticket()?;
             let req = json!({
-                "corpid": self.corp_id,
-                "provider_secret": self.provider_secret,
+                "corpid": self.inner.corp_id,
+                "provider_secret": self.inner.provider_secret,
             });
-            let result = self.client.post(WechatCpMethod::GetProviderToken, vec![], req, RequestType::Json).await?.json::<WechatCpProviderToken>()?;
+            let result = self.inner.client.post(WechatCpMethod::GetProviderToken, vec![], req, RequestType::Json).await?.json::<WechatCpProviderToken>()?;
             let token = result.provider_access_token.to_string();
             let expires_in = result.expires_in;
             // 预留200秒的时间
@@ -336,6 +425,24 @@ impl<T: SessionStore> WechatCpTpClient<T> {
         }
     }
 
+    /// <pre>
+    /// 获取企业永久授权码
+    /// 用临时授权码换取永久授权码，并保存永久授权码与返回的access_token，之后可通过`corp_client`调用该企业的接口
+    /// 详情请见: <a href="https://developer.work.weixin.qq.com/document/path/90603">文档</a>
+    /// </pre>
+    pub async fn get_permanent_code(&self, auth_code: &str) -> LabradorResult<WechatCpThirdPermanentCodeInfo> {
+        let info = self.get_permanent_code_info(auth_code).await?;
+        let auth_corp_id = info.auth_corp_info.corpid.to_owned();
+        self.set_permanent_code(&auth_corp_id, &info.permanent_code)?;
+        if !info.access_token.is_empty() {
+            let session = self.inner.client.session();
+            let expires_at = current_timestamp() + info.expires_in - 200;
+            session.set(self.key_with_prefix(&auth_corp_id) + ACCESS_TOKEN_KEY, info.access_token.to_owned(), Some(info.expires_in as usize))?;
+            session.set(self.corp_token_expires_key(&auth_corp_id), expires_at, Some(info.expires_in as usize))?;
+        }
+        Ok(info)
+    }
+
     /// <pre>
     /// 获取企业永久授权码信息
     /// </pre>
@@ -343,7 +450,7 @@ impl<T: SessionStore> WechatCpTpClient<T> {
         let req = json!({
             "auth_code": auth_code,
         });
-        let result = self.client.post(WechatCpMethod::GetPermanentCode, vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let result = self.post(WechatCpMethod::GetPermanentCode, vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpThirdPermanentCodeInfo>(result)
     }
 
@@ -351,8 +458,8 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     /// 获取预授权链接
     /// </pre>
     pub async fn get_pre_auth_url(&self, redirect_uri: &str, state: Option<&str>) -> LabradorResult<String> {
-        let result = self.client.get(WechatCpMethod::GetPreAuthCode, vec![], RequestType::Json).await?.json::<WechatCpThirdPreauthCode>()?;
-        let mut pre_auth_url = format!("{}?suite_id={}&pre_auth_code={}&redirect_uri={}", AUTH_URL_INSTALL, self.suite_id.to_owned().unwrap_or_default(), result.pre_auth_code, urlencoding::encode(redirect_uri));
+        let result = self.get(WechatCpMethod::GetPreAuthCode, vec![], RequestType::Json).await?.json::<WechatCpThirdPreauthCode>()?;
+        let mut pre_auth_url = format!("{}?suite_id={}&pre_auth_code={}&redirect_uri={}", AUTH_URL_INSTALL, self.inner.suite_id.to_owned().unwrap_or_default(), result.pre_auth_code, urlencoding::encode(redirect_uri));
         if let Some(state) = state {
             pre_auth_url.push_str(&format!("&state={}", state));
         }
@@ -367,7 +474,7 @@ impl<T: SessionStore> WechatCpTpClient<T> {
            "auth_corpid": auth_corp_id,
            "permanent_code": permanent_code
         });
-        let result = self.client.post(WechatCpMethod::GetAuthInfo, vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let result = self.post(WechatCpMethod::GetAuthInfo, vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpThirdAuthInfo>(result)
     }
 
@@ -390,8 +497,8 @@ impl<T: SessionStore> WechatCpTpClient<T> {
     }
 
     fn created_wechat_jsapi_signature(&self, url: &str, auth_corp_id: &str, jsapi_ticket: &str) -> JsapiSignature {
-        let timestamp = get_timestamp() / 1000;
-        let noncestr = get_nonce_str();
+        let timestamp = current_timestamp();
+        let noncestr = nonce_str();
         let signature = WechatCrypto::get_sha1_sign(&vec!["jsapi_ticket=".to_string() + &jsapi_ticket,
                                                           "noncestr=".to_string() + &noncestr,
                                                           "timestamp=".to_string() + &timestamp.to_string(),"url=".to_string() + &url].join("&"));
@@ -421,55 +528,69 @@ impl<T: SessionStore> WechatCpTpClient<T> {
         let params = querys.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<(String, String)>>();
         let mut req = LabraRequest::<B>::new().url(request.get_api_method_name())
             .params(params).method(request.get_request_method()).req_type(request.get_request_type()).body(request.get_request_body::<B>());
-        self.client.request(req).await
+        if let Some(timeout) = request.get_timeout() {
+            req = req.timeout(timeout);
+        }
+        self.inner.client.request(req).await
     }
 
     /// 发送POST请求
     async fn post<D: Serialize>(&self, method: WechatCpMethod, mut querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        if method.need_token() {
-            let token = self.get_suite_access_token_force(false).await?;
-            querys.push((SUITE_ACCESS_TOKEN.to_string(), token));
-        }
-        self.client.post(method, querys, data, request_type).await
+        let headers = self.attach_token(&method, &mut querys).await?;
+        self.inner.client.post_with_headers(method, querys, headers, data, request_type).await
     }
 
     /// 发送GET请求
     async fn get(&self, method: WechatCpMethod, mut params: Vec<(String, String)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        if method.need_token() {
-            let token = self.get_suite_access_token_force(false).await?.to_string();
-            params.push((SUITE_ACCESS_TOKEN.to_string(), token));
+        let headers = self.attach_token(&method, &mut params).await?;
+        self.inner.client.get_with_headers(method, params, headers, request_type).await
+    }
+
+    /// 按接口的`AuthStyle`附加凭证，返回需要附加的请求头
+    async fn attach_token(&self, method: &WechatCpMethod, params: &mut Vec<(String, String)>) -> LabradorResult<Vec<(String, String)>> {
+        if !method.need_token() {
+            return Ok(vec![]);
+        }
+        let (key, token) = self.token_query(method).await?;
+        Ok(method.auth_style().attach(&key, &token, params))
+    }
+
+    /// 接口所需的凭证参数，服务商接口使用provider_access_token，其余使用suite_access_token
+    async fn token_query(&self, method: &WechatCpMethod) -> LabradorResult<(String, String)> {
+        match method.token_param() {
+            CpTokenParam::ProviderAccessToken => Ok((CpTokenParam::ProviderAccessToken.key().to_string(), self.get_wechat_provider_token().await?)),
+            _ => Ok((CpTokenParam::SuiteAccessToken.key().to_string(), self.get_suite_access_token_force(false).await?)),
         }
-        self.client.get(method, params, request_type).await
     }
 
     /// 部门
     pub fn department(&self) -> WechatCpTpDepartment<T> {
-        WechatCpTpDepartment::new(self)
+        WechatCpTpDepartment::from_client(self.clone())
     }
 
     /// 接口调用许可
     pub fn license(&self) -> WechatCpTpLicense<T> {
-        WechatCpTpLicense::new(self)
+        WechatCpTpLicense::from_client(self.clone())
     }
 
     /// 媒体
     pub fn media(&self) -> WechatCpTpMedia<T> {
-        WechatCpTpMedia::new(self)
+        WechatCpTpMedia::from_client(self.clone())
     }
 
     /// 订单
     pub fn order(&self) -> WechatCpTpOrder<T> {
-        WechatCpTpOrder::new(self)
+        WechatCpTpOrder::from_client(self.clone())
     }
 
     /// 标签
     pub fn tag(&self) -> WechatCpTpTag<T> {
-        WechatCpTpTag::new(self)
+        WechatCpTpTag::from_client(self.clone())
     }
 
     /// 用户
     pub fn user(&self) -> WechatCpTpUser<T> {
-        WechatCpTpUser::new(self)
+        WechatCpTpUser::from_client(self.clone())
     }
 }
 
@@ -478,6 +599,7 @@ impl<T: SessionStore> WechatCpTpClient<T> {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WechatCpSuiteAccessTokenResponse {
     pub suite_access_token: String,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
@@ -494,6 +616,7 @@ pub struct WechatCpThirdPermanentCodeInfo {
     pub auth_user_info: Option<AuthUserInfo>,
     /// 企业当前生效的版本信息
     pub edition_info: Option<EditionInfo>,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
@@ -618,6 +741,7 @@ pub struct Privilege {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WechatCpThirdPreauthCode {
     pub pre_auth_code: String,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
@@ -645,3 +769,73 @@ pub struct DealerCorpInfo {
     pub corpid: Option<String>,
     pub corp_name: Option<String>,
 }
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{LabraError, SimpleStorage, WechatCpProviderClient, WechatCpTpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_token_params() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","suite_access_token":"SUITE_TOKEN","expires_in":7200}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","pre_auth_code":"PRE_AUTH_CODE","expires_in":1200}"#),
+            MockResponse::json(r#"{"provider_access_token":"PROVIDER_TOKEN","expires_in":7200}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let client = WechatCpTpClient::<SimpleStorage>::new("tp_token_param_corp", "secret")
+            .suite_id("SUITE_ID").suite_secret("SUITE_SECRET").provider_secret("PROVIDER_SECRET").base_url(&server.url);
+        client.set_suite_ticket("SUITE_TICKET").unwrap();
+        let url = client.get_pre_auth_url("https://example.com", None).await.unwrap();
+        assert!(url.contains("pre_auth_code=PRE_AUTH_CODE"));
+        client.license().cancel_order("CORP_ID", "ORDER_ID").await.unwrap();
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /cgi-bin/service/get_suite_token HTTP/1.1"));
+        assert!(requests[1].starts_with("GET /cgi-bin/service/get_pre_auth_code?suite_access_token=SUITE_TOKEN HTTP/1.1"));
+        assert!(requests[2].starts_with("POST /cgi-bin/service/get_provider_token HTTP/1.1"));
+        assert!(requests[3].starts_with("POST /cgi-bin/license/cancel_order?provider_access_token=PROVIDER_TOKEN HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_corp_client_token_chain() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","suite_access_token":"SUITE_TOKEN","expires_in":7200}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","access_token":"CORP_TOKEN","expires_in":7200,"permanent_code":"PERMANENT_CODE","auth_corp_info":{"corpid":"AUTH_CORP","corp_name":"授权企业"}}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","access_token":"NEW_CORP_TOKEN","expires_in":7200}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let provider = WechatCpProviderClient::<SimpleStorage>::new("tp_chain_provider", "secret")
+            .suite_id("CHAIN_SUITE").suite_secret("SUITE_SECRET").base_url(&server.url);
+        // 未收到suite_ticket时无法换取suite_access_token
+        assert!(provider.get_suite_access_token().await.is_err());
+        assert!(matches!(provider.corp_client("AUTH_CORP"), Err(LabraError::ApiError(_))));
+        provider.set_suite_ticket("SUITE_TICKET").unwrap();
+        let info = provider.get_permanent_code("AUTH_CODE").await.unwrap();
+        assert_eq!(info.auth_corp_info.corpid, "AUTH_CORP");
+        assert_eq!(provider.permanent_code("AUTH_CORP").unwrap().as_deref(), Some("PERMANENT_CODE"));
+
+        // 永久授权码返回的access_token已缓存
+        let corp = provider.corp_client("AUTH_CORP").unwrap();
+        corp.user().authenticate("zhangsan").await.unwrap();
+        // 过期后通过suite_access_token和永久授权码换取
+        assert_eq!(corp.access_token(true).await.unwrap(), "NEW_CORP_TOKEN");
+        corp.user().authenticate("lisi").await.unwrap();
+
+        // 其他应用不共用凭证
+        let other = WechatCpProviderClient::<SimpleStorage>::new("tp_chain_provider", "secret").suite_id("OTHER_SUITE");
+        assert!(other.get_suite_ticket().is_err());
+        assert_eq!(other.permanent_code("AUTH_CORP").unwrap(), None);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 5);
+        assert!(requests[0].starts_with("POST /cgi-bin/service/get_suite_token HTTP/1.1"));
+        assert!(requests[0].ends_with(r#"{"suite_id":"CHAIN_SUITE","suite_secret":"SUITE_SECRET","suite_ticket":"SUITE_TICKET"}"#));
+        assert!(requests[1].starts_with("POST /cgi-bin/service/get_permanent_code?suite_access_token=SUITE_TOKEN HTTP/1.1"));
+        assert!(requests[2].starts_with("GET /cgi-bin/user/authsucc?userid=zhangsan&access_token=CORP_TOKEN HTTP/1.1"));
+        assert!(requests[3].starts_with("POST /cgi-bin/service/get_corp_token?suite_access_token=SUITE_TOKEN HTTP/1.1"));
+        assert!(requests[3].ends_with(r#"{"auth_corpid":"AUTH_CORP","permanent_code":"PERMANENT_CODE"}"#));
+        assert!(requests[4].starts_with("GET /cgi-bin/user/authsucc?userid=lisi&access_token=NEW_CORP_TOKEN HTTP/1.1"));
+    }
+}
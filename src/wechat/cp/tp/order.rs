@@ -6,20 +6,26 @@ use crate::wechat::cp::method::{WechatCpMethod};
 
 /// 服务商接口调用许可相关
 #[derive(Debug, Clone)]
-pub struct WechatCpTpOrder<'a, T: SessionStore> {
-    client: &'a WechatCpTpClient<T>,
+pub struct WechatCpTpOrder<T: SessionStore> {
+    client: WechatCpTpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTpOrder<'a, T> {
+impl<T: SessionStore> WechatCpTpOrder<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpOrder<T> {
+    pub fn from_client(client: WechatCpTpClient<T>) -> WechatCpTpOrder<T> {
         WechatCpTpOrder {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.order()`")]
+    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpOrder<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     ///  获取订单详情
     /// <p>
@@ -49,7 +55,6 @@ impl<'a, T: SessionStore> WechatCpTpOrder<'a, T> {
         if let Some(end) = end_time {
             req["end_time"] = (end / 1000).into();
         }
-        let access_token = self.client.get_wechat_provider_token().await?;
         let v = self.client.post(WechatCpMethod::GetOrderList, vec![], req, RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<WechatCpTpOrderListGetResponse>(v)
     }
@@ -5,20 +5,26 @@ use crate::wechat::cp::method::{CpTagMethod, WechatCpMethod};
 
 /// 企业微信第三方开发-标签相关
 #[derive(Debug, Clone)]
-pub struct WechatCpTpTag<'a, T: SessionStore> {
-    client: &'a WechatCpTpClient<T>,
+pub struct WechatCpTpTag<T: SessionStore> {
+    client: WechatCpTpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTpTag<'a, T> {
+impl<T: SessionStore> WechatCpTpTag<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpTag<T> {
+    pub fn from_client(client: WechatCpTpClient<T>) -> WechatCpTpTag<T> {
         WechatCpTpTag {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.tag()`")]
+    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpTag<T> {
+        Self::from_client(client.clone())
+    }
+
     /// 创建标签.
     /// <pre>
     /// 请求地址：<a href="https://qyapi.weixin.qq.com/cgi-bin/tag/create?access_token=ACCESS_TOKEN">文档</a>
@@ -7,20 +7,26 @@ use crate::wechat::cp::method::{CpUserMethod, WechatCpMethod};
 
 /// 部门管理
 #[derive(Debug, Clone)]
-pub struct WechatCpTpUser<'a, T: SessionStore> {
-    client: &'a WechatCpTpClient<T>,
+pub struct WechatCpTpUser<T: SessionStore> {
+    client: WechatCpTpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatCpTpUser<'a, T> {
+impl<T: SessionStore> WechatCpTpUser<T> {
 
     #[inline]
-    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpUser<T> {
+    pub fn from_client(client: WechatCpTpClient<T>) -> WechatCpTpUser<T> {
         WechatCpTpUser {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.user()`")]
+    pub fn new(client: &WechatCpTpClient<T>) -> WechatCpTpUser<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     ///   用在二次验证的时候.
     ///   企业在员工验证成功后，调用本方法告诉企业号平台该员工关注成功。
@@ -113,14 +119,14 @@ impl<'a, T: SessionStore> WechatCpTpUser<'a, T> {
     /// 请求地址： https://qyapi.weixin.qq.com/cgi-bin/batch/invite?access_token=ACCESS_TOKEN
     /// 文档地址：https://work.weixin.qq.com/api/doc#12543
     /// </pre>
-    pub async fn invite(&self, userids: Vec<&str>, party_ids: Vec<&str>, tag_ids: Vec<&str>) -> LabradorResult<WxCpTpInviteResponse> {
+    pub async fn invite(&self, userids: Vec<&str>, party_ids: Vec<&str>, tag_ids: Vec<&str>) -> LabradorResult<WechatCpTpInviteResponse> {
         let req = json!({
             "user": userids,
             "party": party_ids,
             "tag": tag_ids,
         });
         let v = self.client.post(WechatCpMethod::User(CpUserMethod::Invite), vec![],req, RequestType::Json).await?.json::<Value>()?;
-        WechatCommonResponse::parse::<WxCpTpInviteResponse>(v)
+        WechatCommonResponse::parse::<WechatCpTpInviteResponse>(v)
     }
 
     /// <pre>
@@ -133,13 +139,13 @@ impl<'a, T: SessionStore> WechatCpTpUser<'a, T> {
     ///
     /// 文档地址：https://work.weixin.qq.com/api/doc#11279
     /// </pre>
-    pub async fn userid_2_openid(&self, userid: &str, agent_id: i32) -> LabradorResult<WxCpTpUseridToOpenidResponse> {
+    pub async fn userid_2_openid(&self, userid: &str, agent_id: i32) -> LabradorResult<WechatCpTpUseridToOpenidResponse> {
         let req = json!({
             "userid": userid,
             "agentid": agent_id,
         });
         let v = self.client.post(WechatCpMethod::User(CpUserMethod::ConvertToOpenid), vec![],req, RequestType::Json).await?.json::<Value>()?;
-        WechatCommonResponse::parse::<WxCpTpUseridToOpenidResponse>(v)
+        WechatCommonResponse::parse::<WechatCpTpUseridToOpenidResponse>(v)
     }
 
     /// <pre>
@@ -197,16 +203,22 @@ impl<'a, T: SessionStore> WechatCpTpUser<'a, T> {
 //----------------------------------------------------------------------------------------------------------------------------
 /// 邀请成员的结果对象类
 #[derive(Debug, Clone,Serialize, Deserialize)]
-pub struct WxCpTpInviteResponse {
+pub struct WechatCpTpInviteResponse {
     pub invaliduser: Option<Vec<String>>,
     pub invalidparty: Option<Vec<String>>,
     pub invalidtag: Option<Vec<String>>,
 }
+
+#[deprecated(note = "请使用`WechatCpTpInviteResponse`")]
+pub type WxCpTpInviteResponse = WechatCpTpInviteResponse;
 #[derive(Debug, Clone,Serialize, Deserialize)]
-pub struct WxCpTpUseridToOpenidResponse {
+pub struct WechatCpTpUseridToOpenidResponse {
     pub openid: Option<String>,
     pub appid: Option<String>,
 }
+
+#[deprecated(note = "请使用`WechatCpTpUseridToOpenidResponse`")]
+pub type WxCpTpUseridToOpenidResponse = WechatCpTpUseridToOpenidResponse;
 /// 外部联系人详情
 #[derive(Debug, Clone,Serialize, Deserialize)]
 pub struct WechatCpUserExternalContactInfo {
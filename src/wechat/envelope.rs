@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{LabraError, LabradorResult, ResponseEnvelope, WechatCommonResponse};
+
+/// 将errmsg转换为专门的错误
+type ErrcodeMapper = fn(String) -> LabraError;
+
+/// 错误中保留的原始返回的最大长度（字符）
+const UNEXPECTED_RAW_MAX_CHARS: usize = 512;
+
+/// <pre>
+/// 微信的公共返回：errcode为0或缺失时视为成功，errcode可能以字符串形式返回
+/// 可通过`map_errcode`将特定的错误码转换为专门的错误，便于调用方处理
+/// 返回成功但无法反序列化为预期的结构时返回`LabraError::UnexpectedResponse`，带上缺失的字段与原始返回
+/// </pre>
+#[derive(Debug, Clone, Default)]
+pub struct WechatEnvelope {
+    errcodes: Vec<(i64, ErrcodeMapper)>,
+    /// 接口名称，用于错误信息
+    method: Option<String>,
+}
+
+impl WechatEnvelope {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 将指定的错误码转换为`f(errmsg)`，未指定的错误码返回`LabraError::ClientError`
+    pub fn map_errcode(mut self, errcode: i64, f: ErrcodeMapper) -> Self {
+        self.errcodes.push((errcode, f));
+        self
+    }
+
+    /// 接口名称（如`/cgi-bin/ticket/getticket`），返回内容不符合预期时写入错误信息
+    pub fn method<S: Into<String>>(mut self, method: S) -> Self {
+        self.method = method.into().into();
+        self
+    }
+
+    /// 校验已解析为`Value`的返回并反序列化为`T`
+    pub fn extract_value<T: DeserializeOwned>(&self, v: Value) -> LabradorResult<T> {
+        self.check(serde_json::from_value::<WechatCommonResponse>(v.to_owned())?)?;
+        serde_json::from_value::<T>(v.to_owned()).map_err(|err| self.unexpected(err, &v.to_string()))
+    }
+
+    /// 只校验错误码，不解析业务数据，用于逐条解析的大列表返回
+    pub fn check_slice(&self, raw: &[u8]) -> LabradorResult<()> {
+        self.check(serde_json::from_slice::<WechatCommonResponse>(raw)?)
+    }
+
+    /// 返回成功但反序列化失败，serde的错误信息中只有字段名，这里补充接口与原始返回
+    pub(crate) fn unexpected(&self, err: serde_json::Error, raw: &str) -> LabraError {
+        let message = err.to_string();
+        let missing_field = message.split("missing field `").nth(1).and_then(|v| v.split('`').next()).map(str::to_string);
+        let raw = match raw.char_indices().nth(UNEXPECTED_RAW_MAX_CHARS) {
+            Some((i, _)) => format!("{}...", &raw[..i]),
+            None => raw.to_string(),
+        };
+        tracing::error!("[微信接口] {}返回内容不符合预期:{}，原始返回:{}", self.method.as_deref().unwrap_or_default(), message, raw);
+        LabraError::UnexpectedResponse { method: self.method.to_owned(), missing_field, message, raw }
+    }
+
+    fn check(&self, resp: WechatCommonResponse) -> LabradorResult<()> {
+        if resp.is_success() {
+            return Ok(());
+        }
+        let errcode = resp.errcode.unwrap_or_default();
+        let errmsg = resp.errmsg.unwrap_or_default();
+        match self.errcodes.iter().find(|(code, _)| *code == errcode) {
+            Some((_, f)) => Err(f(errmsg)),
+            None => Err(LabraError::ClientError { errcode: errcode.to_string(), errmsg }),
+        }
+    }
+}
+
+impl ResponseEnvelope for WechatEnvelope {
+    fn open<'a>(&self, raw: &'a str) -> LabradorResult<Cow<'a, str>> {
+        self.check(serde_json::from_str::<WechatCommonResponse>(raw)?)?;
+        Ok(Cow::Borrowed(raw))
+    }
+
+    fn extract<T: DeserializeOwned>(&self, raw: &str) -> LabradorResult<T> {
+        let content = self.open(raw)?;
+        serde_json::from_str::<T>(&content).map_err(|err| self.unexpected(err, &content))
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use crate::{LabraError, ResponseEnvelope};
+    use super::WechatEnvelope;
+
+    #[derive(Debug, Deserialize)]
+    struct Ticket {
+        ticket: String,
+        expires_in: i64,
+    }
+
+    #[test]
+    fn test_wechat_success() {
+        let ticket = WechatEnvelope::new().extract::<Ticket>(r#"{"errcode":0,"errmsg":"ok","ticket":"TICKET","expires_in":7200}"#).unwrap();
+        assert_eq!(ticket.ticket, "TICKET");
+        // 部分接口成功时不返回errcode
+        let ticket = WechatEnvelope::new().extract_value::<Ticket>(json!({"ticket": "TICKET", "expires_in": 7200})).unwrap();
+        assert_eq!(ticket.expires_in, 7200);
+    }
+
+    #[test]
+    fn test_wechat_errcode_string() {
+        let err = WechatEnvelope::new().extract::<Ticket>(r#"{"errcode":"40001","errmsg":"invalid credential"}"#).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "40001" && errmsg == "invalid credential"));
+        let envelope = WechatEnvelope::new().map_errcode(40225, LabraError::ShortKeyExpired);
+        assert!(matches!(envelope.extract::<Ticket>(r#"{"errcode":"40225","errmsg":"invalid short key"}"#), Err(LabraError::ShortKeyExpired(msg)) if msg == "invalid short key"));
+        assert!(matches!(envelope.extract::<Ticket>(r#"{"errcode":45009,"errmsg":"quota"}"#), Err(LabraError::ClientError { errcode, .. }) if errcode == "45009"));
+    }
+
+    #[test]
+    fn test_wechat_unexpected_response() {
+        let envelope = WechatEnvelope::new().method("/cgi-bin/ticket/getticket");
+        match envelope.extract_value::<Ticket>(json!({"errcode": 0, "errmsg": "ok", "expires_in": 7200})) {
+            Err(LabraError::UnexpectedResponse { method, missing_field, raw, .. }) => {
+                assert_eq!(method.as_deref(), Some("/cgi-bin/ticket/getticket"));
+                assert_eq!(missing_field.as_deref(), Some("ticket"));
+                assert_eq!(serde_json::from_str::<serde_json::Value>(&raw).unwrap(), json!({"errcode": 0, "errmsg": "ok", "expires_in": 7200}));
+            }
+            v => panic!("{:?}", v),
+        }
+        // 类型不符时没有缺失的字段，原始返回截断
+        let raw = format!(r#"{{"ticket":"{}","expires_in":"7200s"}}"#, "票".repeat(600));
+        let err = WechatEnvelope::new().extract::<Ticket>(&raw).unwrap_err();
+        assert!(err.to_string().starts_with("Unexpected response: "), "{}", err);
+        match err {
+            LabraError::UnexpectedResponse { method: None, missing_field: None, message, raw } => {
+                assert!(message.contains("7200s"), "{}", message);
+                assert_eq!(raw.chars().count(), 512 + 3);
+                assert!(raw.ends_with("票..."));
+            }
+            v => panic!("{:?}", v),
+        }
+        // 失败的返回仍按错误码处理
+        assert!(matches!(envelope.extract::<Ticket>(r#"{"errcode":40001,"errmsg":"invalid credential"}"#), Err(LabraError::ClientError { .. })));
+    }
+}
@@ -0,0 +1,333 @@
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, errors::LabraError, request::{LabraRequest, Method, RequestType}, WechatCommonResponse, LabradorResult};
+use crate::wechat::miniapp::method::{MaCloudMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+
+/// 自动翻页时每页拉取的记录数
+const DATABASE_QUERY_LIMIT: usize = 100;
+
+/// 云开发相关操作
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/)
+#[derive(Debug, Clone)]
+pub struct WechatMaCloud<T: SessionStore> {
+    client: WechatMaClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaCloud<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaCloud<T> {
+        WechatMaCloud {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.cloud()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaCloud<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 触发云函数
+    /// 注意：HTTP API 途径触发云函数不包含用户信息。
+    /// 云函数的返回值（resp_data）会解析为JSON，无法解析时原样作为字符串返回
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/functions/invokeCloudFunction.html)
+    pub async fn invoke_cloud_function(&self, env: &str, name: &str, body: Value) -> LabradorResult<Value> {
+        let v = self.client.post(WechatMaMethod::Cloud(MaCloudMethod::InvokeCloudFunction), vec![("env".to_string(), env.to_string()), ("name".to_string(), name.to_string())], body, RequestType::Json).await?.json::<Value>()?;
+        let resp = parse_cloud_response::<WechatMaCloudFunctionResponse>(v)?;
+        let resp_data = resp.resp_data.unwrap_or_default();
+        Ok(serde_json::from_str::<Value>(&resp_data).unwrap_or(Value::String(resp_data)))
+    }
+
+    /// <pre>
+    /// 数据库查询记录，自动翻页拉取全部记录
+    /// query 如：db.collection("geo").where({done:true}).get()
+    /// 查询语句中已包含skip/limit时只请求一次
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/database/databaseQuery.html)
+    pub async fn database_query(&self, env: &str, query: &str) -> LabradorResult<Vec<Value>> {
+        if query.contains(".skip(") || query.contains(".limit(") {
+            return Ok(self.database_query_page(env, query).await?.data);
+        }
+        let query = query.trim().trim_end_matches(".get()");
+        let mut items = Vec::new();
+        loop {
+            let page = self.database_query_page(env, &format!("{}.skip({}).limit({}).get()", query, items.len(), DATABASE_QUERY_LIMIT)).await?;
+            let count = page.data.len();
+            let total = page.pager.and_then(|pager| pager.total).unwrap_or_default();
+            items.extend(page.data);
+            if count == 0 || items.len() as i64 >= total {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// <pre>
+    /// 数据库查询记录，单次请求，返回分页信息
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/database/databaseQuery.html)
+    pub async fn database_query_page(&self, env: &str, query: &str) -> LabradorResult<WechatMaCloudQueryResponse> {
+        let v = self.database(MaCloudMethod::DatabaseQuery, env, query).await?;
+        let resp = parse_cloud_response::<WechatMaCloudQueryRawResponse>(v)?;
+        let data = resp.data.unwrap_or_default().iter()
+            .map(|item| serde_json::from_str::<Value>(item).map_err(LabraError::from))
+            .collect::<LabradorResult<Vec<Value>>>()?;
+        Ok(WechatMaCloudQueryResponse {
+            pager: resp.pager,
+            data,
+        })
+    }
+
+    /// <pre>
+    /// 数据库插入记录，返回插入成功的记录id
+    /// query 如：db.collection("geo").add({data: [{description:"item1"}]})
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/database/databaseAdd.html)
+    pub async fn database_add(&self, env: &str, query: &str) -> LabradorResult<Vec<String>> {
+        let v = self.database(MaCloudMethod::DatabaseAdd, env, query).await?;
+        let resp = parse_cloud_response::<WechatMaCloudAddResponse>(v)?;
+        Ok(resp.id_list.unwrap_or_default())
+    }
+
+    /// <pre>
+    /// 数据库更新记录
+    /// query 如：db.collection("geo").where({age:14}).update({data:{age:_.inc(1)}})
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/database/databaseUpdate.html)
+    pub async fn database_update(&self, env: &str, query: &str) -> LabradorResult<WechatMaCloudUpdateResponse> {
+        let v = self.database(MaCloudMethod::DatabaseUpdate, env, query).await?;
+        parse_cloud_response::<WechatMaCloudUpdateResponse>(v)
+    }
+
+    /// <pre>
+    /// 数据库删除记录，返回删除的记录数
+    /// query 如：db.collection("geo").where({done:true}).remove()
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/database/databaseDelete.html)
+    pub async fn database_delete(&self, env: &str, query: &str) -> LabradorResult<i64> {
+        let v = self.database(MaCloudMethod::DatabaseDelete, env, query).await?;
+        let resp = parse_cloud_response::<WechatMaCloudDeleteResponse>(v)?;
+        Ok(resp.deleted.unwrap_or_default())
+    }
+
+    /// <pre>
+    /// 获取文件上传链接
+    /// 返回的url、authorization、token、cos_file_id用于向对象存储发起表单上传
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/storage/uploadFile.html)
+    pub async fn upload_file_meta(&self, env: &str, path: &str) -> LabradorResult<WechatMaCloudUploadFileResponse> {
+        let req = json!({
+            "env": env,
+            "path": path,
+        });
+        let v = self.client.post(WechatMaMethod::Cloud(MaCloudMethod::UploadFile), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        parse_cloud_response::<WechatMaCloudUploadFileResponse>(v)
+    }
+
+    /// <pre>
+    /// 上传文件，返回文件ID（file_id）
+    /// 先获取上传链接，再以表单方式上传文件内容
+    /// path 云存储中的文件路径，如：images/avatar.png
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/wxcloud/reference-http-api/storage/uploadFile.html)
+    pub async fn upload_file(&self, env: &str, path: &str, data: Vec<u8>) -> LabradorResult<String> {
+        let meta = self.upload_file_meta(env, path).await?;
+        let file_name = path.rsplit('/').next().unwrap_or(path).to_string();
+        let form = reqwest::multipart::Form::new()
+            .text("key", path.to_string())
+            .text("Signature", meta.authorization.to_owned().unwrap_or_default())
+            .text("x-cos-security-token", meta.token.to_owned().unwrap_or_default())
+            .text("x-cos-meta-fileid", meta.cos_file_id.to_owned().unwrap_or_default())
+            .part("file", reqwest::multipart::Part::bytes(data).file_name(file_name));
+        let req = LabraRequest::<String>::new().url(meta.url.to_owned().unwrap_or_default()).method(Method::Post).req_type(RequestType::Multipart).multipart_form(form);
+        let response = self.client.inner.client.request(req).await?;
+        if response.status().is_success() {
+            Ok(meta.file_id.unwrap_or_default())
+        } else {
+            Err(LabraError::RequestError(response.text()?))
+        }
+    }
+
+    async fn database(&self, method: MaCloudMethod, env: &str, query: &str) -> LabradorResult<Value> {
+        let req = json!({
+            "env": env,
+            "query": query,
+        });
+        self.client.post(WechatMaMethod::Cloud(method), vec![], req, RequestType::Json).await?.json::<Value>()
+    }
+}
+
+/// <pre>
+/// 解析云开发接口返回
+/// 云函数执行出错时，errmsg只有笼统的描述，具体原因在resp_data的errMsg中，这里合并到错误信息里
+/// </pre>
+fn parse_cloud_response<T: DeserializeOwned>(v: Value) -> LabradorResult<T> {
+    let resp = serde_json::from_value::<WechatCommonResponse>(v.to_owned())?;
+    if resp.is_success() {
+        return serde_json::from_value::<T>(v).map_err(LabraError::from);
+    }
+    let mut errmsg = resp.errmsg.to_owned().unwrap_or_default();
+    if let Some(resp_data) = v["resp_data"].as_str().filter(|data| !data.is_empty()) {
+        let detail = serde_json::from_str::<Value>(resp_data).ok()
+            .and_then(|data| ["errMsg", "errmsg", "message"].iter().find_map(|key| data[key].as_str().map(|msg| msg.to_string())))
+            .unwrap_or(resp_data.to_string());
+        errmsg = format!("{}: {}", errmsg, detail);
+    }
+    Err(LabraError::ClientError { errcode: resp.errcode.unwrap_or_default().to_string(), errmsg })
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WechatMaCloudFunctionResponse {
+    resp_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WechatMaCloudQueryRawResponse {
+    pager: Option<WechatMaCloudPager>,
+    data: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaCloudPager {
+    #[serde(rename="Offset")]
+    pub offset: Option<i64>,
+    #[serde(rename="Limit")]
+    pub limit: Option<i64>,
+    #[serde(rename="Total")]
+    pub total: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaCloudQueryResponse {
+    pub pager: Option<WechatMaCloudPager>,
+    pub data: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaCloudAddResponse {
+    pub id_list: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaCloudUpdateResponse {
+    /// 更新条件匹配到的结果数
+    pub matched: Option<i64>,
+    /// 修改的记录数，注意：使用set操作新插入的数据不计入修改数目
+    pub modified: Option<i64>,
+    /// 新插入记录的id，使用set操作时存在
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaCloudDeleteResponse {
+    pub deleted: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaCloudUploadFileResponse {
+    /// 上传url
+    pub url: Option<String>,
+    /// token
+    pub token: Option<String>,
+    /// authorization
+    pub authorization: Option<String>,
+    /// 文件ID
+    pub file_id: Option<String>,
+    /// cos文件ID
+    pub cos_file_id: Option<String>,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::parse_cloud_response;
+
+    // 各用例使用不同的appid，避免共享SimpleStorage中缓存的access_token
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_upload_file() {
+        let cos = MockServer::start(vec![MockResponse::json("").status(204)]).await;
+        let upload = json!({
+            "errcode": 0,
+            "errmsg": "ok",
+            "url": format!("{}/upload", cos.url),
+            "token": "COS_TOKEN",
+            "authorization": "q-sign-algorithm=sha1",
+            "file_id": "cloud://test-env.7465-test-env/images/avatar.png",
+            "cos_file_id": "HDze32/images/avatar.png",
+        });
+        let api = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(&upload.to_string())]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_cloud_upload", "secret").base_url(&api.url);
+        let file_id = client.cloud().upload_file("test-env", "images/avatar.png", b"PNGDATA".to_vec()).await.unwrap();
+        assert_eq!(file_id, "cloud://test-env.7465-test-env/images/avatar.png");
+
+        let requests = api.requests();
+        assert!(requests[1].starts_with("POST /tcb/uploadfile?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"env":"test-env","path":"images/avatar.png"}"#));
+
+        let requests = cos.requests();
+        assert_eq!(requests.len(), 1);
+        let form = &requests[0];
+        assert!(form.starts_with("POST /upload HTTP/1.1"));
+        assert_eq!(form.to_lowercase().matches("content-type: multipart/form-data; boundary=").count(), 1);
+        for (name, value) in [("key", "images/avatar.png"), ("Signature", "q-sign-algorithm=sha1"), ("x-cos-security-token", "COS_TOKEN"), ("x-cos-meta-fileid", "HDze32/images/avatar.png")] {
+            assert!(form.contains(&format!("name=\"{}\"\r\n\r\n{}\r\n", name, value)), "missing field {}", name);
+        }
+        assert!(form.contains("name=\"file\"; filename=\"avatar.png\""));
+        assert!(form.contains("PNGDATA"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_meta_error() {
+        let api = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"errcode":-501000,"errmsg":"env not exists"}"#)]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_cloud_upload_error", "secret").base_url(&api.url);
+        match client.cloud().upload_file("unknown", "a.png", vec![]).await {
+            Err(LabraError::ClientError { errcode, .. }) => assert_eq!(errcode, "-501000"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(api.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_database_query_pager() {
+        let page1 = json!({"errcode": 0, "errmsg": "ok", "pager": {"Offset": 0, "Limit": 100, "Total": 3}, "data": ["{\"_id\":\"1\"}", "{\"_id\":\"2\"}"]});
+        let page2 = json!({"errcode": 0, "errmsg": "ok", "pager": {"Offset": 2, "Limit": 100, "Total": 3}, "data": ["{\"_id\":\"3\"}"]});
+        let api = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(&page1.to_string()), MockResponse::json(&page2.to_string())]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_cloud_query", "secret").base_url(&api.url);
+        let items = client.cloud().database_query("test-env", "db.collection(\"geo\").get()").await.unwrap();
+        assert_eq!(items.iter().map(|item| item["_id"].as_str().unwrap()).collect::<Vec<_>>(), vec!["1", "2", "3"]);
+        let requests = api.requests();
+        assert!(requests[1].contains(r#"db.collection(\"geo\").skip(0).limit(100).get()"#));
+        assert!(requests[2].contains(r#"db.collection(\"geo\").skip(2).limit(100).get()"#));
+    }
+
+    #[test]
+    fn test_cloud_function_error() {
+        let v = json!({"errcode": -404011, "errmsg": "cloud function execution error", "resp_data": "{\"errCode\":-1,\"errMsg\":\"ReferenceError: a is not defined\"}"});
+        match parse_cloud_response::<Value>(v) {
+            Err(LabraError::ClientError { errcode, errmsg }) => {
+                assert_eq!(errcode, "-404011");
+                assert_eq!(errmsg, "cloud function execution error: ReferenceError: a is not defined");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let v = json!({"errcode": "-404011", "errmsg": "cloud function execution error", "resp_data": "timeout"});
+        assert!(matches!(parse_cloud_response::<Value>(v), Err(LabraError::ClientError { errmsg, .. }) if errmsg.ends_with(": timeout")));
+    }
+}
@@ -1,45 +1,74 @@
 use serde::{Serialize, Deserialize};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult};
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError};
 use crate::wechat::miniapp::constants::{APPID, AUTHORIZATION_CODE, GRANT_TYPE, JS_CODE, SECRET};
 use crate::wechat::miniapp::method::WechatMaMethod;
 use crate::wechat::miniapp::WechatMaClient;
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMaCodeSession<'a, T: SessionStore> {
-    client: &'a WechatMaClient<T>,
+pub struct WechatMaCodeSession<T: SessionStore> {
+    client: WechatMaClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMaCodeSession<'a, T> {
+impl<T: SessionStore> WechatMaCodeSession<T> {
 
     #[inline]
-    pub fn new(client: &WechatMaClient<T>) -> WechatMaCodeSession<T> {
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaCodeSession<T> {
         WechatMaCodeSession {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.code_session()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaCodeSession<T> {
+        Self::from_client(client.clone())
+    }
+
     /// # code换取session
     /// [文档](https://developers.weixin.qq.com/miniprogram/dev/OpenApiDoc/user-login/code2Session.html)
     ///
     /// 登录凭证校验。通过 wx.login 接口获得临时登录凭证 code 后传到开发者服务器调用此接口完成登录流程。更多使用方法详见[小程序登录](https://developers.weixin.qq.com/miniprogram/dev/framework/open-ability/login.html)。
-    pub async fn jscode_2_session(&self, code: &str) -> LabradorResult<JsCodeSession> {
+    pub async fn jscode_2_session(&self, code: &str) -> LabradorResult<WechatMaJsCodeSession> {
         let v = self.client.get(WechatMaMethod::CodeSession, vec![
             (GRANT_TYPE.to_string(), AUTHORIZATION_CODE.to_string()),
             (JS_CODE.to_string(), code.to_string()),
-            (APPID.to_string(), self.client.appid.to_string()),
-            (SECRET.to_string(), self.client.secret.to_string()),
+            (APPID.to_string(), self.client.inner.appid.to_string()),
+            (SECRET.to_string(), self.client.inner.secret.to_string()),
         ], RequestType::Json).await?.json::<serde_json::Value>()?;
-        WechatCommonResponse::parse::<JsCodeSession>(v)
+        WechatCommonResponse::parse::<WechatMaJsCodeSession>(v).map_err(|err| match err {
+            LabraError::ClientError { errcode, errmsg } if errcode == "40029" => LabraError::InvalidCode(errmsg),
+            LabraError::ClientError { errcode, errmsg } if errcode == "40163" => LabraError::CodeUsed(errmsg),
+            err => err,
+        })
+    }
+
+    /// code换取session，并按openid缓存session_key，`ttl`为缓存时长（秒），None表示不过期
+    ///
+    /// 之后解密用户数据时可通过[`get_session_key`](Self::get_session_key)取回
+    pub async fn jscode_2_session_cached(&self, code: &str, ttl: Option<usize>) -> LabradorResult<WechatMaJsCodeSession> {
+        let session = self.jscode_2_session(code).await?;
+        self.client.inner.client.session().set(self.session_key_key(&session.openid), session.session_key.to_owned(), ttl)?;
+        Ok(session)
+    }
+
+    /// 获取缓存的session_key
+    pub fn get_session_key(&self, openid: &str) -> LabradorResult<Option<String>> {
+        let session_key: Option<String> = self.client.inner.client.session().get(self.session_key_key(openid), None)?;
+        Ok(session_key.filter(|v| !v.is_empty()))
+    }
+
+    fn session_key_key(&self, openid: &str) -> String {
+        format!("{}_session_key_ma_{}", self.client.inner.appid, openid)
     }
 }
 
 //----------------------------------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct JsCodeSession {
+pub struct WechatMaJsCodeSession {
     /// 用户唯一标识
     pub openid: String,
     /// 会话密钥
@@ -47,3 +76,49 @@ pub struct JsCodeSession {
     /// 用户在开放平台的唯一标识符，若当前小程序已绑定到微信开放平台帐号下会返回，详见 [UnionID](https://developers.weixin.qq.com/miniprogram/dev/framework/open-ability/union-id.html) 机制说明。
     pub unionid: Option<String>,
 }
+
+#[deprecated(note = "请使用`WechatMaJsCodeSession`")]
+pub type JsCodeSession = WechatMaJsCodeSession;
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::WechatMaJsCodeSession;
+
+    #[tokio::test]
+    async fn test_jscode_2_session() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"openid":"OPENID","session_key":"SESSIONKEY"}"#),
+            MockResponse::json(r#"{"openid":"OPENID2","session_key":"SESSIONKEY2","unionid":"UNIONID"}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_code_session", "secret").base_url(&server.url);
+        let session = client.code_session().jscode_2_session("CODE").await.unwrap();
+        assert_eq!(session, WechatMaJsCodeSession { openid: "OPENID".to_string(), session_key: "SESSIONKEY".to_string(), unionid: None });
+        let session = client.code_session().jscode_2_session_cached("CODE2", None).await.unwrap();
+        assert_eq!(session.unionid.as_deref(), Some("UNIONID"));
+        assert_eq!(client.code_session().get_session_key("OPENID2").unwrap().as_deref(), Some("SESSIONKEY2"));
+        assert_eq!(client.code_session().get_session_key("OPENID").unwrap(), None);
+
+        // code2session不需要access_token
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("GET /sns/jscode2session?grant_type=authorization_code&js_code=CODE&appid=wx_ma_code_session&secret=secret HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_jscode_2_session_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"errcode":40029,"errmsg":"invalid code"}"#),
+            MockResponse::json(r#"{"errcode":40163,"errmsg":"code been used"}"#),
+            MockResponse::json(r#"{"errcode":45011,"errmsg":"api minute-quota reach limit"}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_code_session_error", "secret").base_url(&server.url);
+        assert!(matches!(client.code_session().jscode_2_session("CODE").await, Err(LabraError::InvalidCode(msg)) if msg == "invalid code"));
+        assert!(matches!(client.code_session().jscode_2_session("CODE").await, Err(LabraError::CodeUsed(msg)) if msg == "code been used"));
+        assert!(matches!(client.code_session().jscode_2_session("CODE").await, Err(LabraError::ClientError { errcode, .. }) if errcode == "45011"));
+    }
+}
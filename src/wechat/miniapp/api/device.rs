@@ -0,0 +1,267 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, LabraError};
+use crate::wechat::miniapp::method::{MaDeviceMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+use crate::wechat::subscribe_data::check_subscribe_data;
+
+/// 硬件设备
+///
+/// 用于接入小程序硬件框架的设备，向订阅了设备消息的用户发送通知。
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/framework/device/device-message.html)
+#[derive(Debug, Clone)]
+pub struct WechatMaHardwareDevice<T: SessionStore> {
+    client: WechatMaClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaHardwareDevice<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaHardwareDevice<T> {
+        WechatMaHardwareDevice {
+            client,
+        }
+    }
+
+    /// <pre>
+    /// 获取设备票据
+    /// 设备票据用于小程序前端调用`wx.requestSubscribeDeviceMessage`，有效期5分钟
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/hardware-device/hardwareDevice.getSnTicket.html)
+    pub async fn get_sn_ticket(&self, sn: &str, model_id: &str) -> LabradorResult<String> {
+        let v = self.client.post(WechatMaMethod::Device(MaDeviceMethod::GetSnTicket), vec![], json!({
+            "sn": sn,
+            "model_id": model_id,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let res = parse_device_response::<WechatMaSnTicketResponse>(v)?;
+        Ok(res.sn_ticket)
+    }
+
+    /// <pre>
+    /// 发送设备消息
+    /// 与订阅消息相同，发送前按模板关键词的类型校验参数，见`WechatSubscribeKeyword`
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/hardware-device/hardwareDevice.send.html)
+    pub async fn send_device_subscribe_message(&self, req: WechatMaDeviceSubscribeMsgRequest) -> LabradorResult<()> {
+        req.check_params()?;
+        let v = self.client.post(WechatMaMethod::Device(MaDeviceMethod::SendDeviceSubscribeMsg), vec![], &req, RequestType::Json).await?.json::<Value>()?;
+        parse_device_response::<WechatCommonResponse>(v)?;
+        Ok(())
+    }
+}
+
+/// 解析设备接口的返回，已知的错误码在errmsg后附上说明，见[`WechatMaDeviceErrcode`]
+fn parse_device_response<D: serde::de::DeserializeOwned>(v: Value) -> LabradorResult<D> {
+    WechatCommonResponse::parse::<D>(v).map_err(|err| match err {
+        LabraError::ClientError { errcode, errmsg } => {
+            let errmsg = match errcode.parse::<i64>().ok().and_then(WechatMaDeviceErrcode::from_errcode) {
+                Some(code) => format!("{}（{}）", errmsg, code.description()),
+                None => errmsg,
+            };
+            LabraError::ClientError { errcode, errmsg }
+        }
+        err => err,
+    })
+}
+
+/// 发送设备消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaDeviceSubscribeMsgRequest {
+    /// 接收者（用户）的openid列表
+    pub to_openid_list: Vec<String>,
+    /// 点击消息卡片之后打开的小程序页面路径，仅限本小程序内的页面
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    /// 所需下发的消息模板id
+    pub template_id: String,
+    /// 设备唯一序列号，由厂商分配
+    pub sn: String,
+    /// 设备型号id，通过注册设备获得
+    pub model_id: String,
+    /// 跳转小程序类型：developer为开发版；trial为体验版；formal为正式版；默认为正式版
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub miniprogram_state: Option<String>,
+    /// 进入小程序查看的语言类型，支持zh_CN(简体中文)、en_US(英文)、zh_HK(繁体中文)、zh_TW(繁体中文)，默认为zh_CN
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// 模板内容，格式形如`{"thing1": {"value": "..."}}`
+    pub data: Value,
+}
+
+impl WechatMaDeviceSubscribeMsgRequest {
+    /// 校验接收者、设备信息及模板参数
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if self.to_openid_list.is_empty() {
+            return Err(LabraError::RequestError("设备消息的接收者to_openid_list不能为空".to_string()));
+        }
+        if self.sn.is_empty() || self.model_id.is_empty() {
+            return Err(LabraError::RequestError("设备消息需要传入sn与model_id".to_string()));
+        }
+        check_subscribe_data(&self.data)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaSnTicketResponse {
+    /// 设备票据
+    pub sn_ticket: String,
+}
+
+/// 设备接口的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatMaDeviceErrcode {
+    /// 19999开头的错误码：设备相关的错误，如sn、model_id未注册或与当前小程序不匹配，具体原因见errmsg
+    Device(i64),
+    /// 40003：openid为空或者不正确
+    InvalidOpenid,
+    /// 40037：模板id不正确
+    InvalidTemplateId,
+    /// 41030：page路径不正确
+    InvalidPage,
+    /// 43101：用户未订阅该设备消息
+    UserRefused,
+    /// 47003：模板参数不准确
+    InvalidData,
+}
+
+impl WechatMaDeviceErrcode {
+    /// 按错误码解析，非设备接口的错误码返回`None`
+    pub fn from_errcode(errcode: i64) -> Option<Self> {
+        let code = match errcode {
+            40003 => WechatMaDeviceErrcode::InvalidOpenid,
+            40037 => WechatMaDeviceErrcode::InvalidTemplateId,
+            41030 => WechatMaDeviceErrcode::InvalidPage,
+            43101 => WechatMaDeviceErrcode::UserRefused,
+            47003 => WechatMaDeviceErrcode::InvalidData,
+            _ if errcode.to_string().starts_with("19999") => WechatMaDeviceErrcode::Device(errcode),
+            _ => return None,
+        };
+        Some(code)
+    }
+
+    /// 从接口返回的`LabraError::ClientError`中解析
+    pub fn from_error(err: &LabraError) -> Option<Self> {
+        match err {
+            LabraError::ClientError { errcode, .. } => errcode.parse::<i64>().ok().and_then(Self::from_errcode),
+            _ => None,
+        }
+    }
+
+    pub fn errcode(&self) -> i64 {
+        match self {
+            WechatMaDeviceErrcode::Device(errcode) => *errcode,
+            WechatMaDeviceErrcode::InvalidOpenid => 40003,
+            WechatMaDeviceErrcode::InvalidTemplateId => 40037,
+            WechatMaDeviceErrcode::InvalidPage => 41030,
+            WechatMaDeviceErrcode::UserRefused => 43101,
+            WechatMaDeviceErrcode::InvalidData => 47003,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            WechatMaDeviceErrcode::Device(_) => "设备相关错误，请检查sn、model_id是否已注册且与小程序匹配",
+            WechatMaDeviceErrcode::InvalidOpenid => "openid为空或者不正确",
+            WechatMaDeviceErrcode::InvalidTemplateId => "模板id不正确",
+            WechatMaDeviceErrcode::InvalidPage => "page路径不正确",
+            WechatMaDeviceErrcode::UserRefused => "用户未订阅该设备消息",
+            WechatMaDeviceErrcode::InvalidData => "模板参数不准确",
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::{WechatMaDeviceErrcode, WechatMaDeviceSubscribeMsgRequest};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn request(data: Value) -> WechatMaDeviceSubscribeMsgRequest {
+        WechatMaDeviceSubscribeMsgRequest {
+            to_openid_list: vec!["oXS6k5Bw2YTvQk0cI8dQ_OqLhO_w".to_string()],
+            page: Some("pages/index/index".to_string()),
+            template_id: "8L7tQbEF8rPDvI0Ecn8A2Oh3SDS4wakO2ufcFsh7NO0".to_string(),
+            sn: "sn_0001".to_string(),
+            model_id: "ZoQNSo6v0xlCjGDW8RyYrg".to_string(),
+            miniprogram_state: None,
+            lang: Some("zh_CN".to_string()),
+            data,
+        }
+    }
+
+    #[test]
+    fn test_request_serialize() {
+        let req = request(json!({"thing2": {"value": "门铃被按响"}, "time1": {"value": "2021年9月1日 12:00"}}));
+        assert_eq!(serde_json::to_value(&req).unwrap(), json!({
+            "to_openid_list": ["oXS6k5Bw2YTvQk0cI8dQ_OqLhO_w"],
+            "page": "pages/index/index",
+            "template_id": "8L7tQbEF8rPDvI0Ecn8A2Oh3SDS4wakO2ufcFsh7NO0",
+            "sn": "sn_0001",
+            "model_id": "ZoQNSo6v0xlCjGDW8RyYrg",
+            "lang": "zh_CN",
+            "data": {"thing2": {"value": "门铃被按响"}, "time1": {"value": "2021年9月1日 12:00"}}
+        }));
+        assert!(req.check_params().is_ok());
+    }
+
+    #[test]
+    fn test_request_validation() {
+        // 与订阅消息相同的关键词校验
+        let err = request(json!({"thing2": {"value": "门铃被按响，请及时查看门口的监控画面并确认来访人员"}})).check_params().unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(msg) if msg.contains("thing2")));
+        assert!(request(json!({"time1": {"value": "明天中午"}})).check_params().is_err());
+        assert!(request(json!({"thing2": {}})).check_params().is_err());
+
+        let mut req = request(json!({}));
+        req.sn = String::default();
+        assert!(req.check_params().is_err());
+        let mut req = request(json!({}));
+        req.to_openid_list.clear();
+        assert!(req.check_params().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_sn_ticket_and_send() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","sn_ticket":"aJMVB2yJVP9G3bfuuKOLJA"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+            MockResponse::json(r#"{"errcode":1999901,"errmsg":"invalid sn"}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_device", "secret").base_url(&server.url);
+        let device = client.hardware_device();
+        assert_eq!(device.get_sn_ticket("sn_0001", "ZoQNSo6v0xlCjGDW8RyYrg").await.unwrap(), "aJMVB2yJVP9G3bfuuKOLJA");
+        device.send_device_subscribe_message(request(json!({"thing2": {"value": "门铃被按响"}}))).await.unwrap();
+        let err = device.send_device_subscribe_message(request(json!({"thing2": {"value": "门铃被按响"}}))).await.unwrap_err();
+        assert_eq!(WechatMaDeviceErrcode::from_error(&err), Some(WechatMaDeviceErrcode::Device(1999901)));
+        assert!(matches!(err, LabraError::ClientError { errmsg, .. } if errmsg.starts_with("invalid sn（设备相关错误")));
+        // 参数校验失败时不发送请求
+        assert!(device.send_device_subscribe_message(request(json!({"number1": {"value": "一百"}}))).await.is_err());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].starts_with("POST /wxa/getsnticket?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"model_id":"ZoQNSo6v0xlCjGDW8RyYrg","sn":"sn_0001"}"#));
+        assert!(requests[2].starts_with("POST /cgi-bin/message/device/subscribe/send?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[2].contains(r#""sn":"sn_0001""#));
+    }
+
+    #[test]
+    fn test_device_errcode() {
+        assert_eq!(WechatMaDeviceErrcode::from_errcode(19999), Some(WechatMaDeviceErrcode::Device(19999)));
+        assert_eq!(WechatMaDeviceErrcode::from_errcode(43101), Some(WechatMaDeviceErrcode::UserRefused));
+        assert_eq!(WechatMaDeviceErrcode::from_errcode(40001), None);
+        assert_eq!(WechatMaDeviceErrcode::from_errcode(1999), None);
+        assert_eq!(WechatMaDeviceErrcode::InvalidData.errcode(), 47003);
+        assert_eq!(WechatMaDeviceErrcode::from_error(&LabraError::RequestError("".to_string())), None);
+    }
+}
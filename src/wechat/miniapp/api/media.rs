@@ -11,20 +11,26 @@ use crate::wechat::miniapp::{WechatMaClient, WechatRequest};
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMaMedia<'a, T: SessionStore> {
-    client: &'a WechatMaClient<T>,
+pub struct WechatMaMedia<T: SessionStore> {
+    client: WechatMaClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMaMedia<'a, T> {
+impl<T: SessionStore> WechatMaMedia<T> {
 
     #[inline]
-    pub fn new(client: &WechatMaClient<T>) -> WechatMaMedia<T> {
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaMedia<T> {
         WechatMaMedia {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.media()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaMedia<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 新增临时素材
     /// 小程序可以使用本接口把媒体文件（目前仅支持图片）上传到微信服务器，用户发送客服消息或被动回复用户消息。
@@ -2,42 +2,52 @@ use serde::{Serialize, Deserialize};
 use serde_json::{ Value};
 
 use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult};
-use crate::wechat::constants::{KEFU_MSGTYPE_IMAGE, KEFU_MSGTYPE_MA_PAGE, KEFU_MSGTYPE_TEXT};
+use crate::wechat::constants::{KEFU_MSGTYPE_IMAGE, KEFU_MSGTYPE_LINK, KEFU_MSGTYPE_MA_PAGE, KEFU_MSGTYPE_TEXT};
 use crate::wechat::miniapp::method::{MaMessageMethod, WechatMaMethod};
 use crate::wechat::miniapp::WechatMaClient;
+pub use crate::wechat::subscribe_data::check_subscribe_data;
+use crate::wechat::subscribe_data::WechatSubscribeKeyword;
 
 
 /// 消息发送接口.
 #[derive(Debug, Clone)]
-pub struct WechatMaMessage<'a, T: SessionStore> {
-    client: &'a WechatMaClient<T>,
+pub struct WechatMaMessage<T: SessionStore> {
+    client: WechatMaClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMaMessage<'a, T> {
+impl<T: SessionStore> WechatMaMessage<T> {
 
     #[inline]
-    pub fn new(client: &WechatMaClient<T>) -> WechatMaMessage<T> {
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaMessage<T> {
         WechatMaMessage {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.message()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaMessage<T> {
+        Self::from_client(client.clone())
+    }
+
 
     /// <pre>
     /// 发送客服消息
     /// 详情请见: <a href="https://developers.weixin.qq.com/miniprogram/dev/api-backend/customerServiceMessage.send.html">发送客服消息</a>
     /// 接口url格式：https://api.weixin.qq.com/cgi-bin/message/custom/send?access_token=ACCESS_TOKEN
     /// </pre>
-    pub async fn send_kefu_msg(&self, message: WxMaKefuMsgRequest) -> LabradorResult<WechatCommonResponse> {
+    pub async fn send_kefu_msg(&self, message: WechatMaKefuMsgRequest) -> LabradorResult<WechatCommonResponse> {
         self.client.post(WechatMaMethod::Message(MaMessageMethod::SendCustomMsg), vec![], &message, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
     /// <pre>
     /// 发送订阅消息
+    /// 发送前按模板关键词的类型校验参数，见`WechatSubscribeKeyword`
     /// https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/subscribe-message/subscribeMessage.send.html
     /// </pre>
-    pub async fn send_subscribe_msg(&self, data: WxMaSubscribeMsgRequest) -> LabradorResult<WechatCommonResponse> {
+    pub async fn send_subscribe_msg(&self, data: WechatMaSubscribeMsgRequest) -> LabradorResult<WechatCommonResponse> {
+        data.check_params()?;
         self.client.post(WechatMaMethod::Message(MaMessageMethod::SendSubscribeMsg), vec![], data, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
@@ -46,7 +56,7 @@ impl<'a, T: SessionStore> WechatMaMessage<'a, T> {
     /// 详情请见: <a href="https://developers.weixin.qq.com/miniprogram/dev/api/open-api/uniform-message/sendUniformMessage.html">下发小程序和公众号统一的服务消息</a>
     /// 接口url格式：https://api.weixin.qq.com/cgi-bin/message/wxopen/template/uniform_send?access_token=ACCESS_TOKEN
     /// </pre>
-    pub async fn send_uniform_msg(&self, data: WxMaUniformMsgRequest) -> LabradorResult<WechatCommonResponse> {
+    pub async fn send_uniform_msg(&self, data: WechatMaUniformMsgRequest) -> LabradorResult<WechatCommonResponse> {
         self.client.post(WechatMaMethod::Message(MaMessageMethod::SendUniformTemplate), vec![], &data, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
@@ -77,17 +87,28 @@ impl<'a, T: SessionStore> WechatMaMessage<'a, T> {
 
 //----------------------------------------------------------------------------------------------------------------------------
 
+/// <pre>
 /// 客服消息
+/// 如：`KfMaPage::new().title("标题").pagepath("pages/index/index").thumb_media_id("MEDIA_ID").build_msg().touser("OPENID")`
+/// </pre>
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WxMaKefuMsgRequest {
+pub struct WechatMaKefuMsgRequest {
     pub touser: String,
     pub msgtype: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<KfText>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub image: Option<KfImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub link: Option<KfLink>,
+    /// 小程序卡片，thumb_media_id为上传的封面图片的media_id
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub miniprogrampage: Option<KfMaPage>,
 }
 
+#[deprecated(note = "请使用`WechatMaKefuMsgRequest`")]
+pub type WxMaKefuMsgRequest = WechatMaKefuMsgRequest;
+
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KfText {
@@ -103,13 +124,13 @@ impl KfText {
         }
     }
 
-    fn content(mut self, content: &str) -> Self {
+    pub fn content(mut self, content: &str) -> Self {
         self.content = content.to_string().into();
         self
     }
 
-    pub fn build_msg(self) -> WxMaKefuMsgRequest {
-        WxMaKefuMsgRequest {
+    pub fn build_msg(self) -> WechatMaKefuMsgRequest {
+        WechatMaKefuMsgRequest {
             touser: "".to_string(),
             msgtype: KEFU_MSGTYPE_TEXT.to_string(),
             text: self.into(),
@@ -134,13 +155,13 @@ impl KfImage {
         }
     }
 
-    fn media_id(mut self, media_id: &str) -> Self {
+    pub fn media_id(mut self, media_id: &str) -> Self {
         self.media_id = media_id.to_string().into();
         self
     }
 
-    pub fn build_msg(self) -> WxMaKefuMsgRequest {
-        WxMaKefuMsgRequest {
+    pub fn build_msg(self) -> WechatMaKefuMsgRequest {
+        WechatMaKefuMsgRequest {
             touser: "".to_string(),
             msgtype: KEFU_MSGTYPE_IMAGE.to_string(),
             text: None,
@@ -171,27 +192,27 @@ impl KfLink {
         }
     }
 
-    fn url(mut self, url: &str) -> Self {
+    pub fn url(mut self, url: &str) -> Self {
         self.url = url.to_string().into();
         self
     }
-    fn title(mut self, title: &str) -> Self {
+    pub fn title(mut self, title: &str) -> Self {
         self.title = title.to_string().into();
         self
     }
-    fn thumb_url(mut self, thumb_url: &str) -> Self {
+    pub fn thumb_url(mut self, thumb_url: &str) -> Self {
         self.thumb_url = thumb_url.to_string().into();
         self
     }
-    fn description(mut self, description: &str) -> Self {
+    pub fn description(mut self, description: &str) -> Self {
         self.description = description.to_string().into();
         self
     }
 
-    pub fn build_msg(self) -> WxMaKefuMsgRequest {
-        WxMaKefuMsgRequest {
+    pub fn build_msg(self) -> WechatMaKefuMsgRequest {
+        WechatMaKefuMsgRequest {
             touser: "".to_string(),
-            msgtype: KEFU_MSGTYPE_IMAGE.to_string(),
+            msgtype: KEFU_MSGTYPE_LINK.to_string(),
             text: None,
             image: None,
             link: self.into(),
@@ -218,21 +239,21 @@ impl KfMaPage {
         }
     }
     
-    fn thumb_media_id(mut self, thumb_media_id: &str) -> Self {
+    pub fn thumb_media_id(mut self, thumb_media_id: &str) -> Self {
         self.thumb_media_id = thumb_media_id.to_string().into();
         self
     }
-    fn pagepath(mut self, pagepath: &str) -> Self {
+    pub fn pagepath(mut self, pagepath: &str) -> Self {
         self.pagepath = pagepath.to_string().into();
         self
     }
-    fn title(mut self, title: &str) -> Self {
+    pub fn title(mut self, title: &str) -> Self {
         self.title = title.to_string().into();
         self
     }
 
-    pub fn build_msg(self) -> WxMaKefuMsgRequest {
-        WxMaKefuMsgRequest {
+    pub fn build_msg(self) -> WechatMaKefuMsgRequest {
+        WechatMaKefuMsgRequest {
             touser: "".to_string(),
             msgtype: KEFU_MSGTYPE_MA_PAGE.to_string(),
             text: None,
@@ -244,26 +265,26 @@ impl KfMaPage {
 }
 
 #[allow(unused)]
-impl WxMaKefuMsgRequest {
+impl WechatMaKefuMsgRequest {
 
-    fn touser(mut self, touser: &str) -> Self {
+    pub fn touser(mut self, touser: &str) -> Self {
         self.touser = touser.to_string().into();
         self
     }
 
-    fn text() -> KfText {
+    pub fn text() -> KfText {
         KfText::new()
     }
 
-    fn image() -> KfImage {
+    pub fn image() -> KfImage {
         KfImage::new()
     }
 
-    fn link() -> KfLink {
+    pub fn link() -> KfLink {
         KfLink::new()
     }
 
-    fn miniprogram() -> KfMaPage {
+    pub fn miniprogram() -> KfMaPage {
         KfMaPage::new()
     }
 }
@@ -272,7 +293,7 @@ impl WxMaKefuMsgRequest {
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WxMaSubscribeMsgRequest {
+pub struct WechatMaSubscribeMsgRequest {
     /// 接收者（用户）的 openid.
     /// <pre>
     /// 参数：touser
@@ -307,9 +328,24 @@ pub struct WxMaSubscribeMsgRequest {
     pub lang: Option<String>,
 }
 
+#[deprecated(note = "请使用`WechatMaSubscribeMsgRequest`")]
+pub type WxMaSubscribeMsgRequest = WechatMaSubscribeMsgRequest;
+
+impl WechatMaSubscribeMsgRequest {
+    /// 按模板关键词的类型校验`data`中各参数的取值，见[`check_subscribe_data`]
+    pub fn check_params(&self) -> LabradorResult<()> {
+        match &self.data {
+            Some(data) => check_subscribe_data(data),
+            None => Ok(()),
+        }
+    }
+}
+
+#[deprecated(note = "请使用`WechatSubscribeKeyword`")]
+pub type WechatMaSubscribeKeyword = WechatSubscribeKeyword;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WxMaUniformMsgRequest {
+pub struct WechatMaUniformMsgRequest {
     touser: String,
     /// 小程序模板消息相关的信息，可以参考小程序模板消息接口; 有此节点则优先发送小程序模板消息；（小程序模板消息已下线，不用传此节点）
     weapp_template_msg: Option<WeappTemplateMsg>,
@@ -317,6 +353,9 @@ pub struct WxMaUniformMsgRequest {
     mp_template_msg: MpTemplateMsg,
 }
 
+#[deprecated(note = "请使用`WechatMaUniformMsgRequest`")]
+pub type WxMaUniformMsgRequest = WechatMaUniformMsgRequest;
+
 #[derive(Debug, Clone,Deserialize, Serialize)]
 pub struct MsgData {
     name: String,
@@ -336,9 +375,9 @@ pub struct WeappTemplateMsg {
 
 
 #[allow(unused)]
-impl WxMaUniformMsgRequest {
-    pub fn new<S: Into<String>>(touser: S, weapp_template_msg:  Option<WeappTemplateMsg>, mp_template_msg:MpTemplateMsg) -> WxMaUniformMsgRequest {
-        WxMaUniformMsgRequest {
+impl WechatMaUniformMsgRequest {
+    pub fn new<S: Into<String>>(touser: S, weapp_template_msg:  Option<WeappTemplateMsg>, mp_template_msg:MpTemplateMsg) -> WechatMaUniformMsgRequest {
+        WechatMaUniformMsgRequest {
             touser: touser.into(),
             weapp_template_msg,
             mp_template_msg,
@@ -355,4 +394,57 @@ pub struct MpTemplateMsg {
     url: String,
     miniprogram: Value,
     data: Value,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::{KfMaPage, WechatMaSubscribeMsgRequest};
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_kefu_miniprogrampage() {
+        let msg = KfMaPage::new().title("标题").pagepath("pages/index/index?id=1").thumb_media_id("MEDIA_ID").build_msg().touser("OPENID");
+        assert_eq!(serde_json::to_value(&msg).unwrap(), json!({
+            "touser": "OPENID",
+            "msgtype": "miniprogrampage",
+            "miniprogrampage": { "title": "标题", "pagepath": "pages/index/index?id=1", "thumb_media_id": "MEDIA_ID" }
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_send_subscribe_msg_validates_keywords() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_subscribe_data", "secret").base_url(&server.url);
+        let request = |data: Option<Value>| WechatMaSubscribeMsgRequest {
+            touser: "OPENID".to_string(),
+            template_id: "TEMPLATE_ID".to_string(),
+            page: None,
+            data,
+            miniprogram_state: None,
+            lang: None,
+        };
+        let invalid = vec![
+            json!({"thing1": {"value": "一二三四五六七八九十一二三四五六七八九十一"}}),
+            json!({"number2": {"value": "123456789012345678901234567890123"}}),
+            json!({"phrase3": {"value": "六个汉字啊呀"}}),
+            json!({"time4": {"value": "2019-10-01 25:00"}}),
+        ];
+        for data in invalid {
+            let err = client.message().send_subscribe_msg(request(data.to_owned().into())).await.unwrap_err();
+            assert!(matches!(err, LabraError::RequestError(_)), "{} {:?}", data, err);
+        }
+        assert!(server.requests().is_empty());
+        assert!(request(None).check_params().is_ok());
+        let data = json!({"thing1": {"value": "门铃被按响"}, "number2": {"value": 100}, "phrase3": {"value": "配送中"}, "time4": {"value": "2019-10-01 15:01:00"}});
+        let res = client.message().send_subscribe_msg(request(data.into())).await.unwrap();
+        assert_eq!(res.errcode, Some(0));
+        assert!(server.requests()[1].starts_with("POST /cgi-bin/message/subscribe/send?access_token=ACCESS_TOKEN"));
+    }
+}
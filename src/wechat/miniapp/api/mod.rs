@@ -3,6 +3,13 @@ mod user;
 mod codesession;
 mod message;
 mod media;
+mod cloud;
+mod plugin;
+mod nearby;
+mod device;
+mod operation;
+mod security;
+mod xpay;
 
 // 小程序
 
@@ -11,5 +18,12 @@ pub use self::user::*;
 pub use self::codesession::*;
 pub use self::message::*;
 pub use self::media::*;
+pub use self::cloud::*;
+pub use self::plugin::*;
+pub use self::nearby::*;
+pub use self::device::*;
+pub use self::operation::*;
+pub use self::security::*;
+pub use self::xpay::*;
 
 
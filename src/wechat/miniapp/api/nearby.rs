@@ -0,0 +1,316 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult, CategoryTree, WechatCategory};
+use crate::serde_helper::{json_string, option_json_string};
+use crate::wechat::miniapp::method::{MaNearbyPoiMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+
+/// 附近的小程序
+///
+/// 添加地点时`kf_info`、`pic_list`、`service_infos`需以JSON字符串的形式提交，
+/// 查询地点列表时返回的`data`同样是JSON字符串，这里统一通过[`json_string`]处理，调用方直接使用结构体即可。
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/nearby-poi/nearbyPoi.add.html)
+#[derive(Debug, Clone)]
+pub struct WechatMaNearbyPoi<T: SessionStore> {
+    client: WechatMaClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaNearbyPoi<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaNearbyPoi<T> {
+        WechatMaNearbyPoi {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.nearby_poi()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaNearbyPoi<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 添加地点
+    /// 添加成功后需等待审核，审核结果通过事件推送
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/nearby-poi/nearbyPoi.add.html)
+    pub async fn add(&self, req: WechatMaNearbyPoiRequest) -> LabradorResult<WechatMaNearbyPoiResult> {
+        let v = self.client.post(WechatMaMethod::NearbyPoi(MaNearbyPoiMethod::AddNearbyPoi), vec![], &req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<WechatMaNearbyPoiResult>(v, "data")
+    }
+
+    /// <pre>
+    /// 删除地点
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/nearby-poi/nearbyPoi.delete.html)
+    pub async fn delete(&self, poi_id: &str) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMaMethod::NearbyPoi(MaNearbyPoiMethod::DeleteNearbyPoi), vec![], json!({
+            "poi_id": poi_id,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 查看地点列表
+    /// page：起始页id（从1开始计数），page_rows：每页展示个数（最多1000个）
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/nearby-poi/nearbyPoi.getList.html)
+    pub async fn get_list(&self, page: i32, page_rows: i32) -> LabradorResult<WechatMaNearbyPoiListResult> {
+        let v = self.client.get(WechatMaMethod::NearbyPoi(MaNearbyPoiMethod::GetNearbyPoiList), vec![
+            ("page".to_string(), page.to_string()),
+            ("page_rows".to_string(), page_rows.to_string()),
+        ], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<WechatMaNearbyPoiListResult>(v, "data")
+    }
+
+    /// <pre>
+    /// 展示/取消展示附近小程序
+    /// status：0-取消展示，1-展示
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/nearby-poi/nearbyPoi.setShowStatus.html)
+    pub async fn set_show_status(&self, poi_id: &str, status: i32) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMaMethod::NearbyPoi(MaNearbyPoiMethod::SetShowStatus), vec![], json!({
+            "poi_id": poi_id,
+            "status": status,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 获取门店类目，返回的类目通过father关联父类目，不含id为0的根节点
+    /// use_cache为true时优先使用会话存储中缓存的类目树，缓存24小时
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/doc/oplatform/Third-party_Platforms/2.0/api/Mini_Program_Basic_Info/get_merchant_category.html)
+    pub async fn get_category_tree(&self, use_cache: bool) -> LabradorResult<CategoryTree> {
+        let session = self.client.inner.client.session();
+        let key = format!("{}_nearby_category_tree_ma", self.client.inner.appid);
+        if use_cache {
+            if let Some(tree) = CategoryTree::load(session, &key)? {
+                return Ok(tree);
+            }
+        }
+        let v = self.client.get(WechatMaMethod::NearbyPoi(MaNearbyPoiMethod::GetMerchantCategory), vec![], RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse_with_key::<Value>(v, "data")?;
+        let categories = serde_json::from_value::<Vec<WechatCategory>>(v["all_category_info"]["categories"].to_owned())?;
+        let tree = CategoryTree::from_categories(categories.into_iter().filter(|v| v.id != 0));
+        if use_cache {
+            tree.store(session, &key)?;
+        }
+        Ok(tree)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 添加地点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiRequest {
+    /// 必填，写死为"1"
+    pub is_comm_nearby: String,
+    /// 客服信息，选填
+    #[serde(default, with = "option_json_string", skip_serializing_if = "Option::is_none")]
+    pub kf_info: Option<WechatMaNearbyPoiKfInfo>,
+    /// 门店图片，最多9张，最少1张
+    #[serde(with = "json_string")]
+    pub pic_list: WechatMaNearbyPoiPicList,
+    /// 服务标签列表
+    #[serde(with = "json_string")]
+    pub service_infos: WechatMaNearbyPoiServiceInfos,
+    /// 门店名字
+    pub store_name: String,
+    /// 营业时间，格式11:11-12:12
+    pub hour: String,
+    /// 主体名字
+    pub company_name: String,
+    /// 门店电话
+    pub contract_phone: String,
+    /// 资质号，15位营业执照注册号或9位组织机构代码
+    pub credential: String,
+    /// 地址
+    pub address: String,
+    /// 证明材料，如果company_name和该小程序主体不一致，需要填qualification_list，详细规则见附近的小程序使用指南-如何证明门店的经营主体跟公众号或小程序帐号主体相关
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qualification_list: Option<String>,
+    /// 如果是迁移到附近的小程序的地点，需要填此字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poi_id: Option<String>,
+    /// 对应《在腾讯地图中搜索门店》中的sosomap_poi_uid字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub map_poi_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiKfInfo {
+    /// 是否开启客服
+    pub open_kf: bool,
+    /// 客服头像
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kf_headimg: Option<String>,
+    /// 客服名字
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kf_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiPicList {
+    /// 门店图片url
+    #[serde(default)]
+    pub list: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiServiceInfos {
+    #[serde(default)]
+    pub service_infos: Vec<WechatMaNearbyPoiServiceInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiServiceInfo {
+    /// 服务标签ID
+    pub id: i64,
+    /// 服务类型：1-小程序服务，2-自定义
+    #[serde(rename = "type")]
+    pub r#type: i32,
+    /// 服务名称
+    pub name: String,
+    /// 服务跳转的小程序appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 服务跳转的小程序路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// 添加地点的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiResult {
+    /// 审核单ID
+    pub audit_id: Option<String>,
+    /// 附近地点ID
+    pub poi_id: Option<String>,
+    /// 经营资质证件号
+    pub related_credential: Option<String>,
+}
+
+/// 地点列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiListResult {
+    /// 剩余可添加地点个数
+    pub left_count: Option<i64>,
+    /// 地点列表，接口以JSON字符串返回
+    #[serde(with = "json_string")]
+    pub data: WechatMaNearbyPoiList,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiList {
+    #[serde(default)]
+    pub poi_list: Vec<WechatMaNearbyPoiItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaNearbyPoiItem {
+    /// 附近地点ID
+    pub poi_id: String,
+    /// 资质证件地址
+    pub qualification_address: Option<String>,
+    /// 资质证件证件号
+    pub qualification_num: Option<String>,
+    /// 地点审核状态：3-审核中，4-审核失败，5-审核通过
+    pub audit_status: Option<i32>,
+    /// 地点展示在附近状态：0-未展示，1-展示中
+    pub display_status: Option<i32>,
+    /// 审核失败原因，audit_status=4时返回
+    pub refuse_reason: Option<String>,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::SimpleStorage;
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::{WechatMaNearbyPoiKfInfo, WechatMaNearbyPoiPicList, WechatMaNearbyPoiRequest, WechatMaNearbyPoiServiceInfo, WechatMaNearbyPoiServiceInfos};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn poi_request() -> WechatMaNearbyPoiRequest {
+        WechatMaNearbyPoiRequest {
+            is_comm_nearby: "1".to_string(),
+            kf_info: Some(WechatMaNearbyPoiKfInfo { open_kf: true, kf_headimg: Some("http://kf.png".to_string()), kf_name: Some("客服".to_string()) }),
+            pic_list: WechatMaNearbyPoiPicList { list: vec!["http://mmbiz.qpic.cn/a.png".to_string(), "http://mmbiz.qpic.cn/b.png".to_string()] },
+            service_infos: WechatMaNearbyPoiServiceInfos { service_infos: vec![WechatMaNearbyPoiServiceInfo { id: 2, r#type: 1, name: "外卖".to_string(), appid: Some("wx1234567890".to_string()), path: Some("index/index".to_string()) }] },
+            store_name: "门店".to_string(),
+            hour: "00:00-11:11".to_string(),
+            company_name: "公司".to_string(),
+            contract_phone: "1111111".to_string(),
+            credential: "156718193518281".to_string(),
+            address: "新港中路123号".to_string(),
+            qualification_list: None,
+            poi_id: None,
+            map_poi_id: Some("5938314494307741153".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_request_nested_json_string() {
+        let v = serde_json::to_value(&poi_request()).unwrap();
+        // 嵌套字段以JSON字符串提交，字符串内是JSON
+        let pic_list = v["pic_list"].as_str().unwrap();
+        assert_eq!(pic_list, r#"{"list":["http://mmbiz.qpic.cn/a.png","http://mmbiz.qpic.cn/b.png"]}"#);
+        let service_infos = serde_json::from_str::<Value>(v["service_infos"].as_str().unwrap()).unwrap();
+        assert_eq!(service_infos["service_infos"][0]["type"], 1);
+        assert_eq!(v["kf_info"].as_str().unwrap(), r#"{"open_kf":true,"kf_headimg":"http://kf.png","kf_name":"客服"}"#);
+        assert!(v.get("qualification_list").is_none());
+        // 反序列化还原
+        let req = serde_json::from_value::<WechatMaNearbyPoiRequest>(v).unwrap();
+        assert_eq!(req.pic_list.list.len(), 2);
+        assert_eq!(req.kf_info.unwrap().kf_name.as_deref(), Some("客服"));
+
+        let mut without_kf = poi_request();
+        without_kf.kf_info = None;
+        let v = serde_json::to_value(&without_kf).unwrap();
+        assert!(v.get("kf_info").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_list() {
+        let add = r#"{"errcode":0,"errmsg":"ok","data":{"audit_id":"xxxxx","poi_id":"xxxxx","related_credential":"xxxxx"}}"#;
+        let list = r#"{"errcode":0,"errmsg":"","data":{"left_count":9,"data":"{\"poi_list\":[{\"poi_id\":\"123456\",\"qualification_address\":\"广东省广州市海珠区新港中路123号\",\"qualification_num\":\"123456789-1\",\"audit_status\":3,\"display_status\":0,\"refuse_reason\":\"\"}]}"}}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(add), MockResponse::json(list)]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_nearby_poi", "secret").base_url(&server.url);
+        let res = client.nearby_poi().add(poi_request()).await.unwrap();
+        assert_eq!(res.audit_id.as_deref(), Some("xxxxx"));
+        let res = client.nearby_poi().get_list(1, 20).await.unwrap();
+        assert_eq!(res.left_count, Some(9));
+        assert_eq!(res.data.poi_list[0].poi_id, "123456");
+        assert_eq!(res.data.poi_list[0].audit_status, Some(3));
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /wxa/addnearbypoi?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].contains(r#""pic_list":"{\"list\":[\"http://mmbiz.qpic.cn/a.png\",\"http://mmbiz.qpic.cn/b.png\"]}""#));
+        assert!(requests[2].starts_with("GET /wxa/getnearbypoilist?page=1&page_rows=20&access_token=ACCESS_TOKEN HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_category_tree() {
+        let category = r#"{"errcode":0,"errmsg":"","data":{"all_category_info":{"categories":[{"id":0,"name":"root","level":0,"children":[304]},{"id":304,"name":"快递业与邮政","level":1,"father":0,"children":[305]},{"id":305,"name":"快递、物流","level":2,"father":304,"children":[],"sensitive_type":1,"qualify":{"exter_list":[]}}]}}}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(category)]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_nearby_category", "secret").base_url(&server.url);
+        let tree = client.nearby_poi().get_category_tree(true).await.unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.roots()[0].id, 304);
+        assert_eq!(tree.leaf_categories().iter().map(|v| v.id).collect::<Vec<_>>(), vec![305]);
+        assert_eq!(tree.path_for(305).iter().map(|v| v.name.as_str()).collect::<Vec<_>>(), vec!["快递业与邮政", "快递、物流"]);
+        assert_eq!(client.nearby_poi().get_category_tree(true).await.unwrap(), tree);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("GET /wxa/get_merchant_category?access_token=ACCESS_TOKEN HTTP/1.1"));
+    }
+}
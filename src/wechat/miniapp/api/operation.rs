@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, LabradorResult, LabraError};
+use crate::serde_helper::option_string_or_number;
+use crate::wechat::miniapp::method::{MaOperationMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+
+/// 实时日志单次查询的最大时间跨度（24小时）
+const USER_LOG_MAX_RANGE_SECONDS: i64 = 24 * 60 * 60;
+
+/// 运维中心
+///
+/// 查询小程序的实时日志与用户反馈，便于线上问题排查。
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/operation/operation.realtimelogSearch.html)
+#[derive(Debug, Clone)]
+pub struct WechatMaOperation<T: SessionStore> {
+    client: WechatMaClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaOperation<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaOperation<T> {
+        WechatMaOperation {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.operation()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaOperation<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 实时日志查询
+    /// 查询指定日期（YYYYMMDD）内的实时日志，begintime与endtime为秒级时间戳，单次查询的时间跨度不能超过24小时
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/operation/operation.realtimelogSearch.html)
+    pub async fn search_user_log(&self, req: WechatMaUserLogSearchRequest) -> LabradorResult<WechatMaUserLogSearchResponse> {
+        req.check_params()?;
+        let v = self.client.get(WechatMaMethod::Operation(MaOperationMethod::UserLogSearch), req.to_params(), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        serde_json::from_value::<WechatMaUserLogSearchResponse>(v["data"].to_owned()).map_err(LabraError::from)
+    }
+
+    /// <pre>
+    /// 获取用户反馈列表
+    /// page从1开始，num为每页的数量；feedback_type为空时返回全部类型的反馈
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/operation/operation.getFeedback.html)
+    pub async fn get_feedback_list(&self, page: i32, num: i32, feedback_type: Option<WechatMaFeedbackType>) -> LabradorResult<WechatMaFeedbackListResponse> {
+        let mut params = vec![("page".to_string(), page.to_string()), ("num".to_string(), num.to_string())];
+        if let Some(feedback_type) = feedback_type {
+            params.push(("type".to_string(), i32::from(feedback_type).to_string()));
+        }
+        let v = self.client.get(WechatMaMethod::Operation(MaOperationMethod::FeedbackList), params, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMaFeedbackListResponse>(v)
+    }
+
+    /// <pre>
+    /// 获取用户反馈中的图片等媒体文件
+    /// 调用成功时直接返回文件的二进制内容，失败时返回JSON格式的错误信息
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/operation/operation.getFeedbackmedia.html)
+    pub async fn get_feedback_media(&self, record_id: i64, media_id: &str) -> LabradorResult<Bytes> {
+        let result = self.client.get(WechatMaMethod::Operation(MaOperationMethod::GetFeedbackMedia), vec![
+            ("record_id".to_string(), record_id.to_string()),
+            ("media_id".to_string(), media_id.to_string()),
+        ], RequestType::Json).await?.bytes()?;
+        let res_str = String::from_utf8(result.to_vec()).unwrap_or_default();
+        if let Ok(r) = WechatCommonResponse::from_str(&res_str) {
+            return Err(LabraError::ClientError { errcode: r.errcode.to_owned().unwrap_or_default().to_string(), errmsg: r.errmsg.to_owned().unwrap_or_default() });
+        }
+        Ok(result)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 实时日志的级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatMaLogLevel {
+    /// Info
+    Info,
+    /// Warn
+    Warn,
+    /// Error
+    Error,
+    Unknown(i32),
+}
+
+impl From<i32> for WechatMaLogLevel {
+    fn from(v: i32) -> Self {
+        match v {
+            2 => WechatMaLogLevel::Info,
+            4 => WechatMaLogLevel::Warn,
+            8 => WechatMaLogLevel::Error,
+            v => WechatMaLogLevel::Unknown(v),
+        }
+    }
+}
+
+impl From<WechatMaLogLevel> for i32 {
+    fn from(v: WechatMaLogLevel) -> Self {
+        match v {
+            WechatMaLogLevel::Info => 2,
+            WechatMaLogLevel::Warn => 4,
+            WechatMaLogLevel::Error => 8,
+            WechatMaLogLevel::Unknown(v) => v,
+        }
+    }
+}
+
+/// 实时日志查询
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WechatMaUserLogSearchRequest {
+    /// 查询的日期，格式为YYYYMMDD，如20200101
+    pub date: String,
+    /// 开始时间，秒级时间戳，必须是date指定日期的时间
+    pub begintime: i64,
+    /// 结束时间，秒级时间戳，必须是date指定日期的时间
+    pub endtime: i64,
+    /// 开始返回的数据下标，用于分页
+    pub start: Option<i32>,
+    /// 返回的数据条数，用于分页
+    pub limit: Option<i32>,
+    /// 小程序启动的唯一id，按TraceId查询会展示该次小程序启动过程的所有页面的日志
+    pub trace_id: Option<String>,
+    /// 小程序页面路径，如pages/index/index
+    pub url: Option<String>,
+    /// 用户微信号或者OpenId
+    pub id: Option<String>,
+    /// 开发者通过setFilterMsg/addFilterMsg设置的filterMsg字段
+    pub filter_msg: Option<String>,
+    /// 日志级别
+    pub level: Option<WechatMaLogLevel>,
+}
+
+impl WechatMaUserLogSearchRequest {
+
+    pub fn new(date: &str, begintime: i64, endtime: i64) -> Self {
+        WechatMaUserLogSearchRequest {
+            date: date.to_string(),
+            begintime,
+            endtime,
+            ..Default::default()
+        }
+    }
+
+    /// 校验日期格式与时间范围，时间跨度不能超过24小时
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if self.date.len() != 8 || !self.date.chars().all(|c| c.is_ascii_digit()) {
+            return Err(LabraError::RequestError(format!("date格式应为YYYYMMDD，当前为{}", self.date)));
+        }
+        if self.begintime > self.endtime {
+            return Err(LabraError::RequestError("begintime不能晚于endtime".to_string()));
+        }
+        if self.endtime - self.begintime > USER_LOG_MAX_RANGE_SECONDS {
+            return Err(LabraError::RequestError(format!("实时日志单次查询的时间跨度不能超过{}秒（即24小时）", USER_LOG_MAX_RANGE_SECONDS)));
+        }
+        Ok(())
+    }
+
+    fn to_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            ("date".to_string(), self.date.to_string()),
+            ("begintime".to_string(), self.begintime.to_string()),
+            ("endtime".to_string(), self.endtime.to_string()),
+        ];
+        let optionals = vec![
+            ("start", self.start.map(|v| v.to_string())),
+            ("limit", self.limit.map(|v| v.to_string())),
+            ("traceId", self.trace_id.to_owned()),
+            ("url", self.url.to_owned()),
+            ("id", self.id.to_owned()),
+            ("filterMsg", self.filter_msg.to_owned()),
+            ("level", self.level.map(|v| i32::from(v).to_string())),
+        ];
+        params.extend(optionals.into_iter().filter_map(|(k, v)| v.map(|v| (k.to_string(), v))));
+        params
+    }
+}
+
+/// 实时日志查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaUserLogSearchResponse {
+    #[serde(default)]
+    pub list: Vec<WechatMaRealtimeLog>,
+    #[serde(default, with = "option_string_or_number")]
+    pub total: Option<i64>,
+}
+
+/// 实时日志，不同级别的日志返回的字段不尽相同，未定义的字段保留在`extra`中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaRealtimeLog {
+    /// 日志级别
+    pub level: Option<WechatMaLogLevel>,
+    /// 客户端平台
+    pub platform: Option<i32>,
+    /// 基础库版本
+    #[serde(rename = "libraryVersion")]
+    pub library_version: Option<String>,
+    /// 微信版本
+    #[serde(rename = "clientVersion")]
+    pub client_version: Option<String>,
+    /// 用户微信号或者OpenId
+    pub id: Option<String>,
+    /// 打日志的时间戳
+    #[serde(default, with = "option_string_or_number")]
+    pub timestamp: Option<i64>,
+    /// 日志内容
+    #[serde(default)]
+    pub msg: Vec<WechatMaRealtimeLogMsg>,
+    /// 小程序页面路径
+    pub url: Option<String>,
+    /// 小程序启动的唯一id
+    pub traceid: Option<String>,
+    /// 开发者设置的filterMsg
+    #[serde(rename = "filterMsg")]
+    pub filter_msg: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// 实时日志的单条内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaRealtimeLogMsg {
+    /// 该条日志的时间戳
+    #[serde(default, with = "option_string_or_number")]
+    pub time: Option<i64>,
+    /// 开发者调用log.info等接口时传入的参数，可能是字符串或对象
+    #[serde(default)]
+    pub msg: Vec<Value>,
+    /// 该条日志的级别
+    pub level: Option<WechatMaLogLevel>,
+}
+
+/// 用户反馈的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatMaFeedbackType {
+    /// 无法打开小程序
+    CannotOpen,
+    /// 小程序闪退
+    Crash,
+    /// 卡顿
+    Lag,
+    /// 黑屏白屏
+    BlankScreen,
+    /// 死机
+    Freeze,
+    /// 界面错位
+    Misaligned,
+    /// 界面加载慢
+    SlowLoading,
+    /// 其他异常
+    Other,
+    Unknown(i32),
+}
+
+impl From<i32> for WechatMaFeedbackType {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => WechatMaFeedbackType::CannotOpen,
+            2 => WechatMaFeedbackType::Crash,
+            3 => WechatMaFeedbackType::Lag,
+            4 => WechatMaFeedbackType::BlankScreen,
+            5 => WechatMaFeedbackType::Freeze,
+            6 => WechatMaFeedbackType::Misaligned,
+            7 => WechatMaFeedbackType::SlowLoading,
+            8 => WechatMaFeedbackType::Other,
+            v => WechatMaFeedbackType::Unknown(v),
+        }
+    }
+}
+
+impl From<WechatMaFeedbackType> for i32 {
+    fn from(v: WechatMaFeedbackType) -> Self {
+        match v {
+            WechatMaFeedbackType::CannotOpen => 1,
+            WechatMaFeedbackType::Crash => 2,
+            WechatMaFeedbackType::Lag => 3,
+            WechatMaFeedbackType::BlankScreen => 4,
+            WechatMaFeedbackType::Freeze => 5,
+            WechatMaFeedbackType::Misaligned => 6,
+            WechatMaFeedbackType::SlowLoading => 7,
+            WechatMaFeedbackType::Other => 8,
+            WechatMaFeedbackType::Unknown(v) => v,
+        }
+    }
+}
+
+/// 用户反馈列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaFeedbackListResponse {
+    #[serde(default)]
+    pub list: Vec<WechatMaFeedback>,
+    /// 反馈总数
+    #[serde(default, with = "option_string_or_number")]
+    pub total_num: Option<i64>,
+}
+
+/// 用户反馈
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaFeedback {
+    /// 反馈的id
+    pub record_id: i64,
+    /// 反馈的时间
+    #[serde(default, with = "option_string_or_number")]
+    pub create_time: Option<i64>,
+    /// 反馈的内容
+    pub content: Option<String>,
+    /// 用户留下的手机号
+    #[serde(default, with = "option_string_or_number")]
+    pub phone: Option<i64>,
+    /// 用户的openid
+    pub openid: Option<String>,
+    /// 用户的昵称
+    pub nickname: Option<String>,
+    /// 用户的头像
+    pub head_url: Option<String>,
+    /// 反馈的类型
+    #[serde(rename = "type")]
+    pub feedback_type: Option<WechatMaFeedbackType>,
+    /// 反馈中的媒体文件id，可通过`get_feedback_media`下载
+    #[serde(default, rename = "mediaIds")]
+    pub media_ids: Vec<String>,
+    /// 用户的系统信息
+    #[serde(rename = "systemInfo")]
+    pub system_info: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer, closed_url};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::{WechatMaFeedbackType, WechatMaLogLevel, WechatMaUserLogSearchRequest, WechatMaUserLogSearchResponse};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_user_log_time_range() {
+        assert!(WechatMaUserLogSearchRequest::new("20200101", 1577808000, 1577894400).check_params().is_ok());
+        assert!(matches!(WechatMaUserLogSearchRequest::new("20200101", 1577808000, 1577894401).check_params(), Err(LabraError::RequestError(_))));
+        assert!(matches!(WechatMaUserLogSearchRequest::new("20200101", 1577894400, 1577808000).check_params(), Err(LabraError::RequestError(_))));
+        assert!(matches!(WechatMaUserLogSearchRequest::new("2020-01-01", 1577808000, 1577808600).check_params(), Err(LabraError::RequestError(_))));
+    }
+
+    #[test]
+    fn test_user_log_deserialize() {
+        let res = serde_json::from_value::<WechatMaUserLogSearchResponse>(json!({
+            "list": [
+                {
+                    "level": 2, "platform": 2, "libraryVersion": "2.11.0", "clientVersion": "7.0.13", "id": "oABCD",
+                    "timestamp": 1590392851, "url": "pages/index/index", "traceid": "TRACE", "filterMsg": "pay",
+                    "msg": [{"time": 1590392851, "msg": ["click", {"orderId": 42}], "level": 2}],
+                },
+                {
+                    "level": 8, "platform": 1, "timestamp": "1590392860", "url": "pages/pay/pay",
+                    "msg": [{"time": 1590392860, "msg": ["pay failed"], "level": 8}],
+                    "errMsg": "requestPayment:fail", "stack": "at pay.js:12"
+                },
+                {"level": 16}
+            ],
+            "total": 3
+        })).unwrap();
+        assert_eq!(res.total, Some(3));
+        assert_eq!(res.list[0].level, Some(WechatMaLogLevel::Info));
+        assert_eq!(res.list[0].library_version.as_deref(), Some("2.11.0"));
+        assert_eq!(res.list[0].msg[0].msg, vec![json!("click"), json!({"orderId": 42})]);
+        assert!(res.list[0].extra.is_empty());
+        assert_eq!(res.list[1].level, Some(WechatMaLogLevel::Error));
+        assert_eq!(res.list[1].timestamp, Some(1590392860));
+        assert_eq!(res.list[1].extra["errMsg"], json!("requestPayment:fail"));
+        assert_eq!(res.list[1].extra["stack"], json!("at pay.js:12"));
+        assert_eq!(res.list[2].level, Some(WechatMaLogLevel::Unknown(16)));
+        assert!(res.list[2].msg.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_user_log() {
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_user_log_limit", "secret").base_url(&closed_url().await);
+        let req = WechatMaUserLogSearchRequest::new("20200101", 1577808000, 1577894401);
+        assert!(matches!(client.operation().search_user_log(req).await, Err(LabraError::RequestError(_))));
+
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","data":{"list":[{"level":4,"msg":[{"time":1577808001,"msg":["slow"],"level":4}]}],"total":1}}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_user_log", "secret").base_url(&server.url);
+        let mut req = WechatMaUserLogSearchRequest::new("20200101", 1577808000, 1577811600);
+        req.filter_msg = Some("pay".to_string());
+        req.level = Some(WechatMaLogLevel::Warn);
+        let res = client.operation().search_user_log(req).await.unwrap();
+        assert_eq!(res.list[0].level, Some(WechatMaLogLevel::Warn));
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("GET /wxaapi/userlog/userlog_search?date=20200101&begintime=1577808000&endtime=1577811600&filterMsg=pay&level=4&access_token=ACCESS_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_feedback() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","list":[{"record_id":1001,"create_time":1590392851,"content":"打不开","phone":13800000000,"openid":"oABCD","nickname":"jim","head_url":"http://head","type":1,"mediaIds":["MEDIA_ID"],"systemInfo":"{\"system\":\"iOS 13.4\"}"}],"total_num":1}"#),
+            MockResponse::bytes("image/jpeg", b"JPEGDATA"),
+            MockResponse::json(r#"{"errcode":40007,"errmsg":"invalid media_id"}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_feedback", "secret").base_url(&server.url);
+        let res = client.operation().get_feedback_list(1, 10, Some(WechatMaFeedbackType::CannotOpen)).await.unwrap();
+        assert_eq!(res.total_num, Some(1));
+        let feedback = &res.list[0];
+        assert_eq!(feedback.feedback_type, Some(WechatMaFeedbackType::CannotOpen));
+        assert_eq!(feedback.phone, Some(13800000000));
+        assert_eq!(feedback.media_ids, vec!["MEDIA_ID".to_string()]);
+        let media = client.operation().get_feedback_media(feedback.record_id, &feedback.media_ids[0]).await.unwrap();
+        assert_eq!(media.as_ref(), b"JPEGDATA");
+        assert!(matches!(client.operation().get_feedback_media(1001, "BAD").await, Err(LabraError::ClientError { errcode, .. }) if errcode == "40007"));
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("GET /wxaapi/feedback/list?page=1&num=10&type=1&access_token=ACCESS_TOKEN"));
+        assert!(requests[2].starts_with("GET /cgi-bin/media/getfeedbackmedia?record_id=1001&media_id=MEDIA_ID&access_token=ACCESS_TOKEN"));
+    }
+}
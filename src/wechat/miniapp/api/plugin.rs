@@ -0,0 +1,222 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, LabradorResult};
+use crate::serde_helper::option_string_or_number;
+use crate::wechat::miniapp::method::{MaPluginMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+
+/// 插件管理
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.applyPlugin.html)
+#[derive(Debug, Clone)]
+pub struct WechatMaPlugin<T: SessionStore> {
+    client: WechatMaClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaPlugin<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaPlugin<T> {
+        WechatMaPlugin {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.plugin()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaPlugin<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 向插件开发者发起使用插件的申请
+    /// reason：申请使用理由
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.applyPlugin.html)
+    pub async fn apply_plugin(&self, plugin_appid: &str, reason: Option<&str>) -> LabradorResult<WechatCommonResponse> {
+        let mut req = json!({
+            "action": "apply",
+            "plugin_appid": plugin_appid,
+        });
+        if let Some(reason) = reason {
+            req["reason"] = reason.into();
+        }
+        let v = self.client.post(WechatMaMethod::Plugin(MaPluginMethod::Plugin), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 查询已添加的插件
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.getPluginList.html)
+    pub async fn get_plugin_list(&self) -> LabradorResult<Vec<WechatMaPluginInfo>> {
+        let v = self.client.post(WechatMaMethod::Plugin(MaPluginMethod::Plugin), vec![], json!({
+            "action": "list",
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<WechatMaPluginListResponse>(v)?;
+        Ok(v.plugin_list)
+    }
+
+    /// <pre>
+    /// 删除已添加的插件
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.unbindPlugin.html)
+    pub async fn unbind_plugin(&self, plugin_appid: &str) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMaMethod::Plugin(MaPluginMethod::Plugin), vec![], json!({
+            "action": "unbind",
+            "plugin_appid": plugin_appid,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 获取当前所有插件使用方（供插件开发者调用）
+    /// page：要拉取第几页的数据，num：每页的记录数
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.getPluginDevApplyList.html)
+    pub async fn get_plugin_dev_apply_list(&self, page: i32, num: i32) -> LabradorResult<Vec<WechatMaPluginDevApply>> {
+        let v = self.client.post(WechatMaMethod::Plugin(MaPluginMethod::DevPlugin), vec![], json!({
+            "action": "dev_apply_list",
+            "page": page,
+            "num": num,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<WechatMaPluginDevApplyListResponse>(v)?;
+        Ok(v.apply_list)
+    }
+
+    /// <pre>
+    /// 同意插件使用申请（供插件开发者调用）
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.setDevPluginApplyStatus.html)
+    pub async fn dev_agree(&self, appid: &str) -> LabradorResult<WechatCommonResponse> {
+        self.set_dev_plugin_apply_status(json!({
+            "action": "dev_agree",
+            "appid": appid,
+        })).await
+    }
+
+    /// <pre>
+    /// 拒绝插件使用申请（供插件开发者调用）
+    /// reason：拒绝理由
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.setDevPluginApplyStatus.html)
+    pub async fn dev_refuse(&self, reason: &str) -> LabradorResult<WechatCommonResponse> {
+        self.set_dev_plugin_apply_status(json!({
+            "action": "dev_refuse",
+            "reason": reason,
+        })).await
+    }
+
+    /// <pre>
+    /// 删除已拒绝的申请者（供插件开发者调用）
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/plugin-management/pluginManager.setDevPluginApplyStatus.html)
+    pub async fn dev_delete(&self, appid: &str) -> LabradorResult<WechatCommonResponse> {
+        self.set_dev_plugin_apply_status(json!({
+            "action": "dev_delete",
+            "appid": appid,
+        })).await
+    }
+
+    async fn set_dev_plugin_apply_status(&self, req: Value) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMaMethod::Plugin(MaPluginMethod::DevPlugin), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaPluginListResponse {
+    #[serde(default)]
+    pub plugin_list: Vec<WechatMaPluginInfo>,
+}
+
+/// 已添加的插件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaPluginInfo {
+    /// 插件appId
+    pub appid: String,
+    /// 插件状态：1-申请中，2-申请通过，3-被拒绝，4-已超时
+    pub status: i32,
+    /// 插件昵称
+    pub nickname: Option<String>,
+    /// 插件头像
+    pub headimgurl: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaPluginDevApplyListResponse {
+    #[serde(default)]
+    pub apply_list: Vec<WechatMaPluginDevApply>,
+}
+
+/// 插件使用方
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaPluginDevApply {
+    /// 使用者的appid
+    pub appid: String,
+    /// 插件状态：1-申请中，2-申请通过，3-被拒绝，4-已超时
+    pub status: i32,
+    /// 使用者的昵称
+    pub nickname: Option<String>,
+    /// 使用者的头像
+    pub headimgurl: Option<String>,
+    /// 使用者的类目
+    #[serde(default)]
+    pub categories: Vec<WechatMaPluginCategory>,
+    /// 使用者的申请时间
+    #[serde(default, with = "option_string_or_number")]
+    pub create_time: Option<i64>,
+    /// 使用者的小程序码
+    pub apply_url: Option<String>,
+    /// 使用者的申请说明
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaPluginCategory {
+    pub first: Option<String>,
+    pub second: Option<String>,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::{SimpleStorage, WechatCommonResponse};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::WechatMaPluginDevApplyListResponse;
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_get_plugin_list() {
+        let list = r#"{"errcode":0,"errmsg":"ok","plugin_list":[{"appid":"wxe5f52902cf4de896","status":2,"nickname":"插件昵称","headimgurl":"http://plugin.qq.com"}]}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(list)]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_plugin_list", "secret").base_url(&server.url);
+        let plugins = client.plugin().get_plugin_list().await.unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].appid, "wxe5f52902cf4de896");
+        assert_eq!(plugins[0].status, 2);
+        assert_eq!(plugins[0].nickname.as_deref(), Some("插件昵称"));
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /wxa/plugin?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"action":"list"}"#));
+    }
+
+    #[test]
+    fn test_dev_apply_list_deserialize() {
+        let data = r#"{"errcode":0,"errmsg":"ok","apply_list":[{"appid":"wx1234567890","status":1,"nickname":"名称","headimgurl":"http://a.png","categories":[{"first":"IT科技","second":"硬件与设备"}],"create_time":"1536305096","apply_url":"http://b.png","reason":"polo"}]}"#;
+        let v = serde_json::from_str::<Value>(data).unwrap();
+        let res = WechatCommonResponse::parse::<WechatMaPluginDevApplyListResponse>(v).unwrap();
+        assert_eq!(res.apply_list[0].appid, "wx1234567890");
+        assert_eq!(res.apply_list[0].create_time, Some(1536305096));
+        assert_eq!(res.apply_list[0].categories[0].second.as_deref(), Some("硬件与设备"));
+    }
+}
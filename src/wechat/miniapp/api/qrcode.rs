@@ -12,20 +12,26 @@ use crate::wechat::miniapp::WechatMaClient;
 /// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/OpenApiDoc/qrcode-link/qr-code/getQRCode.html)
 ///
 #[derive(Debug, Clone)]
-pub struct WechatMaQrcode<'a, T: SessionStore> {
-    client: &'a WechatMaClient<T>,
+pub struct WechatMaQrcode<T: SessionStore> {
+    client: WechatMaClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMaQrcode<'a, T> {
+impl<T: SessionStore> WechatMaQrcode<T> {
 
     #[inline]
-    pub fn new(client: &WechatMaClient<T>) -> WechatMaQrcode<T> {
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaQrcode<T> {
         WechatMaQrcode {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.qrcode()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaQrcode<T> {
+        Self::from_client(client.clone())
+    }
+
     ///
     /// 接口C: 获取小程序页面二维码.
     /// <pre>
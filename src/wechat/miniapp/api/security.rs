@@ -0,0 +1,327 @@
+use std::net::IpAddr;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, LabradorResult, LabraError};
+use crate::wechat::miniapp::method::{MaSecurityMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+
+/// 用户近两小时内未访问过小程序，无法评估风险
+const ERRCODE_USER_NOT_VISITED: i64 = 61010;
+
+/// 安全风控
+///
+/// 根据提交的用户信息获取用户的风险等级，用于注册、营销活动等场景的防刷。
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/safety-control-capability/riskControl.getUserRiskRank.html)
+#[derive(Debug, Clone)]
+pub struct WechatMaSecurity<T: SessionStore> {
+    client: WechatMaClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaSecurity<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaSecurity<T> {
+        WechatMaSecurity {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.security()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaSecurity<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 获取用户的安全等级
+    /// 用户近两小时内未访问过小程序时接口返回61010，这里作为[`WechatMaRiskResult::Stale`]返回而非错误
+    /// </pre>
+    /// [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/safety-control-capability/riskControl.getUserRiskRank.html)
+    pub async fn get_user_risk_rank(&self, mut req: WechatMaUserRiskRankRequest) -> LabradorResult<WechatMaRiskResult> {
+        req.check_params()?;
+        if req.appid.is_none() {
+            req.appid = self.client.inner.appid.to_owned().into();
+        }
+        let v = self.client.post(WechatMaMethod::Security(MaSecurityMethod::GetUserRiskRank), vec![], &req, RequestType::Json).await?.json::<Value>()?;
+        if v["errcode"].as_i64() == Some(ERRCODE_USER_NOT_VISITED) {
+            return Ok(WechatMaRiskResult::Stale);
+        }
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        let rank = v["risk_rank"].as_i64().ok_or_else(|| LabraError::MissingField("risk_rank".to_string()))?;
+        Ok(WechatMaRiskResult::Ranked {
+            rank: WechatMaRiskRank::from(rank as i32),
+            union_id: v["unoin_id"].as_i64(),
+        })
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 风控场景
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatMaRiskScene {
+    /// 注册
+    Register,
+    /// 营销作弊
+    Marketing,
+    Unknown(i32),
+}
+
+impl From<i32> for WechatMaRiskScene {
+    fn from(v: i32) -> Self {
+        match v {
+            0 => WechatMaRiskScene::Register,
+            1 => WechatMaRiskScene::Marketing,
+            v => WechatMaRiskScene::Unknown(v),
+        }
+    }
+}
+
+impl From<WechatMaRiskScene> for i32 {
+    fn from(v: WechatMaRiskScene) -> Self {
+        match v {
+            WechatMaRiskScene::Register => 0,
+            WechatMaRiskScene::Marketing => 1,
+            WechatMaRiskScene::Unknown(v) => v,
+        }
+    }
+}
+
+/// 获取用户安全等级的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaUserRiskRankRequest {
+    /// 小程序appid，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 用户的openid
+    pub openid: String,
+    /// 场景
+    pub scene: WechatMaRiskScene,
+    /// 用户手机号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile_no: Option<String>,
+    /// 用户访问源ip
+    pub client_ip: String,
+    /// 用户邮箱地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_address: Option<String>,
+    /// 额外补充信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_info: Option<String>,
+    /// 为true时为测试调用，返回的结果不具参考意义
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_test: Option<bool>,
+}
+
+impl WechatMaUserRiskRankRequest {
+    pub fn new<S: Into<String>>(openid: S, scene: WechatMaRiskScene, client_ip: S) -> Self {
+        WechatMaUserRiskRankRequest {
+            appid: None,
+            openid: openid.into(),
+            scene,
+            mobile_no: None,
+            client_ip: client_ip.into(),
+            email_address: None,
+            extended_info: None,
+            is_test: None,
+        }
+    }
+
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if self.openid.is_empty() {
+            return Err(LabraError::RequestError("openid不能为空".to_string()));
+        }
+        if self.client_ip.parse::<IpAddr>().is_err() {
+            return Err(LabraError::RequestError(format!("client_ip {}不是合法的IP地址", self.client_ip)));
+        }
+        Ok(())
+    }
+}
+
+/// 用户风险等级，等级越高风险越大
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatMaRiskRank {
+    /// 0：风险等级最低
+    Rank0,
+    /// 1
+    Rank1,
+    /// 2
+    Rank2,
+    /// 3
+    Rank3,
+    /// 4：风险等级最高
+    Rank4,
+    Unknown(i32),
+}
+
+impl WechatMaRiskRank {
+    /// 风险等级的数值，未知的等级为None
+    pub fn level(&self) -> Option<i32> {
+        match i32::from(*self) {
+            v @ 0..=4 => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl From<i32> for WechatMaRiskRank {
+    fn from(v: i32) -> Self {
+        match v {
+            0 => WechatMaRiskRank::Rank0,
+            1 => WechatMaRiskRank::Rank1,
+            2 => WechatMaRiskRank::Rank2,
+            3 => WechatMaRiskRank::Rank3,
+            4 => WechatMaRiskRank::Rank4,
+            v => WechatMaRiskRank::Unknown(v),
+        }
+    }
+}
+
+impl From<WechatMaRiskRank> for i32 {
+    fn from(v: WechatMaRiskRank) -> Self {
+        match v {
+            WechatMaRiskRank::Rank0 => 0,
+            WechatMaRiskRank::Rank1 => 1,
+            WechatMaRiskRank::Rank2 => 2,
+            WechatMaRiskRank::Rank3 => 3,
+            WechatMaRiskRank::Rank4 => 4,
+            WechatMaRiskRank::Unknown(v) => v,
+        }
+    }
+}
+
+/// 获取用户安全等级的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatMaRiskResult {
+    /// 风险等级
+    Ranked {
+        rank: WechatMaRiskRank,
+        /// 唯一请求标识，用于问题排查
+        union_id: Option<i64>,
+    },
+    /// 用户近两小时内未访问过小程序（61010），无法评估
+    Stale,
+}
+
+/// <pre>
+/// 风控策略
+/// 风险等级不超过max_rank时放行；无法评估（Stale）时默认拒绝，可通过allow_stale放行；未知的风险等级总是拒绝
+/// </pre>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WechatMaRiskPolicy {
+    pub max_rank: WechatMaRiskRank,
+    pub allow_stale: bool,
+}
+
+impl WechatMaRiskPolicy {
+    pub fn new(max_rank: WechatMaRiskRank) -> Self {
+        WechatMaRiskPolicy {
+            max_rank,
+            allow_stale: false,
+        }
+    }
+
+    pub fn allow_stale(mut self, allow_stale: bool) -> Self {
+        self.allow_stale = allow_stale;
+        self
+    }
+
+    pub fn evaluate(&self, result: &WechatMaRiskResult) -> WechatMaRiskDecision {
+        match result {
+            WechatMaRiskResult::Stale if self.allow_stale => WechatMaRiskDecision::allow("用户近两小时内未访问小程序，按策略放行"),
+            WechatMaRiskResult::Stale => WechatMaRiskDecision::deny("用户近两小时内未访问小程序，无法评估风险"),
+            WechatMaRiskResult::Ranked { rank, .. } => match (rank.level(), self.max_rank.level()) {
+                (Some(level), Some(max)) if level <= max => WechatMaRiskDecision::allow(format!("风险等级{}不超过{}", level, max)),
+                (Some(level), Some(max)) => WechatMaRiskDecision::deny(format!("风险等级{}超过可接受的{}", level, max)),
+                _ => WechatMaRiskDecision::deny(format!("未知的风险等级{}", i32::from(*rank))),
+            },
+        }
+    }
+}
+
+/// 风控策略的判定结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatMaRiskDecision {
+    pub allowed: bool,
+    /// 判定原因
+    pub reason: String,
+}
+
+impl WechatMaRiskDecision {
+    fn allow<S: Into<String>>(reason: S) -> Self {
+        WechatMaRiskDecision { allowed: true, reason: reason.into() }
+    }
+
+    fn deny<S: Into<String>>(reason: S) -> Self {
+        WechatMaRiskDecision { allowed: false, reason: reason.into() }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::{WechatMaRiskPolicy, WechatMaRiskRank, WechatMaRiskResult, WechatMaRiskScene, WechatMaUserRiskRankRequest};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_risk_rank_mapping() {
+        for v in 0..=4 {
+            let rank = WechatMaRiskRank::from(v);
+            assert_eq!(rank.level(), Some(v));
+            assert_eq!(serde_json::to_value(rank).unwrap(), json!(v));
+        }
+        assert_eq!(serde_json::from_value::<WechatMaRiskRank>(json!(4)).unwrap(), WechatMaRiskRank::Rank4);
+        assert_eq!(WechatMaRiskRank::from(7), WechatMaRiskRank::Unknown(7));
+        assert_eq!(WechatMaRiskRank::Unknown(7).level(), None);
+    }
+
+    #[test]
+    fn test_policy_evaluate() {
+        let policy = WechatMaRiskPolicy::new(WechatMaRiskRank::Rank1);
+        let ranked = |v: i32| WechatMaRiskResult::Ranked { rank: WechatMaRiskRank::from(v), union_id: None };
+        assert!(policy.evaluate(&ranked(0)).allowed);
+        assert!(policy.evaluate(&ranked(1)).allowed);
+        let decision = policy.evaluate(&ranked(3));
+        assert!(!decision.allowed);
+        assert!(decision.reason.contains('3'), "{}", decision.reason);
+        assert!(!policy.evaluate(&ranked(9)).allowed);
+        assert!(!policy.evaluate(&WechatMaRiskResult::Stale).allowed);
+        assert!(policy.allow_stale(true).evaluate(&WechatMaRiskResult::Stale).allowed);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_risk_rank() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"getuserriskrank succ","risk_rank":2,"unoin_id":123456}"#),
+            MockResponse::json(r#"{"errcode":61010,"errmsg":"user is not visit in 2h"}"#),
+            MockResponse::json(r#"{"errcode":61011,"errmsg":"invalid scene"}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_security_risk", "secret").base_url(&server.url);
+        let mut req = WechatMaUserRiskRankRequest::new("oahdg58, openid", WechatMaRiskScene::Marketing, "192.168.0.1");
+        req.mobile_no = "12345678".to_string().into();
+        assert_eq!(client.security().get_user_risk_rank(req.clone()).await.unwrap(), WechatMaRiskResult::Ranked { rank: WechatMaRiskRank::Rank2, union_id: Some(123456) });
+        assert_eq!(client.security().get_user_risk_rank(req.clone()).await.unwrap(), WechatMaRiskResult::Stale);
+        assert!(matches!(client.security().get_user_risk_rank(req.clone()).await.unwrap_err(), LabraError::ClientError { errcode, .. } if errcode == "61011"));
+        req.client_ip = "192.168.0".to_string();
+        assert!(matches!(client.security().get_user_risk_rank(req).await.unwrap_err(), LabraError::RequestError(_)));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].starts_with("POST /wxa/getuserriskrank?access_token=ACCESS_TOKEN HTTP/1.1"));
+        let body = serde_json::from_str::<Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({"appid": "wx_ma_security_risk", "openid": "oahdg58, openid", "scene": 1, "mobile_no": "12345678", "client_ip": "192.168.0.1"}));
+    }
+}
@@ -9,20 +9,26 @@ use crate::wechat::miniapp::WechatMaClient;
 
 /// 用户信息相关操作
 #[derive(Debug, Clone)]
-pub struct WechatMaUser<'a, T: SessionStore> {
-    client: &'a WechatMaClient<T>,
+pub struct WechatMaUser<T: SessionStore> {
+    client: WechatMaClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMaUser<'a, T> {
+impl<T: SessionStore> WechatMaUser<T> {
 
     #[inline]
-    pub fn new(client: &WechatMaClient<T>) -> WechatMaUser<T> {
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaUser<T> {
         WechatMaUser {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.user()`")]
+    pub fn new(client: &WechatMaClient<T>) -> WechatMaUser<T> {
+        Self::from_client(client.clone())
+    }
+
     /// 解密用户敏感数据
     pub fn decrypt_user_info(&self, session_key: &str, encrypted_data: &str, iv: &str) -> LabradorResult<WechatMaUserResponse> {
         let result = WechatCrypto::decrypt_data(session_key, encrypted_data, iv)?;
@@ -45,7 +51,7 @@ impl<'a, T: SessionStore> WechatMaUser<'a, T> {
             "kv_list": params
         });
         let signature = WechatCrypto::create_hmac_sha256_sign(session_key, &req.to_string())?;
-        self.client.post(WechatMaMethod::User(MaUserMethod::SetUserStorage), vec![("appid".to_string(), self.client.secret.to_string()),
+        self.client.post(WechatMaMethod::User(MaUserMethod::SetUserStorage), vec![("appid".to_string(), self.client.inner.secret.to_string()),
           ("signature".to_string(), signature),("openid".to_string(), openid.to_string()),("sig_method".to_string(), "hmac_sha256".to_string()),], &req, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
@@ -64,6 +70,31 @@ impl<'a, T: SessionStore> WechatMaUser<'a, T> {
         let result = WechatCrypto::decrypt_data(session_key, encrypted_data, iv)?;
         serde_json::from_str::<PhoneInfo>(&result).map_err(LabraError::from)
     }
+
+    /// 用户支付完成后，获取该用户的UnionId，无需用户授权.
+    /// <pre>
+    /// 仅在用户支付完成后的5分钟内有效，且需要小程序已绑定到微信开放平台帐号下
+    /// 文档地址：<a href="https://developers.weixin.qq.com/miniprogram/dev/OpenApiDoc/user-info/basic-info/getPaidUnionid.html">文档</a>
+    /// </pre>
+    pub async fn get_paid_unionid(&self, openid: &str, transaction_id: &str) -> LabradorResult<String> {
+        let v = self.client.get(WechatMaMethod::User(MaUserMethod::GetPaidUnionid), vec![
+            ("openid".to_string(), openid.to_string()),
+            ("transaction_id".to_string(), transaction_id.to_string()),
+        ], RequestType::Json).await?.json::<serde_json::Value>()?;
+        let v = WechatCommonResponse::parse::<serde_json::Value>(v)?;
+        Ok(v["unionid"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// 用户支付完成后，通过微信支付商户订单号获取该用户的UnionId.
+    pub async fn get_paid_unionid_by_out_trade_no(&self, openid: &str, mch_id: &str, out_trade_no: &str) -> LabradorResult<String> {
+        let v = self.client.get(WechatMaMethod::User(MaUserMethod::GetPaidUnionid), vec![
+            ("openid".to_string(), openid.to_string()),
+            ("mch_id".to_string(), mch_id.to_string()),
+            ("out_trade_no".to_string(), out_trade_no.to_string()),
+        ], RequestType::Json).await?.json::<serde_json::Value>()?;
+        let v = WechatCommonResponse::parse::<serde_json::Value>(v)?;
+        Ok(v["unionid"].as_str().unwrap_or_default().to_string())
+    }
 }
 
 //----------------------------------------------------------------------------------------------------------------------------
@@ -93,3 +124,28 @@ pub struct PhoneInfo {
     /// 区号
     pub country_code: Vec<String>,
 }
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::SimpleStorage;
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_get_paid_unionid() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"unionid":"oTmHYjg-tElZ68xxxxxxxxhy1Rgk","errcode":0,"errmsg":"ok"}"#),
+            MockResponse::json(r#"{"errcode":89300,"errmsg":"订单无效"}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_paid_unionid", "secret").base_url(&server.url);
+        let unionid = client.user().get_paid_unionid("OPENID", "4200000001201811162363945412").await.unwrap();
+        assert_eq!(unionid, "oTmHYjg-tElZ68xxxxxxxxhy1Rgk");
+        assert!(server.requests()[1].starts_with("GET /wxa/getpaidunionid?openid=OPENID&transaction_id=4200000001201811162363945412&access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(client.user().get_paid_unionid("OPENID", "UNKNOWN").await.is_err());
+    }
+}
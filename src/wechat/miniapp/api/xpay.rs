@@ -0,0 +1,286 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, LabradorResult, LabraError};
+use crate::prp::PrpCrypto;
+use crate::wechat::miniapp::method::{MaXPayMethod, WechatMaMethod};
+use crate::wechat::miniapp::WechatMaClient;
+
+/// 虚拟支付
+///
+/// 每次请求需要两个HMAC-SHA256签名，均以query参数附加：
+/// *   pay_sig：支付签名，以AppKey为密钥，对`接口路径 + "&" + 请求体`签名，见[`xpay_pay_sig`]
+/// *   signature：用户态签名，以用户的session_key为密钥，对请求体签名，见[`xpay_user_sig`]
+///
+/// env为1（沙箱环境）时使用沙箱AppKey。
+///
+/// [文档地址](https://developers.weixin.qq.com/miniprogram/dev/platform-capabilities/industry/virtual-payment.html)
+#[derive(Debug, Clone)]
+pub struct WechatMaXPay<T: SessionStore> {
+    client: WechatMaClient<T>,
+    app_key: Option<String>,
+    sandbox_app_key: Option<String>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMaXPay<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMaClient<T>) -> WechatMaXPay<T> {
+        WechatMaXPay {
+            client,
+            app_key: None,
+            sandbox_app_key: None,
+        }
+    }
+
+    /// 现网AppKey，在小程序管理后台虚拟支付的基础配置中查看
+    pub fn app_key(mut self, app_key: &str) -> Self {
+        self.app_key = app_key.to_string().into();
+        self
+    }
+
+    /// 沙箱AppKey，env为1时使用
+    pub fn sandbox_app_key(mut self, sandbox_app_key: &str) -> Self {
+        self.sandbox_app_key = sandbox_app_key.to_string().into();
+        self
+    }
+
+    /// <pre>
+    /// 查询用户代币余额
+    /// session_key为用户的会话密钥，可通过`code_session().get_session_key(openid)`取得缓存的session_key
+    /// </pre>
+    pub async fn query_user_balance(&self, req: WechatMaXPayUserBalanceRequest, session_key: &str) -> LabradorResult<WechatMaXPayUserBalance> {
+        let v = self.post(MaXPayMethod::QueryUserBalance, req.env, &req, Some(session_key)).await?;
+        WechatCommonResponse::parse::<WechatMaXPayUserBalance>(v)
+    }
+
+    /// 扣减代币（一般用于代币支付）
+    pub async fn currency_pay(&self, req: WechatMaXPayCurrencyPayRequest, session_key: &str) -> LabradorResult<WechatMaXPayCurrencyPayResponse> {
+        let v = self.post(MaXPayMethod::CurrencyPay, req.env, &req, Some(session_key)).await?;
+        WechatCommonResponse::parse::<WechatMaXPayCurrencyPayResponse>(v)
+    }
+
+    /// 代币支付退款，退回`currency_pay`扣减的代币
+    pub async fn cancel_currency_pay(&self, req: WechatMaXPayCancelCurrencyPayRequest, session_key: &str) -> LabradorResult<WechatMaXPayCancelCurrencyPayResponse> {
+        let v = self.post(MaXPayMethod::CancelCurrencyPay, req.env, &req, Some(session_key)).await?;
+        WechatCommonResponse::parse::<WechatMaXPayCancelCurrencyPayResponse>(v)
+    }
+
+    /// 代币赠送，不需要用户态签名
+    pub async fn present_currency(&self, req: WechatMaXPayPresentCurrencyRequest) -> LabradorResult<WechatMaXPayPresentCurrencyResponse> {
+        let v = self.post(MaXPayMethod::PresentCurrency, req.env, &req, None).await?;
+        WechatCommonResponse::parse::<WechatMaXPayPresentCurrencyResponse>(v)
+    }
+
+    /// <pre>
+    /// 签名并发送请求
+    /// 请求体先转为`Value`再签名，与实际发送（重试时同样按`Value`序列化）的内容一致
+    /// </pre>
+    async fn post<D: Serialize>(&self, method: MaXPayMethod, env: WechatMaXPayEnv, data: &D, session_key: Option<&str>) -> LabradorResult<Value> {
+        let app_key = match env {
+            WechatMaXPayEnv::Sandbox => self.sandbox_app_key.as_deref().ok_or_else(|| LabraError::InvalidConfig { field: "xpay.sandbox_app_key".to_string(), message: "沙箱环境需要设置沙箱AppKey".to_string() })?,
+            WechatMaXPayEnv::Production => self.app_key.as_deref().ok_or_else(|| LabraError::InvalidConfig { field: "xpay.app_key".to_string(), message: "需要设置AppKey".to_string() })?,
+        };
+        let body = serde_json::to_value(data)?;
+        let post_body = serde_json::to_string(&body)?;
+        let mut querys = vec![("pay_sig".to_string(), xpay_pay_sig(&method.get_method(), &post_body, app_key)?)];
+        if let Some(session_key) = session_key {
+            querys.push(("signature".to_string(), xpay_user_sig(&post_body, session_key)?));
+        }
+        self.client.post(WechatMaMethod::XPay(method), querys, &body, RequestType::Json).await?.json::<Value>()
+    }
+}
+
+/// 支付签名：以AppKey为密钥，对`uri + "&" + post_body`做HMAC-SHA256，uri为接口路径（不含域名与query参数）
+pub fn xpay_pay_sig(uri: &str, post_body: &str, app_key: &str) -> LabradorResult<String> {
+    PrpCrypto::hmac_sha256_sign(app_key, &format!("{}&{}", uri, post_body))
+}
+
+/// 用户态签名：以用户的session_key为密钥，对post_body做HMAC-SHA256
+pub fn xpay_user_sig(post_body: &str, session_key: &str) -> LabradorResult<String> {
+    PrpCrypto::hmac_sha256_sign(session_key, post_body)
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+pub enum WechatMaXPayEnv {
+    /// 0：现网环境
+    Production,
+    /// 1：沙箱环境
+    Sandbox,
+}
+
+impl From<i32> for WechatMaXPayEnv {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => WechatMaXPayEnv::Sandbox,
+            _ => WechatMaXPayEnv::Production,
+        }
+    }
+}
+
+impl From<WechatMaXPayEnv> for i32 {
+    fn from(v: WechatMaXPayEnv) -> Self {
+        match v {
+            WechatMaXPayEnv::Production => 0,
+            WechatMaXPayEnv::Sandbox => 1,
+        }
+    }
+}
+
+/// 查询用户代币余额的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaXPayUserBalanceRequest {
+    /// 用户的openid
+    pub openid: String,
+    pub env: WechatMaXPayEnv,
+    /// 用户的客户端ip
+    pub user_ip: String,
+}
+
+/// 用户代币余额
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMaXPayUserBalance {
+    /// 代币总余额，包括赠送的代币
+    pub balance: i64,
+    /// 赠送的代币余额
+    pub present_balance: i64,
+    /// 累计有效充值金额
+    pub sum_save: i64,
+    /// 累计赠送金额
+    pub sum_present: i64,
+    /// 历史总增加的代币金额
+    pub sum_balance: i64,
+    /// 历史总消耗的代币金额
+    pub sum_cost: i64,
+    /// 是否满足首充活动标记
+    #[serde(default)]
+    pub first_save_flag: bool,
+}
+
+/// 扣减代币的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaXPayCurrencyPayRequest {
+    pub openid: String,
+    pub env: WechatMaXPayEnv,
+    pub user_ip: String,
+    /// 支付的代币数量
+    pub amount: i64,
+    /// 商户订单号，需要保证唯一
+    pub order_id: String,
+    /// 物品信息，JSON数组字符串，如`[{"productid":"物品id","unit_price":100,"quantity":1}]`
+    pub payitem: String,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMaXPayCurrencyPayResponse {
+    /// 商户订单号
+    pub order_id: String,
+    /// 扣减后的总余额
+    pub balance: i64,
+    /// 本次扣减中使用的赠送代币数量
+    #[serde(default)]
+    pub used_present_amount: i64,
+}
+
+/// 代币支付退款的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaXPayCancelCurrencyPayRequest {
+    pub openid: String,
+    pub env: WechatMaXPayEnv,
+    pub user_ip: String,
+    /// 代币支付时的商户订单号
+    pub pay_order_id: String,
+    /// 本次退款的商户订单号，需要保证唯一
+    pub order_id: String,
+    /// 退款的代币数量
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMaXPayCancelCurrencyPayResponse {
+    /// 退款的商户订单号
+    pub order_id: String,
+}
+
+/// 代币赠送的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMaXPayPresentCurrencyRequest {
+    pub openid: String,
+    pub env: WechatMaXPayEnv,
+    /// 赠送的商户订单号，需要保证唯一
+    pub order_id: String,
+    /// 赠送的代币数量
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMaXPayPresentCurrencyResponse {
+    /// 赠送后的总余额
+    pub balance: i64,
+    /// 赠送的商户订单号
+    pub order_id: String,
+    /// 赠送后的赠送代币余额
+    #[serde(default)]
+    pub present_balance: i64,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::miniapp::WechatMaClient;
+    use super::{xpay_pay_sig, xpay_user_sig, WechatMaXPayEnv, WechatMaXPayPresentCurrencyRequest, WechatMaXPayUserBalance, WechatMaXPayUserBalanceRequest};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    /// 官方文档签名示例的参数，签名值由`openssl dgst -sha256 -hmac`计算
+    #[test]
+    fn test_signature() {
+        let post_body = r#"{"openid":"xxx","user_ip":"127.0.0.1","env":0}"#;
+        assert_eq!(xpay_pay_sig("/xpay/query_user_balance", post_body, "12345").unwrap(), "bf7d8464010d1f3766c937d75ba269e79365001595eb35291d8e45b60c50c2c1");
+        assert_eq!(xpay_user_sig(post_body, "9hAb/NEYUlkaMBEsmFgzig==").unwrap(), "0752c249c2ebd270340899457a403941e1513800e29eb78afa67bd367ca273b1");
+    }
+
+    #[tokio::test]
+    async fn test_query_user_balance() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","balance":100,"present_balance":10,"sum_save":90,"sum_present":10,"sum_balance":100,"sum_cost":0,"first_save_flag":false}"#),
+        ]).await;
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_xpay", "secret").base_url(&server.url);
+        let xpay = client.xpay().app_key("APP_KEY").sandbox_app_key("SANDBOX_APP_KEY");
+        let req = WechatMaXPayUserBalanceRequest { openid: "OPENID".to_string(), env: WechatMaXPayEnv::Sandbox, user_ip: "127.0.0.1".to_string() };
+        let balance = xpay.query_user_balance(req, "SESSION_KEY").await.unwrap();
+        assert_eq!((balance.balance, balance.present_balance), (100, 10));
+
+        let requests = server.requests();
+        let (head, body) = requests[1].split_once("\r\n\r\n").unwrap();
+        let query = head.lines().next().unwrap().trim_start_matches("POST /xpay/query_user_balance?").trim_end_matches(" HTTP/1.1");
+        let query = serde_urlencoded::from_str::<Vec<(String, String)>>(query).unwrap();
+        let param = |name: &str| query.iter().find(|(k, _)| k == name).map(|(_, v)| v.to_owned());
+        assert_eq!(param("access_token").as_deref(), Some("ACCESS_TOKEN"));
+        // 沙箱环境使用沙箱AppKey，签名针对实际发送的请求体
+        assert_eq!(param("pay_sig"), Some(xpay_pay_sig("/xpay/query_user_balance", body, "SANDBOX_APP_KEY").unwrap()));
+        assert_eq!(param("signature"), Some(xpay_user_sig(body, "SESSION_KEY").unwrap()));
+        assert_eq!(serde_json::from_str::<Value>(body).unwrap(), json!({"openid": "OPENID", "env": 1, "user_ip": "127.0.0.1"}));
+    }
+
+    #[tokio::test]
+    async fn test_missing_app_key() {
+        let client = WechatMaClient::<SimpleStorage>::new("wx_ma_xpay_key", "secret");
+        let req = WechatMaXPayPresentCurrencyRequest { openid: "OPENID".to_string(), env: WechatMaXPayEnv::Production, order_id: "ORDER_1".to_string(), amount: 1 };
+        let err = client.xpay().sandbox_app_key("SANDBOX_APP_KEY").present_currency(req).await.unwrap_err();
+        assert!(matches!(err, LabraError::InvalidConfig { field, .. } if field == "xpay.app_key"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::RequestMethod;
+use crate::{AuthStyle, RequestMethod};
 
 #[allow(unused)]
 #[derive(Debug, PartialEq, Clone)]
@@ -14,8 +14,24 @@ pub enum WechatMaMethod {
     Media(MaMediaMethod),
     /// 消息相关
     Message(MaMessageMethod),
+    /// 云开发
+    Cloud(MaCloudMethod),
+    /// 插件管理
+    Plugin(MaPluginMethod),
+    /// 附近的小程序
+    NearbyPoi(MaNearbyPoiMethod),
+    /// 硬件设备
+    Device(MaDeviceMethod),
+    /// 运维中心
+    Operation(MaOperationMethod),
+    /// 安全风控
+    Security(MaSecurityMethod),
+    /// 虚拟支付
+    XPay(MaXPayMethod),
     /// 自定义方法
-    Custom(String)
+    Custom(String),
+    /// 自定义方法，并指定接口调用凭证的传递方式
+    CustomWithAuth { path: String, auth_style: AuthStyle },
 }
 
 
@@ -69,12 +85,165 @@ impl MaMessageMethod {
 }
 
 
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaCloudMethod {
+    InvokeCloudFunction,
+    DatabaseQuery,
+    DatabaseAdd,
+    DatabaseUpdate,
+    DatabaseDelete,
+    UploadFile,
+}
+
+
+#[allow(unused)]
+impl MaCloudMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaCloudMethod::InvokeCloudFunction => String::from("/tcb/invokecloudfunction"),
+            MaCloudMethod::DatabaseQuery => String::from("/tcb/databasequery"),
+            MaCloudMethod::DatabaseAdd => String::from("/tcb/databaseadd"),
+            MaCloudMethod::DatabaseUpdate => String::from("/tcb/databaseupdate"),
+            MaCloudMethod::DatabaseDelete => String::from("/tcb/databasedelete"),
+            MaCloudMethod::UploadFile => String::from("/tcb/uploadfile"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaDeviceMethod {
+    /// 获取设备票据
+    GetSnTicket,
+    /// 发送设备消息
+    SendDeviceSubscribeMsg,
+}
+
+#[allow(unused)]
+impl MaDeviceMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaDeviceMethod::GetSnTicket => String::from("/wxa/getsnticket"),
+            MaDeviceMethod::SendDeviceSubscribeMsg => String::from("/cgi-bin/message/device/subscribe/send"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaOperationMethod {
+    /// 实时日志查询
+    UserLogSearch,
+    /// 获取用户反馈列表
+    FeedbackList,
+    /// 获取反馈中的媒体文件
+    GetFeedbackMedia,
+}
+
+#[allow(unused)]
+impl MaOperationMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaOperationMethod::UserLogSearch => String::from("/wxaapi/userlog/userlog_search"),
+            MaOperationMethod::FeedbackList => String::from("/wxaapi/feedback/list"),
+            MaOperationMethod::GetFeedbackMedia => String::from("/cgi-bin/media/getfeedbackmedia"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaSecurityMethod {
+    /// 获取用户安全等级
+    GetUserRiskRank,
+}
+
+#[allow(unused)]
+impl MaSecurityMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaSecurityMethod::GetUserRiskRank => String::from("/wxa/getuserriskrank"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaXPayMethod {
+    /// 查询用户代币余额
+    QueryUserBalance,
+    /// 扣减代币
+    CurrencyPay,
+    /// 代币支付退款
+    CancelCurrencyPay,
+    /// 代币赠送
+    PresentCurrency,
+}
+
+#[allow(unused)]
+impl MaXPayMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaXPayMethod::QueryUserBalance => String::from("/xpay/query_user_balance"),
+            MaXPayMethod::CurrencyPay => String::from("/xpay/currency_pay"),
+            MaXPayMethod::CancelCurrencyPay => String::from("/xpay/cancel_currency_pay"),
+            MaXPayMethod::PresentCurrency => String::from("/xpay/present_currency"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaPluginMethod {
+    /// 使用插件的小程序调用
+    Plugin,
+    /// 插件开发者调用
+    DevPlugin,
+}
+
+
+#[allow(unused)]
+impl MaPluginMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaPluginMethod::Plugin => String::from("/wxa/plugin"),
+            MaPluginMethod::DevPlugin => String::from("/wxa/devplugin"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MaNearbyPoiMethod {
+    AddNearbyPoi,
+    DeleteNearbyPoi,
+    GetNearbyPoiList,
+    SetShowStatus,
+    GetMerchantCategory,
+}
+
+
+#[allow(unused)]
+impl MaNearbyPoiMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MaNearbyPoiMethod::AddNearbyPoi => String::from("/wxa/addnearbypoi"),
+            MaNearbyPoiMethod::DeleteNearbyPoi => String::from("/wxa/delnearbypoi"),
+            MaNearbyPoiMethod::GetNearbyPoiList => String::from("/wxa/getnearbypoilist"),
+            MaNearbyPoiMethod::SetShowStatus => String::from("/wxa/setnearbypoishowstatus"),
+            MaNearbyPoiMethod::GetMerchantCategory => String::from("/wxa/get_merchant_category"),
+        }
+    }
+}
+
 #[allow(unused)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum MaUserMethod {
     SetUserStorage,
     /// 获取手机号信息,基础库:2.21.2及以上
     GetPhoneNumber,
+    GetPaidUnionid,
 }
 
 
@@ -97,6 +266,7 @@ impl MaUserMethod {
         match *self {
             MaUserMethod::SetUserStorage => String::from("/wxa/set_user_storage"),
             MaUserMethod::GetPhoneNumber => String::from("/wxa/business/getuserphonenumber"),
+            MaUserMethod::GetPaidUnionid => String::from("/wxa/getpaidunionid"),
         }
     }
 }
@@ -109,10 +279,26 @@ impl RequestMethod for WechatMaMethod {
             WechatMaMethod::CodeSession => String::from("/sns/jscode2session"),
             WechatMaMethod::AccessToken => String::from("/cgi-bin/token"),
             WechatMaMethod::Custom(v) => v.to_string(),
+            WechatMaMethod::CustomWithAuth { path, .. } => path.to_string(),
             WechatMaMethod::User(v) => v.get_method(),
             WechatMaMethod::Media(v) => v.get_method(),
             WechatMaMethod::QrCode(v) => v.get_method(),
             WechatMaMethod::Message(v) => v.get_method(),
+            WechatMaMethod::Cloud(v) => v.get_method(),
+            WechatMaMethod::Plugin(v) => v.get_method(),
+            WechatMaMethod::NearbyPoi(v) => v.get_method(),
+            WechatMaMethod::Device(v) => v.get_method(),
+            WechatMaMethod::Operation(v) => v.get_method(),
+            WechatMaMethod::Security(v) => v.get_method(),
+            WechatMaMethod::XPay(v) => v.get_method(),
+        }
+    }
+
+    fn auth_style(&self) -> AuthStyle {
+        match self {
+            WechatMaMethod::CodeSession | WechatMaMethod::AccessToken => AuthStyle::None,
+            WechatMaMethod::CustomWithAuth { auth_style, .. } => *auth_style,
+            _ => AuthStyle::QueryParam,
         }
     }
 }
@@ -121,9 +307,6 @@ impl RequestMethod for WechatMaMethod {
 impl WechatMaMethod {
 
     pub fn need_token(&self) -> bool {
-        match self {
-            WechatMaMethod::CodeSession | WechatMaMethod::AccessToken => false,
-            _ => true,
-        }
+        self.auth_style() != AuthStyle::None
     }
 }
\ No newline at end of file
@@ -1,4 +1,8 @@
-use crate::{session::SessionStore, client::APIClient, request::{Method, RequestType, LabraResponse, LabraRequest, RequestMethod}, util::current_timestamp, LabradorResult, SimpleStorage, WechatCrypto, WechatRequest};
+use std::sync::Arc;
+
+use crate::{session::{SessionStore, DynSessionStore}, DnsOverrides, Resolve, MetricsRecorder, StoreFallbackPolicy, TokenInfo, TokenRefreshHook, DebugRecorder, DebugRecord, client::APIClient, request::{Method, RequestType, LabraResponse, LabraRequest, RequestMethod}, LabradorResult, SimpleStorage, WechatCrypto, WechatRequest};
+use crate::wechat::WECHAT_API_BASE_URL;
+use crate::wechat::token_cache::{TokenCache, TokenRequest};
 use serde::{Serialize, Deserialize};
 
 mod method;
@@ -9,14 +13,24 @@ mod constants;
 pub use api::*;
 use crate::wechat::miniapp::constants::{ACCESS_TOKEN, APPID, CLIENT_CREDENTIAL, GRANT_TYPE, SECRET};
 use crate::wechat::miniapp::method::WechatMaMethod;
+use crate::serde_helper::string_or_number;
 
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct WechatMaClient<T: SessionStore> {
+    inner: Arc<WechatMaClientInner<T>>,
+}
+
+/// 客户端配置与会话存储，克隆客户端时共享
+#[derive(Debug, Clone)]
+struct WechatMaClientInner<T: SessionStore> {
     appid: String,
     secret: String,
     token: Option<String>,
     aes_key: Option<String>,
+    /// 换取到新access_token时的回调
+    on_token_refresh: Option<TokenRefreshHook>,
+    tokens: TokenCache,
     client: APIClient<T>,
 }
 
@@ -24,79 +38,191 @@ pub struct WechatMaClient<T: SessionStore> {
 #[derive(Serialize, Deserialize)]
 pub struct AccessTokenResponse{
     pub access_token: String,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
+impl WechatMaClient<DynSessionStore> {
+    /// <pre>
+    /// 使用运行时选择的会话存储创建客户端
+    /// 例如按配置在内存与Redis之间切换时，客户端类型保持为`WechatMaClient<DynSessionStore>`
+    /// </pre>
+    pub fn new_dyn<S: Into<String>>(appid: S, secret: S, session: DynSessionStore) -> WechatMaClient<DynSessionStore> {
+        Self::from_session(appid, secret, session)
+    }
+}
+
 #[allow(unused)]
 impl<T: SessionStore> WechatMaClient<T> {
 
     fn from_client(client: APIClient<T>) -> WechatMaClient<T> {
         WechatMaClient {
-            appid: client.app_key.to_owned(),
-            secret: client.secret.to_owned(),
-            token: None,
-            aes_key: None,
-            client
+            inner: Arc::new(WechatMaClientInner {
+                appid: client.app_key.to_owned(),
+                secret: client.secret.to_owned(),
+                token: None,
+                aes_key: None,
+                on_token_refresh: None,
+                tokens: TokenCache::default(),
+                client
+            }),
         }
     }
 
     pub fn aes_key(mut self, aes_key: &str) -> Self {
-        self.aes_key = aes_key.to_string().into();
+        Arc::make_mut(&mut self.inner).aes_key = aes_key.to_string().into();
         self
     }
 
     pub fn token(mut self, token: &str) -> Self {
-        self.token = token.to_string().into();
+        Arc::make_mut(&mut self.inner).token = token.to_string().into();
         self
     }
 
     /// get the wechat client
     pub fn new<S: Into<String>>(appid: S, secret: S) -> WechatMaClient<SimpleStorage> {
-        let client = APIClient::<SimpleStorage>::from_session(appid.into(), secret.into(), "https://api.weixin.qq.com", SimpleStorage::new());
+        let client = APIClient::<SimpleStorage>::from_session(appid.into(), secret.into(), WECHAT_API_BASE_URL, SimpleStorage::new());
         WechatMaClient::<SimpleStorage>::from_client(client)
     }
 
     /// get the wechat client
     pub fn from_session<S: Into<String>>(appid: S, secret: S, session: T) -> WechatMaClient<T> {
-        let client = APIClient::from_session(appid.into(), secret.into(), "https://api.weixin.qq.com", session);
+        let client = APIClient::from_session(appid.into(), secret.into(), WECHAT_API_BASE_URL, session);
         Self::from_client(client)
     }
 
+    /// 替换接口域名（默认[`WECHAT_API_BASE_URL`]），如私有网关、就近接入域名
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().api_path(base_url);
+        self
+    }
+
+    /// 备用域名，连接失败时按顺序切换，如`WECHAT_API2_BASE_URL`
+    pub fn fallback_urls(mut self, fallback_urls: Vec<&str>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().fallback_paths(fallback_urls);
+        self
+    }
+
+    /// 为接口域名指定固定IP，见[`DnsOverrides`]，TLS校验与Host请求头仍使用原域名
+    pub fn dns_overrides(mut self, overrides: DnsOverrides) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().dns_overrides(overrides);
+        self
+    }
+
+    /// 使用自定义的域名解析器
+    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().dns_resolver(resolver);
+        self
+    }
+
+    /// 请求指标记录，每次请求（含切换备用域名的重试）都会回调
+    pub fn metrics_recorder(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().metrics_recorder(metrics);
+        self
+    }
+
+    /// 开启请求调试记录，在内存中保留最近的请求/响应报文，URL中的凭证会被隐去
+    pub fn debug_recorder(mut self, debug: Arc<DebugRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().debug_recorder(debug);
+        self
+    }
+
+    /// 当前保留的请求调试记录，未开启时为空
+    pub fn debug_records(&self) -> Vec<DebugRecord> {
+        self.inner.client.debug_records()
+    }
+
+    /// <pre>
+    /// 换取到新access_token时回调，如主动推送给无法接入本库的旧系统
+    /// 回调在写入会话存储之后、不持有任何锁时调用，每次换取调用一次；请勿在回调中阻塞
+    /// </pre>
+    pub fn on_token_refresh<F>(mut self, callback: F) -> Self where F: Fn(&TokenInfo) + Send + Sync + 'static {
+        Arc::make_mut(&mut self.inner).on_token_refresh = TokenRefreshHook::new(callback).into();
+        self
+    }
+
+    /// <pre>
+    /// 会话存储（如Redis）不可用时降级：直接向微信换取access_token并缓存在进程内，定期重试会话存储
+    /// 未配置时读取会话存储出错直接返回错误
+    /// </pre>
+    pub fn store_fallback(mut self, policy: StoreFallbackPolicy) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.tokens = inner.tokens.with_policy(policy);
+        self
+    }
+
+    /// 会话存储是否可用，配置了`store_fallback`且读写出错时为false，重试成功后恢复为true
+    pub fn store_healthy(&self) -> bool {
+        self.inner.tokens.store_healthy()
+    }
+
     #[inline]
     pub async fn access_token(&self, force_refresh: bool) -> LabradorResult<String> {
-        let mut session = self.client.session();
-        let token_key = format!("{}_access_token", self.appid);
-        let expires_key = format!("{}_expires_at", self.appid);
-        let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
-        let timestamp = current_timestamp();
-        let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
-        if expires_at <= timestamp || force_refresh {
-            let mut req = LabraRequest::<String>::new().url(WechatMaMethod::AccessToken.get_method()).params(vec![
+        self.token_info(force_refresh).await.map(|info| info.token)
+    }
+
+    /// <pre>
+    /// 获取access_token及其过期时间，缓存过期时会重新换取
+    /// 用于将access_token交给其他系统使用
+    /// </pre>
+    pub async fn access_token_info(&self) -> LabradorResult<TokenInfo> {
+        self.token_info(false).await
+    }
+
+    async fn token_info(&self, force_refresh: bool) -> LabradorResult<TokenInfo> {
+        // 与公众号客户端区分开，共用会话存储时互不覆盖
+        let token_key = format!("{}_access_token_ma", self.inner.appid);
+        let expires_key = format!("{}_expires_at_ma", self.inner.appid);
+        let token_req = TokenRequest { session: self.inner.client.session(), token_key: &token_key, expires_key: &expires_key, appid: &self.inner.appid, metrics: self.inner.client.metrics() };
+        let (info, refreshed) = self.inner.tokens.token_info(&token_req, force_refresh, || async {
+            let req = LabraRequest::<String>::new().url(WechatMaMethod::AccessToken.get_method()).params(vec![
                 (GRANT_TYPE.to_string(), CLIENT_CREDENTIAL.to_string()),
-                (APPID.to_string(), self.client.app_key.to_string()),
-                (SECRET.to_string(), self.client.secret.to_string()),
+                (APPID.to_string(), self.inner.client.app_key.to_string()),
+                (SECRET.to_string(), self.inner.client.secret.to_string()),
             ]).method(Method::Get).req_type(RequestType::Json);
-            let res = self.client.request(req).await?.json::<AccessTokenResponse>()?;
-            let token = res.access_token;
-            let expires_in = res.expires_in;
-            // 预留200秒的时间
-            let expires_at = current_timestamp() + expires_in - 200;
-            session.set(&token_key, token.to_owned(), Some(expires_in as usize));
-            session.set(&expires_key, expires_at, Some(expires_in as usize));
-            Ok(token)
-        } else {
-            Ok(token)
+            let res = self.inner.client.request(req).await?.json::<AccessTokenResponse>()?;
+            Ok((res.access_token, res.expires_in))
+        }).await?;
+        if refreshed {
+            TokenRefreshHook::notify(&self.inner.on_token_refresh, &info);
         }
+        Ok(info)
+    }
+
+    /// <pre>
+    /// 启动access_token后台刷新，需要在tokio运行时中调用
+    /// 在缓存的access_token过期前`margin`强制刷新并写入会话存储，刷新失败时退避重试；
+    /// 丢弃或abort返回的句柄即停止刷新，后台任务未能及时刷新时请求仍会自行刷新
+    /// </pre>
+    #[cfg(feature = "refresher")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "refresher")))]
+    pub fn spawn_token_refresher(&self, margin: std::time::Duration) -> crate::TokenRefreshHandle where T: Send + Sync + 'static {
+        let client = self.clone();
+        let expires_key = format!("{}_expires_at_ma", self.inner.appid);
+        let session = self.inner.client.session().to_owned();
+        crate::wechat::refresher::spawn_token_refresher(self.inner.appid.to_owned(), margin, move || {
+            session.get::<_, i64>(&expires_key, Some(0)).map(Option::unwrap_or_default)
+        }, move || {
+            let client = client.clone();
+            async move { client.access_token(true).await }
+        })
     }
 
     ///
     /// <pre>
     /// 验证消息的确来自微信服务器.
+    /// signature只由token、timestamp、nonce生成，echo_str不参与签名
     /// 详情(http://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1421135319&token=&lang=zh_CN)
     /// </pre>
-    pub fn check_signature(&self, signature: &str, timestamp: i64, nonce: &str, echo_str: &str) -> LabradorResult<bool> {
-        let crp = WechatCrypto::new(&self.aes_key.to_owned().unwrap_or_default());
-        let _ = crp.check_signature(signature, timestamp, nonce, echo_str, &self.token.to_owned().unwrap_or_default())?;
+    pub fn check_signature(&self, signature: &str, timestamp: i64, nonce: &str, _echo_str: &str) -> LabradorResult<bool> {
+        let crp = WechatCrypto::new(&self.inner.aes_key.to_owned().unwrap_or_default());
+        let _ = crp.check_signature(signature, timestamp, nonce, "", &self.inner.token.to_owned().unwrap_or_default())?;
         Ok(true)
     }
 
@@ -115,47 +241,81 @@ impl<T: SessionStore> WechatMaClient<T> {
         let params = querys.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<(String, String)>>();
         let mut req = LabraRequest::<B>::new().url(request.get_api_method_name())
             .params(params).method(request.get_request_method()).req_type(request.get_request_type()).body(request.get_request_body::<B>());
-        self.client.request(req).await
+        if let Some(timeout) = request.get_timeout() {
+            req = req.timeout(timeout);
+        }
+        self.inner.client.request(req).await
     }
 
     /// 发送POST请求
     async fn post<D: Serialize>(&self, method: WechatMaMethod, mut querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let access_token = self.access_token(false).await?;
-        if !access_token.is_empty() && method.need_token() {
-            querys.push((ACCESS_TOKEN.to_string(), access_token));
-        }
-        self.client.post(method, querys, data, request_type).await
+        let headers = self.attach_token(&method, &mut querys).await?;
+        self.inner.client.post_with_headers(method, querys, headers, data, request_type).await
     }
 
     /// 发送GET请求
     async fn get(&self, method: WechatMaMethod, mut params: Vec<(String, String)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let access_token = self.access_token(false).await?;
-        if !access_token.is_empty() && method.need_token() {
-            params.push((ACCESS_TOKEN.to_string(), access_token));
+        let headers = self.attach_token(&method, &mut params).await?;
+        self.inner.client.get_with_headers(method, params, headers, request_type).await
+    }
+
+    /// 按接口的`AuthStyle`附加access_token，返回需要附加的请求头
+    async fn attach_token(&self, method: &WechatMaMethod, params: &mut Vec<(String, String)>) -> LabradorResult<Vec<(String, String)>> {
+        if !method.need_token() {
+            return Ok(vec![]);
         }
-        self.client.get(method, params, request_type).await
+        let access_token = self.access_token(false).await?;
+        Ok(method.auth_style().attach(ACCESS_TOKEN, &access_token, params))
     }
 
     /// codesssion相关服务
     pub fn code_session(&self) -> WechatMaCodeSession<T> {
-        WechatMaCodeSession::new(self)
+        WechatMaCodeSession::from_client(self.clone())
     }
 
     /// 二维码相关操作接口
     pub fn qrcode(&self) -> WechatMaQrcode<T> {
-        WechatMaQrcode::new(self)
+        WechatMaQrcode::from_client(self.clone())
     }
     /// 用户相关操作接口
     pub fn user(&self) -> WechatMaUser<T> {
-        WechatMaUser::new(self)
+        WechatMaUser::from_client(self.clone())
     }
     /// 媒体操作接口
     pub fn media(&self) -> WechatMaMedia<T> {
-        WechatMaMedia::new(self)
+        WechatMaMedia::from_client(self.clone())
     }
     /// 媒体操作接口
     pub fn message(&self) -> WechatMaMessage<T> {
-        WechatMaMessage::new(self)
+        WechatMaMessage::from_client(self.clone())
+    }
+    /// 云开发接口
+    pub fn cloud(&self) -> WechatMaCloud<T> {
+        WechatMaCloud::from_client(self.clone())
+    }
+    /// 插件管理接口
+    pub fn plugin(&self) -> WechatMaPlugin<T> {
+        WechatMaPlugin::from_client(self.clone())
+    }
+    /// 附近的小程序接口
+    pub fn nearby_poi(&self) -> WechatMaNearbyPoi<T> {
+        WechatMaNearbyPoi::from_client(self.clone())
+    }
+    /// 硬件设备接口
+    pub fn hardware_device(&self) -> WechatMaHardwareDevice<T> {
+        WechatMaHardwareDevice::from_client(self.clone())
+    }
+    /// 运维中心接口
+    pub fn operation(&self) -> WechatMaOperation<T> {
+        WechatMaOperation::from_client(self.clone())
+    }
+    /// 安全风控接口
+    pub fn security(&self) -> WechatMaSecurity<T> {
+        WechatMaSecurity::from_client(self.clone())
+    }
+    /// 虚拟支付接口，需设置AppKey，见[`WechatMaXPay::app_key`]
+    pub fn xpay(&self) -> WechatMaXPay<T> {
+        WechatMaXPay::from_client(self.clone())
     }
 
 }
@@ -1,23 +1,177 @@
 use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
+#[cfg(feature = "wechat-mp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wechat-mp")))]
 mod mp;
+#[cfg(feature = "wechat-cp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wechat-cp")))]
 mod cp;
+#[cfg(feature = "wechat-pay")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wechat-pay")))]
 mod pay;
 mod cryptos;
-mod miniapp;
+mod envelope;
+#[cfg(feature = "wechat-ma")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wechat-ma")))]
+pub mod miniapp;
 #[allow(unused)]
 mod constants;
+#[cfg(feature = "wechat-mp")]
 mod msg_parser;
+#[cfg(feature = "refresher")]
+#[cfg_attr(docsrs, doc(cfg(feature = "refresher")))]
+mod refresher;
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma"))]
+mod category;
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma"))]
+mod subscribe_data;
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma", feature = "wechat-cp"))]
+mod token_cache;
 
+#[cfg(feature = "wechat-cp")]
 pub use cp::*;
+#[cfg(feature = "wechat-mp")]
 pub use mp::*;
+#[cfg(feature = "wechat-pay")]
 pub use pay::*;
 pub use cryptos::*;
+pub use envelope::*;
+#[cfg(feature = "wechat-mp")]
 pub use msg_parser::*;
+#[cfg(feature = "refresher")]
+pub use refresher::*;
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma"))]
+pub use category::*;
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma"))]
+pub use subscribe_data::*;
+#[cfg(any(feature = "wechat-mp", feature = "wechat-ma", feature = "wechat-cp"))]
+pub use token_cache::StoreFallbackPolicy;
 use crate::{LabradorResult, LabraError, Method, RequestBody, RequestType};
+use crate::serde_helper::{datetime_from_seconds, option_string_or_number, string_or_number};
+
+/// 公众号/小程序接口默认域名
+pub const WECHAT_API_BASE_URL: &str = "https://api.weixin.qq.com";
+/// 公众号/小程序容灾域名，默认域名不可用时使用
+pub const WECHAT_API2_BASE_URL: &str = "https://api2.weixin.qq.com";
+/// 公众号/小程序上海就近接入域名
+pub const WECHAT_SH_API_BASE_URL: &str = "https://sh.api.weixin.qq.com";
+/// 公众号/小程序深圳就近接入域名
+pub const WECHAT_SZ_API_BASE_URL: &str = "https://sz.api.weixin.qq.com";
+/// 公众号/小程序香港就近接入域名
+pub const WECHAT_HK_API_BASE_URL: &str = "https://hk.api.weixin.qq.com";
+/// 企业微信接口域名
+pub const WECHAT_CP_BASE_URL: &str = "https://qyapi.weixin.qq.com";
+/// 微信支付接口默认域名
+pub const WECHAT_PAY_BASE_URL: &str = "https://api.mch.weixin.qq.com";
+/// 微信支付容灾域名
+pub const WECHAT_PAY2_BASE_URL: &str = "https://api2.mch.weixin.qq.com";
+
+
+#[allow(unused)]
+#[derive(Serialize, Deserialize)]
+pub struct JsapiTicket {
+    pub ticket: String,
+    #[serde(with = "string_or_number")]
+    pub expires_in: i64,
+}
+
+#[allow(unused)]
+#[derive(Serialize, Deserialize)]
+pub struct JsapiSignature {
+    pub app_id: String,
+    #[serde(rename="nonceStr")]
+    pub nonce_str: String,
+    pub url: String,
+    pub signature: String,
+    pub timestamp: i64,
+}
+
+/// <pre>
+/// 当前的access_token及其过期时间
+/// `expires_at`为客户端缓存的失效时间，已比微信返回的有效期提前200秒
+/// </pre>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[allow(unused)]
+impl TokenInfo {
+    fn from_cache(token: String, expires_at: i64) -> LabradorResult<Self> {
+        Ok(TokenInfo {
+            token,
+            expires_at: datetime_from_seconds(expires_at)?,
+        })
+    }
+}
+
+/// 换取到新access_token时的回调，见`on_token_refresh`
+#[derive(Clone)]
+pub struct TokenRefreshHook(Arc<dyn Fn(&TokenInfo) + Send + Sync>);
+
+impl TokenRefreshHook {
+    pub fn new<F>(callback: F) -> Self where F: Fn(&TokenInfo) + Send + Sync + 'static {
+        TokenRefreshHook(Arc::new(callback))
+    }
+
+    /// 在写入会话存储之后、不持有任何锁时调用
+    #[allow(unused)]
+    fn notify(hook: &Option<TokenRefreshHook>, info: &TokenInfo) {
+        if let Some(hook) = hook {
+            (hook.0)(info)
+        }
+    }
+}
+
+impl fmt::Debug for TokenRefreshHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenRefreshHook")
+    }
+}
+
+/// 图文消息article.
+/// 1. thumbMediaId  (必填) 图文消息的封面图片素材id（必须是永久mediaID）
+/// 2. author          图文消息的作者
+/// 3. title           (必填) 图文消息的标题
+/// 4. contentSourceUrl 在图文消息页面点击“阅读原文”后的页面链接
+/// 5. content (必填)  图文消息页面的内容，支持HTML标签
+/// 6. digest          图文消息的描述
+/// 7. showCoverPic  是否显示封面，true为显示，false为不显示
+/// 8. url           点击图文消息跳转链接
+/// 9. need_open_comment（新增字段） 否 Uint32 是否打开评论，0不打开，1打开
+/// 10. only_fans_can_comment（新增字段） 否 Uint32 是否粉丝才可评论，0所有人可评论，1粉丝才可评论
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpNewsArticle {
+    /// (必填) 图文消息缩略图的media_id，可以在基础支持-上传多媒体文件接口中获得.
+    pub thumb_media_id: String,
+    /// 图文消息的封面url
+    pub thumb_url: Option<String>,
+    /// 图文消息的作者
+    pub author: Option<String>,
+    /// (必填) 图文消息的标题.
+    pub title: String,
+    /// 在图文消息页面点击“阅读原文”后的页面链接.
+    pub content_source_url: Option<String>,
+    /// (必填) 图文消息页面的内容，支持HTML标签.
+    pub content: String,
+    /// 图文消息的描述
+    pub digest: Option<String>,
+    /// 是否显示封面，true为显示，false为不显示.
+    pub show_cover_pic: bool,
+    /// 点击图文消息跳转链接
+    pub url: Option<String>,
+    /// 是否打开评论，0不打开，1打开.
+    pub need_open_comment: Option<u8>,
+    /// 是否粉丝才可评论，0所有人可评论，1粉丝才可评论.
+    pub only_fans_can_comment: Option<u8>,
+}
 
 
 pub trait WechatRequest {
@@ -50,11 +204,17 @@ pub trait WechatRequest {
         true
     }
 
+    /// 本次请求的超时时间，上传、下载大文件时可单独设置
+    fn get_timeout(&self) -> Option<std::time::Duration> {
+        None
+    }
+
 }
 
 #[allow(unused)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatCommonResponse {
+    #[serde(default, with = "option_string_or_number")]
     pub errcode: Option<i64>,
     pub errmsg: Option<String>,
     pub body: Option<String>,
@@ -66,26 +226,17 @@ impl WechatCommonResponse {
     }
 
     pub fn parse<T: DeserializeOwned>(v: Value) -> LabradorResult<T> {
-        let resp = serde_json::from_value::<Self>(v.to_owned())?;
-        if resp.is_success() {
-            serde_json::from_str::<T>(&v.to_string()).map_err(LabraError::from)
-        } else {
-            Err(LabraError::ClientError { errcode: resp.errcode.to_owned().unwrap_or_default().to_string(), errmsg: resp.errmsg.to_owned().unwrap_or_default() })
-        }
+        WechatEnvelope::new().extract_value::<T>(v)
     }
 
     pub fn parse_with_key<T: DeserializeOwned>(v: Value, key: &str) -> LabradorResult<T> {
-        let resp = serde_json::from_value::<Self>(v.to_owned())?;
-        if resp.is_success() {
-            let v = serde_json::from_str::<Value>(&v.to_string())?;
-            let result = &v[key];
-            if result.is_string() {
-                serde_json::from_str::<T>(result.as_str().unwrap_or_default()).map_err(LabraError::from)
-            } else {
-                serde_json::from_value::<T>(v[key].to_owned()).map_err(LabraError::from)
-            }
+        let envelope = WechatEnvelope::new();
+        let v = envelope.extract_value::<Value>(v)?;
+        let result = &v[key];
+        if result.is_string() {
+            serde_json::from_str::<T>(result.as_str().unwrap_or_default()).map_err(|err| envelope.unexpected(err, &v.to_string()))
         } else {
-            Err(LabraError::ClientError { errcode: resp.errcode.to_owned().unwrap_or_default().to_string(), errmsg: resp.errmsg.to_owned().unwrap_or_default() })
+            serde_json::from_value::<T>(v[key].to_owned()).map_err(|err| envelope.unexpected(err, &v.to_string()))
         }
     }
 
@@ -103,16 +254,18 @@ impl WechatCommonResponse {
 
     pub fn get_biz_model<T: DeserializeOwned>(&self, key: Option<&str>) -> LabradorResult<T> {
         if self.is_success() {
+            let body = self.body.to_owned().unwrap_or_default();
+            let envelope = WechatEnvelope::new();
             if let Some(key) = key {
-                let v = serde_json::from_str::<Value>(&self.body.to_owned().unwrap_or_default())?;
+                let v = serde_json::from_str::<Value>(&body)?;
                 let result = &v[key];
                 if result.is_string() {
-                    serde_json::from_str::<T>(result.as_str().unwrap_or_default()).map_err(LabraError::from)
+                    serde_json::from_str::<T>(result.as_str().unwrap_or_default()).map_err(|err| envelope.unexpected(err, &body))
                 } else {
-                    serde_json::from_value::<T>(v[key].to_owned()).map_err(LabraError::from)
+                    serde_json::from_value::<T>(v[key].to_owned()).map_err(|err| envelope.unexpected(err, &body))
                 }
             } else {
-                serde_json::from_str::<T>(&self.body.to_owned().unwrap_or_default()).map_err(LabraError::from)
+                serde_json::from_str::<T>(&body).map_err(|err| envelope.unexpected(err, &body))
             }
         } else {
             Err(LabraError::ClientError { errcode: self.errcode.to_owned().unwrap_or_default().to_string(), errmsg: self.errmsg.to_owned().unwrap_or_default() })
@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, SelfMenuInfoResponse, SelfMenuNewsButton};
+use crate::serde_helper::option_string_or_number;
+use crate::wechat::mp::method::{MpMenuMethod, WechatMpMethod};
+
+/// 公众号当前配置.
+/// <pre>
+/// 读取公众号在公众平台官网或通过API设置的自动回复规则与自定义菜单，格式与创建接口不同，且各字段的返回并不稳定，
+/// 因此类型、模式等均使用带`Unknown`的枚举，缺失的字段为None
+/// </pre>
+#[derive(Debug, Clone)]
+pub struct WechatMpAccount<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpAccount<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpAccount<T> {
+        WechatMpAccount {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.account()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpAccount<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 获取公众号的自动回复规则
+    /// 返回关注后自动回复、消息自动回复（默认回复）与关键词自动回复的配置。
+    /// 图片、语音为临时素材的media_id，视频为视频的URL，图文消息为永久素材的信息。
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Getting_Rules_for_Auto_Replies.html
+    /// </pre>
+    pub async fn get_current_autoreply_info(&self) -> LabradorResult<WechatMpAutoReplyInfo> {
+        let v = self.client.get(WechatMpMethod::GetCurrentAutoreplyInfo, vec![], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpAutoReplyInfo>(v)
+    }
+
+    /// <pre>
+    /// 获取公众号当前使用的自定义菜单配置
+    /// 无论菜单是通过API还是在公众平台官网设置的，均可查询到
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Custom_Menus/Querying_Custom_Menus.html
+    /// </pre>
+    pub async fn get_current_selfmenu_info(&self) -> LabradorResult<SelfMenuInfoResponse> {
+        let v = self.client.get(WechatMpMethod::Menu(MpMenuMethod::GetCurrentMenuInfo), vec![], RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<SelfMenuInfoResponse>(v)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 自动回复的消息类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WechatMpAutoReplyType {
+    /// 文本
+    Text,
+    /// 图片
+    Img,
+    /// 语音
+    Voice,
+    /// 视频
+    Video,
+    /// 图文消息
+    News,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 关键词规则的回复模式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WechatMpAutoReplyMode {
+    /// 全部回复
+    ReplyAll,
+    /// 随机回复其中一条
+    RandomOne,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 关键词的匹配模式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WechatMpKeywordMatchMode {
+    /// 消息中含有该关键词即可
+    Contain,
+    /// 消息内容必须和关键词严格相同
+    Equal,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 公众号的自动回复规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpAutoReplyInfo {
+    /// 关注后自动回复是否开启，0代表未开启，1代表开启
+    pub is_add_friend_reply_open: Option<u8>,
+    /// 消息自动回复是否开启，0代表未开启，1代表开启
+    pub is_autoreply_open: Option<u8>,
+    /// 关注后自动回复的信息
+    pub add_friend_autoreply_info: Option<WechatMpAutoReplyContent>,
+    /// 消息自动回复（默认回复）的信息
+    pub message_default_autoreply_info: Option<WechatMpAutoReplyContent>,
+    /// 关键词自动回复的信息
+    pub keyword_autoreply_info: Option<WechatMpKeywordAutoReplyInfo>,
+}
+
+/// 自动回复的内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpAutoReplyContent {
+    /// 消息类型
+    #[serde(rename = "type")]
+    pub reply_type: WechatMpAutoReplyType,
+    /// 文本为内容，图片、语音为media_id，视频为URL
+    pub content: Option<String>,
+    /// 图文消息的信息，仅关键词自动回复的图文消息返回
+    pub news_info: Option<SelfMenuNewsButton>,
+}
+
+/// 关键词自动回复的信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpKeywordAutoReplyInfo {
+    #[serde(default)]
+    pub list: Vec<WechatMpKeywordAutoReplyRule>,
+}
+
+/// 关键词自动回复规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpKeywordAutoReplyRule {
+    /// 规则名称
+    pub rule_name: Option<String>,
+    /// 创建时间
+    #[serde(default, with = "option_string_or_number")]
+    pub create_time: Option<i64>,
+    /// 回复模式
+    pub reply_mode: Option<WechatMpAutoReplyMode>,
+    /// 匹配的关键词列表
+    #[serde(default)]
+    pub keyword_list_info: Vec<WechatMpAutoReplyKeyword>,
+    /// 回复列表
+    #[serde(default)]
+    pub reply_list_info: Vec<WechatMpAutoReplyContent>,
+}
+
+/// 关键词
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpAutoReplyKeyword {
+    /// 关键词类型，通常为text
+    #[serde(rename = "type")]
+    pub keyword_type: Option<WechatMpAutoReplyType>,
+    /// 匹配模式
+    pub match_mode: Option<WechatMpKeywordMatchMode>,
+    /// 关键词
+    pub content: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatMpAutoReplyInfo, WechatMpAutoReplyMode, WechatMpAutoReplyType, WechatMpKeywordMatchMode};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    /// 只设置了文本关键词规则的公众号
+    const TEXT_RULE: &str = r#"{
+        "is_add_friend_reply_open": 1,
+        "is_autoreply_open": 1,
+        "add_friend_autoreply_info": {"type": "text", "content": "Thanks for your attention!"},
+        "message_default_autoreply_info": {"type": "text", "content": "Hello, this is autoreply!"},
+        "keyword_autoreply_info": {"list": [
+            {
+                "rule_name": "autoreply-text",
+                "create_time": 1423027926,
+                "reply_mode": "random_one",
+                "keyword_list_info": [
+                    {"type": "text", "match_mode": "contain", "content": "text测试"},
+                    {"type": "text", "match_mode": "equal", "content": "你好"}
+                ],
+                "reply_list_info": [
+                    {"type": "text", "content": "hello!text!"},
+                    {"type": "img", "content": "NESsxgHEvAcg3egJTtYj4uG1PTL6iPhratdWKDLAXYErhN6oEEfMdVyblWtBY5vp"}
+                ]
+            },
+            {
+                "rule_name": "autoreply-video",
+                "create_time": "1423027801",
+                "reply_mode": "reply_all",
+                "keyword_list_info": [{"type": "text", "match_mode": "equal", "content": "video测试"}],
+                "reply_list_info": [{"type": "video", "content": "http://61.182.133.153/vweixinp.tc.qq.com/1007_114bcede9a2244eeb5ab7f76d951df5f.f10.mp4"}]
+            }
+        ]}
+    }"#;
+
+    /// 设置了图文关键词规则、未开启关注回复的公众号
+    const NEWS_RULE: &str = r#"{
+        "is_add_friend_reply_open": 0,
+        "is_autoreply_open": 1,
+        "keyword_autoreply_info": {"list": [
+            {
+                "rule_name": "autoreply-news",
+                "create_time": 1423028166,
+                "reply_mode": "reply_all",
+                "keyword_list_info": [{"type": "text", "match_mode": "contain", "content": "news测试"}],
+                "reply_list_info": [
+                    {"type": "news", "news_info": {"list": [
+                        {"title": "it's news", "author": "jim", "digest": "it's digest", "show_cover": 1, "cover_url": "http://mmbiz.qpic.cn/mmbiz/GE7et87vE9vicuCibqXsX9GPPLuEtBfXfKbE8sWdt2DDcL0dMfQWJWTVn1N8DxI0gcRmrtqBOuwQHeuPKmFLK0ZQ/0", "content_url": "http://mp.weixin.qq.com/s?__biz=MjM5ODUwNTM3Ng==&mid=203929886&idx=1&sn=628f964cf0c6d84c026881b6959aea8b#rd", "source_url": "http://www.url.com"},
+                        {"title": "MULTI_NEWS", "author": "JIMZHENG", "digest": "text", "show_cover": 0, "cover_url": "http://mmbiz.qpic.cn/mmbiz/GE7et87vE9vicuCibqXsX9GPPLuEtBfXfK0HKuBIa1A1cypS0uY1wickv70iaY1gf3I1DTszuJoS3lAVLvhTcm9sDA/0", "content_url": "http://mp.weixin.qq.com/s?__biz=MjM5ODUwNTM3Ng==&mid=204013432&idx=1&sn=80ce6d9abcb832237bf86c87e50fda15#rd", "source_url": ""}
+                    ]}},
+                    {"type": "mpnewsarticle", "content": "ARTICLE_ID"}
+                ]
+            }
+        ]}
+    }"#;
+
+    #[test]
+    fn test_text_rule() {
+        let info = serde_json::from_str::<WechatMpAutoReplyInfo>(TEXT_RULE).unwrap();
+        assert_eq!(info.is_add_friend_reply_open, Some(1));
+        let add_friend = info.add_friend_autoreply_info.unwrap();
+        assert_eq!(add_friend.reply_type, WechatMpAutoReplyType::Text);
+        assert_eq!(add_friend.content.as_deref(), Some("Thanks for your attention!"));
+        let rules = info.keyword_autoreply_info.unwrap().list;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].reply_mode, Some(WechatMpAutoReplyMode::RandomOne));
+        assert_eq!(rules[0].create_time, Some(1423027926));
+        assert_eq!(rules[0].keyword_list_info[1].match_mode, Some(WechatMpKeywordMatchMode::Equal));
+        assert_eq!(rules[0].keyword_list_info[1].content.as_deref(), Some("你好"));
+        assert_eq!(rules[0].reply_list_info[1].reply_type, WechatMpAutoReplyType::Img);
+        assert_eq!(rules[1].create_time, Some(1423027801));
+        assert_eq!(rules[1].reply_mode, Some(WechatMpAutoReplyMode::ReplyAll));
+        assert_eq!(rules[1].reply_list_info[0].reply_type, WechatMpAutoReplyType::Video);
+    }
+
+    #[test]
+    fn test_news_rule() {
+        let info = serde_json::from_str::<WechatMpAutoReplyInfo>(NEWS_RULE).unwrap();
+        assert_eq!(info.is_add_friend_reply_open, Some(0));
+        assert!(info.add_friend_autoreply_info.is_none());
+        assert!(info.message_default_autoreply_info.is_none());
+        let rules = info.keyword_autoreply_info.unwrap().list;
+        let replies = &rules[0].reply_list_info;
+        assert_eq!(replies[0].reply_type, WechatMpAutoReplyType::News);
+        assert!(replies[0].content.is_none());
+        let news = replies[0].news_info.to_owned().unwrap().list.unwrap();
+        assert_eq!(news.len(), 2);
+        assert_eq!(news[0].title.as_deref(), Some("it's news"));
+        assert_eq!(news[0].show_cover, Some(1));
+        assert_eq!(news[1].source_url.as_deref(), Some(""));
+        // 未知的回复类型
+        assert_eq!(replies[1].reply_type, WechatMpAutoReplyType::Unknown);
+        assert_eq!(replies[1].content.as_deref(), Some("ARTICLE_ID"));
+    }
+
+    #[tokio::test]
+    async fn test_get_current_info() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(NEWS_RULE),
+            MockResponse::json(r#"{"is_menu_open":1,"selfmenu_info":{"button":[{"type":"click","name":"今日歌曲","key":"V1001_TODAY_MUSIC"},{"name":"菜单","sub_button":{"list":[{"type":"view","name":"搜索","url":"http://www.soso.com/"},{"type":"news","name":"图文","value":"KQb_w_Tiz-nSdVLoTV35Psmty8hGBulGhEdbb9SKs-o","news_info":{"list":[{"title":"MULTI_NEWS","author":"JIMZHENG","digest":"text","show_cover":0,"cover_url":"http://mmbiz.qpic.cn/mmbiz/GE7et87vE9vicuCibqXsX9GPPLuEtBfXfK0HKuBIa1A1cypS0uY1wickv70iaY1gf3I1DTszuJoS3lAVLvhTcm9sDA/0","content_url":"http://mp.weixin.qq.com/s?__biz=MjM5ODUwNTM3Ng==&mid=204013432&idx=1&sn=80ce6d9abcb832237bf86c87e50fda15#rd","source_url":""}]}}]}}]}}"#),
+            MockResponse::json(r#"{"errcode":48001,"errmsg":"api unauthorized"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_account_info", "secret").base_url(&server.url);
+        let info = client.account().get_current_autoreply_info().await.unwrap();
+        assert_eq!(info.is_autoreply_open, Some(1));
+        let menu = client.account().get_current_selfmenu_info().await.unwrap();
+        assert_eq!(menu.is_menu_open, Some(1));
+        let buttons = menu.selfmenu_info.unwrap().button.unwrap();
+        assert_eq!(buttons[0].key.as_deref(), Some("V1001_TODAY_MUSIC"));
+        let sub_buttons = buttons[1].sub_button.to_owned().unwrap().list.unwrap();
+        assert_eq!(sub_buttons[1].news_info.to_owned().unwrap().list.unwrap()[0].title.as_deref(), Some("MULTI_NEWS"));
+        assert!(client.account().get_current_autoreply_info().await.is_err());
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("GET /cgi-bin/get_current_autoreply_info?access_token=ACCESS_TOKEN"));
+        assert!(requests[2].starts_with("GET /cgi-bin/get_current_selfmenu_info?access_token=ACCESS_TOKEN"));
+    }
+}
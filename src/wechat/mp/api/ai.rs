@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, WechatRequest, RequestBody};
+use crate::wechat::mp::method::{MpAiMethod, WechatMpMethod};
+
+/// 语音识别结果未就绪时返回的错误码（系统繁忙，稍后再试）
+const RECOGNITION_PENDING_ERRCODE: &str = "-1";
+/// 语音识别默认语言
+const DEFAULT_LANG: &str = "zh_CN";
+
+/// 微信智能接口（语音识别、微信翻译）.
+#[derive(Debug, Clone)]
+pub struct WechatMpAi<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpAi<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpAi<T> {
+        WechatMpAi {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.ai()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpAi<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 微信翻译
+    /// lfrom、lto为源语言与目标语言，zh_CN或en_US；content为待翻译的UTF-8文本，直接作为请求体上传，不超过600字节
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Intelligent_Interface/AI_Open_API.html
+    /// </pre>
+    pub async fn translate(&self, lfrom: &str, lto: &str, content: &[u8]) -> LabradorResult<WechatMpTranslateResponse> {
+        let req = WechatMpAiRawRequest {
+            method: MpAiMethod::Translate,
+            params: vec![("lfrom".to_string(), lfrom.to_string()), ("lto".to_string(), lto.to_string())],
+            data: content.to_vec(),
+        };
+        let v = self.client.execute::<WechatMpAiRawRequest, String>(req).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpTranslateResponse>(v)
+    }
+
+    /// <pre>
+    /// 提交语音，语音文件直接作为请求体上传
+    /// voice_id为语音的唯一标识，format目前仅支持mp3；语音时长不超过60秒，大小不超过600K
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Intelligent_Interface/AI_Open_API.html
+    /// </pre>
+    pub async fn submit_voice_for_recognition(&self, voice_id: &str, format: &str, voice: &[u8]) -> LabradorResult<()> {
+        let req = WechatMpAiRawRequest {
+            method: MpAiMethod::AddVoiceToRecoForText,
+            params: vec![("voice_id".to_string(), voice_id.to_string()), ("format".to_string(), format.to_string()), ("lang".to_string(), DEFAULT_LANG.to_string())],
+            data: voice.to_vec(),
+        };
+        let v = self.client.execute::<WechatMpAiRawRequest, String>(req).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<Value>(v)?;
+        Ok(())
+    }
+
+    /// <pre>
+    /// 获取语音识别结果，需在提交语音后调用；识别尚未完成时返回`Pending`而非错误，可稍后再次查询
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Intelligent_Interface/AI_Open_API.html
+    /// </pre>
+    pub async fn query_recognition_result(&self, voice_id: &str, lang: &str) -> LabradorResult<WechatMpVoiceRecognition> {
+        let params = vec![("voice_id".to_string(), voice_id.to_string()), ("lang".to_string(), lang.to_string())];
+        let v = self.client.post(WechatMpMethod::Ai(MpAiMethod::QueryRecoResultForText), params, Value::Null, RequestType::Json).await?.json::<Value>()?;
+        WechatMpVoiceRecognition::from_value(v)
+    }
+}
+
+/// 以原始报文作为请求体的请求
+struct WechatMpAiRawRequest {
+    method: MpAiMethod,
+    params: Vec<(String, String)>,
+    data: Vec<u8>,
+}
+
+impl WechatRequest for WechatMpAiRawRequest {
+    fn get_api_method_name(&self) -> String {
+        self.method.get_method()
+    }
+
+    fn get_query_params(&self) -> BTreeMap<String, String> {
+        self.params.iter().cloned().collect()
+    }
+
+    fn get_request_body<T: Serialize>(&self) -> RequestBody<T> {
+        self.data.to_vec().into()
+    }
+}
+
+/// 微信翻译结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpTranslateResponse {
+    /// 原文内容
+    pub from_content: String,
+    /// 译文内容
+    pub to_content: String,
+}
+
+/// 语音识别结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatMpVoiceRecognition {
+    /// 识别尚未完成
+    Pending,
+    /// 识别完成，为识别出的文本
+    Ready(String),
+}
+
+impl WechatMpVoiceRecognition {
+    fn from_value(v: Value) -> LabradorResult<Self> {
+        match WechatCommonResponse::parse::<Value>(v) {
+            Ok(v) => match v["result"].as_str() {
+                Some(result) if !result.is_empty() => Ok(WechatMpVoiceRecognition::Ready(result.to_string())),
+                _ => Ok(WechatMpVoiceRecognition::Pending),
+            },
+            Err(LabraError::ClientError { errcode, .. }) if errcode == RECOGNITION_PENDING_ERRCODE => Ok(WechatMpVoiceRecognition::Pending),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self, WechatMpVoiceRecognition::Ready(_))
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{LabraError, SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::WechatMpVoiceRecognition;
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_translate_raw_body() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"from_content":"你好","to_content":"Hello"}"#)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_ai_translate", "secret").base_url(&server.url);
+        let res = client.ai().translate("zh_CN", "en_US", "你好".as_bytes()).await.unwrap();
+        assert_eq!(res.to_content, "Hello");
+        let request = &server.requests()[1];
+        assert!(request.starts_with("POST /cgi-bin/media/voice/translatecontent?"));
+        assert!(request.contains("access_token=ACCESS_TOKEN") && request.contains("lfrom=zh_CN") && request.contains("lto=en_US"));
+        // 请求体为原文，而不是JSON
+        assert!(request.ends_with("\r\n\r\n你好"));
+    }
+
+    #[tokio::test]
+    async fn test_submit_voice_raw_body() {
+        let voice = [0x49u8, 0x44, 0x33, 0x03, 0x00];
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_ai_voice", "secret").base_url(&server.url);
+        client.ai().submit_voice_for_recognition("VOICE_ID", "mp3", &voice).await.unwrap();
+        let request = &server.requests()[1];
+        assert!(request.starts_with("POST /cgi-bin/media/voice/addvoicetorecofortext?"));
+        assert!(request.contains("voice_id=VOICE_ID") && request.contains("format=mp3") && request.contains("lang=zh_CN"));
+        assert!(request.ends_with("\r\n\r\nID3\u{3}\u{0}"));
+    }
+
+    #[test]
+    fn test_recognition_result() {
+        assert_eq!(WechatMpVoiceRecognition::from_value(json!({"result": "你好"})).unwrap(), WechatMpVoiceRecognition::Ready("你好".to_string()));
+        assert_eq!(WechatMpVoiceRecognition::from_value(json!({"result": ""})).unwrap(), WechatMpVoiceRecognition::Pending);
+        assert_eq!(WechatMpVoiceRecognition::from_value(json!({"errcode": -1, "errmsg": "system error"})).unwrap(), WechatMpVoiceRecognition::Pending);
+        let err = WechatMpVoiceRecognition::from_value(json!({"errcode": 40001, "errmsg": "invalid credential"})).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "40001"));
+    }
+
+    #[tokio::test]
+    async fn test_query_recognition_result() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"errcode":-1,"errmsg":"system error"}"#), MockResponse::json(r#"{"result":"你好"}"#)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_ai_query", "secret").base_url(&server.url);
+        assert_eq!(client.ai().query_recognition_result("VOICE_ID", "zh_CN").await.unwrap(), WechatMpVoiceRecognition::Pending);
+        let result = client.ai().query_recognition_result("VOICE_ID", "zh_CN").await.unwrap();
+        assert!(result.is_ready());
+        assert_eq!(result, WechatMpVoiceRecognition::Ready("你好".to_string()));
+        assert!(server.requests()[2].starts_with("POST /cgi-bin/media/voice/queryrecoresultfortext?"));
+    }
+}
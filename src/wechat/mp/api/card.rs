@@ -1,28 +1,35 @@
 use std::vec;
 
 use serde::{Serialize, Deserialize, Serializer};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, get_timestamp, TicketType, get_nonce_str, WechatCrypto, BaseInfo, AdvancedInfo};
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, current_timestamp, TicketType, nonce_str, WechatCrypto, BaseInfo, AdvancedInfo};
 use crate::wechat::mp::constants::{QR_CODE};
 use crate::wechat::mp::method::{MpCardMethod, WechatMpMethod};
+use crate::serde_helper::option_string_or_number;
 
 /// 卡券相关.
 #[derive(Debug, Clone)]
-pub struct WechatMpCard<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpCard<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpCard<'a, T> {
+impl<T: SessionStore> WechatMpCard<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpCard<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpCard<T> {
         WechatMpCard {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.card()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpCard<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 获得卡券api_ticket，不强制刷新卡券api_ticket.
     /// </pre>
@@ -42,16 +49,15 @@ impl<'a, T: SessionStore> WechatMpCard<'a, T> {
     /// 创建调用卡券api时所需要的签名
     /// </pre>
     pub async fn create_card_api_signature(&self, mut params: Vec<String>) -> LabradorResult<WechatMpCardApiSignature> {
-        let timestamp = get_timestamp() / 1000;
-        let noncestr = get_nonce_str();
+        let timestamp = current_timestamp();
+        let noncestr = nonce_str();
         let api_ticket = self.get_card_api_ticket_force(false).await?;
         params.push(timestamp.to_string());
         params.push(noncestr.to_string());
         params.push(api_ticket);
-        params.sort();
-        let signature = WechatCrypto::get_sha1_sign(&params.join(""));
+        let signature = Self::card_sign(params);
         Ok(WechatMpCardApiSignature{
-            app_id: self.client.appid.to_string(),
+            app_id: self.client.inner.appid.to_string(),
             card_id: "".to_string(),
             card_type: "".to_string(),
             location_id: "".to_string(),
@@ -63,6 +69,51 @@ impl<'a, T: SessionStore> WechatMpCard<'a, T> {
         })
     }
 
+    /// <pre>
+    /// 卡券签名cardSign.
+    /// 将api_ticket、timestamp、card_id、code、openid、nonce_str等参数的value值按字典序排序后拼接，再进行sha1加密
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/OA_Web_Apps/JS-SDK.html#65">附录4-卡券扩展字段及签名生成算法</a>
+    /// </pre>
+    pub fn card_sign(mut values: Vec<String>) -> String {
+        values.retain(|v| !v.is_empty());
+        values.sort();
+        WechatCrypto::get_sha1_sign(&values.join(""))
+    }
+
+    /// <pre>
+    /// 卡券Code解码.
+    /// encrypt_code为卡券跳转页面URL上的加密code，由微信接口解码（使用access_token，不需要api_ticket）
+    /// 文档地址： <a href="https://developers.weixin.qq.com/doc/offiaccount/Cards_and_Offer/Redeeming_a_coupon_voucher_or_card.html">文档</a>
+    /// </pre>
+    pub async fn decrypt_code(&self, encrypt_code: &str) -> LabradorResult<String> {
+        self.decrypt_card_code(encrypt_code).await
+    }
+
+    /// <pre>
+    /// 卡券Code核销.
+    /// 自定义Code码的卡券必须传card_id
+    /// </pre>
+    pub async fn consume_code(&self, code: &str, card_id: Option<&str>) -> LabradorResult<WechatMpCardCodeConsumeResponse> {
+        self.consume_card_code_with_cardid(card_id, code).await
+    }
+
+    /// <pre>
+    /// 创建卡券，返回card_id.
+    /// 文档地址： <a href="https://developers.weixin.qq.com/doc/offiaccount/Cards_and_Offer/Create_a_Coupon_Voucher_or_Card.html">文档</a>
+    /// </pre>
+    pub async fn create(&self, card: WechatMpCardPayload) -> LabradorResult<String> {
+        let v = self.client.post(WechatMpMethod::Card(MpCardMethod::Create), vec![], card, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpCardCreateResponse>(v).map(|v| v.card_id)
+    }
+
+    /// <pre>
+    /// 创建领取单张卡券的二维码.
+    /// code为指定的卡券code，expire_seconds为有效时间（60~1800秒），不填默认永久有效
+    /// </pre>
+    pub async fn create_qrcode(&self, card_id: &str, code: Option<&str>, expire_seconds: Option<i64>) -> LabradorResult<WechatMpCardQrcodeCreateResponse> {
+        self.create_qrcode_card_complex(card_id, "", expire_seconds.unwrap_or_default(), None, code, false).await
+    }
+
     /// <pre>
     /// 卡券Code解码
     /// </pre>
@@ -598,6 +649,55 @@ impl Serialize for WechatMpCardCreateRequest {
     }
 }
 
+/// 创建卡券的请求
+///
+/// 公共的base_info、advanced_info有类型约束，各卡券类型特有的字段（如团购券的deal_detail、代金券的least_cost）放在`fields`中，
+/// 序列化为`{"card": {"card_type": "GROUPON", "groupon": {"base_info": {...}, "deal_detail": "..."}}}`
+#[derive(Debug, Clone)]
+pub struct WechatMpCardPayload {
+    /// 卡券类型：GROUPON、CASH、DISCOUNT、GIFT、GENERAL_COUPON、MEMBER_CARD等
+    pub card_type: String,
+    pub base_info: BaseInfo,
+    pub advanced_info: Option<AdvancedInfo>,
+    /// 卡券类型特有的字段
+    pub fields: Map<String, Value>,
+}
+
+impl WechatMpCardPayload {
+    pub fn new<S: Into<String>>(card_type: S, base_info: BaseInfo) -> Self {
+        Self {
+            card_type: card_type.into(),
+            base_info,
+            advanced_info: None,
+            fields: Map::new(),
+        }
+    }
+
+    pub fn advanced_info(mut self, advanced_info: AdvancedInfo) -> Self {
+        self.advanced_info = advanced_info.into();
+        self
+    }
+
+    pub fn field<S: Into<String>, V: Into<Value>>(mut self, key: S, value: V) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl Serialize for WechatMpCardPayload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut detail = self.fields.to_owned();
+        detail.insert("base_info".to_string(), serde_json::to_value(&self.base_info).map_err(serde::ser::Error::custom)?);
+        if let Some(advanced_info) = &self.advanced_info {
+            detail.insert("advanced_info".to_string(), serde_json::to_value(advanced_info).map_err(serde::ser::Error::custom)?);
+        }
+        let mut card = Map::new();
+        card.insert("card_type".to_string(), self.card_type.to_owned().into());
+        card.insert(self.card_type.to_lowercase(), Value::Object(detail));
+        json!({ "card": card }).serialize(serializer)
+    }
+}
+
 #[allow(unused)]
 #[derive(Serialize, Deserialize)]
 pub struct WechatMpCardCreateResponse {
@@ -753,8 +853,10 @@ pub struct SubmerchantResponse {
     /// 2）该卡券详情页关联的公众号为子商户配置这个公众号。
     pub app_id: Option<String>,
     /// 子商户信息创建时间
+    #[serde(default, with = "option_string_or_number")]
     pub create_time: Option<i64>,
     /// 子商户信息更新时间
+    #[serde(default, with = "option_string_or_number")]
     pub update_time: Option<i64>,
     /// 子商户名称（12个汉字内），该名称将在制券时填入并显示在卡券页面上
     pub brand_name: String,
@@ -800,3 +902,56 @@ pub struct SecondaryCategory {
     pub need_qualification_stuffs: Option<Vec<String>>,
 }
 
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::{SimpleStorage, WechatMpClient, BaseInfo};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatMpCard, WechatMpCardPayload};
+
+    #[test]
+    fn test_card_sign() {
+        let values = vec!["ojZ8YtyVyr30HheH3CM73y7h4jJE", "1404896688", "pjZ8Yt1XGILfi-FUsewpnnolGgZk", "jonyqin_1434008071", "jonyqin", ""];
+        // sha1("1404896688jonyqinjonyqin_1434008071ojZ8YtyVyr30HheH3CM73y7h4jJEpjZ8Yt1XGILfi-FUsewpnnolGgZk")
+        let sign = WechatMpCard::<SimpleStorage>::card_sign(values.iter().map(|v| v.to_string()).collect());
+        assert_eq!(sign, "2b4d29b5f60fa4be37522ddfd9583c329446d3e3");
+        let reversed = WechatMpCard::<SimpleStorage>::card_sign(values.iter().rev().map(|v| v.to_string()).collect());
+        assert_eq!(sign, reversed);
+    }
+
+    #[test]
+    fn test_create_payload() {
+        let base_info = serde_json::from_value::<BaseInfo>(json!({
+            "logo_url": "http://mmbiz.qpic.cn/mmbiz/iaL1LJM1mF9aRKPZ/0",
+            "code_type": "CODE_TYPE_TEXT",
+            "brand_name": "微信餐厅",
+            "title": "132元双人火锅套餐",
+            "color": "Color010",
+            "notice": "使用时向服务员出示此券",
+            "description": "不可与其他优惠同享",
+            "sku": {"quantity": 500000, "total_quantity": 500000},
+            "date_info": {"type": "DATE_TYPE_FIX_TIME_RANGE", "begin_timestamp": 1397577600, "end_timestamp": 1472724261}
+        })).unwrap();
+        let payload = WechatMpCardPayload::new("GROUPON", base_info).field("deal_detail", "以下锅底2选1");
+        let v = serde_json::to_value(&payload).unwrap();
+        assert_eq!(v["card"]["card_type"], "GROUPON");
+        assert_eq!(v["card"]["groupon"]["deal_detail"], "以下锅底2选1");
+        assert_eq!(v["card"]["groupon"]["base_info"]["sku"]["quantity"], 500000);
+        assert!(v["card"]["groupon"].get("advanced_info").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_card_api_ticket_cache() {
+        let token = MockResponse::json(r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#);
+        let ticket = MockResponse::json(r#"{"errcode":0,"errmsg":"ok","ticket":"CARD_TICKET","expires_in":7200}"#);
+        let server = MockServer::start(vec![token, ticket]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_card_ticket_cache", "secret").base_url(&server.url);
+        assert_eq!(client.card().get_card_api_ticket().await.unwrap(), "CARD_TICKET");
+        assert_eq!(client.card().get_card_api_ticket().await.unwrap(), "CARD_TICKET");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("type=wx_card"));
+    }
+}
@@ -5,24 +5,32 @@ use serde_json::{json, Value};
 
 use crate::{session::SessionStore, request::{RequestType}, errors::LabraError, WechatCommonResponse, WechatMpClient, LabradorResult};
 use crate::util::md5::md5;
+use crate::wechat::mp::replies::{Article, ArticleLimit};
 use crate::wechat::mp::method::{MpCustomServiceMethod, WechatMpMethod};
+use crate::text::{self, TextPolicy};
 
 /// 客服接口.
 #[derive(Debug, Clone)]
-pub struct WechatMpCustomService<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpCustomService<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpCustomService<'a, T> {
+impl<T: SessionStore> WechatMpCustomService<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpCustomService<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpCustomService<T> {
         WechatMpCustomService {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.custom_service()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpCustomService<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 发送客服消息
     /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Service_Center_messages.html">发送客服消息</a>
@@ -35,7 +43,7 @@ impl<'a, T: SessionStore> WechatMpCustomService<'a, T> {
 
     /// 客服接口 - 发送文字消息
     pub async fn send_text(&self, openid: &str, content: &str) -> LabradorResult<WechatCommonResponse> {
-        let req = SendTextRequest::new(openid, content);
+        let req = SendTextRequest::new(openid, content).sanitize(&TextPolicy::kf_text())?;
         self.send_kefu_message(req.to_json()).await
     }
 
@@ -51,6 +59,21 @@ impl<'a, T: SessionStore> WechatMpCustomService<'a, T> {
         self.send_kefu_message(req.to_json()).await
     }
 
+    /// 客服接口 - 发送图文消息（点击跳转到外链），图文消息条数限制在1条以内
+    pub async fn send_news(&self, openid: &str, articles: &[Article]) -> LabradorResult<WechatCommonResponse> {
+        let req = SendNewsRequest::new(openid, articles.to_vec());
+        self.send_kefu_message(req.to_json()?).await
+    }
+
+    /// <pre>
+    /// 客服接口 - 发送菜单消息
+    /// 用户点击菜单后，会推送一条带有bizmsgmenuid的文本消息，见[`TextMessage::menu_selection`](crate::messages::TextMessage::menu_selection)
+    /// </pre>
+    pub async fn send_msg_menu(&self, openid: &str, menu: WechatMpMsgMenu) -> LabradorResult<WechatCommonResponse> {
+        let req = SendMsgMenuRequest::new(openid, menu);
+        self.send_kefu_message(req.to_json()?).await
+    }
+
 
     //*******************客服管理接口***********************//
 
@@ -60,7 +83,7 @@ impl<'a, T: SessionStore> WechatMpCustomService<'a, T> {
     /// 接口url格式：https://api.weixin.qq.com/customservice/kfaccount/add?access_token=ACCESS_TOKEN
     /// </pre>
     pub async fn add_account(&self, account: &str, nickname: &str, password: &str) -> LabradorResult<WechatCommonResponse> {
-        let encrypted_password = md5(password);
+        let encrypted_password = md5(password)?;
         let data = json!({
             "kf_account": account.to_owned(),
             "nickname": nickname.to_owned(),
@@ -76,7 +99,7 @@ impl<'a, T: SessionStore> WechatMpCustomService<'a, T> {
     /// </pre>
     pub async fn update_account(&self, account: &str, nickname: &str, password: &str) -> LabradorResult<WechatCommonResponse> {
         // let encrypted_password = hash::hash(MessageDigest::md5(), password.as_bytes())?;
-        let encrypted_password = md5(password);
+        let encrypted_password = md5(password)?;
         let data = json!({
             "kf_account": account.to_owned(),
             "nickname": nickname.to_owned(),
@@ -305,6 +328,12 @@ impl SendTextRequest {
         }
     }
 
+    /// 按策略处理文本内容，如超出长度时截断：`req.sanitize(&TextPolicy::kf_text().truncate("…"))`
+    pub fn sanitize(mut self, policy: &TextPolicy) -> LabradorResult<Self> {
+        self.content = text::sanitize(&self.content, policy)?;
+        Ok(self)
+    }
+
     fn to_json(&self) -> Value {
         let mut data = json!({
             "msgtype": "text".to_owned(),
@@ -322,3 +351,211 @@ impl SendTextRequest {
         data
     }
 }
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendNewsRequest {
+    openid: String,
+    account: Option<String>,
+    articles: Vec<Article>,
+}
+
+#[allow(unused)]
+impl SendNewsRequest {
+    pub fn new<S: Into<String>>(openid: S, articles: Vec<Article>) -> SendNewsRequest {
+        SendNewsRequest {
+            openid: openid.into(),
+            account: None,
+            articles,
+        }
+    }
+
+    pub fn with_account<S: Into<String>>(openid: S, articles: Vec<Article>, account: S) -> SendNewsRequest {
+        SendNewsRequest {
+            openid: openid.into(),
+            account: Some(account.into()),
+            articles,
+        }
+    }
+
+    fn to_json(&self) -> LabradorResult<Value> {
+        ArticleLimit::KfNews.check(self.articles.len())?;
+        let articles = self.articles.iter().map(|article| json!({
+            "title": article.title.to_owned(),
+            "description": article.description.to_owned(),
+            "url": article.url.to_owned(),
+            "picurl": article.image.to_owned(),
+        })).collect::<Vec<_>>();
+        let mut data = json!({
+            "msgtype": "news".to_owned(),
+            "touser": self.openid.to_owned(),
+            "news": {
+                "articles": articles
+            }
+        });
+        if let Some(ref account) = self.account {
+            data.as_object_mut().unwrap().insert("customservice".to_string(), json!({
+                "kf_account": account.to_owned()
+            }));
+        }
+        Ok(data)
+    }
+}
+
+/// 菜单消息最多的选项数
+const MSG_MENU_MAX_ITEMS: usize = 10;
+/// 菜单选项内容的最大长度（字符）
+const MSG_MENU_ITEM_MAX_CHARS: usize = 20;
+
+/// 客服菜单消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMpMsgMenu {
+    /// 菜单上方的文字
+    pub head_content: String,
+    /// 菜单选项，最多10个
+    pub list: Vec<WechatMpMsgMenuItem>,
+    /// 菜单下方的文字
+    pub tail_content: String,
+}
+
+/// 客服菜单消息的选项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMpMsgMenuItem {
+    /// 选项ID，用户点击后作为bizmsgmenuid推送回来
+    pub id: String,
+    /// 选项内容，最多20个字符，用户点击后作为文本消息的内容推送回来
+    pub content: String,
+}
+
+impl WechatMpMsgMenuItem {
+    pub fn new<S: Into<String>>(id: S, content: S) -> Self {
+        WechatMpMsgMenuItem {
+            id: id.into(),
+            content: content.into(),
+        }
+    }
+}
+
+impl WechatMpMsgMenu {
+    pub fn new<S: Into<String>>(head_content: S, list: Vec<WechatMpMsgMenuItem>, tail_content: S) -> Self {
+        WechatMpMsgMenu {
+            head_content: head_content.into(),
+            list,
+            tail_content: tail_content.into(),
+        }
+    }
+
+    /// 校验选项数量与内容长度
+    pub fn validate(&self) -> LabradorResult<()> {
+        if self.list.is_empty() || self.list.len() > MSG_MENU_MAX_ITEMS {
+            return Err(LabraError::RequestError(format!("菜单消息的选项不能为空且最多{}个，当前为{}个", MSG_MENU_MAX_ITEMS, self.list.len())));
+        }
+        if let Some(item) = self.list.iter().find(|item| item.content.chars().count() > MSG_MENU_ITEM_MAX_CHARS) {
+            return Err(LabraError::RequestError(format!("菜单选项[{}]的内容最多{}个字符", item.id, MSG_MENU_ITEM_MAX_CHARS)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendMsgMenuRequest {
+    openid: String,
+    account: Option<String>,
+    menu: WechatMpMsgMenu,
+}
+
+#[allow(unused)]
+impl SendMsgMenuRequest {
+    pub fn new<S: Into<String>>(openid: S, menu: WechatMpMsgMenu) -> SendMsgMenuRequest {
+        SendMsgMenuRequest {
+            openid: openid.into(),
+            account: None,
+            menu,
+        }
+    }
+
+    pub fn with_account<S: Into<String>>(openid: S, menu: WechatMpMsgMenu, account: S) -> SendMsgMenuRequest {
+        SendMsgMenuRequest {
+            openid: openid.into(),
+            account: Some(account.into()),
+            menu,
+        }
+    }
+
+    fn to_json(&self) -> LabradorResult<Value> {
+        self.menu.validate()?;
+        let mut data = json!({
+            "msgtype": "msgmenu".to_owned(),
+            "touser": self.openid.to_owned(),
+            "msgmenu": self.menu,
+        });
+        if let Some(ref account) = self.account {
+            data.as_object_mut().unwrap().insert("customservice".to_string(), json!({
+                "kf_account": account.to_owned()
+            }));
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{LabraError, SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::mp::replies::Article;
+    use super::{SendMsgMenuRequest, WechatMpMsgMenu, WechatMpMsgMenuItem};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_send_news_limit() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_kf_news", "secret").base_url(&server.url);
+        let article = Article::with_image("Happy Day", "URL", "PIC_URL");
+        assert!(matches!(client.custom_service().send_news("OPENID", &[]).await, Err(LabraError::RequestError(_))));
+        let articles = vec![article.clone(), article.clone()];
+        assert!(matches!(client.custom_service().send_news("OPENID", &articles).await, Err(LabraError::RequestError(msg)) if msg.contains("客服图文消息") && msg.contains("1条")));
+        client.custom_service().send_news("OPENID", &[article]).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("POST /cgi-bin/message/custom/send?access_token=ACCESS_TOKEN"));
+        let body = serde_json::from_str::<serde_json::Value>(requests[1].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({
+            "msgtype": "news",
+            "touser": "OPENID",
+            "news": {"articles": [{"title": "Happy Day", "description": "", "url": "URL", "picurl": "PIC_URL"}]}
+        }));
+    }
+
+    #[test]
+    fn test_msg_menu_json_and_limits() {
+        let menu = WechatMpMsgMenu::new("您对本次服务是否满意呢? ", vec![WechatMpMsgMenuItem::new("101", "满意"), WechatMpMsgMenuItem::new("102", "不满意")], "欢迎再次光临");
+        let req = SendMsgMenuRequest::with_account("OPENID", menu.clone(), "test1@kftest");
+        assert_eq!(req.to_json().unwrap(), json!({
+            "msgtype": "msgmenu",
+            "touser": "OPENID",
+            "msgmenu": {
+                "head_content": "您对本次服务是否满意呢? ",
+                "list": [{"id": "101", "content": "满意"}, {"id": "102", "content": "不满意"}],
+                "tail_content": "欢迎再次光临"
+            },
+            "customservice": {"kf_account": "test1@kftest"}
+        }));
+
+        let items = (0..11).map(|i| WechatMpMsgMenuItem::new(i.to_string(), "选项".to_string())).collect::<Vec<_>>();
+        assert!(WechatMpMsgMenu::new("head", items[..10].to_vec(), "tail").validate().is_ok());
+        assert!(matches!(WechatMpMsgMenu::new("head", items, "tail").validate(), Err(LabraError::RequestError(_))));
+        assert!(matches!(WechatMpMsgMenu::new("head", vec![], "tail").validate(), Err(LabraError::RequestError(_))));
+        // 按字符而非字节计算长度
+        assert!(WechatMpMsgMenu::new("head", vec![WechatMpMsgMenuItem::new("1", &"满".repeat(20))], "tail").validate().is_ok());
+        let menu = WechatMpMsgMenu::new("head", vec![WechatMpMsgMenuItem::new("1", &"满".repeat(21))], "tail");
+        assert!(matches!(SendMsgMenuRequest::new("OPENID", menu).to_json(), Err(LabraError::RequestError(msg)) if msg.contains("[1]")));
+    }
+}
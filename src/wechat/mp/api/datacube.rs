@@ -0,0 +1,280 @@
+use chrono::{Local, NaiveDate};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, DateRange};
+use crate::wechat::mp::method::{MpDataCubeMethod, WechatMpMethod};
+
+/// 数据统计接口.
+///
+/// 各接口对单次查询的时间跨度有限制（见各方法说明），超出时会自动按允许的天数拆分请求并合并结果；
+/// 结束日期最大为昨日。
+///
+/// [文档地址](https://developers.weixin.qq.com/doc/offiaccount/Analytics/User_Analysis_Data_Interface.html)
+#[derive(Debug, Clone)]
+pub struct WechatMpDataCube<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpDataCube<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpDataCube<T> {
+        WechatMpDataCube {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.data_cube()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpDataCube<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 获取用户增减数据，最大时间跨度7天
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Analytics/User_Analysis_Data_Interface.html">用户分析数据接口</a>
+    /// 接口url格式：https://api.weixin.qq.com/datacube/getusersummary?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn get_user_summary(&self, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<WechatMpUserSummary>> {
+        self.fetch(MpDataCubeMethod::GetUserSummary, begin, end).await
+    }
+
+    /// <pre>
+    /// 获取累计用户数据，最大时间跨度7天
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Analytics/User_Analysis_Data_Interface.html">用户分析数据接口</a>
+    /// 接口url格式：https://api.weixin.qq.com/datacube/getusercumulate?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn get_user_cumulate(&self, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<WechatMpUserCumulate>> {
+        self.fetch(MpDataCubeMethod::GetUserCumulate, begin, end).await
+    }
+
+    /// <pre>
+    /// 获取图文群发每日数据，最大时间跨度1天
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Analytics/Graphic_Analysis_Data_Interface.html">图文分析数据接口</a>
+    /// 接口url格式：https://api.weixin.qq.com/datacube/getarticlesummary?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn get_article_summary(&self, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<WechatMpArticleSummary>> {
+        self.fetch(MpDataCubeMethod::GetArticleSummary, begin, end).await
+    }
+
+    /// <pre>
+    /// 获取图文群发总数据，最大时间跨度1天
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Analytics/Graphic_Analysis_Data_Interface.html">图文分析数据接口</a>
+    /// 接口url格式：https://api.weixin.qq.com/datacube/getarticletotal?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn get_article_total(&self, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<WechatMpArticleTotal>> {
+        self.fetch(MpDataCubeMethod::GetArticleTotal, begin, end).await
+    }
+
+    /// <pre>
+    /// 获取图文统计数据，最大时间跨度3天
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Analytics/Graphic_Analysis_Data_Interface.html">图文分析数据接口</a>
+    /// 接口url格式：https://api.weixin.qq.com/datacube/getuserread?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn get_user_read(&self, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<WechatMpUserRead>> {
+        self.fetch(MpDataCubeMethod::GetUserRead, begin, end).await
+    }
+
+    /// <pre>
+    /// 获取接口分析数据，最大时间跨度30天
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Analytics/Analytics_API.html">接口分析数据接口</a>
+    /// 接口url格式：https://api.weixin.qq.com/datacube/getinterfacesummary?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn get_interface_summary(&self, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<WechatMpInterfaceSummary>> {
+        self.fetch(MpDataCubeMethod::GetInterfaceSummary, begin, end).await
+    }
+
+    /// 按接口允许的时间跨度拆分请求并合并结果
+    async fn fetch<R: DeserializeOwned>(&self, method: MpDataCubeMethod, begin: NaiveDate, end: NaiveDate) -> LabradorResult<Vec<R>> {
+        let range = DateRange::new(begin, end)?;
+        let today = Local::now().naive_local().date();
+        if range.end >= today {
+            return Err(LabraError::RequestError(format!("结束日期最大为昨日，当前为：{}", range.end)));
+        }
+        let mut rows = Vec::new();
+        for window in range.days(method.max_days()) {
+            let req = json!({
+                "begin_date": window.begin,
+                "end_date": window.end,
+            });
+            let v = self.client.post(WechatMpMethod::DataCube(method.to_owned()), vec![], req, RequestType::Json).await?.json::<Value>()?;
+            rows.extend(WechatCommonResponse::parse_with_key::<Vec<R>>(v, "list")?);
+        }
+        Ok(rows)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpUserSummary {
+    /// 数据的日期
+    pub ref_date: NaiveDate,
+    /// 用户的渠道，数值代表的含义如下： 0代表其他合计 1代表公众号搜索 17代表名片分享 30代表扫描二维码 51代表支付后关注（在支付完成页） 57代表文章内账号名称 100微信广告 161他人转载 149小程序关注 200视频号 201直播
+    pub user_source: Option<i32>,
+    /// 新增的用户数量
+    pub new_user: Option<i64>,
+    /// 取消关注的用户数量，new_user减去cancel_user即为净增用户数量
+    pub cancel_user: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpUserCumulate {
+    /// 数据的日期
+    pub ref_date: NaiveDate,
+    /// 总用户量
+    pub cumulate_user: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpArticleSummary {
+    /// 数据的日期
+    pub ref_date: NaiveDate,
+    /// 这里的msgid实际上是由msgid（图文消息id，这也就是群发接口调用后返回的msg_data_id）和index（消息次序索引）组成
+    pub msgid: Option<String>,
+    /// 图文消息的标题
+    pub title: Option<String>,
+    /// 图文页（点击群发图文卡片进入的页面）的阅读人数
+    pub int_page_read_user: Option<i64>,
+    /// 图文页的阅读次数
+    pub int_page_read_count: Option<i64>,
+    /// 原文页（点击图文页“阅读原文”进入的页面）的阅读人数，无原文页时此处数据为0
+    pub ori_page_read_user: Option<i64>,
+    /// 原文页的阅读次数
+    pub ori_page_read_count: Option<i64>,
+    /// 分享的人数
+    pub share_user: Option<i64>,
+    /// 分享的次数
+    pub share_count: Option<i64>,
+    /// 收藏的人数
+    pub add_to_fav_user: Option<i64>,
+    /// 收藏的次数
+    pub add_to_fav_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpArticleTotal {
+    /// 数据的日期
+    pub ref_date: NaiveDate,
+    pub msgid: Option<String>,
+    pub title: Option<String>,
+    /// 群发后每天的累计数据
+    pub details: Option<Vec<WechatMpArticleTotalDetail>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpArticleTotalDetail {
+    /// 统计的日期，在getarticletotal接口中，ref_date指的是文章群发出日期， 而stat_date是数据统计日期
+    pub stat_date: NaiveDate,
+    /// 送达人数，一般约等于总粉丝数（需排除黑名单或其他异常情况下无法收到消息的粉丝）
+    pub target_user: Option<i64>,
+    pub int_page_read_user: Option<i64>,
+    pub int_page_read_count: Option<i64>,
+    pub ori_page_read_user: Option<i64>,
+    pub ori_page_read_count: Option<i64>,
+    pub share_user: Option<i64>,
+    pub share_count: Option<i64>,
+    pub add_to_fav_user: Option<i64>,
+    pub add_to_fav_count: Option<i64>,
+    /// 公众号会话阅读人数
+    pub int_page_from_session_read_user: Option<i64>,
+    pub int_page_from_session_read_count: Option<i64>,
+    /// 历史消息页阅读人数
+    pub int_page_from_hist_msg_read_user: Option<i64>,
+    pub int_page_from_hist_msg_read_count: Option<i64>,
+    /// 朋友圈阅读人数
+    pub int_page_from_feed_read_user: Option<i64>,
+    pub int_page_from_feed_read_count: Option<i64>,
+    /// 好友转发阅读人数
+    pub int_page_from_friends_read_user: Option<i64>,
+    pub int_page_from_friends_read_count: Option<i64>,
+    /// 其他场景阅读人数
+    pub int_page_from_other_read_user: Option<i64>,
+    pub int_page_from_other_read_count: Option<i64>,
+    /// 公众号会话转发朋友圈人数
+    pub feed_share_from_session_user: Option<i64>,
+    pub feed_share_from_session_cnt: Option<i64>,
+    /// 朋友圈转发朋友圈人数
+    pub feed_share_from_feed_user: Option<i64>,
+    pub feed_share_from_feed_cnt: Option<i64>,
+    /// 其他场景转发朋友圈人数
+    pub feed_share_from_other_user: Option<i64>,
+    pub feed_share_from_other_cnt: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpUserRead {
+    /// 数据的日期
+    pub ref_date: NaiveDate,
+    /// 用户从哪里进入来阅读该图文。99999999.全部；0:会话;1.好友;2.朋友圈;4.历史消息页;5.其他;6.看一看;7.搜一搜
+    pub user_source: Option<i32>,
+    pub int_page_read_user: Option<i64>,
+    pub int_page_read_count: Option<i64>,
+    pub ori_page_read_user: Option<i64>,
+    pub ori_page_read_count: Option<i64>,
+    pub share_user: Option<i64>,
+    pub share_count: Option<i64>,
+    pub add_to_fav_user: Option<i64>,
+    pub add_to_fav_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpInterfaceSummary {
+    /// 数据的日期
+    pub ref_date: NaiveDate,
+    /// 通过服务器配置地址获得消息后，被动回复用户消息的次数
+    pub callback_count: Option<i64>,
+    /// 上述动作的失败次数
+    pub fail_count: Option<i64>,
+    /// 总耗时，除以callback_count即为平均耗时
+    pub total_time_cost: Option<i64>,
+    /// 最大耗时
+    pub max_time_cost: Option<i64>,
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::{Duration, Local, NaiveDate};
+    use serde_json::json;
+    use crate::{LabraError, SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatMpArticleTotal, WechatMpUserSummary};
+
+    #[test]
+    fn test_ref_date_format() {
+        let rows = serde_json::from_str::<Vec<WechatMpUserSummary>>(r#"[{"ref_date":"2014-12-07","user_source":0,"new_user":0,"cancel_user":0}]"#).unwrap();
+        assert_eq!(rows[0].ref_date, NaiveDate::from_ymd_opt(2014, 12, 7).unwrap());
+        assert_eq!(serde_json::to_value(&rows[0]).unwrap()["ref_date"], "2014-12-07");
+        assert!(serde_json::from_str::<Vec<WechatMpUserSummary>>(r#"[{"ref_date":"2014/12/07"}]"#).is_err());
+
+        let total = serde_json::from_str::<WechatMpArticleTotal>(r#"{"ref_date":"2014-12-14","msgid":"202457380_1","title":"马航丢画记","details":[{"stat_date":"2014-12-14","target_user":261917,"int_page_read_user":23676}]}"#).unwrap();
+        assert_eq!(total.details.unwrap()[0].stat_date, NaiveDate::from_ymd_opt(2014, 12, 14).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge() {
+        let token = MockResponse::json(r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#);
+        let page1 = json!({"list": [{"ref_date": "2023-01-01", "cumulate_user": 100}]});
+        let page2 = json!({"list": [{"ref_date": "2023-01-08", "cumulate_user": 120}]});
+        let server = MockServer::start(vec![token, MockResponse::json(&page1.to_string()), MockResponse::json(&page2.to_string())]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_datacube_cumulate", "secret").base_url(&server.url);
+        let rows = client.data_cube().get_user_cumulate(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2023, 1, 10).unwrap()).await.unwrap();
+        assert_eq!(rows.iter().map(|row| row.cumulate_user.unwrap()).collect::<Vec<_>>(), vec![100, 120]);
+        let requests = server.requests();
+        assert!(requests[1].ends_with(r#"{"begin_date":"2023-01-01","end_date":"2023-01-07"}"#));
+        assert!(requests[2].ends_with(r#"{"begin_date":"2023-01-08","end_date":"2023-01-10"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_end_date_not_before_today() {
+        let client = WechatMpClient::<SimpleStorage>::new("wx_datacube_today", "secret");
+        let today = Local::now().naive_local().date();
+        let result = client.data_cube().get_user_summary(today - Duration::days(1), today).await;
+        assert!(matches!(result, Err(LabraError::RequestError(_))));
+    }
+}
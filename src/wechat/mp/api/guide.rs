@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use futures_util::Stream;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, Page, PagedStream};
+use crate::wechat::mp::method::{MpGuideMethod, WechatMpMethod};
+
+/// 单次建立或修改关系的客户数上限
+const GUIDE_BUYER_MAX_COUNT: usize = 200;
+/// 客户列表每页最多返回的数量
+const GUIDE_BUYER_LIST_MAX_COUNT: u32 = 50;
+
+/// 导购助手.
+#[derive(Debug, Clone)]
+pub struct WechatMpGuide<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpGuide<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpGuide<T> {
+        WechatMpGuide {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.guide()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpGuide<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 添加顾问，顾问需要先绑定个人微信号（guide_account）或关注公众号（guide_openid）
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/guide-account/shopping-guide.addGuideAcct.html
+    /// </pre>
+    pub async fn add_guide(&self, guide: WechatMpGuideInfo) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::AddGuideAcct), vec![], guide, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 为顾问分配客户，单次最多200个
+    /// 返回每个客户的结果，部分客户失败时不影响其他客户
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/buyer-account/shopping-guide.addGuideBuyerRelation.html
+    /// </pre>
+    pub async fn add_buyer_relation(&self, guide: &GuideId, buyers: &[WechatMpGuideBuyer]) -> LabradorResult<Vec<WechatMpGuideBuyerResult>> {
+        check_buyer_count(buyers.len())?;
+        let mut req = guide.to_json();
+        req.insert("buyer_list".to_string(), serde_json::to_value(buyers)?);
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::AddGuideBuyerRelation), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        let ids = buyers.iter().map(|v| v.buyer.to_owned()).collect::<Vec<_>>();
+        WechatMpGuideBuyerResult::from_response(v, &ids)
+    }
+
+    /// <pre>
+    /// 获取顾问的客户列表，begin为开始位置（0即为从第一条开始查询），count最大为50
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/buyer-account/shopping-guide.getGuideBuyerRelationList.html
+    /// </pre>
+    pub async fn get_buyer_relation_list(&self, guide: &GuideId, begin: u32, count: u32) -> LabradorResult<WechatMpGuideBuyerRelationList> {
+        if count == 0 || count > GUIDE_BUYER_LIST_MAX_COUNT {
+            return Err(LabraError::RequestError(format!("count必须为1~{}", GUIDE_BUYER_LIST_MAX_COUNT)));
+        }
+        let mut req = guide.to_json();
+        req.insert("begin".to_string(), begin.into());
+        req.insert("count".to_string(), count.into());
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::GetGuideBuyerRelationList), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpGuideBuyerRelationList>(v)
+    }
+
+    /// <pre>
+    /// 获取顾问的全部客户
+    /// 基于get_buyer_relation_list按begin自动翻页，消费时才会发起请求；取到total_num条或空页时结束
+    /// </pre>
+    pub fn list_all_buyer_relation(&self, guide: GuideId) -> impl Stream<Item = LabradorResult<WechatMpGuideBuyerRelation>> + '_ {
+        PagedStream::new(move |begin: Option<u32>| {
+            let guide = guide.to_owned();
+            async move {
+                let begin = begin.unwrap_or_default();
+                let res = self.get_buyer_relation_list(&guide, begin, GUIDE_BUYER_LIST_MAX_COUNT).await?;
+                let end = begin + res.list.len() as u32;
+                Ok(Page::new(res.list, (end < res.total_num).then(|| end)))
+            }
+        }).into_stream()
+    }
+
+    /// <pre>
+    /// 修改客户在顾问侧的昵称
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/buyer-account/shopping-guide.updateGuideBuyerRelation.html
+    /// </pre>
+    pub async fn update_buyer_relation(&self, guide: &GuideId, buyer: &WechatMpGuideBuyer) -> LabradorResult<WechatCommonResponse> {
+        let mut req = guide.to_json();
+        req.extend(buyer.buyer.to_json());
+        req.insert("buyer_nickname".to_string(), buyer.buyer_nickname.to_owned().unwrap_or_default().into());
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::UpdateGuideBuyerRelation), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 新建标签类型及其可选值，如标签类型“会员等级”，可选值“银卡”“金卡”
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/tag-account/shopping-guide.newGuideTagOption.html
+    /// </pre>
+    pub async fn new_tag_option(&self, option: &WechatMpGuideTagOption) -> LabradorResult<WechatCommonResponse> {
+        if option.tag_values.is_empty() {
+            return Err(LabraError::MissingField("标签可选值不能为空".to_string()));
+        }
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::NewGuideTagOption), vec![], option, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 获取全部标签类型及其可选值
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/tag-account/shopping-guide.getGuideTagOption.html
+    /// </pre>
+    pub async fn get_tag_options(&self) -> LabradorResult<Vec<WechatMpGuideTagOption>> {
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::GetGuideTagOption), vec![], json!({}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse_with_key::<Vec<WechatMpGuideTagOption>>(v, "options")
+    }
+
+    /// <pre>
+    /// 为客户设置标签，tag_value必须是已创建的标签可选值，单次最多200个客户
+    /// 返回每个客户的结果，部分客户失败时不影响其他客户
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Shopping_Guide/tag-account/shopping-guide.addGuideBuyerTag.html
+    /// </pre>
+    pub async fn add_buyer_tag(&self, guide: &GuideId, tag_value: &str, buyers: &[BuyerId]) -> LabradorResult<Vec<WechatMpGuideBuyerResult>> {
+        check_buyer_count(buyers.len())?;
+        let mut req = guide.to_json();
+        req.insert("tag_value".to_string(), tag_value.into());
+        req.extend(BuyerId::to_list_json(buyers));
+        let v = self.client.post(WechatMpMethod::Guide(MpGuideMethod::AddGuideBuyerTag), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatMpGuideBuyerResult::from_response(v, buyers)
+    }
+}
+
+fn check_buyer_count(count: usize) -> LabradorResult<()> {
+    if count == 0 || count > GUIDE_BUYER_MAX_COUNT {
+        return Err(LabraError::RequestError(format!("客户数量为{}，不能为空且最多只能包含{}个", count, GUIDE_BUYER_MAX_COUNT)));
+    }
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 顾问的标识，绑定的个人微信号或顾问在公众号下的openid
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GuideId {
+    /// 顾问微信号，序列化为`guide_account`
+    #[serde(rename = "guide_account")]
+    Account(String),
+    /// 顾问openid或unionid，序列化为`guide_openid`
+    #[serde(rename = "guide_openid")]
+    OpenId(String),
+}
+
+impl GuideId {
+    fn to_json(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+        match self {
+            GuideId::Account(v) => map.insert("guide_account".to_string(), v.as_str().into()),
+            GuideId::OpenId(v) => map.insert("guide_openid".to_string(), v.as_str().into()),
+        };
+        map
+    }
+}
+
+/// <pre>
+/// 客户的标识
+/// 导购助手的接口既可以用客户在公众号下的openid，也可以用unionid，分别对应`openid`与`unionid`字段
+/// </pre>
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BuyerId {
+    #[serde(rename = "openid")]
+    OpenId(String),
+    #[serde(rename = "unionid")]
+    UnionId(String),
+}
+
+impl BuyerId {
+    fn to_json(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+        match self {
+            BuyerId::OpenId(v) => map.insert("openid".to_string(), v.as_str().into()),
+            BuyerId::UnionId(v) => map.insert("unionid".to_string(), v.as_str().into()),
+        };
+        map
+    }
+
+    /// 批量接口中按标识类型分为`openid_list`与`unionid_list`，为空的列表不传
+    fn to_list_json(buyers: &[BuyerId]) -> Map<String, Value> {
+        let (mut openids, mut unionids) = (vec![], vec![]);
+        for buyer in buyers {
+            match buyer {
+                BuyerId::OpenId(v) => openids.push(v.as_str()),
+                BuyerId::UnionId(v) => unionids.push(v.as_str()),
+            }
+        }
+        let mut map = Map::new();
+        if !openids.is_empty() {
+            map.insert("openid_list".to_string(), openids.into());
+        }
+        if !unionids.is_empty() {
+            map.insert("unionid_list".to_string(), unionids.into());
+        }
+        map
+    }
+}
+
+/// 顾问信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpGuideInfo {
+    #[serde(flatten)]
+    pub guide: GuideId,
+    /// 顾问头像，不填时使用微信头像
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guide_headimgurl: Option<String>,
+    /// 顾问昵称，不填时使用微信昵称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guide_nickname: Option<String>,
+}
+
+/// 分配给顾问的客户
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMpGuideBuyer {
+    #[serde(flatten)]
+    pub buyer: BuyerId,
+    /// 客户在顾问侧的昵称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_nickname: Option<String>,
+}
+
+impl WechatMpGuideBuyer {
+    pub fn new(buyer: BuyerId) -> Self {
+        WechatMpGuideBuyer { buyer, buyer_nickname: None }
+    }
+
+    pub fn nickname<S: Into<String>>(mut self, nickname: S) -> Self {
+        self.buyer_nickname = nickname.into().into();
+        self
+    }
+}
+
+/// 批量接口中单个客户的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatMpGuideBuyerResult {
+    /// 传入的客户标识
+    pub buyer: BuyerId,
+    /// 失败时为接口返回的错误码与错误信息
+    pub result: Result<(), WechatMpGuideBuyerFailure>,
+}
+
+/// 批量接口中单个客户失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatMpGuideBuyerFailure {
+    pub errcode: i64,
+    pub errmsg: String,
+}
+
+/// buyer_resp中的单条结果
+#[derive(Debug, Deserialize)]
+struct WechatMpGuideBuyerResp {
+    #[serde(flatten)]
+    buyer: BuyerId,
+    #[serde(default)]
+    errcode: i64,
+    #[serde(default)]
+    errmsg: String,
+}
+
+impl WechatMpGuideBuyerResult {
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// <pre>
+    /// 按buyer_resp合并每个客户的结果
+    /// 部分失败时接口的errcode可能不为0，只要返回了buyer_resp就按客户区分结果，否则按接口错误返回
+    /// </pre>
+    fn from_response(v: Value, buyers: &[BuyerId]) -> LabradorResult<Vec<Self>> {
+        let failures = match v.get("buyer_resp").filter(|v| v.is_array()) {
+            Some(list) => serde_json::from_value::<Vec<WechatMpGuideBuyerResp>>(list.to_owned())?.into_iter()
+                .filter(|item| item.errcode != 0)
+                .map(|item| (item.buyer, WechatMpGuideBuyerFailure { errcode: item.errcode, errmsg: item.errmsg }))
+                .collect::<HashMap<_, _>>(),
+            None => {
+                WechatCommonResponse::parse::<Value>(v)?;
+                HashMap::new()
+            }
+        };
+        Ok(buyers.iter().map(|buyer| WechatMpGuideBuyerResult {
+            buyer: buyer.to_owned(),
+            result: failures.get(buyer).cloned().map_or(Ok(()), Err),
+        }).collect())
+    }
+}
+
+/// 顾问的客户列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpGuideBuyerRelationList {
+    /// 客户总数
+    #[serde(default)]
+    pub total_num: u32,
+    #[serde(default)]
+    pub list: Vec<WechatMpGuideBuyerRelation>,
+}
+
+/// 顾问与客户的关系
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpGuideBuyerRelation {
+    #[serde(flatten)]
+    pub buyer: BuyerId,
+    /// 客户在顾问侧的昵称
+    pub buyer_nickname: Option<String>,
+    /// 建立关系的时间，秒级时间戳
+    pub create_time: Option<i64>,
+}
+
+/// 标签类型及其可选值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMpGuideTagOption {
+    /// 标签类型的名称，如“会员等级”
+    pub tag_name: String,
+    /// 标签可选值，如“银卡”“金卡”
+    #[serde(default)]
+    pub tag_values: Vec<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{BuyerId, GuideId, WechatMpGuideBuyer, WechatMpGuideBuyerFailure, WechatMpGuideBuyerRelationList, WechatMpGuideBuyerResult};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_buyer_id_serialize() {
+        let buyers = vec![
+            WechatMpGuideBuyer::new(BuyerId::OpenId("oOpenid01".to_string())).nickname("张三"),
+            WechatMpGuideBuyer::new(BuyerId::UnionId("oUnionid02".to_string())),
+        ];
+        assert_eq!(serde_json::to_value(&buyers).unwrap(), json!([
+            {"openid": "oOpenid01", "buyer_nickname": "张三"},
+            {"unionid": "oUnionid02"}
+        ]));
+        assert_eq!(serde_json::from_value::<Vec<WechatMpGuideBuyer>>(json!([
+            {"openid": "oOpenid01", "buyer_nickname": "张三"},
+            {"unionid": "oUnionid02"}
+        ])).unwrap(), buyers);
+        assert_eq!(Value::Object(BuyerId::to_list_json(&[buyers[0].buyer.to_owned(), buyers[1].buyer.to_owned()])), json!({"openid_list": ["oOpenid01"], "unionid_list": ["oUnionid02"]}));
+        assert_eq!(Value::Object(GuideId::Account("guide01".to_string()).to_json()), json!({"guide_account": "guide01"}));
+
+        let list = serde_json::from_value::<WechatMpGuideBuyerRelationList>(json!({
+            "errcode": 0, "errmsg": "ok", "total_num": 2,
+            "list": [{"openid": "oOpenid01", "buyer_nickname": "张三", "create_time": 1520000000}, {"unionid": "oUnionid02", "create_time": 1520000001}]
+        })).unwrap();
+        assert_eq!(list.list[0].buyer, BuyerId::OpenId("oOpenid01".to_string()));
+        assert_eq!(list.list[1].buyer, BuyerId::UnionId("oUnionid02".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_add_buyer_relation_partial_failure() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","buyer_resp":[{"openid":"oOpenid01","errcode":0,"errmsg":"ok"},{"unionid":"oUnionid02","errcode":9300866,"errmsg":"buyer already has guide"},{"openid":"oOpenid03","errcode":0,"errmsg":"ok"}]}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_guide_buyer_relation", "secret").base_url(&server.url);
+        let buyers = vec![
+            WechatMpGuideBuyer::new(BuyerId::OpenId("oOpenid01".to_string())).nickname("张三"),
+            WechatMpGuideBuyer::new(BuyerId::UnionId("oUnionid02".to_string())),
+            WechatMpGuideBuyer::new(BuyerId::OpenId("oOpenid03".to_string())),
+        ];
+        let guide = GuideId::OpenId("oGuide01".to_string());
+        let items = client.guide().add_buyer_relation(&guide, &buyers).await.unwrap();
+        assert_eq!(items.iter().map(|v| v.is_success()).collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(items[1].buyer, BuyerId::UnionId("oUnionid02".to_string()));
+        assert_eq!(items[1].result, Err(WechatMpGuideBuyerFailure { errcode: 9300866, errmsg: "buyer already has guide".to_string() }));
+        // 空列表不发送请求
+        assert!(matches!(client.guide().add_buyer_relation(&guide, &[]).await, Err(LabraError::RequestError(_))));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("POST /cgi-bin/guide/addguidebuyerrelation?"), "{}", requests[1]);
+        assert_eq!(body(&requests[1]), json!({
+            "guide_openid": "oGuide01",
+            "buyer_list": [{"openid": "oOpenid01", "buyer_nickname": "张三"}, {"unionid": "oUnionid02"}, {"openid": "oOpenid03"}]
+        }));
+
+        // 没有buyer_resp时按接口错误返回
+        let err = WechatMpGuideBuyerResult::from_response(json!({"errcode": 9300801, "errmsg": "guide not exist"}), &[BuyerId::OpenId("oOpenid01".to_string())]).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "9300801"));
+    }
+}
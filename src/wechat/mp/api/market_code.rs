@@ -0,0 +1,334 @@
+use bytes::Bytes;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::{request, RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, prp::PrpCrypto};
+use crate::wechat::mp::method::{MpMarketCodeMethod, WechatMpMethod};
+use crate::serde_helper::{string_or_number, option_string_or_number};
+
+/// 一物一码解密key的长度（AES-128）
+const CODE_KEY_SIZE: usize = 16;
+
+/// 一物一码.
+#[derive(Debug, Clone)]
+pub struct WechatMpMarketCode<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpMarketCode<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpMarketCode<T> {
+        WechatMpMarketCode {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.market_code()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpMarketCode<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 申请二维码，返回申请单号application_id，用于查询、下载与激活
+    /// isv_application_id为外部单号，相同的外部单号不会重复申请
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Unique_Item_Code/Unique_Item_Code_API_Documentation.html
+    /// </pre>
+    pub async fn apply(&self, goods_name: &str, total_count: i64, isv_application_id: &str) -> LabradorResult<i64> {
+        let v = self.client.post(WechatMpMethod::MarketCode(MpMarketCodeMethod::ApplyCode), vec![], json!({
+            "goods_name": goods_name,
+            "total_count": total_count,
+            "isv_application_id": isv_application_id,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<WechatMpMarketCodeApplyResponse>(v)?;
+        Ok(v.application_id)
+    }
+
+    /// <pre>
+    /// 查询二维码申请单，申请单生成完成后返回可下载的号段
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Unique_Item_Code/Unique_Item_Code_API_Documentation.html
+    /// </pre>
+    pub async fn query(&self, application_id: i64, isv_application_id: Option<&str>) -> LabradorResult<WechatMpMarketCodeApplication> {
+        let mut data = json!({ "application_id": application_id });
+        if let Some(isv_application_id) = isv_application_id {
+            data["isv_application_id"] = isv_application_id.into();
+        }
+        let v = self.client.post(WechatMpMethod::MarketCode(MpMarketCodeMethod::ApplyCodeQuery), vec![], data, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMarketCodeApplication>(v)
+    }
+
+    /// <pre>
+    /// 下载号段内的二维码，返回加密的码包，使用[`decrypt_code_package`]解密
+    /// 接口直接返回base64编码的码包（buffer），或返回短时有效的下载地址，此时会立即下载
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Unique_Item_Code/Unique_Item_Code_API_Documentation.html
+    /// </pre>
+    pub async fn download(&self, application_id: i64, code_start: i64, code_end: i64) -> LabradorResult<Bytes> {
+        let v = self.client.post(WechatMpMethod::MarketCode(MpMarketCodeMethod::ApplyCodeDownload), vec![], json!({
+            "application_id": application_id,
+            "code_start": code_start,
+            "code_end": code_end,
+        }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        if let Some(buffer) = v["buffer"].as_str() {
+            return Ok(Bytes::from(base64::decode(buffer)?));
+        }
+        match v["url"].as_str().or_else(|| v["download_url"].as_str()) {
+            Some(url) => request(|client| client.get(url)).await?.bytes(),
+            None => Err(LabraError::ApiError("applycodedownload未返回码包".to_string())),
+        }
+    }
+
+    /// <pre>
+    /// 激活号段内的二维码，激活后扫码跳转到指定的小程序页面
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Unique_Item_Code/Unique_Item_Code_API_Documentation.html
+    /// </pre>
+    pub async fn active(&self, req: WechatMpMarketCodeActiveRequest) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMpMethod::MarketCode(MpMarketCodeMethod::CodeActive), vec![], req, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 查询二维码激活状态，code_index、code_url、code三者任选其一
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Unique_Item_Code/Unique_Item_Code_API_Documentation.html
+    /// </pre>
+    pub async fn active_query(&self, application_id: i64, code: WechatMpMarketCodeKey) -> LabradorResult<WechatMpMarketCodeActiveInfo> {
+        let mut data = json!({ "application_id": application_id });
+        match code {
+            WechatMpMarketCodeKey::Index(code_index) => data["code_index"] = code_index.into(),
+            WechatMpMarketCodeKey::Url(code_url) => data["code_url"] = code_url.into(),
+            WechatMpMarketCodeKey::Code(code) => data["code"] = code.into(),
+        }
+        let v = self.client.post(WechatMpMethod::MarketCode(MpMarketCodeMethod::CodeActiveQuery), vec![], data, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMarketCodeActiveInfo>(v)
+    }
+
+    /// <pre>
+    /// 用户扫码进入小程序后，将小程序收到的code_ticket转换为码的信息
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Unique_Item_Code/Unique_Item_Code_API_Documentation.html
+    /// </pre>
+    pub async fn ticket_to_code(&self, openid: &str, code_ticket: &str) -> LabradorResult<WechatMpMarketCodeTicketInfo> {
+        let v = self.client.post(WechatMpMethod::MarketCode(MpMarketCodeMethod::TicketToCode), vec![], json!({
+            "openid": openid,
+            "code_ticket": code_ticket,
+        }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMarketCodeTicketInfo>(v)
+    }
+}
+
+/// <pre>
+/// 解密下载的码包，返回其中的码
+/// 码包为AES-128-CBC（PKCS#7补位）加密，key为公众平台申请一物一码时获取的16位key，IV与key相同；
+/// 解密后每行一个码
+/// </pre>
+pub fn decrypt_code_package(package: &[u8], key: &str) -> LabradorResult<Vec<String>> {
+    if key.len() != CODE_KEY_SIZE {
+        return Err(LabraError::RequestError(format!("一物一码key必须为{}位", CODE_KEY_SIZE)));
+    }
+    let plaintext = PrpCrypto::new(key.as_bytes().to_vec()).aes_128_cbc_decrypt_data_bytes(package, key.as_bytes())?;
+    let plaintext = String::from_utf8(plaintext).map_err(|_| LabraError::DecodeError("码包解密后不是有效的文本".to_string().into()))?;
+    Ok(plaintext.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect())
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WechatMpMarketCodeApplyResponse {
+    #[serde(with = "string_or_number")]
+    application_id: i64,
+}
+
+/// 已生成的号段
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WechatMpMarketCodeRange {
+    #[serde(with = "string_or_number")]
+    pub code_start: i64,
+    #[serde(with = "string_or_number")]
+    pub code_end: i64,
+}
+
+/// 二维码申请单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpMarketCodeApplication {
+    /// 申请单状态：INIT（初始化），PROCESSING（生成中），FINISHED（已完成）等
+    pub status: Option<String>,
+    /// 外部单号
+    pub isv_application_id: Option<String>,
+    /// 申请单号
+    #[serde(default, with = "option_string_or_number")]
+    pub application_id: Option<i64>,
+    #[serde(default, with = "option_string_or_number")]
+    pub create_time: Option<i64>,
+    #[serde(default, with = "option_string_or_number")]
+    pub update_time: Option<i64>,
+    /// 已生成的号段，下载时使用
+    #[serde(default)]
+    pub code_generate_list: Vec<WechatMpMarketCodeRange>,
+}
+
+impl WechatMpMarketCodeApplication {
+    /// 二维码是否已全部生成
+    pub fn is_finished(&self) -> bool {
+        self.status.as_deref() == Some("FINISHED")
+    }
+}
+
+/// 激活二维码
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WechatMpMarketCodeActiveRequest {
+    /// 申请单号
+    pub application_id: i64,
+    /// 活动名称
+    pub activity_name: String,
+    /// 商品品牌
+    pub product_brand: String,
+    /// 商品标题
+    pub product_title: String,
+    /// 商品条码
+    pub product_code: String,
+    /// 跳转的小程序appid
+    pub wxa_appid: String,
+    /// 跳转的小程序路径
+    pub wxa_path: String,
+    /// 小程序版本：0正式版，1开发版，2体验版
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wxa_type: Option<u8>,
+    /// 激活号段的起始位置
+    pub code_start: i64,
+    /// 激活号段的结束位置
+    pub code_end: i64,
+}
+
+/// 查询激活状态时指定码的方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatMpMarketCodeKey {
+    /// 码的序号
+    Index(i64),
+    /// 码的链接
+    Url(String),
+    /// 码
+    Code(String),
+}
+
+/// 二维码激活信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpMarketCodeActiveInfo {
+    #[serde(default, with = "option_string_or_number")]
+    pub code_start: Option<i64>,
+    #[serde(default, with = "option_string_or_number")]
+    pub code_end: Option<i64>,
+    pub activity_name: Option<String>,
+    pub product_brand: Option<String>,
+    pub product_title: Option<String>,
+    pub product_code: Option<String>,
+    pub wxa_appid: Option<String>,
+    pub wxa_path: Option<String>,
+    pub wxa_type: Option<u8>,
+}
+
+/// code_ticket对应的码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpMarketCodeTicketInfo {
+    pub code: Option<String>,
+    #[serde(default, with = "option_string_or_number")]
+    pub code_index: Option<i64>,
+    #[serde(default, with = "option_string_or_number")]
+    pub application_id: Option<i64>,
+    pub code_url: Option<String>,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::{LabraError, prp::PrpCrypto, SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{decrypt_code_package, WechatMpMarketCodeKey, WechatMpMarketCodeRange};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+    const KEY: &str = "0123456789abcdef";
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    fn package(codes: &str) -> Vec<u8> {
+        PrpCrypto::new(KEY.as_bytes().to_vec()).aes_128_cbc_encrypt_data_bytes(codes.as_bytes(), KEY.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_decrypt_code_package() {
+        let codes = decrypt_code_package(&package("CODE0001\nCODE0002\r\nCODE0003\n\n"), KEY).unwrap();
+        assert_eq!(codes, vec!["CODE0001", "CODE0002", "CODE0003"]);
+        assert!(decrypt_code_package(&package(""), KEY).unwrap().is_empty());
+        // key错误时解密失败
+        assert!(decrypt_code_package(&package("CODE0001"), "fedcba9876543210").is_err());
+        match decrypt_code_package(&package("CODE0001"), "short") {
+            Err(LabraError::RequestError(msg)) => assert!(msg.contains("16")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_and_query() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","application_id":1502}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","status":"FINISHED","isv_application_id":"ISV_001","application_id":"1502","create_time":1564368630,"update_time":1564368640,"code_generate_list":[{"code_start":0,"code_end":99}]}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_market_code_apply", "secret").base_url(&server.url);
+        let market_code = client.market_code();
+        let application_id = market_code.apply("goods", 100, "ISV_001").await.unwrap();
+        assert_eq!(application_id, 1502);
+        let application = market_code.query(application_id, Some("ISV_001")).await.unwrap();
+        assert!(application.is_finished());
+        assert_eq!(application.application_id, Some(1502));
+        assert_eq!(application.code_generate_list, vec![WechatMpMarketCodeRange { code_start: 0, code_end: 99 }]);
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /intp/marketcode/applycode?"));
+        assert_eq!(body(&requests[1])["total_count"], 100);
+        assert!(requests[2].starts_with("POST /intp/marketcode/applycodequery?"));
+        // 申请返回的单号用于查询
+        assert_eq!(body(&requests[2])["application_id"], 1502);
+        assert_eq!(body(&requests[2])["isv_application_id"], "ISV_001");
+    }
+
+    #[tokio::test]
+    async fn test_download() {
+        let encrypted = package("CODE0001\nCODE0002");
+        let buffer = MockResponse::json(&format!(r#"{{"errcode":0,"errmsg":"ok","buffer":"{}"}}"#, base64::encode(&encrypted)));
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), buffer]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_market_code_download", "secret").base_url(&server.url);
+        let package = client.market_code().download(1502, 0, 1).await.unwrap();
+        assert_eq!(decrypt_code_package(&package, KEY).unwrap(), vec!["CODE0001", "CODE0002"]);
+        let request = &server.requests()[1];
+        assert!(request.starts_with("POST /intp/marketcode/applycodedownload?"));
+        assert_eq!(body(request)["code_end"], 1);
+
+        // 返回下载地址时通过二进制接口下载
+        let file = MockServer::start(vec![MockResponse::bytes("application/octet-stream", &encrypted)]).await;
+        let url = MockResponse::json(&format!(r#"{{"errcode":0,"errmsg":"ok","url":"{}/package"}}"#, file.url));
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), url]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_market_code_download_url", "secret").base_url(&server.url);
+        let package = client.market_code().download(1502, 0, 1).await.unwrap();
+        assert_eq!(decrypt_code_package(&package, KEY).unwrap(), vec!["CODE0001", "CODE0002"]);
+        assert!(file.requests()[0].starts_with("GET /package"));
+    }
+
+    #[tokio::test]
+    async fn test_active_query() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","code_start":0,"code_end":99,"activity_name":"activity","wxa_appid":"wx_wxa"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_market_code_active_query", "secret").base_url(&server.url);
+        let info = client.market_code().active_query(1502, WechatMpMarketCodeKey::Code("CODE0001".to_string())).await.unwrap();
+        assert_eq!(info.code_end, Some(99));
+        assert_eq!(info.wxa_appid.as_deref(), Some("wx_wxa"));
+        let body = body(&server.requests()[1]);
+        assert_eq!(body["code"], "CODE0001");
+        assert!(body.get("code_index").is_none());
+    }
+}
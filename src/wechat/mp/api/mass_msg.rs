@@ -0,0 +1,398 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, WechatMpNewsArticle};
+use crate::wechat::mp::method::{MpMassMessageMethod, WechatMpMethod};
+use crate::wechat::mp::replies::ArticleLimit;
+use crate::serde_helper::{string_or_number, option_string_or_number};
+
+/// 按OpenID列表群发时，接收者数量下限
+const MASS_OPENID_MIN: usize = 2;
+/// 按OpenID列表群发时，接收者数量上限
+const MASS_OPENID_MAX: usize = 10000;
+
+/// 群发消息.
+#[derive(Debug, Clone)]
+pub struct WechatMpMassMessage<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpMassMessage<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpMassMessage<T> {
+        WechatMpMassMessage {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.mass_msg()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpMassMessage<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 上传图文消息素材，返回的media_id用于群发图文消息，图文消息最多8条
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Batch_Sends_and_Originality_Checks.html
+    /// </pre>
+    pub async fn upload_news(&self, articles: Vec<WechatMpNewsArticle>) -> LabradorResult<WechatMpMassUploadNewsResponse> {
+        ArticleLimit::MassNews.check(articles.len())?;
+        let v = self.client.post(WechatMpMethod::MassMessage(MpMassMessageMethod::UploadNews), vec![], json!({"articles": articles}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMassUploadNewsResponse>(v)
+    }
+
+    /// <pre>
+    /// 根据标签进行群发，is_to_all为true时发送给全部用户
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Batch_Sends_and_Originality_Checks.html
+    /// </pre>
+    pub async fn send_by_tag(&self, filter: WechatMpMassFilter, message: WechatMpMassMessageContent) -> LabradorResult<WechatMpMassSendResponse> {
+        let data = message.to_value("filter", serde_json::to_value(filter)?, false);
+        let v = self.client.post(WechatMpMethod::MassMessage(MpMassMessageMethod::SendAll), vec![], data, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMassSendResponse>(v)
+    }
+
+    /// <pre>
+    /// 根据OpenID列表群发，OpenID最少2个，最多10000个
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Batch_Sends_and_Originality_Checks.html
+    /// </pre>
+    pub async fn send_by_openids(&self, openids: Vec<String>, message: WechatMpMassMessageContent) -> LabradorResult<WechatMpMassSendResponse> {
+        check_openids(&openids)?;
+        let data = message.to_value("touser", json!(openids), false);
+        let v = self.client.post(WechatMpMethod::MassMessage(MpMassMessageMethod::Send), vec![], data, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMassSendResponse>(v)
+    }
+
+    /// <pre>
+    /// 预览接口，发送给指定的OpenID或微信号，每日调用上限为100次
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Batch_Sends_and_Originality_Checks.html
+    /// </pre>
+    pub async fn preview(&self, target: WechatMpMassPreviewTarget, message: WechatMpMassMessageContent) -> LabradorResult<WechatMpMassSendResponse> {
+        let (key, value) = match target {
+            WechatMpMassPreviewTarget::OpenId(openid) => ("touser", openid),
+            WechatMpMassPreviewTarget::WxName(wxname) => ("towxname", wxname),
+        };
+        let data = message.to_value(key, Value::String(value), true);
+        let v = self.client.post(WechatMpMethod::MassMessage(MpMassMessageMethod::Preview), vec![], data, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMassSendResponse>(v)
+    }
+
+    /// <pre>
+    /// 查询群发消息发送状态
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Batch_Sends_and_Originality_Checks.html
+    /// </pre>
+    pub async fn get_status(&self, msg_id: i64) -> LabradorResult<WechatMpMassStatusResponse> {
+        let v = self.client.post(WechatMpMethod::MassMessage(MpMassMessageMethod::Get), vec![], json!({"msg_id": msg_id.to_string()}), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMassStatusResponse>(v)
+    }
+
+    /// <pre>
+    /// 删除群发，只能删除图文消息和视频消息，群发后半小时内可删除
+    /// article_idx为要删除的文章在图文消息中的位置，从1开始，不填或为0时删除全部文章
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Batch_Sends_and_Originality_Checks.html
+    /// </pre>
+    pub async fn delete(&self, msg_id: i64, article_idx: Option<u32>) -> LabradorResult<()> {
+        let mut data = json!({"msg_id": msg_id});
+        if let Some(article_idx) = article_idx {
+            data["article_idx"] = json!(article_idx);
+        }
+        let v = self.client.post(WechatMpMethod::MassMessage(MpMassMessageMethod::Delete), vec![], data, RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<Value>(v)?;
+        Ok(())
+    }
+}
+
+fn check_openids(openids: &[String]) -> LabradorResult<()> {
+    if openids.len() < MASS_OPENID_MIN || openids.len() > MASS_OPENID_MAX {
+        return Err(LabraError::RequestError(format!("群发的OpenID数量应为{}～{}个，当前为{}个", MASS_OPENID_MIN, MASS_OPENID_MAX, openids.len())));
+    }
+    Ok(())
+}
+
+/// 群发的接收范围
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WechatMpMassFilter {
+    /// 是否发送给全部用户，为true时忽略tag_id
+    pub is_to_all: bool,
+    /// 群发的标签ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_id: Option<i64>,
+}
+
+impl WechatMpMassFilter {
+    /// 发送给全部用户
+    pub fn to_all() -> Self {
+        WechatMpMassFilter { is_to_all: true, tag_id: None }
+    }
+
+    /// 发送给指定标签的用户
+    pub fn tag(tag_id: i64) -> Self {
+        WechatMpMassFilter { is_to_all: false, tag_id: Some(tag_id) }
+    }
+}
+
+/// 预览的接收者
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatMpMassPreviewTarget {
+    OpenId(String),
+    /// 微信号，优先级高于OpenID
+    WxName(String),
+}
+
+/// 群发的消息内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatMpMassMessageContent {
+    /// 图文消息，send_ignore_reprint为文章被判定为转载时是否继续群发
+    MpNews { media_id: String, send_ignore_reprint: bool },
+    /// 文本消息
+    Text { content: String },
+    /// 图片消息，预览时只发送第一张
+    Image { media_ids: Vec<String>, recommend: Option<String> },
+    /// 语音消息
+    Voice { media_id: String },
+    /// 视频消息，media_id为上传视频素材后转换得到的群发视频media_id
+    Video { media_id: String },
+}
+
+impl WechatMpMassMessageContent {
+    pub fn msgtype(&self) -> &'static str {
+        match self {
+            WechatMpMassMessageContent::MpNews { .. } => "mpnews",
+            WechatMpMassMessageContent::Text { .. } => "text",
+            WechatMpMassMessageContent::Image { .. } => "image",
+            WechatMpMassMessageContent::Voice { .. } => "voice",
+            WechatMpMassMessageContent::Video { .. } => "mpvideo",
+        }
+    }
+
+    /// 组装请求报文，`key`、`target`为接收范围；预览接口的图片消息格式与群发不同
+    fn to_value(&self, key: &str, target: Value, preview: bool) -> Value {
+        let mut data = Map::new();
+        data.insert(key.to_string(), target);
+        data.insert("msgtype".to_string(), json!(self.msgtype()));
+        match self {
+            WechatMpMassMessageContent::MpNews { media_id, send_ignore_reprint } => {
+                data.insert("mpnews".to_string(), json!({"media_id": media_id}));
+                if !preview {
+                    data.insert("send_ignore_reprint".to_string(), json!(*send_ignore_reprint as u8));
+                }
+            }
+            WechatMpMassMessageContent::Text { content } => {
+                data.insert("text".to_string(), json!({"content": content}));
+            }
+            WechatMpMassMessageContent::Image { media_ids, .. } if preview => {
+                data.insert("image".to_string(), json!({"media_id": media_ids.first()}));
+            }
+            WechatMpMassMessageContent::Image { media_ids, recommend } => {
+                let mut images = json!({"media_ids": media_ids});
+                if let Some(recommend) = recommend {
+                    images["recommend"] = json!(recommend);
+                }
+                data.insert("images".to_string(), images);
+            }
+            WechatMpMassMessageContent::Voice { media_id } => {
+                data.insert("voice".to_string(), json!({"media_id": media_id}));
+            }
+            WechatMpMassMessageContent::Video { media_id } => {
+                data.insert("mpvideo".to_string(), json!({"media_id": media_id}));
+            }
+        }
+        Value::Object(data)
+    }
+}
+
+/// 群发、预览的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpMassSendResponse {
+    /// 消息发送任务的ID
+    #[serde(with = "string_or_number")]
+    pub msg_id: i64,
+    /// 消息的数据ID，仅在群发图文消息时返回，可用于图文分析数据接口
+    #[serde(default, with = "option_string_or_number")]
+    pub msg_data_id: Option<i64>,
+}
+
+/// 上传群发图文消息素材的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpMassUploadNewsResponse {
+    /// 媒体文件类型，图文消息为news
+    #[serde(rename = "type")]
+    pub media_type: Option<String>,
+    /// 图文消息的media_id
+    pub media_id: String,
+    /// 上传时间
+    pub created_at: Option<i64>,
+}
+
+/// 群发消息的发送状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WechatMpMassStatus {
+    /// 发送成功
+    SendSuccess,
+    /// 发送中
+    Sending,
+    /// 发送失败
+    SendFail,
+    /// 已删除
+    Delete,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 查询群发消息发送状态的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpMassStatusResponse {
+    #[serde(with = "string_or_number")]
+    pub msg_id: i64,
+    pub msg_status: WechatMpMassStatus,
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{LabraError, SimpleStorage, WechatMpClient, WechatMpNewsArticle};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatMpMassFilter, WechatMpMassMessageContent, WechatMpMassPreviewTarget, WechatMpMassStatus};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn body(request: &str) -> serde_json::Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_message_serialization() {
+        let filter = serde_json::to_value(WechatMpMassFilter::tag(2)).unwrap();
+        let mpnews = WechatMpMassMessageContent::MpNews { media_id: "MEDIA_ID".to_string(), send_ignore_reprint: false };
+        assert_eq!(mpnews.to_value("filter", filter.clone(), false), json!({
+            "filter": {"is_to_all": false, "tag_id": 2},
+            "mpnews": {"media_id": "MEDIA_ID"},
+            "msgtype": "mpnews",
+            "send_ignore_reprint": 0,
+        }));
+        let text = WechatMpMassMessageContent::Text { content: "CONTENT".to_string() };
+        assert_eq!(text.to_value("filter", serde_json::to_value(WechatMpMassFilter::to_all()).unwrap(), false), json!({
+            "filter": {"is_to_all": true},
+            "text": {"content": "CONTENT"},
+            "msgtype": "text",
+        }));
+        let image = WechatMpMassMessageContent::Image { media_ids: vec!["MEDIA_1".to_string(), "MEDIA_2".to_string()], recommend: Some("推荐语".to_string()) };
+        assert_eq!(image.to_value("filter", filter.clone(), false), json!({
+            "filter": {"is_to_all": false, "tag_id": 2},
+            "images": {"media_ids": ["MEDIA_1", "MEDIA_2"], "recommend": "推荐语"},
+            "msgtype": "image",
+        }));
+        let voice = WechatMpMassMessageContent::Voice { media_id: "MEDIA_ID".to_string() };
+        assert_eq!(voice.to_value("touser", json!(["OPENID1", "OPENID2"]), false), json!({
+            "touser": ["OPENID1", "OPENID2"],
+            "voice": {"media_id": "MEDIA_ID"},
+            "msgtype": "voice",
+        }));
+        let video = WechatMpMassMessageContent::Video { media_id: "MEDIA_ID".to_string() };
+        assert_eq!(video.to_value("filter", filter, false), json!({
+            "filter": {"is_to_all": false, "tag_id": 2},
+            "mpvideo": {"media_id": "MEDIA_ID"},
+            "msgtype": "mpvideo",
+        }));
+        // 预览
+        assert_eq!(image.to_value("towxname", json!("WXNAME"), true), json!({
+            "towxname": "WXNAME",
+            "image": {"media_id": "MEDIA_1"},
+            "msgtype": "image",
+        }));
+        assert_eq!(mpnews.to_value("touser", json!("OPENID"), true), json!({
+            "touser": "OPENID",
+            "mpnews": {"media_id": "MEDIA_ID"},
+            "msgtype": "mpnews",
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_send_by_openids_limit() {
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_mass_limit", "secret").base_url("http://127.0.0.1:1");
+        let text = WechatMpMassMessageContent::Text { content: "CONTENT".to_string() };
+        for count in [0usize, 1, 10001].iter() {
+            let openids = (0..*count).map(|i| format!("OPENID{}", i)).collect();
+            let err = client.mass_msg().send_by_openids(openids, text.clone()).await.unwrap_err();
+            assert!(matches!(err, LabraError::RequestError(_)), "{}", count);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_and_status() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"send job submission success","msg_id":34182,"msg_data_id":206227730}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"preview success","msg_id":34183}"#),
+            MockResponse::json(r#"{"msg_id":201053012,"msg_status":"SEND_SUCCESS"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_mass_send", "secret").base_url(&server.url);
+        let mpnews = WechatMpMassMessageContent::MpNews { media_id: "MEDIA_ID".to_string(), send_ignore_reprint: true };
+        let res = client.mass_msg().send_by_tag(WechatMpMassFilter::tag(2), mpnews.clone()).await.unwrap();
+        assert_eq!(res.msg_id, 34182);
+        assert_eq!(res.msg_data_id, Some(206227730));
+        let res = client.mass_msg().preview(WechatMpMassPreviewTarget::OpenId("OPENID".to_string()), mpnews).await.unwrap();
+        assert_eq!(res.msg_id, 34183);
+        assert_eq!(res.msg_data_id, None);
+        let status = client.mass_msg().get_status(201053012).await.unwrap();
+        assert_eq!(status.msg_status, WechatMpMassStatus::SendSuccess);
+        client.mass_msg().delete(34182, Some(2)).await.unwrap();
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/message/mass/sendall?"));
+        assert_eq!(body(&requests[1])["send_ignore_reprint"], 1);
+        assert!(requests[2].starts_with("POST /cgi-bin/message/mass/preview?"));
+        assert!(requests[3].starts_with("POST /cgi-bin/message/mass/get?"));
+        assert_eq!(body(&requests[3]), json!({"msg_id": "201053012"}));
+        assert!(requests[4].starts_with("POST /cgi-bin/message/mass/delete?"));
+        assert_eq!(body(&requests[4]), json!({"msg_id": 34182, "article_idx": 2}));
+    }
+
+    #[test]
+    fn test_status() {
+        for (status, expected) in [("SEND_SUCCESS", WechatMpMassStatus::SendSuccess), ("SENDING", WechatMpMassStatus::Sending), ("SEND_FAIL", WechatMpMassStatus::SendFail), ("DELETE", WechatMpMassStatus::Delete), ("OTHER", WechatMpMassStatus::Unknown)].iter() {
+            assert_eq!(&serde_json::from_value::<WechatMpMassStatus>(json!(status)).unwrap(), expected);
+        }
+    }
+
+    fn news_article(title: &str) -> WechatMpNewsArticle {
+        WechatMpNewsArticle {
+            thumb_media_id: "THUMB_MEDIA_ID".to_string(),
+            thumb_url: None,
+            author: None,
+            title: title.to_string(),
+            content_source_url: None,
+            content: "CONTENT".to_string(),
+            digest: None,
+            show_cover_pic: true,
+            url: None,
+            need_open_comment: None,
+            only_fans_can_comment: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_news_limit() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"type":"news","media_id":"CsEf3ldqkAYJAU6EJeIkStVDSvffUJ54vqbThMgplD-VJXXof6ctX5fI6-aYyUiQ","created_at":1391857799}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_mass_upload_news", "secret").base_url(&server.url);
+        assert!(matches!(client.mass_msg().upload_news(vec![]).await, Err(LabraError::RequestError(_))));
+        let articles = (0..9).map(|i| news_article(&format!("TITLE{}", i))).collect::<Vec<_>>();
+        assert!(matches!(client.mass_msg().upload_news(articles.clone()).await, Err(LabraError::RequestError(msg)) if msg.contains("群发图文消息") && msg.contains("8条")));
+        let res = client.mass_msg().upload_news(articles[..8].to_vec()).await.unwrap();
+        assert_eq!(res.media_type.as_deref(), Some("news"));
+        assert_eq!(res.created_at, Some(1391857799));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("POST /cgi-bin/media/uploadnews?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[1])["articles"].as_array().unwrap().len(), 8);
+    }
+}
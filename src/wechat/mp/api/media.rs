@@ -20,30 +20,40 @@
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::collections::HashMap;
+use std::future::Future;
 use bytes::Bytes;
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
+use futures_util::Stream;
 
-use crate::{session::SessionStore, LabradorResult, RequestBody, RequestType, WechatMpClient, WechatCommonResponse, WechatRequest, get_nonce_str, request};
+use crate::{session::SessionStore, LabradorResult, LabraError, WechatMpNewsArticle, RequestBody, RequestType, WechatMpClient, WechatCommonResponse, WechatRequest, get_nonce_str, request, Page, PagedStream};
 use crate::wechat::mp::constants::MATERIAL_TYPE_NEWS;
 use crate::wechat::mp::method::{MpMediaMethod, WechatMpMethod};
+use crate::serde_helper::option_string_or_number;
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMpMedia<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpMedia<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpMedia<'a, T> {
+impl<T: SessionStore> WechatMpMedia<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpMedia<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpMedia<T> {
         WechatMpMedia {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.media()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpMedia<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 新增临时素材
     /// 公众号经常有需要用到一些临时性的多媒体素材的场景，例如在使用接口特别是发送消息时，对多媒体文件、多媒体消息的获取和调用等操作，是通过media_id来进行的。
@@ -130,6 +140,33 @@ impl<'a, T: SessionStore> WechatMpMedia<'a, T> {
         response.bytes()
     }
 
+    /// <pre>
+    /// 下载用户发送的语音消息
+    /// 语音消息推送中的MediaId通过获取临时素材接口下载，通常为AMR格式（8K采样率），这里按文件头识别实际格式，
+    /// 失败时微信返回JSON格式的错误信息
+    /// </pre>
+    pub async fn download_voice(&self, media_id: &str) -> LabradorResult<WechatMpVoice> {
+        let data = self.get_media(media_id).await?;
+        let format = WechatMpVoiceFormat::detect(&data);
+        if format == WechatMpVoiceFormat::Unknown {
+            if let Ok(resp) = WechatCommonResponse::from_str(&String::from_utf8_lossy(&data)) {
+                if !resp.is_success() {
+                    return Err(LabraError::ClientError { errcode: resp.errcode.unwrap_or_default().to_string(), errmsg: resp.errmsg.unwrap_or_default() });
+                }
+            }
+        }
+        Ok(WechatMpVoice { data, format })
+    }
+
+    /// <pre>
+    /// 下载语音消息并转换为transcoder的目标格式，已是目标格式时不做转换
+    /// 转码在当前任务中同步执行，耗时较长的实现（如调用ffmpeg）可自行放到`spawn_blocking`中
+    /// </pre>
+    pub async fn download_voice_as(&self, media_id: &str, transcoder: &dyn AudioTranscoder) -> LabradorResult<WechatMpVoice> {
+        let voice = self.download_voice(media_id).await?;
+        voice.transcode(transcoder)
+    }
+
     /// 上传图文消息内的图片获取URL
     /// <pre>
     /// 对于常用的素材，开发者可通过本接口上传到微信服务器，永久使用。新增的永久素材也可以在公众平台官网素材管理模块中查询管理。
@@ -154,6 +191,19 @@ impl<'a, T: SessionStore> WechatMpMedia<'a, T> {
         WechatCommonResponse::parse::<WechatMpMediaResponse>(v)
     }
 
+    /// 上传图文消息内的图片，返回可在图文正文中使用的URL
+    /// <pre>
+    /// 该接口返回的是图片url而非media_id，所上传的图片不占用公众号素材库的数量限制
+    /// 图片仅支持jpg/png格式，大小必须在1MB以下，上传前会在本地校验，不满足时直接返回错误
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Asset_Management/Adding_Permanent_Assets.html">上传图文消息内的图片获取URL</a>
+    /// 接口url格式：https://api.weixin.qq.com/cgi-bin/media/uploadimg?access_token=ACCESS_TOKEN
+    /// </pre>
+    pub async fn upload_article_image(&self, data: Vec<u8>, file_name: &str) -> LabradorResult<String> {
+        check_article_image(&data, file_name)?;
+        let res = self.upload_img_media(file_name, data).await?;
+        res.url.filter(|url| !url.is_empty()).ok_or_else(|| LabraError::MissingField("上传图文消息内的图片未返回url".to_string()))
+    }
+
     /// <pre>
     /// 新增非图文永久素材
     /// 通过POST表单来调用接口，表单id为media，包含需要上传的素材内容，有filename、filelength、content-type等信息。请注意：图片素材将进入公众平台官网素材管理模块中的默认分组。
@@ -176,6 +226,34 @@ impl<'a, T: SessionStore> WechatMpMedia<'a, T> {
         WechatCommonResponse::parse::<WechatMpMediaResponse>(v)
     }
 
+    /// <pre>
+    /// 流式新增非图文永久素材
+    /// 与`upload_material`相同，但素材内容从`AsyncRead`边读边上传，适合较大的视频素材，无需整个读入内存。
+    /// 上传前按调用方给出的长度校验大小：图片、语音不超过10M，视频不超过200M，缩略图不超过64KB；超出时直接返回错误，不发起请求。
+    /// 详情请见: <a href="http://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1444738729&token=&lang=zh_CN">新增永久素材</a>
+    /// 接口url格式：https://api.weixin.qq.com/cgi-bin/material/add_material?access_token=ACCESS_TOKEN&type=TYPE
+    /// </pre>
+    #[cfg(feature = "upload-stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "upload-stream")))]
+    pub async fn upload_material_stream<R>(&self, req: WechatMpMaterialStream<R>) -> LabradorResult<WechatMpMediaResponse> where R: tokio::io::AsyncRead + Send + Unpin + 'static {
+        check_material_length(&req.media_type, req.length)?;
+        let WechatMpMaterialStream { media_type, filename, reader, length, video_title, video_introduction, progress, timeout } = req;
+        let body = match progress {
+            Some(progress) => crate::upload::reader_body(crate::upload::ProgressReader::new(reader, length, progress), length),
+            None => crate::upload::reader_body(reader, length),
+        };
+        let mut form = reqwest::multipart::Form::new().part("media", reqwest::multipart::Part::stream_with_length(body, length).file_name(filename));
+        if let Some(video_title) = video_title {
+            form = form.text("title", video_title);
+        }
+        if let Some(video_introduction) = video_introduction {
+            form = form.text("introduction", video_introduction);
+        }
+        let req = WechatMpMaterialStreamRequest { media_type, form: std::sync::Mutex::new(Some(form)), timeout };
+        let v = self.client.execute::<WechatMpMaterialStreamRequest, String>(req).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpMediaResponse>(v)
+    }
+
     /// <pre>
     /// 获取声音或者图片永久素材
     ///
@@ -270,9 +348,160 @@ impl<'a, T: SessionStore> WechatMpMedia<'a, T> {
         WechatCommonResponse::parse::<WechatMpMaterialBatchResponse>(v)
     }
 
+    /// <pre>
+    /// 获取全部其他媒体素材
+    /// 基于get_material_batch按offset自动翻页，消费时才会发起请求。
+    /// `count` 每页返回的条数，取值在1到20之间，`max_items` 最多返回的条数
+    /// </pre>
+    pub fn list_all_material(&self, material_type: &str, count: i32, max_items: Option<usize>) -> impl Stream<Item = LabradorResult<WechatMpMaterialBatchItem>> + '_ {
+        let client = self.client.to_owned();
+        let material_type = material_type.to_string();
+        let mut pager = PagedStream::new(move |offset: Option<i32>| {
+            let material_type = material_type.to_owned();
+            let media = WechatMpMedia::from_client(client.to_owned());
+            async move {
+                let offset = offset.unwrap_or_default();
+                let res = media.get_material_batch(&material_type, offset, count).await?;
+                let items = res.items.unwrap_or_default();
+                let next = offset + items.len() as i32;
+                let next = if next < res.total_count.unwrap_or_default() { Some(next) } else { None };
+                Ok(Page::new(items, next))
+            }
+        });
+        if let Some(max_items) = max_items {
+            pager = pager.max_items(max_items);
+        }
+        pager.into_stream()
+    }
+
 
 }
 
+/// 素材管理，与[`WechatMpMedia`]为同一服务
+pub type WechatMpMaterial<T> = WechatMpMedia<T>;
+
+/// 图文消息内的图片最大1MB
+pub const ARTICLE_IMAGE_MAX_SIZE: usize = 1024 * 1024;
+
+/// 校验图文消息内的图片：仅支持jpg/png格式，大小在1MB以下
+pub fn check_article_image(data: &[u8], file_name: &str) -> LabradorResult<()> {
+    let extension = Path::new(file_name).extension().and_then(|v| v.to_str()).unwrap_or_default().to_lowercase();
+    if !matches!(extension.as_str(), "jpg" | "jpeg" | "png") {
+        return Err(LabraError::RequestError(format!("图文消息内的图片仅支持jpg/png格式，当前文件：{}", file_name)));
+    }
+    if data.is_empty() {
+        return Err(LabraError::RequestError(format!("图文消息内的图片内容为空：{}", file_name)));
+    }
+    if data.len() >= ARTICLE_IMAGE_MAX_SIZE {
+        return Err(LabraError::RequestError(format!("图文消息内的图片大小必须在1MB以下，当前文件：{}，大小：{}字节", file_name, data.len())));
+    }
+    Ok(())
+}
+
+/// <pre>
+/// 替换HTML中`<img>`标签的src
+/// 依次将每个图片地址交给`resolver`（通常是读取本地图片后调用`upload_article_image`），
+/// 返回`Some(url)`时替换为该地址，返回`None`时保持不变；已是微信域名（mmbiz.qpic.cn等）的图片不会交给`resolver`。
+/// 相同的地址只会处理一次，支持双引号、单引号及不带引号的src属性。
+/// </pre>
+pub async fn replace_local_images_in_html<F, Fut>(html: &str, mut resolver: F) -> LabradorResult<String>
+    where F: FnMut(String) -> Fut,
+          Fut: Future<Output = LabradorResult<Option<String>>> {
+    let mut resolved: HashMap<String, Option<String>> = HashMap::new();
+    let mut result = String::with_capacity(html.len());
+    let mut last = 0;
+    for (start, end) in find_img_srcs(html) {
+        let src = html[start..end].trim();
+        if src.is_empty() || is_wechat_image_url(src) {
+            continue;
+        }
+        let url = match resolved.get(src) {
+            Some(url) => url.to_owned(),
+            None => {
+                let url = resolver(src.to_string()).await?;
+                resolved.insert(src.to_string(), url.to_owned());
+                url
+            }
+        };
+        if let Some(url) = url {
+            result.push_str(&html[last..start]);
+            result.push_str(&url);
+            last = end;
+        }
+    }
+    result.push_str(&html[last..]);
+    Ok(result)
+}
+
+fn is_wechat_image_url(src: &str) -> bool {
+    let host = src.split("://").nth(1).unwrap_or_default().split(|c| c == '/' || c == '?').next().unwrap_or_default();
+    host.ends_with("mmbiz.qpic.cn") || host.ends_with("mmbiz.qlogo.cn")
+}
+
+/// 查找所有`<img>`标签src属性值的位置
+fn find_img_srcs(html: &str) -> Vec<(usize, usize)> {
+    let bytes = html.as_bytes();
+    let len = bytes.len();
+    let mut srcs = Vec::new();
+    let mut i = 0;
+    while i < len {
+        let is_img = bytes[i] == b'<' && i + 4 <= len && bytes[i + 1..i + 4].eq_ignore_ascii_case(b"img")
+            && (i + 4 == len || bytes[i + 4].is_ascii_whitespace() || bytes[i + 4] == b'/' || bytes[i + 4] == b'>');
+        if !is_img {
+            i += 1;
+            continue;
+        }
+        let mut j = i + 4;
+        let mut found = false;
+        loop {
+            while j < len && (bytes[j].is_ascii_whitespace() || bytes[j] == b'/') {
+                j += 1;
+            }
+            if j >= len || bytes[j] == b'>' {
+                break;
+            }
+            let name_start = j;
+            while j < len && !bytes[j].is_ascii_whitespace() && !matches!(bytes[j], b'=' | b'>' | b'/') {
+                j += 1;
+            }
+            if j == name_start {
+                j += 1;
+                continue;
+            }
+            let is_src = bytes[name_start..j].eq_ignore_ascii_case(b"src");
+            while j < len && bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j >= len || bytes[j] != b'=' {
+                continue;
+            }
+            j += 1;
+            while j < len && bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            let (start, end) = if j < len && (bytes[j] == b'"' || bytes[j] == b'\'') {
+                let quote = bytes[j];
+                let start = j + 1;
+                let end = bytes[start..].iter().position(|b| *b == quote).map(|p| start + p).unwrap_or(len);
+                j = (end + 1).min(len);
+                (start, end)
+            } else {
+                let start = j;
+                while j < len && !bytes[j].is_ascii_whitespace() && bytes[j] != b'>' {
+                    j += 1;
+                }
+                (start, j)
+            };
+            if is_src && !found {
+                srcs.push((start, end));
+                found = true;
+            }
+        }
+        i = j;
+    }
+    srcs
+}
+
 //----------------------------------------------------------------------------------------------------------------------------
 
 
@@ -353,6 +582,113 @@ impl WechatRequest for WechatMpMaterialRequest {
     }
 }
 
+/// 永久图片、语音素材大小上限
+#[cfg(feature = "upload-stream")]
+pub const MATERIAL_MAX_SIZE: u64 = 10 * 1024 * 1024;
+/// 永久视频素材大小上限
+#[cfg(feature = "upload-stream")]
+pub const MATERIAL_VIDEO_MAX_SIZE: u64 = 200 * 1024 * 1024;
+/// 缩略图素材大小上限
+#[cfg(feature = "upload-stream")]
+pub const MATERIAL_THUMB_MAX_SIZE: u64 = 64 * 1024;
+
+/// 按素材类型校验永久素材长度
+#[cfg(feature = "upload-stream")]
+fn check_material_length(media_type: &str, length: u64) -> LabradorResult<()> {
+    let limit = match media_type {
+        "image" | "voice" => MATERIAL_MAX_SIZE,
+        "video" => MATERIAL_VIDEO_MAX_SIZE,
+        "thumb" => MATERIAL_THUMB_MAX_SIZE,
+        _ => return Err(LabraError::RequestError(format!("不支持流式上传的素材类型: {}", media_type))),
+    };
+    if length == 0 {
+        return Err(LabraError::RequestError("素材内容不能为空".to_string()));
+    }
+    if length > limit {
+        return Err(LabraError::RequestError(format!("{}素材大小为{}字节，超过上限{}字节", media_type, length, limit)));
+    }
+    Ok(())
+}
+
+/// <pre>
+/// 流式上传的永久素材
+/// `length`为素材的字节数，用于上传前的大小校验和请求体长度
+/// </pre>
+#[cfg(feature = "upload-stream")]
+#[cfg_attr(docsrs, doc(cfg(feature = "upload-stream")))]
+pub struct WechatMpMaterialStream<R> {
+    pub media_type: String,
+    pub filename: String,
+    pub reader: R,
+    pub length: u64,
+    pub video_title: Option<String>,
+    pub video_introduction: Option<String>,
+    pub progress: Option<crate::upload::UploadProgress>,
+    /// 上传的超时时间，未设置时不限制
+    pub timeout: Option<std::time::Duration>,
+}
+
+#[cfg(feature = "upload-stream")]
+impl<R> WechatMpMaterialStream<R> {
+    pub fn new(media_type: &str, filename: &str, reader: R, length: u64) -> Self {
+        WechatMpMaterialStream {
+            media_type: media_type.to_string(),
+            filename: filename.to_string(),
+            reader,
+            length,
+            video_title: None,
+            video_introduction: None,
+            progress: None,
+            timeout: None,
+        }
+    }
+
+    /// 视频素材的标题和描述
+    pub fn video_description(mut self, title: &str, introduction: &str) -> Self {
+        self.video_title = title.to_string().into();
+        self.video_introduction = introduction.to_string().into();
+        self
+    }
+
+    /// 上传进度回调，参数为已发送字节数与总字节数
+    pub fn progress<F>(mut self, progress: F) -> Self where F: Fn(u64, u64) + Send + Sync + 'static {
+        self.progress = Some(std::sync::Arc::new(progress));
+        self
+    }
+
+    /// 上传的超时时间，大文件上传通常需要比普通接口更长的超时
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+}
+
+/// multipart请求体只能发送一次，由`get_request_body`取走
+#[cfg(feature = "upload-stream")]
+struct WechatMpMaterialStreamRequest {
+    media_type: String,
+    form: std::sync::Mutex<Option<reqwest::multipart::Form>>,
+    timeout: Option<std::time::Duration>,
+}
+
+#[cfg(feature = "upload-stream")]
+impl WechatRequest for WechatMpMaterialStreamRequest {
+    fn get_api_method_name(&self) -> String {
+        MpMediaMethod::AddMaterial(self.media_type.to_string()).get_method()
+    }
+
+    fn get_request_body<T: Serialize>(&self) -> RequestBody<T> {
+        match self.form.lock().ok().and_then(|mut form| form.take()) {
+            Some(form) => form.into(),
+            None => RequestBody::Null,
+        }
+    }
+
+    fn get_timeout(&self) -> Option<std::time::Duration> {
+        self.timeout
+    }
+}
+
 /// 视频素材
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatMpMaterialVideoInfoResponse {
@@ -370,42 +706,6 @@ pub struct WechatMpMaterialNewsResponse {
 }
 
 
-/// 图文消息article.
-/// 1. thumbMediaId  (必填) 图文消息的封面图片素材id（必须是永久mediaID）
-/// 2. author          图文消息的作者
-/// 3. title           (必填) 图文消息的标题
-/// 4. contentSourceUrl 在图文消息页面点击“阅读原文”后的页面链接
-/// 5. content (必填)  图文消息页面的内容，支持HTML标签
-/// 6. digest          图文消息的描述
-/// 7. showCoverPic  是否显示封面，true为显示，false为不显示
-/// 8. url           点击图文消息跳转链接
-/// 9. need_open_comment（新增字段） 否 Uint32 是否打开评论，0不打开，1打开
-/// 10. only_fans_can_comment（新增字段） 否 Uint32 是否粉丝才可评论，0所有人可评论，1粉丝才可评论
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WechatMpNewsArticle {
-    /// (必填) 图文消息缩略图的media_id，可以在基础支持-上传多媒体文件接口中获得.
-    pub thumb_media_id: String,
-    /// 图文消息的封面url
-    pub thumb_url: Option<String>,
-    /// 图文消息的作者
-    pub author: Option<String>,
-    /// (必填) 图文消息的标题.
-    pub title: String,
-    /// 在图文消息页面点击“阅读原文”后的页面链接.
-    pub content_source_url: Option<String>,
-    /// (必填) 图文消息页面的内容，支持HTML标签.
-    pub content: String,
-    /// 图文消息的描述
-    pub digest: Option<String>,
-    /// 是否显示封面，true为显示，false为不显示.
-    pub show_cover_pic: bool,
-    /// 点击图文消息跳转链接
-    pub url: Option<String>,
-    /// 是否打开评论，0不打开，1打开.
-    pub need_open_comment: Option<u8>,
-    /// 是否粉丝才可评论，0所有人可评论，1粉丝才可评论.
-    pub only_fans_can_comment: Option<u8>,
-}
 
 
 
@@ -413,9 +713,13 @@ pub struct WechatMpNewsArticle {
 /// 素材数量
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatMpMaterialCountResponse {
+    #[serde(default, with = "option_string_or_number")]
     pub voice_count: Option<i32>,
+    #[serde(default, with = "option_string_or_number")]
     pub video_count: Option<i32>,
+    #[serde(default, with = "option_string_or_number")]
     pub image_count: Option<i32>,
+    #[serde(default, with = "option_string_or_number")]
     pub news_count: Option<i32>,
 }
 
@@ -423,7 +727,9 @@ pub struct WechatMpMaterialCountResponse {
 /// 图文素材
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatMpMaterialNewsBatchResponse {
+    #[serde(default, with = "option_string_or_number")]
     pub total_count: Option<i32>,
+    #[serde(default, with = "option_string_or_number")]
     pub item_count: Option<i32>,
     pub items: Option<Vec<WechatMpMaterialNewsBatchItem>>,
 }
@@ -438,8 +744,11 @@ pub struct WechatMpMaterialNewsBatchItem {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatMpMaterialBatchResponse {
+    #[serde(default, with = "option_string_or_number")]
     pub total_count: Option<i32>,
+    #[serde(default, with = "option_string_or_number")]
     pub item_count: Option<i32>,
+    #[serde(alias = "item")]
     pub items: Option<Vec<WechatMpMaterialBatchItem>>,
 }
 
@@ -453,3 +762,299 @@ pub struct WechatMpMaterialBatchItem {
 }
 
 
+
+/// 语音的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatMpVoiceFormat {
+    /// AMR-NB，语音消息的默认格式
+    Amr,
+    /// AMR-WB
+    AmrWb,
+    /// Ogg封装的speex，高清语音素材的格式
+    Speex,
+    Mp3,
+    Wav,
+    Unknown,
+}
+
+impl WechatMpVoiceFormat {
+
+    /// 按文件头识别格式
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(b"#!AMR-WB\n") {
+            WechatMpVoiceFormat::AmrWb
+        } else if data.starts_with(b"#!AMR\n") {
+            WechatMpVoiceFormat::Amr
+        } else if data.starts_with(b"OggS") && data.get(28..36) == Some(b"Speex   ") {
+            WechatMpVoiceFormat::Speex
+        } else if data.starts_with(b"ID3") || (data.len() > 1 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+            WechatMpVoiceFormat::Mp3
+        } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE") {
+            WechatMpVoiceFormat::Wav
+        } else {
+            WechatMpVoiceFormat::Unknown
+        }
+    }
+
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            WechatMpVoiceFormat::Amr | WechatMpVoiceFormat::AmrWb => "amr",
+            WechatMpVoiceFormat::Speex => "speex",
+            WechatMpVoiceFormat::Mp3 => "mp3",
+            WechatMpVoiceFormat::Wav => "wav",
+            WechatMpVoiceFormat::Unknown => "bin",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            WechatMpVoiceFormat::Amr => "audio/amr",
+            WechatMpVoiceFormat::AmrWb => "audio/amr-wb",
+            WechatMpVoiceFormat::Speex => "audio/ogg",
+            WechatMpVoiceFormat::Mp3 => "audio/mpeg",
+            WechatMpVoiceFormat::Wav => "audio/wav",
+            WechatMpVoiceFormat::Unknown => "application/octet-stream",
+        }
+    }
+}
+
+/// 下载的语音
+#[derive(Debug, Clone)]
+pub struct WechatMpVoice {
+    pub data: Bytes,
+    pub format: WechatMpVoiceFormat,
+}
+
+impl WechatMpVoice {
+
+    /// 转换为transcoder的目标格式，已是目标格式时原样返回
+    pub fn transcode(self, transcoder: &dyn AudioTranscoder) -> LabradorResult<WechatMpVoice> {
+        let target = transcoder.target_format();
+        if self.format == target {
+            return Ok(self);
+        }
+        let data = transcoder.transcode(&self.data, self.format)?;
+        Ok(WechatMpVoice { data: Bytes::from(data), format: target })
+    }
+}
+
+/// <pre>
+/// 语音转码
+/// 由使用方实现（如调用ffmpeg），将AMR、speex等格式的语音转换为`target_format`
+/// </pre>
+pub trait AudioTranscoder: Send + Sync {
+    /// 转码后的格式
+    fn target_format(&self) -> WechatMpVoiceFormat;
+
+    /// 将from格式的语音转换为目标格式
+    fn transcode(&self, data: &[u8], from: WechatMpVoiceFormat) -> LabradorResult<Vec<u8>>;
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::{LabraError, LabradorResult};
+    use super::{check_article_image, replace_local_images_in_html, ARTICLE_IMAGE_MAX_SIZE};
+
+    #[test]
+    fn test_check_article_image() {
+        assert!(check_article_image(&[0u8; 1024], "cover.jpg").is_ok());
+        assert!(check_article_image(&[0u8; 1024], "cover.JPEG").is_ok());
+        assert!(check_article_image(&[0u8; 1024], "a/b/cover.png").is_ok());
+        match check_article_image(&[0u8; 1024], "cover.gif") {
+            Err(LabraError::RequestError(msg)) => assert!(msg.contains("cover.gif")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(check_article_image(&[0u8; 1024], "cover").is_err());
+        assert!(check_article_image(&[], "cover.png").is_err());
+        let data = vec![0u8; ARTICLE_IMAGE_MAX_SIZE + 10];
+        match check_article_image(&data, "big.png") {
+            Err(LabraError::RequestError(msg)) => assert!(msg.contains("1048586字节"), "{}", msg),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(check_article_image(&vec![0u8; ARTICLE_IMAGE_MAX_SIZE - 1], "ok.png").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replace_local_images_in_html() {
+        let html = r#"<p>图片</p><img src="/tmp/a.png" alt="a"><IMG class='x' SRC='b.jpg'/><img src=c.png>
+<img data-src="x.png" src = "/tmp/a.png"><img src="http://mmbiz.qpic.cn/mmbiz_png/abc/0?wx_fmt=png"><img alt="无src"><imgx src="d.png">"#;
+        let mut called = Vec::new();
+        let result = replace_local_images_in_html(html, |src| {
+            called.push(src.to_owned());
+            async move {
+                if src == "b.jpg" {
+                    return Ok(None);
+                }
+                Ok(Some(format!("http://mmbiz.qpic.cn/{}", src.trim_start_matches("/tmp/"))))
+            }
+        }).await.unwrap();
+        assert_eq!(result, r#"<p>图片</p><img src="http://mmbiz.qpic.cn/a.png" alt="a"><IMG class='x' SRC='b.jpg'/><img src=http://mmbiz.qpic.cn/c.png>
+<img data-src="x.png" src = "http://mmbiz.qpic.cn/a.png"><img src="http://mmbiz.qpic.cn/mmbiz_png/abc/0?wx_fmt=png"><img alt="无src"><imgx src="d.png">"#);
+        // 相同地址只处理一次，微信域名的图片不处理
+        assert_eq!(called, vec!["/tmp/a.png", "b.jpg", "c.png"]);
+    }
+
+    #[tokio::test]
+    async fn test_replace_local_images_error() {
+        let html = r#"<img src='a.png'>"#;
+        let result = replace_local_images_in_html(html, |_| async { Err::<Option<String>, _>(LabraError::RequestError("upload failed".to_string())) }).await;
+        assert!(result.is_err());
+        let unchanged = replace_local_images_in_html("<p>no image</p>", |_| async { Ok(None) }).await.unwrap();
+        assert_eq!(unchanged, "<p>no image</p>");
+    }
+
+    #[cfg(feature = "upload-stream")]
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[cfg(feature = "upload-stream")]
+    #[tokio::test]
+    async fn test_upload_material_stream() {
+        use std::sync::{Arc, Mutex};
+        use crate::{SimpleStorage, WechatMpClient};
+        use crate::util::mock::{MockResponse, MockServer};
+        use super::WechatMpMaterialStream;
+
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"media_id":"MEDIA_ID","url":""}"#)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_material_stream", "secret").base_url(&server.url);
+        let length = 256 * 1024;
+        let data = (0..length).map(|i| b'a' + (i % 26) as u8).collect::<Vec<u8>>();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let received = calls.clone();
+        let req = WechatMpMaterialStream::new("video", "movie.mp4", std::io::Cursor::new(data.clone()), length as u64)
+            .video_description("title", "introduction")
+            .progress(move |sent, total| received.lock().unwrap().push((sent, total)));
+        let res = client.media().upload_material_stream(req).await.unwrap();
+        assert_eq!(res.media_id.as_deref(), Some("MEDIA_ID"));
+        let calls = calls.lock().unwrap();
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(calls.iter().all(|(_, total)| *total == length as u64));
+        assert_eq!(calls.last().map(|v| v.0), Some(length as u64));
+        let request = &server.requests()[1];
+        assert!(request.starts_with("POST /cgi-bin/material/add_material?"));
+        assert!(request.contains("type=video"));
+        assert!(request.contains("filename=\"movie.mp4\""));
+        assert!(request.contains(std::str::from_utf8(&data).unwrap()));
+        assert!(request.contains("name=\"introduction\"\r\n\r\nintroduction"));
+    }
+
+    #[cfg(feature = "upload-stream")]
+    #[tokio::test]
+    async fn test_upload_material_stream_timeout() {
+        use std::time::Duration;
+        use crate::{SimpleStorage, WechatMpClient};
+        use crate::util::mock::{MockResponse, MockServer};
+        use super::WechatMpMaterialStream;
+
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"media_id":"MEDIA_ID","url":""}"#).delay(Duration::from_millis(500))]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_material_stream_timeout", "secret").base_url(&server.url);
+        let req = WechatMpMaterialStream::new("image", "a.png", std::io::Cursor::new(vec![1u8; 1024]), 1024).timeout(Duration::from_millis(50));
+        assert!(matches!(client.media().upload_material_stream(req).await, Err(LabraError::Timeout(_))));
+    }
+
+    #[cfg(feature = "upload-stream")]
+    #[tokio::test]
+    async fn test_upload_material_stream_too_large() {
+        use crate::{SimpleStorage, WechatMpClient};
+        use crate::util::mock::closed_url;
+        use super::{WechatMpMaterialStream, MATERIAL_MAX_SIZE, MATERIAL_VIDEO_MAX_SIZE};
+
+        // 请求地址不可用，校验失败时不会发起请求
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_material_stream_large", "secret").base_url(&closed_url().await);
+        for (media_type, length) in [("video", MATERIAL_VIDEO_MAX_SIZE + 1), ("image", MATERIAL_MAX_SIZE + 1), ("voice", MATERIAL_MAX_SIZE + 1), ("thumb", 64 * 1024 + 1), ("video", 0), ("news", 10)] {
+            let req = WechatMpMaterialStream::new(media_type, "a.bin", tokio::io::empty(), length)
+                .progress(|_, _| panic!("should not read"));
+            match client.media().upload_material_stream(req).await {
+                Err(LabraError::RequestError(_)) => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+
+    /// AMR-NB文件头加一帧12.2kbps的静音帧（帧头0x3C，共32字节）
+    fn amr_fixture() -> Vec<u8> {
+        let mut data = b"#!AMR\n".to_vec();
+        data.push(0x3C);
+        data.extend([0u8; 31]);
+        data
+    }
+
+    /// Ogg页头（27字节）、段表（1字节）后为speex头
+    fn speex_fixture() -> Vec<u8> {
+        let mut data = b"OggS".to_vec();
+        data.extend([0u8; 24]);
+        data.extend(b"Speex   1.2rc1");
+        data
+    }
+
+    /// 把任意语音“转码”为带WAV头的数据，只用于验证调用流程
+    struct FakeWavTranscoder;
+
+    impl super::AudioTranscoder for FakeWavTranscoder {
+        fn target_format(&self) -> super::WechatMpVoiceFormat {
+            super::WechatMpVoiceFormat::Wav
+        }
+
+        fn transcode(&self, data: &[u8], from: super::WechatMpVoiceFormat) -> LabradorResult<Vec<u8>> {
+            if from != super::WechatMpVoiceFormat::Amr {
+                return Err(LabraError::Unsupported(format!("{:?}", from)));
+            }
+            let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+            wav.extend((data.len() as u32).to_le_bytes());
+            Ok(wav)
+        }
+    }
+
+    #[test]
+    fn test_detect_voice_format() {
+        use super::WechatMpVoiceFormat;
+        assert_eq!(WechatMpVoiceFormat::detect(&amr_fixture()), WechatMpVoiceFormat::Amr);
+        assert_eq!(WechatMpVoiceFormat::detect(b"#!AMR-WB\n\x04"), WechatMpVoiceFormat::AmrWb);
+        assert_eq!(WechatMpVoiceFormat::detect(&speex_fixture()), WechatMpVoiceFormat::Speex);
+        assert_eq!(WechatMpVoiceFormat::detect(b"OggS\0\0\0\0"), WechatMpVoiceFormat::Unknown);
+        assert_eq!(WechatMpVoiceFormat::detect(b"ID3\x03\0\0\0\0\0\0"), WechatMpVoiceFormat::Mp3);
+        assert_eq!(WechatMpVoiceFormat::detect(&[0xFF, 0xFB, 0x90, 0x64]), WechatMpVoiceFormat::Mp3);
+        assert_eq!(WechatMpVoiceFormat::detect(b"RIFF\x24\0\0\0WAVEfmt "), WechatMpVoiceFormat::Wav);
+        assert_eq!(WechatMpVoiceFormat::detect(b"#!AM"), WechatMpVoiceFormat::Unknown);
+        assert_eq!(WechatMpVoiceFormat::detect(b""), WechatMpVoiceFormat::Unknown);
+        assert_eq!(WechatMpVoiceFormat::Amr.extension(), "amr");
+        assert_eq!(WechatMpVoiceFormat::Amr.mime_type(), "audio/amr");
+    }
+
+    #[tokio::test]
+    async fn test_download_voice() {
+        use crate::{SimpleStorage, WechatMpClient};
+        use crate::util::mock::{MockResponse, MockServer};
+        use super::WechatMpVoiceFormat;
+
+        let amr = amr_fixture();
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#),
+            MockResponse::bytes("audio/amr", &amr),
+            MockResponse::bytes("audio/amr", &amr),
+            MockResponse::bytes("audio/ogg", &speex_fixture()),
+            MockResponse::json(r#"{"errcode":40007,"errmsg":"invalid media_id"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_download_voice", "secret").base_url(&server.url);
+        let voice = client.media().download_voice("MEDIA_ID").await.unwrap();
+        assert_eq!(voice.format, WechatMpVoiceFormat::Amr);
+        assert_eq!(voice.data.as_ref(), amr.as_slice());
+
+        let wav = client.media().download_voice_as("MEDIA_ID", &FakeWavTranscoder).await.unwrap();
+        assert_eq!(wav.format, WechatMpVoiceFormat::Wav);
+        assert_eq!(WechatMpVoiceFormat::detect(&wav.data), WechatMpVoiceFormat::Wav);
+        assert_eq!(&wav.data[12..], &(amr.len() as u32).to_le_bytes());
+        // 转码失败时返回transcoder的错误
+        assert!(matches!(client.media().download_voice_as("MEDIA_ID", &FakeWavTranscoder).await, Err(LabraError::Unsupported(_))));
+        assert!(matches!(client.media().download_voice("BAD").await, Err(LabraError::ClientError { errcode, .. }) if errcode == "40007"));
+
+        // 已是目标格式时不转码
+        let voice = super::WechatMpVoice { data: wav.data.clone(), format: WechatMpVoiceFormat::Wav };
+        assert_eq!(voice.transcode(&FakeWavTranscoder).unwrap().data, wav.data);
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/media/get?media_id=MEDIA_ID&access_token=ACCESS_TOKEN"));
+    }
+}
@@ -3,26 +3,32 @@ use std::vec;
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, get_timestamp};
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, current_timestamp};
 use crate::wechat::mp::constants::MEMBER_CARD;
 use crate::wechat::mp::method::{MpMemeberCardMethod, WechatMpMethod};
 
 /// 会员卡相关.
 #[derive(Debug, Clone)]
-pub struct WechatMpMember<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpMember<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpMember<'a, T> {
+impl<T: SessionStore> WechatMpMember<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpMember<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpMember<T> {
         WechatMpMember {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，持有克隆的客户端")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpMember<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 会员卡创建接口
     /// </pre>
@@ -191,8 +197,8 @@ impl WechatMpMemberCardCreateRequest {
         if date_info_type == DateInfoType::DATE_TYPE_FIX_TIME_RANGE && (date_info.begin_timestamp.is_none() || date_info.end_timestamp.is_none()) {
             return Err(LabraError::RequestError(format!("会员卡基本信息的使用日期为:固定期限 fixedTerm和fixedBeginTerm不能为空")));
         }
-        let current_tmp = get_timestamp();
-        if date_info_type == DateInfoType::DATE_TYPE_FIX_TIME_RANGE && (date_info.begin_timestamp.unwrap_or_default() * 1000 < current_tmp || date_info.end_timestamp.unwrap_or_default() * 1000 < current_tmp || date_info.begin_timestamp.unwrap_or_default() > date_info.end_timestamp.unwrap_or_default()) {
+        let current_tmp = current_timestamp();
+        if date_info_type == DateInfoType::DATE_TYPE_FIX_TIME_RANGE && (date_info.begin_timestamp.unwrap_or_default() < current_tmp || date_info.end_timestamp.unwrap_or_default() < current_tmp || date_info.begin_timestamp.unwrap_or_default() > date_info.end_timestamp.unwrap_or_default()) {
             return Err(LabraError::RequestError(format!("会员卡基本信息的使用日期为:固定期限，beginTimestamp和endTimestamp的值不合法，请检查")));
         }
 
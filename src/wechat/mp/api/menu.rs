@@ -26,20 +26,26 @@ use crate::wechat::mp::method::{MpMenuMethod, WechatMpMethod};
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMpMenu<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpMenu<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpMenu<'a, T> {
+impl<T: SessionStore> WechatMpMenu<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpMenu<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpMenu<T> {
         WechatMpMenu {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.menu()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpMenu<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 自定义菜单创建接口
     /// 详情请见：https://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1421141013&token=&lang=zh_CN
@@ -0,0 +1,170 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{session::SessionStore, request::RequestType, errors::LabraError, WechatCommonResponse, WechatMpClient, LabradorResult, TemplateMessage};
+use crate::wechat::mp::method::{MpTemplateMessageMethod, WechatMpMethod};
+
+/// 回复时间超过限制（超出48小时客服消息窗口）
+const ERRCODE_RESPONSE_OUT_OF_TIME: &str = "45015";
+/// 客服接口下行条数超过上限
+const ERRCODE_CUSTOM_SERVICE_LIMIT: &str = "45047";
+
+/// 消息发送（客服消息与模板消息组合使用）.
+#[derive(Debug, Clone)]
+pub struct WechatMpMessaging<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpMessaging<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpMessaging<T> {
+        WechatMpMessaging {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.messaging()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpMessaging<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 发送客服消息，超出客服消息可下发范围时改为发送模板消息
+    /// 客服消息只能在用户与公众号交互后的48小时内下发，超时返回45015，下发条数超限返回45047，
+    /// 出现这两种错误时使用fallback_template发送模板消息，其余错误直接返回。
+    /// kf_message与fallback_template未指定touser时，使用openid补全。
+    /// </pre>
+    pub async fn send_with_fallback<D: Serialize>(&self, openid: &str, kf_message: D, mut fallback_template: TemplateMessage) -> LabradorResult<WechatMpSendResult> {
+        let mut kf_message = serde_json::to_value(kf_message)?;
+        if kf_message.get("touser").is_none() {
+            kf_message["touser"] = openid.into();
+        }
+        let kf_result = self.client.custom_service().send_kefu_message(kf_message).await?;
+        let (errcode, errmsg) = match check_custom_service_response(kf_result) {
+            Ok(_) => return Ok(WechatMpSendResult::CustomService),
+            Err(LabraError::CustomServiceOutOfLimit { errcode, errmsg }) => (errcode, errmsg),
+            Err(err) => return Err(err),
+        };
+        if fallback_template.touser.is_none() {
+            fallback_template.touser = openid.to_string().into();
+        }
+        let v = self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::SendTemplate), vec![], fallback_template, RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        Ok(WechatMpSendResult::Template {
+            msgid: v["msgid"].as_i64(),
+            errcode,
+            errmsg,
+        })
+    }
+}
+
+/// 校验客服消息的发送结果，45015、45047转换为`LabraError::CustomServiceOutOfLimit`
+fn check_custom_service_response(resp: WechatCommonResponse) -> LabradorResult<WechatCommonResponse> {
+    if resp.is_success() {
+        return Ok(resp);
+    }
+    let errcode = resp.errcode.unwrap_or_default().to_string();
+    let errmsg = resp.errmsg.unwrap_or_default();
+    match errcode.as_str() {
+        ERRCODE_RESPONSE_OUT_OF_TIME | ERRCODE_CUSTOM_SERVICE_LIMIT => Err(LabraError::CustomServiceOutOfLimit { errcode, errmsg }),
+        _ => Err(LabraError::ClientError { errcode, errmsg }),
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 消息最终的发送方式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WechatMpSendResult {
+    /// 客服消息发送成功
+    CustomService,
+    /// 客服消息不可下发，已改为发送模板消息
+    Template {
+        /// 模板消息id
+        msgid: Option<i64>,
+        /// 客服消息返回的错误码（45015或45047）
+        errcode: String,
+        /// 客服消息返回的错误信息
+        errmsg: String,
+    },
+}
+
+impl WechatMpSendResult {
+    /// 是否回退为模板消息
+    pub fn is_fallback(&self) -> bool {
+        matches!(self, WechatMpSendResult::Template { .. })
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{LabraError, SimpleStorage, TemplateMessage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::WechatMpSendResult;
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn template() -> TemplateMessage {
+        TemplateMessage {
+            touser: None,
+            template_id: "TEMPLATE_ID".to_string(),
+            url: None,
+            miniprogram: None,
+            data: json!({"first": {"value": "您好"}}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_with_fallback_to_template() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":45015,"errmsg":"response out of time limit or subscription is canceled"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","msgid":200228332}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_send_fallback", "secret").base_url(&server.url);
+        let kf = json!({"msgtype": "text", "text": {"content": "Hello"}});
+        let result = client.messaging().send_with_fallback("OPENID", kf, template()).await.unwrap();
+        assert!(result.is_fallback());
+        assert_eq!(result, WechatMpSendResult::Template {
+            msgid: Some(200228332),
+            errcode: "45015".to_string(),
+            errmsg: "response out of time limit or subscription is canceled".to_string(),
+        });
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/message/custom/send?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[1].ends_with(r#"{"msgtype":"text","text":{"content":"Hello"},"touser":"OPENID"}"#));
+        assert!(requests[2].starts_with("POST /cgi-bin/message/template/send?access_token=ACCESS_TOKEN HTTP/1.1"));
+        assert!(requests[2].contains(r#""touser":"OPENID""#));
+        assert!(requests[2].contains(r#""template_id":"TEMPLATE_ID""#));
+    }
+
+    #[tokio::test]
+    async fn test_send_with_fallback_paths() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+            MockResponse::json(r#"{"errcode":45047,"errmsg":"out of response count limit"}"#),
+            MockResponse::json(r#"{"errcode":43004,"errmsg":"require subscribe"}"#),
+            MockResponse::json(r#"{"errcode":40003,"errmsg":"invalid openid"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_send_fallback_paths", "secret").base_url(&server.url);
+        let kf = json!({"touser": "KF_OPENID", "msgtype": "text", "text": {"content": "Hello"}});
+        // 客服消息发送成功时不发送模板消息
+        let result = client.messaging().send_with_fallback("OPENID", kf.clone(), template()).await.unwrap();
+        assert_eq!(result, WechatMpSendResult::CustomService);
+        assert!(server.requests()[1].contains(r#""touser":"KF_OPENID""#));
+        // 45047回退后模板消息失败，返回模板消息的错误
+        let err = client.messaging().send_with_fallback("OPENID", kf.clone(), template()).await.unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "43004"));
+        // 其余错误不回退
+        let err = client.messaging().send_with_fallback("OPENID", kf, template()).await.unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "40003"));
+        assert_eq!(server.requests().len(), 5);
+    }
+}
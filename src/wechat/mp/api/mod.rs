@@ -10,6 +10,15 @@ mod wifi;
 mod ocr;
 mod member;
 mod card;
+mod datacube;
+mod messaging;
+mod ai;
+mod mass_msg;
+mod poi;
+mod market_code;
+mod short_key;
+mod account;
+mod guide;
 
 pub use self::oauth2::*;
 pub use self::qrcode::*;
@@ -23,5 +32,14 @@ pub use self::wifi::*;
 pub use self::ocr::*;
 pub use self::member::*;
 pub use self::card::*;
+pub use self::datacube::*;
+pub use self::messaging::*;
+pub use self::ai::*;
+pub use self::mass_msg::*;
+pub use self::poi::*;
+pub use self::market_code::*;
+pub use self::short_key::*;
+pub use self::account::*;
+pub use self::guide::*;
 
 
@@ -3,23 +3,30 @@ use serde::{Serialize, Deserialize};
 use crate::{session::SessionStore, request::{RequestType}, wechat::{mp::method::WechatMpMethod}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError};
 use crate::wechat::mp::constants::{ACCESS_TOKEN, APPID, CODE, GRANT_TYPE, LANG, OPENID, REFRESH_TOKEN, SECRET, ZH_CN};
 use crate::wechat::mp::method::Oauth2Method;
+use crate::serde_helper::string_or_number;
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMpOauth2<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpOauth2<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpOauth2<'a, T> {
+impl<T: SessionStore> WechatMpOauth2<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpOauth2<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpOauth2<T> {
         WechatMpOauth2 {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.oauth2()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpOauth2<T> {
+        Self::from_client(client.clone())
+    }
+
 
     /// # 通过 code 换取网页授权access_token
     ///
@@ -30,8 +37,8 @@ impl<'a, T: SessionStore> WechatMpOauth2<'a, T> {
         let v = self.client.get(WechatMpMethod::Oauth2(Oauth2Method::AccessToken), vec![
             (GRANT_TYPE.to_string(), "authorization_code".to_string()),
             (CODE.to_string(), code.to_string()),
-            (APPID.to_string(), self.client.appid.to_string()),
-            (SECRET.to_string(), self.client.secret.to_string()),
+            (APPID.to_string(), self.client.inner.appid.to_string()),
+            (SECRET.to_string(), self.client.secret()),
         ], RequestType::Json).await?.json::<serde_json::Value>()?;
         let mut result = WechatCommonResponse::from_value(v.clone())?;
         if result.is_success() {
@@ -49,7 +56,7 @@ impl<'a, T: SessionStore> WechatMpOauth2<'a, T> {
         let v = self.client.get(WechatMpMethod::Oauth2(Oauth2Method::RefreshToken), vec![
             (GRANT_TYPE.to_string(), REFRESH_TOKEN.to_string()),
             (REFRESH_TOKEN.to_string(), refresh_token.to_string()),
-            (APPID.to_string(), self.client.appid.to_string()),
+            (APPID.to_string(), self.client.inner.appid.to_string()),
         ], RequestType::Json).await?.json::<serde_json::Value>()?;
         let mut result = WechatCommonResponse::from_value(v.to_owned())?;
         if result.is_success() {
@@ -86,6 +93,7 @@ pub struct WechatMpOauth2AccessTokenResponse{
     pub refresh_token: Option<String>,
     pub openid: String,
     pub scope: String,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
@@ -94,6 +102,7 @@ pub struct WechatMpOauth2AccessTokenResponse{
 pub struct WechatMpOauth2UserInfo {
     pub openid: String,
     pub nickname: String,
+    #[serde(with = "string_or_number")]
     pub sex: u8,
     pub city: String,
     pub province: String,
@@ -12,20 +12,26 @@ use crate::wechat::mp::method::{MpOcrMethod, WechatMpMethod};
 
 /// 微信连接WI-FI接口.
 #[derive(Debug, Clone)]
-pub struct WechatMpOcr<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpOcr<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpOcr<'a, T> {
+impl<T: SessionStore> WechatMpOcr<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpOcr<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpOcr<T> {
         WechatMpOcr {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.ocr()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpOcr<T> {
+        Self::from_client(client.clone())
+    }
+
     /// 身份证OCR识别接口
     pub async fn id_card(&self, img_url: &str) -> LabradorResult<WechatOcrIdCardResponse> {
         let img_url = urlencoding::encode(img_url).to_string();
@@ -0,0 +1,378 @@
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, CategoryTree};
+use crate::wechat::mp::method::{MpPoiMethod, WechatMpMethod};
+use crate::serde_helper::{string_or_number, option_string_or_number};
+
+/// 门店列表每页最多返回的数量
+const POI_LIST_MAX_LIMIT: u32 = 50;
+
+/// 门店管理.
+#[derive(Debug, Clone)]
+pub struct WechatMpPoi<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpPoi<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpPoi<T> {
+        WechatMpPoi {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.poi()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpPoi<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 创建门店，创建后需要审核，审核结果通过事件推送
+    /// 返回的poi_id在部分情况下为空，需通过审核结果事件或门店列表获取
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/WeChat_Stores/WeChat_Store_Interface.html
+    /// </pre>
+    pub async fn add(&self, info: WechatMpPoiBaseInfo) -> LabradorResult<Option<String>> {
+        let data = WechatMpPoiBusiness { base_info: info };
+        let v = self.client.post(WechatMpMethod::Poi(MpPoiMethod::AddPoi), vec![], json!({ "business": data }), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        // poi_id可能为字符串或数字
+        Ok(match &v["poi_id"] {
+            Value::String(poi_id) => Some(poi_id.to_string()),
+            Value::Number(poi_id) => Some(poi_id.to_string()),
+            _ => None,
+        })
+    }
+
+    /// <pre>
+    /// 查询门店信息，返回结果中的门店信息嵌套在business.base_info下
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/WeChat_Stores/WeChat_Store_Interface.html
+    /// </pre>
+    pub async fn get(&self, poi_id: &str) -> LabradorResult<WechatMpPoiBaseInfo> {
+        let v = self.client.post(WechatMpMethod::Poi(MpPoiMethod::GetPoi), vec![], json!({ "poi_id": poi_id }), RequestType::Json).await?.json::<Value>()?;
+        let business = WechatCommonResponse::parse_with_key::<WechatMpPoiBusiness<WechatMpPoiBaseInfo>>(v, "business")?;
+        Ok(business.base_info)
+    }
+
+    /// <pre>
+    /// 查询门店列表，begin为开始位置（0即为从第一条开始查询），limit最大为50
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/WeChat_Stores/WeChat_Store_Interface.html
+    /// </pre>
+    pub async fn list(&self, begin: u32, limit: u32) -> LabradorResult<WechatMpPoiListResponse> {
+        if limit == 0 || limit > POI_LIST_MAX_LIMIT {
+            return Err(LabraError::RequestError(format!("limit必须为1~{}", POI_LIST_MAX_LIMIT)));
+        }
+        let v = self.client.post(WechatMpMethod::Poi(MpPoiMethod::GetPoiList), vec![], json!({ "begin": begin, "limit": limit }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatMpPoiListResponse>(v)
+    }
+
+    /// <pre>
+    /// 修改门店服务信息，只能修改WechatMpPoiUpdateInfo中的字段，未设置的字段保持不变
+    /// 门店名称、地址等基础信息不能通过该接口修改
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/WeChat_Stores/WeChat_Store_Interface.html
+    /// </pre>
+    pub async fn update(&self, info: WechatMpPoiUpdateInfo) -> LabradorResult<WechatCommonResponse> {
+        let data = WechatMpPoiBusiness { base_info: info };
+        let v = self.client.post(WechatMpMethod::Poi(MpPoiMethod::UpdatePoi), vec![], json!({ "business": data }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 删除门店
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/WeChat_Stores/WeChat_Store_Interface.html
+    /// </pre>
+    pub async fn delete(&self, poi_id: &str) -> LabradorResult<WechatCommonResponse> {
+        let v = self.client.post(WechatMpMethod::Poi(MpPoiMethod::DelPoi), vec![], json!({ "poi_id": poi_id }), RequestType::Json).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<WechatCommonResponse>(v)
+    }
+
+    /// <pre>
+    /// 获取门店类目表，返回的类目为逗号分隔的路径（如`美食,江浙菜,上海菜`），创建门店时categories填写末级类目的路径
+    /// use_cache为true时优先使用会话存储中缓存的类目树，缓存24小时
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/WeChat_Stores/WeChat_Store_Interface.html
+    /// </pre>
+    pub async fn get_category_tree(&self, use_cache: bool) -> LabradorResult<CategoryTree> {
+        let session = self.client.inner.client.session();
+        let key = format!("{}_poi_category_tree", self.client.inner.appid);
+        if use_cache {
+            if let Some(tree) = CategoryTree::load(session, &key)? {
+                return Ok(tree);
+            }
+        }
+        let v = self.client.get(WechatMpMethod::Poi(MpPoiMethod::GetWxCategory), vec![], RequestType::Json).await?.json::<Value>()?;
+        let category_list = WechatCommonResponse::parse_with_key::<Vec<String>>(v, "category_list")?;
+        let tree = CategoryTree::from_paths(category_list);
+        if use_cache {
+            tree.store(session, &key)?;
+        }
+        Ok(tree)
+    }
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+/// 门店接口的请求与返回均嵌套在business.base_info下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WechatMpPoiBusiness<B> {
+    base_info: B,
+}
+
+/// 门店照片
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WechatMpPoiPhoto {
+    /// 照片url，须通过上传图文消息内的图片接口获取
+    pub photo_url: String,
+}
+
+/// 门店信息
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WechatMpPoiBaseInfo {
+    /// 门店ID，创建时不填
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "option_string_or_number")]
+    pub poi_id: Option<String>,
+    /// 商户自己的id，用于后续审核通过收到poi_id的通知时，做对应关系
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// 门店名称（仅为商户名，如：国美、麦当劳，不应包含地区、地址、分店名等信息）
+    pub business_name: String,
+    /// 分店名称（不应包含地区信息，不应与门店名有重复）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_name: Option<String>,
+    /// 门店所在的省份（直辖市填城市名，如：北京市）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub province: Option<String>,
+    /// 门店所在的城市
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// 门店所在地区
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub district: Option<String>,
+    /// 门店所在的详细街道地址（不要填写省市信息）
+    pub address: String,
+    /// 门店的电话（纯数字，区号、分机号均由“-”隔开）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telephone: Option<String>,
+    /// 门店的类型（不同级分类用“,”隔开，如：美食,小吃快餐）
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// 坐标类型：1为火星坐标，2为sogou经纬度，3为百度经纬度，4为mapbar经纬度，5为GPS坐标，6为sogou墨卡托坐标
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_type: Option<u8>,
+    /// 门店所在地理位置的经度
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    /// 门店所在地理位置的纬度
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    /// 图片列表
+    #[serde(default)]
+    pub photo_list: Vec<WechatMpPoiPhoto>,
+    /// 推荐品，餐厅可为推荐菜；酒店为推荐套房；景点为推荐游玩景点等
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommend: Option<String>,
+    /// 特色服务，如免费wifi，免费停车，送货上门等商户能提供的特色功能或服务
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub special: Option<String>,
+    /// 商户简介，主要介绍商户信息等
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub introduction: Option<String>,
+    /// 营业时间，24小时制表示，用“-”连接，如8:00-20:00
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_time: Option<String>,
+    /// 人均价格，大于0的整数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_price: Option<i32>,
+    /// 门店是否可用状态（仅查询时返回）。1表示系统错误、2表示审核中、3审核通过、4审核驳回
+    #[serde(skip_serializing)]
+    pub available_state: Option<i32>,
+    /// 扩展字段是否正在更新中（仅查询时返回）。1表示扩展字段正在更新中，尚未生效，不允许再次更新；0表示扩展字段没有在更新中或更新已生效，可以再次更新
+    #[serde(skip_serializing)]
+    pub update_status: Option<i32>,
+}
+
+impl WechatMpPoiBaseInfo {
+    /// 是否审核通过
+    pub fn is_available(&self) -> bool {
+        self.available_state == Some(3)
+    }
+}
+
+/// <pre>
+/// 可修改的门店服务信息
+/// 修改门店时只允许修改以下字段，门店名称、地址、分类、经纬度等基础信息不能修改：
+/// </pre>
+///
+/// ```compile_fail
+/// let info = labrador::WechatMpPoiUpdateInfo {
+///     poi_id: "271262077".to_string(),
+///     business_name: "麦当劳".to_string(),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WechatMpPoiUpdateInfo {
+    /// 门店ID
+    #[serde(with = "string_or_number")]
+    pub poi_id: String,
+    /// 商户自己的id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// 门店的电话
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telephone: Option<String>,
+    /// 图片列表，修改时为全量替换
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photo_list: Option<Vec<WechatMpPoiPhoto>>,
+    /// 推荐品
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommend: Option<String>,
+    /// 特色服务
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub special: Option<String>,
+    /// 商户简介
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub introduction: Option<String>,
+    /// 营业时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_time: Option<String>,
+    /// 人均价格
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_price: Option<i32>,
+}
+
+/// 门店列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatMpPoiListResponse {
+    /// 门店列表
+    #[serde(default, with = "poi_business_list")]
+    pub business_list: Vec<WechatMpPoiBaseInfo>,
+    /// 门店总数
+    #[serde(default, with = "string_or_number")]
+    pub total_count: i64,
+}
+
+/// 门店列表中每一项同样嵌套在base_info下
+mod poi_business_list {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{WechatMpPoiBaseInfo, WechatMpPoiBusiness};
+
+    pub fn serialize<S: Serializer>(list: &[WechatMpPoiBaseInfo], serializer: S) -> Result<S::Ok, S::Error> {
+        list.iter().map(|base_info| WechatMpPoiBusiness { base_info }).collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<WechatMpPoiBaseInfo>, D::Error> {
+        let list = Vec::<WechatMpPoiBusiness<WechatMpPoiBaseInfo>>::deserialize(deserializer)?;
+        Ok(list.into_iter().map(|v| v.base_info).collect())
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatCommonResponse, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::{WechatMpPoiBaseInfo, WechatMpPoiBusiness, WechatMpPoiListResponse, WechatMpPoiPhoto, WechatMpPoiUpdateInfo};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_get_poi_deserialize() {
+        let data = r#"{"errcode":0,"errmsg":"ok","business":{"base_info":{"sid":"42","business_name":"麦当劳","branch_name":"艺苑路店","province":"广东省","city":"广州市","address":"海珠区艺苑路11号","telephone":"020-12345678","categories":["美食,快餐小吃"],"offset_type":1,"longitude":115.32375,"latitude":25.097486,"photo_list":[{"photo_url":"https://XXX.com"},{"photo_url":"https://XXX.com"}],"recommend":"麦辣鸡腿堡套餐，麦乐鸡，全家桶","special":"免费wifi，外卖服务","introduction":"麦当劳是全球大型跨国连锁餐厅","open_time":"8:00-20:00","avg_price":35,"available_state":3,"update_status":0,"poi_id":"271262077"}}}"#;
+        let business = WechatCommonResponse::parse_with_key::<WechatMpPoiBusiness<WechatMpPoiBaseInfo>>(serde_json::from_str::<Value>(data).unwrap(), "business").unwrap();
+        let info = business.base_info;
+        assert_eq!(info.poi_id.as_deref(), Some("271262077"));
+        assert_eq!(info.business_name, "麦当劳");
+        assert_eq!(info.categories, vec!["美食,快餐小吃".to_string()]);
+        assert_eq!(info.photo_list.len(), 2);
+        assert_eq!(info.photo_list[0].photo_url, "https://XXX.com");
+        assert_eq!(info.avg_price, Some(35));
+        assert_eq!(info.update_status, Some(0));
+        assert!(info.is_available());
+        // 仅查询时返回的字段不会在创建时发出
+        let v = serde_json::to_value(&info).unwrap();
+        assert!(v.get("available_state").is_none() && v.get("update_status").is_none());
+    }
+
+    #[test]
+    fn test_poi_list_deserialize() {
+        let data = r#"{"errcode":0,"errmsg":"ok","business_list":[{"base_info":{"sid":"100","poi_id":"271864249","business_name":"麦当劳","branch_name":"艺苑路店","address":"艺苑路11号","available_state":3}},{"base_info":{"sid":"101","business_name":"麦当劳","branch_name":"赤岗路店","address":"赤岗路102号","available_state":4}}],"total_count":"2"}"#;
+        let res = WechatCommonResponse::parse::<WechatMpPoiListResponse>(serde_json::from_str::<Value>(data).unwrap()).unwrap();
+        assert_eq!(res.total_count, 2);
+        assert_eq!(res.business_list.len(), 2);
+        assert_eq!(res.business_list[0].poi_id.as_deref(), Some("271864249"));
+        assert!(res.business_list[1].poi_id.is_none());
+        assert!(!res.business_list[1].is_available());
+        assert!(res.business_list[1].photo_list.is_empty());
+    }
+
+    #[test]
+    fn test_update_info_fields() {
+        // 解构时列出全部字段，修改可更新字段的范围时需同步修改此处
+        let info = WechatMpPoiUpdateInfo {
+            poi_id: "271262077".to_string(),
+            telephone: "020-12345678".to_string().into(),
+            photo_list: vec![WechatMpPoiPhoto { photo_url: "https://XXX.com".to_string() }].into(),
+            ..Default::default()
+        };
+        let WechatMpPoiUpdateInfo { poi_id, sid, telephone, photo_list, recommend, special, introduction, open_time, avg_price } = info.clone();
+        assert_eq!(serde_json::to_value(&WechatMpPoiBusiness { base_info: info }).unwrap(), json!({
+            "base_info": {"poi_id": "271262077", "telephone": "020-12345678", "photo_list": [{"photo_url": "https://XXX.com"}]}
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_add_and_list() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","poi_id":271262077}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","business_list":[],"total_count":"0"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_poi_add", "secret").base_url(&server.url);
+        let poi_id = client.poi().add(WechatMpPoiBaseInfo {
+            sid: "33788392".to_string().into(),
+            business_name: "麦当劳".to_string(),
+            address: "艺苑路11号".to_string(),
+            categories: vec!["美食,快餐小吃".to_string()],
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(poi_id.as_deref(), Some("271262077"));
+        assert_eq!(client.poi().list(0, 10).await.unwrap().total_count, 0);
+        assert!(matches!(client.poi().list(0, 51).await.unwrap_err(), LabraError::RequestError(_)));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].starts_with("POST /cgi-bin/poi/addpoi?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[1]), json!({"business": {"base_info": {"sid": "33788392", "business_name": "麦当劳", "address": "艺苑路11号", "categories": ["美食,快餐小吃"], "photo_list": []}}}));
+        assert_eq!(body(&requests[2]), json!({"begin": 0, "limit": 10}));
+    }
+
+    #[tokio::test]
+    async fn test_category_tree_cached() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"category_list":["美食,江浙菜,上海菜","美食,江浙菜,淮扬菜","美食,粤菜","休闲娱乐,KTV"]}"#),
+            MockResponse::json(r#"{"category_list":["美食,粤菜"]}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_poi_category", "secret").base_url(&server.url);
+        let tree = client.poi().get_category_tree(true).await.unwrap();
+        assert_eq!(tree.len(), 7);
+        let shanghai = tree.find_by_name("上海菜")[0].id;
+        assert_eq!(tree.path_for(shanghai).iter().map(|v| v.name.as_str()).collect::<Vec<_>>().join(","), "美食,江浙菜,上海菜");
+        // 命中缓存
+        assert_eq!(client.poi().get_category_tree(true).await.unwrap(), tree);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(client.poi().get_category_tree(false).await.unwrap().len(), 2);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].starts_with("GET /cgi-bin/poi/getwxcategory?access_token=ACCESS_TOKEN"));
+    }
+}
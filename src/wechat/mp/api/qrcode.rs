@@ -5,20 +5,26 @@ use crate::wechat::mp::constants::{QR_LIMIT_SCENE, QR_SCENE};
 use crate::wechat::mp::method::{MpQrCodeMethod, WechatMpMethod};
 
 #[derive(Debug, Clone)]
-pub struct WechatMpQRCode<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpQRCode<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpQRCode<'a, T> {
+impl<T: SessionStore> WechatMpQRCode<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpQRCode<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpQRCode<T> {
         WechatMpQRCode {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.qrcode()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpQRCode<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 换取临时二维码ticket
     /// 详情请见: <a href="https://mp.weixin.qq.com/wiki?action=doc&id=mp1443433542&t=0.9274944716856435">生成带参数的二维码</a>
@@ -0,0 +1,112 @@
+use serde_json::{json, Value};
+
+use crate::{session::SessionStore, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError, WechatEnvelope, WechatMpShortKeyResponse};
+use crate::wechat::mp::method::WechatMpMethod;
+
+/// 长信息的最大字节数（4KB）
+const SHORT_KEY_LONG_DATA_MAX_SIZE: usize = 4 * 1024;
+/// 短key的最大有效期（30天）
+const SHORT_KEY_MAX_EXPIRE_SECONDS: u64 = 2592000;
+/// 短key不存在或已过期
+const ERRCODE_SHORT_KEY_EXPIRED: i64 = 40225;
+
+/// 短key托管.
+#[derive(Debug, Clone)]
+pub struct WechatMpShortKey<T: SessionStore> {
+    client: WechatMpClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpShortKey<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpShortKey<T> {
+        WechatMpShortKey {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.short_key()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpShortKey<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// <pre>
+    /// 生成短key，long_data最大4KB，expire_seconds最大不超过2592000（即30天）
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Account_Management/KEY_Shortener.html
+    /// </pre>
+    pub async fn gen(&self, long_data: &str, expire_seconds: u64) -> LabradorResult<String> {
+        if long_data.is_empty() || long_data.len() > SHORT_KEY_LONG_DATA_MAX_SIZE {
+            return Err(LabraError::RequestError(format!("long_data不能为空且最大不能超过{}字节！", SHORT_KEY_LONG_DATA_MAX_SIZE)));
+        }
+        if expire_seconds > SHORT_KEY_MAX_EXPIRE_SECONDS {
+            return Err(LabraError::RequestError(format!("短key有效时间最大不能超过{}（即30天）！", SHORT_KEY_MAX_EXPIRE_SECONDS)));
+        }
+        let v = self.client.post(WechatMpMethod::GenShortenUrl, vec![], json!({"long_data": long_data, "expire_seconds": expire_seconds}), RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        v["short_key"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("short_key".to_string()))
+    }
+
+    /// <pre>
+    /// 解析短key，将短key还原为长信息
+    /// 短key不存在或已过期时返回`LabraError::ShortKeyExpired`，可据此重新生成
+    /// 详情请见: https://developers.weixin.qq.com/doc/offiaccount/Account_Management/KEY_Shortener.html
+    /// </pre>
+    pub async fn fetch(&self, short_key: &str) -> LabradorResult<WechatMpShortKeyResponse> {
+        let v = self.client.post(WechatMpMethod::FetchShortenUrl, vec![], json!({"short_key": short_key}), RequestType::Json).await?.json::<Value>()?;
+        WechatEnvelope::new().map_errcode(ERRCODE_SHORT_KEY_EXPIRED, LabraError::ShortKeyExpired).extract_value::<WechatMpShortKeyResponse>(v)
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer, closed_url};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gen_limits() {
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_short_key_limit", "secret").base_url(&closed_url().await);
+        let short_key = client.short_key();
+        assert!(matches!(short_key.gen("", 100).await, Err(LabraError::RequestError(_))));
+        assert!(matches!(short_key.gen(&"a".repeat(4097), 100).await, Err(LabraError::RequestError(_))));
+        assert!(matches!(short_key.gen("data", 2592001).await, Err(LabraError::RequestError(_))));
+        // 边界值通过校验，之后因无法连接而失败
+        assert!(!matches!(short_key.gen(&"a".repeat(4096), 2592000).await, Err(LabraError::RequestError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_gen_and_fetch() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","short_key":"iTHgGLvegsTLWJg"}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","long_data":"loooooong data","create_time":1611047541,"expire_seconds":86300}"#),
+            MockResponse::json(r#"{"errcode":40225,"errmsg":"invalid short key"}"#),
+            MockResponse::json(r#"{"errcode":45009,"errmsg":"reach max api daily quota limit"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_short_key", "secret").base_url(&server.url);
+        let short_key = client.short_key();
+        assert_eq!(short_key.gen("loooooong data", 86400).await.unwrap(), "iTHgGLvegsTLWJg");
+        let res = short_key.fetch("iTHgGLvegsTLWJg").await.unwrap();
+        assert_eq!(res.long_data.as_deref(), Some("loooooong data"));
+        assert_eq!(res.create_time, Some(1611047541));
+        assert_eq!(res.expire_seconds, Some(86300));
+        assert!(matches!(short_key.fetch("iTHgGLvegsTLWJg").await, Err(LabraError::ShortKeyExpired(msg)) if msg == "invalid short key"));
+        assert!(matches!(short_key.fetch("iTHgGLvegsTLWJg").await, Err(LabraError::ClientError { errcode, .. }) if errcode == "45009"));
+
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /cgi-bin/shorten/gen?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[1]), json!({"long_data": "loooooong data", "expire_seconds": 86400}));
+        assert!(requests[2].starts_with("POST /cgi-bin/shorten/fetch?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[2]), json!({"short_key": "iTHgGLvegsTLWJg"}));
+    }
+}
@@ -3,30 +3,38 @@ use serde_json::{json, Value};
 
 use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult};
 use crate::wechat::mp::method::{MpSubscribeMessageMethod, WechatMpMethod};
+use crate::serde_helper::option_string_or_number;
+use crate::wechat::subscribe_data::check_subscribe_data;
 
 /// 订阅消息服务接口
 #[derive(Debug, Clone)]
-pub struct WechatMpSubscribeMessage<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpSubscribeMessage<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpSubscribeMessage<'a, T> {
+impl<T: SessionStore> WechatMpSubscribeMessage<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpSubscribeMessage<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpSubscribeMessage<T> {
         WechatMpSubscribeMessage {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.subscribe_msg()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpSubscribeMessage<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 构造用户订阅一条模板消息授权的url连接
     /// 详情请见: https://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1500374289_66bvB
     /// </pre>
     pub async fn subscribe_message_authorization_url(&self, redirect_uri: &str, scene: i32, reserved: &str) -> String {
         format!("{}?action=get_confirm&appid={}&scene={}&template_id={}&redirect_url={}&reserved={}#wechat_redirect", MpSubscribeMessageMethod::SubscribeAuthorizeUrl.get_method(),
-                          self.client.appid, scene, self.client.template_id.to_owned().unwrap_or_default(), urlencoding::encode(redirect_uri), reserved)
+                          self.client.inner.appid, scene, self.client.inner.template_id.to_owned().unwrap_or_default(), urlencoding::encode(redirect_uri), reserved)
     }
 
     /// <pre>
@@ -117,9 +125,11 @@ impl<'a, T: SessionStore> WechatMpSubscribeMessage<'a, T> {
 
     /// <pre>
     /// 发送订阅消息
+    /// 发送前按模板关键词的类型校验参数，见`WechatSubscribeKeyword`，可使用`WechatSubscribeDataBuilder`生成data
     /// https://developers.weixin.qq.com/doc/offiaccount/Subscription_Messages/api.html
     /// </pre>
     pub async fn send_subscribe_message(&self, msg: &MpSendSubscribeMessageRequest) -> LabradorResult<WechatCommonResponse> {
+        check_subscribe_data(&msg.data)?;
         self.client.post(WechatMpMethod::SubscribeMessage(MpSubscribeMessageMethod::SendSubscribeMessage), vec![], msg, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
@@ -163,6 +173,7 @@ pub struct WechatMpPubTemplateKeywordResponse {
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatMpPubTemplateTitleListResponse {
+    #[serde(default, with = "option_string_or_number")]
     pub count: Option<i32>,
     pub data: Option<Vec<WechatMpTemplateItem>>,
 }
@@ -217,3 +228,51 @@ pub struct MpSendSubscribeMessageRequest {
     /// 消息正文，value为消息内容文本（200字以内），没有固定格式，可用\n换行，color为整段消息内容的字体颜色（目前仅支持整段消息为一种颜色）
     pub data: Value,
 }
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{LabraError, SimpleStorage, WechatMpClient, WechatSubscribeDataBuilder};
+    use crate::util::mock::{MockResponse, MockServer};
+    use super::MpSendSubscribeMessageRequest;
+
+    const TOKEN: &str = r#"{"errcode":0,"errmsg":"ok","access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[tokio::test]
+    async fn test_send_subscribe_message_validates_keywords() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_subscribe_data", "secret").base_url(&server.url);
+        let request = |data| MpSendSubscribeMessageRequest {
+            touser: "OPENID".to_string(),
+            template_id: "TEMPLATE_ID".to_string(),
+            url: None,
+            miniprogram: None,
+            scene: None,
+            data,
+        };
+        let invalid = vec![
+            json!({"thing1": {"value": "一二三四五六七八九十一二三四五六七八九十一"}}),
+            json!({"number2": {"value": "一百"}}),
+            json!({"phrase3": {"value": "配送中ing"}}),
+            json!({"time4": {"value": "明天中午"}}),
+            json!({"phone_number5": {"value": "+86-0766-668888661234"}}),
+            json!({"car_number6": {"value": "粤A8Z888挂1"}}),
+        ];
+        for data in invalid {
+            let err = client.subscribe_msg().send_subscribe_message(&request(data.to_owned())).await.unwrap_err();
+            assert!(matches!(err, LabraError::RequestError(_)), "{} {:?}", data, err);
+        }
+        assert!(server.requests().is_empty());
+        let data = WechatSubscribeDataBuilder::new()
+            .value("thing1", "门铃被按响")
+            .value("time4", "2019-10-01 15:01~2019-10-01 16:01")
+            .value("phone_number5", "13800138000")
+            .value("car_number6", "粤A8Z888")
+            .build().unwrap();
+        let res = client.subscribe_msg().send_subscribe_message(&request(data)).await.unwrap();
+        assert_eq!(res.errcode, Some(0));
+        assert!(server.requests()[1].starts_with("POST /cgi-bin/message/subscribe/bizsend?access_token=ACCESS_TOKEN"));
+    }
+}
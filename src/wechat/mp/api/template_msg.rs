@@ -1,35 +1,42 @@
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 
-use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult};
+use crate::{session::SessionStore, request::{RequestType}, WechatCommonResponse, WechatMpClient, LabradorResult, LabraError};
 use crate::wechat::mp::method::{MpTemplateMessageMethod, WechatMpMethod};
+use crate::text::{self, TextPolicy};
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMpTemplateMessage<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpTemplateMessage<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpTemplateMessage<'a, T> {
+impl<T: SessionStore> WechatMpTemplateMessage<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpTemplateMessage<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpTemplateMessage<T> {
         WechatMpTemplateMessage {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.template_msg()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpTemplateMessage<T> {
+        Self::from_client(client.clone())
+    }
+
     /// 设置所属行业
     /// 设置行业可在微信公众平台后台完成，每月可修改行业1次，帐号仅可使用所属行业中相关的模板，为方便第三方开发者，提供通过接口调用的方式来修改账号所属行业
     /// `industry_id1` 公众号模板消息所属行业编号
     /// `industry_id2` 公众号模板消息所属行业编号
     ///
     /// [地址](https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Template_Message_Interface.html)
-    pub async fn set_industry(&self, industry_id1: &str, industry_id2: &str) -> LabradorResult<WechatCommonResponse> {
+    pub async fn set_industry(&self, industry_id1: WechatMpIndustry, industry_id2: WechatMpIndustry) -> LabradorResult<WechatCommonResponse> {
         self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::SetIndustry), vec![], json!({
-            "industry_id1": industry_id1,
-            "industry_id2": industry_id2,
+            "industry_id1": industry_id1.code().to_string(),
+            "industry_id2": industry_id2.code().to_string(),
         }), RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
@@ -38,7 +45,7 @@ impl<'a, T: SessionStore> WechatMpTemplateMessage<'a, T> {
     ///
     /// [地址](https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Template_Message_Interface.html)
     pub async fn get_industry(&self) -> LabradorResult<IndustryResponse> {
-        let response = self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::GetIndustry), vec![], Value::Null, RequestType::Json).await?.json::<Value>()?;
+        let response = self.client.get(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::GetIndustry), vec![], RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse::<IndustryResponse>(response)
     }
 
@@ -46,6 +53,7 @@ impl<'a, T: SessionStore> WechatMpTemplateMessage<'a, T> {
     ///
     /// [地址](https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Template_Message_Interface.html)
     pub async fn send_mp_message(&self, data: TemplateMessage) -> LabradorResult<WechatCommonResponse> {
+        let data = data.sanitize(&TextPolicy::new())?;
         self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::SendTemplate), vec![], data, RequestType::Json).await?.json::<WechatCommonResponse>()
     }
 
@@ -55,10 +63,22 @@ impl<'a, T: SessionStore> WechatMpTemplateMessage<'a, T> {
     ///
     /// [地址](https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Template_Message_Interface.html)
     pub async fn get_template_id(&self, template_id_short: &str) -> LabradorResult<String> {
-        let response = self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::GetTemplateId), vec![], json!({ "template_id_short": template_id_short }), RequestType::Json).await?.json::<Value>()?;
+        self.add_template(template_id_short, &[]).await
+    }
+
+    /// 从模板库添加模板
+    /// `template_id_short` 模板库中模板的编号，有“TM**”和“OPENTMTM**”等形式
+    /// `keyword_name_list` 选用的类目模板的关键词，按顺序传入，为空时使用模板的全部关键词
+    ///
+    /// [地址](https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Template_Message_Interface.html)
+    pub async fn add_template(&self, template_id_short: &str, keyword_name_list: &[&str]) -> LabradorResult<String> {
+        let mut req = json!({ "template_id_short": template_id_short });
+        if !keyword_name_list.is_empty() {
+            req["keyword_name_list"] = json!(keyword_name_list);
+        }
+        let response = self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::GetTemplateId), vec![], req, RequestType::Json).await?.json::<Value>()?;
         let v = WechatCommonResponse::parse::<Value>(response)?;
-        let template_id = v["template_id"].as_str().unwrap_or_default();
-        Ok(template_id.to_string())
+        v["template_id"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("template_id".to_string()))
     }
 
     /// 获得模板列表
@@ -66,7 +86,7 @@ impl<'a, T: SessionStore> WechatMpTemplateMessage<'a, T> {
     ///
     /// [地址](https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Template_Message_Interface.html)
     pub async fn get_template_list(&self) -> LabradorResult<Vec<TemplateMessageInfo>> {
-        let response = self.client.post(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::GetTemplateList), vec![], Value::Null, RequestType::Json).await?.json::<Value>()?;
+        let response = self.client.get(WechatMpMethod::TemplateMessage(MpTemplateMessageMethod::GetTemplateList), vec![], RequestType::Json).await?.json::<Value>()?;
         WechatCommonResponse::parse_with_key::<Vec<TemplateMessageInfo>>(response, "template_list")
     }
 
@@ -92,20 +112,159 @@ pub struct TemplateMessage {
     pub data: Value,
 }
 
+impl TemplateMessage {
+    /// <pre>
+    /// 按策略处理data中每个关键词的value
+    /// 发送时默认去除控制字符；关键词有长度限制时可先调用，如`message.sanitize(&TextPolicy::new().max_bytes(60).truncate("…"))`
+    /// </pre>
+    pub fn sanitize(mut self, policy: &TextPolicy) -> LabradorResult<Self> {
+        if let Some(data) = self.data.as_object_mut() {
+            for item in data.values_mut() {
+                let value = match item {
+                    Value::Object(item) => item.get_mut("value"),
+                    item => Some(item),
+                };
+                if let Some(Value::String(value)) = value {
+                    *value = text::sanitize(value, policy)?;
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
 
 /// 行业信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndustryResponse {
-    primary_industry: Option<IndustryClass>,
-    secondary_industry: Option<IndustryClass>,
+    pub primary_industry: Option<IndustryClass>,
+    pub secondary_industry: Option<IndustryClass>,
 }
 
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndustryClass {
-    first_class: Option<String>,
-    second_class: Option<String>,
+    pub first_class: Option<String>,
+    pub second_class: Option<String>,
+}
+
+impl IndustryClass {
+    /// 按行业名称对应到行业编号，未收录的行业为None
+    pub fn industry(&self) -> Option<WechatMpIndustry> {
+        WechatMpIndustry::from_class(self.first_class.as_deref().unwrap_or_default(), self.second_class.as_deref().unwrap_or_default())
+    }
+}
+
+macro_rules! industries {
+    ($($variant:ident = $code:expr, $first:expr, $second:expr;)*) => {
+        /// <pre>
+        /// 模板消息的行业
+        /// 编号与名称见文档中的行业代码查询，未收录的编号为Other
+        /// </pre>
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "i32", into = "i32")]
+        pub enum WechatMpIndustry {
+            $(
+                #[doc = concat!($first, " / ", $second)]
+                $variant,
+            )*
+            Other(i32),
+        }
+
+        impl WechatMpIndustry {
+            /// 行业编号
+            pub fn code(&self) -> i32 {
+                match *self {
+                    $(WechatMpIndustry::$variant => $code,)*
+                    WechatMpIndustry::Other(v) => v,
+                }
+            }
+
+            /// 主行业
+            pub fn first_class(&self) -> Option<&'static str> {
+                match *self {
+                    $(WechatMpIndustry::$variant => Some($first),)*
+                    WechatMpIndustry::Other(_) => None,
+                }
+            }
+
+            /// 副行业
+            pub fn second_class(&self) -> Option<&'static str> {
+                match *self {
+                    $(WechatMpIndustry::$variant => Some($second),)*
+                    WechatMpIndustry::Other(_) => None,
+                }
+            }
+
+            /// 由get_industry返回的主行业、副行业名称得到行业
+            pub fn from_class(first_class: &str, second_class: &str) -> Option<WechatMpIndustry> {
+                match (first_class, second_class) {
+                    $(($first, $second) => Some(WechatMpIndustry::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<i32> for WechatMpIndustry {
+            fn from(v: i32) -> Self {
+                match v {
+                    $($code => WechatMpIndustry::$variant,)*
+                    v => WechatMpIndustry::Other(v),
+                }
+            }
+        }
+    };
+}
+
+industries! {
+    Ecommerce = 1, "IT科技", "互联网/电子商务";
+    ItSoftware = 2, "IT科技", "IT软件与服务";
+    ItHardware = 3, "IT科技", "IT硬件与设备";
+    Electronics = 4, "IT科技", "电子技术";
+    Telecom = 5, "IT科技", "通信与运营商";
+    OnlineGame = 6, "IT科技", "网络游戏";
+    Bank = 7, "金融业", "银行";
+    Fund = 8, "金融业", "基金理财信托";
+    Insurance = 9, "金融业", "保险";
+    Catering = 10, "餐饮", "餐饮";
+    Hotel = 11, "酒店旅游", "酒店";
+    Travel = 12, "酒店旅游", "旅游";
+    Express = 13, "运输与仓储", "快递";
+    Logistics = 14, "运输与仓储", "物流";
+    Warehouse = 15, "运输与仓储", "仓储";
+    Training = 16, "教育", "培训";
+    School = 17, "教育", "院校";
+    Research = 18, "政府与公共事业", "学术科研";
+    TrafficPolice = 19, "政府与公共事业", "交警";
+    Museum = 20, "政府与公共事业", "博物馆";
+    PublicWelfare = 21, "政府与公共事业", "公共事业非盈利机构";
+    Medical = 22, "医药护理", "医药医疗";
+    Beauty = 23, "医药护理", "护理美容";
+    Health = 24, "医药护理", "保健与卫生";
+    Automobile = 25, "交通工具", "汽车相关";
+    Motorcycle = 26, "交通工具", "摩托车相关";
+    Train = 27, "交通工具", "火车相关";
+    Airplane = 28, "交通工具", "飞机相关";
+    Construction = 29, "房地产", "建筑";
+    Property = 30, "房地产", "物业";
+    ConsumerGoods = 31, "消费品", "消费品";
+    Legal = 32, "商业服务", "法律";
+    Exhibition = 33, "商业服务", "会展";
+    Agency = 34, "商业服务", "中介服务";
+    Certification = 35, "商业服务", "认证";
+    Audit = 36, "商业服务", "审计";
+    Media = 37, "文体娱乐", "传媒";
+    Sports = 38, "文体娱乐", "体育";
+    Entertainment = 39, "文体娱乐", "娱乐休闲";
+    Printing = 40, "印刷", "印刷";
+    Others = 41, "其它", "其它";
+}
+
+impl From<WechatMpIndustry> for i32 {
+    fn from(v: WechatMpIndustry) -> Self {
+        v.code()
+    }
 }
 
 
@@ -113,16 +272,130 @@ pub struct IndustryClass {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateMessageInfo {
     /// 模板ID
-    template_id: Option<String>,
+    pub template_id: Option<String>,
     /// 模板标题
-    title: Option<String>,
+    pub title: Option<String>,
     /// 模板所属行业的一级行业
-    primary_industry: Option<String>,
+    pub primary_industry: Option<String>,
     /// 模板所属行业的二级行业
-    deputy_industry: Option<String>,
-    /// 模板内容
-    content: Option<String>,
+    pub deputy_industry: Option<String>,
+    /// 模板内容，关键词为`{{keyword.DATA}}`形式的占位符
+    pub content: Option<String>,
     /// 模板示例
-    example: Option<String>,
+    pub example: Option<String>,
+}
+
+impl TemplateMessageInfo {
+    /// 模板内容中的关键词
+    pub fn keywords(&self) -> Vec<String> {
+        extract_keywords(self.content.as_deref().unwrap_or_default())
+    }
+}
+
+/// <pre>
+/// 提取模板内容中`{{keyword.DATA}}`占位符的关键词，按出现顺序去重
+/// 如"{{first.DATA}}\n金额：{{amount1.DATA}}"得到["first", "amount1"]，可据此构造模板消息的data
+/// </pre>
+pub fn extract_keywords(content: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let end = match rest.find("}}") {
+            Some(end) => end,
+            None => break,
+        };
+        if let Some(keyword) = rest[..end].trim().strip_suffix(".DATA") {
+            if !keyword.is_empty() && !keywords.iter().any(|v| v == keyword) {
+                keywords.push(keyword.to_string());
+            }
+        }
+        rest = &rest[end + 2..];
+    }
+    keywords
 }
 
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{SimpleStorage, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::text::TextPolicy;
+    use super::{extract_keywords, IndustryClass, TemplateMessage, WechatMpIndustry};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+
+    #[test]
+    fn test_extract_keywords() {
+        let content = "{{ first.DATA }}\n会员编号：{{keyword1.DATA}}\n积分：{{ thing2.DATA}}\n{{keyword1.DATA}}{{remark.DATA}}{{broken}}{{.DATA}}{{unclosed.DATA";
+        assert_eq!(extract_keywords(content), vec!["first", "keyword1", "thing2", "remark"]);
+        assert!(extract_keywords("没有占位符").is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_values() {
+        let message = TemplateMessage {
+            touser: None,
+            template_id: "TEMPLATE_ID".to_string(),
+            url: None,
+            miniprogram: None,
+            data: json!({"thing1": {"value": "订单\u{0}已发货，请注意查收", "color": "#173177"}, "remark": "备注\u{200B}"}),
+        };
+        let sanitized = message.clone().sanitize(&TextPolicy::new()).unwrap();
+        assert_eq!(sanitized.data, json!({"thing1": {"value": "订单已发货，请注意查收", "color": "#173177"}, "remark": "备注"}));
+        let truncated = message.clone().sanitize(&TextPolicy::new().max_bytes(12).truncate("…")).unwrap();
+        assert_eq!(truncated.data["thing1"]["value"], "订单已…");
+        assert!(message.sanitize(&TextPolicy::new().max_bytes(12)).is_err());
+    }
+
+    #[test]
+    fn test_industry_mapping() {
+        assert_eq!(WechatMpIndustry::from(1), WechatMpIndustry::Ecommerce);
+        assert_eq!(WechatMpIndustry::Ecommerce.first_class(), Some("IT科技"));
+        assert_eq!(WechatMpIndustry::Ecommerce.second_class(), Some("互联网/电子商务"));
+        assert_eq!(WechatMpIndustry::from(41), WechatMpIndustry::Others);
+        assert_eq!(WechatMpIndustry::from(99), WechatMpIndustry::Other(99));
+        assert_eq!(WechatMpIndustry::Other(99).first_class(), None);
+        for code in 1..=41 {
+            let industry = WechatMpIndustry::from(code);
+            assert_eq!(industry.code(), code);
+            assert_eq!(WechatMpIndustry::from_class(industry.first_class().unwrap(), industry.second_class().unwrap()), Some(industry));
+        }
+        assert_eq!(serde_json::from_value::<WechatMpIndustry>(json!(10)).unwrap(), WechatMpIndustry::Catering);
+        assert_eq!(serde_json::to_value(WechatMpIndustry::Catering).unwrap(), json!(10));
+        let class = IndustryClass { first_class: Some("运输与仓储".to_string()), second_class: Some("快递".to_string()) };
+        assert_eq!(class.industry(), Some(WechatMpIndustry::Express));
+    }
+
+    #[tokio::test]
+    async fn test_template_management() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+            MockResponse::json(r#"{"primary_industry":{"first_class":"运输与仓储","second_class":"快递"},"secondary_industry":{"first_class":"IT科技","second_class":"互联网/电子商务"}}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","template_id":"Doclyl5uP7Aciu-qZ7mJNPtWkbkYnWBWVja26EGbNyk"}"#),
+            MockResponse::json(r#"{"template_list":[{"template_id":"iPk5sOIt5X_flOVKn5GrTFpncEYTojx6ddbt8WYoV5s","title":"领取奖金提醒","primary_industry":"IT科技","deputy_industry":"互联网|电子商务","content":"{ {result.DATA} }\n\n领奖金额:{{withdrawMoney.DATA}}\n领奖  时间:    {{withdrawTime.DATA}}\n{{remark.DATA}}","example":"您已提交领奖申请\n\n领奖金额：xxxx元"}]}"#),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok"}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_template_management", "secret").base_url(&server.url);
+        let template = client.template_msg();
+        assert!(template.set_industry(WechatMpIndustry::Express, WechatMpIndustry::Ecommerce).await.unwrap().is_success());
+        let industry = template.get_industry().await.unwrap();
+        assert_eq!(industry.primary_industry.unwrap().industry(), Some(WechatMpIndustry::Express));
+        assert_eq!(template.add_template("TM00015", &["物品名称", "购买时间"]).await.unwrap(), "Doclyl5uP7Aciu-qZ7mJNPtWkbkYnWBWVja26EGbNyk");
+        let list = template.get_template_list().await.unwrap();
+        assert_eq!(list[0].keywords(), vec!["withdrawMoney", "withdrawTime", "remark"]);
+        assert!(template.delete_template("iPk5sOIt5X_flOVKn5GrTFpncEYTojx6ddbt8WYoV5s").await.unwrap().is_success());
+
+        let requests = server.requests();
+        let body = |request: &str| serde_json::from_str::<Value>(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert!(requests[1].starts_with("POST /cgi-bin/template/api_set_industry?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[1]), json!({"industry_id1": "13", "industry_id2": "1"}));
+        assert!(requests[2].starts_with("GET /cgi-bin/template/get_industry?access_token=ACCESS_TOKEN"));
+        assert_eq!(body(&requests[3]), json!({"template_id_short": "TM00015", "keyword_name_list": ["物品名称", "购买时间"]}));
+        assert!(requests[4].starts_with("GET /cgi-bin/template/get_all_private_template?access_token=ACCESS_TOKEN"));
+        assert!(requests[5].starts_with("POST /cgi-bin/template/del_private_template?access_token=ACCESS_TOKEN"));
+    }
+}
@@ -2,26 +2,35 @@ use std::collections::HashMap;
 use serde_json::{json, Value};
 
 use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use futures_util::Stream;
 
-use crate::{session::SessionStore, errors::LabraError, wechat::{cryptos::WechatCrypto}, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult};
+use crate::{session::SessionStore, errors::LabraError, wechat::{cryptos::WechatCrypto}, request::RequestType, WechatCommonResponse, WechatMpClient, LabradorResult, Page, PagedStream};
 use crate::wechat::mp::method::{MpUserMethod, WechatMpMethod};
+use crate::serde_helper::{bool_from_int, option_timestamp_seconds, string_or_number};
 
 
 #[derive(Debug, Clone)]
-pub struct WechatMpUser<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpUser<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpUser<'a, T> {
+impl<T: SessionStore> WechatMpUser<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpUser<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpUser<T> {
         WechatMpUser {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.user()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpUser<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 获取用户基本信息（语言为默认的zh_CN 简体）
     /// 详情请见: http://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1421140839&token=&lang=zh_CN
@@ -42,7 +51,7 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
         let res = self.client.get(WechatMpMethod::User(MpUserMethod::Info), vec![("openid".to_string(), openid.to_string()), ("lang".to_string(), lang.to_string())], RequestType::Json).await?.json::<serde_json::Value>()?;
         let result = WechatCommonResponse::from_value(res.clone())?;
         if result.is_success() {
-            Ok(self.json_to_user(&res))
+            self.json_to_user(&res)
         } else {
             Err(LabraError::ClientError {errcode: result.errcode.to_owned().unwrap_or_default().to_string(), errmsg: result.errmsg.to_owned().unwrap_or_default()})
         }
@@ -89,7 +98,7 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
                     province,
                     country,
                     avatar,
-                    subscribe_time: 0,
+                    subscribe_time: None,
                     unionid,
                     remark: "".to_string(),
                     group_id: 0,
@@ -112,7 +121,12 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
             "openid": openid.to_owned(),
             "remark": remark.to_owned()
         });
-        self.client.post(WechatMpMethod::User(MpUserMethod::UpdateRemark), vec![], data, RequestType::Json).await?.json::<WechatCommonResponse>()
+        let res = self.client.post(WechatMpMethod::User(MpUserMethod::UpdateRemark), vec![], data, RequestType::Json).await?.json::<WechatCommonResponse>();
+        let prefix = format!("/cgi-bin/user/info?openid={}", urlencoding::encode(openid));
+        if let Err(err) = self.client.invalidate_cache_prefix(&prefix) {
+            tracing::warn!("[响应缓存] 使{}失效失败:{}", prefix, err);
+        }
+        res
     }
 
     /// <pre>
@@ -132,10 +146,8 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
         let res = self.client.get(WechatMpMethod::User(MpUserMethod::Get), params, RequestType::Json, ).await?.json::<serde_json::Value>()?;
         let mut result = WechatCommonResponse::from_value(res.clone())?;
         if result.is_success() {
-            let total = &res["total"];
-            let total = total.as_u64().unwrap_or_default();
-            let count = &res["count"];
-            let count = count.as_u64().unwrap_or_default();
+            let total = string_or_number::deserialize::<u64, _>(&res["total"]).unwrap_or_default();
+            let count = string_or_number::deserialize::<u64, _>(&res["count"]).unwrap_or_default();
             let next_id = &res["next_openid"];
             let next_id = next_id.as_str().unwrap_or_default().to_owned();
             let s = res.as_object().unwrap();
@@ -165,6 +177,26 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
         }
     }
 
+    /// <pre>
+    /// 拉取全部关注者OpenID
+    /// 基于get_followers按next_openid自动翻页，消费时才会发起请求。
+    /// `max_items` 最多返回的条数
+    /// </pre>
+    pub fn list_all_followers(&self, max_items: Option<usize>) -> impl Stream<Item = LabradorResult<String>> + '_ {
+        let client = self.client.to_owned();
+        let mut pager = PagedStream::new(move |next_openid: Option<String>| {
+            let mut user = WechatMpUser::from_client(client.to_owned());
+            async move {
+                let followers = user.get_followers(next_openid.as_deref()).await?;
+                Ok(Page::with_cursor(followers.openids, followers.next_openid.into()))
+            }
+        });
+        if let Some(max_items) = max_items {
+            pager = pager.max_items(max_items);
+        }
+        pager.into_stream()
+    }
+
     /// 获取分组编号
     pub async fn get_group_id(&mut self, openid: &str) -> LabradorResult<u64> {
         let data = json!({
@@ -181,57 +213,8 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
         }
     }
 
-    fn json_to_user(&self, res: &Value) -> WechatUser {
-        let _subscribe = &res["subscribe"];
-        let subscribe = match _subscribe.as_u64().unwrap_or_default() {
-            1 => true,
-            0 => false,
-            _ => unreachable!(),
-        };
-        let openid = &res["openid"];
-        let openid = openid.as_str().unwrap_or_default().to_owned();
-        let nickname = &res["nickname"];
-        let nickname = nickname.as_str().unwrap_or_default().to_owned();
-        let sex = &res["sex"];
-        let sex = sex.as_u64().unwrap_or_default();
-        let language = &res["language"];
-        let language = language.as_str().unwrap_or_default().to_owned();
-        let city = &res["city"];
-        let city = city.as_str().unwrap_or_default().to_owned();
-        let province = &res["province"];
-        let province = province.as_str().unwrap_or_default().to_owned();
-        let country = &res["country"];
-        let country = country.as_str().unwrap_or_default().to_owned();
-        let avatar = &res["headimgurl"];
-        let avatar = avatar.as_str().unwrap_or_default().to_owned();
-        let subscribe_time = &res["subscribe_time"];
-        let subscribe_time = subscribe_time.as_u64().unwrap_or_default();
-        let unionid = match res.get("unionid") {
-            Some(ref uid) => {
-                let _uid = uid.as_str().unwrap_or_default().to_owned();
-                Some(_uid.to_owned())
-            },
-            None => None,
-        };
-        let remark = &res["remark"];
-        let remark = remark.as_str().unwrap_or_default().to_owned();
-        let group_id = &res["groupid"];
-        let group_id = group_id.as_u64().unwrap_or_default();
-        WechatUser {
-            subscribe,
-            openid: openid.to_owned(),
-            nickname: nickname.to_owned(),
-            sex: sex as u8,
-            language: language.to_owned(),
-            city: city.to_owned(),
-            province: province.to_owned(),
-            country: country.to_owned(),
-            avatar: avatar.to_owned(),
-            subscribe_time,
-            unionid,
-            remark: remark.to_owned(),
-            group_id,
-        }
+    fn json_to_user(&self, res: &Value) -> LabradorResult<WechatUser> {
+        serde_json::from_value::<WechatUser>(res.to_owned()).map_err(LabraError::from)
     }
 
     /// <pre>
@@ -252,7 +235,7 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
             let info_list = info_list.as_array().unwrap();
             let mut users = vec![];
             for info in info_list {
-                users.push(self.json_to_user(&info));
+                users.push(self.json_to_user(&info)?);
             }
             Ok(users)
         } else {
@@ -281,27 +264,36 @@ impl<'a, T: SessionStore> WechatMpUser<'a, T> {
 
 //----------------------------------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone,  Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WechatUser {
+    #[serde(with = "bool_from_int")]
     pub subscribe: bool,
     pub openid: String,
     pub nickname: String,
+    #[serde(with = "string_or_number")]
     pub sex: u8,
     pub language: String,
     pub city: String,
     pub province: String,
     pub country: String,
+    #[serde(alias = "headimgurl")]
     pub avatar: String,
-    pub subscribe_time: u64,
+    /// 关注时间，未关注时为None
+    #[serde(with = "option_timestamp_seconds")]
+    pub subscribe_time: Option<DateTime<Utc>>,
     pub unionid: Option<String>,
     pub remark: String,
+    #[serde(alias = "groupid", with = "string_or_number")]
     pub group_id: u64,
 }
 
 
 #[derive(Debug, Clone,  Serialize, Deserialize)]
 pub struct Followers {
+    #[serde(with = "string_or_number")]
     pub total: u64,
+    #[serde(with = "string_or_number")]
     pub count: u64,
     pub openids: Vec<String>,
     pub next_openid: String,
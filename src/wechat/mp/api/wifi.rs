@@ -8,20 +8,26 @@ use crate::wechat::mp::method::{MpWifiMethod, WechatMpMethod};
 
 /// 微信连接WI-FI接口.
 #[derive(Debug, Clone)]
-pub struct WechatMpWifi<'a, T: SessionStore> {
-    client: &'a WechatMpClient<T>,
+pub struct WechatMpWifi<T: SessionStore> {
+    client: WechatMpClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WechatMpWifi<'a, T> {
+impl<T: SessionStore> WechatMpWifi<T> {
 
     #[inline]
-    pub fn new(client: &WechatMpClient<T>) -> WechatMpWifi<T> {
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpWifi<T> {
         WechatMpWifi {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.wifi()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpWifi<T> {
+        Self::from_client(client.clone())
+    }
+
     /// <pre>
     /// 获取Wi-Fi门店列表.
     /// 通过此接口获取WiFi的门店列表，该列表包括公众平台的门店信息、以及添加设备后的WiFi相关信息。创建门店方法请参考“微信门店接口”。
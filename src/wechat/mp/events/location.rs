@@ -28,9 +28,9 @@ impl MessageParser for LocationEvent {
         let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
         let id = xmlutil::evaluate(&doc, "//xml/MsgId/text()").number() as i64;
         let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
-        let latitude = xmlutil::evaluate(&doc, "//xml/Latitude/text()").number() as f64;
-        let longitude = xmlutil::evaluate(&doc, "//xml/Longitude/text()").number() as f64;
-        let precision = xmlutil::evaluate(&doc, "//xml/Precision/text()").number() as f64;
+        let latitude = xmlutil::evaluate(&doc, "//xml/Latitude/text()").string().trim().parse::<f64>().unwrap_or_default();
+        let longitude = xmlutil::evaluate(&doc, "//xml/Longitude/text()").string().trim().parse::<f64>().unwrap_or_default();
+        let precision = xmlutil::evaluate(&doc, "//xml/Precision/text()").string().trim().parse::<f64>().unwrap_or_default();
         LocationEvent {
             source: source,
             target: target,
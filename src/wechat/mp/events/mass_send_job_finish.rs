@@ -0,0 +1,97 @@
+use chrono::NaiveDateTime;
+
+use crate::wechat::mp::messages::MessageParser;
+use crate::xmlutil;
+
+/// 群发任务完成事件，Status为群发的结果，如`send success`、`send fail`、`err(10001)`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MassSendJobFinishEvent {
+    pub source: String,
+    pub target: String,
+    pub time: i64,
+    pub create_time: NaiveDateTime,
+    /// 群发的消息ID
+    pub id: i64,
+    pub status: String,
+    /// 标签或OpenID列表中的粉丝数
+    pub total_count: i64,
+    /// 过滤（过滤是指特定地区、性别的过滤、用户设置拒收的过滤，用户接收已超4条的过滤）后，准备发送的粉丝数
+    pub filter_count: i64,
+    /// 发送成功的粉丝数
+    pub sent_count: i64,
+    /// 发送失败的粉丝数
+    pub error_count: i64,
+    pub event: String,
+    pub raw: String,
+}
+
+impl MessageParser for MassSendJobFinishEvent {
+    type WechatMessage = MassSendJobFinishEvent;
+
+    #[inline]
+    fn from_xml(xml: &str) -> MassSendJobFinishEvent {
+        let package = xmlutil::parse(xml);
+        let doc = package.as_document();
+        let source = xmlutil::evaluate(&doc, "//xml/FromUserName/text()").string();
+        let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
+        let id = xmlutil::evaluate(&doc, "//xml/MsgID/text()").number() as i64;
+        let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
+        let status = xmlutil::evaluate(&doc, "//xml/Status/text()").string();
+        let total_count = xmlutil::evaluate(&doc, "//xml/TotalCount/text()").number() as i64;
+        let filter_count = xmlutil::evaluate(&doc, "//xml/FilterCount/text()").number() as i64;
+        let sent_count = xmlutil::evaluate(&doc, "//xml/SentCount/text()").number() as i64;
+        let error_count = xmlutil::evaluate(&doc, "//xml/ErrorCount/text()").number() as i64;
+        MassSendJobFinishEvent {
+            source,
+            target,
+            id,
+            time,
+            create_time: NaiveDateTime::from_timestamp(time, 0),
+            status,
+            total_count,
+            filter_count,
+            sent_count,
+            error_count,
+            event: "masssendjobfinish".to_owned(),
+            raw: xml.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wechat::{messages::MessageParser};
+    use super::MassSendJobFinishEvent;
+
+    #[test]
+    fn test_from_xml() {
+        let xml = "<xml>
+        <ToUserName><![CDATA[gh_4d00ed8d6399]]></ToUserName>
+        <FromUserName><![CDATA[oV5CrjpxgaGXNHIQigzNlgLTnwic]]></FromUserName>
+        <CreateTime>1481013459</CreateTime>
+        <MsgType><![CDATA[event]]></MsgType>
+        <Event><![CDATA[MASSSENDJOBFINISH]]></Event>
+        <MsgID>1000001625</MsgID>
+        <Status><![CDATA[err(30003)]]></Status>
+        <TotalCount>0</TotalCount>
+        <FilterCount>0</FilterCount>
+        <SentCount>0</SentCount>
+        <ErrorCount>0</ErrorCount>
+        </xml>";
+        let msg = MassSendJobFinishEvent::from_xml(xml);
+
+        assert_eq!("oV5CrjpxgaGXNHIQigzNlgLTnwic", &msg.source);
+        assert_eq!("gh_4d00ed8d6399", &msg.target);
+        assert_eq!("masssendjobfinish", &msg.event);
+        assert_eq!(1481013459, msg.time);
+        assert_eq!(1000001625, msg.id);
+        assert_eq!("err(30003)", &msg.status);
+
+        let xml = "<xml><ToUserName><![CDATA[toUser]]></ToUserName><FromUserName><![CDATA[fromUser]]></FromUserName>
+        <CreateTime>1394524295</CreateTime><MsgType><![CDATA[event]]></MsgType><Event><![CDATA[MASSSENDJOBFINISH]]></Event>
+        <MsgID>1988</MsgID><Status><![CDATA[sendsuccess]]></Status><TotalCount>100</TotalCount><FilterCount>80</FilterCount>
+        <SentCount>75</SentCount><ErrorCount>5</ErrorCount></xml>";
+        let msg = MassSendJobFinishEvent::from_xml(xml);
+        assert_eq!((msg.total_count, msg.filter_count, msg.sent_count, msg.error_count), (100, 80, 75, 5));
+    }
+}
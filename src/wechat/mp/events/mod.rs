@@ -7,9 +7,13 @@ mod click;
 mod view;
 mod qualification_verify_success;
 mod template_send_job_finish;
+mod mass_send_job_finish;
+mod user_enter_tempsession;
+mod subscribe_msg_popup;
 
 pub use self::subscribe::SubscribeEvent;
-pub use self::template_send_job_finish::TemplateSendJobFinishEvent;
+pub use self::template_send_job_finish::{TemplateSendJobFinishEvent, TemplateSendStatus};
+pub use self::mass_send_job_finish::MassSendJobFinishEvent;
 pub use self::unsubscribe::UnsubscribeEvent;
 pub use self::scan::ScanEvent;
 pub use self::subscribe_scan::SubscribeScanEvent;
@@ -17,3 +21,16 @@ pub use self::location::LocationEvent;
 pub use self::click::ClickEvent;
 pub use self::view::ViewEvent;
 pub use self::qualification_verify_success::QualificationVerifySuccessEvent;
+pub use self::user_enter_tempsession::UserEnterTempSessionEvent;
+pub use self::subscribe_msg_popup::{SubscribeMsgPopupEvent, SubscribeMsgPopupItem};
+
+
+/// 带参二维码关注事件的EventKey带有`qrscene_`前缀，扫码事件则没有，这里统一去掉前缀
+pub(crate) fn qrscene(event_key: &str) -> Option<String> {
+    let scene = event_key.strip_prefix("qrscene_").unwrap_or(event_key);
+    if scene.is_empty() {
+        None
+    } else {
+        Some(scene.to_string())
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::NaiveDateTime;
 
 use crate::wechat::mp::messages::MessageParser;
+use crate::wechat::mp::events::qrscene;
 use crate::xmlutil;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -16,6 +17,13 @@ pub struct ScanEvent {
     pub raw: String,
 }
 
+impl ScanEvent {
+    /// 二维码场景值，与关注事件保持一致
+    pub fn scan_scene(&self) -> Option<String> {
+        qrscene(&self.scene_id)
+    }
+}
+
 impl MessageParser for ScanEvent {
     type WechatMessage = ScanEvent;
 
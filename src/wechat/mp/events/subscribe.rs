@@ -1,6 +1,7 @@
 use chrono::NaiveDateTime;
 
 use crate::wechat::mp::messages::MessageParser;
+use crate::wechat::mp::events::qrscene;
 use crate::xmlutil;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -10,10 +11,21 @@ pub struct SubscribeEvent {
     pub time: i64,
     pub create_time: NaiveDateTime,
     pub id: i64,
+    /// 扫描带参二维码关注时为`qrscene_`加二维码参数，普通关注时为空
+    pub event_key: String,
+    /// 二维码的ticket，可用来换取二维码图片
+    pub ticket: String,
     pub event: String,
     pub raw: String,
 }
 
+impl SubscribeEvent {
+    /// 二维码场景值（已去掉`qrscene_`前缀），普通关注时为None
+    pub fn scan_scene(&self) -> Option<String> {
+        qrscene(&self.event_key)
+    }
+}
+
 impl MessageParser for SubscribeEvent {
     type WechatMessage = SubscribeEvent;
 
@@ -25,12 +37,16 @@ impl MessageParser for SubscribeEvent {
         let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
         let id = xmlutil::evaluate(&doc, "//xml/MsgId/text()").number() as i64;
         let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
+        let event_key = xmlutil::evaluate(&doc, "//xml/EventKey/text()").string();
+        let ticket = xmlutil::evaluate(&doc, "//xml/Ticket/text()").string();
         SubscribeEvent {
             source: source,
             target: target,
             id: id,
             time: time,
             create_time: NaiveDateTime::from_timestamp(time, 0),
+            event_key,
+            ticket,
             event: "subscribe".to_owned(),
             raw: xml.to_owned(),
         }
@@ -57,5 +73,6 @@ mod tests {
         assert_eq!("toUser", &msg.target);
         assert_eq!("subscribe", &msg.event);
         assert_eq!(123456789, msg.time);
+        assert_eq!(None, msg.scan_scene());
     }
 }
\ No newline at end of file
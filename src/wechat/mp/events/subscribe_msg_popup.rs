@@ -0,0 +1,119 @@
+use chrono::NaiveDateTime;
+
+use crate::wechat::mp::messages::MessageParser;
+use crate::xmlutil;
+
+/// 订阅消息弹窗中用户对单个模板的操作
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SubscribeMsgPopupItem {
+    /// 模板id
+    pub template_id: String,
+    /// 用户点击行为，accept：同意，reject：拒绝
+    pub subscribe_status: String,
+    /// 弹框场景，0：在h5页面中，1：在图文消息中（公众号），小程序中为0
+    pub popup_scene: i32,
+}
+
+impl SubscribeMsgPopupItem {
+    pub fn is_accepted(&self) -> bool {
+        self.subscribe_status == "accept"
+    }
+}
+
+/// <pre>
+/// 用户操作订阅通知弹窗事件
+/// 一次弹窗可以包含多个模板，每个模板对应一个List节点
+/// </pre>
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SubscribeMsgPopupEvent {
+    pub source: String,
+    pub target: String,
+    pub time: i64,
+    pub create_time: NaiveDateTime,
+    pub items: Vec<SubscribeMsgPopupItem>,
+    pub event: String,
+    pub raw: String,
+}
+
+impl SubscribeMsgPopupEvent {
+    /// 用户同意订阅的模板id
+    pub fn accepted_template_ids(&self) -> Vec<String> {
+        self.items.iter().filter(|item| item.is_accepted()).map(|item| item.template_id.to_owned()).collect()
+    }
+
+    /// 用户拒绝订阅的模板id
+    pub fn rejected_template_ids(&self) -> Vec<String> {
+        self.items.iter().filter(|item| !item.is_accepted()).map(|item| item.template_id.to_owned()).collect()
+    }
+}
+
+impl MessageParser for SubscribeMsgPopupEvent {
+    type WechatMessage = SubscribeMsgPopupEvent;
+
+    #[inline]
+    fn from_xml(xml: &str) -> SubscribeMsgPopupEvent {
+        let package = xmlutil::parse(xml);
+        let doc = package.as_document();
+        let source = xmlutil::evaluate(&doc, "//xml/FromUserName/text()").string();
+        let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
+        let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
+        let count = xmlutil::evaluate(&doc, "count(//xml/SubscribeMsgPopupEvent/List)").number() as usize;
+        let items = (1..=count).map(|i| {
+            let list = format!("//xml/SubscribeMsgPopupEvent/List[{}]", i);
+            SubscribeMsgPopupItem {
+                template_id: xmlutil::evaluate(&doc, format!("{}/TemplateId/text()", list)).string(),
+                subscribe_status: xmlutil::evaluate(&doc, format!("{}/SubscribeStatusString/text()", list)).string(),
+                popup_scene: xmlutil::evaluate(&doc, format!("{}/PopupScene/text()", list)).number() as i32,
+            }
+        }).collect();
+        SubscribeMsgPopupEvent {
+            source,
+            target,
+            time,
+            create_time: NaiveDateTime::from_timestamp(time, 0),
+            items,
+            event: "subscribe_msg_popup_event".to_owned(),
+            raw: xml.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wechat::mp::messages::MessageParser;
+    use super::SubscribeMsgPopupEvent;
+
+    #[test]
+    fn test_from_xml() {
+        let xml = "<xml>
+        <ToUserName><![CDATA[gh_123456789abc]]></ToUserName>
+        <FromUserName><![CDATA[otFpruAK8D-E6EfStSYonYSBZ8_4]]></FromUserName>
+        <CreateTime>1610969440</CreateTime>
+        <MsgType><![CDATA[event]]></MsgType>
+        <Event><![CDATA[subscribe_msg_popup_event]]></Event>
+        <SubscribeMsgPopupEvent>
+            <List>
+                <TemplateId><![CDATA[VRR0UEO9VJOLs0MHlU0OilqX6MVFDwH3_3gz3Oc0NIc]]></TemplateId>
+                <SubscribeStatusString><![CDATA[accept]]></SubscribeStatusString>
+                <PopupScene>2</PopupScene>
+            </List>
+            <List>
+                <TemplateId><![CDATA[9nLIlbOQZC5Y89AZteFEux3WCXRRRG5Wfzkpssu4bLI]]></TemplateId>
+                <SubscribeStatusString><![CDATA[reject]]></SubscribeStatusString>
+                <PopupScene>2</PopupScene>
+            </List>
+        </SubscribeMsgPopupEvent>
+        </xml>";
+        let msg = SubscribeMsgPopupEvent::from_xml(xml);
+
+        assert_eq!("otFpruAK8D-E6EfStSYonYSBZ8_4", &msg.source);
+        assert_eq!("gh_123456789abc", &msg.target);
+        assert_eq!(1610969440, msg.time);
+        assert_eq!(2, msg.items.len());
+        assert_eq!("VRR0UEO9VJOLs0MHlU0OilqX6MVFDwH3_3gz3Oc0NIc", &msg.items[0].template_id);
+        assert_eq!("accept", &msg.items[0].subscribe_status);
+        assert_eq!(2, msg.items[1].popup_scene);
+        assert_eq!(vec!["VRR0UEO9VJOLs0MHlU0OilqX6MVFDwH3_3gz3Oc0NIc".to_string()], msg.accepted_template_ids());
+        assert_eq!(vec!["9nLIlbOQZC5Y89AZteFEux3WCXRRRG5Wfzkpssu4bLI".to_string()], msg.rejected_template_ids());
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::NaiveDateTime;
 
 use crate::wechat::mp::messages::MessageParser;
+use crate::wechat::mp::events::qrscene;
 use crate::xmlutil;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -10,12 +11,20 @@ pub struct SubscribeScanEvent {
     pub time: i64,
     pub create_time: NaiveDateTime,
     pub id: i64,
+    /// 二维码参数（已去掉`qrscene_`前缀）
     pub scene_id: String,
     pub ticket: String,
     pub event: String,
     pub raw: String,
 }
 
+impl SubscribeScanEvent {
+    /// 二维码场景值（已去掉`qrscene_`前缀）
+    pub fn scan_scene(&self) -> Option<String> {
+        qrscene(&self.scene_id)
+    }
+}
+
 impl MessageParser for SubscribeScanEvent {
     type WechatMessage = SubscribeScanEvent;
 
@@ -35,7 +44,7 @@ impl MessageParser for SubscribeScanEvent {
             id: id,
             time: time,
             create_time: NaiveDateTime::from_timestamp(time, 0),
-            scene_id: scene_id.replace("qrscene_", ""),
+            scene_id: qrscene(&scene_id).unwrap_or_default(),
             ticket: ticket,
             event: "subscribe_scan".to_owned(),
             raw: xml.to_owned(),
@@ -3,13 +3,49 @@ use chrono::NaiveDateTime;
 use crate::wechat::mp::messages::MessageParser;
 use crate::xmlutil;
 
+/// 模板消息发送结果
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum TemplateSendStatus {
+    /// success：送达成功
+    Success,
+    /// failed:user block：用户拒收（关闭了接收该公众号的模板消息）
+    UserBlock,
+    /// failed: system failed：其他原因失败
+    SystemFailed,
+    Other(String),
+}
+
+impl TemplateSendStatus {
+    pub fn from_status(status: &str) -> TemplateSendStatus {
+        // 文档中的写法不统一（如`failed: system failed`），冒号后的空格不参与比较
+        match status.replace(' ', "").to_lowercase().as_str() {
+            "success" => TemplateSendStatus::Success,
+            "failed:userblock" => TemplateSendStatus::UserBlock,
+            "failed:systemfailed" => TemplateSendStatus::SystemFailed,
+            _ => TemplateSendStatus::Other(status.to_owned()),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, TemplateSendStatus::Success)
+    }
+}
+
+/// <pre>
+/// 模板消息发送任务完成事件
+/// id为发送模板消息时返回的msgid，可用来关联发送记录
+/// </pre>
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct TemplateSendJobFinishEvent {
     pub source: String,
     pub target: String,
     pub time: i64,
     pub create_time: NaiveDateTime,
+    /// 模板消息的msgid
     pub id: i64,
+    /// 原始的Status，如`success`、`failed:user block`、`failed: system failed`
+    pub status: String,
+    pub send_status: TemplateSendStatus,
     pub event: String,
     pub raw: String,
 }
@@ -23,14 +59,18 @@ impl MessageParser for TemplateSendJobFinishEvent {
         let doc = package.as_document();
         let source = xmlutil::evaluate(&doc, "//xml/FromUserName/text()").string();
         let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
-        let id = xmlutil::evaluate(&doc, "//xml/MsgId/text()").number() as i64;
+        // 推送中为MsgID，兼容MsgId的写法
+        let id = xmlutil::evaluate(&doc, "//xml/MsgID/text() | //xml/MsgId/text()").number() as i64;
         let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
+        let status = xmlutil::evaluate(&doc, "//xml/Status/text()").string();
         TemplateSendJobFinishEvent {
-            source: source,
-            target: target,
-            id: id,
-            time: time,
+            source,
+            target,
+            id,
+            time,
             create_time: NaiveDateTime::from_timestamp(time, 0),
+            send_status: TemplateSendStatus::from_status(&status),
+            status,
             event: "templatesendjobfinish".to_owned(),
             raw: xml.to_owned(),
         }
@@ -39,25 +79,38 @@ impl MessageParser for TemplateSendJobFinishEvent {
 
 #[cfg(test)]
 mod tests {
-    use crate::events::TemplateSendJobFinishEvent;
-    use crate::wechat::{messages::MessageParser};
-    use super::UnsubscribeEvent;
+    use crate::wechat::mp::messages::MessageParser;
+    use super::{TemplateSendJobFinishEvent, TemplateSendStatus};
 
-    #[test]
-    fn test_from_xml() {
-        let xml = "<xml><ToUserName><![CDATA[ToUserName]]></ToUserName>
-        <FromUserName><![CDATA[FromUserName]]></FromUserName>
-        <CreateTime>1661061510</CreateTime>
+    fn event_xml(status: &str) -> String {
+        format!("<xml><ToUserName><![CDATA[gh_7f083739789a]]></ToUserName>
+        <FromUserName><![CDATA[oia2TjuEGTNoeX76QEjQNrcURxG8]]></FromUserName>
+        <CreateTime>1395658920</CreateTime>
         <MsgType><![CDATA[event]]></MsgType>
         <Event><![CDATA[TEMPLATESENDJOBFINISH]]></Event>
-        <MsgID>MsgID</MsgID>
-        <Status><![CDATA[success]]></Status>
-        </xml>";
-        let msg = TemplateSendJobFinishEvent::from_xml(xml);
-
-        assert_eq!("fromUser", &msg.source);
-        assert_eq!("toUser", &msg.target);
-        assert_eq!("unsubscribe", &msg.event);
-        assert_eq!(123456789, msg.time);
+        <MsgID>200163836</MsgID>
+        <Status><![CDATA[{}]]></Status>
+        </xml>", status)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_from_xml() {
+        let msg = TemplateSendJobFinishEvent::from_xml(&event_xml("success"));
+
+        assert_eq!("oia2TjuEGTNoeX76QEjQNrcURxG8", &msg.source);
+        assert_eq!("gh_7f083739789a", &msg.target);
+        assert_eq!("templatesendjobfinish", &msg.event);
+        assert_eq!(1395658920, msg.time);
+        assert_eq!(200163836, msg.id);
+        assert_eq!(TemplateSendStatus::Success, msg.send_status);
+        assert!(msg.send_status.is_success());
+
+        let msg = TemplateSendJobFinishEvent::from_xml(&event_xml("failed:user block"));
+        assert_eq!(TemplateSendStatus::UserBlock, msg.send_status);
+        assert_eq!("failed:user block", &msg.status);
+        let msg = TemplateSendJobFinishEvent::from_xml(&event_xml("failed: system failed"));
+        assert_eq!(TemplateSendStatus::SystemFailed, msg.send_status);
+        let msg = TemplateSendJobFinishEvent::from_xml(&event_xml("unknown"));
+        assert_eq!(TemplateSendStatus::Other("unknown".to_string()), msg.send_status);
+    }
+}
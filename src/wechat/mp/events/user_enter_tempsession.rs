@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+
+use crate::wechat::mp::messages::MessageParser;
+use crate::xmlutil;
+
+/// 用户进入小程序客服会话事件
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct UserEnterTempSessionEvent {
+    pub source: String,
+    pub target: String,
+    pub time: i64,
+    pub create_time: NaiveDateTime,
+    pub id: i64,
+    /// 开发者在客服会话按钮设置的session-from属性
+    pub session_from: String,
+    pub event: String,
+    pub raw: String,
+}
+
+impl MessageParser for UserEnterTempSessionEvent {
+    type WechatMessage = UserEnterTempSessionEvent;
+
+    #[inline]
+    fn from_xml(xml: &str) -> UserEnterTempSessionEvent {
+        let package = xmlutil::parse(xml);
+        let doc = package.as_document();
+        let source = xmlutil::evaluate(&doc, "//xml/FromUserName/text()").string();
+        let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
+        let id = xmlutil::evaluate(&doc, "//xml/MsgId/text()").number() as i64;
+        let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
+        let session_from = xmlutil::evaluate(&doc, "//xml/SessionFrom/text()").string();
+        UserEnterTempSessionEvent {
+            source,
+            target,
+            id,
+            time,
+            create_time: NaiveDateTime::from_timestamp(time, 0),
+            session_from,
+            event: "user_enter_tempsession".to_owned(),
+            raw: xml.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wechat::mp::messages::MessageParser;
+    use super::UserEnterTempSessionEvent;
+
+    #[test]
+    fn test_from_xml() {
+        let xml = "<xml>\
+        <ToUserName><![CDATA[toUser]]></ToUserName>\
+        <FromUserName><![CDATA[fromUser]]></FromUserName>\
+        <CreateTime>1482048670</CreateTime>\
+        <MsgType><![CDATA[event]]></MsgType>\
+        <Event><![CDATA[user_enter_tempsession]]></Event>\
+        <SessionFrom><![CDATA[sessionFrom]]></SessionFrom>\
+        </xml>";
+        let msg = UserEnterTempSessionEvent::from_xml(xml);
+
+        assert_eq!("fromUser", &msg.source);
+        assert_eq!("toUser", &msg.target);
+        assert_eq!("user_enter_tempsession", &msg.event);
+        assert_eq!("sessionFrom", &msg.session_from);
+        assert_eq!(1482048670, msg.time);
+    }
+}
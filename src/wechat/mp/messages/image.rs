@@ -44,7 +44,6 @@ impl MessageParser for ImageMessage {
 #[cfg(test)]
 mod tests {
     use crate::wechat::{messages::MessageParser};
-    use crate::wechat::mp::messages::MessageParser;
     use super::ImageMessage;
 
     #[test]
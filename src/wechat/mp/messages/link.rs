@@ -47,7 +47,6 @@ impl MessageParser for LinkMessage {
 #[cfg(test)]
 mod tests {
     use crate::wechat::{messages::MessageParser};
-    use crate::wechat::mp::messages::MessageParser;
     use super::LinkMessage;
 
     #[test]
@@ -53,7 +53,6 @@ impl MessageParser for LocationMessage {
 #[cfg(test)]
 mod tests {
     use crate::wechat::{messages::MessageParser};
-    use crate::wechat::mp::messages::MessageParser;
     use super::LocationMessage;
 
     #[test]
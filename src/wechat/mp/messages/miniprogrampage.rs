@@ -0,0 +1,87 @@
+use chrono::NaiveDateTime;
+
+use crate::wechat::mp::messages::MessageParser;
+use crate::xmlutil;
+
+/// 小程序客服消息中用户发送的小程序卡片
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MiniProgramPageMessage {
+    pub source: String,
+    pub target: String,
+    pub time: i64,
+    pub create_time: NaiveDateTime,
+    pub id: i64,
+    pub title: String,
+    pub app_id: String,
+    pub page_path: String,
+    /// 封面图片在微信服务器上的地址
+    pub thumb_url: String,
+    /// 封面图片的临时素材media_id
+    pub thumb_media_id: String,
+    pub raw: String,
+}
+
+impl MessageParser for MiniProgramPageMessage {
+    type WechatMessage = MiniProgramPageMessage;
+
+    #[inline]
+    fn from_xml(xml: &str) -> MiniProgramPageMessage {
+        let package = xmlutil::parse(xml);
+        let doc = package.as_document();
+        let source = xmlutil::evaluate(&doc, "//xml/FromUserName/text()").string();
+        let target = xmlutil::evaluate(&doc, "//xml/ToUserName/text()").string();
+        let id = xmlutil::evaluate(&doc, "//xml/MsgId/text()").number() as i64;
+        let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
+        let title = xmlutil::evaluate(&doc, "//xml/Title/text()").string();
+        let app_id = xmlutil::evaluate(&doc, "//xml/AppId/text()").string();
+        let page_path = xmlutil::evaluate(&doc, "//xml/PagePath/text()").string();
+        let thumb_url = xmlutil::evaluate(&doc, "//xml/ThumbUrl/text()").string();
+        let thumb_media_id = xmlutil::evaluate(&doc, "//xml/ThumbMediaId/text()").string();
+        MiniProgramPageMessage {
+            source,
+            target,
+            id,
+            time,
+            create_time: NaiveDateTime::from_timestamp(time, 0),
+            title,
+            app_id,
+            page_path,
+            thumb_url,
+            thumb_media_id,
+            raw: xml.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wechat::mp::messages::MessageParser;
+    use super::MiniProgramPageMessage;
+
+    #[test]
+    fn test_from_xml() {
+        let xml = "<xml>\
+        <ToUserName><![CDATA[toUser]]></ToUserName>\
+        <FromUserName><![CDATA[fromUser]]></FromUserName>\
+        <CreateTime>1482048670</CreateTime>\
+        <MsgType><![CDATA[miniprogrampage]]></MsgType>\
+        <MsgId>1234567890123456</MsgId>\
+        <Title><![CDATA[标题]]></Title>\
+        <AppId><![CDATA[wx1234567890]]></AppId>\
+        <PagePath><![CDATA[pages/index/index?id=1]]></PagePath>\
+        <ThumbUrl><![CDATA[http://mmbiz.qpic.cn/thumb]]></ThumbUrl>\
+        <ThumbMediaId><![CDATA[MEDIA_ID]]></ThumbMediaId>\
+        </xml>";
+        let msg = MiniProgramPageMessage::from_xml(xml);
+
+        assert_eq!("fromUser", &msg.source);
+        assert_eq!("toUser", &msg.target);
+        assert_eq!(1234567890123456, msg.id);
+        assert_eq!(1482048670, msg.time);
+        assert_eq!("标题", &msg.title);
+        assert_eq!("wx1234567890", &msg.app_id);
+        assert_eq!("pages/index/index?id=1", &msg.page_path);
+        assert_eq!("http://mmbiz.qpic.cn/thumb", &msg.thumb_url);
+        assert_eq!("MEDIA_ID", &msg.thumb_media_id);
+    }
+}
@@ -11,9 +11,10 @@ mod shortvideo;
 mod video;
 mod location;
 mod link;
+mod miniprogrampage;
 mod unknown;
 
-use crate::parse_message;
+use crate::{parse_json_message, parse_message, LabradorResult};
 // export Message types
 pub use self::text::TextMessage;
 pub use self::image::ImageMessage;
@@ -22,6 +23,7 @@ pub use self::shortvideo::ShortVideoMessage;
 pub use self::video::VideoMessage;
 pub use self::location::LocationMessage;
 pub use self::link::LinkMessage;
+pub use self::miniprogrampage::MiniProgramPageMessage;
 pub use self::unknown::UnknownMessage;
 
 // export Event types
@@ -34,6 +36,9 @@ pub use super::events::ClickEvent;
 pub use super::events::ViewEvent;
 pub use super::events::QualificationVerifySuccessEvent;
 pub use super::events::TemplateSendJobFinishEvent;
+pub use super::events::MassSendJobFinishEvent;
+pub use super::events::UserEnterTempSessionEvent;
+pub use super::events::SubscribeMsgPopupEvent;
 
 // an enum or messages and events
 #[allow(unused)]
@@ -46,16 +51,20 @@ pub enum Message {
     VideoMessage(VideoMessage),
     LocationMessage(LocationMessage),
     LinkMessage(LinkMessage),
+    MiniProgramPageMessage(MiniProgramPageMessage),
     UnknownMessage(UnknownMessage),
     SubscribeEvent(SubscribeEvent),
     UnsubscribeEvent(UnsubscribeEvent),
     TemplateSendJobFinishEvent(TemplateSendJobFinishEvent),
+    MassSendJobFinishEvent(MassSendJobFinishEvent),
     ScanEvent(ScanEvent),
     SubscribeScanEvent(SubscribeScanEvent),
     LocationEvent(LocationEvent),
     ClickEvent(ClickEvent),
     ViewEvent(ViewEvent),
     QualificationVerifySuccessEvent(QualificationVerifySuccessEvent),
+    UserEnterTempSessionEvent(UserEnterTempSessionEvent),
+    SubscribeMsgPopupEvent(SubscribeMsgPopupEvent),
 }
 
 #[allow(unused)]
@@ -64,6 +73,11 @@ impl Message {
         parse_message(xml.as_ref())
     }
 
+    /// 解析JSON格式的推送（小程序消息推送可选择JSON数据格式）
+    pub fn parse_json<S: AsRef<str>>(json: S) -> LabradorResult<Message> {
+        parse_json_message(json.as_ref())
+    }
+
     pub fn get_source(&self) -> String {
         match *self {
             Message::TextMessage(ref msg) => msg.source.to_owned(),
@@ -73,6 +87,7 @@ impl Message {
             Message::VideoMessage(ref msg) => msg.source.to_owned(),
             Message::LocationMessage(ref msg) => msg.source.to_owned(),
             Message::LinkMessage(ref msg) => msg.source.to_owned(),
+            Message::MiniProgramPageMessage(ref msg) => msg.source.to_owned(),
             Message::UnknownMessage(ref msg) => msg.source.to_owned(),
             Message::SubscribeEvent(ref msg) => msg.source.to_owned(),
             Message::UnsubscribeEvent(ref msg) => msg.source.to_owned(),
@@ -82,7 +97,10 @@ impl Message {
             Message::ClickEvent(ref msg) => msg.source.to_owned(),
             Message::ViewEvent(ref msg) => msg.source.to_owned(),
             Message::TemplateSendJobFinishEvent(ref msg) => msg.source.to_owned(),
+            Message::MassSendJobFinishEvent(ref msg) => msg.source.to_owned(),
             Message::QualificationVerifySuccessEvent(ref msg) => msg.source.to_owned(),
+            Message::UserEnterTempSessionEvent(ref msg) => msg.source.to_owned(),
+            Message::SubscribeMsgPopupEvent(ref msg) => msg.source.to_owned(),
         }
     }
 
@@ -95,16 +113,54 @@ impl Message {
             Message::VideoMessage(ref msg) => msg.target.to_owned(),
             Message::LocationMessage(ref msg) => msg.target.to_owned(),
             Message::LinkMessage(ref msg) => msg.target.to_owned(),
+            Message::MiniProgramPageMessage(ref msg) => msg.target.to_owned(),
             Message::UnknownMessage(ref msg) => msg.target.to_owned(),
             Message::SubscribeEvent(ref msg) => msg.target.to_owned(),
             Message::UnsubscribeEvent(ref msg) => msg.target.to_owned(),
             Message::SubscribeScanEvent(ref msg) => msg.target.to_owned(),
             Message::TemplateSendJobFinishEvent(ref msg) => msg.target.to_owned(),
+            Message::MassSendJobFinishEvent(ref msg) => msg.target.to_owned(),
             Message::ScanEvent(ref msg) => msg.target.to_owned(),
             Message::LocationEvent(ref msg) => msg.target.to_owned(),
             Message::ClickEvent(ref msg) => msg.target.to_owned(),
             Message::ViewEvent(ref msg) => msg.target.to_owned(),
             Message::QualificationVerifySuccessEvent(ref msg) => msg.target.to_owned(),
+            Message::UserEnterTempSessionEvent(ref msg) => msg.target.to_owned(),
+            Message::SubscribeMsgPopupEvent(ref msg) => msg.target.to_owned(),
+        }
+    }
+
+    /// 二维码场景值，关注（带参二维码）与扫码事件已统一去掉`qrscene_`前缀，其它消息为None
+    pub fn scan_scene(&self) -> Option<String> {
+        match *self {
+            Message::SubscribeEvent(ref msg) => msg.scan_scene(),
+            Message::SubscribeScanEvent(ref msg) => msg.scan_scene(),
+            Message::ScanEvent(ref msg) => msg.scan_scene(),
+            _ => None,
+        }
+    }
+
+    /// 二维码的ticket，可用来换取二维码图片，非扫码相关事件为None
+    pub fn ticket(&self) -> Option<String> {
+        let ticket = match *self {
+            Message::SubscribeEvent(ref msg) => msg.ticket.to_owned(),
+            Message::SubscribeScanEvent(ref msg) => msg.ticket.to_owned(),
+            Message::ScanEvent(ref msg) => msg.ticket.to_owned(),
+            _ => String::default(),
+        };
+        if ticket.is_empty() {
+            None
+        } else {
+            Some(ticket)
+        }
+    }
+
+    /// 模板消息、群发任务完成事件对应的msgid，与发送接口返回的msgid一致，其它消息为None
+    pub fn send_msg_id(&self) -> Option<i64> {
+        match *self {
+            Message::TemplateSendJobFinishEvent(ref msg) => Some(msg.id),
+            Message::MassSendJobFinishEvent(ref msg) => Some(msg.id),
+            _ => None,
         }
     }
 }
@@ -45,7 +45,6 @@ impl MessageParser for ShortVideoMessage {
 #[cfg(test)]
 mod tests {
     use crate::wechat::{messages::MessageParser};
-    use crate::wechat::mp::messages::MessageParser;
     use super::ShortVideoMessage;
 
     #[test]
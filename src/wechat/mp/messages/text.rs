@@ -12,9 +12,18 @@ pub struct TextMessage {
     pub create_time: NaiveDateTime,
     pub id: i64,
     pub content: String,
+    /// 用户点击客服菜单消息的选项时推送，为选项的ID，此时content为选项内容
+    pub biz_msg_menu_id: Option<String>,
     pub raw: String,
 }
 
+impl TextMessage {
+    /// 点击客服菜单消息产生的文本消息，返回选中的选项ID与内容，用户直接输入的文本返回None
+    pub fn menu_selection(&self) -> Option<(&str, &str)> {
+        self.biz_msg_menu_id.as_deref().map(|id| (id, self.content.as_str()))
+    }
+}
+
 impl MessageParser for TextMessage {
     type WechatMessage = TextMessage;
 
@@ -27,6 +36,7 @@ impl MessageParser for TextMessage {
         let id = xmlutil::evaluate(&doc, "//xml/MsgId/text()").number() as i64;
         let time = xmlutil::evaluate(&doc, "//xml/CreateTime/text()").number() as i64;
         let content = xmlutil::evaluate(&doc, "//xml/Content/text()").string();
+        let biz_msg_menu_id = xmlutil::evaluate(&doc, "//xml/bizmsgmenuid/text()").string();
         TextMessage {
             source: source,
             target: target,
@@ -34,6 +44,7 @@ impl MessageParser for TextMessage {
             time: time,
             create_time: NaiveDateTime::from_timestamp(time, 0),
             content: content,
+            biz_msg_menu_id: Some(biz_msg_menu_id).filter(|v| !v.is_empty()),
             raw: xml.to_owned(),
         }
     }
@@ -61,5 +72,22 @@ mod tests {
         assert_eq!(1234567890123456, msg.id);
         assert_eq!(1348831860, msg.time);
         assert_eq!("this is a test", &msg.content);
+        assert_eq!(msg.menu_selection(), None);
+    }
+
+    #[test]
+    fn test_menu_reply_from_xml() {
+        let xml = "<xml>\
+        <ToUserName><![CDATA[toUser]]></ToUserName>\
+        <FromUserName><![CDATA[fromUser]]></FromUserName>\
+        <CreateTime>1500000000</CreateTime>\
+        <MsgType><![CDATA[text]]></MsgType>\
+        <Content><![CDATA[满意]]></Content>\
+        <MsgId>1234567890123456</MsgId>\
+        <bizmsgmenuid>101</bizmsgmenuid>\
+        </xml>";
+        let msg = TextMessage::from_xml(xml);
+        assert_eq!(msg.biz_msg_menu_id.as_deref(), Some("101"));
+        assert_eq!(msg.menu_selection(), Some(("101", "满意")));
     }
 }
@@ -1,4 +1,4 @@
-use crate::RequestMethod;
+use crate::{AuthStyle, RequestMethod};
 
 #[allow(unused)]
 #[derive(Debug, PartialEq, Clone)]
@@ -8,11 +8,15 @@ pub enum WechatMpMethod {
     /// 短key托管(生成短key的url)
     GenShortenUrl,
     GetCallbackIp,
+    /// 获取微信API接口的IP地址
+    GetApiDomainIp,
     QrConnectUrl,
     /// 获得各种类型的ticket
     GetTicket,
     /// 短key解析(解析短key的url)
     FetchShortenUrl,
+    /// 获取公众号的自动回复规则
+    GetCurrentAutoreplyInfo,
     Oauth2(Oauth2Method),
     /// codesession
     CodeSession,
@@ -38,8 +42,22 @@ pub enum WechatMpMethod {
     QrCode(MpQrCodeMethod),
     /// 媒体文件
     Media(MpMediaMethod),
+    /// 数据统计
+    DataCube(MpDataCubeMethod),
+    /// 智能接口
+    Ai(MpAiMethod),
+    /// 群发消息
+    MassMessage(MpMassMessageMethod),
+    /// 门店
+    Poi(MpPoiMethod),
+    /// 一物一码
+    MarketCode(MpMarketCodeMethod),
+    /// 导购助手
+    Guide(MpGuideMethod),
     /// 自定义方法
-    Custom(String)
+    Custom(String),
+    /// 自定义方法，并指定接口调用凭证的传递方式
+    CustomWithAuth { path: String, auth_style: AuthStyle },
 }
 
 
@@ -171,6 +189,41 @@ pub enum MpWifiMethod {
     UpdateShop,
 }
 
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MpDataCubeMethod {
+    GetUserSummary,
+    GetUserCumulate,
+    GetArticleSummary,
+    GetArticleTotal,
+    GetUserRead,
+    GetInterfaceSummary,
+}
+
+#[allow(unused)]
+impl MpDataCubeMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MpDataCubeMethod::GetUserSummary => String::from("/datacube/getusersummary"),
+            MpDataCubeMethod::GetUserCumulate => String::from("/datacube/getusercumulate"),
+            MpDataCubeMethod::GetArticleSummary => String::from("/datacube/getarticlesummary"),
+            MpDataCubeMethod::GetArticleTotal => String::from("/datacube/getarticletotal"),
+            MpDataCubeMethod::GetUserRead => String::from("/datacube/getuserread"),
+            MpDataCubeMethod::GetInterfaceSummary => String::from("/datacube/getinterfacesummary"),
+        }
+    }
+
+    /// 单次查询允许的最大时间跨度（天）
+    pub fn max_days(&self) -> i64 {
+        match *self {
+            MpDataCubeMethod::GetUserSummary | MpDataCubeMethod::GetUserCumulate => 7,
+            MpDataCubeMethod::GetArticleSummary | MpDataCubeMethod::GetArticleTotal => 1,
+            MpDataCubeMethod::GetUserRead => 3,
+            MpDataCubeMethod::GetInterfaceSummary => 30,
+        }
+    }
+}
+
 #[allow(unused)]
 impl MpWifiMethod {
     pub fn get_method(&self) -> String {
@@ -388,8 +441,10 @@ impl RequestMethod for WechatMpMethod {
             WechatMpMethod::AccessToken => String::from("/cgi-bin/token"),
             WechatMpMethod::GenShortenUrl => String::from("/cgi-bin/shorten/gen"),
             WechatMpMethod::FetchShortenUrl => String::from("/cgi-bin/shorten/fetch"),
+            WechatMpMethod::GetCurrentAutoreplyInfo => String::from("/cgi-bin/get_current_autoreply_info"),
             WechatMpMethod::GetTicket => String::from("/cgi-bin/ticket/getticket"),
             WechatMpMethod::GetCallbackIp => String::from("/cgi-bin/getcallbackip"),
+            WechatMpMethod::GetApiDomainIp => String::from("/cgi-bin/get_api_domain_ip"),
             WechatMpMethod::QrConnectUrl => String::from("/connect/qrconnect"),
             WechatMpMethod::Oauth2(v) => v.get_method(),
             WechatMpMethod::CustomService(v) => v.get_method(),
@@ -401,9 +456,24 @@ impl RequestMethod for WechatMpMethod {
             WechatMpMethod::QrCode(v) => v.get_method(),
             WechatMpMethod::Media(v) => v.get_method(),
             WechatMpMethod::Custom(v) => v.to_string(),
+            WechatMpMethod::CustomWithAuth { path, .. } => path.to_string(),
             WechatMpMethod::SubscribeMessage(v) => v.get_method(),
             WechatMpMethod::Ocr(v) => v.get_method(),
             WechatMpMethod::Card(v) => v.get_method(),
+            WechatMpMethod::DataCube(v) => v.get_method(),
+            WechatMpMethod::Ai(v) => v.get_method(),
+            WechatMpMethod::MassMessage(v) => v.get_method(),
+            WechatMpMethod::Poi(v) => v.get_method(),
+            WechatMpMethod::MarketCode(v) => v.get_method(),
+            WechatMpMethod::Guide(v) => v.get_method(),
+        }
+    }
+
+    fn auth_style(&self) -> AuthStyle {
+        match self {
+            WechatMpMethod::CodeSession | WechatMpMethod::AccessToken | WechatMpMethod::Oauth2(_)  => AuthStyle::None,
+            WechatMpMethod::CustomWithAuth { auth_style, .. } => *auth_style,
+            _ => AuthStyle::QueryParam,
         }
     }
 }
@@ -412,10 +482,7 @@ impl RequestMethod for WechatMpMethod {
 impl WechatMpMethod {
 
     pub fn need_token(&self) -> bool {
-        match self {
-            WechatMpMethod::CodeSession | WechatMpMethod::AccessToken | WechatMpMethod::Oauth2(_)  => false,
-            _ => true,
-        }
+        self.auth_style() != AuthStyle::None
     }
 }
 
@@ -486,3 +553,156 @@ impl MpQrCodeMethod {
         }
     }
 }
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MpAiMethod {
+    /// 微信翻译
+    Translate,
+    /// 提交语音
+    AddVoiceToRecoForText,
+    /// 获取语音识别结果
+    QueryRecoResultForText,
+}
+
+#[allow(unused)]
+impl MpAiMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MpAiMethod::Translate => String::from("/cgi-bin/media/voice/translatecontent"),
+            MpAiMethod::AddVoiceToRecoForText => String::from("/cgi-bin/media/voice/addvoicetorecofortext"),
+            MpAiMethod::QueryRecoResultForText => String::from("/cgi-bin/media/voice/queryrecoresultfortext"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MpMassMessageMethod {
+    /// 根据标签群发
+    SendAll,
+    /// 根据OpenID列表群发
+    Send,
+    /// 预览
+    Preview,
+    /// 查询群发状态
+    Get,
+    /// 删除群发
+    Delete,
+    /// 上传群发图文消息素材
+    UploadNews,
+}
+
+#[allow(unused)]
+impl MpMassMessageMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MpMassMessageMethod::SendAll => String::from("/cgi-bin/message/mass/sendall"),
+            MpMassMessageMethod::Send => String::from("/cgi-bin/message/mass/send"),
+            MpMassMessageMethod::Preview => String::from("/cgi-bin/message/mass/preview"),
+            MpMassMessageMethod::Get => String::from("/cgi-bin/message/mass/get"),
+            MpMassMessageMethod::Delete => String::from("/cgi-bin/message/mass/delete"),
+            MpMassMessageMethod::UploadNews => String::from("/cgi-bin/media/uploadnews"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MpPoiMethod {
+    /// 创建门店
+    AddPoi,
+    /// 查询门店信息
+    GetPoi,
+    /// 查询门店列表
+    GetPoiList,
+    /// 修改门店服务信息
+    UpdatePoi,
+    /// 删除门店
+    DelPoi,
+    /// 获取门店类目表
+    GetWxCategory,
+}
+
+#[allow(unused)]
+impl MpPoiMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MpPoiMethod::AddPoi => String::from("/cgi-bin/poi/addpoi"),
+            MpPoiMethod::GetPoi => String::from("/cgi-bin/poi/getpoi"),
+            MpPoiMethod::GetPoiList => String::from("/cgi-bin/poi/getpoilist"),
+            MpPoiMethod::UpdatePoi => String::from("/cgi-bin/poi/updatepoi"),
+            MpPoiMethod::DelPoi => String::from("/cgi-bin/poi/delpoi"),
+            MpPoiMethod::GetWxCategory => String::from("/cgi-bin/poi/getwxcategory"),
+        }
+    }
+}
+
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MpMarketCodeMethod {
+    /// 申请二维码
+    ApplyCode,
+    /// 查询二维码申请单
+    ApplyCodeQuery,
+    /// 下载二维码包
+    ApplyCodeDownload,
+    /// 激活二维码
+    CodeActive,
+    /// 查询二维码激活状态
+    CodeActiveQuery,
+    /// code_ticket换码
+    TicketToCode,
+}
+
+#[allow(unused)]
+impl MpMarketCodeMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MpMarketCodeMethod::ApplyCode => String::from("/intp/marketcode/applycode"),
+            MpMarketCodeMethod::ApplyCodeQuery => String::from("/intp/marketcode/applycodequery"),
+            MpMarketCodeMethod::ApplyCodeDownload => String::from("/intp/marketcode/applycodedownload"),
+            MpMarketCodeMethod::CodeActive => String::from("/intp/marketcode/codeactive"),
+            MpMarketCodeMethod::CodeActiveQuery => String::from("/intp/marketcode/codeactivequery"),
+            MpMarketCodeMethod::TicketToCode => String::from("/intp/marketcode/ticket2code"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MpGuideMethod {
+    /// 添加顾问
+    AddGuideAcct,
+    /// 为顾问分配客户
+    AddGuideBuyerRelation,
+    /// 获取顾问的客户列表
+    GetGuideBuyerRelationList,
+    /// 修改客户昵称
+    UpdateGuideBuyerRelation,
+    /// 新建标签类型
+    NewGuideTagOption,
+    /// 获取标签类型
+    GetGuideTagOption,
+    /// 为客户设置标签
+    AddGuideBuyerTag,
+}
+
+#[allow(unused)]
+impl MpGuideMethod {
+    pub fn get_method(&self) -> String {
+        match *self {
+            MpGuideMethod::AddGuideAcct => String::from("/cgi-bin/guide/addguideacct"),
+            MpGuideMethod::AddGuideBuyerRelation => String::from("/cgi-bin/guide/addguidebuyerrelation"),
+            MpGuideMethod::GetGuideBuyerRelationList => String::from("/cgi-bin/guide/getguidebuyerrelationlist"),
+            MpGuideMethod::UpdateGuideBuyerRelation => String::from("/cgi-bin/guide/updateguidebuyerrelation"),
+            MpGuideMethod::NewGuideTagOption => String::from("/cgi-bin/guide/newguidetagoption"),
+            MpGuideMethod::GetGuideTagOption => String::from("/cgi-bin/guide/getguidetagoption"),
+            MpGuideMethod::AddGuideBuyerTag => String::from("/cgi-bin/guide/addguidebuyertag"),
+        }
+    }
+}
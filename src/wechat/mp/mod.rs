@@ -1,4 +1,8 @@
-use crate::{session::SessionStore, client::APIClient, request::{Method, RequestType, LabraResponse, LabraRequest, RequestMethod}, WechatCrypto, util::current_timestamp, LabradorResult, SimpleStorage, WechatRequest, WechatCommonResponse, JsapiSignature, get_timestamp, get_nonce_str};
+use std::sync::{Arc, RwLock};
+
+use crate::{session::{SessionStore, DynSessionStore}, DnsOverrides, Resolve, MetricsRecorder, CachePolicy, TokenInfo, TokenRefreshHook, StoreFallbackPolicy, DebugRecorder, DebugRecord, SecretGeneration, LabraError, client::APIClient, request::{Method, RequestType, LabraResponse, LabraRequest, RequestMethod}, WechatCrypto, util::current_timestamp, LabradorResult, SimpleStorage, WechatRequest, WechatCommonResponse, WechatEnvelope, JsapiTicket, JsapiSignature, nonce_str};
+use crate::wechat::WECHAT_API_BASE_URL;
+use crate::wechat::token_cache::{TokenCache, TokenRequest};
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 use crate::wechat::mp::method::WechatMpMethod;
@@ -8,40 +12,85 @@ mod method;
 pub mod events;
 pub mod messages;
 pub mod replies;
+#[cfg(feature = "server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+mod server;
+#[cfg(feature = "axum-integration")]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum-integration")))]
+mod routes;
 #[allow(unused)]
 mod constants;
 
 pub use api::*;
+#[cfg(feature = "server")]
+pub use server::*;
+#[cfg(feature = "axum-integration")]
+pub use routes::*;
 use crate::wechat::mp::constants::{ACCESS_TOKEN, APPID, CLIENT_CREDENTIAL, GRANT_TYPE, SECRET, TICKET_TYPE, TICKET_TYPE_JSAPI, TICKET_TYPE_SDK, TICKET_TYPE_WXCARD};
 use crate::wechat::mp::method::WechatMpMethod::QrConnectUrl;
+use crate::serde_helper::{string_or_number, option_string_or_number};
 
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct WechatMpClient<T: SessionStore> {
+    inner: Arc<WechatMpClientInner<T>>,
+}
+
+/// 客户端配置与会话存储，克隆客户端时共享
+#[derive(Debug, Clone)]
+struct WechatMpClientInner<T: SessionStore> {
     appid: String,
-    secret: String,
+    /// 主备secret，克隆的客户端共享，轮换secret时整体替换
+    secrets: Arc<RwLock<WechatMpSecrets>>,
     token: Option<String>,
     template_id: Option<String>,
     aes_key: Option<String>,
+    /// 换取到新access_token时的回调
+    on_token_refresh: Option<TokenRefreshHook>,
+    tokens: TokenCache,
     client: APIClient<T>,
 }
 
+#[derive(Debug, Clone)]
+struct WechatMpSecrets {
+    primary: String,
+    secondary: Option<String>,
+    generation: SecretGeneration,
+}
+
+impl WechatMpSecrets {
+    fn current(&self) -> String {
+        match self.generation {
+            SecretGeneration::Secondary => self.secondary.to_owned().unwrap_or_else(|| self.primary.to_owned()),
+            SecretGeneration::Primary => self.primary.to_owned(),
+        }
+    }
+}
+
+/// 换取access_token时，表示secret无效、需要改用备用secret的错误码
+fn is_invalid_secret(err: &LabraError) -> bool {
+    matches!(err, LabraError::ClientError { errcode, .. } if errcode == "40001" || errcode == "40125")
+}
+
 
 #[allow(unused)]
 #[derive(Serialize, Deserialize)]
 pub struct AccessTokenResponse{
     pub access_token: String,
+    #[serde(with = "string_or_number")]
     pub expires_in: i64,
 }
 
 #[allow(unused)]
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WechatMpShortKeyResponse{
     /// 长信息
     pub long_data: Option<String>,
     /// 创建的时间戳
+    #[serde(default, with = "option_string_or_number")]
     pub create_time: Option<i64>,
     /// 剩余的过期秒数
+    #[serde(default, with = "option_string_or_number")]
     pub expire_seconds: Option<i64>,
 }
 
@@ -64,72 +113,271 @@ impl ToString for TicketType {
     }
 }
 
+impl WechatMpClient<DynSessionStore> {
+    /// <pre>
+    /// 使用运行时选择的会话存储创建客户端
+    /// 例如按配置在内存与Redis之间切换时，客户端类型保持为`WechatMpClient<DynSessionStore>`
+    /// </pre>
+    pub fn new_dyn<S: Into<String>>(appid: S, secret: S, session: DynSessionStore) -> WechatMpClient<DynSessionStore> {
+        Self::from_session(appid, secret, session)
+    }
+}
+
 #[allow(unused)]
 impl<T: SessionStore> WechatMpClient<T> {
 
     fn from_client(client: APIClient<T>) -> WechatMpClient<T> {
         WechatMpClient {
-            appid: client.app_key.to_owned(),
-            secret: client.secret.to_owned(),
-            token: None,
-            template_id: None,
-            aes_key: None,
-            client
+            inner: Arc::new(WechatMpClientInner {
+                appid: client.app_key.to_owned(),
+                secrets: Arc::new(RwLock::new(WechatMpSecrets {
+                    primary: client.secret.to_owned(),
+                    secondary: None,
+                    generation: SecretGeneration::Primary,
+                })),
+                token: None,
+                template_id: None,
+                aes_key: None,
+                on_token_refresh: None,
+                tokens: TokenCache::default(),
+                client
+            }),
         }
     }
 
     /// get the wechat client
     pub fn new<S: Into<String>>(appid: S, secret: S) -> WechatMpClient<SimpleStorage> {
-        let client = APIClient::<SimpleStorage>::from_session(appid.into(), secret.into(), "https://api.weixin.qq.com", SimpleStorage::new());
+        let client = APIClient::<SimpleStorage>::from_session(appid.into(), secret.into(), WECHAT_API_BASE_URL, SimpleStorage::new());
         WechatMpClient::from_client(client)
     }
 
     /// get the wechat client
     pub fn from_session<S: Into<String>>(appid: S, secret: S, session: T) -> WechatMpClient<T> {
-        let client = APIClient::from_session(appid.into(), secret.into(), "https://api.weixin.qq.com", session);
+        let client = APIClient::from_session(appid.into(), secret.into(), WECHAT_API_BASE_URL, session);
         Self::from_client(client)
     }
 
+    /// 替换接口域名（默认[`WECHAT_API_BASE_URL`]），如私有网关、就近接入域名
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().api_path(base_url);
+        self
+    }
+
+    /// 备用域名，连接失败时按顺序切换，如`WECHAT_API2_BASE_URL`
+    pub fn fallback_urls(mut self, fallback_urls: Vec<&str>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().fallback_paths(fallback_urls);
+        self
+    }
+
+    /// <pre>
+    /// 为接口域名指定固定IP，见[`DnsOverrides`]，TLS校验与Host请求头仍使用原域名
+    /// 使用官方公布的IP可直接调用`pin_official_ips`
+    /// </pre>
+    pub fn dns_overrides(mut self, overrides: DnsOverrides) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().dns_overrides(overrides);
+        self
+    }
+
+    /// 使用自定义的域名解析器
+    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().dns_resolver(resolver);
+        self
+    }
+
+    /// 请求指标记录，每次请求（含切换备用域名的重试）都会回调
+    pub fn metrics_recorder(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().metrics_recorder(metrics);
+        self
+    }
+
+    /// 开启请求调试记录，在内存中保留最近的请求/响应报文，URL中的凭证会被隐去
+    pub fn debug_recorder(mut self, debug: Arc<DebugRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().debug_recorder(debug);
+        self
+    }
+
+    /// 当前保留的请求调试记录，未开启时为空
+    pub fn debug_records(&self) -> Vec<DebugRecord> {
+        self.inner.client.debug_records()
+    }
+
+    /// <pre>
+    /// 添加GET接口的响应缓存策略，如用户详情、部门列表
+    /// 缓存写入会话存储，通过本crate调用对应的修改接口（如更新成员）时自动失效
+    /// </pre>
+    pub fn cache_policy(mut self, policy: CachePolicy) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().cache_policy(policy);
+        self
+    }
+
+    /// 不读取缓存的客户端，请求结果仍会刷新缓存，如`client.no_cache().user().get_by_id(..)`
+    pub fn no_cache(&self) -> Self {
+        let mut client = self.clone();
+        let inner = Arc::make_mut(&mut client.inner);
+        inner.client = inner.client.to_owned().no_cache();
+        client
+    }
+
+    /// 使前缀下的响应缓存失效，如`/cgi-bin/user/get?userid=zhangsan`、`/cgi-bin/department`
+    pub fn invalidate_cache_prefix(&self, key_prefix: &str) -> LabradorResult<()> {
+        self.inner.client.invalidate_cache_prefix(key_prefix)
+    }
+
+    /// <pre>
+    /// 备用secret，用于secret轮换期间的过渡
+    /// 主secret换取access_token返回40001、40125时，改用备用secret并记录，见`current_secret_generation`
+    /// </pre>
+    pub fn secondary_secret(self, secondary_secret: &str) -> Self {
+        if let Ok(mut secrets) = self.inner.secrets.write() {
+            secrets.secondary = secondary_secret.to_string().into();
+        }
+        self
+    }
+
+    /// 最近一次换取access_token成功所用的secret
+    pub fn current_secret_generation(&self) -> SecretGeneration {
+        self.inner.secrets.read().map(|secrets| secrets.generation).unwrap_or(SecretGeneration::Primary)
+    }
+
+    /// <pre>
+    /// 更换secret
+    /// 新secret作为主secret，原主secret作为备用secret，并使缓存的access_token失效，下次请求时重新获取
+    /// 所有克隆的客户端同时生效
+    /// </pre>
+    pub fn update_secret(&self, new_secret: &str) -> LabradorResult<()> {
+        {
+            let mut secrets = self.inner.secrets.write().map_err(|_| LabraError::RequestError("secret lock poisoned".to_string()))?;
+            let old = std::mem::replace(&mut secrets.primary, new_secret.to_string());
+            secrets.secondary = Some(old);
+            secrets.generation = SecretGeneration::Primary;
+        }
+        self.inner.tokens.invalidate();
+        self.inner.client.session().set(self.expires_key(), 0i64, None)
+    }
+
+    /// 当前使用的secret
+    pub(crate) fn secret(&self) -> String {
+        self.inner.secrets.read().map(|secrets| secrets.current()).unwrap_or_default()
+    }
+
+    fn expires_key(&self) -> String {
+        format!("{}_expires_at", self.inner.appid)
+    }
+
     pub fn aes_key(mut self, aes_key: &str) -> Self {
-        self.aes_key = aes_key.to_string().into();
+        Arc::make_mut(&mut self.inner).aes_key = aes_key.to_string().into();
         self
     }
 
     pub fn token(mut self, token: &str) -> Self {
-        self.token = token.to_string().into();
+        Arc::make_mut(&mut self.inner).token = token.to_string().into();
         self
     }
 
     pub fn template_id(mut self, template_id: &str) -> Self {
-        self.template_id = template_id.to_string().into();
+        Arc::make_mut(&mut self.inner).template_id = template_id.to_string().into();
+        self
+    }
+
+    /// <pre>
+    /// 换取到新access_token时回调，如主动推送给无法接入本库的旧系统
+    /// 回调在写入会话存储之后、不持有任何锁时调用，每次换取调用一次；请勿在回调中阻塞
+    /// </pre>
+    pub fn on_token_refresh<F>(mut self, callback: F) -> Self where F: Fn(&TokenInfo) + Send + Sync + 'static {
+        Arc::make_mut(&mut self.inner).on_token_refresh = TokenRefreshHook::new(callback).into();
         self
     }
 
+    /// <pre>
+    /// 会话存储（如Redis）不可用时降级：直接向微信换取access_token并缓存在进程内，定期重试会话存储
+    /// 未配置时读取会话存储出错直接返回错误
+    /// </pre>
+    pub fn store_fallback(mut self, policy: StoreFallbackPolicy) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.tokens = inner.tokens.with_policy(policy);
+        self
+    }
+
+    /// 会话存储是否可用，配置了`store_fallback`且读写出错时为false，重试成功后恢复为true
+    pub fn store_healthy(&self) -> bool {
+        self.inner.tokens.store_healthy()
+    }
+
     #[inline]
     pub async fn access_token(&self, force_refresh: bool) -> LabradorResult<String> {
-        let session = self.client.session();
-        let token_key = format!("{}_access_token", self.appid);
-        let expires_key = format!("{}_expires_at", self.appid);
-        let token: String = session.get(&token_key, Some("".to_owned()))?.unwrap_or_default();
-        let timestamp = current_timestamp();
-        let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
-        if expires_at <= timestamp || force_refresh {
-            let mut req = LabraRequest::<String>::new().url(WechatMpMethod::AccessToken.get_method()).params(vec![
-                (GRANT_TYPE.to_string(), CLIENT_CREDENTIAL.to_string()),
-                (APPID.to_string(), self.client.app_key.to_string()),
-                (SECRET.to_string(), self.client.secret.to_string()),
-            ]).method(Method::Get).req_type(RequestType::Json);
-            let res = self.client.request(req).await?.json::<AccessTokenResponse>()?;
-            let token = res.access_token;
-            let expires_in = res.expires_in;
-            // 预留200秒的时间
-            let expires_at = current_timestamp() + expires_in - 200;
-            session.set(&token_key, token.to_owned(), Some(expires_in as usize));
-            session.set(&expires_key, expires_at, Some(expires_in as usize));
-            Ok(token)
-        } else {
-            Ok(token)
+        self.token_info(force_refresh).await.map(|info| info.token)
+    }
+
+    /// <pre>
+    /// 获取access_token及其过期时间，缓存过期时会重新换取
+    /// 用于将access_token交给其他系统使用
+    /// </pre>
+    pub async fn access_token_info(&self) -> LabradorResult<TokenInfo> {
+        self.token_info(false).await
+    }
+
+    async fn token_info(&self, force_refresh: bool) -> LabradorResult<TokenInfo> {
+        let token_key = format!("{}_access_token", self.inner.appid);
+        let expires_key = self.expires_key();
+        let token_req = TokenRequest { session: self.inner.client.session(), token_key: &token_key, expires_key: &expires_key, appid: &self.inner.appid, metrics: self.inner.client.metrics() };
+        let (info, refreshed) = self.inner.tokens.token_info(&token_req, force_refresh, || async {
+            let secrets = self.inner.secrets.read().map_err(|_| LabraError::RequestError("secret lock poisoned".to_string()))?.clone();
+            let (res, generation) = match self.request_access_token(&secrets.primary).await {
+                Err(err) if is_invalid_secret(&err) && secrets.secondary.is_some() => {
+                    let secondary = secrets.secondary.to_owned().unwrap_or_default();
+                    (self.request_access_token(&secondary).await?, SecretGeneration::Secondary)
+                }
+                res => (res?, SecretGeneration::Primary),
+            };
+            if let Ok(mut current) = self.inner.secrets.write() {
+                // 换取期间secret已被更换时不覆盖
+                if current.primary == secrets.primary {
+                    current.generation = generation;
+                }
+            }
+            self.inner.client.metrics().record_secret_generation(&self.inner.appid, generation);
+            Ok((res.access_token, res.expires_in))
+        }).await?;
+        if refreshed {
+            TokenRefreshHook::notify(&self.inner.on_token_refresh, &info);
         }
+        Ok(info)
+    }
+
+    /// <pre>
+    /// 启动access_token后台刷新，需要在tokio运行时中调用
+    /// 在缓存的access_token过期前`margin`强制刷新并写入会话存储，刷新失败时退避重试；
+    /// 丢弃或abort返回的句柄即停止刷新，后台任务未能及时刷新时请求仍会自行刷新
+    /// </pre>
+    #[cfg(feature = "refresher")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "refresher")))]
+    pub fn spawn_token_refresher(&self, margin: std::time::Duration) -> crate::TokenRefreshHandle where T: Send + Sync + 'static {
+        let client = self.clone();
+        let expires_key = self.expires_key();
+        let session = self.inner.client.session().to_owned();
+        crate::wechat::refresher::spawn_token_refresher(self.inner.appid.to_owned(), margin, move || {
+            session.get::<_, i64>(&expires_key, Some(0)).map(Option::unwrap_or_default)
+        }, move || {
+            let client = client.clone();
+            async move { client.access_token(true).await }
+        })
+    }
+
+    async fn request_access_token(&self, secret: &str) -> LabradorResult<AccessTokenResponse> {
+        let req = LabraRequest::<String>::new().url(WechatMpMethod::AccessToken.get_method()).params(vec![
+            (GRANT_TYPE.to_string(), CLIENT_CREDENTIAL.to_string()),
+            (APPID.to_string(), self.inner.client.app_key.to_string()),
+            (SECRET.to_string(), secret.to_string()),
+        ]).method(Method::Get).req_type(RequestType::Json);
+        let v = self.inner.client.request(req).await?.json::<Value>()?;
+        WechatCommonResponse::parse::<AccessTokenResponse>(v)
     }
 
     /// <pre>
@@ -138,10 +386,7 @@ impl<T: SessionStore> WechatMpClient<T> {
     /// </pre>
     #[inline]
     pub async fn gen_shorten(&self, long_data: &str, expire_seconds: u64) -> LabradorResult<String> {
-        let res = self.post(WechatMpMethod::GenShortenUrl, vec![], json!({"long_data": long_data, "expire_seconds": expire_seconds}), RequestType::Json).await?.json::<Value>()?;
-        let v = WechatCommonResponse::parse::<Value>(res)?;
-        let short_key = v["short_key"].as_str().unwrap_or_default();
-        Ok(short_key.to_string())
+        self.short_key().gen(long_data, expire_seconds).await
     }
 
     /// <pre>
@@ -150,8 +395,7 @@ impl<T: SessionStore> WechatMpClient<T> {
     /// </pre>
     #[inline]
     pub async fn fetch_shorten(&self, short_key: &str) -> LabradorResult<WechatMpShortKeyResponse> {
-        let res = self.post(WechatMpMethod::GenShortenUrl, vec![], json!({"short_key": short_key}), RequestType::Json).await?.json::<Value>()?;
-        WechatCommonResponse::parse::<WechatMpShortKeyResponse>(res)
+        self.short_key().fetch(short_key).await
     }
 
     /// <pre>
@@ -170,17 +414,15 @@ impl<T: SessionStore> WechatMpClient<T> {
     /// </pre>
     #[inline]
     pub async fn get_ticket_force(&self, ticket_type: TicketType, force_refresh: bool) -> LabradorResult<String> {
-        let session = self.client.session();
-        let key = format!("{}_{}_ticket", self.appid, &ticket_type.to_string());
-        let expires_key = format!("{}_{}_ticket_expires_at", self.appid, &ticket_type.to_string());
+        let session = self.inner.client.session();
+        let key = format!("{}_{}_ticket", self.inner.appid, &ticket_type.to_string());
+        let expires_key = format!("{}_{}_ticket_expires_at", self.inner.appid, &ticket_type.to_string());
         let ticket: String = session.get(&key, Some("".to_owned()))?.unwrap_or_default();
         let timestamp = current_timestamp();
         let expires_at: i64 = session.get(&expires_key, Some(timestamp))?.unwrap_or_default();
         if expires_at <= timestamp || force_refresh {
             let res = self.get(WechatMpMethod::GetTicket, vec![(TICKET_TYPE.to_string(), ticket_type.to_string())], RequestType::Json).await?.json::<Value>()?;
-            let v = WechatCommonResponse::parse::<Value>(res)?;
-            let ticket = v["ticket"].as_str().unwrap_or_default();
-            let expires_in = v["expires_in"].as_i64().unwrap_or_default();
+            let JsapiTicket { ticket, expires_in } = WechatEnvelope::new().method(WechatMpMethod::GetTicket.get_method()).extract_value::<JsapiTicket>(res)?;
             // 预留200秒的时间
             let expires_at = current_timestamp() + expires_in - 200;
             session.set(&key, ticket.to_string(), Some(expires_in as usize));
@@ -198,14 +440,14 @@ impl<T: SessionStore> WechatMpClient<T> {
     /// 详情请见：<a href="http://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1421141115&token=&lang=zh_CN">链接</a>
     /// </pre>
     pub async fn create_jsapi_signature(&self, url: &str) -> LabradorResult<JsapiSignature> {
-        let timestamp = get_timestamp() / 1000;
-        let noncestr = get_nonce_str();
+        let timestamp = current_timestamp();
+        let noncestr = nonce_str();
         let jsapi_ticket = self.get_jsapi_ticket(false).await?;
         let signature = WechatCrypto::get_sha1_sign(&vec!["jsapi_ticket=".to_string() + &jsapi_ticket,
                                                           "noncestr=".to_string() + &noncestr,
                                                           "timestamp=".to_string() + &timestamp.to_string(),"url=".to_string() + &url].join("&"));
         Ok(JsapiSignature{
-            app_id: self.appid.to_string(),
+            app_id: self.inner.appid.to_string(),
             nonce_str: noncestr,
             url: url.to_string(),
             signature,
@@ -220,7 +462,7 @@ impl<T: SessionStore> WechatMpClient<T> {
     /// URL格式为https://open.weixin.qq.com/connect/qrconnect?appid=APPID&redirect_uri=REDIRECT_URI&response_type=code&scope=SCOPE&state=STATE#wechat_redirect
     /// </pre>
     pub async fn build_qr_connect_url(&self, redirect_url: &str, scope: &str, state: &str, ) -> LabradorResult<String> {
-        Ok(format!("{}?appid={}&redirect_uri={}&response_type=code&scope={}&state={}#wechat_redirect", QrConnectUrl.get_method(), self.appid.to_string(), urlencoding::encode(redirect_url), scope, state))
+        Ok(format!("{}?appid={}&redirect_uri={}&response_type=code&scope={}&state={}#wechat_redirect", QrConnectUrl.get_method(), self.inner.appid.to_string(), urlencoding::encode(redirect_url), scope, state))
     }
 
     ///
@@ -235,6 +477,63 @@ impl<T: SessionStore> WechatMpClient<T> {
         Ok(ip_list)
     }
 
+    ///
+    /// <pre>
+    /// 获取微信API接口的IP地址
+    /// [文档](https://developers.weixin.qq.com/doc/offiaccount/Basic_Information/Get_the_WeChat_server_IP_address.html)
+    /// </pre>
+    pub async fn get_api_domain_ip(&self) -> LabradorResult<Vec<String>> {
+        let v = self.get(WechatMpMethod::GetApiDomainIp, vec![], RequestType::Json).await?.json::<Value>()?;
+        let v = WechatCommonResponse::parse::<Value>(v)?;
+        let ip_list = v["ip_list"].as_array().unwrap_or(&vec![]).iter().map(|v| v.as_str().unwrap_or_default().to_string()).collect::<Vec<String>>();
+        Ok(ip_list)
+    }
+
+    /// <pre>
+    /// 将接口域名固定解析到官方公布的IP
+    /// 调用一次get_api_domain_ip，之后的请求只连接返回的IP，TLS校验与Host请求头仍使用原域名
+    /// 这次调用本身通过系统DNS（或已配置的`dns_overrides`）访问接口域名
+    /// 需要定期刷新时调用`refresh_official_ips`，或开启`refresher`特性后使用`spawn_official_ip_refresher`
+    /// </pre>
+    pub async fn pin_official_ips(self) -> LabradorResult<Self> {
+        let client = match self.inner.client.get_dns_overrides() {
+            Some(_) => self,
+            None => self.dns_overrides(DnsOverrides::new()),
+        };
+        client.refresh_official_ips().await?;
+        Ok(client)
+    }
+
+    /// <pre>
+    /// 重新获取官方IP并更新固定解析，下次请求时生效
+    /// 未返回可用的IP时保留原来的配置并返回错误
+    /// </pre>
+    pub async fn refresh_official_ips(&self) -> LabradorResult<Vec<std::net::IpAddr>> {
+        let overrides = self.inner.client.get_dns_overrides().cloned().ok_or_else(|| LabraError::InvalidConfig { field: "dns_overrides".to_string(), message: "请先调用pin_official_ips".to_string() })?;
+        let host = reqwest::Url::parse(&self.inner.client.api_path).ok().and_then(|url| url.host_str().map(|host| host.to_string()))
+            .ok_or_else(|| LabraError::InvalidConfig { field: "base_url".to_string(), message: format!("无法解析接口域名：{}", self.inner.client.api_path) })?;
+        let ips = crate::dns::parse_ips(&self.get_api_domain_ip().await?);
+        if ips.is_empty() {
+            return Err(LabraError::RequestError("get_api_domain_ip未返回可用的IP".to_string()));
+        }
+        overrides.insert(&host, ips.to_owned());
+        Ok(ips)
+    }
+
+    /// <pre>
+    /// 后台按`interval`定期刷新官方IP，需先调用`pin_official_ips`，需要在tokio运行时中调用
+    /// 刷新失败时保留原来的IP，丢弃返回的句柄时停止刷新
+    /// </pre>
+    #[cfg(feature = "refresher")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "refresher")))]
+    pub fn spawn_official_ip_refresher(&self, interval: std::time::Duration) -> crate::TokenRefreshHandle where T: Send + Sync + 'static {
+        let client = self.clone();
+        crate::wechat::refresher::spawn_periodic(format!("{}刷新官方IP", self.inner.appid), interval, move || {
+            let client = client.clone();
+            async move { client.refresh_official_ips().await.map(|_| ()) }
+        })
+    }
+
     ///
     /// <pre>
     /// 获得jsapi_ticket.
@@ -251,21 +550,19 @@ impl<T: SessionStore> WechatMpClient<T> {
     ///
     /// <pre>
     /// 验证消息的确来自微信服务器.
+    /// signature只由token、timestamp、nonce生成，echo_str不参与签名
     /// 详情(http://mp.weixin.qq.com/wiki?t=resource/res_main&id=mp1421135319&token=&lang=zh_CN)
     /// </pre>
-    pub fn check_signature(&self, signature: &str, timestamp: i64, nonce: &str, echo_str: &str) -> LabradorResult<bool> {
-        let crp = WechatCrypto::new(&self.aes_key.to_owned().unwrap_or_default());
-        let _ = crp.check_signature(signature, timestamp, nonce, echo_str, &self.token.to_owned().unwrap_or_default())?;
+    pub fn check_signature(&self, signature: &str, timestamp: i64, nonce: &str, _echo_str: &str) -> LabradorResult<bool> {
+        let crp = WechatCrypto::new(&self.inner.aes_key.to_owned().unwrap_or_default());
+        let _ = crp.check_signature(signature, timestamp, nonce, "", &self.inner.token.to_owned().unwrap_or_default())?;
         Ok(true)
     }
 
     /// 发送POST请求
     async fn post<D: Serialize>(&self, method: WechatMpMethod, mut querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let access_token = self.access_token(false).await?;
-        if !access_token.is_empty() && method.need_token() {
-            querys.push((ACCESS_TOKEN.to_string(), access_token));
-        }
-        self.client.post(method, querys, data, request_type).await
+        let headers = self.attach_token(&method, &mut querys).await?;
+        self.inner.client.post_with_headers(method, querys, headers, data, request_type).await
     }
 
     ///<pre>
@@ -283,66 +580,414 @@ impl<T: SessionStore> WechatMpClient<T> {
         let params = querys.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<(String, String)>>();
         let mut req = LabraRequest::<B>::new().url(request.get_api_method_name())
             .params(params).method(request.get_request_method()).req_type(request.get_request_type()).body(request.get_request_body::<B>());
-        self.client.request(req).await
+        if let Some(timeout) = request.get_timeout() {
+            req = req.timeout(timeout);
+        }
+        self.inner.client.request(req).await
     }
 
     /// 发送GET请求
     async fn get(&self, method: WechatMpMethod, mut params: Vec<(String, String)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let access_token = self.access_token(false).await?;
-        if !access_token.is_empty() && method.need_token() {
-            params.push((ACCESS_TOKEN.to_string(), access_token));
+        let headers = self.attach_token(&method, &mut params).await?;
+        self.inner.client.get_with_headers(method, params, headers, request_type).await
+    }
+
+    /// 按接口的`AuthStyle`附加access_token，返回需要附加的请求头
+    async fn attach_token(&self, method: &WechatMpMethod, params: &mut Vec<(String, String)>) -> LabradorResult<Vec<(String, String)>> {
+        if !method.need_token() {
+            return Ok(vec![]);
         }
-        self.client.get(method, params, request_type).await
+        let access_token = self.access_token(false).await?;
+        Ok(method.auth_style().attach(ACCESS_TOKEN, &access_token, params))
     }
 
     /// 用户相关服务
     pub fn user(&self) -> WechatMpUser<T> {
-        WechatMpUser::new(self)
+        WechatMpUser::from_client(self.clone())
     }
 
     /// Oauth2授权相关服务
     pub fn oauth2(&self) -> WechatMpOauth2<T> {
-        WechatMpOauth2::new(self)
+        WechatMpOauth2::from_client(self.clone())
     }
 
     /// qrcode相关服务
     pub fn qrcode(&self) -> WechatMpQRCode<T> {
-        WechatMpQRCode::new(self)
+        WechatMpQRCode::from_client(self.clone())
     }
 
     /// 客服相关服务
     pub fn custom_service(&self) -> WechatMpCustomService<T> {
-        WechatMpCustomService::new(self)
+        WechatMpCustomService::from_client(self.clone())
     }
 
     /// 菜单相关服务
     pub fn menu(&self) -> WechatMpMenu<T> {
-        WechatMpMenu::new(self)
+        WechatMpMenu::from_client(self.clone())
     }
 
     /// 多媒体服务
     pub fn media(&self) -> WechatMpMedia<T> {
-        WechatMpMedia::new(self)
+        WechatMpMedia::from_client(self.clone())
+    }
+
+    /// 素材管理服务，与`media()`相同
+    pub fn material(&self) -> WechatMpMaterial<T> {
+        WechatMpMaterial::from_client(self.clone())
     }
 
     /// 模板消息服务
     pub fn template_msg(&self) -> WechatMpTemplateMessage<T> {
-        WechatMpTemplateMessage::new(self)
+        WechatMpTemplateMessage::from_client(self.clone())
+    }
+
+    /// 消息发送服务（客服消息发送失败时回退为模板消息）
+    pub fn messaging(&self) -> WechatMpMessaging<T> {
+        WechatMpMessaging::from_client(self.clone())
+    }
+
+    /// 群发消息服务
+    pub fn mass_msg(&self) -> WechatMpMassMessage<T> {
+        WechatMpMassMessage::from_client(self.clone())
+    }
+
+    /// 门店服务
+    pub fn poi(&self) -> WechatMpPoi<T> {
+        WechatMpPoi::from_client(self.clone())
+    }
+
+    /// 一物一码服务
+    pub fn market_code(&self) -> WechatMpMarketCode<T> {
+        WechatMpMarketCode::from_client(self.clone())
+    }
+
+    /// 短key托管服务
+    pub fn short_key(&self) -> WechatMpShortKey<T> {
+        WechatMpShortKey::from_client(self.clone())
+    }
+
+    /// 公众号当前配置（自动回复、自定义菜单）服务
+    pub fn account(&self) -> WechatMpAccount<T> {
+        WechatMpAccount::from_client(self.clone())
+    }
+
+    /// 导购助手服务
+    pub fn guide(&self) -> WechatMpGuide<T> {
+        WechatMpGuide::from_client(self.clone())
     }
 
     /// 订阅消息服务
     pub fn subscribe_msg(&self) -> WechatMpSubscribeMessage<T> {
-        WechatMpSubscribeMessage::new(self)
+        WechatMpSubscribeMessage::from_client(self.clone())
     }
 
     /// Wifi服务
     pub fn wifi(&self) -> WechatMpWifi<T> {
-        WechatMpWifi::new(self)
+        WechatMpWifi::from_client(self.clone())
     }
 
     /// OCR服务
     pub fn ocr(&self) -> WechatMpOcr<T> {
-        WechatMpOcr::new(self)
+        WechatMpOcr::from_client(self.clone())
+    }
+
+    /// 智能接口服务（语音识别、微信翻译）
+    pub fn ai(&self) -> WechatMpAi<T> {
+        WechatMpAi::from_client(self.clone())
     }
 
+    /// 数据统计服务
+    pub fn data_cube(&self) -> WechatMpDataCube<T> {
+        WechatMpDataCube::from_client(self.clone())
+    }
+
+    /// 会员卡服务
+    pub fn member(&self) -> WechatMpMember<T> {
+        WechatMpMember::from_client(self.clone())
+    }
+
+    /// 卡券服务
+    pub fn card(&self) -> WechatMpCard<T> {
+        WechatMpCard::from_client(self.clone())
+    }
+
+    /// 消息服务端
+    #[cfg(feature = "server")]
+    pub fn server(&self) -> WechatMpServer<T> {
+        WechatMpServer::from_client(self.clone())
+    }
+
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::{AuthStyle, DnsOverrides, LabraError, MetricsRecorder, Outcome, RequestType, SecretGeneration, SessionStore, SimpleStorage, WechatMpClient};
+    use super::WechatMpMethod;
+    use crate::util::mock::{MockResponse, MockServer};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+    const INVALID_SECRET: &str = r#"{"errcode":40125,"errmsg":"invalid appsecret"}"#;
+
+    #[derive(Default)]
+    struct GenerationRecorder {
+        generations: Mutex<Vec<(String, SecretGeneration)>>,
+    }
+
+    impl MetricsRecorder for GenerationRecorder {
+        fn record(&self, _appid: &str, _method: &str, _status: Outcome, _elapsed: Duration, _attempt: usize) {}
+
+        fn record_secret_generation(&self, appid: &str, generation: SecretGeneration) {
+            self.generations.lock().unwrap().push((appid.to_string(), generation));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_access_token_secondary_secret() {
+        let server = MockServer::start(vec![MockResponse::json(INVALID_SECRET), MockResponse::json(TOKEN)]).await;
+        let recorder = Arc::new(GenerationRecorder::default());
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_secondary_secret", "OLD_SECRET")
+            .secondary_secret("NEW_SECRET").base_url(&server.url).metrics_recorder(recorder.clone());
+        assert_eq!(client.current_secret_generation(), SecretGeneration::Primary);
+        assert_eq!(client.access_token(false).await.unwrap(), "ACCESS_TOKEN");
+        assert_eq!(client.current_secret_generation(), SecretGeneration::Secondary);
+        assert_eq!(client.secret(), "NEW_SECRET");
+        let requests = server.requests();
+        assert!(requests[0].contains("secret=OLD_SECRET"));
+        assert!(requests[1].contains("secret=NEW_SECRET"));
+        assert_eq!(*recorder.generations.lock().unwrap(), vec![("wx_mp_secondary_secret".to_string(), SecretGeneration::Secondary)]);
+        // 已缓存，不再请求
+        client.access_token(false).await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_access_token_without_secondary_secret() {
+        let server = MockServer::start(vec![MockResponse::json(INVALID_SECRET)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_no_secondary_secret", "OLD_SECRET").base_url(&server.url);
+        let err = client.access_token(false).await.unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, .. } if errcode == "40125"));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_secret() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(r#"{"access_token":"NEW_TOKEN","expires_in":7200}"#)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_update_secret", "OLD_SECRET").base_url(&server.url);
+        let cloned = client.clone();
+        assert_eq!(client.access_token(false).await.unwrap(), "ACCESS_TOKEN");
+        client.update_secret("NEW_SECRET").unwrap();
+        // 克隆的客户端同样使用新secret，且缓存已失效
+        assert_eq!(cloned.access_token(false).await.unwrap(), "NEW_TOKEN");
+        assert_eq!(cloned.current_secret_generation(), SecretGeneration::Primary);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("secret=NEW_SECRET"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_on_token_refresh() {
+        let tokens = (1..=9).map(|i| MockResponse::json(&format!(r#"{{"access_token":"TOKEN_{}","expires_in":7200}}"#, i))).collect();
+        let server = MockServer::start(tokens).await;
+        let refreshed = Arc::new(Mutex::new(Vec::new()));
+        let received = refreshed.clone();
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_on_token_refresh", "SECRET").base_url(&server.url)
+            .on_token_refresh(move |info| {
+                // 回调时已写入会话存储
+                let stored = SimpleStorage::new().get::<_, String>("wx_mp_on_token_refresh_access_token", None).unwrap();
+                assert_eq!(stored.as_deref(), Some(info.token.as_str()));
+                received.lock().unwrap().push(info.clone());
+            });
+        let tasks = (0..8).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.access_token_info().await.unwrap() })
+        }).collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // 并发的换取合并为一次，恰好回调一次
+        let count = server.requests().len();
+        assert_eq!(count, 1);
+        assert_eq!(refreshed.lock().unwrap().len(), count);
+        let info = client.access_token_info().await.unwrap();
+        assert_eq!(refreshed.lock().unwrap().len(), count);
+        assert_eq!(refreshed.lock().unwrap().last(), Some(&info));
+        let expires_in = (info.expires_at - chrono::Utc::now()).num_seconds();
+        assert!((7000 - 2..=7000).contains(&expires_in), "{}", expires_in);
+        // 强制刷新
+        let token = client.access_token(true).await.unwrap();
+        assert_eq!(token, format!("TOKEN_{}", count + 1));
+        assert_eq!(refreshed.lock().unwrap().len(), count + 1);
+        assert_eq!(refreshed.lock().unwrap().last().map(|info| info.token.to_owned()), Some(token));
+    }
+
+    #[tokio::test]
+    async fn test_on_token_refresh_not_called_on_error() {
+        let server = MockServer::start(vec![MockResponse::json(INVALID_SECRET)]).await;
+        let refreshed = Arc::new(Mutex::new(0));
+        let received = refreshed.clone();
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_on_token_refresh_error", "SECRET").base_url(&server.url)
+            .on_token_refresh(move |_| *received.lock().unwrap() += 1);
+        assert!(client.access_token_info().await.is_err());
+        assert_eq!(*refreshed.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_token_refresh() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN).delay(Duration::from_millis(500)),
+            MockResponse::json(r#"{"access_token":"NEW_TOKEN","expires_in":7200}"#),
+            MockResponse::json(r#"{"ip_list":["127.0.0.1"]}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_cancel_token_refresh", "SECRET").base_url(&server.url);
+        // 换取access_token的过程中被取消
+        assert!(tokio::time::timeout(Duration::from_millis(50), client.access_token(false)).await.is_err());
+        // 被取消的换取没有写入缓存，之后的请求重新换取并正常发出
+        assert_eq!(client.get_callback_ip(false).await.unwrap(), vec!["127.0.0.1".to_string()]);
+        assert_eq!(client.access_token(false).await.unwrap(), "NEW_TOKEN");
+        client.update_secret("NEW_SECRET").unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].starts_with("GET /cgi-bin/getcallbackip?access_token=NEW_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_pin_official_ips() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"ip_list":["127.0.0.1","127.0.0.0/24"]}"#),
+            MockResponse::json(r#"{"ip_list":["101.226.103.0/25"]}"#),
+            MockResponse::json(r#"{"ip_list":[]}"#),
+            MockResponse::json(r#"{"ip_list":["127.0.0.2"]}"#),
+        ]).await;
+        let port = server.url.rsplit(':').next().unwrap().to_string();
+        // 首次获取IP时通过已有的映射访问接口域名，返回的网段被忽略
+        let overrides = DnsOverrides::new().with("api.weixin.qq.com", vec!["127.0.0.1".parse().unwrap()]);
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_pin_official_ips", "SECRET").base_url(&format!("http://api.weixin.qq.com:{}", port))
+            .dns_overrides(overrides.clone())
+            .pin_official_ips().await.unwrap();
+        assert_eq!(overrides.get("api.weixin.qq.com"), Some(vec!["127.0.0.1".parse().unwrap()]));
+        assert_eq!(client.get_callback_ip(false).await.unwrap(), vec!["101.226.103.0/25".to_string()]);
+        let requests = server.requests();
+        assert!(requests[1].starts_with("GET /cgi-bin/get_api_domain_ip?access_token=ACCESS_TOKEN"));
+        assert!(requests[2].to_lowercase().contains(&format!("\r\nhost: api.weixin.qq.com:{}\r\n", port)), "{}", requests[2]);
+
+        // 未返回IP时保留原来的配置
+        assert!(matches!(client.refresh_official_ips().await, Err(LabraError::RequestError(_))));
+        assert_eq!(overrides.get("api.weixin.qq.com"), Some(vec!["127.0.0.1".parse().unwrap()]));
+        // 更新后的IP在下次请求时生效
+        client.refresh_official_ips().await.unwrap();
+        assert!(matches!(client.get_callback_ip(false).await, Err(LabraError::ConnectError(_))));
+        assert_eq!(server.requests().len(), 5);
+
+        let unpinned = WechatMpClient::<SimpleStorage>::new("wx_mp_unpinned", "SECRET");
+        assert!(matches!(unpinned.refresh_official_ips().await, Err(LabraError::InvalidConfig { .. })));
+    }
+
+    /// 等到下一秒开始，使按秒计算的过期时间与刷新时机可预期
+    #[cfg(feature = "refresher")]
+    async fn align_to_second() {
+        let millis = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().subsec_millis() as u64;
+        tokio::time::sleep(Duration::from_millis(1050 - millis)).await;
+    }
+
+    #[cfg(feature = "refresher")]
+    #[tokio::test]
+    async fn test_token_refresher() {
+        let tokens = (1..=6).map(|i| MockResponse::json(&format!(r#"{{"access_token":"TOKEN_{}","expires_in":202}}"#, i))).collect();
+        let server = MockServer::start(tokens).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_token_refresher", "SECRET").base_url(&server.url);
+        align_to_second().await;
+        // 扣除预留的200秒后有效期为2秒，提前1秒刷新：启动时预热一次，之后每秒刷新一次
+        let handle = client.spawn_token_refresher(Duration::from_secs(1));
+        let start = std::time::Instant::now();
+        let mut observed: Vec<String> = Vec::new();
+        while start.elapsed() < Duration::from_millis(3500) {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let expires_at = client.inner.client.session().get::<_, i64>(client.expires_key(), Some(0)).unwrap().unwrap_or_default();
+            assert!(expires_at > crate::current_timestamp());
+            let token = client.access_token(false).await.unwrap();
+            if observed.last() != Some(&token) {
+                observed.push(token);
+            }
+        }
+        assert_eq!(observed, vec!["TOKEN_1", "TOKEN_2", "TOKEN_3", "TOKEN_4"]);
+        // 请求时没有自行刷新
+        assert_eq!(server.requests().len(), 4);
+        drop(handle);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[cfg(feature = "refresher")]
+    #[tokio::test]
+    async fn test_token_refresher_backoff() {
+        const SYSTEM_ERROR: &str = r#"{"errcode":-1,"errmsg":"system error"}"#;
+        let server = MockServer::start(vec![MockResponse::json(SYSTEM_ERROR), MockResponse::json(SYSTEM_ERROR), MockResponse::json(TOKEN)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_token_refresher_backoff", "SECRET").base_url(&server.url);
+        let handle = client.spawn_token_refresher(Duration::from_secs(60));
+        // 失败后间隔500毫秒、1秒重试
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert_eq!(server.requests().len(), 2);
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert_eq!(server.requests().len(), 3);
+        assert!(!handle.is_finished());
+        assert_eq!(client.access_token(false).await.unwrap(), "ACCESS_TOKEN");
+        assert_eq!(server.requests().len(), 3);
+        handle.abort();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(handle.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_ticket_missing_field() {
+        let server = MockServer::start(vec![
+            MockResponse::json(TOKEN),
+            MockResponse::json(r#"{"errcode":0,"errmsg":"ok","expires_in":7200}"#),
+        ]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_ticket_missing", "secret").base_url(&server.url);
+        // 缺少ticket时不再缓存空的ticket
+        match client.get_ticket(super::TicketType::JSAPI).await {
+            Err(LabraError::UnexpectedResponse { method, missing_field, raw, .. }) => {
+                assert_eq!(method.as_deref(), Some("/cgi-bin/ticket/getticket"));
+                assert_eq!(missing_field.as_deref(), Some("ticket"));
+                assert!(raw.contains("7200"));
+            }
+            v => panic!("{:?}", v),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_style_request_shape() {
+        const OK: &str = r#"{"errcode":0,"errmsg":"ok"}"#;
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(OK), MockResponse::json(OK), MockResponse::json(OK), MockResponse::json(OK)]).await;
+        let client = WechatMpClient::<SimpleStorage>::new("wx_mp_auth_style", "secret").base_url(&server.url);
+        let stale = || vec![("access_token".to_string(), "STALE".to_string())];
+        client.post(WechatMpMethod::Custom("/cgi-bin/query".to_string()), stale(), json!({}), RequestType::Json).await.unwrap();
+        client.post(WechatMpMethod::CustomWithAuth { path: "/channels/ec/bearer".to_string(), auth_style: AuthStyle::BearerHeader }, stale(), json!({}), RequestType::Json).await.unwrap();
+        client.get(WechatMpMethod::CustomWithAuth { path: "/channels/ec/bearer".to_string(), auth_style: AuthStyle::BearerHeader }, vec![("id".to_string(), "1".to_string())], RequestType::Json).await.unwrap();
+        client.get(WechatMpMethod::CustomWithAuth { path: "/cgi-bin/none".to_string(), auth_style: AuthStyle::None }, vec![], RequestType::Json).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 5);
+        assert!(requests[1].starts_with("POST /cgi-bin/query?access_token=ACCESS_TOKEN HTTP/1.1"), "{}", requests[1]);
+        assert!(requests[2].starts_with("POST /channels/ec/bearer HTTP/1.1"), "{}", requests[2]);
+        assert!(requests[3].starts_with("GET /channels/ec/bearer?id=1 HTTP/1.1"), "{}", requests[3]);
+        assert!(requests[4].starts_with("GET /cgi-bin/none HTTP/1.1"), "{}", requests[4]);
+        for (i, request) in requests.iter().enumerate().skip(1) {
+            let lower = request.to_lowercase();
+            let in_header = lower.contains("authorization: bearer access_token");
+            let in_query = request.contains("access_token=");
+            // 凭证不会同时出现在URL与请求头中
+            assert!(!(in_header && in_query), "{}", request);
+            assert_eq!(in_header, i == 2 || i == 3, "{}", request);
+            assert_eq!(in_query, i == 1, "{}", request);
+            assert!(!request.contains("STALE"), "{}", request);
+        }
+    }
 }
@@ -1,5 +1,49 @@
-use crate::current_timestamp;
-use super::ReplyRenderer;
+use chrono::{DateTime, TimeZone};
+
+use crate::{current_timestamp, LabradorResult, LabraError};
+use super::{envelope_issues, require, ReplyIssue, ReplyRenderer};
+
+/// <pre>
+/// 各场景下图文消息允许的最大条数
+/// 被动回复与客服消息的图文消息只能包含1条，群发的图文消息最多8条
+/// </pre>
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ArticleLimit {
+    /// 被动回复图文消息
+    PassiveReply,
+    /// 客服图文消息（点击跳转到外链）
+    KfNews,
+    /// 群发图文消息（上传图文消息素材）
+    MassNews,
+}
+
+impl ArticleLimit {
+    /// 允许的最大条数
+    pub fn max(&self) -> usize {
+        match self {
+            ArticleLimit::PassiveReply => 1,
+            ArticleLimit::KfNews => 1,
+            ArticleLimit::MassNews => 8,
+        }
+    }
+
+    /// 场景名称，用于错误信息
+    pub fn api(&self) -> &'static str {
+        match self {
+            ArticleLimit::PassiveReply => "被动回复图文消息",
+            ArticleLimit::KfNews => "客服图文消息",
+            ArticleLimit::MassNews => "群发图文消息",
+        }
+    }
+
+    /// 校验图文条数，为空或超过上限时返回`LabraError::RequestError`
+    pub fn check(&self, count: usize) -> LabradorResult<()> {
+        if count == 0 || count > self.max() {
+            return Err(LabraError::RequestError(format!("{}的图文数量为{}，不能为空且最多只能包含{}条", self.api(), count, self.max())));
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Article {
@@ -15,6 +59,8 @@ pub struct ArticlesReply {
     pub target: String,
     pub time: i64,
     pub articles: Vec<Article>,
+    /// 图文条数上限，默认为被动回复的1条
+    pub limit: ArticleLimit,
 }
 
 #[allow(dead_code)]
@@ -89,14 +135,28 @@ impl Article {
 impl ArticlesReply {
     #[inline]
     pub fn new<S: Into<String>>(source: S, target: S) -> ArticlesReply {
+        Self::with_limit(source, target, ArticleLimit::PassiveReply)
+    }
+
+    /// 使用指定场景的图文条数上限
+    #[inline]
+    pub fn with_limit<S: Into<String>>(source: S, target: S, limit: ArticleLimit) -> ArticlesReply {
         ArticlesReply {
             source: source.into(),
             target: target.into(),
             time: current_timestamp(),
             articles: vec![],
+            limit,
         }
     }
 
+    /// 设置回复的创建时间，渲染为秒级时间戳
+    #[inline]
+    pub fn set_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>) -> &mut Self {
+        self.time = time.timestamp();
+        self
+    }
+
     #[inline]
     pub fn with_articles<S: Into<String>>(source: S, target: S, articles: &[Article]) -> ArticlesReply {
         ArticlesReply {
@@ -104,15 +164,25 @@ impl ArticlesReply {
             target: target.into(),
             time: current_timestamp(),
             articles: articles.to_vec(),
+            limit: ArticleLimit::PassiveReply,
         }
     }
 
+    /// 添加图文，已达到条数上限时返回false
     pub fn add_article(&mut self, article: Article) -> bool {
-        if self.articles.len() >= 10 {
-            return false;
-        }
+        self.try_add_article(article).is_ok()
+    }
+
+    /// 添加图文，已达到条数上限时返回说明上限与场景的错误
+    pub fn try_add_article(&mut self, article: Article) -> LabradorResult<()> {
+        self.limit.check(self.articles.len() + 1)?;
         self.articles.push(article);
-        true
+        Ok(())
+    }
+
+    /// 校验图文条数是否符合当前场景的上限
+    pub fn check(&self) -> LabradorResult<()> {
+        self.limit.check(self.articles.len())
     }
 }
 
@@ -139,16 +209,29 @@ impl ReplyRenderer for ArticlesReply {
             articles=articles_str,
         )
     }
+
+    fn validate(&self) -> Vec<ReplyIssue> {
+        let mut issues = envelope_issues(&self.target, &self.source);
+        if let Err(LabraError::RequestError(message)) = self.check() {
+            issues.push(ReplyIssue { field: "Articles".to_string(), message });
+        }
+        for (i, article) in self.articles.iter().enumerate() {
+            require(&mut issues, &format!("Articles.item[{}].Title", i), &article.title);
+            require(&mut issues, &format!("Articles.item[{}].Url", i), &article.url);
+        }
+        issues
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::LabraError;
     use super::ReplyRenderer;
-    use super::{Article, ArticlesReply};
+    use super::{Article, ArticleLimit, ArticlesReply};
 
     #[test]
     fn test_render_articles_reply() {
-        let mut reply = ArticlesReply::new("test1", "test2");
+        let mut reply = ArticlesReply::with_limit("test1", "test2", ArticleLimit::MassNews);
         let article1 = Article::new("test3", "test4");
         let article2 = Article::with_image("test5", "test6", "test7");
         let article3 = Article::with_description("test8", "test9", "test10");
@@ -165,4 +248,33 @@ mod tests {
         assert!(rendered.contains("test6"));
         assert!(rendered.contains("test7"));
     }
+
+    #[test]
+    fn test_article_limit() {
+        assert!(ArticleLimit::PassiveReply.check(1).is_ok());
+        assert!(ArticleLimit::KfNews.check(1).is_ok());
+        assert!(ArticleLimit::MassNews.check(8).is_ok());
+        assert!(matches!(ArticleLimit::PassiveReply.check(0), Err(LabraError::RequestError(_))));
+        assert!(matches!(ArticleLimit::PassiveReply.check(2), Err(LabraError::RequestError(msg)) if msg.contains("被动回复图文消息") && msg.contains("1条")));
+        assert!(matches!(ArticleLimit::KfNews.check(2), Err(LabraError::RequestError(msg)) if msg.contains("客服图文消息") && msg.contains("1条")));
+        assert!(matches!(ArticleLimit::MassNews.check(9), Err(LabraError::RequestError(msg)) if msg.contains("群发图文消息") && msg.contains("8条")));
+    }
+
+    #[test]
+    fn test_passive_reply_limit() {
+        let mut reply = ArticlesReply::new("test1", "test2");
+        assert!(reply.check().is_err());
+        assert!(reply.try_add_article(Article::new("test3", "test4")).is_ok());
+        assert!(reply.check().is_ok());
+        assert!(matches!(reply.try_add_article(Article::new("test5", "test6")), Err(LabraError::RequestError(msg)) if msg.contains("被动回复图文消息")));
+        assert!(!reply.add_article(Article::new("test5", "test6")));
+        assert_eq!(reply.articles.len(), 1);
+
+        let mut reply = ArticlesReply::with_limit("test1", "test2", ArticleLimit::MassNews);
+        for i in 0..8 {
+            assert!(reply.add_article(Article::new(format!("title{}", i), format!("url{}", i))));
+        }
+        assert!(!reply.add_article(Article::new("title8", "url8")));
+        assert_eq!(reply.articles.len(), 8);
+    }
 }
@@ -1,5 +1,7 @@
+use chrono::{DateTime, TimeZone};
+
 use crate::current_timestamp;
-use super::ReplyRenderer;
+use super::{envelope_issues, require, ReplyIssue, ReplyRenderer};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct ImageReply {
@@ -20,6 +22,13 @@ impl ImageReply {
             media_id: media_id.into(),
         }
     }
+
+    /// 设置回复的创建时间，渲染为秒级时间戳
+    #[inline]
+    pub fn set_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>) -> &mut Self {
+        self.time = time.timestamp();
+        self
+    }
 }
 
 impl ReplyRenderer for ImageReply {
@@ -40,6 +49,12 @@ impl ReplyRenderer for ImageReply {
             media_id=self.media_id
         )
     }
+
+    fn validate(&self) -> Vec<ReplyIssue> {
+        let mut issues = envelope_issues(&self.target, &self.source);
+        require(&mut issues, "Image.MediaId", &self.media_id);
+        issues
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,126 @@
+use sxd_document::dom::{ChildOfElement, Element};
+use sxd_document::parser;
+
+use crate::{LabradorResult, LabraError};
+
 pub trait ReplyRenderer {
     fn render(&self) -> String;
+
+    /// 校验必填字段，返回字段级的问题，为空表示校验通过
+    fn validate(&self) -> Vec<ReplyIssue> {
+        vec![]
+    }
+
+    /// <pre>
+    /// 格式化输出，经XML解析器往返校验
+    /// 每个元素一行、两个空格缩进，数字按原样输出，其余文本使用CDATA，输出稳定，适合用于golden文件测试
+    /// </pre>
+    fn render_pretty(&self) -> LabradorResult<String> {
+        format_xml(&self.render(), true)
+    }
+
+    /// 紧凑输出，经XML解析器往返校验，元素之间不含空白
+    fn render_compact(&self) -> LabradorResult<String> {
+        format_xml(&self.render(), false)
+    }
+}
+
+/// 回复消息的字段级问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyIssue {
+    /// 字段路径，如`Image.MediaId`
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ReplyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// 校验字段不能为空
+fn require(issues: &mut Vec<ReplyIssue>, field: &str, value: &str) {
+    if value.trim().is_empty() {
+        issues.push(ReplyIssue { field: field.to_string(), message: "不能为空".to_string() });
+    }
+}
+
+/// 所有回复共有的接收方与发送方
+fn envelope_issues(target: &str, source: &str) -> Vec<ReplyIssue> {
+    let mut issues = vec![];
+    require(&mut issues, "ToUserName", target);
+    require(&mut issues, "FromUserName", source);
+    issues
+}
+
+fn format_xml(xml: &str, pretty: bool) -> LabradorResult<String> {
+    let package = parser::parse(xml).map_err(|(position, errors)| LabraError::DecodeError(format!("回复消息不是合法的XML，位置{}：{:?}", position, errors).into()))?;
+    let document = package.as_document();
+    let root = document.root().children().into_iter().find_map(|child| child.element())
+        .ok_or_else(|| LabraError::DecodeError("回复消息缺少根元素".into()))?;
+    let mut output = String::new();
+    write_element(&mut output, root, 0, pretty);
+    Ok(output)
+}
+
+fn write_element(output: &mut String, element: Element, depth: usize, pretty: bool) {
+    let indent = if pretty { "  ".repeat(depth) } else { String::new() };
+    let name = element.name().local_part();
+    output.push_str(&indent);
+    output.push('<');
+    output.push_str(name);
+    for attribute in element.attributes() {
+        output.push_str(&format!(" {}=\"{}\"", attribute.name().local_part(), escape(attribute.value())));
+    }
+    output.push('>');
+    let children = element.children();
+    let elements = children.iter().filter_map(|child| child.element()).collect::<Vec<_>>();
+    if elements.is_empty() {
+        let text = children.iter().filter_map(|child| child.text()).map(|text| text.text()).collect::<String>();
+        write_text(output, &text);
+    } else {
+        for child in children.iter() {
+            match child {
+                ChildOfElement::Element(child) => {
+                    if pretty {
+                        output.push('\n');
+                    }
+                    write_element(output, *child, depth + 1, pretty);
+                }
+                // 元素之间的空白不输出
+                ChildOfElement::Text(text) if !text.text().trim().is_empty() => {
+                    if pretty {
+                        output.push('\n');
+                        output.push_str(&"  ".repeat(depth + 1));
+                    }
+                    write_text(output, text.text());
+                }
+                _ => {}
+            }
+        }
+        if pretty {
+            output.push('\n');
+            output.push_str(&indent);
+        }
+    }
+    output.push_str("</");
+    output.push_str(name);
+    output.push('>');
+}
+
+fn write_text(output: &mut String, text: &str) {
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+        output.push_str(text);
+    } else {
+        output.push_str("<![CDATA[");
+        output.push_str(&text.replace("]]>", "]]]]><![CDATA[>"));
+        output.push_str("]]>");
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
 }
 
 mod text;
@@ -15,7 +136,7 @@ pub use self::image::ImageReply;
 pub use self::voice::VoiceReply;
 pub use self::video::VideoReply;
 pub use self::music::MusicReply;
-pub use self::articles::ArticlesReply;
+pub use self::articles::{Article, ArticleLimit, ArticlesReply};
 pub use self::transfer_customer_service::TransferCustomerServiceReply;
 
 
@@ -34,15 +155,83 @@ pub enum Reply {
 #[allow(unused)]
 impl Reply {
     pub fn render(&self) -> String {
-        let reply = match *self {
-            Reply::TextReply(ref r) => r.render(),
-            Reply::ImageReply(ref r) => r.render(),
-            Reply::VoiceReply(ref r) => r.render(),
-            Reply::VideoReply(ref r) => r.render(),
-            Reply::MusicReply(ref r) => r.render(),
-            Reply::ArticlesReply(ref r) => r.render(),
-            Reply::TransferCustomerServiceReply(ref r) => r.render(),
-        };
+        self.renderer().render()
+    }
+
+    /// 校验必填字段，返回字段级的问题，为空表示校验通过
+    pub fn validate(&self) -> Vec<ReplyIssue> {
+        self.renderer().validate()
+    }
+
+    /// 格式化输出，详见[`ReplyRenderer::render_pretty`]
+    pub fn render_pretty(&self) -> LabradorResult<String> {
+        self.renderer().render_pretty()
+    }
+
+    /// 紧凑输出，详见[`ReplyRenderer::render_compact`]
+    pub fn render_compact(&self) -> LabradorResult<String> {
+        self.renderer().render_compact()
+    }
+
+    fn renderer(&self) -> &dyn ReplyRenderer {
+        match *self {
+            Reply::TextReply(ref r) => r,
+            Reply::ImageReply(ref r) => r,
+            Reply::VoiceReply(ref r) => r,
+            Reply::VideoReply(ref r) => r,
+            Reply::MusicReply(ref r) => r,
+            Reply::ArticlesReply(ref r) => r,
+            Reply::TransferCustomerServiceReply(ref r) => r,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use super::{Article, ArticlesReply, ImageReply, Reply, ReplyIssue, ReplyRenderer, TextReply};
+
+    fn text_reply() -> TextReply {
+        let mut reply = TextReply::new("gh_01", "openid_01", "你好 <world> & 1");
+        reply.time = 1411525903;
         reply
     }
+
+    #[test]
+    fn test_render_pretty() {
+        let pretty = text_reply().render_pretty().unwrap();
+        assert_eq!(pretty, "<xml>\n\
+            \x20 <ToUserName><![CDATA[openid_01]]></ToUserName>\n\
+            \x20 <FromUserName><![CDATA[gh_01]]></FromUserName>\n\
+            \x20 <CreateTime>1411525903</CreateTime>\n\
+            \x20 <MsgType><![CDATA[text]]></MsgType>\n\
+            \x20 <Content><![CDATA[你好 <world> & 1]]></Content>\n\
+            </xml>");
+        // 多次渲染、对输出再次格式化的结果都不变
+        assert_eq!(text_reply().render_pretty().unwrap(), pretty);
+        assert_eq!(super::format_xml(&pretty, true).unwrap(), pretty);
+        assert_eq!(super::format_xml(&pretty, false).unwrap(), text_reply().render_compact().unwrap());
+
+        let mut reply = ArticlesReply::with_articles("gh_01", "openid_01", &[Article::new("标题", "https://example.com")]);
+        reply.time = 1411525903;
+        let compact = Reply::ArticlesReply(reply).render_compact().unwrap();
+        assert_eq!(compact, "<xml><ToUserName><![CDATA[openid_01]]></ToUserName><FromUserName><![CDATA[gh_01]]></FromUserName><CreateTime>1411525903</CreateTime><MsgType><![CDATA[news]]></MsgType><ArticleCount>1</ArticleCount><Articles><item><Title><![CDATA[标题]]></Title><Description><![CDATA[]]></Description><PicUrl><![CDATA[]]></PicUrl><Url><![CDATA[https://example.com]]></Url></item></Articles></xml>");
+        assert!(super::format_xml("<xml><a></xml>", true).is_err());
+        // 内容中的`]]>`会提前结束CDATA，往返校验不通过
+        assert!(TextReply::new("gh_01", "openid_01", "]]><a>").render_pretty().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(text_reply().validate().is_empty());
+        let reply = Reply::ImageReply(ImageReply::new("gh_01", "", " "));
+        assert_eq!(reply.validate(), vec![
+            ReplyIssue { field: "ToUserName".to_string(), message: "不能为空".to_string() },
+            ReplyIssue { field: "Image.MediaId".to_string(), message: "不能为空".to_string() },
+        ]);
+        let mut reply = ArticlesReply::new("gh_01", "openid_01");
+        assert_eq!(reply.validate().iter().map(|v| v.field.as_str()).collect::<Vec<_>>(), vec!["Articles"]);
+        reply.add_article(Article::new("", "https://example.com"));
+        assert_eq!(reply.validate().iter().map(|v| v.to_string()).collect::<Vec<_>>(), vec!["Articles.item[0].Title: 不能为空"]);
+    }
 }
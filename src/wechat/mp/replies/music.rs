@@ -1,5 +1,7 @@
+use chrono::{DateTime, TimeZone};
+
 use crate::current_timestamp;
-use super::ReplyRenderer;
+use super::{envelope_issues, require, ReplyIssue, ReplyRenderer};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct MusicReply {
@@ -28,6 +30,13 @@ impl MusicReply {
             hq_music_url: "".to_owned(),
         }
     }
+
+    /// 设置回复的创建时间，渲染为秒级时间戳
+    #[inline]
+    pub fn set_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>) -> &mut Self {
+        self.time = time.timestamp();
+        self
+    }
 }
 
 impl ReplyRenderer for MusicReply {
@@ -56,6 +65,12 @@ impl ReplyRenderer for MusicReply {
             hq_music_url=self.hq_music_url,
         )
     }
+
+    fn validate(&self) -> Vec<ReplyIssue> {
+        let mut issues = envelope_issues(&self.target, &self.source);
+        require(&mut issues, "Music.ThumbMediaId", &self.thumb_media_id);
+        issues
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,8 @@
+use chrono::{DateTime, TimeZone};
+
 use crate::current_timestamp;
 
-use super::ReplyRenderer;
+use super::{envelope_issues, require, ReplyIssue, ReplyRenderer};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct TextReply {
@@ -21,6 +23,13 @@ impl TextReply {
             content: content.into(),
         }
     }
+
+    /// 设置回复的创建时间，渲染为秒级时间戳
+    #[inline]
+    pub fn set_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>) -> &mut Self {
+        self.time = time.timestamp();
+        self
+    }
 }
 
 impl ReplyRenderer for TextReply {
@@ -39,10 +48,17 @@ impl ReplyRenderer for TextReply {
             content=self.content
         )
     }
+
+    fn validate(&self) -> Vec<ReplyIssue> {
+        let mut issues = envelope_issues(&self.target, &self.source);
+        require(&mut issues, "Content", &self.content);
+        issues
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::{TimeZone, Utc};
     use super::ReplyRenderer;
     use super::TextReply;
 
@@ -54,4 +70,12 @@ mod tests {
         assert!(rendered.contains("test2"));
         assert!(rendered.contains("test"));
     }
+
+    #[test]
+    fn test_set_time() {
+        let mut reply = TextReply::new("test1", "test2", "test");
+        reply.set_time(Utc.timestamp_opt(1348831860, 0).unwrap());
+        assert_eq!(reply.time, 1348831860);
+        assert!(reply.render().contains("<CreateTime>1348831860</CreateTime>"));
+    }
 }
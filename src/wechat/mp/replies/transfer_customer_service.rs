@@ -1,5 +1,7 @@
+use chrono::{DateTime, TimeZone};
+
 use crate::current_timestamp;
-use super::ReplyRenderer;
+use super::{envelope_issues, ReplyIssue, ReplyRenderer};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct TransferCustomerServiceReply {
@@ -18,6 +20,13 @@ impl TransferCustomerServiceReply {
             time: current_timestamp(),
         }
     }
+
+    /// 设置回复的创建时间，渲染为秒级时间戳
+    #[inline]
+    pub fn set_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>) -> &mut Self {
+        self.time = time.timestamp();
+        self
+    }
 }
 
 impl ReplyRenderer for TransferCustomerServiceReply {
@@ -34,6 +43,10 @@ impl ReplyRenderer for TransferCustomerServiceReply {
             time=self.time,
         )
     }
+
+    fn validate(&self) -> Vec<ReplyIssue> {
+        envelope_issues(&self.target, &self.source)
+    }
 }
 
 #[cfg(test)]
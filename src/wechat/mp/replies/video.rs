@@ -1,5 +1,7 @@
+use chrono::{DateTime, TimeZone};
+
 use crate::current_timestamp;
-use super::ReplyRenderer;
+use super::{envelope_issues, require, ReplyIssue, ReplyRenderer};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct VideoReply {
@@ -24,6 +26,13 @@ impl VideoReply {
             description: "".to_owned(),
         }
     }
+
+    /// 设置回复的创建时间，渲染为秒级时间戳
+    #[inline]
+    pub fn set_time<Tz: TimeZone>(&mut self, time: DateTime<Tz>) -> &mut Self {
+        self.time = time.timestamp();
+        self
+    }
 }
 
 impl ReplyRenderer for VideoReply {
@@ -48,6 +57,12 @@ impl ReplyRenderer for VideoReply {
             description=self.description,
         )
     }
+
+    fn validate(&self) -> Vec<ReplyIssue> {
+        let mut issues = envelope_issues(&self.target, &self.source);
+        require(&mut issues, "Video.MediaId", &self.media_id);
+        issues
+    }
 }
 
 #[cfg(test)]
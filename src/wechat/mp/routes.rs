@@ -0,0 +1,266 @@
+//!
+//! 公众号消息服务端的axum集成
+//!
+//! [`wechat_mp_routes`]把[`WechatMpServer`]包装为`tower::Service`，请求与应答使用hyper 0.14的`Body`，
+//! 可以直接挂载到axum 0.6的路由上，也可以交给hyper的`Server`使用：GET请求做服务器配置校验，POST请求处理消息推送，
+//! 明文与安全模式根据`encrypt_type=aes`区分，处理超时时应答`success`。
+//!
+//! ```no_run
+//! use labrador::{wechat_mp_routes, WechatMpClient, SimpleStorage};
+//! use labrador::messages::Message;
+//! use labrador::replies::{Reply, TextReply};
+//!
+//! async fn handler(message: Message) -> Option<Reply> {
+//!     Some(Reply::TextReply(TextReply::new(message.get_target(), message.get_source(), "hello".to_string())))
+//! }
+//!
+//! let client = WechatMpClient::<SimpleStorage>::new("appid", "secret").token("token").aes_key("encoding_aes_key");
+//! let app: axum::Router = axum::Router::new().route_service("/wechat", wechat_mp_routes(client.server(), handler));
+//! ```
+//!
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::{Body, Method, Request, Response, StatusCode};
+use hyper::body::HttpBody;
+use hyper::service::Service;
+
+use crate::{session::SessionStore, LabraError, WechatMpServer, WechatMpServerQuery};
+use crate::wechat::mp::messages::Message;
+use crate::wechat::mp::replies::Reply;
+
+/// 消息推送的请求体上限，超出时返回413
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// <pre>
+/// 公众号消息推送的路由，GET为服务器配置校验，POST为消息推送，其余方法返回405
+/// 签名不匹配返回403，请求参数有误返回400，handler返回None或处理超时时应答success
+/// </pre>
+pub fn wechat_mp_routes<T, F, Fut>(server: WechatMpServer<T>, handler: F) -> WechatMpRoutes<T, F>
+    where T: SessionStore,
+          F: Fn(Message) -> Fut + Clone,
+          Fut: Future<Output = Option<Reply>> {
+    WechatMpRoutes {
+        server: Arc::new(server),
+        handler,
+    }
+}
+
+/// [`wechat_mp_routes`]返回的`tower::Service`
+#[derive(Debug)]
+pub struct WechatMpRoutes<T: SessionStore, F> {
+    server: Arc<WechatMpServer<T>>,
+    handler: F,
+}
+
+impl<T: SessionStore, F: Clone> Clone for WechatMpRoutes<T, F> {
+    fn clone(&self) -> Self {
+        WechatMpRoutes {
+            server: self.server.clone(),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+impl<T, F, Fut> Service<Request<Body>> for WechatMpRoutes<T, F>
+    where T: SessionStore + Send + Sync + 'static,
+          F: Fn(Message) -> Fut + Clone + Send + 'static,
+          Fut: Future<Output = Option<Reply>> + Send {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let server = self.server.clone();
+        let handler = self.handler.clone();
+        Box::pin(async move { Ok(respond(&server, req, handler).await) })
+    }
+}
+
+async fn respond<T, F, Fut>(server: &WechatMpServer<T>, req: Request<Body>, handler: F) -> Response<Body>
+    where T: SessionStore,
+          F: Fn(Message) -> Fut,
+          Fut: Future<Output = Option<Reply>> {
+    let query = match serde_urlencoded::from_str::<WechatMpServerQuery>(req.uri().query().unwrap_or_default()) {
+        Ok(query) => query,
+        Err(err) => return text(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    let result = match *req.method() {
+        Method::GET => server.verify(&query),
+        Method::POST => {
+            let body = match read_body(req.into_body()).await {
+                Ok(body) => body,
+                Err(status) => return text(status, String::default()),
+            };
+            server.handle(&query, &String::from_utf8_lossy(&body), handler).await
+        }
+        _ => return text(StatusCode::METHOD_NOT_ALLOWED, String::default()),
+    };
+    match result {
+        Ok(reply) => text(StatusCode::OK, reply),
+        Err(err @ LabraError::MessageSignatureMismatch { .. }) | Err(err @ LabraError::InvalidSignature(_)) => {
+            tracing::warn!("rejected wechat message: {}", err);
+            text(StatusCode::FORBIDDEN, String::default())
+        }
+        Err(err) => {
+            tracing::error!("failed to handle wechat message: {}", err);
+            text(StatusCode::INTERNAL_SERVER_ERROR, String::default())
+        }
+    }
+}
+
+/// 读取请求体，超出[`MAX_BODY_SIZE`]时返回413
+async fn read_body(mut body: Body) -> Result<Vec<u8>, StatusCode> {
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if data.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+fn text(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use hyper::{Body, Method, Request, StatusCode};
+    use hyper::service::Service;
+
+    use crate::{WechatMpClient, WechatCrypto, SimpleStorage};
+    use crate::util::xmlutil;
+    use crate::wechat::mp::messages::Message;
+    use crate::wechat::mp::replies::{Reply, TextReply};
+    use crate::testkit::msg_crypto::{APPID, ENCODING_AES_KEY as AES_KEY, TOKEN};
+    use super::{wechat_mp_routes, WechatMpServerQuery};
+
+    const MESSAGE: &str = "<xml>\n\
+        <ToUserName><![CDATA[gh_01]]></ToUserName>\n\
+        <FromUserName><![CDATA[openid_01]]></FromUserName>\n\
+        <CreateTime>1411525903</CreateTime>\n\
+        <MsgType><![CDATA[text]]></MsgType>\n\
+        <Content><![CDATA[hello]]></Content>\n\
+        <MsgId>1234567890123456</MsgId>\n\
+        </xml>";
+
+    async fn echo(message: Message) -> Option<Reply> {
+        let content = match message {
+            Message::TextMessage(ref msg) => msg.content.to_owned(),
+            _ => String::default(),
+        };
+        Some(Reply::TextReply(TextReply::new(message.get_target(), message.get_source(), format!("echo {}", content))))
+    }
+
+    fn query(encrypt_type: Option<&str>) -> WechatMpServerQuery {
+        let timestamp = 1411525903;
+        let nonce = "461056294";
+        WechatMpServerQuery {
+            signature: WechatCrypto::new(AES_KEY).get_signature(timestamp, nonce, "", TOKEN),
+            timestamp,
+            nonce: nonce.to_string(),
+            echostr: Some("echo".to_string()),
+            encrypt_type: encrypt_type.map(|v| v.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn request(method: Method, query: &WechatMpServerQuery, body: &str) -> Request<Body> {
+        let uri = format!("/wechat?{}", serde_urlencoded::to_string(query).unwrap());
+        Request::builder().method(method).uri(uri).body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn body_string(body: Body) -> String {
+        String::from_utf8(hyper::body::to_bytes(body).await.unwrap().to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes_verify() {
+        let client = WechatMpClient::<SimpleStorage>::new(APPID, "secret").token(TOKEN).aes_key(AES_KEY);
+        let mut routes = wechat_mp_routes(client.server(), echo);
+        let response = routes.call(request(Method::GET, &query(None), "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response.into_body()).await, "echo");
+
+        let mut forged = query(None);
+        forged.nonce = "forged".to_string();
+        let response = routes.call(request(Method::GET, &forged, "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = routes.call(request(Method::PUT, &query(None), "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let response = routes.call(Request::builder().uri("/wechat?timestamp=now").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// 安全模式下的消息推送请求
+    fn encrypted_request() -> Request<Body> {
+        let mut query = query(Some("aes"));
+        let body = WechatCrypto::new(AES_KEY).encrypt_message(MESSAGE, query.timestamp, &query.nonce, TOKEN, APPID).unwrap();
+        let package = xmlutil::parse(&body);
+        query.msg_signature = Some(xmlutil::evaluate(&package.as_document(), "//xml/MsgSignature/text()").string());
+        request(Method::POST, &query, &body)
+    }
+
+    /// 解密应答并校验为回显的文本消息
+    fn assert_echo_reply(reply: &str) {
+        let crypto = WechatCrypto::new(AES_KEY);
+        // 应答为加密信封
+        let package = xmlutil::parse(reply);
+        let doc = package.as_document();
+        assert!(!xmlutil::evaluate(&doc, "//xml/Encrypt/text()").string().is_empty());
+        let signature = xmlutil::evaluate(&doc, "//xml/MsgSignature/text()").string();
+        let timestamp = xmlutil::evaluate(&doc, "//xml/TimeStamp/text()").string().parse::<i64>().unwrap();
+        let nonce = xmlutil::evaluate(&doc, "//xml/Nonce/text()").string();
+        let decrypted = crypto.decrypt_message(reply, &signature, timestamp, &nonce, TOKEN, APPID).unwrap();
+        assert!(decrypted.contains("<ToUserName><![CDATA[openid_01]]></ToUserName>"));
+        assert!(decrypted.contains("<Content><![CDATA[echo hello]]></Content>"));
+    }
+
+    #[tokio::test]
+    async fn test_routes_encrypted() {
+        let client = WechatMpClient::<SimpleStorage>::new(APPID, "secret").token(TOKEN).aes_key(AES_KEY);
+        let mut routes = wechat_mp_routes(client.server(), echo);
+        let response = routes.call(encrypted_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_echo_reply(&body_string(response.into_body()).await);
+    }
+
+    #[tokio::test]
+    async fn test_routes_axum_router() {
+        let client = WechatMpClient::<SimpleStorage>::new(APPID, "secret").token(TOKEN).aes_key(AES_KEY);
+        let mut app: axum::Router = axum::Router::new().route_service("/wechat", wechat_mp_routes(client.server(), echo));
+        let response = app.call(encrypted_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_echo_reply(std::str::from_utf8(&body).unwrap());
+
+        let response = app.call(Request::builder().uri("/other").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_routes_timeout() {
+        let client = WechatMpClient::<SimpleStorage>::new(APPID, "secret").token(TOKEN).aes_key(AES_KEY);
+        let server = client.server().timeout(std::time::Duration::from_millis(50));
+        let mut routes = wechat_mp_routes(server, |message| async move {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            echo(message).await
+        });
+        let response = routes.call(request(Method::POST, &query(None), MESSAGE)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response.into_body()).await, "success");
+    }
+}
@@ -0,0 +1,310 @@
+//!
+//! 公众号消息服务端
+//!
+//! 把签名校验、消息解密、解析、分发和回复渲染串起来，与具体web框架无关，
+//! 框架只需要把查询参数和请求体交给[`WechatMpServer`]。
+//!
+//! 开启`axum-integration`特性后，`wechat_mp_routes`把它包装为可挂载到axum路由上的`tower::Service`。
+//!
+use std::future::Future;
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{session::SessionStore, WechatMpClient, WechatCrypto, LabradorResult, LabraError, parse_message, current_timestamp, nonce_str, verify_plain_signature};
+use crate::wechat::mp::messages::Message;
+use crate::wechat::mp::replies::Reply;
+
+/// 微信要求5秒内应答，否则会重试
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(4500);
+/// 不需要回复时的应答
+const SUCCESS: &str = "success";
+const ENCRYPT_TYPE_AES: &str = "aes";
+
+/// 微信服务器推送时携带的查询参数
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WechatMpServerQuery {
+    pub signature: String,
+    pub timestamp: i64,
+    pub nonce: String,
+    /// 服务器配置校验时才有
+    pub echostr: Option<String>,
+    /// 安全模式下为aes
+    pub encrypt_type: Option<String>,
+    /// 安全模式下的消息签名
+    pub msg_signature: Option<String>,
+    pub openid: Option<String>,
+}
+
+impl WechatMpServerQuery {
+    pub fn is_aes(&self) -> bool {
+        self.encrypt_type.as_deref() == Some(ENCRYPT_TYPE_AES)
+    }
+}
+
+/// 公众号消息服务端.
+///
+/// 明文模式和安全模式根据`encrypt_type=aes`自动区分，处理超时（默认4.5秒）时回复`success`，避免微信重试。
+/// 两种模式下都会先校验查询参数中的signature，签名不匹配时返回`LabraError::MessageSignatureMismatch`。
+///
+/// [文档地址](https://developers.weixin.qq.com/doc/offiaccount/Basic_Information/Access_Overview.html)
+#[derive(Debug, Clone)]
+pub struct WechatMpServer<T: SessionStore> {
+    client: WechatMpClient<T>,
+    timeout: Duration,
+    signature_check: bool,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatMpServer<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatMpClient<T>) -> WechatMpServer<T> {
+        WechatMpServer {
+            client,
+            timeout: DEFAULT_TIMEOUT,
+            signature_check: true,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.server()`")]
+    pub fn new(client: &WechatMpClient<T>) -> WechatMpServer<T> {
+        Self::from_client(client.clone())
+    }
+
+    /// 消息处理超时时间
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 是否校验签名（默认校验），仅用于本地调试，线上关闭后任何人都可以伪造FromUserName
+    pub fn signature_check(mut self, enabled: bool) -> Self {
+        if !enabled {
+            tracing::warn!("wechat message signature check disabled, do not use it in production");
+        }
+        self.signature_check = enabled;
+        self
+    }
+
+    /// <pre>
+    /// 服务器配置校验（GET请求），校验通过返回echostr原样应答.
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Basic_Information/Access_Overview.html">接入概述</a>
+    /// </pre>
+    pub fn verify(&self, query: &WechatMpServerQuery) -> LabradorResult<String> {
+        self.check_signature(query)?;
+        Ok(query.echostr.to_owned().unwrap_or_default())
+    }
+
+    /// <pre>
+    /// 处理消息推送（POST请求），返回应答内容.
+    /// handler返回None或处理超时时应答success；安全模式下回复会被加密.
+    /// 调试构建下会先校验回复的必填字段（[`Reply::validate`]），不通过时返回`LabraError::RequestError`.
+    /// 详情请见: <a href="https://developers.weixin.qq.com/doc/offiaccount/Message_Management/Receiving_standard_messages.html">接收普通消息</a>
+    /// </pre>
+    pub async fn handle<F, Fut>(&self, query: &WechatMpServerQuery, body: &str, handler: F) -> LabradorResult<String>
+        where F: FnOnce(Message) -> Fut,
+              Fut: Future<Output = Option<Reply>> {
+        self.check_signature(query)?;
+        let xml = if query.is_aes() {
+            let msg_signature = query.msg_signature.to_owned().ok_or_else(|| LabraError::InvalidSignature("missing msg_signature.".to_string()))?;
+            self.crypto().decrypt_message(body, &msg_signature, query.timestamp, &query.nonce, &self.token(), &self.client.inner.appid)?
+        } else {
+            body.to_string()
+        };
+        let message = parse_message(xml);
+        let reply = match crate::runtime::timeout(self.timeout, handler(message)).await {
+            Some(reply) => reply,
+            None => {
+                tracing::warn!("wechat message handler timed out after {:?}, reply success", self.timeout);
+                None
+            }
+        };
+        // 调试构建下在发送前校验回复，避免到微信侧才失败
+        if let Some(reply) = reply.as_ref().filter(|_| cfg!(debug_assertions)) {
+            let issues = reply.validate();
+            if !issues.is_empty() {
+                let issues = issues.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; ");
+                tracing::error!("invalid wechat reply: {}", issues);
+                return Err(LabraError::RequestError(format!("回复消息校验失败：{}", issues)));
+            }
+        }
+        match reply {
+            Some(reply) if query.is_aes() => self.crypto().encrypt_message(&reply.render(), current_timestamp(), &nonce_str(), &self.token(), &self.client.inner.appid),
+            Some(reply) => Ok(reply.render()),
+            None => Ok(SUCCESS.to_string()),
+        }
+    }
+
+    fn check_signature(&self, query: &WechatMpServerQuery) -> LabradorResult<()> {
+        if !self.signature_check {
+            return Ok(());
+        }
+        verify_plain_signature(&self.token(), query.timestamp, &query.nonce, &query.signature)
+    }
+
+    fn crypto(&self) -> WechatCrypto {
+        WechatCrypto::new(&self.client.inner.aes_key.to_owned().unwrap_or_default())
+    }
+
+    fn token(&self) -> String {
+        self.client.inner.token.to_owned().unwrap_or_default()
+    }
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{WechatMpClient, WechatCrypto, SimpleStorage, LabraError, verify_plain_signature};
+    use crate::util::xmlutil;
+    use crate::wechat::mp::messages::Message;
+    use crate::wechat::mp::replies::{ImageReply, Reply, TextReply};
+
+    use crate::testkit::msg_crypto::{self, MsgCrypto, APPID, ENCODING_AES_KEY as AES_KEY, PLAIN_SIGNATURE, TOKEN};
+    use super::WechatMpServerQuery;
+
+    const MESSAGE: &str = "<xml>\n\
+        <ToUserName><![CDATA[gh_01]]></ToUserName>\n\
+        <FromUserName><![CDATA[openid_01]]></FromUserName>\n\
+        <CreateTime>1411525903</CreateTime>\n\
+        <MsgType><![CDATA[text]]></MsgType>\n\
+        <Content><![CDATA[hello]]></Content>\n\
+        <MsgId>1234567890123456</MsgId>\n\
+        </xml>";
+
+    fn client() -> WechatMpClient<SimpleStorage> {
+        WechatMpClient::<SimpleStorage>::new(APPID, "secret").token(TOKEN).aes_key(AES_KEY)
+    }
+
+    fn query(encrypt_type: Option<&str>, msg_signature: Option<String>) -> WechatMpServerQuery {
+        let timestamp = 1411525903;
+        let nonce = "461056294";
+        WechatMpServerQuery {
+            signature: WechatCrypto::new(AES_KEY).get_signature(timestamp, nonce, "", TOKEN),
+            timestamp,
+            nonce: nonce.to_string(),
+            echostr: Some("echo".to_string()),
+            encrypt_type: encrypt_type.map(|v| v.to_string()),
+            msg_signature,
+            openid: Some("openid_01".to_string()),
+        }
+    }
+
+    async fn echo(message: Message) -> Option<Reply> {
+        let content = match message {
+            Message::TextMessage(ref msg) => msg.content.to_owned(),
+            _ => String::default(),
+        };
+        Some(Reply::TextReply(TextReply::new(message.get_target(), message.get_source(), format!("echo {}", content))))
+    }
+
+    #[test]
+    fn test_verify() {
+        let client = client();
+        assert_eq!(client.server().verify(&query(None, None)).unwrap(), "echo");
+        let mut invalid = query(None, None);
+        invalid.signature = "invalid".to_string();
+        assert!(matches!(client.server().verify(&invalid), Err(LabraError::MessageSignatureMismatch { .. })));
+        assert_eq!(client.server().signature_check(false).verify(&invalid).unwrap(), "echo");
+    }
+
+    #[test]
+    fn test_verify_plain_signature() {
+        assert!(verify_plain_signature(TOKEN, msg_crypto::ECHO_TIMESTAMP, msg_crypto::ECHO_NONCE, PLAIN_SIGNATURE).is_ok());
+        let err = verify_plain_signature(TOKEN, msg_crypto::ECHO_TIMESTAMP, "437374426", PLAIN_SIGNATURE).unwrap_err();
+        match err {
+            LabraError::MessageSignatureMismatch { signature, computed } => {
+                assert_eq!(signature, PLAIN_SIGNATURE);
+                let expected = WechatCrypto::new("").get_signature(1411443780, "437374426", "", TOKEN);
+                assert_eq!(computed, if cfg!(debug_assertions) { Some(expected) } else { None });
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_rejects_forged_message() {
+        let client = client();
+        let mut forged = query(None, None);
+        forged.nonce = "forged".to_string();
+        assert!(matches!(client.server().handle(&forged, MESSAGE, echo).await, Err(LabraError::MessageSignatureMismatch { .. })));
+        let reply = client.server().signature_check(false).handle(&forged, MESSAGE, echo).await.unwrap();
+        assert!(reply.contains("<Content><![CDATA[echo hello]]></Content>"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_plaintext() {
+        let client = client();
+        let reply = client.server().handle(&query(None, None), MESSAGE, echo).await.unwrap();
+        assert!(reply.contains("<ToUserName><![CDATA[openid_01]]></ToUserName>"));
+        assert!(reply.contains("<Content><![CDATA[echo hello]]></Content>"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_official_sample() {
+        // 官方示例的msg_signature包含密文
+        let client = client();
+        let query = query(Some("aes"), Some(msg_crypto::MSG_SIGNATURE.to_string()));
+        let reply = client.server().handle(&query, msg_crypto::ENCRYPTED_XML, echo).await.unwrap();
+        let package = xmlutil::parse(&reply);
+        let doc = package.as_document();
+        let signature = xmlutil::evaluate(&doc, "//xml/MsgSignature/text()").string();
+        let timestamp = xmlutil::evaluate(&doc, "//xml/TimeStamp/text()").string().parse::<i64>().unwrap();
+        let nonce = xmlutil::evaluate(&doc, "//xml/Nonce/text()").string();
+        let decrypted = msg_crypto::WechatMsgCrypto::sample().decrypt_message(&reply, &signature, timestamp, &nonce).unwrap();
+        assert!(decrypted.contains("<ToUserName><![CDATA[messense]]></ToUserName>"), "{}", decrypted);
+        assert!(decrypted.contains("<Content><![CDATA[echo test]]></Content>"), "{}", decrypted);
+    }
+
+    #[tokio::test]
+    async fn test_handle_encrypted() {
+        let client = client();
+        let crypto = WechatCrypto::new(AES_KEY);
+        let mut query = query(Some("aes"), None);
+        let body = crypto.encrypt_message(MESSAGE, query.timestamp, &query.nonce, TOKEN, APPID).unwrap();
+        let package = xmlutil::parse(&body);
+        query.msg_signature = Some(xmlutil::evaluate(&package.as_document(), "//xml/MsgSignature/text()").string());
+
+        let reply = client.server().handle(&query, &body, echo).await.unwrap();
+        assert!(!reply.contains("echo hello"));
+        let package = xmlutil::parse(&reply);
+        let doc = package.as_document();
+        let signature = xmlutil::evaluate(&doc, "//xml/MsgSignature/text()").string();
+        let timestamp = xmlutil::evaluate(&doc, "//xml/TimeStamp/text()").string().parse::<i64>().unwrap();
+        let nonce = xmlutil::evaluate(&doc, "//xml/Nonce/text()").string();
+        let decrypted = crypto.decrypt_message(&reply, &signature, timestamp, &nonce, TOKEN, APPID).unwrap();
+        assert!(decrypted.contains("<Content><![CDATA[echo hello]]></Content>"));
+
+        query.msg_signature = None;
+        assert!(client.server().handle(&query, &body, echo).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handle_timeout() {
+        let client = client();
+        let reply = client.server().timeout(Duration::from_millis(50)).handle(&query(None, None), MESSAGE, |message| async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            echo(message).await
+        }).await.unwrap();
+        assert_eq!(reply, "success");
+        let reply = client.server().handle(&query(None, None), MESSAGE, |_| async { None }).await.unwrap();
+        assert_eq!(reply, "success");
+    }
+
+    #[tokio::test]
+    async fn test_handle_rejects_invalid_reply() {
+        let client = client();
+        let result = client.server().handle(&query(None, None), MESSAGE, |message| async move {
+            Some(Reply::ImageReply(ImageReply::new(message.get_target(), message.get_source(), String::new())))
+        }).await;
+        if cfg!(debug_assertions) {
+            assert!(matches!(result, Err(LabraError::RequestError(msg)) if msg.contains("Image.MediaId")));
+        } else {
+            assert!(result.is_ok());
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use serde_json::Value;
+
 use crate::messages::{Message, MessageParser};
-use crate::{messages, xmlutil};
+use crate::{messages, xmlutil, LabraError, LabradorResult};
 
 pub fn parse_message<S: AsRef<str>>(xml: S) -> Message {
     let xml = xml.as_ref();
@@ -15,6 +17,7 @@ pub fn parse_message<S: AsRef<str>>(xml: S) -> Message {
         "video" => Message::VideoMessage(messages::VideoMessage::from_xml(xml)),
         "location" => Message::LocationMessage(messages::LocationMessage::from_xml(xml)),
         "link" => Message::LinkMessage(messages::LinkMessage::from_xml(xml)),
+        "miniprogrampage" => Message::MiniProgramPageMessage(messages::MiniProgramPageMessage::from_xml(xml)),
         "event" => {
             let event_str = xmlutil::evaluate(&doc, "//xml/Event/text()").string().to_lowercase();
             if &event_str == "subscribe" {
@@ -35,12 +38,201 @@ fn parse_event(event: &str, xml: &str) -> Message {
     match event {
         "subscribe" => Message::SubscribeEvent(messages::SubscribeEvent::from_xml(xml)),
         "unsubscribe" => Message::UnsubscribeEvent(messages::UnsubscribeEvent::from_xml(xml)),
-        "templatesendjobfinish" => Message::UnsubscribeEvent(messages::UnsubscribeEvent::from_xml(xml)),
+        "templatesendjobfinish" => Message::TemplateSendJobFinishEvent(messages::TemplateSendJobFinishEvent::from_xml(xml)),
+        "masssendjobfinish" => Message::MassSendJobFinishEvent(messages::MassSendJobFinishEvent::from_xml(xml)),
         "scan" => Message::ScanEvent(messages::ScanEvent::from_xml(xml)),
         "location" => Message::LocationEvent(messages::LocationEvent::from_xml(xml)),
         "click" => Message::ClickEvent(messages::ClickEvent::from_xml(xml)),
         "view" => Message::ViewEvent(messages::ViewEvent::from_xml(xml)),
         "qualification_verify_success" => Message::QualificationVerifySuccessEvent(messages::QualificationVerifySuccessEvent::from_xml(xml)),
+        "user_enter_tempsession" => Message::UserEnterTempSessionEvent(messages::UserEnterTempSessionEvent::from_xml(xml)),
+        "subscribe_msg_popup_event" => Message::SubscribeMsgPopupEvent(messages::SubscribeMsgPopupEvent::from_xml(xml)),
         _ => Message::UnknownMessage(messages::UnknownMessage::from_xml(xml)),
     }
 }
+
+/// <pre>
+/// 解析JSON格式的推送
+/// 小程序的消息推送可选择JSON数据格式，字段名与XML格式一致，转换为XML后按`parse_message`解析，消息的raw为转换后的XML
+/// </pre>
+pub fn parse_json_message<S: AsRef<str>>(json: S) -> LabradorResult<Message> {
+    Ok(parse_message(json_to_xml(json.as_ref())?))
+}
+
+fn json_to_xml(json: &str) -> LabradorResult<String> {
+    let value = serde_json::from_str::<Value>(json)?;
+    let fields = value.as_object().ok_or_else(|| LabraError::RequestError(format!("消息推送不是JSON对象：{}", json)))?;
+    let mut xml = String::from("<xml>");
+    for (key, value) in fields {
+        let text = match value {
+            Value::Number(number) => number.to_string(),
+            Value::String(text) => cdata(text),
+            Value::Null => String::default(),
+            _ => cdata(&value.to_string()),
+        };
+        xml.push_str(&format!("<{key}>{}</{key}>", text, key = key));
+    }
+    xml.push_str("</xml>");
+    Ok(xml)
+}
+
+/// 使用CDATA保证为单个文本节点（实体转义会拆分文本节点，`text()`只能取到第一段）
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::events::TemplateSendStatus;
+    use crate::messages::Message;
+    use super::{parse_json_message, parse_message};
+
+    fn event_xml(event: &str, extra: &str) -> String {
+        format!("<xml><ToUserName><![CDATA[toUser]]></ToUserName><FromUserName><![CDATA[FromUser]]></FromUserName>\
+        <CreateTime>123456789</CreateTime><MsgType><![CDATA[event]]></MsgType><Event><![CDATA[{}]]></Event>{}</xml>", event, extra)
+    }
+
+    #[test]
+    fn test_subscribe_with_scene() {
+        let msg = parse_message(event_xml("subscribe", "<EventKey><![CDATA[qrscene_123123]]></EventKey><Ticket><![CDATA[TICKET]]></Ticket>"));
+        assert!(matches!(msg, Message::SubscribeScanEvent(_)));
+        assert_eq!(msg.scan_scene(), Some("123123".to_string()));
+        assert_eq!(msg.ticket(), Some("TICKET".to_string()));
+    }
+
+    #[test]
+    fn test_subscribe_with_string_scene() {
+        // 字符串场景值中包含qrscene_时只去掉前缀
+        let msg = parse_message(event_xml("subscribe", "<EventKey><![CDATA[qrscene_from_qrscene_a]]></EventKey><Ticket><![CDATA[TICKET]]></Ticket>"));
+        assert_eq!(msg.scan_scene(), Some("from_qrscene_a".to_string()));
+    }
+
+    #[test]
+    fn test_plain_subscribe() {
+        let msg = parse_message(event_xml("subscribe", ""));
+        assert!(matches!(msg, Message::SubscribeEvent(_)));
+        assert_eq!(msg.scan_scene(), None);
+        assert_eq!(msg.ticket(), None);
+    }
+
+    #[test]
+    fn test_scan() {
+        let msg = parse_message(event_xml("SCAN", "<EventKey><![CDATA[123123]]></EventKey><Ticket><![CDATA[TICKET]]></Ticket>"));
+        assert!(matches!(msg, Message::ScanEvent(_)));
+        assert_eq!(msg.scan_scene(), Some("123123".to_string()));
+        assert_eq!(msg.ticket(), Some("TICKET".to_string()));
+    }
+
+    #[test]
+    fn test_location() {
+        let msg = parse_message(event_xml("LOCATION", "<Latitude>23.137466</Latitude><Longitude>113.352425</Longitude><Precision>119.385040</Precision>"));
+        match msg {
+            Message::LocationEvent(ref event) => {
+                assert_eq!(event.latitude, 23.137466);
+                assert_eq!(event.longitude, 113.352425);
+                assert_eq!(event.precision, 119.38504);
+            }
+            _ => panic!("unexpected message: {:?}", msg),
+        }
+        assert_eq!(msg.scan_scene(), None);
+        assert_eq!(msg.ticket(), None);
+    }
+
+    #[test]
+    fn test_mass_send_job_finish() {
+        let msg = parse_message(event_xml("MASSSENDJOBFINISH", "<MsgID>1988</MsgID><Status><![CDATA[sendsuccess]]></Status>\
+        <TotalCount>100</TotalCount><FilterCount>80</FilterCount><SentCount>75</SentCount><ErrorCount>5</ErrorCount>"));
+        match msg {
+            Message::MassSendJobFinishEvent(ref event) => {
+                assert_eq!(event.id, 1988);
+                assert_eq!(event.status, "sendsuccess");
+                assert_eq!((event.total_count, event.filter_count, event.sent_count, event.error_count), (100, 80, 75, 5));
+            }
+            _ => panic!("unexpected message: {:?}", msg),
+        }
+        assert_eq!(msg.get_source(), "FromUser");
+    }
+
+    #[test]
+    fn test_template_send_job_finish() {
+        let msg = parse_message(event_xml("TEMPLATESENDJOBFINISH", "<MsgID>200163840</MsgID><Status><![CDATA[failed:user block]]></Status>"));
+        match msg {
+            Message::TemplateSendJobFinishEvent(ref event) => {
+                assert_eq!(event.send_status, TemplateSendStatus::UserBlock);
+                assert!(!event.send_status.is_success());
+            }
+            _ => panic!("unexpected message: {:?}", msg),
+        }
+        assert_eq!(msg.send_msg_id(), Some(200163840));
+        assert_eq!(msg.get_source(), "FromUser");
+        assert_eq!(parse_message(event_xml("subscribe", "")).send_msg_id(), None);
+    }
+
+    #[test]
+    fn test_subscribe_msg_popup() {
+        let msg = parse_message(event_xml("subscribe_msg_popup_event", "<SubscribeMsgPopupEvent>\
+        <List><TemplateId><![CDATA[TEMPLATE_1]]></TemplateId><SubscribeStatusString><![CDATA[accept]]></SubscribeStatusString><PopupScene>2</PopupScene></List>\
+        <List><TemplateId><![CDATA[TEMPLATE_2]]></TemplateId><SubscribeStatusString><![CDATA[reject]]></SubscribeStatusString><PopupScene>2</PopupScene></List>\
+        <List><TemplateId><![CDATA[TEMPLATE_3]]></TemplateId><SubscribeStatusString><![CDATA[accept]]></SubscribeStatusString><PopupScene>1</PopupScene></List>\
+        </SubscribeMsgPopupEvent>"));
+        match msg {
+            Message::SubscribeMsgPopupEvent(ref event) => {
+                assert_eq!(event.items.len(), 3);
+                assert_eq!(event.items[2].popup_scene, 1);
+                assert_eq!(event.accepted_template_ids(), vec!["TEMPLATE_1".to_string(), "TEMPLATE_3".to_string()]);
+                assert_eq!(event.rejected_template_ids(), vec!["TEMPLATE_2".to_string()]);
+            }
+            _ => panic!("unexpected message: {:?}", msg),
+        }
+
+        // 单个模板
+        let msg = parse_message(event_xml("subscribe_msg_popup_event", "<SubscribeMsgPopupEvent><List><TemplateId><![CDATA[TEMPLATE_1]]></TemplateId>\
+        <SubscribeStatusString><![CDATA[reject]]></SubscribeStatusString><PopupScene>0</PopupScene></List></SubscribeMsgPopupEvent>"));
+        assert!(matches!(msg, Message::SubscribeMsgPopupEvent(ref event) if event.items.len() == 1 && !event.items[0].is_accepted()));
+    }
+
+    fn assert_miniprogrampage(msg: &Message) {
+        match msg {
+            Message::MiniProgramPageMessage(ref page) => {
+                assert_eq!(page.id, 1234567890123456);
+                assert_eq!(page.time, 1482048670);
+                assert_eq!(page.title, "标题<&>");
+                assert_eq!(page.app_id, "wx1234567890");
+                assert_eq!(page.page_path, "pages/index/index?id=1");
+                assert_eq!(page.thumb_url, "http://mmbiz.qpic.cn/thumb");
+                assert_eq!(page.thumb_media_id, "MEDIA_ID");
+            }
+            _ => panic!("unexpected message: {:?}", msg),
+        }
+        assert_eq!(msg.get_source(), "fromUser");
+        assert_eq!(msg.get_target(), "toUser");
+    }
+
+    #[test]
+    fn test_miniprogrampage() {
+        let msg = parse_message("<xml><ToUserName><![CDATA[toUser]]></ToUserName><FromUserName><![CDATA[fromUser]]></FromUserName>\
+        <CreateTime>1482048670</CreateTime><MsgType><![CDATA[miniprogrampage]]></MsgType><MsgId>1234567890123456</MsgId>\
+        <Title><![CDATA[标题<&>]]></Title><AppId><![CDATA[wx1234567890]]></AppId><PagePath><![CDATA[pages/index/index?id=1]]></PagePath>\
+        <ThumbUrl><![CDATA[http://mmbiz.qpic.cn/thumb]]></ThumbUrl><ThumbMediaId><![CDATA[MEDIA_ID]]></ThumbMediaId></xml>");
+        assert_miniprogrampage(&msg);
+
+        let msg = parse_json_message(r#"{"ToUserName":"toUser","FromUserName":"fromUser","CreateTime":1482048670,"MsgType":"miniprogrampage",
+        "MsgId":1234567890123456,"Title":"标题<&>","AppId":"wx1234567890","PagePath":"pages/index/index?id=1",
+        "ThumbUrl":"http://mmbiz.qpic.cn/thumb","ThumbMediaId":"MEDIA_ID"}"#).unwrap();
+        assert_miniprogrampage(&msg);
+        assert!(parse_json_message("[]").is_err());
+    }
+
+    #[test]
+    fn test_user_enter_tempsession() {
+        let msg = parse_message(event_xml("user_enter_tempsession", "<SessionFrom><![CDATA[sessionFrom]]></SessionFrom>"));
+        assert!(matches!(msg, Message::UserEnterTempSessionEvent(ref event) if event.session_from == "sessionFrom"));
+
+        let msg = Message::parse_json(r#"{"ToUserName":"toUser","FromUserName":"FromUser","CreateTime":1482048670,"MsgType":"event",
+        "Event":"user_enter_tempsession","SessionFrom":"sessionFrom"}"#).unwrap();
+        assert!(matches!(msg, Message::UserEnterTempSessionEvent(ref event) if event.session_from == "sessionFrom"));
+        assert_eq!(msg.get_source(), "FromUser");
+    }
+}
@@ -0,0 +1,243 @@
+use serde_json::Value;
+use crate::{LabradorResult, LabraError, OriginNotifyResponse, RequestType, SessionStore, WechatCombineCloseRequest, WechatCombineNotifyResponse, WechatCombineOrderRequest, WechatCombineOrderResponse, WechatPayClient, WechatPayResponseV3};
+use crate::wechat::cryptos::{SignatureHeader, WechatCryptoV3};
+use crate::wechat::pay::method::{CombinePayMethod, WechatPayMethod};
+use crate::wechat::pay::TradeType;
+
+/// 合单支付
+#[derive(Debug, Clone)]
+pub struct WechatPayCombine<T: SessionStore> {
+    client: WechatPayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatPayCombine<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatPayClient<T>) -> WechatPayCombine<T> {
+        WechatPayCombine {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.combine()`")]
+    pub fn new(client: &WechatPayClient<T>) -> WechatPayCombine<T> {
+        Self::from_client(client.clone())
+    }
+
+    ///
+    /// # 合单下单 - V3版本
+    /// <pre>
+    /// 使用合单支付接口，用户只输入一次密码，即可完成多个订单的支付。目前最多一次可支持10笔订单进行合单支付，子单币种必须一致。
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter5_1_3.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/combine-transactions/jsapi)
+    /// </pre>
+    pub async fn create(&self, trade_type: TradeType, mut params: WechatCombineOrderRequest) -> LabradorResult<WechatPayResponseV3> {
+        if params.combine_appid.is_none() {
+            params.combine_appid = self.client.inner.appid.to_owned().into();
+        }
+        if params.combine_mchid.is_none() {
+            params.combine_mchid = self.client.inner.mch_id.to_owned();
+        }
+        params.check_params()?;
+        let res = self.client.post_v3(params.combine_mchid.to_owned(), WechatPayMethod::Combine(CombinePayMethod::CreateOrder(trade_type)), vec![], &params, RequestType::Json).await?.json::<Value>()?;
+        serde_json::from_value::<WechatPayResponseV3>(res).map_err(LabraError::from)
+    }
+
+    /// 调用合单下单接口，并组装生成支付所需参数对象.
+    pub async fn create_order(&self, trade_type: TradeType, params: WechatCombineOrderRequest) -> LabradorResult<Value> {
+        let appid = params.combine_appid.to_owned().unwrap_or(self.client.inner.appid.to_owned());
+        let mchid = params.combine_mchid.to_owned().or(self.client.inner.mch_id.to_owned()).unwrap_or_default();
+        let result = self.create(trade_type.to_owned(), params).await?;
+        result.get_pay_info(trade_type, appid.into(), mchid, self.client.inner.private_key.to_owned())
+    }
+
+    ///
+    /// # 合单查询 - V3版本
+    /// <pre>
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter5_1_11.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/combine-transactions/out-trade-no/{combine_out_trade_no})
+    /// </pre>
+    pub async fn query(&self, combine_out_trade_no: &str) -> LabradorResult<WechatCombineOrderResponse> {
+        self.client.get_v3(WechatPayMethod::Combine(CombinePayMethod::QueryOrder(combine_out_trade_no.to_string())), vec![], RequestType::Json)
+            .await?.json::<WechatCombineOrderResponse>()
+    }
+
+    ///
+    /// # 合单关单 - V3版本
+    /// <pre>
+    /// 合单支付订单只能使用此合单关单api完成关单，需要列出要关闭的全部子单。
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter5_1_12.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/combine-transactions/out-trade-no/{combine_out_trade_no}/close)
+    /// </pre>
+    pub async fn close(&self, mut params: WechatCombineCloseRequest) -> LabradorResult<()> {
+        if params.sub_orders.is_empty() {
+            return Err(LabraError::RequestError("合单关单需要列出子单".to_string()));
+        }
+        if params.combine_appid.is_none() {
+            params.combine_appid = self.client.inner.appid.to_owned().into();
+        }
+        let method = WechatPayMethod::Combine(CombinePayMethod::CloseOrder(params.combine_out_trade_no.to_string()));
+        let res = self.client.post_v3(None, method, vec![], &params, RequestType::Json).await?;
+        let _ = res.text()?;
+        Ok(())
+    }
+
+    /// # 解析合单支付结果通知. - v3
+    /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter5_1_13.shtml)
+    pub async fn parse_notify(&self, notify_data: &str, header: Option<SignatureHeader>) -> LabradorResult<WechatCombineNotifyResponse> {
+        let header = header.ok_or_else(|| LabraError::RequestError("非法请求，头部信息为空".to_string()))?;
+        if !self.client.verify_notify_sign(&header, notify_data).await {
+            return Err(LabraError::RequestError("非法请求，头部信息验证失败".to_string()));
+        }
+        let origin = serde_json::from_str::<OriginNotifyResponse>(notify_data)?;
+        let v3_key = self.client.inner.api_key_v3.to_owned().unwrap_or_default();
+        let crypto = WechatCryptoV3::new(&v3_key);
+        let decrypted = crypto.decrypt_data_v3(&origin.resource)?;
+        let result = serde_json::from_slice::<WechatCombineOrderResponse>(&decrypted)?;
+        Ok(WechatCombineNotifyResponse {
+            raw_data: origin.into(),
+            result: result.into()
+        })
+    }
+}
+
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use openssl::symm;
+    use serde_json::{json, Value};
+
+    use crate::{CombineAmount, CombineSubOrder, LabraError, Payer, WechatCombineOrderRequest};
+    use crate::util::mock::MockServer;
+    use crate::util::prp::PrpCrypto;
+    use crate::wechat::cryptos::SignatureHeader;
+    use crate::wechat::pay::{TradeNo, TradeType};
+    use crate::wechat::pay::tests::{generate_cert, pay_client, signed_response};
+
+    const API_V3_KEY: &str = "a7cde1ef41e24d64b3f8c0be2c5b8e3a";
+
+    fn sub_order(mchid: &str, out_trade_no: &str, total: i64, currency: &str) -> CombineSubOrder {
+        CombineSubOrder {
+            mchid: mchid.to_string(),
+            attach: "深圳分店".to_string(),
+            amount: CombineAmount { total_amount: total, currency: currency.to_string(), payer_amount: None, payer_currency: None },
+            out_trade_no: TradeNo::try_new(out_trade_no).unwrap(),
+            sub_mchid: None,
+            description: "腾讯充值中心-QQ会员充值".to_string(),
+            settle_info: None,
+            goods_tag: None,
+        }
+    }
+
+    fn request(sub_orders: Vec<CombineSubOrder>) -> WechatCombineOrderRequest {
+        WechatCombineOrderRequest {
+            combine_appid: None,
+            combine_mchid: None,
+            combine_out_trade_no: TradeNo::try_new("P20150806125346").unwrap(),
+            scene_info: None,
+            sub_orders,
+            combine_payer_info: Some(Payer { openid: "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o".to_string() }),
+            time_start: None,
+            time_expire: Some("2018-06-08T10:34:56+08:00".to_string()),
+            notify_url: "https://yourapp.com/notify".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_check_params() {
+        assert!(request(vec![sub_order("1900000109", "20150806125346", 10, "CNY")]).check_params().is_ok());
+        assert!(matches!(request(vec![]).check_params(), Err(LabraError::RequestError(_))));
+        let orders = (0..11).map(|i| sub_order("1900000109", &format!("2015080612534{}", i), 10, "CNY")).collect::<Vec<_>>();
+        assert!(matches!(request(orders).check_params(), Err(LabraError::RequestError(msg)) if msg.contains("10")));
+        let orders = vec![sub_order("1900000109", "20150806125346", 10, "CNY"), sub_order("1900000110", "20150806125347", 10, "USD")];
+        assert!(matches!(request(orders).check_params(), Err(LabraError::RequestError(msg)) if msg.contains("20150806125347")));
+        // 商户号与appid
+        assert!(matches!(request(vec![sub_order("mchid", "20150806125346", 10, "CNY")]).check_params(), Err(LabraError::RequestError(msg)) if msg.contains("mchid")));
+        let mut req = request(vec![sub_order("1900000109", "20150806125346", 10, "CNY")]);
+        req.combine_appid = Some("wxd678efh567hg6787".to_string());
+        assert!(matches!(req.check_params(), Err(LabraError::RequestError(msg)) if msg.contains("wxd678efh567hg6787")));
+    }
+
+    #[tokio::test]
+    async fn test_create() {
+        let (private_key, cert) = generate_cert();
+        let body = r#"{"prepay_id":"wx201410272009395522657a690389285100"}"#;
+        let server = MockServer::start(vec![signed_response(&private_key, &cert.serial_no, body)]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let orders = vec![sub_order("1900000109", "20150806125346", 10, "CNY"), sub_order("1900000110", "20150806125347", 20, "CNY")];
+        let result = client.combine().create(TradeType::Jsapi, request(orders)).await.unwrap();
+        assert_eq!(result.prepay_id.as_deref(), Some("wx201410272009395522657a690389285100"));
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /v3/combine-transactions/jsapi HTTP/1.1"), "{}", requests[0]);
+        let sent = serde_json::from_str::<Value>(requests[0].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(sent, json!({
+            "combine_appid": "wxd930ea5d5a258f4f",
+            "combine_mchid": "1230000109",
+            "combine_out_trade_no": "P20150806125346",
+            "sub_orders": [
+                {"mchid": "1900000109", "attach": "深圳分店", "amount": {"total_amount": 10, "currency": "CNY"}, "out_trade_no": "20150806125346", "description": "腾讯充值中心-QQ会员充值"},
+                {"mchid": "1900000110", "attach": "深圳分店", "amount": {"total_amount": 20, "currency": "CNY"}, "out_trade_no": "20150806125347", "description": "腾讯充值中心-QQ会员充值"}
+            ],
+            "combine_payer_info": {"openid": "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"},
+            "time_expire": "2018-06-08T10:34:56+08:00",
+            "notify_url": "https://yourapp.com/notify"
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_parse_notify() {
+        let (private_key, cert) = generate_cert();
+        let serial_no = cert.serial_no.to_owned();
+        let client = pay_client("http://127.0.0.1:1".to_string(), &private_key, cert).key_v3(API_V3_KEY.to_string());
+        let resource = json!({
+            "combine_appid": "wxd678efh567hg6787",
+            "combine_mchid": "1230000109",
+            "combine_out_trade_no": "P20150806125346",
+            "sub_orders": [
+                {"mchid": "1900000109", "trade_type": "JSAPI", "trade_state": "SUCCESS", "bank_type": "CMC", "attach": "深圳分店", "success_time": "2015-05-20T13:29:35+08:00", "transaction_id": "4200000001201811162363945412", "out_trade_no": "20150806125346", "amount": {"total_amount": 10, "currency": "CNY", "payer_amount": 10, "payer_currency": "CNY"}},
+                {"mchid": "1900000110", "trade_type": "JSAPI", "trade_state": "PAYERROR", "attach": "深圳分店", "out_trade_no": "20150806125347", "amount": {"total_amount": 20, "currency": "CNY"}}
+            ],
+            "combine_payer_info": {"openid": "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"}
+        });
+        let nonce = "fdasflkja484";
+        let associated_data = "transaction";
+        let mut tag = vec![0u8; 16];
+        let mut ciphertext = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), API_V3_KEY.as_bytes(), Some(nonce.as_bytes()), associated_data.as_bytes(), resource.to_string().as_bytes(), &mut tag).unwrap();
+        ciphertext.extend(tag);
+        let notify = json!({
+            "id": "EV-2018022511223320873",
+            "create_time": "2015-05-20T13:29:35+08:00",
+            "resource_type": "encrypt-resource",
+            "event_type": "TRANSACTION.SUCCESS",
+            "summary": "支付成功",
+            "resource": {"original_type": "transaction", "algorithm": "AEAD_AES_256_GCM", "ciphertext": base64::encode(&ciphertext), "associated_data": associated_data, "nonce": nonce}
+        }).to_string();
+        let timestamp = "1554208460";
+        let header = SignatureHeader {
+            time_stamp: timestamp.to_string(),
+            nonce: nonce.to_string(),
+            signature: PrpCrypto::rsa_sha256_sign(&format!("{}\n{}\n{}\n", timestamp, nonce, notify), &private_key).unwrap(),
+            serial: serial_no,
+        };
+
+        let result = client.combine().parse_notify(&notify, Some(header.to_owned())).await.unwrap().result.unwrap();
+        assert_eq!(result.combine_out_trade_no, "P20150806125346");
+        assert_eq!(result.sub_orders.len(), 2);
+        assert!(result.sub_orders[0].is_success());
+        assert_eq!(result.sub_orders[0].amount.payer_amount, Some(10));
+        assert_eq!(result.sub_orders[1].trade_state, "PAYERROR");
+        assert_eq!(result.sub_orders[1].transaction_id, None);
+
+        let mut forged = header;
+        forged.signature = PrpCrypto::rsa_sha256_sign("forged", &private_key).unwrap();
+        assert!(client.combine().parse_notify(&notify, Some(forged)).await.is_err());
+        assert!(client.combine().parse_notify(&notify, None).await.is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use reqwest::multipart;
+use serde_json::{json, Value};
+
+use crate::{EcommerceApplymentNo, LabradorResult, LabraError, RequestType, SessionStore, WechatEcommerceApplymentQueryResponse, WechatEcommerceApplymentRequest, WechatEcommerceApplymentResponse, WechatPayClient};
+use crate::util::hex;
+use crate::wechat::cryptos::WechatCryptoV3;
+use crate::wechat::pay::method::{EcommerceMethod, WechatPayMethod};
+
+/// 图片上传的最大字节数（2MB）
+const IMAGE_MAX_SIZE: usize = 2 * 1024 * 1024;
+
+/// 电商收付通 - 二级商户进件
+#[derive(Debug, Clone)]
+pub struct WechatPayEcommerceApplyment<T: SessionStore> {
+    client: WechatPayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatPayEcommerceApplyment<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatPayClient<T>) -> WechatPayEcommerceApplyment<T> {
+        WechatPayEcommerceApplyment {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.ecommerce_applyment()`")]
+    pub fn new(client: &WechatPayClient<T>) -> WechatPayEcommerceApplyment<T> {
+        Self::from_client(client.clone())
+    }
+
+    ///
+    /// # 图片上传 - V3版本
+    /// <pre>
+    /// 上传身份证、营业执照等图片，返回的media_id用于进件申请。仅支持JPG、BMP、PNG格式，文件大小不超过2M。
+    /// 请求体为multipart，包含meta（文件名与文件SHA256摘要的JSON）与file两部分，签名主体为meta的JSON而非整个请求体。
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter2_1_1.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/merchant/media/upload)
+    /// </pre>
+    pub async fn upload_image(&self, bytes: Vec<u8>, filename: &str) -> LabradorResult<String> {
+        let mime = image_mime(filename).ok_or_else(|| LabraError::RequestError(format!("图片{}格式有误，仅支持JPG、BMP、PNG格式", filename)))?;
+        if bytes.is_empty() || bytes.len() > IMAGE_MAX_SIZE {
+            return Err(LabraError::RequestError(format!("图片不能为空且最大不能超过{}字节！", IMAGE_MAX_SIZE)));
+        }
+        let meta = media_meta(&bytes, filename);
+        let form = multipart::Form::new()
+            .part("meta", multipart::Part::text(meta.to_owned()).mime_str("application/json")?)
+            .part("file", multipart::Part::bytes(bytes).file_name(filename.to_string()).mime_str(mime)?);
+        let v = self.client.upload_v3(WechatPayMethod::Ecommerce(EcommerceMethod::UploadImage), &meta, form).await?.json::<Value>()?;
+        v["media_id"].as_str().map(str::to_string).ok_or_else(|| LabraError::MissingField("media_id".to_string()))
+    }
+
+    ///
+    /// # 二级商户进件 - V3版本
+    /// <pre>
+    /// 电商平台为二级商户提交入驻申请。请求中的敏感字段填写明文，提交前使用过期时间最晚的平台证书加密，
+    /// 并通过Wechatpay-Serial请求头传递该证书序列号。
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter7_1_1.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/ecommerce/applyments/)
+    /// </pre>
+    pub async fn submit_applyment(&self, mut request: WechatEcommerceApplymentRequest) -> LabradorResult<WechatEcommerceApplymentResponse> {
+        let cert = self.client.platform_cert().await?;
+        request.encrypt_fields(|plaintext| WechatCryptoV3::encrypt_sensitive(plaintext, &cert.public_key))?;
+        self.client.post_v3_sensitive(None, WechatPayMethod::Ecommerce(EcommerceMethod::Applyments), &request, &cert)
+            .await?.json::<WechatEcommerceApplymentResponse>()
+    }
+
+    ///
+    /// # 查询申请状态 - V3版本
+    /// <pre>
+    /// 通过微信支付申请单号或业务申请编号查询二级商户进件的审核状态。
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter7_1_2.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/ecommerce/applyments/{applyment_id})
+    /// </pre>
+    pub async fn query_applyment(&self, applyment_no: EcommerceApplymentNo) -> LabradorResult<WechatEcommerceApplymentQueryResponse> {
+        let method = match applyment_no {
+            EcommerceApplymentNo::ApplymentId(v) => EcommerceMethod::QueryApplymentById(v),
+            EcommerceApplymentNo::OutRequestNo(v) => EcommerceMethod::QueryApplymentByOutRequestNo(v),
+        };
+        let response = self.client.get_v3(WechatPayMethod::Ecommerce(method), vec![], RequestType::Json).await?;
+        if response.status().is_success() {
+            response.json::<WechatEcommerceApplymentQueryResponse>()
+        } else {
+            Err(LabraError::RequestError(response.text()?))
+        }
+    }
+}
+
+/// 图片上传的meta部分，同时作为签名主体
+fn media_meta(bytes: &[u8], filename: &str) -> String {
+    json!({"filename": filename, "sha256": hex::encode(openssl::sha::sha256(bytes))}).to_string()
+}
+
+/// 根据文件扩展名获取图片的MIME类型
+fn image_mime(filename: &str) -> Option<&'static str> {
+    let extension = filename.rsplit_once('.').map(|(_, v)| v.to_lowercase())?;
+    match extension.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "bmp" => Some("image/bmp"),
+        "png" => Some("image/png"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{EcommerceAccountInfo, EcommerceApplymentNo, EcommerceApplymentState, EcommerceContactInfo, EcommerceIdCardInfo, EcommerceSalesSceneInfo, LabraError, WechatEcommerceApplymentRequest};
+    use crate::util::mock::MockServer;
+    use crate::util::prp::PrpCrypto;
+    use crate::wechat::cryptos::WechatCryptoV3;
+    use crate::wechat::pay::tests::{generate_cert, pay_client, signed_response};
+    use super::media_meta;
+
+    /// 请求头的值，请求头名称不区分大小写
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.split("\r\n\r\n").next().unwrap().lines()
+            .filter_map(|line| line.split_once(": "))
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Authorization中的签名参数
+    fn auth_param<'a>(auth: &'a str, name: &str) -> &'a str {
+        let start = auth.find(&format!("{}=\"", name)).unwrap() + name.len() + 2;
+        let end = start + auth[start..].find('"').unwrap();
+        &auth[start..end]
+    }
+
+    fn applyment_request() -> WechatEcommerceApplymentRequest {
+        WechatEcommerceApplymentRequest {
+            out_request_no: "APPLYMENT_00000000001".to_string(),
+            organization_type: "2401".to_string(),
+            business_license_info: None,
+            id_doc_type: None,
+            id_card_info: Some(EcommerceIdCardInfo {
+                id_card_copy: "jTpGmxUX3FBWVQ5NJTZvlKX_gdU4cRz7z5NxpnFuAxhBTEO_PvWkfSCJ3zVIn001D8daLC-ehEuo0BJqRTvDujqhThn4ReFxikqJ5YW6zFQ".to_string(),
+                id_card_national: "47ZC6GC-vnrbEny__Ie_An5-tCpqxucuxi-vByf3Gjm7KE53JXvGy9tqZm2XAUf-4KGprrKhpVBDIUv0OF4wFNIO4kqg05InE4d2I6_H7I4".to_string(),
+                id_card_name: "张三".to_string(),
+                id_card_number: "110101199003070011".to_string(),
+                id_card_valid_time_begin: None,
+                id_card_valid_time: "2026-06-06".to_string(),
+            }),
+            need_account_info: true,
+            account_info: Some(EcommerceAccountInfo {
+                bank_account_type: "75".to_string(),
+                account_bank: "工商银行".to_string(),
+                account_name: "张三".to_string(),
+                bank_address_code: "110000".to_string(),
+                bank_branch_id: None,
+                bank_name: None,
+                account_number: "6222021234567890123".to_string(),
+            }),
+            contact_info: EcommerceContactInfo {
+                contact_type: "65".to_string(),
+                contact_name: "张三".to_string(),
+                contact_id_card_number: None,
+                mobile_phone: "13900000000".to_string(),
+                contact_email: Some("zhangsan@example.com".to_string()),
+            },
+            sales_scene_info: EcommerceSalesSceneInfo {
+                store_name: "爱烧烤".to_string(),
+                store_url: Some("http://www.qq.com".to_string()),
+                store_qr_code: None,
+                mini_program_sub_appid: None,
+            },
+            merchant_shortname: "爱烧烤".to_string(),
+            qualifications: None,
+            business_addition_pics: None,
+            business_addition_desc: None,
+        }
+    }
+
+    #[test]
+    fn test_media_meta() {
+        assert_eq!(media_meta(b"abc", "id_card.png"), r#"{"filename":"id_card.png","sha256":"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_upload_image() {
+        let (private_key, cert) = generate_cert();
+        let public_key = String::from_utf8(cert.public_key.to_owned()).unwrap();
+        let body = r#"{"media_id":"H1ihR9JW7zXQ2P8vKBqUjSKmQZR6YXmRLRaA_kivrhQ"}"#;
+        let server = MockServer::start(vec![signed_response(&private_key, &cert.serial_no, body)]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let ecommerce = client.ecommerce_applyment();
+        let media_id = ecommerce.upload_image(b"abc".to_vec(), "id_card.png").await.unwrap();
+        assert_eq!(media_id, "H1ihR9JW7zXQ2P8vKBqUjSKmQZR6YXmRLRaA_kivrhQ");
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /v3/merchant/media/upload HTTP/1.1"), "{}", requests[0]);
+        assert!(header(&requests[0], "content-type").unwrap().starts_with("multipart/form-data; boundary="));
+        let meta = media_meta(b"abc", "id_card.png");
+        assert!(requests[0].contains(&format!("Content-Type: application/json\r\n\r\n{}\r\n", meta)));
+        assert!(requests[0].contains("filename=\"id_card.png\"\r\nContent-Type: image/png\r\n\r\nabc\r\n"));
+        // 签名主体为meta部分的JSON
+        let auth = header(&requests[0], "authorization").unwrap();
+        assert!(auth.starts_with("WECHATPAY2-SHA256-RSA2048 mchid=\"1230000109\""));
+        let message = format!("POST\n/v3/merchant/media/upload\n{}\n{}\n{}\n", auth_param(auth, "timestamp"), auth_param(auth, "nonce_str"), meta);
+        assert!(PrpCrypto::rsa_sha256_verify(&public_key, &message, auth_param(auth, "signature")).unwrap());
+        let body = requests[0].split_once("\r\n\r\n").unwrap().1;
+        let message = format!("POST\n/v3/merchant/media/upload\n{}\n{}\n{}\n", auth_param(auth, "timestamp"), auth_param(auth, "nonce_str"), body);
+        assert!(!PrpCrypto::rsa_sha256_verify(&public_key, &message, auth_param(auth, "signature")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upload_image_check() {
+        let (private_key, cert) = generate_cert();
+        let client = pay_client("http://127.0.0.1:1".to_string(), &private_key, cert);
+        let ecommerce = client.ecommerce_applyment();
+        assert!(matches!(ecommerce.upload_image(b"abc".to_vec(), "id_card.gif").await, Err(LabraError::RequestError(_))));
+        assert!(matches!(ecommerce.upload_image(b"abc".to_vec(), "id_card").await, Err(LabraError::RequestError(_))));
+        assert!(matches!(ecommerce.upload_image(vec![], "id_card.jpg").await, Err(LabraError::RequestError(_))));
+        assert!(matches!(ecommerce.upload_image(vec![0u8; 2 * 1024 * 1024 + 1], "id_card.JPG").await, Err(LabraError::RequestError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_submit_applyment() {
+        let (private_key, cert) = generate_cert();
+        let serial_no = cert.serial_no.to_owned();
+        let body = r#"{"applyment_id":2000002124775691,"out_request_no":"APPLYMENT_00000000001"}"#;
+        let server = MockServer::start(vec![signed_response(&private_key, &serial_no, body)]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let result = client.ecommerce_applyment().submit_applyment(applyment_request()).await.unwrap();
+        assert_eq!(result.applyment_id, 2000002124775691);
+        assert_eq!(result.out_request_no, "APPLYMENT_00000000001");
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /v3/ecommerce/applyments/ HTTP/1.1"), "{}", requests[0]);
+        assert_eq!(header(&requests[0], "wechatpay-serial"), Some(serial_no.as_str()));
+        let sent = serde_json::from_str::<Value>(requests[0].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        // 敏感字段使用平台证书公钥加密，可用平台证书私钥解密
+        let decrypt = |v: &Value| WechatCryptoV3::decrypt_sensitive(v.as_str().unwrap(), &private_key).unwrap();
+        assert_ne!(sent["id_card_info"]["id_card_name"], "张三");
+        assert_eq!(decrypt(&sent["id_card_info"]["id_card_name"]), "张三");
+        assert_eq!(decrypt(&sent["id_card_info"]["id_card_number"]), "110101199003070011");
+        assert_eq!(decrypt(&sent["account_info"]["account_name"]), "张三");
+        assert_eq!(decrypt(&sent["account_info"]["account_number"]), "6222021234567890123");
+        assert_eq!(decrypt(&sent["contact_info"]["contact_name"]), "张三");
+        assert_eq!(decrypt(&sent["contact_info"]["mobile_phone"]), "13900000000");
+        assert_eq!(decrypt(&sent["contact_info"]["contact_email"]), "zhangsan@example.com");
+        assert!(sent["contact_info"].get("contact_id_card_number").is_none());
+        // 其它字段保持明文
+        assert_eq!(sent["id_card_info"]["id_card_valid_time"], "2026-06-06");
+        assert_eq!(sent["account_info"]["account_bank"], "工商银行");
+        assert_eq!(sent["sales_scene_info"], json!({"store_name": "爱烧烤", "store_url": "http://www.qq.com"}));
+        assert_eq!(sent["need_account_info"], true);
+    }
+
+    #[tokio::test]
+    async fn test_query_applyment() {
+        let (private_key, cert) = generate_cert();
+        let server = MockServer::start(vec![
+            signed_response(&private_key, &cert.serial_no, r#"{"applyment_state":"ACCOUNT_NEED_VERIFY","applyment_state_desc":"待账户验证","account_validation":{"account_name":"****","pay_amount":124,"destination_account_number":"7222223333322332","destination_account_name":"财付通支付科技有限公司","destination_account_bank":"招商银行威盛大厦支行","city":"深圳","remark":"入驻账户验证","deadline":"2018-12-10 17:09:01"},"out_request_no":"APPLYMENT_00000000001","applyment_id":2000002124775691}"#),
+            signed_response(&private_key, &cert.serial_no, r#"{"applyment_state":"FINISH","sign_state":"SIGNED","sub_mchid":"1542488631","out_request_no":"APPLYMENT_00000000001","applyment_id":2000002124775691}"#),
+            signed_response(&private_key, &cert.serial_no, r#"{"applyment_state":"PAUSED","out_request_no":"APPLYMENT_00000000001","applyment_id":2000002124775691}"#),
+        ]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let ecommerce = client.ecommerce_applyment();
+        let result = ecommerce.query_applyment(EcommerceApplymentNo::ApplymentId(2000002124775691)).await.unwrap();
+        assert_eq!(result.applyment_state, EcommerceApplymentState::AccountNeedVerify);
+        assert_eq!(result.account_validation.unwrap().pay_amount, Some(124));
+        let result = ecommerce.query_applyment(EcommerceApplymentNo::OutRequestNo("APPLYMENT_00000000001".to_string())).await.unwrap();
+        assert_eq!(result.applyment_state, EcommerceApplymentState::Finish);
+        assert_eq!(result.sub_mchid.as_deref(), Some("1542488631"));
+        let result = ecommerce.query_applyment(EcommerceApplymentNo::ApplymentId(2000002124775691)).await.unwrap();
+        assert_eq!(result.applyment_state, EcommerceApplymentState::Unknown);
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("GET /v3/ecommerce/applyments/2000002124775691 HTTP/1.1"), "{}", requests[0]);
+        assert!(requests[1].starts_with("GET /v3/ecommerce/applyments/out-request-no/APPLYMENT_00000000001 HTTP/1.1"), "{}", requests[1]);
+    }
+}
@@ -1,3 +1,13 @@
 mod wxpay;
+mod combine;
+mod ecommerce;
+mod v2;
+mod payscore;
+mod parking;
 
-pub use self::wxpay::*;
\ No newline at end of file
+pub use self::wxpay::*;
+pub use self::combine::*;
+pub use self::ecommerce::*;
+pub use self::v2::*;
+pub use self::payscore::*;
+pub use self::parking::*;
@@ -0,0 +1,240 @@
+use serde_json::Value;
+
+use crate::{LabradorResult, LabraError, OriginNotifyResponse, ParkingNotifyResult, PlateColor, RequestType, SessionStore, WechatPayClient, WechatPayParkingNotifyResponse, WechatPayParkingRequest, WechatPayParkingResponse, WechatPayParkingServiceResponse, WechatPayParkingTransaction, WechatPayParkingTransactionRequest};
+use crate::plate::{normalize_plate_number, validate_plate_number};
+use crate::wechat::cryptos::{SignatureHeader, WechatCryptoV3};
+use crate::wechat::pay::method::{ParkingMethod, WechatPayMethod};
+
+/// 扣费成功的通知类型
+const EVENT_TRANSACTION_SUCCESS: &str = "TRANSACTION.SUCCESS";
+/// 扣费失败的通知类型
+const EVENT_TRANSACTION_FAIL: &str = "TRANSACTION.FAIL";
+
+/// 微信支付分停车服务
+#[derive(Debug, Clone)]
+pub struct WechatPayParking<T: SessionStore> {
+    client: WechatPayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatPayParking<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatPayClient<T>) -> WechatPayParking<T> {
+        WechatPayParking {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.parking()`")]
+    pub fn new(client: &WechatPayClient<T>) -> WechatPayParking<T> {
+        Self::from_client(client.clone())
+    }
+
+    ///
+    /// # 查询车牌服务开通信息 - V3版本
+    /// <pre>
+    /// 车辆入场前查询用户是否开通了微信支付分停车服务，车牌号会先规范化（如`沪A·12345`）再查询
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter8_8_1.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/vehicle/parking/services/find)
+    /// </pre>
+    pub async fn find_service(&self, plate_number: &str, plate_color: PlateColor, openid: &str) -> LabradorResult<WechatPayParkingServiceResponse> {
+        let (plate_number, _) = validate_plate_number(plate_number)?;
+        let appid = self.client.inner.appid.to_owned();
+        let plate_color = serde_json::to_value(plate_color)?.as_str().unwrap_or_default().to_string();
+        let query = serde_urlencoded::to_string([("appid", appid.as_str()), ("plate_number", plate_number.as_str()), ("plate_color", plate_color.as_str()), ("openid", openid)])?;
+        self.client.get_v3(WechatPayMethod::Parking(ParkingMethod::FindService(query)), vec![], RequestType::Json)
+            .await?.json::<WechatPayParkingServiceResponse>()
+    }
+
+    ///
+    /// # 创建停车入场 - V3版本
+    /// <pre>
+    /// 车辆入场后创建停车入场信息，返回的入场id用于扣费
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter8_8_2.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/vehicle/parking/parkings)
+    /// </pre>
+    pub async fn create_parking(&self, mut params: WechatPayParkingRequest) -> LabradorResult<WechatPayParkingResponse> {
+        params.check_params()?;
+        params.plate_number = normalize_plate_number(&params.plate_number);
+        self.client.post_v3(None, WechatPayMethod::Parking(ParkingMethod::CreateParking), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayParkingResponse>()
+    }
+
+    ///
+    /// # 扣费受理 - V3版本
+    /// <pre>
+    /// 车辆出场后发起扣费，受理成功后扣费结果通过通知返回
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter8_8_3.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/vehicle/transactions/parking)
+    /// </pre>
+    pub async fn transaction(&self, mut params: WechatPayParkingTransactionRequest) -> LabradorResult<WechatPayParkingTransaction> {
+        params.check_params()?;
+        params.appid = params.appid.or_else(|| self.client.inner.appid.to_owned().into());
+        params.parking_info.plate_number = normalize_plate_number(&params.parking_info.plate_number);
+        self.client.post_v3(None, WechatPayMethod::Parking(ParkingMethod::Transaction), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayParkingTransaction>()
+    }
+
+    /// # 解析停车服务通知. - v3
+    /// <pre>
+    /// 扣费成功（TRANSACTION.SUCCESS）与扣费失败（TRANSACTION.FAIL）的数据解析为扣费订单，
+    /// 其余通知保留解密后的原始数据
+    /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter8_8_5.shtml)
+    /// </pre>
+    pub async fn parse_notify(&self, notify_data: &str, header: Option<SignatureHeader>) -> LabradorResult<WechatPayParkingNotifyResponse> {
+        let header = header.ok_or_else(|| LabraError::RequestError("非法请求，头部信息为空".to_string()))?;
+        if !self.client.verify_notify_sign(&header, notify_data).await {
+            return Err(LabraError::RequestError("非法请求，头部信息验证失败".to_string()));
+        }
+        let origin = serde_json::from_str::<OriginNotifyResponse>(notify_data)?;
+        let v3_key = self.client.inner.api_key_v3.to_owned().unwrap_or_default();
+        let crypto = WechatCryptoV3::new(&v3_key);
+        let decrypted = crypto.decrypt_data_v3(&origin.resource)?;
+        let result = match origin.event_type.as_str() {
+            EVENT_TRANSACTION_SUCCESS => ParkingNotifyResult::TransactionSuccess(serde_json::from_slice(&decrypted)?),
+            EVENT_TRANSACTION_FAIL => ParkingNotifyResult::TransactionFail(serde_json::from_slice(&decrypted)?),
+            _ => ParkingNotifyResult::Other(serde_json::from_slice::<Value>(&decrypted)?),
+        };
+        Ok(WechatPayParkingNotifyResponse {
+            raw_data: origin.into(),
+            result: result.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use chrono::{DateTime, FixedOffset};
+    use openssl::symm;
+    use serde_json::{json, Value};
+
+    use crate::{Amount, LabraError, ParkingInfo, ParkingNotifyResult, PlateColor, WechatPayParkingTransactionRequest};
+    use crate::util::mock::MockServer;
+    use crate::util::prp::PrpCrypto;
+    use crate::wechat::cryptos::SignatureHeader;
+    use crate::wechat::pay::tests::{generate_cert, pay_client, signed_response};
+
+    const API_V3_KEY: &str = "a7cde1ef41e24d64b3f8c0be2c5b8e3a";
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    fn time(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn transaction_request(plate_number: &str, total: i64) -> WechatPayParkingTransactionRequest {
+        WechatPayParkingTransactionRequest {
+            appid: None,
+            sub_appid: None,
+            sub_mchid: None,
+            description: "停车场扣费".to_string(),
+            attach: None,
+            out_trade_no: "20150806125346".to_string(),
+            trade_scene: "PARKING".to_string(),
+            goods_tag: None,
+            notify_url: "https://api.test.com".to_string(),
+            profit_sharing: None,
+            amount: Amount { total, currency: "CNY".to_string().into(), payer_total: None, payer_currency: None },
+            parking_info: ParkingInfo {
+                parking_id: "5K8264ILTKCH16CQ250".to_string(),
+                plate_number: plate_number.to_string(),
+                plate_color: PlateColor::Blue,
+                start_time: time("2017-08-26T10:43:39+08:00"),
+                end_time: time("2017-08-26T12:43:39+08:00"),
+                parking_name: "欢乐海岸停车场".to_string(),
+                charging_duration: 7200,
+                device_id: "12313".to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction() {
+        let (private_key, cert) = generate_cert();
+        let response = r#"{"appid":"wxd930ea5d5a258f4f","description":"停车场扣费","create_time":"2017-08-26T12:43:40+08:00","out_trade_no":"20150806125346","trade_state":"ACCEPTED","trade_scene":"PARKING","amount":{"total":888,"currency":"CNY"}}"#;
+        let server = MockServer::start(vec![signed_response(&private_key, &cert.serial_no, response)]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let res = client.parking().transaction(transaction_request("沪A·12345", 888)).await.unwrap();
+        assert_eq!(res.trade_state, "ACCEPTED");
+        assert!(!res.is_success());
+        // 车牌号或金额有误时不发送请求
+        assert!(client.parking().transaction(transaction_request("沪A1234", 888)).await.is_err());
+        assert!(matches!(client.parking().transaction(transaction_request("沪A12345", 0)).await, Err(LabraError::RequestError(_))));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /v3/vehicle/transactions/parking HTTP/1.1"), "{}", requests[0]);
+        assert_eq!(body(&requests[0]), json!({
+            "appid": "wxd930ea5d5a258f4f",
+            "description": "停车场扣费",
+            "out_trade_no": "20150806125346",
+            "trade_scene": "PARKING",
+            "notify_url": "https://api.test.com",
+            "amount": {"total": 888, "currency": "CNY"},
+            "parking_info": {
+                "parking_id": "5K8264ILTKCH16CQ250",
+                "plate_number": "沪A12345",
+                "plate_color": "BLUE",
+                "start_time": "2017-08-26T10:43:39+08:00",
+                "end_time": "2017-08-26T12:43:39+08:00",
+                "parking_name": "欢乐海岸停车场",
+                "charging_duration": 7200,
+                "device_id": "12313"
+            }
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_parse_notify() {
+        let (private_key, cert) = generate_cert();
+        let serial_no = cert.serial_no.to_owned();
+        let client = pay_client("http://127.0.0.1:1".to_string(), &private_key, cert).key_v3(API_V3_KEY.to_string());
+        let resource = json!({
+            "appid": "wxd930ea5d5a258f4f", "sp_mchid": "1230000109", "description": "停车场扣费", "out_trade_no": "20150806125346",
+            "transaction_id": "1217752501201407033233368018", "trade_state": "SUCCESS", "success_time": "2017-08-26T12:43:45+08:00",
+            "bank_type": "CMC", "user_repaid": "N", "trade_scene": "PARKING",
+            "parking_info": {"parking_id": "5K8264ILTKCH16CQ250", "plate_number": "粤BD88888", "plate_color": "GREEN", "start_time": "2017-08-26T10:43:39+08:00", "end_time": "2017-08-26T12:43:39+08:00", "parking_name": "欢乐海岸停车场", "charging_duration": 7200, "device_id": "12313"},
+            "payer": {"openid": "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"},
+            "amount": {"total": 888, "currency": "CNY", "payer_total": 888}
+        });
+        let nonce = "fdasflkja484";
+        let associated_data = "transaction";
+        let mut tag = vec![0u8; 16];
+        let mut ciphertext = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), API_V3_KEY.as_bytes(), Some(nonce.as_bytes()), associated_data.as_bytes(), resource.to_string().as_bytes(), &mut tag).unwrap();
+        ciphertext.extend(tag);
+        let notify = json!({
+            "id": "EV-2018022511223320873",
+            "create_time": "2017-08-26T12:43:45+08:00",
+            "resource_type": "encrypt-resource",
+            "event_type": "TRANSACTION.SUCCESS",
+            "summary": "支付成功",
+            "resource": {"original_type": "transaction", "algorithm": "AEAD_AES_256_GCM", "ciphertext": base64::encode(&ciphertext), "associated_data": associated_data, "nonce": nonce}
+        }).to_string();
+        let timestamp = "1554208460";
+        let header = SignatureHeader {
+            time_stamp: timestamp.to_string(),
+            nonce: nonce.to_string(),
+            signature: PrpCrypto::rsa_sha256_sign(&format!("{}\n{}\n{}\n", timestamp, nonce, notify), &private_key).unwrap(),
+            serial: serial_no,
+        };
+        match client.parking().parse_notify(&notify, Some(header)).await.unwrap().result.unwrap() {
+            ParkingNotifyResult::TransactionSuccess(transaction) => {
+                assert!(transaction.is_success());
+                assert_eq!(transaction.transaction_id.as_deref(), Some("1217752501201407033233368018"));
+                let parking_info = transaction.parking_info.unwrap();
+                assert_eq!(parking_info.plate_color, PlateColor::Green);
+                assert_eq!(parking_info.end_time, time("2017-08-26T12:43:39+08:00"));
+                assert_eq!(transaction.payer.unwrap().openid, "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o");
+            }
+            v => panic!("{:?}", v),
+        }
+    }
+}
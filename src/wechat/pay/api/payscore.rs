@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::{LabradorResult, LabraError, OriginNotifyResponse, PayScoreConfirmExtraData, PayScoreNotifyResult, PayScorePermissionsKey, RequestType, SessionStore, WechatPayClient, WechatPayScoreAuthorizationResponse, WechatPayScoreCancelRequest, WechatPayScoreCompleteRequest, WechatPayScoreModifyRequest, WechatPayScoreNotifyResponse, WechatPayScoreOrderRequest, WechatPayScoreOrderResponse, WechatPayScorePermissionsRequest, WechatPayScorePermissionsResponse, WechatPayScoreSyncRequest, WechatPaySignType};
+use crate::util::{current_timestamp, nonce_str};
+use crate::wechat::cryptos::{SignatureHeader, WechatCryptoV3};
+use crate::wechat::pay::method::{PayScoreMethod, WechatPayMethod};
+
+/// 用户确认订单的通知类型
+const EVENT_USER_CONFIRM: &str = "PAYSCORE.USER_CONFIRM";
+/// 用户支付成功的通知类型
+const EVENT_USER_PAID: &str = "PAYSCORE.USER_PAID";
+
+/// 微信支付分
+#[derive(Debug, Clone)]
+pub struct WechatPayScore<T: SessionStore> {
+    client: WechatPayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatPayScore<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatPayClient<T>) -> WechatPayScore<T> {
+        WechatPayScore {
+            client,
+        }
+    }
+
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.pay_score()`")]
+    pub fn new(client: &WechatPayClient<T>) -> WechatPayScore<T> {
+        Self::from_client(client.clone())
+    }
+
+    fn appid(&self, appid: Option<String>) -> Option<String> {
+        appid.or_else(|| self.client.inner.appid.to_owned().into())
+    }
+
+    ///
+    /// # 创建支付分订单 - V3版本
+    /// <pre>
+    /// 需用户确认的订单返回package，通过[`confirm_extra_data`](Self::confirm_extra_data)生成拉起确认页的参数
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_14.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/serviceorder)
+    /// </pre>
+    pub async fn create(&self, mut params: WechatPayScoreOrderRequest) -> LabradorResult<WechatPayScoreOrderResponse> {
+        params.check_params()?;
+        params.appid = self.appid(params.appid);
+        self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::CreateServiceOrder), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayScoreOrderResponse>()
+    }
+
+    ///
+    /// # 查询支付分订单 - V3版本
+    /// <pre>
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_15.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/serviceorder)
+    /// </pre>
+    pub async fn query(&self, service_id: &str, out_order_no: &str) -> LabradorResult<WechatPayScoreOrderResponse> {
+        let appid = self.appid(None).unwrap_or_default();
+        let query = serde_urlencoded::to_string([("out_order_no", out_order_no), ("service_id", service_id), ("appid", appid.as_str())])?;
+        self.client.get_v3(WechatPayMethod::PayScore(PayScoreMethod::QueryServiceOrder(query)), vec![], RequestType::Json)
+            .await?.json::<WechatPayScoreOrderResponse>()
+    }
+
+    ///
+    /// # 取消支付分订单 - V3版本
+    /// <pre>
+    /// 只能取消未完结的订单
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_16.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/serviceorder/{out_order_no}/cancel)
+    /// </pre>
+    pub async fn cancel(&self, out_order_no: &str, mut params: WechatPayScoreCancelRequest) -> LabradorResult<WechatPayScoreOrderResponse> {
+        params.appid = self.appid(params.appid);
+        self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::CancelServiceOrder(out_order_no.to_string())), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayScoreOrderResponse>()
+    }
+
+    ///
+    /// # 修改订单金额 - V3版本
+    /// <pre>
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_17.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/serviceorder/{out_order_no}/modify)
+    /// </pre>
+    pub async fn modify(&self, out_order_no: &str, mut params: WechatPayScoreModifyRequest) -> LabradorResult<WechatPayScoreOrderResponse> {
+        params.check_params()?;
+        params.appid = self.appid(params.appid);
+        self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::ModifyServiceOrder(out_order_no.to_string())), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayScoreOrderResponse>()
+    }
+
+    ///
+    /// # 完结支付分订单 - V3版本
+    /// <pre>
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_18.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/serviceorder/{out_order_no}/complete)
+    /// </pre>
+    pub async fn complete(&self, out_order_no: &str, mut params: WechatPayScoreCompleteRequest) -> LabradorResult<WechatPayScoreOrderResponse> {
+        params.check_params()?;
+        params.appid = self.appid(params.appid);
+        self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::CompleteServiceOrder(out_order_no.to_string())), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayScoreOrderResponse>()
+    }
+
+    ///
+    /// # 同步服务订单信息 - V3版本
+    /// <pre>
+    /// 用户通过其它渠道支付后，同步为已收款（Order_Paid）
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_20.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/serviceorder/{out_order_no}/sync)
+    /// </pre>
+    pub async fn sync(&self, out_order_no: &str, mut params: WechatPayScoreSyncRequest) -> LabradorResult<WechatPayScoreOrderResponse> {
+        params.appid = self.appid(params.appid);
+        self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::SyncServiceOrder(out_order_no.to_string())), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayScoreOrderResponse>()
+    }
+
+    /// <pre>
+    /// 生成拉起支付分确认订单页的参数
+    /// 使用商户APIv2密钥对mch_id、package、timestamp、nonce_str、sign_type做HMAC-SHA256签名
+    /// </pre>
+    pub fn confirm_extra_data(&self, package: &str) -> LabradorResult<PayScoreConfirmExtraData> {
+        let mch_id = self.client.inner.mch_id.to_owned().unwrap_or_default();
+        let api_key = self.client.inner.api_key.to_owned().unwrap_or_default();
+        if mch_id.is_empty() || api_key.is_empty() {
+            return Err(LabraError::MissingField("生成确认订单参数需要商户号与APIv2密钥".to_string()));
+        }
+        let sign_type = WechatPaySignType::HmacSha256;
+        let mut params = BTreeMap::new();
+        params.insert("mch_id".to_string(), mch_id);
+        params.insert("package".to_string(), package.to_string());
+        params.insert("timestamp".to_string(), current_timestamp().to_string());
+        params.insert("nonce_str".to_string(), nonce_str());
+        params.insert("sign_type".to_string(), sign_type.as_str().to_string());
+        let sign = sign_type.sign(&params, &api_key)?;
+        let mut take = |key: &str| params.remove(key).unwrap_or_default();
+        Ok(PayScoreConfirmExtraData {
+            mch_id: take("mch_id"),
+            package: take("package"),
+            timestamp: take("timestamp"),
+            nonce_str: take("nonce_str"),
+            sign_type: take("sign_type"),
+            sign,
+        })
+    }
+
+    ///
+    /// # 商户预授权 - V3版本
+    /// <pre>
+    /// 返回的apply_permissions_token用于跳转支付分小程序授权页
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_2.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/permissions)
+    /// </pre>
+    pub async fn apply_permissions(&self, mut params: WechatPayScorePermissionsRequest) -> LabradorResult<WechatPayScorePermissionsResponse> {
+        params.appid = self.appid(params.appid);
+        self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::ApplyPermissions), vec![], &params, RequestType::Json)
+            .await?.json::<WechatPayScorePermissionsResponse>()
+    }
+
+    ///
+    /// # 查询用户授权记录 - V3版本
+    /// <pre>
+    /// 按授权协议号或openid查询
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_3.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/permissions/authorization-code/{authorization_code})
+    /// </pre>
+    pub async fn query_permissions(&self, service_id: &str, key: PayScorePermissionsKey) -> LabradorResult<WechatPayScoreAuthorizationResponse> {
+        let path = match &key {
+            PayScorePermissionsKey::AuthorizationCode(code) => format!("authorization-code/{}?{}", code, serde_urlencoded::to_string([("service_id", service_id)])?),
+            PayScorePermissionsKey::Openid(openid) => {
+                let appid = self.appid(None).unwrap_or_default();
+                format!("openid/{}?{}", openid, serde_urlencoded::to_string([("appid", appid.as_str()), ("service_id", service_id)])?)
+            }
+        };
+        self.client.get_v3(WechatPayMethod::PayScore(PayScoreMethod::QueryPermissions(path)), vec![], RequestType::Json)
+            .await?.json::<WechatPayScoreAuthorizationResponse>()
+    }
+
+    ///
+    /// # 解除用户授权关系 - V3版本
+    /// <pre>
+    /// 详见:[文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_4.shtml)
+    ///
+    /// [接口地址](https://api.mch.weixin.qq.com/v3/payscore/permissions/authorization-code/{authorization_code}/terminate)
+    /// </pre>
+    pub async fn terminate_permissions(&self, service_id: &str, key: PayScorePermissionsKey, reason: &str) -> LabradorResult<()> {
+        let (path, data) = match &key {
+            PayScorePermissionsKey::AuthorizationCode(code) => (format!("authorization-code/{}", code), json!({ "service_id": service_id, "reason": reason })),
+            PayScorePermissionsKey::Openid(openid) => (format!("openid/{}", openid), json!({ "service_id": service_id, "appid": self.appid(None), "reason": reason })),
+        };
+        let res = self.client.post_v3(None, WechatPayMethod::PayScore(PayScoreMethod::TerminatePermissions(path)), vec![], data, RequestType::Json).await?;
+        let _ = res.text()?;
+        Ok(())
+    }
+
+    /// # 解析支付分通知. - v3
+    /// <pre>
+    /// 用户确认订单（PAYSCORE.USER_CONFIRM）与支付成功（PAYSCORE.USER_PAID）的数据解析为支付分订单，
+    /// 其余通知保留解密后的原始数据
+    /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_21.shtml)
+    /// </pre>
+    pub async fn parse_notify(&self, notify_data: &str, header: Option<SignatureHeader>) -> LabradorResult<WechatPayScoreNotifyResponse> {
+        let header = header.ok_or_else(|| LabraError::RequestError("非法请求，头部信息为空".to_string()))?;
+        if !self.client.verify_notify_sign(&header, notify_data).await {
+            return Err(LabraError::RequestError("非法请求，头部信息验证失败".to_string()));
+        }
+        let origin = serde_json::from_str::<OriginNotifyResponse>(notify_data)?;
+        let v3_key = self.client.inner.api_key_v3.to_owned().unwrap_or_default();
+        let crypto = WechatCryptoV3::new(&v3_key);
+        let decrypted = crypto.decrypt_data_v3(&origin.resource)?;
+        let result = match origin.event_type.as_str() {
+            EVENT_USER_CONFIRM => PayScoreNotifyResult::UserConfirm(serde_json::from_slice(&decrypted)?),
+            EVENT_USER_PAID => PayScoreNotifyResult::UserPaid(serde_json::from_slice(&decrypted)?),
+            _ => PayScoreNotifyResult::Other(serde_json::from_slice::<Value>(&decrypted)?),
+        };
+        Ok(WechatPayScoreNotifyResponse {
+            raw_data: origin.into(),
+            result: result.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use openssl::symm;
+    use serde_json::{json, Value};
+
+    use crate::{LabraError, PayScoreNotifyResult, PayScorePostDiscount, PayScorePostPayment, PayScoreRiskFund, PayScoreTime, PayScoreTimeRange, WechatPayScoreCompleteRequest, WechatPayScoreOrderRequest, WechatPaySignType};
+    use crate::util::mock::MockServer;
+    use crate::util::prp::PrpCrypto;
+    use crate::wechat::cryptos::SignatureHeader;
+    use crate::wechat::pay::tests::{generate_cert, pay_client, signed_response};
+
+    const API_V3_KEY: &str = "a7cde1ef41e24d64b3f8c0be2c5b8e3a";
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    fn payment(name: &str, amount: i64) -> PayScorePostPayment {
+        PayScorePostPayment { name: name.to_string().into(), amount: amount.into(), description: None, count: 1.into() }
+    }
+
+    fn complete_request(total_amount: i64) -> WechatPayScoreCompleteRequest {
+        WechatPayScoreCompleteRequest {
+            appid: None,
+            service_id: "500001".to_string(),
+            post_payments: vec![payment("租借费", 4000), payment("赔偿费", 1000)],
+            post_discounts: vec![PayScorePostDiscount { name: "新用户优惠".to_string().into(), description: None, amount: 500.into(), count: 1.into() }],
+            total_amount,
+            time_range: PayScoreTimeRange {
+                start_time: PayScoreTime::DateTime(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap().and_hms_opt(9, 0, 0).unwrap()),
+                start_time_remark: None,
+                end_time: PayScoreTime::Date(NaiveDate::from_ymd_opt(2021, 6, 3).unwrap()).into(),
+                end_time_remark: "归还时间".to_string().into(),
+            }.into(),
+            location: None,
+            profit_sharing: false.into(),
+            goods_tag: None,
+        }
+    }
+
+    #[test]
+    fn test_time_serialize() {
+        let time_range = PayScoreTimeRange {
+            start_time: PayScoreTime::OnAccept,
+            start_time_remark: None,
+            end_time: PayScoreTime::Date(NaiveDate::from_ymd_opt(2021, 6, 3).unwrap()).into(),
+            end_time_remark: None,
+        };
+        let v = serde_json::to_value(&time_range).unwrap();
+        assert_eq!(v, json!({"start_time": "OnAccept", "end_time": "20210603"}));
+        assert_eq!(serde_json::from_value::<PayScoreTimeRange>(v).unwrap(), time_range);
+        let time = serde_json::from_str::<PayScoreTime>(r#""20210601091500""#).unwrap();
+        assert_eq!(time, PayScoreTime::DateTime(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap().and_hms_opt(9, 15, 0).unwrap()));
+        assert_eq!(time.to_string(), "20210601091500");
+        for invalid in ["onaccept", "2021-06-01", "202106", "20211301"].iter() {
+            assert!(serde_json::from_value::<PayScoreTime>(json!(invalid)).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_check_params() {
+        assert!(complete_request(4500).check_params().is_ok());
+        assert!(matches!(complete_request(5000).check_params(), Err(LabraError::RequestError(msg)) if msg.contains("5000")));
+        let mut req = complete_request(4500);
+        req.time_range.as_mut().unwrap().start_time = PayScoreTime::OnAccept;
+        assert!(req.check_params().is_err());
+        let mut req = complete_request(4500);
+        req.post_payments[0].amount = None;
+        assert!(req.check_params().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        let (private_key, cert) = generate_cert();
+        let response = r#"{"appid":"wxd930ea5d5a258f4f","mchid":"1230000109","service_id":"500001","out_order_no":"1234323JKHDFE1243252","state":"DOING","state_description":"MCH_COMPLETE","total_amount":4500,"order_id":"15646546545165651651","need_collection":true}"#;
+        let server = MockServer::start(vec![signed_response(&private_key, &cert.serial_no, response)]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let res = client.pay_score().complete("1234323JKHDFE1243252", complete_request(4500)).await.unwrap();
+        assert_eq!(res.state_description.as_deref(), Some("MCH_COMPLETE"));
+        assert_eq!(res.total_amount, Some(4500));
+        assert!(!res.is_paid());
+        // 金额不一致时不发送请求
+        assert!(client.pay_score().complete("1234323JKHDFE1243252", complete_request(5000)).await.is_err());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /v3/payscore/serviceorder/1234323JKHDFE1243252/complete HTTP/1.1"), "{}", requests[0]);
+        assert_eq!(body(&requests[0]), json!({
+            "appid": "wxd930ea5d5a258f4f",
+            "service_id": "500001",
+            "post_payments": [{"name": "租借费", "amount": 4000, "count": 1}, {"name": "赔偿费", "amount": 1000, "count": 1}],
+            "post_discounts": [{"name": "新用户优惠", "amount": 500, "count": 1}],
+            "total_amount": 4500,
+            "time_range": {"start_time": "20210601090000", "end_time": "20210603", "end_time_remark": "归还时间"},
+            "profit_sharing": false
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_create_need_user_confirm() {
+        let (private_key, cert) = generate_cert();
+        let response = r#"{"appid":"wxd930ea5d5a258f4f","mchid":"1230000109","out_order_no":"1234323JKHDFE1243252","service_id":"500001","state":"CREATED","order_id":"15646546545165651651","package":"DJIOSQPYWDxsjdldeuwhdodwxasd_dDiodnwjh9we"}"#;
+        let server = MockServer::start(vec![signed_response(&private_key, &cert.serial_no, response)]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert).key("192006250b4c09247ec02edce69f6a2d".to_string());
+        let req = WechatPayScoreOrderRequest {
+            out_order_no: "1234323JKHDFE1243252".to_string(),
+            appid: None,
+            service_id: "500001".to_string(),
+            service_introduction: "充电宝租借".to_string(),
+            post_payments: vec![PayScorePostPayment { name: "租借费".to_string().into(), description: "2元/小时".to_string().into(), ..Default::default() }],
+            post_discounts: vec![],
+            time_range: PayScoreTimeRange { start_time: PayScoreTime::OnAccept, start_time_remark: None, end_time: None, end_time_remark: None },
+            location: None,
+            risk_fund: PayScoreRiskFund { name: "DEPOSIT".to_string(), amount: 9900, description: "充电宝押金".to_string().into() },
+            attach: None,
+            notify_url: "https://api.test.com".to_string(),
+            openid: None,
+            need_user_confirm: true,
+        };
+        let res = client.pay_score().create(req).await.unwrap();
+        let package = res.package.unwrap();
+        let extra_data = client.pay_score().confirm_extra_data(&package).unwrap();
+        assert_eq!(extra_data.mch_id, "1230000109");
+        assert_eq!(extra_data.package, package);
+        assert_eq!(extra_data.sign_type, "HMAC-SHA256");
+        let params = serde_json::from_value::<BTreeMap<String, String>>(serde_json::to_value(&extra_data).unwrap()).unwrap();
+        assert!(WechatPaySignType::verify(&params, "192006250b4c09247ec02edce69f6a2d"));
+
+        let sent = body(&server.requests()[0]);
+        assert_eq!(sent["time_range"], json!({"start_time": "OnAccept"}));
+        assert_eq!(sent["risk_fund"], json!({"name": "DEPOSIT", "amount": 9900, "description": "充电宝押金"}));
+        assert_eq!(sent["need_user_confirm"], true);
+        assert!(sent.get("openid").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_parse_notify() {
+        let (private_key, cert) = generate_cert();
+        let serial_no = cert.serial_no.to_owned();
+        let client = pay_client("http://127.0.0.1:1".to_string(), &private_key, cert).key_v3(API_V3_KEY.to_string());
+        let resource = json!({
+            "appid": "wxd930ea5d5a258f4f", "mchid": "1230000109", "out_order_no": "1234323JKHDFE1243252", "service_id": "500001",
+            "state": "DONE", "total_amount": 4500, "order_id": "15646546545165651651", "need_collection": true,
+            "collection": {"state": "USER_PAID", "total_amount": 4500, "paying_amount": 0, "paid_amount": 4500, "details": [{"seq": 1, "amount": 4500, "paid_type": "NEWTON", "paid_time": "20210603180000", "transaction_id": "4200000001201811162363945412"}]}
+        });
+        let nonce = "fdasflkja484";
+        let associated_data = "payscore";
+        let mut tag = vec![0u8; 16];
+        let mut ciphertext = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), API_V3_KEY.as_bytes(), Some(nonce.as_bytes()), associated_data.as_bytes(), resource.to_string().as_bytes(), &mut tag).unwrap();
+        ciphertext.extend(tag);
+        let notify = json!({
+            "id": "EV-2018022511223320873",
+            "create_time": "2021-06-03T18:00:00+08:00",
+            "resource_type": "encrypt-resource",
+            "event_type": "PAYSCORE.USER_PAID",
+            "summary": "微信支付分服务订单支付成功",
+            "resource": {"original_type": "payscore", "algorithm": "AEAD_AES_256_GCM", "ciphertext": base64::encode(&ciphertext), "associated_data": associated_data, "nonce": nonce}
+        }).to_string();
+        let timestamp = "1554208460";
+        let header = SignatureHeader {
+            time_stamp: timestamp.to_string(),
+            nonce: nonce.to_string(),
+            signature: PrpCrypto::rsa_sha256_sign(&format!("{}\n{}\n{}\n", timestamp, nonce, notify), &private_key).unwrap(),
+            serial: serial_no,
+        };
+        match client.pay_score().parse_notify(&notify, Some(header)).await.unwrap().result.unwrap() {
+            PayScoreNotifyResult::UserPaid(order) => {
+                assert!(order.is_paid());
+                assert_eq!(order.collection.unwrap().details[0].transaction_id.as_deref(), Some("4200000001201811162363945412"));
+            }
+            v => panic!("{:?}", v),
+        }
+    }
+}
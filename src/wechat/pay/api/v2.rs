@@ -0,0 +1,506 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{IdempotencyKey, LabradorResult, LabraError, SessionStore, WechatPayClient, WechatPaySendRedpackRequest, WechatPaySendRedpackResponse, WechatPaySignType, WechatPayTransfersRequest, WechatPayTransfersResponse};
+use crate::serde_helper::beijing;
+use crate::util::nonce_str;
+use crate::wechat::pay::method::{EntPayMethod, WechatPayMethod};
+use crate::wechat::pay::sign::{params_to_xml, xml_to_params};
+use crate::wechat::pay::{AppId, MchId};
+
+const SUCCESS: &str = "SUCCESS";
+const FAIL: &str = "FAIL";
+
+/// 微信支付V2接口（XML报文，MD5/HMAC-SHA256签名）
+///
+/// 现金红包、企业付款到零钱等仅提供V2接口的能力，签名使用商户API密钥（`WechatPayClient::key`）
+#[derive(Debug, Clone)]
+pub struct WechatPayV2<T: SessionStore> {
+    client: WechatPayClient<T>,
+}
+
+#[allow(unused)]
+impl<T: SessionStore> WechatPayV2<T> {
+
+    #[inline]
+    pub fn from_client(client: WechatPayClient<T>) -> WechatPayV2<T> {
+        WechatPayV2 {
+            client,
+        }
+    }
+
+    /// <pre>
+    /// 发放现金红包
+    /// 需要商户API证书，仅支持MD5签名
+    /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/tools/cash_coupon.php?chapter=13_4&index=3)
+    /// </pre>
+    pub async fn send_redpack(&self, mut req: WechatPaySendRedpackRequest) -> LabradorResult<WechatPaySendRedpackResponse> {
+        if req.mch_id.is_none() {
+            req.mch_id = self.client.inner.mch_id.to_owned();
+        }
+        if req.wxappid.is_none() {
+            req.wxappid = self.client.inner.appid.to_owned().into();
+        }
+        check_mch(req.mch_id.as_deref(), req.wxappid.as_deref())?;
+        let params = to_params(&req)?;
+        let res = self.execute(WechatPayMethod::EntPay(EntPayMethod::SendRedpack), params, WechatPaySignType::Md5, true).await?;
+        from_params(res)
+    }
+
+    /// <pre>
+    /// 企业付款到零钱
+    /// 需要商户API证书，仅支持MD5签名
+    /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/tools/mch_pay.php?chapter=14_2)
+    /// </pre>
+    pub async fn transfers(&self, mut req: WechatPayTransfersRequest) -> LabradorResult<WechatPayTransfersResponse> {
+        if req.mchid.is_none() {
+            req.mchid = self.client.inner.mch_id.to_owned();
+        }
+        if req.mch_appid.is_none() {
+            req.mch_appid = self.client.inner.appid.to_owned().into();
+        }
+        check_mch(req.mchid.as_deref(), req.mch_appid.as_deref())?;
+        let params = to_params(&req)?;
+        let res = self.execute(WechatPayMethod::EntPay(EntPayMethod::Transfers), params, WechatPaySignType::Md5, true).await?;
+        from_params(res)
+    }
+
+    /// <pre>
+    /// 企业付款到零钱，商户付款单号（partner_trade_no）由key提供
+    /// 自动生成单号时，hook在发送请求前调用，hook返回错误时不发送请求
+    /// </pre>
+    pub async fn transfers_with_key(&self, mut req: WechatPayTransfersRequest, key: IdempotencyKey) -> LabradorResult<WechatPayTransfersResponse> {
+        req.partner_trade_no = key.resolve().await?;
+        self.transfers(req).await
+    }
+
+    /// <pre>
+    /// 调用V2接口
+    /// 补全nonce_str，HMAC-SHA256签名时补全sign_type，计算sign后以XML发送
+    /// return_code、result_code不为SUCCESS时返回`LabraError::ClientError`，应答带sign时校验签名
+    /// </pre>
+    pub(crate) async fn execute(&self, method: WechatPayMethod, mut params: BTreeMap<String, String>, sign_type: WechatPaySignType, use_cert: bool) -> LabradorResult<BTreeMap<String, String>> {
+        let key = self.client.sign_key(&self.client.inner.api_key.to_owned().unwrap_or_default()).await?;
+        if key.is_empty() {
+            return Err(LabraError::MissingField("V2接口需要设置商户API密钥(key)".to_string()));
+        }
+        params.entry("nonce_str".to_string()).or_insert_with(nonce_str);
+        if sign_type == WechatPaySignType::HmacSha256 {
+            params.insert("sign_type".to_string(), sign_type.as_str().to_string());
+        }
+        let sign = sign_type.sign(&params, &key)?;
+        params.insert("sign".to_string(), sign);
+        let res = self.client.post_xml(method, params_to_xml(&params), use_cert).await?.text()?;
+        let res = xml_to_params(&res)?;
+        if res.get("return_code").map(String::as_str) != Some(SUCCESS) {
+            return Err(LabraError::ClientError { errcode: res.get("return_code").cloned().unwrap_or_default(), errmsg: res.get("return_msg").cloned().unwrap_or_default() });
+        }
+        if res.get("result_code").map(String::as_str) != Some(SUCCESS) {
+            return Err(LabraError::ClientError { errcode: res.get("err_code").cloned().unwrap_or_default(), errmsg: res.get("err_code_des").cloned().unwrap_or_default() });
+        }
+        if res.contains_key("sign") && !WechatPaySignType::verify(&res, &key) {
+            return Err(LabraError::InvalidSignature("V2应答签名校验失败".to_string()));
+        }
+        Ok(res)
+    }
+
+    /// <pre>
+    /// 解析并校验V2支付结果通知
+    /// 使用商户API密钥校验签名，沙箱环境中为沙箱密钥
+    /// </pre>
+    pub async fn parse_notify(&self, xml: &str, sign_type: WechatPaySignType) -> LabradorResult<WechatPayV2Notify> {
+        let key = self.client.sign_key(&self.client.inner.api_key.to_owned().unwrap_or_default()).await?;
+        WechatPayV2Notify::parse_and_verify(xml, &key, sign_type)
+    }
+}
+
+/// <pre>
+/// V2支付结果通知
+/// 仍在使用V2接口下单的商户会收到XML格式的回调，需校验sign后再处理，处理完成后返回[`WechatPayV2Notify::render_success_response`]
+/// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/jsapi.php?chapter=9_7)
+/// </pre>
+#[derive(Debug, Clone, PartialEq)]
+pub struct WechatPayV2Notify {
+    pub appid: String,
+    pub mch_id: String,
+    /// 业务结果，SUCCESS表示支付成功
+    pub result_code: String,
+    pub err_code: Option<String>,
+    pub err_code_des: Option<String>,
+    pub openid: String,
+    pub trade_type: Option<String>,
+    /// 付款银行
+    pub bank_type: Option<String>,
+    /// 订单金额，单位为分
+    pub total_fee: i64,
+    /// 现金支付金额，单位为分
+    pub cash_fee: Option<i64>,
+    pub fee_type: Option<String>,
+    /// 微信支付订单号
+    pub transaction_id: String,
+    /// 商户订单号
+    pub out_trade_no: String,
+    /// 商家数据包
+    pub attach: Option<String>,
+    /// 支付完成时间（北京时间）
+    pub time_end: DateTime<FixedOffset>,
+    /// 通知中的全部参数，包含代金券（coupon_fee_$n等）等未列出的字段
+    pub params: BTreeMap<String, String>,
+}
+
+impl WechatPayV2Notify {
+    /// <pre>
+    /// 解析通知XML并校验签名
+    /// 参数按参数名排序，值为空的参数与sign不参与签名；通知中带sign_type时须与sign_type一致
+    /// return_code不为SUCCESS时返回`LabraError::ClientError`，签名校验失败时返回`LabraError::InvalidSignature`
+    /// result_code为FAIL的通知仍会正常返回，通过[`WechatPayV2Notify::is_success`]判断是否支付成功
+    /// </pre>
+    pub fn parse_and_verify(xml: &str, key: &str, sign_type: WechatPaySignType) -> LabradorResult<Self> {
+        let params = xml_to_params(xml)?;
+        if params.get("return_code").map(String::as_str) != Some(SUCCESS) {
+            return Err(LabraError::ClientError { errcode: params.get("return_code").cloned().unwrap_or_default(), errmsg: params.get("return_msg").cloned().unwrap_or_default() });
+        }
+        if let Some(notify_sign_type) = params.get("sign_type") {
+            if WechatPaySignType::parse(notify_sign_type) != Some(sign_type) {
+                return Err(LabraError::InvalidSignature(format!("通知的签名类型{}与{}不一致", notify_sign_type, sign_type.as_str())));
+            }
+        }
+        let sign = params.get("sign").map(String::as_str).unwrap_or_default();
+        if sign.is_empty() || !sign_type.sign(&params, key)?.eq_ignore_ascii_case(sign) {
+            return Err(LabraError::InvalidSignature("V2支付结果通知签名校验失败".to_string()));
+        }
+        Self::from_params(params)
+    }
+
+    fn from_params(params: BTreeMap<String, String>) -> LabradorResult<Self> {
+        let required = |name: &str| params.get(name).filter(|v| !v.is_empty()).cloned()
+            .ok_or_else(|| LabraError::MissingField(format!("通知缺少{}", name)));
+        let optional = |name: &str| params.get(name).filter(|v| !v.is_empty()).cloned();
+        let fee = |name: &str, value: &str| value.parse::<i64>()
+            .map_err(|_| LabraError::DecodeError(format!("{}不是以分为单位的整数：{}", name, value).into()));
+        let time_end = required("time_end")?;
+        let time_end = NaiveDateTime::parse_from_str(&time_end, "%Y%m%d%H%M%S").ok()
+            .and_then(|v| beijing().from_local_datetime(&v).single())
+            .ok_or_else(|| LabraError::DecodeError(format!("time_end格式有误，应为yyyyMMddHHmmss：{}", time_end).into()))?;
+        Ok(WechatPayV2Notify {
+            appid: required("appid")?,
+            mch_id: required("mch_id")?,
+            result_code: required("result_code")?,
+            err_code: optional("err_code"),
+            err_code_des: optional("err_code_des"),
+            openid: required("openid")?,
+            trade_type: optional("trade_type"),
+            bank_type: optional("bank_type"),
+            total_fee: fee("total_fee", &required("total_fee")?)?,
+            cash_fee: optional("cash_fee").map(|v| fee("cash_fee", &v)).transpose()?,
+            fee_type: optional("fee_type"),
+            transaction_id: required("transaction_id")?,
+            out_trade_no: required("out_trade_no")?,
+            attach: optional("attach"),
+            time_end,
+            params,
+        })
+    }
+
+    /// 是否支付成功
+    pub fn is_success(&self) -> bool {
+        self.result_code == SUCCESS
+    }
+
+    /// 处理成功后返回给微信的应答
+    pub fn render_success_response() -> String {
+        render_response(SUCCESS, "OK")
+    }
+
+    /// <pre>
+    /// 处理失败时返回给微信的应答，微信会稍后重新通知
+    /// msg为失败原因
+    /// </pre>
+    pub fn render_fail_response(msg: &str) -> String {
+        render_response(FAIL, msg)
+    }
+}
+
+fn render_response(return_code: &str, return_msg: &str) -> String {
+    let mut params = BTreeMap::new();
+    params.insert("return_code".to_string(), return_code.to_string());
+    params.insert("return_msg".to_string(), return_msg.to_string());
+    params_to_xml(&params)
+}
+
+/// 校验商户号与appid
+fn check_mch(mchid: Option<&str>, appid: Option<&str>) -> LabradorResult<()> {
+    MchId::validate(mchid.unwrap_or_default())?;
+    if let Some(appid) = appid {
+        AppId::validate(appid)?;
+    }
+    Ok(())
+}
+
+/// 请求结构转换为参数表，忽略值为`null`的字段
+fn to_params<D: Serialize>(data: &D) -> LabradorResult<BTreeMap<String, String>> {
+    let mut params = BTreeMap::new();
+    if let Value::Object(map) = serde_json::to_value(data)? {
+        for (k, v) in map {
+            match v {
+                Value::Null => {}
+                Value::String(v) => { params.insert(k, v); }
+                v => { params.insert(k, v.to_string()); }
+            }
+        }
+    }
+    Ok(params)
+}
+
+fn from_params<D: DeserializeOwned>(params: BTreeMap<String, String>) -> LabradorResult<D> {
+    let v = params.into_iter().map(|(k, v)| (k, Value::String(v))).collect::<serde_json::Map<String, Value>>();
+    Ok(serde_json::from_value(Value::Object(v))?)
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509, X509NameBuilder};
+
+    use chrono::{FixedOffset, TimeZone};
+
+    use crate::{APIClient, IdempotencyKey, LabraError, LabraIdentity, SimpleStorage, TradeNo, WechatPayClient, WechatPaySendRedpackRequest, WechatPaySignType, WechatPayTransfersRequest, WechatPayV2Notify};
+    use crate::util::mock::{MockResponse, MockServer};
+    use crate::wechat::pay::sign::{params_to_xml, xml_to_params};
+
+    const KEY: &str = "192006250b4c09247ec02edce69f6a2d";
+
+    /// 自签名的商户API证书
+    fn identity() -> LabraIdentity {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "1900000109").unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        LabraIdentity::from_pkcs8_pem(&builder.build().to_pem().unwrap(), &pkey.private_key_to_pem_pkcs8().unwrap()).unwrap()
+    }
+
+    fn client(api_path: &str) -> WechatPayClient<SimpleStorage> {
+        let client = APIClient::from_session("wx8888888888888888".to_string(), "secret".to_string(), api_path, SimpleStorage::new());
+        WechatPayClient::from_client(client).mch_id("1900000109".to_string()).key(KEY.to_string())
+    }
+
+    fn redpack() -> WechatPaySendRedpackRequest {
+        WechatPaySendRedpackRequest {
+            mch_billno: "10000098201411111234567890".to_string(),
+            send_name: "天虹百货".to_string(),
+            re_openid: "oxTWIuGaIt6gTKsQRLau2M0yL16E".to_string(),
+            total_amount: 100,
+            total_num: 1,
+            wishing: "感谢您参加猜灯谜活动，祝您元宵节快乐！".to_string(),
+            client_ip: "192.168.0.1".to_string(),
+            act_name: "猜灯谜抢红包活动".to_string(),
+            remark: "猜越多得越多，快来抢！".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 请求体解析为参数表
+    fn request_params(request: &str) -> BTreeMap<String, String> {
+        xml_to_params(&request[request.find("<xml>").unwrap()..]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_send_redpack() {
+        let server = MockServer::start(vec![MockResponse::json("<xml>\
+            <return_code><![CDATA[SUCCESS]]></return_code>\
+            <return_msg><![CDATA[发放成功]]></return_msg>\
+            <result_code><![CDATA[SUCCESS]]></result_code>\
+            <err_code><![CDATA[SUCCESS]]></err_code>\
+            <err_code_des><![CDATA[发放成功]]></err_code_des>\
+            <mch_billno><![CDATA[10000098201411111234567890]]></mch_billno>\
+            <mch_id><![CDATA[1900000109]]></mch_id>\
+            <wxappid><![CDATA[wx8888888888888888]]></wxappid>\
+            <re_openid><![CDATA[oxTWIuGaIt6gTKsQRLau2M0yL16E]]></re_openid>\
+            <total_amount>100</total_amount>\
+            <send_listid><![CDATA[1000041701201411111234567890]]></send_listid>\
+            </xml>")]).await;
+        let client = client(&server.url).client_identity(identity()).unwrap();
+        let res = client.wxpay_v2().send_redpack(redpack()).await.unwrap();
+        assert_eq!(res.total_amount, 100);
+        assert_eq!(res.send_listid.as_deref(), Some("1000041701201411111234567890"));
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /mmpaymkttransfers/sendredpack HTTP/1.1"));
+        let params = request_params(&requests[0]);
+        assert_eq!(params["mch_id"], "1900000109");
+        assert_eq!(params["wxappid"], "wx8888888888888888");
+        assert_eq!(params["total_amount"], "100");
+        assert!(!params["nonce_str"].is_empty());
+        assert!(!params.contains_key("sign_type"));
+        assert!(!params.contains_key("scene_id"));
+        assert_eq!(params["sign"], WechatPaySignType::Md5.sign(&params, KEY).unwrap());
+        assert!(WechatPaySignType::verify(&params, KEY));
+    }
+
+    #[tokio::test]
+    async fn test_transfers_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::json("<xml><return_code><![CDATA[SUCCESS]]></return_code><return_msg><![CDATA[]]></return_msg>\
+                <result_code><![CDATA[FAIL]]></result_code><err_code><![CDATA[NOTENOUGH]]></err_code><err_code_des><![CDATA[余额不足]]></err_code_des></xml>"),
+            MockResponse::json("<xml><return_code><![CDATA[FAIL]]></return_code><return_msg><![CDATA[签名错误]]></return_msg></xml>"),
+        ]).await;
+        let req = WechatPayTransfersRequest {
+            mch_appid: None,
+            mchid: None,
+            device_info: None,
+            partner_trade_no: TradeNo::try_new("10000098201411111234567890").unwrap(),
+            openid: "oxTWIuGaIt6gTKsQRLau2M0yL16E".to_string(),
+            check_name: "NO_CHECK".to_string(),
+            re_user_name: None,
+            amount: 100,
+            desc: "理赔".to_string(),
+            spbill_create_ip: None,
+            nonce_str: None,
+        };
+        // 未配置商户API证书时不发送请求
+        let err = client(&server.url).wxpay_v2().transfers(req.clone()).await.unwrap_err();
+        assert!(matches!(err, LabraError::MissingField(_)));
+        assert!(server.requests().is_empty());
+
+        let client = client(&server.url).client_identity(identity()).unwrap();
+        let err = client.wxpay_v2().transfers(req.clone()).await.unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "NOTENOUGH" && errmsg == "余额不足"));
+        let err = client.wxpay_v2().transfers(req).await.unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "FAIL" && errmsg == "签名错误"));
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /mmpaymkttransfers/promotion/transfers HTTP/1.1"));
+        let params = request_params(&requests[0]);
+        assert_eq!(params["mchid"], "1900000109");
+        assert_eq!(params["mch_appid"], "wx8888888888888888");
+        assert!(WechatPaySignType::verify(&params, KEY));
+    }
+
+    #[tokio::test]
+    async fn test_transfers_with_key() {
+        let server = MockServer::start(vec![MockResponse::json("<xml><return_code><![CDATA[SUCCESS]]></return_code><return_msg><![CDATA[]]></return_msg>\
+            <mch_appid><![CDATA[wx8888888888888888]]></mch_appid><mchid><![CDATA[1900000109]]></mchid><result_code><![CDATA[SUCCESS]]></result_code>\
+            <partner_trade_no><![CDATA[TRANSFER_00000001]]></partner_trade_no><payment_no><![CDATA[1000018301201505190181489473]]></payment_no><payment_time><![CDATA[2015-05-19 15:26:59]]></payment_time></xml>")]).await;
+        let client = client(&server.url).client_identity(identity()).unwrap();
+        let req = WechatPayTransfersRequest {
+            mch_appid: None,
+            mchid: None,
+            device_info: None,
+            partner_trade_no: TradeNo::try_new("PLACEHOLDER").unwrap(),
+            openid: "oxTWIuGaIt6gTKsQRLau2M0yL16E".to_string(),
+            check_name: "NO_CHECK".to_string(),
+            re_user_name: None,
+            amount: 100,
+            desc: "理赔".to_string(),
+            spbill_create_ip: None,
+            nonce_str: None,
+        };
+        // hook返回错误时不发送请求
+        let key = IdempotencyKey::generated(|_| async { Err(LabraError::RequestError("保存付款单号失败".to_string())) });
+        assert!(client.wxpay_v2().transfers_with_key(req.clone(), key).await.is_err());
+        assert!(server.requests().is_empty());
+
+        let key = IdempotencyKey::explicit(TradeNo::try_new("TRANSFER_00000001").unwrap());
+        client.wxpay_v2().transfers_with_key(req, key).await.unwrap();
+        let params = request_params(&server.requests()[0]);
+        assert_eq!(params["partner_trade_no"], "TRANSFER_00000001");
+    }
+
+    /// 文档中的支付结果通知示例，sign使用测试密钥重新计算
+    fn notify_params(sign_type: WechatPaySignType) -> BTreeMap<String, String> {
+        let mut params = xml_to_params("<xml>\
+            <appid><![CDATA[wx2421b1c4370ec43b]]></appid>\
+            <attach><![CDATA[支付测试]]></attach>\
+            <bank_type><![CDATA[CFT]]></bank_type>\
+            <fee_type><![CDATA[CNY]]></fee_type>\
+            <is_subscribe><![CDATA[Y]]></is_subscribe>\
+            <mch_id><![CDATA[10000100]]></mch_id>\
+            <nonce_str><![CDATA[5d2b6c2a8db53831f7eda20af46e531c]]></nonce_str>\
+            <openid><![CDATA[oUpF8uMEb4qRXf22hE3X68TekukE]]></openid>\
+            <out_trade_no><![CDATA[1409811653]]></out_trade_no>\
+            <result_code><![CDATA[SUCCESS]]></result_code>\
+            <return_code><![CDATA[SUCCESS]]></return_code>\
+            <time_end><![CDATA[20140903131540]]></time_end>\
+            <total_fee>1</total_fee>\
+            <coupon_fee_0><![CDATA[10]]></coupon_fee_0>\
+            <coupon_count><![CDATA[1]]></coupon_count>\
+            <coupon_type><![CDATA[CASH]]></coupon_type>\
+            <coupon_id><![CDATA[10000]]></coupon_id>\
+            <trade_type><![CDATA[JSAPI]]></trade_type>\
+            <transaction_id><![CDATA[1004400740201409030005092168]]></transaction_id>\
+            </xml>").unwrap();
+        if sign_type == WechatPaySignType::HmacSha256 {
+            params.insert("sign_type".to_string(), sign_type.as_str().to_string());
+        }
+        let sign = sign_type.sign(&params, KEY).unwrap();
+        params.insert("sign".to_string(), sign);
+        params
+    }
+
+    #[test]
+    fn test_parse_notify() {
+        let xml = params_to_xml(&notify_params(WechatPaySignType::Md5));
+        let notify = WechatPayV2Notify::parse_and_verify(&xml, KEY, WechatPaySignType::Md5).unwrap();
+        assert!(notify.is_success());
+        assert_eq!(notify.out_trade_no, "1409811653");
+        assert_eq!(notify.openid, "oUpF8uMEb4qRXf22hE3X68TekukE");
+        assert_eq!(notify.total_fee, 1);
+        assert_eq!(notify.cash_fee, None);
+        assert_eq!(notify.attach.as_deref(), Some("支付测试"));
+        assert_eq!(notify.time_end, FixedOffset::east_opt(8 * 3600).unwrap().with_ymd_and_hms(2014, 9, 3, 13, 15, 40).unwrap());
+        assert_eq!(notify.time_end.timestamp(), 1409721340);
+        assert_eq!(notify.params["coupon_fee_0"], "10");
+
+        let xml = params_to_xml(&notify_params(WechatPaySignType::HmacSha256));
+        assert!(WechatPayV2Notify::parse_and_verify(&xml, KEY, WechatPaySignType::HmacSha256).is_ok());
+        // 签名类型不一致
+        assert!(matches!(WechatPayV2Notify::parse_and_verify(&xml, KEY, WechatPaySignType::Md5), Err(LabraError::InvalidSignature(_))));
+
+        // return_code为FAIL时不带sign
+        let err = WechatPayV2Notify::parse_and_verify("<xml><return_code><![CDATA[FAIL]]></return_code><return_msg><![CDATA[参数格式校验错误]]></return_msg></xml>", KEY, WechatPaySignType::Md5).unwrap_err();
+        assert!(matches!(err, LabraError::ClientError { errcode, errmsg } if errcode == "FAIL" && errmsg == "参数格式校验错误"));
+    }
+
+    #[test]
+    fn test_parse_tampered_notify() {
+        let mut params = notify_params(WechatPaySignType::Md5);
+        params.insert("total_fee".to_string(), "100".to_string());
+        let err = WechatPayV2Notify::parse_and_verify(&params_to_xml(&params), KEY, WechatPaySignType::Md5).unwrap_err();
+        assert!(matches!(err, LabraError::InvalidSignature(_)));
+
+        let xml = params_to_xml(&notify_params(WechatPaySignType::Md5));
+        assert!(matches!(WechatPayV2Notify::parse_and_verify(&xml, "wrong_key", WechatPaySignType::Md5), Err(LabraError::InvalidSignature(_))));
+        let mut params = notify_params(WechatPaySignType::Md5);
+        params.remove("sign");
+        assert!(matches!(WechatPayV2Notify::parse_and_verify(&params_to_xml(&params), KEY, WechatPaySignType::Md5), Err(LabraError::InvalidSignature(_))));
+
+        // 签名正确但金额格式有误
+        let mut params = notify_params(WechatPaySignType::Md5);
+        params.insert("total_fee".to_string(), "0.01".to_string());
+        let sign = WechatPaySignType::Md5.sign(&params, KEY).unwrap();
+        params.insert("sign".to_string(), sign);
+        assert!(matches!(WechatPayV2Notify::parse_and_verify(&params_to_xml(&params), KEY, WechatPaySignType::Md5), Err(LabraError::DecodeError(_))));
+    }
+
+    #[test]
+    fn test_render_response() {
+        assert_eq!(WechatPayV2Notify::render_success_response(), "<xml><return_code><![CDATA[SUCCESS]]></return_code><return_msg><![CDATA[OK]]></return_msg></xml>");
+        assert_eq!(WechatPayV2Notify::render_fail_response("签名失败"), "<xml><return_code><![CDATA[FAIL]]></return_code><return_msg><![CDATA[签名失败]]></return_msg></xml>");
+    }
+}
@@ -1,25 +1,31 @@
 use serde_json::Value;
-use crate::{DecryptNotifyResult, DecryptRefundNotifyResult, IsvWechatPayRequestV3, LabradorResult, LabraError, OriginNotifyResponse, RequestType, SessionStore, WechatCloseOrderRequest, WechatCloseOrderRequestV3, WechatCloseOrderResponse, WechatDecryptRefundNotifyResponse, WechatOrderReverseRequest, WechatOrderReverseResponse, WechatPayClient, WechatPayNotifyResponse, WechatPayNotifyResponseV3, WechatPayRequestV3, WechatPayResponse, WechatPayResponseV3, WechatQueryOrderRequest, WechatQueryOrderRequestV3, WechatQueryOrderResponse, WechatQueryOrderResponseV3, WechatQueryRefundOrderRequest, WechatQueryRefundResponse, WechatQueryRefundResponseV3, WechatRefundNotifyResponse, WechatRefundNotifyResponseV3, WechatRefundRequest, WechatRefundRequestV3, WechatRefundResponse, WechatRefundResponseV3, WxPayShorturlRequest, WxPayShortUrlResponse, WxScanPayNotifyResponse};
+use crate::{DecryptNotifyResult, DecryptRefundNotifyResult, IdempotencyKey, IsvWechatPayRequestV3, LabradorResult, LabraError, OriginNotifyResponse, RequestType, SessionStore, WechatCloseOrderRequest, WechatCloseOrderRequestV3, WechatCloseOrderResponse, WechatDecryptRefundNotifyResponse, WechatOrderReverseRequest, WechatOrderReverseResponse, WechatPayClient, WechatPayNotifyResponse, WechatPayNotifyResponseV3, WechatPayRequestV3, WechatPayResponse, WechatPayResponseV3, WechatQueryOrderRequest, WechatQueryOrderRequestV3, WechatQueryOrderResponse, WechatQueryOrderResponseV3, WechatQueryRefundOrderRequest, WechatQueryRefundResponse, WechatQueryRefundResponseV3, WechatRefundNotifyResponse, WechatRefundNotifyResponseV3, WechatRefundRequest, WechatRefundRequestV3, WechatRefundResponse, WechatRefundResponseV3, WechatPayShortUrlRequest, WechatPayShortUrlResponse, WechatPayScanNotifyResponse};
 use crate::wechat::cryptos::{SignatureHeader, WechatCryptoV3};
 use crate::wechat::pay::method::{WechatPayMethod, WxPayMethod};
-use crate::wechat::pay::{TradeType};
+use crate::wechat::pay::{MchId, TradeNo, TradeType};
 use crate::wechat::pay::request::WechatPayRequest;
 
 #[derive(Debug, Clone)]
-pub struct WxPay<'a, T: SessionStore> {
-    client: &'a WechatPayClient<T>,
+pub struct WechatPay<T: SessionStore> {
+    client: WechatPayClient<T>,
 }
 
 #[allow(unused)]
-impl<'a, T: SessionStore> WxPay<'a, T> {
+impl<T: SessionStore> WechatPay<T> {
 
     #[inline]
-    pub fn new(client: &WechatPayClient<T>) -> WxPay<T> {
-        WxPay {
+    pub fn from_client(client: WechatPayClient<T>) -> WechatPay<T> {
+        WechatPay {
             client,
         }
     }
 
+    #[inline]
+    #[deprecated(note = "请使用`from_client`，或直接通过客户端获取，如`client.wxpay()`")]
+    pub fn new(client: &WechatPayClient<T>) -> WechatPay<T> {
+        Self::from_client(client.clone())
+    }
+
     ///
     /// # 统一下单
     /// <pre>
@@ -68,12 +74,12 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     pub async fn unified_order(&self, mut params: WechatPayRequest) -> LabradorResult<WechatPayResponse> {
         params.check_params()?;
         let method = if params.trade_type == TradeType::Micro { WxPayMethod::MicroPay } else { WxPayMethod::UnifiedOrder };
-        params.appid = self.client.appid.to_owned().into();
+        params.appid = self.client.inner.appid.to_owned().into();
         if params.trade_type == TradeType::Micro {
             // 將通知url置空
             params.notify_url = None;
         }
-        params.get_sign(&self.client.secret);
+        params.get_sign(&self.client.sign_key(&self.client.inner.secret).await?)?;
         let res = self.client.post(WechatPayMethod::WxPay(method), &params.parse_xml(), RequestType::Xml).await?.text()?;
         WechatPayResponse::parse_xml(res)
     }
@@ -96,6 +102,7 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// # use labrador::TradeType;
     /// # use labrador::Amount;
     /// # use labrador::Payer;
+    /// # use labrador::{out_trade_no, TradeNo};
     /// # use chrono::NaiveDateTime;
     /// # async fn main() {
     /// let client = WechatPayClient::new("appid","secret").wxpay();
@@ -107,7 +114,7 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     ///     payer: Payer { openid: "".to_string()}.into(),
     ///     detail: None,
     ///     scene_info: None,attach: None,
-    ///     out_trade_no: "".to_string(),
+    ///     out_trade_no: TradeNo::try_new(out_trade_no()).unwrap(),
     ///     description: "".to_string(),
     ///     time_expire: "".to_string(),
     ///     settle_info: None
@@ -122,16 +129,18 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     ///
     pub async fn unified_order_v3(&self, trade_type: TradeType, mut params: WechatPayRequestV3) -> LabradorResult<WechatPayResponseV3> {
         if params.mch_id.is_empty() {
-            params.mch_id = self.client.mch_id.to_owned().unwrap_or_default();
+            params.mch_id = self.client.inner.mch_id.to_owned().unwrap_or_default();
         }
         if params.appid.is_none() {
-            params.appid = self.client.appid.to_owned().into();
+            params.appid = self.client.inner.appid.to_owned().into();
         }
+        params.check_params()?;
         let res = self.client.post_v3(params.mch_id.to_owned().into(), WechatPayMethod::WxPay(WxPayMethod::UnifiedOrderV3(trade_type)), vec![],&params, RequestType::Json).await?.json::<serde_json::Value>()?;
         serde_json::from_value::<WechatPayResponseV3>(res).map_err(LabraError::from)
     }
 
     pub async fn isv_unified_order_v3(&self, trade_type: TradeType, mut params: IsvWechatPayRequestV3) -> LabradorResult<WechatPayResponseV3> {
+        params.check_params()?;
         let res = self.client.post_v3(None, WechatPayMethod::WxPay(WxPayMethod::IsvUnifiedOrderV3(trade_type)), vec![],&params, RequestType::Json).await?.json::<serde_json::Value>()?;
         serde_json::from_value::<WechatPayResponseV3>(res).map_err(LabraError::from)
     }
@@ -139,13 +148,13 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// 调用统一下单接口，并组装生成支付所需参数对象.
     pub async fn create_order_v3(&self, trade_type: TradeType, params: WechatPayRequestV3) -> LabradorResult<Value> {
         let result = self.unified_order_v3(trade_type.to_owned(), params.to_owned()).await?;
-        result.get_pay_info(trade_type, params.appid, params.mch_id, self.client.private_key.to_owned())
+        result.get_pay_info(trade_type, params.appid, params.mch_id, self.client.inner.private_key.to_owned())
     }
 
     /// 服务商调用统一下单接口，并组装生成支付所需参数对象.
     pub async fn isv_create_order_v3(&self, trade_type: TradeType, params: IsvWechatPayRequestV3) -> LabradorResult<Value> {
         let result = self.isv_unified_order_v3(trade_type.to_owned(), params.to_owned()).await?;
-        result.get_pay_info(trade_type, params.sub_appid.to_owned(), params.sub_mchid.to_owned().unwrap_or_default(), self.client.private_key.to_owned())
+        result.get_pay_info(trade_type, params.sub_appid.to_owned(), params.sub_mchid.to_owned().unwrap_or_default(), self.client.inner.private_key.to_owned())
     }
 
     ///
@@ -185,8 +194,8 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     ///
     pub async fn close_order(&self,
                              mut params: WechatCloseOrderRequest) -> LabradorResult<WechatCloseOrderResponse> {
-        params.appid = self.client.appid.to_owned().into();
-        params.get_sign(&self.client.api_key.to_owned().unwrap_or_default());
+        params.appid = self.client.inner.appid.to_owned().into();
+        params.get_sign(&self.client.sign_key(&self.client.inner.api_key.to_owned().unwrap_or_default()).await?)?;
         let res = self.client.post(WechatPayMethod::WxPay(WxPayMethod::CloseOrder), &params.parse_xml(), RequestType::Xml).await?.text()?;
         WechatCloseOrderResponse::parse_xml(res)
     }
@@ -224,9 +233,9 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// ```
     ///
     pub async fn close_order_v3(&self, mut params: WechatCloseOrderRequestV3) -> LabradorResult<()> {
-        let out_trade_no = params.out_trade_no.to_owned().unwrap_or_default();
-        params.out_trade_no = None;
-        let res = self.client.post_v3(params.mchid.to_owned().into(), WechatPayMethod::WxPay(WxPayMethod::CloseOrderV3(out_trade_no)), vec![], &params, RequestType::Json).await?;
+        MchId::validate(&params.mchid)?;
+        let out_trade_no = params.out_trade_no.take().ok_or_else(|| LabraError::MissingField("关闭订单需要传入out_trade_no".to_string()))?;
+        let res = self.client.post_v3(params.mchid.to_owned().into(), WechatPayMethod::WxPay(WxPayMethod::CloseOrderV3(out_trade_no.into_inner())), vec![], &params, RequestType::Json).await?;
         let _ = res.text()?;
         // let s = res.json::<serde_json::Value>().await?;
         Ok(())
@@ -317,7 +326,9 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// ```
     ///
     pub async fn query_order_v3(&self, params: WechatQueryOrderRequestV3) -> LabradorResult<WechatQueryOrderResponseV3> {
-        self.client.post_v3(params.mchid.to_owned().into(), WechatPayMethod::WxPay(WxPayMethod::QueryOrderV3((params.out_trade_no.to_owned(), params.out_trade_no.to_owned()))), vec![], "", RequestType::Json)
+        MchId::validate(&params.mchid)?;
+        let out_trade_no = params.out_trade_no.map(TradeNo::into_inner);
+        self.client.post_v3(params.mchid.to_owned().into(), WechatPayMethod::WxPay(WxPayMethod::QueryOrderV3((out_trade_no.to_owned(), out_trade_no))), vec![], "", RequestType::Json)
             .await?.json::<WechatQueryOrderResponseV3>()
     }
 
@@ -336,8 +347,8 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// 接口链接：https://api.mch.weixin.qq.com/pay/refundquery
     /// </pre>
     pub async fn query_refund_order(&self, mut params: WechatQueryRefundOrderRequest) -> LabradorResult<WechatQueryRefundResponse> {
-        params.appid = self.client.appid.to_owned().into();
-        params.get_sign(&self.client.api_key.to_owned().unwrap_or_default());
+        params.appid = self.client.inner.appid.to_owned().into();
+        params.get_sign(&self.client.sign_key(&self.client.inner.api_key.to_owned().unwrap_or_default()).await?)?;
         let res = self.client.post(WechatPayMethod::WxPay(WxPayMethod::QueryRefundOrder), params, RequestType::Xml)
             .await?.text()?;
         WechatQueryRefundResponse::parse_xml(res)
@@ -379,11 +390,14 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// 接口链接：https://api.mch.weixin.qq.com/v3/refund/domestic/refunds/{out_refund_no}
     /// </pre>
     pub async fn query_refund_order_v3(&self, out_refund_no: String) -> LabradorResult<WechatQueryRefundResponseV3> {
+        TradeNo::validate(&out_refund_no)?;
         self.client.post_v3(None, WechatPayMethod::WxPay(WxPayMethod::QueryRefundOrderV3(out_refund_no)), vec![], "", RequestType::Json)
             .await?.json::<WechatQueryRefundResponseV3>()
     }
 
     pub async fn isv_query_refund_order_v3(&self, out_refund_no: String, sub_mch_id: String) -> LabradorResult<WechatQueryRefundResponseV3> {
+        TradeNo::validate(&out_refund_no)?;
+        MchId::validate(&sub_mch_id)?;
         self.client.post_v3(None, WechatPayMethod::WxPay(WxPayMethod::QueryRefundOrderV3(out_refund_no)), vec![("sub_mchid".to_string(), sub_mch_id)], "", RequestType::Json)
             .await?.json::<WechatQueryRefundResponseV3>()
     }
@@ -407,7 +421,7 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
         }
         let origin = serde_json::from_str::<OriginNotifyResponse>(notify_data)?;
         let resource = origin.resource.to_owned();
-        let v3_key = self.client.api_key_v3.to_owned().unwrap_or_default();
+        let v3_key = self.client.inner.api_key_v3.to_owned().unwrap_or_default();
         let crypto = WechatCryptoV3::new(&v3_key);
         let decrypted = crypto.decrypt_data_v3(&resource)?;
         let decrypt_notify_result = serde_json::from_slice::<DecryptNotifyResult>(&decrypted)?;
@@ -420,7 +434,7 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     /// # 解析退款结果通知.
     /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/jsapi.php?chapter=9_16&index=9)
     pub fn parse_refund_notify(&self, xml: &str) -> LabradorResult<WechatDecryptRefundNotifyResponse> {
-        WechatRefundNotifyResponse::parse_xml(xml.to_string(), &self.client.appid)
+        WechatRefundNotifyResponse::parse_xml(xml.to_string(), &self.client.inner.appid)
     }
 
     /// # 解析退款结果通知 - V3.
@@ -435,7 +449,7 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
         }
         let origin = serde_json::from_str::<OriginNotifyResponse>(notify_data)?;
         let resource = origin.resource.to_owned();
-        let v3_key = self.client.api_key_v3.to_owned().unwrap_or_default();
+        let v3_key = self.client.inner.api_key_v3.to_owned().unwrap_or_default();
         let crypto = WechatCryptoV3::new(&v3_key);
         let decrypted = crypto.decrypt_data_v3(&resource)?;
         let decrypt_notify_result = serde_json::from_slice::<DecryptRefundNotifyResult>(&decrypted)?;
@@ -447,8 +461,8 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
 
     /// # 解析扫码支付回调通知
     /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/native.php?chapter=6_4)
-    pub fn parse_scan_pay_notify(&self, xml: &str) -> LabradorResult<WxScanPayNotifyResponse> {
-        WxScanPayNotifyResponse::parse_xml(xml.to_string())
+    pub fn parse_scan_pay_notify(&self, xml: &str) -> LabradorResult<WechatPayScanNotifyResponse> {
+        WechatPayScanNotifyResponse::parse_xml(xml.to_string())
     }
 
 
@@ -504,13 +518,22 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
         &self,
         mut params: WechatRefundRequest
     ) -> LabradorResult<WechatRefundResponse> {
-        params.appid = self.client.appid.to_owned().into();
+        params.appid = self.client.inner.appid.to_owned().into();
         let mch_id = params.mch_id.as_str();
-        params.get_sign(&self.client.api_key.to_owned().unwrap_or_default());
+        params.get_sign(&self.client.sign_key(&self.client.inner.api_key.to_owned().unwrap_or_default()).await?)?;
         let res = self.client.post(WechatPayMethod::WxPay(WxPayMethod::Refund), &params.parse_xml(), RequestType::Xml).await?.text()?;
         WechatRefundResponse::parse_xml(res)
     }
 
+    /// <pre>
+    /// 申请退款，退款单号由key提供
+    /// 自动生成退款单号时，hook在发送请求前调用，hook返回错误时不发送请求
+    /// </pre>
+    pub async fn refund_with_key(&self, mut params: WechatRefundRequest, key: IdempotencyKey) -> LabradorResult<WechatRefundResponse> {
+        params.out_refund_no = key.resolve().await?.into_inner();
+        self.refund(params).await
+    }
+
     ///
     ///
     /// # 撤销订单API.
@@ -530,7 +553,7 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
         &self,
         mut params: WechatOrderReverseRequest
     ) -> LabradorResult<WechatOrderReverseResponse> {
-        params.appid = self.client.appid.to_owned().into();
+        params.appid = self.client.inner.appid.to_owned().into();
         let mch_id = params.mch_id.as_str();
         let res = self.client.post(WechatPayMethod::WxPay(WxPayMethod::ReverseOrder), &params.parse_xml(), RequestType::Xml).await?.text()?;
         WechatOrderReverseResponse::parse_xml(res)
@@ -549,12 +572,12 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
     ///
     pub async fn short_url(
         &self,
-        mut params: WxPayShorturlRequest
-    ) -> LabradorResult<WxPayShortUrlResponse> {
-        params.appid = self.client.appid.to_owned().into();
+        mut params: WechatPayShortUrlRequest
+    ) -> LabradorResult<WechatPayShortUrlResponse> {
+        params.appid = self.client.inner.appid.to_owned().into();
         let mch_id = params.mch_id.as_str();
         let res = self.client.post(WechatPayMethod::WxPay(WxPayMethod::ShortUrl), &params.parse_xml(), RequestType::Xml).await?.text()?;
-        WxPayShortUrlResponse::parse_xml(res)
+        WechatPayShortUrlResponse::parse_xml(res)
     }
 
     ///
@@ -585,6 +608,15 @@ impl<'a, T: SessionStore> WxPay<'a, T> {
        self.client.post_v3(None, WechatPayMethod::WxPay(WxPayMethod::RefundV3), vec![],params, RequestType::Json).await?
             .json::<WechatRefundResponseV3>()
     }
+
+    /// <pre>
+    /// 申请退款 - V3，退款单号由key提供
+    /// 自动生成退款单号时，hook在发送请求前调用，hook返回错误时不发送请求
+    /// </pre>
+    pub async fn refund_v3_with_key(&self, mut params: WechatRefundRequestV3, key: IdempotencyKey) -> LabradorResult<WechatRefundResponseV3> {
+        params.out_refund_no = key.resolve().await?;
+        self.refund_v3(params).await
+    }
 }
 
 
@@ -596,19 +628,20 @@ mod tests {
     use std::io::Read;
     use std::ops::Add;
     use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat};
-    use crate::{Amount, Payer, request, SimpleStorage, TradeType, WechatCloseOrderRequestV3, WechatPayClient, WechatPayRequestV3};
+    use crate::{Amount, Payer, request, SimpleStorage, TradeNo, TradeType, WechatCloseOrderRequestV3, WechatPayClient, WechatPayRequestV3};
 
     #[test]
+    #[ignore = "需要商户私钥src/wechat/pay/sec/apiclient_key.pem"]
     fn test_close_order_v3() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         let mut private_key = Vec::new();
         File::open("src/wechat/pay/sec/apiclient_key.pem").unwrap().read_to_end(&mut private_key).unwrap();
         let r = rt.spawn(async {
-            let c =  WechatPayClient::new("appid", "secret");
+            let c =  WechatPayClient::<SimpleStorage>::new("appid", "secret");
             let mut client =c.wxpay();
             let result = client.close_order_v3(WechatCloseOrderRequestV3 {
                 mchid: "mchid".to_string(),
-                out_trade_no: "23234234234".to_string().into()
+                out_trade_no: TradeNo::try_new("23234234234").ok()
             });
             match result.await {
                 Ok(res) => {
@@ -625,12 +658,13 @@ mod tests {
 
 
     #[test]
+    #[ignore = "需要商户私钥src/wechat/pay/sec/apiclient_key.pem"]
     fn test_callback_v3() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         let mut private_key = Vec::new();
         File::open("src/wechat/pay/sec/apiclient_key.pem").unwrap().read_to_end(&mut private_key).unwrap();
         let r = rt.spawn(async {
-            let c =  WechatPayClient::new("appid", "secret");
+            let c =  WechatPayClient::<SimpleStorage>::new("appid", "secret");
             let mut client =c.wxpay();
             // .cert(MchCert {
             //     mch_id: "1602920235".to_string().into(),
@@ -645,7 +679,7 @@ mod tests {
                 appid: "wx7959501b424a9e93".to_string().into(),
                 mch_id: "1602920235".to_string(),
                 description: "测试商品支付".to_string(),
-                out_trade_no: "1602920235sdfsdfas32234234".to_string(),
+                out_trade_no: TradeNo::try_new("1602920235sdfsdfas32234234").unwrap(),
                 time_expire: date,
                 attach: None,
                 notify_url: "https://api.snackcloud.cn/trade/notify".to_string(),
@@ -662,7 +696,7 @@ mod tests {
             });*/
             let result = client.close_order_v3(WechatCloseOrderRequestV3 {
                 mchid: "mchid".to_string(),
-                out_trade_no: "23234234234".to_string().into()
+                out_trade_no: TradeNo::try_new("23234234234").ok()
             });
             match result.await {
                 Ok(res) => {
@@ -677,19 +711,20 @@ mod tests {
     }
 
     #[test]
+    #[ignore = "需要商户私钥src/wechat/pay/sec/apiclient_key.pem"]
     fn test_create_order_v3() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         let mut private_key = Vec::new();
         File::open("src/wechat/pay/sec/apiclient_key.pem").unwrap().read_to_end(&mut private_key).unwrap();
         let r = rt.spawn(async {
-            let c =  WechatPayClient::new("appid", "secret");
+            let c =  WechatPayClient::<SimpleStorage>::new("appid", "secret");
             let mut client =c.wxpay();
             let date = Local::now().to_rfc3339_opts(SecondsFormat::Secs, false);
             let result = client.unified_order_v3(TradeType::Jsapi, WechatPayRequestV3 {
                 appid: "appid".to_string().into(),
                 mch_id: "mchid".to_string(),
                 description: "测试商品支付".to_string(),
-                out_trade_no: "1602920235sdfsdfas32234234".to_string(),
+                out_trade_no: TradeNo::try_new("1602920235sdfsdfas32234234").unwrap(),
                 time_expire: date,
                 attach: None,
                 notify_url: "https://xxx.cn/trade/notify".to_string(),
@@ -12,3 +12,7 @@ pub static ACCESS_TOKEN: &str = "access_token";
 pub static ACCEPT: &str = "Accept";
 pub static AUTHORIZATION: &str = "Authorization";
 pub static CONTENT_TYPE_JSON: &str = "application/json";
+/// 微信支付沙箱环境（仿真测试系统）的V2接口路径前缀
+pub static SANDBOX_PATH: &str = "/sandboxnew";
+/// 请求中敏感信息加密所用的平台证书序列号
+pub static WECHATPAY_SERIAL: &str = "Wechatpay-Serial";
@@ -0,0 +1,181 @@
+//!
+//! 退款、转账等接口的幂等键
+//!
+//! 退款单号（out_refund_no）、商户付款单号（partner_trade_no）等由商户生成，同一单号多次请求只处理一次。
+//! 如果请求前没有保存单号，超时重试时只能生成新的单号，会导致重复退款或重复付款。
+//! [`IdempotencyKey`]可以直接传入已保存的单号，也可以自动生成单号，并在发送请求前调用hook，由调用方先写入数据库。
+//!
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+
+use crate::{LabradorResult, TradeNo};
+use crate::util::{out_trade_no, out_trade_no_with_prefix};
+
+type Generator = Arc<dyn Fn() -> LabradorResult<TradeNo> + Send + Sync>;
+type Hook = Arc<dyn Fn(TradeNo) -> BoxFuture<'static, LabradorResult<()>> + Send + Sync>;
+
+/// <pre>
+/// 幂等键
+/// 重试时应传入首次生成并已保存的单号（`IdempotencyKey::explicit`），而不是重新生成
+/// </pre>
+#[derive(Clone)]
+pub enum IdempotencyKey {
+    /// 调用方已保存的单号
+    Explicit(TradeNo),
+    /// 自动生成单号，生成后调用hook保存，hook返回错误时不发送请求
+    Generated {
+        generator: Generator,
+        hook: Hook,
+    },
+}
+
+impl IdempotencyKey {
+    /// 使用已保存的单号
+    pub fn explicit(key: TradeNo) -> Self {
+        IdempotencyKey::Explicit(key)
+    }
+
+    /// <pre>
+    /// 按商户订单号的格式（北京时间yyyyMMddHHmmss加18位随机数字）生成单号
+    /// hook在发送请求前调用，用于保存生成的单号
+    /// </pre>
+    pub fn generated<F, Fut>(hook: F) -> Self
+        where F: Fn(TradeNo) -> Fut + Send + Sync + 'static, Fut: Future<Output = LabradorResult<()>> + Send + 'static {
+        Self::generated_with(|| TradeNo::try_new(out_trade_no()), hook)
+    }
+
+    /// 生成带前缀的单号，前缀的规则见[`out_trade_no_with_prefix`]
+    pub fn generated_with_prefix<F, Fut>(prefix: &str, hook: F) -> LabradorResult<Self>
+        where F: Fn(TradeNo) -> Fut + Send + Sync + 'static, Fut: Future<Output = LabradorResult<()>> + Send + 'static {
+        out_trade_no_with_prefix(prefix)?;
+        let prefix = prefix.to_string();
+        Ok(Self::generated_with(move || TradeNo::try_new(out_trade_no_with_prefix(&prefix)?), hook))
+    }
+
+    /// 使用自定义的生成方式
+    pub fn generated_with<G, F, Fut>(generator: G, hook: F) -> Self
+        where G: Fn() -> LabradorResult<TradeNo> + Send + Sync + 'static,
+              F: Fn(TradeNo) -> Fut + Send + Sync + 'static, Fut: Future<Output = LabradorResult<()>> + Send + 'static {
+        IdempotencyKey::Generated {
+            generator: Arc::new(generator),
+            hook: Arc::new(move |key| Box::pin(hook(key))),
+        }
+    }
+
+    /// <pre>
+    /// 取得本次请求的单号
+    /// 自动生成时先调用hook，hook返回错误时返回该错误，调用方不应再发送请求
+    /// </pre>
+    pub async fn resolve(&self) -> LabradorResult<TradeNo> {
+        match self {
+            IdempotencyKey::Explicit(key) => Ok(key.to_owned()),
+            IdempotencyKey::Generated { generator, hook } => {
+                let key = generator()?;
+                hook(key.to_owned()).await?;
+                Ok(key)
+            }
+        }
+    }
+}
+
+impl From<TradeNo> for IdempotencyKey {
+    fn from(key: TradeNo) -> Self {
+        IdempotencyKey::Explicit(key)
+    }
+}
+
+impl fmt::Debug for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdempotencyKey::Explicit(key) => f.debug_tuple("Explicit").field(key).finish(),
+            IdempotencyKey::Generated { .. } => f.write_str("Generated"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+
+    use crate::{LabraError, RefundAmount, TradeNo, WechatRefundRequestV3};
+    use crate::util::mock::MockServer;
+    use crate::wechat::pay::tests::{generate_cert, pay_client, signed_response};
+    use super::IdempotencyKey;
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let key = IdempotencyKey::explicit(TradeNo::try_new("REFUND_000001").unwrap());
+        assert_eq!(key.resolve().await.unwrap().as_str(), "REFUND_000001");
+        assert_eq!(format!("{:?}", key), r#"Explicit(TradeNo("REFUND_000001"))"#);
+
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let hook_saved = saved.clone();
+        let key = IdempotencyKey::generated_with_prefix("RF", move |key: TradeNo| {
+            let saved = hook_saved.clone();
+            async move {
+                saved.lock().unwrap().push(key.into_inner());
+                Ok(())
+            }
+        }).unwrap();
+        let first = key.resolve().await.unwrap();
+        assert!(first.as_str().starts_with("RF"));
+        assert_eq!(first.len(), 32);
+        // 每次生成新的单号，均先经过hook
+        let second = key.resolve().await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(*saved.lock().unwrap(), vec![first.into_inner(), second.into_inner()]);
+
+        assert!(IdempotencyKey::generated_with_prefix("退款", |_| async { Ok(()) }).is_err());
+        let key = IdempotencyKey::generated(|_| async { Err(LabraError::RequestError("数据库不可用".to_string())) });
+        assert!(matches!(key.resolve().await, Err(LabraError::RequestError(msg)) if msg == "数据库不可用"));
+    }
+
+    fn refund_request() -> WechatRefundRequestV3 {
+        WechatRefundRequestV3 {
+            transaction_id: Some("1217752501201407033233368018".to_string()),
+            out_trade_no: None,
+            out_refund_no: TradeNo::try_new("PLACEHOLDER").unwrap(),
+            reason: None,
+            notify_url: None,
+            amount: RefundAmount { refund: 888, total: 888, payer_total: None, payer_refund: None, currency: Some("CNY".to_string()) },
+            goods_detail: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refund_v3_with_key() {
+        let (private_key, cert) = generate_cert();
+        let body = r#"{"refund_id":"50000000382019052709732678859","out_refund_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","out_trade_no":"1217752501201407033233368018","channel":"ORIGINAL","user_received_account":"招商银行信用卡0403","create_time":"2020-12-01T16:18:12+08:00","status":"PROCESSING","amount":{"refund":888,"total":888}}"#;
+        let server = Arc::new(MockServer::start(vec![signed_response(&private_key, &cert.serial_no, body)]).await);
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+
+        // hook在发送请求前执行
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let (hook_saved, hook_server) = (saved.clone(), server.clone());
+        let key = IdempotencyKey::generated(move |key: TradeNo| {
+            let (saved, server) = (hook_saved.clone(), hook_server.clone());
+            async move {
+                assert!(server.requests().is_empty(), "hook应在发送请求前执行");
+                saved.lock().unwrap().push(key.into_inner());
+                Ok(())
+            }
+        });
+        client.wxpay().refund_v3_with_key(refund_request(), key).await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let body = serde_json::from_str::<Value>(requests[0].split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(saved.lock().unwrap().as_slice(), [body["out_refund_no"].as_str().unwrap()]);
+
+        // hook返回错误时不发送请求
+        let key = IdempotencyKey::generated(|_| async { Err(LabraError::RequestError("保存退款单号失败".to_string())) });
+        let err = client.wxpay().refund_v3_with_key(refund_request(), key).await.unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(msg) if msg == "保存退款单号失败"));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -7,6 +7,14 @@ pub enum WechatPayMethod {
     WxPay(WxPayMethod),
     /// 企业支付
     EntPay(EntPayMethod),
+    /// 合单支付
+    Combine(CombinePayMethod),
+    /// 电商收付通
+    Ecommerce(EcommerceMethod),
+    /// 微信支付分
+    PayScore(PayScoreMethod),
+    /// 微信支付分停车服务
+    Parking(ParkingMethod),
     /// 证书下载
     Certificate,
     /// 自定义方法
@@ -16,7 +24,137 @@ pub enum WechatPayMethod {
 #[allow(unused)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum EntPayMethod {
+    /// 企业付款到零钱
+    Transfers,
+    /// 发放现金红包
+    SendRedpack,
+}
+
+#[allow(unused)]
+impl EntPayMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            EntPayMethod::Transfers => String::from("/mmpaymkttransfers/promotion/transfers"),
+            EntPayMethod::SendRedpack => String::from("/mmpaymkttransfers/sendredpack"),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum CombinePayMethod {
+    /// 合单下单
+    CreateOrder(TradeType),
+    /// 合单查询
+    QueryOrder(String),
+    /// 合单关单
+    CloseOrder(String),
+}
+
+#[allow(unused)]
+impl CombinePayMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            CombinePayMethod::CreateOrder(v) => {
+                match v {
+                    TradeType::MWeb => String::from("/v3/combine-transactions/h5"),
+                    TradeType::Jsapi => String::from("/v3/combine-transactions/jsapi"),
+                    TradeType::Native => String::from("/v3/combine-transactions/native"),
+                    TradeType::App => String::from("/v3/combine-transactions/app"),
+                    _ => String::default(),
+                }
+            }
+            CombinePayMethod::QueryOrder(v) => format!("/v3/combine-transactions/out-trade-no/{}", v),
+            CombinePayMethod::CloseOrder(v) => format!("/v3/combine-transactions/out-trade-no/{}/close", v),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum EcommerceMethod {
+    /// 图片上传
+    UploadImage,
+    /// 二级商户进件
+    Applyments,
+    /// 通过申请单ID查询申请状态
+    QueryApplymentById(u64),
+    /// 通过业务申请编号查询申请状态
+    QueryApplymentByOutRequestNo(String),
+}
+
+#[allow(unused)]
+impl EcommerceMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            EcommerceMethod::UploadImage => String::from("/v3/merchant/media/upload"),
+            EcommerceMethod::Applyments => String::from("/v3/ecommerce/applyments/"),
+            EcommerceMethod::QueryApplymentById(v) => format!("/v3/ecommerce/applyments/{}", v),
+            EcommerceMethod::QueryApplymentByOutRequestNo(v) => format!("/v3/ecommerce/applyments/out-request-no/{}", v),
+        }
+    }
+}
 
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum PayScoreMethod {
+    /// 创建支付分订单
+    CreateServiceOrder,
+    /// 查询支付分订单，参数为查询字符串
+    QueryServiceOrder(String),
+    /// 取消支付分订单
+    CancelServiceOrder(String),
+    /// 修改订单金额
+    ModifyServiceOrder(String),
+    /// 完结支付分订单
+    CompleteServiceOrder(String),
+    /// 同步服务订单信息
+    SyncServiceOrder(String),
+    /// 商户预授权
+    ApplyPermissions,
+    /// 查询用户授权记录，参数为授权协议号或openid对应的路径与查询字符串
+    QueryPermissions(String),
+    /// 解除用户授权关系，参数为授权协议号或openid对应的路径
+    TerminatePermissions(String),
+}
+
+#[allow(unused)]
+impl PayScoreMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            PayScoreMethod::CreateServiceOrder => String::from("/v3/payscore/serviceorder"),
+            PayScoreMethod::QueryServiceOrder(v) => format!("/v3/payscore/serviceorder?{}", v),
+            PayScoreMethod::CancelServiceOrder(v) => format!("/v3/payscore/serviceorder/{}/cancel", v),
+            PayScoreMethod::ModifyServiceOrder(v) => format!("/v3/payscore/serviceorder/{}/modify", v),
+            PayScoreMethod::CompleteServiceOrder(v) => format!("/v3/payscore/serviceorder/{}/complete", v),
+            PayScoreMethod::SyncServiceOrder(v) => format!("/v3/payscore/serviceorder/{}/sync", v),
+            PayScoreMethod::ApplyPermissions => String::from("/v3/payscore/permissions"),
+            PayScoreMethod::QueryPermissions(v) => format!("/v3/payscore/permissions/{}", v),
+            PayScoreMethod::TerminatePermissions(v) => format!("/v3/payscore/permissions/{}/terminate", v),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParkingMethod {
+    /// 查询车牌服务开通信息，参数为查询字符串
+    FindService(String),
+    /// 创建停车入场
+    CreateParking,
+    /// 扣费受理
+    Transaction,
+}
+
+#[allow(unused)]
+impl ParkingMethod {
+    pub fn get_method(&self) -> String {
+        match self {
+            ParkingMethod::FindService(v) => format!("/v3/vehicle/parking/services/find?{}", v),
+            ParkingMethod::CreateParking => String::from("/v3/vehicle/parking/parkings"),
+            ParkingMethod::Transaction => String::from("/v3/vehicle/transactions/parking"),
+        }
+    }
 }
 
 #[allow(unused)]
@@ -51,6 +189,8 @@ pub enum WxPayMethod {
     ReverseOrder,
     /// 转换短链接
     ShortUrl,
+    /// 获取沙箱密钥
+    GetSandboxSignKey,
 }
 
 
@@ -59,9 +199,11 @@ impl RequestMethod for WechatPayMethod {
     fn get_method(&self) -> String {
         match self {
             WechatPayMethod::WxPay(v) => v.get_method(),
-            WechatPayMethod::EntPay(_) => {
-                String::default()
-            }
+            WechatPayMethod::EntPay(v) => v.get_method(),
+            WechatPayMethod::Combine(v) => v.get_method(),
+            WechatPayMethod::Ecommerce(v) => v.get_method(),
+            WechatPayMethod::PayScore(v) => v.get_method(),
+            WechatPayMethod::Parking(v) => v.get_method(),
             WechatPayMethod::Certificate => String::from("/v3/certificates"),
             WechatPayMethod::Custom(v) => v.to_string()
         }
@@ -79,6 +221,7 @@ impl WxPayMethod {
             WxPayMethod::RefundV3 => String::from("/v3/refund/domestic/refunds"),
             WxPayMethod::QueryOrder => String::from("/pay/orderquery"),
             WxPayMethod::ShortUrl => String::from("/tools/shorturl"),
+            WxPayMethod::GetSandboxSignKey => String::from("/pay/getsignkey"),
             WxPayMethod::QueryOrderV3((otr, tid)) => {
                 if let Some(otr) = otr {
                     format!("/v3/pay/transactions/out-trade-no/{}", otr)
@@ -3,25 +3,37 @@ use std::sync::Arc;
 use dashmap::DashMap;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
-use crate::{APIClient, LabraCertificate, LabraError, LabraIdentity, LabraRequest, LabraResponse, Method, RequestType, SessionStore, RequestMethod, LabradorResult, SimpleStorage};
-use crate::util::{get_nonce_str, get_timestamp};
+use reqwest::multipart;
+use crate::{APIClient, MetricsRecorder, DebugRecorder, DebugRecord, LabraCertificate, LabraError, LabraIdentity, LabraRequest, LabraResponse, Method, RequestBody, RequestType, SessionStore, RequestMethod, LabradorResult, SimpleStorage};
+use crate::wechat::WECHAT_PAY_BASE_URL;
+use crate::util::{current_timestamp, nonce_str};
 
 mod method;
 mod api;
+mod idempotency;
 mod request;
 mod response;
+mod sign;
+mod sign_debug;
+mod types;
 #[allow(unused)]
 mod constants;
 
+pub use api::*;
+pub use idempotency::*;
 pub use request::*;
 pub use response::*;
+pub use sign::*;
+pub use sign_debug::*;
+pub use types::*;
 use tracing::info;
 use crate::wechat::cryptos::{SignatureHeader, WechatCryptoV3};
-use crate::wechat::pay::api::WxPay;
-use crate::wechat::pay::constants::{ACCEPT, AUTHORIZATION, CONTENT_TYPE_JSON};
-use crate::wechat::pay::method::WechatPayMethod;
+use crate::wechat::pay::constants::{ACCEPT, AUTHORIZATION, CONTENT_TYPE_JSON, SANDBOX_PATH, WECHATPAY_SERIAL};
+use crate::wechat::pay::method::{WechatPayMethod, WxPayMethod};
 
 const SCHEMA: &str = "WECHATPAY2-SHA256-RSA2048";
+/// V3应答时间戳与本地时间允许的最大偏差（秒）
+const RESPONSE_MAX_AGE: i64 = 300;
 
 /// 交易类型
 #[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
@@ -66,23 +78,35 @@ impl TradeType {
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct WechatPayClient<T: SessionStore> {
-    pub appid: String,
+    inner: Arc<WechatPayClientInner<T>>,
+}
+
+/// 客户端配置与会话存储，克隆客户端时共享
+#[derive(Debug, Clone)]
+struct WechatPayClientInner<T: SessionStore> {
+    appid: String,
     secret: String,
     /// 私钥 V3
     api_key_v3: Option<String>,
     /// 私钥
     api_key: Option<String>,
     /// 商户编号
-    pub mch_id: Option<String>,
+    mch_id: Option<String>,
     /// API证书序列号
     serial_no: Option<String>,
     /// API商户证书秘钥
     private_key: Option<String>,
     /// 证书文件
     pkcs12_path: Option<String>,
+    /// 携带商户API证书的HTTP客户端，用于需要双向TLS的V2接口
+    cert_client: Option<reqwest::Client>,
     client: APIClient<T>,
     /// 缓存的证书文件
     certs: Arc<DashMap<String, LabraCertificate>>,
+    /// 是否校验V3应答签名
+    verify_response_signature: bool,
+    /// 是否为沙箱环境（仿真测试系统），仅支持V2接口
+    sandbox: bool,
 }
 
 
@@ -91,48 +115,96 @@ impl<T: SessionStore> WechatPayClient<T> {
 
     fn from_client(client: APIClient<T>) -> WechatPayClient<T> {
         WechatPayClient {
-            appid: client.app_key.to_owned(),
-            secret: client.secret.to_owned(),
-            api_key_v3: None,
-            api_key: None,
-            mch_id: None,
-            serial_no: None,
-            private_key: None,
-            client,
-            pkcs12_path: None,
-            certs: Arc::new(DashMap::new())
+            inner: Arc::new(WechatPayClientInner {
+                appid: client.app_key.to_owned(),
+                secret: client.secret.to_owned(),
+                api_key_v3: None,
+                api_key: None,
+                mch_id: None,
+                serial_no: None,
+                private_key: None,
+                client,
+                pkcs12_path: None,
+                cert_client: None,
+                certs: Arc::new(DashMap::new()),
+                verify_response_signature: true,
+                sandbox: false,
+            }),
         }
     }
 
     /// get the wechat client
     pub fn new<S: Into<String>>(appid: S, secret: S) -> WechatPayClient<SimpleStorage> {
-        let client = APIClient::<SimpleStorage>::from_session(appid.into(), secret.into(),"https://api.mch.weixin.qq.com", SimpleStorage::new());
+        let client = APIClient::<SimpleStorage>::from_session(appid.into(), secret.into(),WECHAT_PAY_BASE_URL, SimpleStorage::new());
         WechatPayClient::<SimpleStorage>::from_client(client)
     }
 
     /// get the wechat client
     pub fn from_session<S: Into<String>>(appid: S, secret: S, session: T) -> WechatPayClient<T> {
-        let client = APIClient::from_session(appid.into(), secret.into(), "https://api.mch.weixin.qq.com", session);
+        let client = APIClient::from_session(appid.into(), secret.into(), WECHAT_PAY_BASE_URL, session);
         Self::from_client(client)
     }
 
+    /// 替换接口域名（默认[`WECHAT_PAY_BASE_URL`]），如私有网关、就近接入域名
+    pub fn base_url(mut self, base_url: String) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().api_path(base_url);
+        self
+    }
+
+    /// 备用域名，连接失败时按顺序切换，如`WECHAT_PAY2_BASE_URL`
+    pub fn fallback_urls(mut self, fallback_urls: Vec<String>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().fallback_paths(fallback_urls);
+        self
+    }
+
+    /// 请求指标记录，每次请求（含切换备用域名的重试）都会回调
+    pub fn metrics_recorder(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().metrics_recorder(metrics);
+        self
+    }
+
+    /// 开启请求调试记录，在内存中保留最近的请求/响应报文，URL中的凭证会被隐去
+    pub fn debug_recorder(mut self, debug: Arc<DebugRecorder>) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.client = inner.client.to_owned().debug_recorder(debug);
+        self
+    }
+
+    /// 当前保留的请求调试记录，未开启时为空
+    pub fn debug_records(&self) -> Vec<DebugRecord> {
+        self.inner.client.debug_records()
+    }
+
+    /// 应用ID
+    pub fn get_appid(&self) -> &str {
+        &self.inner.appid
+    }
+
+    /// 商户编号
+    pub fn get_mch_id(&self) -> Option<&str> {
+        self.inner.mch_id.as_deref()
+    }
+
     pub fn key_v3(mut self, key: String) -> Self {
-        self.api_key_v3 = key.into();
+        Arc::make_mut(&mut self.inner).api_key_v3 = key.into();
         self
     }
 
     pub fn key(mut self, key: String) -> Self {
-        self.api_key = key.into();
+        Arc::make_mut(&mut self.inner).api_key = key.into();
         self
     }
 
     pub fn mch_id(mut self, mch_id: String) -> Self {
-        self.mch_id = mch_id.into();
+        Arc::make_mut(&mut self.inner).mch_id = mch_id.into();
         self
     }
 
     pub fn private_key(mut self, private_key: String) -> Self {
-        self.private_key = private_key.into();
+        Arc::make_mut(&mut self.inner).private_key = private_key.into();
         self
     }
 
@@ -156,45 +228,77 @@ impl<T: SessionStore> WechatPayClient<T> {
             return Err(LabraError::InvalidSignature("证书文件有误！".to_string()));
         }
         let content = fs::read_to_string(private_key_path)?;
-        self.private_key = content.into();
+        Arc::make_mut(&mut self.inner).private_key = content.into();
         Ok(self)
     }
 
     pub fn serial_no(mut self, serial_no: String) -> Self {
-        self.serial_no = serial_no.into();
+        Arc::make_mut(&mut self.inner).serial_no = serial_no.into();
         self
     }
 
     pub fn pkcs12_path(mut self, pkcs12_path: String) -> Self {
-        self.pkcs12_path = pkcs12_path.into();
+        Arc::make_mut(&mut self.inner).pkcs12_path = pkcs12_path.into();
         self
     }
 
+    /// 是否校验V3应答签名，默认开启，仅建议在沙箱或测试环境关闭
+    /// <pre>
+    /// 商户API证书，红包、企业付款、退款等V2接口需要双向TLS
+    /// 证书配置在复用的HTTP客户端上，不再每次请求读取证书文件
+    /// apiclient_cert.p12使用`LabraIdentity::from_pkcs12_der`（密码默认为商户号），
+    /// apiclient_cert.pem与apiclient_key.pem使用`LabraIdentity::from_pkcs8_pem`
+    /// </pre>
+    pub fn client_identity(mut self, identity: LabraIdentity) -> LabradorResult<Self> {
+        Arc::make_mut(&mut self.inner).cert_client = identity.http_client()?.into();
+        Ok(self)
+    }
+
+    pub fn verify_response_signature(mut self, verify: bool) -> Self {
+        Arc::make_mut(&mut self.inner).verify_response_signature = verify;
+        self
+    }
+
+    /// <pre>
+    /// 是否使用沙箱环境（仿真测试系统）
+    /// 开启后V2接口改为请求`/sandboxnew`下的路径，并使用通过getsignkey获取的沙箱密钥签名（首次调用时获取并缓存在会话存储中），
+    /// 需要同时设置商户号`mch_id`与正式的商户API密钥`key`；沙箱环境不支持V3接口，调用时直接返回[`LabraError::Unsupported`]
+    /// </pre>
+    pub fn sandbox(mut self, sandbox: bool) -> Self {
+        Arc::make_mut(&mut self.inner).sandbox = sandbox;
+        self
+    }
+
+    /// 是否为沙箱环境
+    pub fn is_sandbox(&self) -> bool {
+        self.inner.sandbox
+    }
+
     fn get_identity(&self, password: Option<String>) -> LabradorResult<LabraIdentity> {
         let password = if let Some(password) = password {
             password
         } else {
-            self.mch_id.to_owned().unwrap_or_default()
+            self.inner.mch_id.to_owned().unwrap_or_default()
         };
-        let path = self.pkcs12_path.to_owned().unwrap_or_default();
+        let path = self.inner.pkcs12_path.to_owned().unwrap_or_default();
         if path.is_empty() {
             return Err(LabraError::InvalidSignature("pkcs12证书文件路径有误！".to_string()));
         }
-        let content = fs::read_to_string(path)?;
-        let buf = content.as_bytes();
-        LabraIdentity::from_pkcs12_der(buf.to_vec(), &password)
+        LabraIdentity::from_pkcs12_der(fs::read(path)?, &password)
     }
 
     #[inline]
     pub fn token<F: Serialize>(&self, req: &LabraRequest<F>, mch_id: Option<String>) -> LabradorResult<String> {
-        let api_path = self.client.api_path.to_owned();
         let LabraRequest { url, method, body, ..} = req;
-        let method = method.to_string();
-        let body = body.to_string();
+        self.token_with_body(&method.to_string(), url, &body.to_string(), mch_id)
+    }
+
+    /// 使用指定的签名主体生成Authorization，如图片上传接口签名的是meta部分的JSON而非整个请求体
+    fn token_with_body(&self, method: &str, url: &str, body: &str, mch_id: Option<String>) -> LabradorResult<String> {
         let mut mch_id = mch_id.unwrap_or_default();
-        let mut private_key = self.private_key.to_owned().unwrap_or_default();
-        let mut serial_no = self.serial_no.to_owned().unwrap_or_default();
-        if let Some(mchid) = &self.mch_id {
+        let private_key = self.inner.private_key.to_owned().unwrap_or_default();
+        let serial_no = self.inner.serial_no.to_owned().unwrap_or_default();
+        if let Some(mchid) = &self.inner.mch_id {
             if mch_id.is_empty() {
                 mch_id = mchid.to_owned();
             }
@@ -202,23 +306,92 @@ impl<T: SessionStore> WechatPayClient<T> {
         if mch_id.is_empty() || serial_no.is_empty()  || private_key.is_empty() {
             return Err(LabraError::InvalidSignature("商户参数有误，无法进行操作".to_string()))
         }
-        let nonce_str = get_nonce_str().to_uppercase();
+        let nonce_str = nonce_str().to_uppercase();
 
-        let timestamp = get_timestamp() / 1000;
-        let signature = WechatCryptoV3::signature_v3(&method, url, timestamp, &nonce_str, &body, &private_key)?;
+        let timestamp = current_timestamp();
+        let signature = WechatCryptoV3::signature_v3(&method.to_string(), &url.to_string(), timestamp, &nonce_str, &body.to_string(), &private_key)?;
         let token = format!("{} mchid=\"{}\",nonce_str=\"{}\",signature=\"{}\",timestamp=\"{}\",serial_no=\"{}\"",
                             SCHEMA, mch_id, nonce_str, signature, timestamp, serial_no);
         Ok(token)
     }
 
+    /// 获取沙箱密钥
+    /// <pre>
+    /// 使用正式的商户API密钥签名请求getsignkey，结果按商户号缓存在会话存储中，之后不再重复获取
+    /// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/jsapi.php?chapter=23_1&index=2)
+    /// </pre>
+    pub async fn get_sandbox_sign_key(&self) -> LabradorResult<String> {
+        let mch_id = self.inner.mch_id.to_owned().unwrap_or_default();
+        let api_key = self.inner.api_key.to_owned().unwrap_or_default();
+        if mch_id.is_empty() || api_key.is_empty() {
+            return Err(LabraError::MissingField("沙箱环境需要设置商户号(mch_id)与商户API密钥(key)".to_string()));
+        }
+        let session = self.inner.client.session();
+        let cache_key = format!("{}_sandbox_signkey_pay", mch_id);
+        let cached: Option<String> = session.get(&cache_key, None)?;
+        if let Some(sign_key) = cached.filter(|v| !v.is_empty()) {
+            return Ok(sign_key);
+        }
+        let mut req = WechatPaySandboxSignKeyRequest {
+            mch_id,
+            nonce_str: nonce_str(),
+            sign: String::default(),
+        };
+        req.get_sign(&api_key)?;
+        let res = self.post(WechatPayMethod::WxPay(WxPayMethod::GetSandboxSignKey), &req.parse_xml(), RequestType::Xml).await?.text()?;
+        let res = WechatPaySandboxSignKeyResponse::parse_xml(res)?;
+        session.set(&cache_key, res.sandbox_signkey.to_owned(), None)?;
+        Ok(res.sandbox_signkey)
+    }
+
+    /// V2接口签名使用的密钥，沙箱环境中为沙箱密钥
+    pub(crate) async fn sign_key(&self, key: &str) -> LabradorResult<String> {
+        if self.inner.sandbox {
+            self.get_sandbox_sign_key().await
+        } else {
+            Ok(key.to_string())
+        }
+    }
+
+    /// V2接口路径，沙箱环境中增加`/sandboxnew`前缀
+    fn url_v2(&self, method: &WechatPayMethod) -> String {
+        if self.inner.sandbox {
+            format!("{}{}", SANDBOX_PATH, method.get_method())
+        } else {
+            method.get_method()
+        }
+    }
+
+    /// 沙箱环境不支持V3接口
+    fn check_v3(&self, method: &WechatPayMethod) -> LabradorResult<()> {
+        if self.inner.sandbox {
+            Err(LabraError::Unsupported(format!("微信支付沙箱环境不支持V3接口：{}", method.get_method())))
+        } else {
+            Ok(())
+        }
+    }
+
     /// 发送POST请求
     async fn post<D: Serialize>(&self, method: WechatPayMethod, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
         let mut querys = Vec::new();
-        let mut req = LabraRequest::new().url(method.get_method()).params(querys).method(Method::Post).json(data).req_type(request_type);
-        if let Some(_) = &self.pkcs12_path {
+        let mut req = LabraRequest::new().url(self.url_v2(&method)).params(querys).method(Method::Post).json(data).req_type(request_type);
+        if self.inner.pkcs12_path.is_some() {
             req = req.identity(self.get_identity(None)?);
         }
-        self.client.request(req).await
+        self.inner.client.request(req).await
+    }
+
+    /// 发送V2接口的XML请求，use_cert为true时使用商户API证书
+    pub(crate) async fn post_xml(&self, method: WechatPayMethod, xml: String, use_cert: bool) -> LabradorResult<LabraResponse> {
+        let mut req = LabraRequest::<String>::new().url(self.url_v2(&method)).method(Method::Post).body(RequestBody::Xml(xml)).req_type(RequestType::Xml);
+        if use_cert {
+            req = match &self.inner.cert_client {
+                Some(cert_client) => req.http_client(cert_client.clone()),
+                None if self.inner.pkcs12_path.is_some() => req.identity(self.get_identity(None)?),
+                None => return Err(LabraError::MissingField(format!("{}需要商户API证书，请通过client_identity设置", method.get_method()))),
+            };
+        }
+        self.inner.client.request(req).await
     }
 
     /// 发送POST请求
@@ -229,48 +402,90 @@ impl<T: SessionStore> WechatPayClient<T> {
     /// request_type 请求方式
     /// </pre>
     async fn post_v3<D: Serialize>(&self, mchid: Option<String>, method: WechatPayMethod, mut querys: Vec<(String, String)>, data: D, request_type: RequestType) -> LabradorResult<LabraResponse> {
-        let mut req = LabraRequest::new().url(method.get_method()).params(querys).method(Method::Post).json(data).req_type(request_type);
+        self.check_v3(&method)?;
+        let req = LabraRequest::new().url(method.get_method()).params(querys).method(Method::Post).json(data).req_type(request_type);
         let auth = self.token(&req, mchid)?;
+        self.send_v3(req, auth, vec![]).await
+    }
+
+    /// 发送带敏感信息的POST请求
+    /// <pre>
+    /// 请求数据中的敏感字段需已使用`platform_cert`的公钥加密，并通过Wechatpay-Serial请求头告知平台证书序列号
+    /// </pre>
+    pub(crate) async fn post_v3_sensitive<D: Serialize>(&self, mchid: Option<String>, method: WechatPayMethod, data: D, platform_cert: &LabraCertificate) -> LabradorResult<LabraResponse> {
+        self.check_v3(&method)?;
+        let req = LabraRequest::new().url(method.get_method()).method(Method::Post).json(data).req_type(RequestType::Json);
+        let auth = self.token(&req, mchid)?;
+        self.send_v3(req, auth, vec![(String::from(WECHATPAY_SERIAL), platform_cert.serial_no.to_owned())]).await
+    }
+
+    /// 发送multipart上传请求，签名主体为meta部分的JSON
+    pub(crate) async fn upload_v3(&self, method: WechatPayMethod, meta: &str, form: multipart::Form) -> LabradorResult<LabraResponse> {
+        self.check_v3(&method)?;
+        let url = method.get_method();
+        let auth = self.token_with_body(&Method::Post.to_string(), &url, meta, None)?;
+        let req = LabraRequest::<String>::new().url(url).method(Method::Post).multipart_form(form).req_type(RequestType::Multipart);
+        self.send_v3(req, auth, vec![]).await
+    }
+
+    /// 附加签名与平台证书后发送V3请求，状态码200、204视为成功并对应答验签
+    async fn send_v3<D: Serialize>(&self, mut req: LabraRequest<D>, auth: String, extra_headers: Vec<(String, String)>) -> LabradorResult<LabraResponse> {
         self.auto_load_cert().await?;
-        let headers = vec![(String::from(AUTHORIZATION), auth),(String::from(ACCEPT), String::from(CONTENT_TYPE_JSON))];
+        let mut headers = vec![(String::from(AUTHORIZATION), auth),(String::from(ACCEPT), String::from(CONTENT_TYPE_JSON))];
+        headers.extend(extra_headers);
         req = req.headers(headers);
-        if let Some(cert) = self.certs.iter().take(1).next() {
+        if let Some(cert) = self.inner.certs.iter().take(1).next() {
             req = req.cert(cert.clone());
         }
-        let result = self.client.request(req).await?;
+        let result = self.inner.client.request(req).await?;
         // v3已经改为通过状态码判断200 204 成功
         let status = result.status();
-        // 返回结果验签
-        // let header = SignatureHeader::from_header(result.header());
-
         if status.as_u16() == 200 || status.as_u16() == 204 {
+            // 返回结果验签
+            self.verify_response(&result).await?;
             Ok(result)
         } else {
             Err(LabraError::RequestError(result.text()?))
         }
     }
 
+    /// 用于加密敏感信息的平台证书，有多张证书时（如平台证书轮换期间）使用过期时间最晚的一张
+    pub(crate) async fn platform_cert(&self) -> LabradorResult<LabraCertificate> {
+        self.auto_load_cert().await?;
+        self.inner.certs.iter().max_by(|a, b| a.expire_time.cmp(&b.expire_time)).map(|cert| cert.clone())
+            .ok_or_else(|| LabraError::MissingField("平台证书".to_string()))
+    }
+
     /// 校验通知签名
     /// header 通知头信息
     /// data   通知数据
     /// true:校验通过 false:校验不通过
     async fn verify_notify_sign(&self, header: &SignatureHeader, data: &str) -> bool {
-        let serial_no = header.serial.to_owned();
-        let before_sign = format!("{}\n{}\n{}\n", header.time_stamp, header.nonce, data);
-        let result = self.certs.contains_key(&serial_no);
-        // V3  验证签名
-        let verify = if let Some(cert) = self.certs.get(&serial_no) {
-            let content = String::from_utf8_lossy(&cert.public_key).to_string();
-            WechatCryptoV3::verify(&before_sign, &header.signature, &content).unwrap_or(false)
-        } else {
-            false
-        };
-        result && verify
+        verify_signature(&self.inner.certs, header, data)
+    }
+
+    /// V3 响应验签
+    /// <pre>
+    /// 使用响应头Wechatpay-Serial对应的平台证书校验Wechatpay-Signature，并拒绝时间戳超过5分钟的应答，
+    /// 本地没有对应序列号的证书时（如平台证书轮换）重新下载一次证书后再校验，
+    /// 验签失败时返回的错误中附带Request-ID，便于向微信支付反馈问题
+    /// </pre>
+    async fn verify_response(&self, response: &LabraResponse) -> LabradorResult<()> {
+        if !self.inner.verify_response_signature {
+            return Ok(());
+        }
+        match verify_response_with(&self.inner.certs, response) {
+            Err(LabraError::UnknownSerial { .. }) => {
+                self.refresh_certs().await?;
+                verify_response_with(&self.inner.certs, response)
+            }
+            result => result,
+        }
     }
 
     /// V3  验证签名
     pub async fn verify(&self, serial_number: &str, message: &str, signature: &str) -> bool {
-        if let Some(cert) = self.certs.get(serial_number) {
+        if let Some(cert) = self.inner.certs.get(serial_number) {
             let content = String::from_utf8_lossy(&cert.content).to_string();
             WechatCryptoV3::verify(message, signature, &content).unwrap_or(false)
         } else {
@@ -281,24 +496,38 @@ impl<T: SessionStore> WechatPayClient<T> {
     /// 自动加载证书
     pub async fn auto_load_cert(&self) -> LabradorResult<()> {
         // 如果已经有证书了，则不用自动获取
-        if self.certs.is_empty() {
-            let response = self.get_v3(WechatPayMethod::Certificate, vec![], RequestType::Json).await?;
-            let status_code = response.status().as_u16();
-            if status_code == 200 {
-                let body = response.json::<Value>()?;
-                info!("获取平台证书:{}", serde_json::to_string(&body).unwrap_or_default());
-                let bodys = serde_json::from_value::<Vec<PlatformCertificateResponse>>(body["data"].to_owned())?;
-                for body in bodys {
-                    let data =body.encrypt_certificate;
-                    let crypto = WechatCryptoV3::new(&self.api_key_v3.to_owned().unwrap_or_default());
-                    let res = crypto.decrypt_data_v3(&data)?;
-                    let mut cert = LabraCertificate::from_pem(res)?;
-                    let serial_no = body.serial_no;
-                    cert.serial_no = serial_no.to_owned();
-                    cert.effective_time = body.effective_time.to_owned();
-                    cert.expire_time = body.expire_time.to_owned();
-                    self.certs.insert(serial_no, cert);
-                }
+        if self.inner.certs.is_empty() {
+            self.refresh_certs().await?;
+        }
+        Ok(())
+    }
+
+    /// 重新下载平台证书，已缓存的证书保留，用于平台证书轮换
+    pub async fn refresh_certs(&self) -> LabradorResult<()> {
+        let response = self.get_v3_unverified(WechatPayMethod::Certificate, vec![], RequestType::Json).await?;
+        let status_code = response.status().as_u16();
+        if status_code == 200 {
+            let body = response.json::<Value>()?;
+            info!("获取平台证书:{}", serde_json::to_string(&body).unwrap_or_default());
+            let bodys = serde_json::from_value::<Vec<PlatformCertificateResponse>>(body["data"].to_owned())?;
+            let certs = DashMap::new();
+            for body in bodys {
+                let data =body.encrypt_certificate;
+                let crypto = WechatCryptoV3::new(&self.inner.api_key_v3.to_owned().unwrap_or_default());
+                let res = crypto.decrypt_data_v3(&data)?;
+                let mut cert = LabraCertificate::from_pem(res)?;
+                let serial_no = body.serial_no;
+                cert.serial_no = serial_no.to_owned();
+                cert.effective_time = body.effective_time.to_owned();
+                cert.expire_time = body.expire_time.to_owned();
+                certs.insert(serial_no, cert);
+            }
+            // 证书下载接口的应答同样需要验签，使用刚解密出的平台证书校验后再缓存
+            if self.inner.verify_response_signature {
+                verify_response_with(&certs, &response)?;
+            }
+            for (serial_no, cert) in certs {
+                self.inner.certs.insert(serial_no, cert);
             }
         }
         Ok(())
@@ -309,26 +538,38 @@ impl<T: SessionStore> WechatPayClient<T> {
     /// 发送GET请求
     async fn get(&self, method: WechatPayMethod, params: Vec<(&str, &str)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
         let mut querys = params.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<(String,String)>>();
-        let mut req = LabraRequest::<String>::new().url(method.get_method()).params(querys).method(Method::Get).req_type(request_type);
-        if let Some(_) = &self.pkcs12_path {
+        let mut req = LabraRequest::<String>::new().url(self.url_v2(&method)).params(querys).method(Method::Get).req_type(request_type);
+        if self.inner.pkcs12_path.is_some() {
             req = req.identity(self.get_identity(None)?);
         }
-        self.client.request(req).await
+        self.inner.client.request(req).await
     }
 
-    /// 发送GET请求
+    /// 发送GET请求 - 成功的响应均会验签
     async fn get_v3(&self, method: WechatPayMethod, params: Vec<(&str, &str)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
+        self.check_v3(&method)?;
+        self.auto_load_cert().await?;
+        let result = self.get_v3_unverified(method, params, request_type).await?;
+        if result.status().is_success() {
+            self.verify_response(&result).await?;
+        }
+        Ok(result)
+    }
+
+    /// 发送GET请求 - 不验签，仅用于平台证书下载（由auto_load_cert使用下载到的证书验签）
+    async fn get_v3_unverified(&self, method: WechatPayMethod, params: Vec<(&str, &str)>, request_type: RequestType) -> LabradorResult<LabraResponse> {
+        self.check_v3(&method)?;
         let querys = params.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<(String,String)>>();
         let mut req = LabraRequest::<String>::new().url(method.get_method()).params(querys).method(Method::Get).req_type(request_type);
         let auth = self.token(&req, None)?;
         let headers = vec![(String::from(AUTHORIZATION), auth),(String::from(ACCEPT), String::from(CONTENT_TYPE_JSON))];
         req = req.headers(headers);
-        self.client.request(req).await
+        self.inner.client.request(req).await
     }
 
     /// # 获取平台证书 - V3版本
     pub async fn get_certificates(&self) -> LabradorResult<Vec<PlatformCertificateResponse>> {
-        let response = self.get_v3(WechatPayMethod::Certificate, vec![], RequestType::Json).await?;
+        let response = self.get_v3_unverified(WechatPayMethod::Certificate, vec![], RequestType::Json).await?;
         let status_code = response.status().as_u16();
         if status_code == 200 {
             let body = response.json::<Value>()?;
@@ -339,9 +580,364 @@ impl<T: SessionStore> WechatPayClient<T> {
     }
 
     /// 微信支付服务
-    pub fn wxpay(&self) -> WxPay<T> {
-        WxPay::new(self)
+    pub fn wxpay(&self) -> WechatPay<T> {
+        WechatPay::from_client(self.clone())
+    }
+
+    /// V2接口（现金红包、企业付款到零钱等）
+    pub fn wxpay_v2(&self) -> WechatPayV2<T> {
+        WechatPayV2::from_client(self.clone())
     }
 
+    /// 合单支付服务
+    pub fn combine(&self) -> WechatPayCombine<T> {
+        WechatPayCombine::from_client(self.clone())
+    }
+
+    /// 电商收付通二级商户进件
+    pub fn ecommerce_applyment(&self) -> WechatPayEcommerceApplyment<T> {
+        WechatPayEcommerceApplyment::from_client(self.clone())
+    }
+
+    /// 微信支付分
+    pub fn pay_score(&self) -> WechatPayScore<T> {
+        WechatPayScore::from_client(self.clone())
+    }
+
+    /// 微信支付分停车服务
+    pub fn parking(&self) -> WechatPayParking<T> {
+        WechatPayParking::from_client(self.clone())
+    }
+
+
+}
+
+/// 使用平台证书校验签名，签名串为`时间戳\n随机串\n报文主体\n`
+fn verify_signature(certs: &DashMap<String, LabraCertificate>, header: &SignatureHeader, data: &str) -> bool {
+    let before_sign = format!("{}\n{}\n{}\n", header.time_stamp, header.nonce, data);
+    // V3  验证签名
+    if let Some(cert) = certs.get(&header.serial) {
+        let content = String::from_utf8_lossy(&cert.public_key).to_string();
+        WechatCryptoV3::verify(&before_sign, &header.signature, &content).unwrap_or(false)
+    } else {
+        false
+    }
+}
+
+fn verify_response_with(certs: &DashMap<String, LabraCertificate>, response: &LabraResponse) -> LabradorResult<()> {
+    let header = SignatureHeader::from_header(response.header());
+    let request_id = response.request_id().unwrap_or_default().to_string();
+    if header.signature.is_empty() || header.serial.is_empty() || header.time_stamp.is_empty() || header.nonce.is_empty() {
+        return Err(LabraError::MissingHeaders { request_id });
+    }
+    let timestamp = header.time_stamp.parse::<i64>().map_err(|_| LabraError::MissingHeaders { request_id: request_id.to_owned() })?;
+    if (current_timestamp() - timestamp).abs() > RESPONSE_MAX_AGE {
+        return Err(LabraError::InvalidSignature(format!("应答时间戳{}已超过5分钟，Request-ID：{}", timestamp, request_id)));
+    }
+    if !certs.contains_key(&header.serial) {
+        return Err(LabraError::UnknownSerial { serial: header.serial, request_id });
+    }
+    let body = String::from_utf8_lossy(&response.bytes()?).to_string();
+    if verify_signature(certs, &header, &body) {
+        Ok(())
+    } else {
+        Err(LabraError::SignatureMismatch { serial: header.serial, request_id })
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509, X509NameBuilder};
+    use openssl::symm;
+    use serde_json::json;
+    use crate::{APIClient, LabraCertificate, LabraError, RequestType, SimpleStorage};
+    use crate::util::current_timestamp;
+    use crate::util::prp::PrpCrypto;
+    use crate::util::mock::{closed_url, MockResponse, MockServer};
+    use crate::{Amount, SessionStore, TradeNo, WechatCloseOrderRequest, WechatCloseOrderRequestV3, WechatPayRequestV3, WechatQueryOrderRequestV3};
+    use super::{TradeType, WechatPayClient};
+    use super::method::{WechatPayMethod, WxPayMethod};
+
+    const BODY: &str = r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#;
+
+    pub(crate) fn generate_cert() -> (String, LabraCertificate) {
+        let rsa = Rsa::generate(2048).unwrap();
+        let private_key = String::from_utf8(rsa.private_key_to_pem().unwrap()).unwrap();
+        let pkey = PKey::from_rsa(rsa).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "Tenpay.com Root CA").unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(5157).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        let cert = LabraCertificate::from_pem(builder.build().to_pem().unwrap()).unwrap();
+        (private_key, cert)
+    }
+
+    const API_V3_KEY: &str = "a7cde1ef41c4d4d4c8b9b0a5c3e2f1d0";
+
+    /// 使用平台证书私钥对`signed_body`签名，返回`body`
+    fn response_signed_at(private_key: &str, serial_no: &str, body: &str, signed_body: &str, timestamp: i64) -> MockResponse {
+        let timestamp = timestamp.to_string();
+        let nonce = "593BEC0C930BF1AFEB40B4A08C8FB242";
+        let signature = PrpCrypto::rsa_sha256_sign(&format!("{}\n{}\n{}\n", timestamp, nonce, signed_body), private_key).unwrap();
+        MockResponse::json(body)
+            .header("Request-ID", "08F78BB5AF0610D302839A0519C4A0C5")
+            .header("Wechatpay-Serial", serial_no)
+            .header("Wechatpay-Timestamp", &timestamp)
+            .header("Wechatpay-Nonce", nonce)
+            .header("Wechatpay-Signature", &signature)
+    }
+
+    fn response_signed_with(private_key: &str, serial_no: &str, body: &str, signed_body: &str) -> MockResponse {
+        response_signed_at(private_key, serial_no, body, signed_body, current_timestamp())
+    }
 
+    /// 正确签名的V3应答
+    pub(crate) fn signed_response(private_key: &str, serial_no: &str, body: &str) -> MockResponse {
+        response_signed_with(private_key, serial_no, body, body)
+    }
+
+    /// 平台证书下载接口的应答，证书使用APIv3密钥加密
+    fn certificates_response(private_key: &str, serial_no: &str, cert: &LabraCertificate) -> MockResponse {
+        let (nonce, associated_data) = ("61f9c719728a", "certificate");
+        let mut tag = [0u8; 16];
+        let mut ciphertext = symm::encrypt_aead(symm::Cipher::aes_256_gcm(), API_V3_KEY.as_bytes(), Some(nonce.as_bytes()), associated_data.as_bytes(), &cert.content, &mut tag).unwrap();
+        ciphertext.extend_from_slice(&tag);
+        let body = json!({
+            "data": [{
+                "serial_no": serial_no,
+                "effective_time": "2018-06-08T10:34:56+08:00",
+                "expire_time": "2023-06-08T10:34:56+08:00",
+                "encrypt_certificate": {
+                    "algorithm": "AEAD_AES_256_GCM",
+                    "nonce": nonce,
+                    "associated_data": associated_data,
+                    "ciphertext": base64::encode(&ciphertext),
+                }
+            }]
+        });
+        signed_response(private_key, serial_no, &body.to_string())
+    }
+
+    async fn mock_server(private_key: &str, serial_no: &str, signed_body: &str) -> String {
+        MockServer::start(vec![response_signed_with(private_key, serial_no, BODY, signed_body)]).await.url
+    }
+
+    pub(crate) fn pay_client(api_path: String, private_key: &str, cert: LabraCertificate) -> WechatPayClient<SimpleStorage> {
+        let client = APIClient::from_session("wxd930ea5d5a258f4f".to_string(), "secret".to_string(), api_path.as_str(), SimpleStorage::new());
+        let client = WechatPayClient::from_client(client)
+            .mch_id("1230000109".to_string())
+            .serial_no("5157F09EFDC096DE15EBE81A47057A7232F1B8E1".to_string())
+            .private_key(private_key.to_string());
+        client.inner.certs.insert(cert.serial_no.to_owned(), cert);
+        client
+    }
+
+    async fn native_order(client: &WechatPayClient<SimpleStorage>) -> Result<String, LabraError> {
+        let response = client.post_v3(None, WechatPayMethod::WxPay(WxPayMethod::UnifiedOrderV3(TradeType::Native)), vec![], json!({"description": "test"}), RequestType::Json).await?;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.request_id(), Some("08F78BB5AF0610D302839A0519C4A0C5"));
+        assert!(response.header().contains_key("wechatpay-signature"));
+        response.text()
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response() {
+        let (private_key, cert) = generate_cert();
+        let url = mock_server(&private_key, &cert.serial_no, BODY).await;
+        let client = pay_client(url, &private_key, cert);
+        assert_eq!(native_order(&client).await.unwrap(), BODY);
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response_wrong_signature() {
+        let (private_key, cert) = generate_cert();
+        // 使用其它报文的签名
+        let url = mock_server(&private_key, &cert.serial_no, r#"{"code_url":"weixin://wxpay/other"}"#).await;
+        let serial_no = cert.serial_no.to_owned();
+        let client = pay_client(url, &private_key, cert);
+        match native_order(&client).await {
+            Err(LabraError::SignatureMismatch { serial, request_id }) => {
+                assert_eq!(serial, serial_no);
+                assert_eq!(request_id, "08F78BB5AF0610D302839A0519C4A0C5");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response_unknown_serial() {
+        let (private_key, cert) = generate_cert();
+        // 重新下载的证书中也没有该序列号
+        let server = MockServer::start(vec![
+            response_signed_with(&private_key, "UNKNOWN", BODY, BODY),
+            certificates_response(&private_key, &cert.serial_no, &cert),
+        ]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert).key_v3(API_V3_KEY.to_string());
+        assert!(matches!(native_order(&client).await, Err(LabraError::UnknownSerial { serial, .. }) if serial == "UNKNOWN"));
+        assert!(server.requests()[1].starts_with("GET /v3/certificates HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response_refresh_cert() {
+        let (private_key, cert) = generate_cert();
+        // 平台证书轮换，应答使用了本地还没有的新证书
+        let (new_private_key, new_cert) = generate_cert();
+        let new_serial = "7132D72A03E93CDDF8C03BBD1F37EEDF43EA1A5D";
+        let server = MockServer::start(vec![
+            signed_response(&new_private_key, new_serial, BODY),
+            certificates_response(&new_private_key, new_serial, &new_cert),
+        ]).await;
+        let client = pay_client(server.url.to_owned(), &private_key, cert).key_v3(API_V3_KEY.to_string());
+        assert_eq!(native_order(&client).await.unwrap(), BODY);
+        assert_eq!(client.inner.certs.len(), 2);
+        assert!(client.inner.certs.contains_key(new_serial));
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response_missing_signature() {
+        let (private_key, cert) = generate_cert();
+        let url = MockServer::start(vec![MockResponse::json(BODY).header("Request-ID", "08F78BB5AF0610D302839A0519C4A0C5")]).await.url;
+        let client = pay_client(url, &private_key, cert);
+        assert!(matches!(native_order(&client).await, Err(LabraError::MissingHeaders { request_id }) if request_id == "08F78BB5AF0610D302839A0519C4A0C5"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response_expired() {
+        let (private_key, cert) = generate_cert();
+        let url = MockServer::start(vec![response_signed_at(&private_key, &cert.serial_no, BODY, BODY, current_timestamp() - 301)]).await.url;
+        let client = pay_client(url, &private_key, cert);
+        assert!(matches!(native_order(&client).await, Err(LabraError::InvalidSignature(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_v3_response_disabled() {
+        let (private_key, cert) = generate_cert();
+        let url = mock_server(&private_key, &cert.serial_no, r#"{"code_url":"weixin://wxpay/other"}"#).await;
+        let client = pay_client(url, &private_key, cert).verify_response_signature(false);
+        assert_eq!(native_order(&client).await.unwrap(), BODY);
+    }
+
+    fn sandbox_client(api_path: String, mch_id: &str) -> WechatPayClient<SimpleStorage> {
+        let client = APIClient::from_session("wxd678efh567hg6787".to_string(), "secret".to_string(), api_path.as_str(), SimpleStorage::new());
+        WechatPayClient::from_client(client)
+            .mch_id(mch_id.to_string())
+            .key("192006250b4c09247ec02edce69f6a2d".to_string())
+            .sandbox(true)
+    }
+
+    fn close_order_request(mch_id: &str) -> WechatCloseOrderRequest {
+        WechatCloseOrderRequest {
+            appid: None,
+            mch_id: mch_id.to_string(),
+            out_trade_no: "1217752501201407033233368018".to_string(),
+            sign: "".to_string(),
+            nonce_str: Some("5K8264ILTKCH16CQ2502SI8ZNMTM67VS".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_sign_key() {
+        let sign_key = r#"<xml><return_code><![CDATA[SUCCESS]]></return_code><return_msg><![CDATA[ok]]></return_msg><sandbox_signkey><![CDATA[013467007045764]]></sandbox_signkey><mch_id><![CDATA[1900000109]]></mch_id></xml>"#;
+        let close = r#"<xml><return_code><![CDATA[SUCCESS]]></return_code><return_msg><![CDATA[OK]]></return_msg><appid><![CDATA[wxd678efh567hg6787]]></appid><mch_id><![CDATA[1900000109]]></mch_id><nonce_str><![CDATA[BFK89FC6rxKCOjLX]]></nonce_str><sign><![CDATA[72B321D92A7BFA0B2509F3D13C7B1631]]></sign><result_code><![CDATA[SUCCESS]]></result_code></xml>"#;
+        let server = MockServer::start(vec![
+            MockResponse::bytes("text/xml", sign_key.as_bytes()),
+            MockResponse::bytes("text/xml", close.as_bytes()),
+            MockResponse::bytes("text/xml", close.as_bytes()),
+        ]).await;
+        let client = sandbox_client(server.url.to_owned(), "1900000109");
+        assert!(client.is_sandbox());
+        let res = client.wxpay().close_order(close_order_request("1900000109")).await.unwrap();
+        assert_eq!(res.result_code, "SUCCESS");
+        // 沙箱密钥已缓存，不再重复获取
+        client.wxpay().close_order(close_order_request("1900000109")).await.unwrap();
+        assert_eq!(client.get_sandbox_sign_key().await.unwrap(), "013467007045764");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with("POST /sandboxnew/pay/getsignkey HTTP/1.1"));
+        assert!(requests[0].contains("<mch_id>1900000109</mch_id>"));
+        assert!(requests[1].starts_with("POST /sandboxnew/pay/closeorder HTTP/1.1"));
+        // 沙箱环境中的V2接口使用沙箱密钥签名
+        let mut expected = close_order_request("1900000109");
+        expected.appid = Some("wxd678efh567hg6787".to_string());
+        expected.get_sign("013467007045764").unwrap();
+        assert!(requests[1].contains(&format!("<sign>{}</sign>", expected.sign)));
+        assert!(requests[2].starts_with("POST /sandboxnew/pay/closeorder HTTP/1.1"));
+
+        let session = client.inner.client.session();
+        let cached: Option<String> = session.get("1900000109_sandbox_signkey_pay", None).unwrap();
+        assert_eq!(cached.as_deref(), Some("013467007045764"));
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_sign_key_error() {
+        let fail = r#"<xml><return_code><![CDATA[FAIL]]></return_code><return_msg><![CDATA[签名错误]]></return_msg></xml>"#;
+        let server = MockServer::start(vec![MockResponse::bytes("text/xml", fail.as_bytes())]).await;
+        let client = sandbox_client(server.url.to_owned(), "1900000110");
+        match client.wxpay().close_order(close_order_request("1900000110")).await {
+            Err(LabraError::ClientError { errmsg, .. }) => assert_eq!(errmsg, "签名错误"),
+            other => panic!("unexpected {:?}", other),
+        }
+        // 缺少商户API密钥
+        let client = WechatPayClient::<SimpleStorage>::new("wxd678efh567hg6787", "secret").mch_id("1900000111".to_string()).sandbox(true);
+        assert!(matches!(client.get_sandbox_sign_key().await, Err(LabraError::MissingField(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_v3_unsupported() {
+        let url = closed_url().await;
+        let (private_key, cert) = generate_cert();
+        let client = pay_client(url, &private_key, cert).sandbox(true);
+        assert!(matches!(native_order(&client).await, Err(LabraError::Unsupported(_))));
+        let res = client.wxpay().query_order_v3(WechatQueryOrderRequestV3 { mchid: "1230000109".to_string(), transaction_id: None, out_trade_no: TradeNo::try_new("1217752501201407033233368018").ok() }).await;
+        match res {
+            Err(LabraError::Unsupported(msg)) => assert!(msg.contains("/v3/pay/transactions/out-trade-no/1217752501201407033233368018"), "{}", msg),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(client.get_certificates().await, Err(LabraError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_validate_before_request() {
+        let server = MockServer::start(vec![]).await;
+        let (private_key, cert) = generate_cert();
+        let client = pay_client(server.url.to_owned(), &private_key, cert);
+        let order = WechatPayRequestV3 {
+            appid: None,
+            mch_id: "mchid".to_string(),
+            description: "测试商品支付".to_string(),
+            out_trade_no: TradeNo::sanitize("订单 20221001 0001").unwrap(),
+            time_expire: "2022-10-01T00:00:00+08:00".to_string(),
+            attach: None,
+            notify_url: "https://example.com/notify".to_string(),
+            amount: Amount { total: 1, currency: None, payer_total: None, payer_currency: None },
+            payer: None,
+            detail: None,
+            scene_info: None,
+            settle_info: None,
+        };
+        assert_eq!(serde_json::to_value(&order).unwrap()["out_trade_no"], "_20221001_0001");
+        let err = client.wxpay().unified_order_v3(TradeType::Native, order.clone()).await.unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(msg) if msg.contains("mchid")));
+        let err = client.wxpay().unified_order_v3(TradeType::Native, WechatPayRequestV3 { mch_id: "1230000109".to_string(), appid: Some("appid".to_string()), ..order }).await.unwrap_err();
+        assert!(matches!(err, LabraError::RequestError(msg) if msg.contains("appid")));
+        let err = client.wxpay().close_order_v3(WechatCloseOrderRequestV3 { mchid: "1230000109".to_string(), out_trade_no: None }).await.unwrap_err();
+        assert!(matches!(err, LabraError::MissingField(_)));
+        assert!(client.wxpay().query_refund_order_v3("退款单号".to_string()).await.is_err());
+        assert!(server.requests().is_empty());
+    }
 }
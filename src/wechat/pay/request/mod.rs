@@ -1,9 +1,12 @@
 use std::collections::BTreeMap;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
 use serde::{Serialize, Deserialize};
 use crate::{LabradorResult, LabraError};
+use crate::plate::validate_plate_number;
+use crate::serde_helper::rfc3339;
 
 use crate::util::get_sign;
-use crate::wechat::pay::TradeType;
+use crate::wechat::pay::{AppId, MchId, TradeNo, TradeType};
 
 //----------------------------------------------------------------------------------------------------------------------------
 
@@ -58,7 +61,7 @@ pub struct WechatPayRequestV3 {
     /// 商品描述
     pub description: String,
     /// 商户订单号
-    pub out_trade_no: String,
+    pub out_trade_no: TradeNo,
     /// 交易结束时间
     pub time_expire: String,
     /// 附加数据
@@ -101,7 +104,7 @@ pub struct IsvWechatPayRequestV3 {
     /// 商品描述
     pub description: String,
     /// 商户订单号
-    pub out_trade_no: String,
+    pub out_trade_no: TradeNo,
     /// 交易结束时间
     pub time_expire: String,
     /// 附加数据
@@ -125,6 +128,30 @@ pub struct IsvWechatPayRequestV3 {
     pub settle_info: Option<SettleInfo>,
 }
 
+impl WechatPayRequestV3 {
+    /// 校验商户号与appid，商户订单号在构造[`TradeNo`]时已校验
+    pub fn check_params(&self) -> LabradorResult<()> {
+        MchId::validate(&self.mch_id)?;
+        if let Some(appid) = &self.appid {
+            AppId::validate(appid)?;
+        }
+        Ok(())
+    }
+}
+
+impl IsvWechatPayRequestV3 {
+    /// 校验服务商与子商户的商户号、appid
+    pub fn check_params(&self) -> LabradorResult<()> {
+        for mchid in self.sp_mchid.iter().chain(self.sub_mchid.iter()) {
+            MchId::validate(mchid)?;
+        }
+        for appid in self.sp_appid.iter().chain(self.sub_appid.iter()) {
+            AppId::validate(appid)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Amount {
     /// 订单总金额，单位为分。
@@ -274,7 +301,7 @@ impl WechatPayRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -297,7 +324,8 @@ impl WechatPayRequest {
         if let Some(notify_url) = self.notify_url.to_owned() {
             pairs.insert("notify_url".to_string(), notify_url);
         }
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
     }
 
     pub(crate) fn check_params(&self) -> LabradorResult<()> {
@@ -348,7 +376,131 @@ pub struct WechatCloseOrderRequestV3 {
     pub mchid: String,
     /// 商户订单号
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub out_trade_no: Option<String>,
+    pub out_trade_no: Option<TradeNo>,
+}
+
+/// 合单支付单次最多的子单数
+const MAX_COMBINE_SUB_ORDERS: usize = 10;
+
+/// 合单下单请求
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatCombineOrderRequest {
+    /// 合单发起方的appid，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub combine_appid: Option<String>,
+    /// 合单发起方商户号，为空时使用客户端的商户号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub combine_mchid: Option<String>,
+    /// 合单商户订单号
+    pub combine_out_trade_no: TradeNo,
+    /// 场景信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene_info: Option<SceneInfo>,
+    /// 子单信息，最多10笔
+    pub sub_orders: Vec<CombineSubOrder>,
+    /// 支付者，JSAPI下单必传
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub combine_payer_info: Option<Payer>,
+    /// 交易起始时间，遵循rfc3339标准格式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_start: Option<String>,
+    /// 交易结束时间，遵循rfc3339标准格式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_expire: Option<String>,
+    /// 通知地址
+    pub notify_url: String,
+}
+
+impl WechatCombineOrderRequest {
+    /// 校验子单数量与币种，以及合单发起方、子单的商户号与appid
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if let Some(combine_appid) = &self.combine_appid {
+            AppId::validate(combine_appid)?;
+        }
+        if let Some(combine_mchid) = &self.combine_mchid {
+            MchId::validate(combine_mchid)?;
+        }
+        for order in self.sub_orders.iter() {
+            MchId::validate(&order.mchid)?;
+            if let Some(sub_mchid) = &order.sub_mchid {
+                MchId::validate(sub_mchid)?;
+            }
+        }
+        if self.sub_orders.is_empty() {
+            return Err(LabraError::RequestError("合单支付子单不能为空".to_string()));
+        }
+        if self.sub_orders.len() > MAX_COMBINE_SUB_ORDERS {
+            return Err(LabraError::RequestError(format!("合单支付子单不能超过{}笔，当前{}笔", MAX_COMBINE_SUB_ORDERS, self.sub_orders.len())));
+        }
+        let currency = &self.sub_orders[0].amount.currency;
+        if let Some(order) = self.sub_orders.iter().find(|order| &order.amount.currency != currency) {
+            return Err(LabraError::RequestError(format!("合单支付子单币种必须一致，子单{}币种{}与{}不一致", order.out_trade_no, order.amount.currency, currency)));
+        }
+        Ok(())
+    }
+}
+
+/// 合单子单
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CombineSubOrder {
+    /// 子单发起方商户号
+    pub mchid: String,
+    /// 附加数据
+    pub attach: String,
+    /// 订单金额
+    pub amount: CombineAmount,
+    /// 子单商户订单号
+    pub out_trade_no: TradeNo,
+    /// 二级商户号（电商平台模式）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_mchid: Option<String>,
+    /// 商品描述
+    pub description: String,
+    /// 结算信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settle_info: Option<SettleInfo>,
+    /// 订单优惠标记
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<String>,
+}
+
+/// 合单金额
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CombineAmount {
+    /// 标价金额，单位为分
+    pub total_amount: i64,
+    /// 标价币种，境内商户号仅支持人民币CNY
+    pub currency: String,
+    /// 现金支付金额，查询与通知时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer_amount: Option<i64>,
+    /// 现金支付币种，查询与通知时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer_currency: Option<String>,
+}
+
+/// 合单关单请求
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatCombineCloseRequest {
+    /// 合单发起方的appid，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub combine_appid: Option<String>,
+    /// 合单商户订单号，路径参数，不参与序列化
+    #[serde(skip_serializing)]
+    pub combine_out_trade_no: TradeNo,
+    /// 需关闭的子单信息
+    pub sub_orders: Vec<CombineCloseSubOrder>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CombineCloseSubOrder {
+    /// 子单发起方商户号
+    pub mchid: String,
+    /// 子单商户订单号
+    pub out_trade_no: TradeNo,
+    /// 二级商户号（电商平台模式）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_mchid: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -376,7 +528,7 @@ pub struct WechatQueryOrderRequestV3 {
     pub transaction_id: Option<String>,
     /// 商户订单号
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub out_trade_no: Option<String>,
+    pub out_trade_no: Option<TradeNo>,
 }
 
 
@@ -400,7 +552,7 @@ impl WechatCloseOrderRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -410,7 +562,8 @@ impl WechatCloseOrderRequest {
         if let Some(nonce_str) = self.nonce_str.to_owned() {
             pairs.insert("nonce_str".to_string(), nonce_str);
         }
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
     }
 }
 
@@ -436,7 +589,7 @@ impl WechatQueryOrderRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -447,7 +600,8 @@ impl WechatQueryOrderRequest {
         if let Some(nonce_str) = self.nonce_str.to_owned() {
             pairs.insert("nonce_str".to_string(), nonce_str);
         }
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
     }
 }
 
@@ -478,9 +632,9 @@ pub struct WechatRefundRequestV3 {
     pub transaction_id: Option<String>,
     /// 商户订单号 原支付交易对应的商户订单号。 与transaction_id二选一
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub out_trade_no: Option<String>,
+    pub out_trade_no: Option<TradeNo>,
     /// 退款订单号 商户系统内部的退款单号，商户系统内部唯一，只能是数字、大小写字母_-|*@ ，同一退款单号多次请求只退一笔。
-    pub out_refund_no: String,
+    pub out_refund_no: TradeNo,
     /// 原因 若商户传入，会在下发给用户的退款消息中体现退款原因。
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reason: Option<String>,
@@ -581,7 +735,7 @@ impl WechatRefundRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -599,7 +753,8 @@ impl WechatRefundRequest {
             pairs.insert("nonce_str".to_string(), nonce_str);
         }
         // let setting = &SETTINGS;
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
     }
 }
 
@@ -626,7 +781,7 @@ impl WechatOrderReverseRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -634,7 +789,8 @@ impl WechatOrderReverseRequest {
         pairs.insert("mch_id".to_string(), self.mch_id.to_owned());
         pairs.insert("out_trade_no".to_string(), self.out_trade_no.to_owned());
         pairs.insert("transaction_id".to_string(), self.transaction_id.to_owned());
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
     }
 }
 
@@ -668,7 +824,7 @@ pub struct WechatQueryRefundOrderRequestV3 {
     /// 退款订单号
     /// 商户系统内部的退款单号，商户系统内部唯一，只能是数字、大小写字母_-|*@ ，同一退款单号多次请求只退一笔。
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub out_refund_no: Option<String>,
+    pub out_refund_no: Option<TradeNo>,
 }
 
 #[allow(unused)]
@@ -697,7 +853,7 @@ impl WechatQueryRefundOrderRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -711,14 +867,15 @@ impl WechatQueryRefundOrderRequest {
             pairs.insert("nonce_str".to_string(), nonce_str);
         }
         // let setting = &SETTINGS;
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
     }
 }
 
 
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct WxPayShorturlRequest {
+pub struct WechatPayShortUrlRequest {
     /// <pre>
     /// URL链接
     /// long_url
@@ -738,9 +895,12 @@ pub struct WxPayShorturlRequest {
 
 }
 
+#[deprecated(note = "请使用`WechatPayShortUrlRequest`")]
+pub type WxPayShorturlRequest = WechatPayShortUrlRequest;
+
 
 #[allow(unused)]
-impl WxPayShorturlRequest {
+impl WechatPayShortUrlRequest {
     pub fn parse_xml(&self) -> String {
         let msg = format!(
             "<xml>\n\
@@ -759,7 +919,7 @@ impl WxPayShorturlRequest {
         msg
     }
 
-    pub fn get_sign(&mut self, appkey: &str) {
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
         let mut pairs = BTreeMap::new();
         if let Some(appid) = self.appid.to_owned() {
             pairs.insert("appid".to_string(), appid);
@@ -770,6 +930,744 @@ impl WxPayShorturlRequest {
             pairs.insert("nonce_str".to_string(), nonce_str);
         }
         // let setting = &SETTINGS;
-        self.sign = get_sign(&pairs, appkey);
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
+    }
+}
+
+/// 获取沙箱密钥
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WechatPaySandboxSignKeyRequest {
+    /// 商户号
+    pub mch_id: String,
+    /// 随机字符串
+    pub nonce_str: String,
+    /// 签名，使用正式的商户API密钥
+    pub sign: String,
+}
+
+#[allow(unused)]
+impl WechatPaySandboxSignKeyRequest {
+    pub fn parse_xml(&self) -> String {
+        let msg = format!(
+            "<xml>\n\
+                <mch_id>{mch_id}</mch_id>\n\
+                <nonce_str>{nonce_str}</nonce_str>\n\
+                <sign>{sign}</sign>\n\
+            </xml>",
+            mch_id=self.mch_id,
+            nonce_str=self.nonce_str,
+            sign=self.sign,
+        );
+        msg
+    }
+
+    pub fn get_sign(&mut self, appkey: &str) -> LabradorResult<()> {
+        let mut pairs = BTreeMap::new();
+        pairs.insert("mch_id".to_string(), self.mch_id.to_owned());
+        pairs.insert("nonce_str".to_string(), self.nonce_str.to_owned());
+        self.sign = get_sign(&pairs, appkey)?;
+        Ok(())
+    }
+}
+
+/// 发放现金红包
+/// <pre>
+/// mch_id、wxappid、nonce_str未设置时使用客户端配置补全，sign由`WechatPayV2`计算
+/// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/tools/cash_coupon.php?chapter=13_4&index=3)
+/// </pre>
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WechatPaySendRedpackRequest {
+    /// 商户订单号，每个订单号必须唯一，失败重试时需使用原订单号
+    pub mch_billno: String,
+    /// 商户号
+    pub mch_id: Option<String>,
+    /// 公众账号appid
+    pub wxappid: Option<String>,
+    /// 商户名称
+    pub send_name: String,
+    /// 用户openid
+    pub re_openid: String,
+    /// 付款金额，单位分
+    pub total_amount: i64,
+    /// 红包发放总人数
+    pub total_num: i32,
+    /// 红包祝福语
+    pub wishing: String,
+    /// 调用接口的机器IP地址
+    pub client_ip: String,
+    /// 活动名称
+    pub act_name: String,
+    /// 备注
+    pub remark: String,
+    /// 场景id，发放红包金额大于200或者小于1元时必传
+    pub scene_id: Option<String>,
+    /// 活动信息，urlencode后的键值对
+    pub risk_info: Option<String>,
+    /// 随机字符串
+    pub nonce_str: Option<String>,
+}
+
+/// 企业付款到零钱
+/// <pre>
+/// mchid、mch_appid、nonce_str未设置时使用客户端配置补全，sign由`WechatPayV2`计算
+/// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/tools/mch_pay.php?chapter=14_2)
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayTransfersRequest {
+    /// 商户账号appid
+    pub mch_appid: Option<String>,
+    /// 商户号
+    pub mchid: Option<String>,
+    /// 设备号
+    pub device_info: Option<String>,
+    /// 商户订单号，失败重试时需使用原订单号
+    pub partner_trade_no: TradeNo,
+    /// 用户openid
+    pub openid: String,
+    /// 校验用户姓名选项，NO_CHECK：不校验真实姓名，FORCE_CHECK：强校验真实姓名
+    pub check_name: String,
+    /// 收款用户姓名，check_name为FORCE_CHECK时必填
+    pub re_user_name: Option<String>,
+    /// 付款金额，单位分
+    pub amount: i64,
+    /// 付款备注
+    pub desc: String,
+    /// 调用接口的机器IP地址
+    pub spbill_create_ip: Option<String>,
+    /// 随机字符串
+    pub nonce_str: Option<String>,
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+// 电商收付通 ↓
+
+/// 二级商户进件申请
+/// <pre>
+/// 身份证、营业执照等图片需先通过图片上传接口获取media_id，
+/// 姓名、证件号码、银行账号、联系方式等敏感字段填写明文即可，提交时由`WechatPayEcommerceApplyment`使用平台证书加密
+/// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter7_1_1.shtml)
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatEcommerceApplymentRequest {
+    /// 业务申请编号，服务商自定义的唯一编号
+    pub out_request_no: String,
+    /// 主体类型，2401：小微商户，2500：个人卖家，4：个体工商户，2：企业，3：党政、机关及事业单位，1708：其他组织
+    pub organization_type: String,
+    /// 营业执照/登记证书信息，小微商户、个人卖家不填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub business_license_info: Option<EcommerceBusinessLicenseInfo>,
+    /// 经营者/法人证件类型，默认为IDENTIFICATION_TYPE_MAINLAND_IDCARD
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_doc_type: Option<String>,
+    /// 经营者/法人身份证信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_card_info: Option<EcommerceIdCardInfo>,
+    /// 是否填写结算银行账户
+    pub need_account_info: bool,
+    /// 结算银行账户，need_account_info为true时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_info: Option<EcommerceAccountInfo>,
+    /// 超级管理员信息
+    pub contact_info: EcommerceContactInfo,
+    /// 店铺信息
+    pub sales_scene_info: EcommerceSalesSceneInfo,
+    /// 商户简称，在支付完成页向买家展示
+    pub merchant_shortname: String,
+    /// 特殊资质，media_id组成的JSON数组字符串
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qualifications: Option<String>,
+    /// 补充材料，media_id组成的JSON数组字符串
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub business_addition_pics: Option<String>,
+    /// 补充说明
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub business_addition_desc: Option<String>,
+}
+
+impl WechatEcommerceApplymentRequest {
+    /// 使用`encrypt`逐个加密敏感字段：身份证姓名与号码、开户名称与银行账号、超级管理员姓名、身份证号码、手机号码与邮箱
+    pub fn encrypt_fields<F: Fn(&str) -> LabradorResult<String>>(&mut self, encrypt: F) -> LabradorResult<()> {
+        if let Some(id_card_info) = self.id_card_info.as_mut() {
+            id_card_info.id_card_name = encrypt(&id_card_info.id_card_name)?;
+            id_card_info.id_card_number = encrypt(&id_card_info.id_card_number)?;
+        }
+        if let Some(account_info) = self.account_info.as_mut() {
+            account_info.account_name = encrypt(&account_info.account_name)?;
+            account_info.account_number = encrypt(&account_info.account_number)?;
+        }
+        let contact_info = &mut self.contact_info;
+        contact_info.contact_name = encrypt(&contact_info.contact_name)?;
+        if let Some(contact_id_card_number) = contact_info.contact_id_card_number.as_mut() {
+            *contact_id_card_number = encrypt(contact_id_card_number)?;
+        }
+        contact_info.mobile_phone = encrypt(&contact_info.mobile_phone)?;
+        if let Some(contact_email) = contact_info.contact_email.as_mut() {
+            *contact_email = encrypt(contact_email)?;
+        }
+        Ok(())
+    }
+}
+
+/// 营业执照/登记证书信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceBusinessLicenseInfo {
+    /// 证件扫描件，图片上传返回的media_id
+    pub business_license_copy: String,
+    /// 证件注册号，统一社会信用代码或营业执照注册号
+    pub business_license_number: String,
+    /// 商户名称
+    pub merchant_name: String,
+    /// 经营者/法定代表人姓名
+    pub legal_person: String,
+    /// 注册地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_address: Option<String>,
+    /// 营业期限，如`["2017-10-28","长期"]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub business_time: Option<String>,
+}
+
+/// 经营者/法人身份证信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceIdCardInfo {
+    /// 身份证人像面照片，图片上传返回的media_id
+    pub id_card_copy: String,
+    /// 身份证国徽面照片，图片上传返回的media_id
+    pub id_card_national: String,
+    /// 身份证姓名，敏感字段
+    pub id_card_name: String,
+    /// 身份证号码，敏感字段
+    pub id_card_number: String,
+    /// 身份证有效期开始时间，如2019-06-06
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_card_valid_time_begin: Option<String>,
+    /// 身份证有效期结束时间，如2026-06-06或长期
+    pub id_card_valid_time: String,
+}
+
+/// 结算银行账户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceAccountInfo {
+    /// 账户类型，74：对公账户，75：对私账户
+    pub bank_account_type: String,
+    /// 开户银行
+    pub account_bank: String,
+    /// 开户名称，敏感字段
+    pub account_name: String,
+    /// 开户银行省市编码
+    pub bank_address_code: String,
+    /// 开户银行联行号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_branch_id: Option<String>,
+    /// 开户银行全称（含支行）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_name: Option<String>,
+    /// 银行账号，敏感字段
+    pub account_number: String,
+}
+
+/// 超级管理员信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceContactInfo {
+    /// 超级管理员类型，65：经营者/法人，66：经办人
+    pub contact_type: String,
+    /// 超级管理员姓名，敏感字段
+    pub contact_name: String,
+    /// 超级管理员身份证件号码，敏感字段，类型为经办人时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_id_card_number: Option<String>,
+    /// 超级管理员手机，敏感字段
+    pub mobile_phone: String,
+    /// 超级管理员邮箱，敏感字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_email: Option<String>,
+}
+
+/// 店铺信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceSalesSceneInfo {
+    /// 店铺名称
+    pub store_name: String,
+    /// 店铺链接，与店铺二维码二选一
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_url: Option<String>,
+    /// 店铺二维码，图片上传返回的media_id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_qr_code: Option<String>,
+    /// 小程序AppID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mini_program_sub_appid: Option<String>,
+}
+
+/// 二级商户进件申请状态的查询方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcommerceApplymentNo {
+    /// 微信支付申请单号
+    ApplymentId(u64),
+    /// 业务申请编号
+    OutRequestNo(String),
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+// 微信支付分 ↓
+
+/// <pre>
+/// 支付分服务时间
+/// 服务开始时间可以填写`OnAccept`，表示以用户确认订单成功的时间为准；其余时间为yyyyMMdd或yyyyMMddHHmmss
+/// </pre>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayScoreTime {
+    /// 用户确认订单成功的时间，只能用于服务开始时间
+    OnAccept,
+    /// yyyyMMdd，精确到日
+    Date(NaiveDate),
+    /// yyyyMMddHHmmss
+    DateTime(NaiveDateTime),
+}
+
+/// 用户确认订单成功的时间
+const PAY_SCORE_ON_ACCEPT: &str = "OnAccept";
+
+impl std::fmt::Display for PayScoreTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayScoreTime::OnAccept => f.write_str(PAY_SCORE_ON_ACCEPT),
+            PayScoreTime::Date(v) => write!(f, "{}", v.format("%Y%m%d")),
+            PayScoreTime::DateTime(v) => write!(f, "{}", v.format("%Y%m%d%H%M%S")),
+        }
+    }
+}
+
+impl std::str::FromStr for PayScoreTime {
+    type Err = LabraError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let time = match s.len() {
+            _ if s == PAY_SCORE_ON_ACCEPT => Some(PayScoreTime::OnAccept),
+            8 => NaiveDate::parse_from_str(s, "%Y%m%d").ok().map(PayScoreTime::Date),
+            14 => NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S").ok().map(PayScoreTime::DateTime),
+            _ => None,
+        };
+        time.ok_or_else(|| LabraError::RequestError(format!("支付分服务时间{}格式有误，应为yyyyMMdd、yyyyMMddHHmmss或OnAccept", s)))
+    }
+}
+
+impl Serialize for PayScoreTime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PayScoreTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 服务时间段
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PayScoreTimeRange {
+    /// 服务开始时间
+    pub start_time: PayScoreTime,
+    /// 服务开始时间备注
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time_remark: Option<String>,
+    /// 预计服务结束时间，完结订单时为实际服务结束时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<PayScoreTime>,
+    /// 服务结束时间备注
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_time_remark: Option<String>,
+}
+
+/// 服务位置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PayScoreLocation {
+    /// 服务开始地点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_location: Option<String>,
+    /// 服务结束地点，有结束地点时必须同时填写开始地点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_location: Option<String>,
+}
+
+/// 订单风险金
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PayScoreRiskFund {
+    /// 风险金名称：DEPOSIT（押金）、ADVANCE（预付款）、CASH_DEPOSIT（保证金）、ESTIMATE_ORDER_COST（预估订单费用）
+    pub name: String,
+    /// 风险金额，单位分，不能超过服务ID的风险金额上限
+    pub amount: i64,
+    /// 风险说明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// 后付费项目
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PayScorePostPayment {
+    /// 付费项目名称，如“就餐费”、“租借费”
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 金额，单位分，创建订单时可不填，完结订单时必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    /// 计费说明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 付费数量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<i64>,
+}
+
+/// 后付费商户优惠
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PayScorePostDiscount {
+    /// 优惠名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 优惠说明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 优惠金额，单位分，完结订单时必填
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    /// 优惠数量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<i64>,
+}
+
+/// 总金额需等于付费项目金额之和减去优惠金额之和
+fn check_pay_score_amount(total_amount: i64, post_payments: &[PayScorePostPayment], post_discounts: &[PayScorePostDiscount]) -> LabradorResult<()> {
+    if post_payments.iter().any(|v| v.amount.is_none()) || post_discounts.iter().any(|v| v.amount.is_none()) {
+        return Err(LabraError::RequestError("付费项目与优惠项目需填写金额".to_string()));
+    }
+    let payments = post_payments.iter().filter_map(|v| v.amount).sum::<i64>();
+    let discounts = post_discounts.iter().filter_map(|v| v.amount).sum::<i64>();
+    if payments - discounts != total_amount {
+        return Err(LabraError::RequestError(format!("总金额{}须等于付费项目金额之和{}减去优惠金额之和{}", total_amount, payments, discounts)));
+    }
+    Ok(())
+}
+
+/// 创建支付分订单
+/// <pre>
+/// need_user_confirm为true时需用户确认订单，创建成功后使用返回的package拉起支付分小程序确认页；
+/// 为false时为免确认订单，需商户已获得用户授权
+/// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_14.shtml)
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayScoreOrderRequest {
+    /// 商户服务订单号
+    pub out_order_no: String,
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 服务ID
+    pub service_id: String,
+    /// 服务信息，用于介绍本订单所提供的服务
+    pub service_introduction: String,
+    /// 后付费项目
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_payments: Vec<PayScorePostPayment>,
+    /// 后付费商户优惠
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    /// 服务时间段
+    pub time_range: PayScoreTimeRange,
+    /// 服务位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<PayScoreLocation>,
+    /// 订单风险金
+    pub risk_fund: PayScoreRiskFund,
+    /// 商户数据包，在查询订单及通知中原样返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attach: Option<String>,
+    /// 商户回调地址
+    pub notify_url: String,
+    /// 用户在appid下的openid，免确认订单必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub openid: Option<String>,
+    /// 是否需要用户确认
+    pub need_user_confirm: bool,
+}
+
+impl WechatPayScoreOrderRequest {
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if !self.need_user_confirm && self.openid.is_none() {
+            return Err(LabraError::RequestError("免确认订单需填写openid".to_string()));
+        }
+        if self.time_range.end_time == Some(PayScoreTime::OnAccept) {
+            return Err(LabraError::RequestError("OnAccept只能用于服务开始时间".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 取消支付分订单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayScoreCancelRequest {
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 服务ID
+    pub service_id: String,
+    /// 取消原因，最长50个字符
+    pub reason: String,
+}
+
+/// 修改订单金额，只能在订单完结后、用户支付前调减金额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayScoreModifyRequest {
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 服务ID
+    pub service_id: String,
+    /// 后付费项目
+    pub post_payments: Vec<PayScorePostPayment>,
+    /// 后付费商户优惠
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    /// 总金额，单位分，不能超过完结订单时的总金额
+    pub total_amount: i64,
+    /// 修改原因，最长50个字符
+    pub reason: String,
+}
+
+impl WechatPayScoreModifyRequest {
+    pub fn check_params(&self) -> LabradorResult<()> {
+        check_pay_score_amount(self.total_amount, &self.post_payments, &self.post_discounts)
+    }
+}
+
+/// 完结支付分订单
+/// <pre>
+/// 服务结束后按实际服务扣费，total_amount需等于付费项目金额之和减去优惠金额之和，且不能超过创建订单时的风险金额
+/// 详见 [文档](https://pay.weixin.qq.com/wiki/doc/apiv3/apis/chapter6_1_18.shtml)
+/// </pre>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayScoreCompleteRequest {
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 服务ID
+    pub service_id: String,
+    /// 后付费项目
+    pub post_payments: Vec<PayScorePostPayment>,
+    /// 后付费商户优惠
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    /// 总金额，单位分
+    pub total_amount: i64,
+    /// 实际服务时间段，服务开始时间不能为OnAccept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_range: Option<PayScoreTimeRange>,
+    /// 实际服务位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<PayScoreLocation>,
+    /// 是否需要分账
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_sharing: Option<bool>,
+    /// 订单优惠标记
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<String>,
+}
+
+impl WechatPayScoreCompleteRequest {
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if let Some(time_range) = &self.time_range {
+            if time_range.start_time == PayScoreTime::OnAccept || time_range.end_time == Some(PayScoreTime::OnAccept) {
+                return Err(LabraError::RequestError("完结订单需填写实际服务时间，不能为OnAccept".to_string()));
+            }
+        }
+        check_pay_score_amount(self.total_amount, &self.post_payments, &self.post_discounts)
+    }
+}
+
+/// 同步服务订单信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayScoreSyncRequest {
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 服务ID
+    pub service_id: String,
+    /// 场景类型，目前只有Order_Paid（订单收款成功）
+    #[serde(rename = "type")]
+    pub sync_type: String,
+    /// 内容信息详情
+    pub detail: PayScoreSyncDetail,
+}
+
+impl WechatPayScoreSyncRequest {
+    /// 用户通过其它方式完成支付后，将订单同步为已收款
+    pub fn order_paid<S: Into<String>>(service_id: S, paid_time: NaiveDateTime) -> Self {
+        WechatPayScoreSyncRequest {
+            appid: None,
+            service_id: service_id.into(),
+            sync_type: "Order_Paid".to_string(),
+            detail: PayScoreSyncDetail { paid_time: PayScoreTime::DateTime(paid_time) },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayScoreSyncDetail {
+    /// 收款成功时间，yyyyMMddHHmmss
+    pub paid_time: PayScoreTime,
+}
+
+/// 商户预授权
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayScorePermissionsRequest {
+    /// 服务ID
+    pub service_id: String,
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 授权协议号，商户侧生成的唯一编号，用于查询或解除授权
+    pub authorization_code: String,
+    /// 商户接收用户授权状态变更通知的地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
+}
+
+/// 用户授权记录的查询方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayScorePermissionsKey {
+    /// 授权协议号
+    AuthorizationCode(String),
+    /// 用户在appid下的openid
+    Openid(String),
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+// 停车服务 ↓
+
+/// 车牌颜色
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PlateColor {
+    /// 蓝色
+    Blue,
+    /// 绿色，新能源车牌
+    Green,
+    /// 黄色
+    Yellow,
+    /// 黑色
+    Black,
+    /// 白色
+    White,
+    /// 黄绿色，大型新能源车牌
+    Limegreen,
+}
+
+/// 创建停车入场
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayParkingRequest {
+    /// 子商户号，服务商模式下必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_mchid: Option<String>,
+    /// 商户侧入场标识id，在同一个商户号下唯一
+    pub out_parking_no: String,
+    /// 车牌号，仅包括省份+车牌，不包括特殊字符
+    pub plate_number: String,
+    /// 车牌颜色
+    pub plate_color: PlateColor,
+    /// 接收入场状态变更回调通知的url
+    pub notify_url: String,
+    /// 入场时间，遵循rfc3339标准格式
+    #[serde(with = "rfc3339")]
+    pub start_time: DateTime<FixedOffset>,
+    /// 所在停车位车场的名称
+    pub parking_name: String,
+    /// 停车场的免费停车时长，单位为秒
+    pub free_duration: i64,
+}
+
+impl WechatPayParkingRequest {
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if self.out_parking_no.is_empty() || self.parking_name.is_empty() || self.notify_url.is_empty() {
+            return Err(LabraError::MissingField("入场标识id、停车场名称与通知地址不能为空".to_string()));
+        }
+        validate_plate_number(&self.plate_number)?;
+        Ok(())
+    }
+}
+
+/// 停车场景信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParkingInfo {
+    /// 微信支付分停车服务创建入场时返回的停车入场id
+    pub parking_id: String,
+    /// 车牌号，仅包括省份+车牌，不包括特殊字符
+    pub plate_number: String,
+    /// 车牌颜色
+    pub plate_color: PlateColor,
+    /// 入场时间，遵循rfc3339标准格式
+    #[serde(with = "rfc3339")]
+    pub start_time: DateTime<FixedOffset>,
+    /// 出场时间，遵循rfc3339标准格式
+    #[serde(with = "rfc3339")]
+    pub end_time: DateTime<FixedOffset>,
+    /// 所在停车位车场的名称
+    pub parking_name: String,
+    /// 计费的时间长，单位为秒
+    pub charging_duration: i64,
+    /// 停车场设备id
+    pub device_id: String,
+}
+
+/// 扣费受理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayParkingTransactionRequest {
+    /// 应用ID，为空时使用客户端的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    /// 子商户应用ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_appid: Option<String>,
+    /// 子商户号，服务商模式下必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_mchid: Option<String>,
+    /// 商品信息描述，用户微信账单的商品字段中显示
+    pub description: String,
+    /// 附加数据，在查询API和支付通知中原样返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attach: Option<String>,
+    /// 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一
+    pub out_trade_no: String,
+    /// 交易场景，目前只支持PARKING：车场停车场景
+    pub trade_scene: String,
+    /// 订单优惠标记
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<String>,
+    /// 接收扣费结果通知的url
+    pub notify_url: String,
+    /// 是否指定分账，枚举值：Y、N
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_sharing: Option<String>,
+    /// 订单金额
+    pub amount: Amount,
+    /// 停车场景信息
+    pub parking_info: ParkingInfo,
+}
+
+impl WechatPayParkingTransactionRequest {
+    pub fn check_params(&self) -> LabradorResult<()> {
+        if self.out_trade_no.is_empty() || self.description.is_empty() || self.notify_url.is_empty() || self.parking_info.parking_id.is_empty() {
+            return Err(LabraError::MissingField("商户订单号、商品描述、通知地址与停车入场id不能为空".to_string()));
+        }
+        if self.amount.total <= 0 {
+            return Err(LabraError::RequestError(format!("扣费金额{}必须大于0", self.amount.total)));
+        }
+        validate_plate_number(&self.parking_info.plate_number)?;
+        Ok(())
     }
 }
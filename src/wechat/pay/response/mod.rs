@@ -1,8 +1,10 @@
+use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value};
 
-use crate::{Amount, errors::LabraError, GoodsDetail, LabradorResult, Payer, RefundAmount, SceneInfo, TradeType};
-use crate::util::{get_nonce_str, get_timestamp, xmlutil};
+use crate::{Amount, CombineAmount, errors::LabraError, GoodsDetail, LabradorResult, Payer, PayScoreLocation, PayScorePostDiscount, PayScorePostPayment, PayScoreRiskFund, PayScoreTimeRange, ParkingInfo, PlateColor, RefundAmount, SceneInfo, TradeType};
+use crate::util::{current_timestamp, nonce_str, xmlutil};
+use crate::serde_helper::{option_rfc3339, rfc3339, string_or_number};
 use crate::wechat::cryptos::{EncryptV3, WechatCrypto, WechatCryptoV3};
 
 
@@ -64,8 +66,8 @@ pub struct WechatPayResponseV3 {
 
 impl WechatPayResponseV3 {
     pub fn get_pay_info(&self, trade_type: TradeType, appid: Option<String>, mchid: String, private_key: Option<String>) -> LabradorResult<Value> {
-        let timpstamp = get_timestamp() / 1000;
-        let nonce_str = get_nonce_str();
+        let timpstamp = current_timestamp();
+        let nonce_str = nonce_str();
         let private_key= private_key.unwrap_or_default();
         let appid = appid.unwrap_or_default();
         match trade_type {
@@ -323,7 +325,8 @@ pub struct WechatQueryOrderResponseV3 {
     /// 附加数据，原样返回
     pub attach: Option<String>,
     /// 支付完成时间，遵循rfc3339标准格式，格式为YYYY-MM-DDTHH:mm:ss+TIMEZONE，YYYY-MM-DD表示年月日，T出现在字符串中，表示time元素的开头，HH:mm:ss表示时分秒，TIMEZONE表示时区（+08:00表示东八区时间，领先UTC 8小时，即北京时间）。例如：2015-05-20T13:29:35+08:00表示，北京时间2015年5月20日 13点29分35秒。
-    pub success_time: String,
+    #[serde(default, with = "option_rfc3339")]
+    pub success_time: Option<DateTime<FixedOffset>>,
     /// 支付者
     pub payer: Payer,
     /// 订单金额信息，当支付成功时返回该字段。
@@ -523,9 +526,11 @@ pub struct WechatRefundResponseV3 {
     ///  4）退回支付用户零钱通:支付用户零钱通
     pub user_received_account: String,
     ///  退款成功时间
-    pub success_time: Option<String>,
+    #[serde(default, with = "option_rfc3339")]
+    pub success_time: Option<DateTime<FixedOffset>>,
     ///  退款创建时间
-    pub create_time: String,
+    #[serde(with = "rfc3339")]
+    pub create_time: DateTime<FixedOffset>,
     ///  退款状态
     ///  退款到银行发现用户的卡作废或者冻结了，导致原路退款银行卡失败，可前往商户平台（pay.weixin.qq.com）-交易中心，手动处理此笔退款。
     ///  枚举值：
@@ -841,9 +846,11 @@ pub struct WechatQueryRefundResponseV3 {
     ///  4）退回支付用户零钱通:支付用户零钱通
     pub user_received_account: String,
     /// 退款成功时间，当退款状态为退款成功时有返回。
-    pub success_time: Option<String>,
+    #[serde(default, with = "option_rfc3339")]
+    pub success_time: Option<DateTime<FixedOffset>>,
     /// 退款受理时间
-    pub create_time: String,
+    #[serde(with = "rfc3339")]
+    pub create_time: DateTime<FixedOffset>,
     /// 退款状态
     /// 描述：
     ///  退款到银行发现用户的卡作废或者冻结了，导致原路退款银行卡失败，可前往商户平台（pay.weixin.qq.com）-交易中心，手动处理此笔退款。
@@ -955,6 +962,57 @@ impl WechatPayNotifyResponseV3 {
 }
 
 
+/// 合单订单（查询结果与支付通知解密后的数据）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatCombineOrderResponse {
+    pub combine_appid: String,
+    pub combine_mchid: String,
+    pub combine_out_trade_no: String,
+    /// 场景信息
+    pub scene_info: Option<SceneInfo>,
+    /// 子单信息，各子单有各自的交易状态
+    pub sub_orders: Vec<CombineSubOrderResult>,
+    /// 支付者
+    pub combine_payer_info: Option<Payer>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CombineSubOrderResult {
+    pub mchid: String,
+    /// 交易类型：JSAPI、NATIVE、APP、MWEB
+    pub trade_type: Option<String>,
+    /// SUCCESS—支付成功,REFUND—转入退款,NOTPAY—未支付,CLOSED—已关闭,USERPAYING--用户支付中,PAYERROR--支付失败
+    pub trade_state: String,
+    /// 付款银行
+    pub bank_type: Option<String>,
+    pub attach: Option<String>,
+    /// 支付完成时间，遵循rfc3339标准格式
+    #[serde(default, with = "option_rfc3339")]
+    pub success_time: Option<DateTime<FixedOffset>>,
+    /// 微信支付订单号
+    pub transaction_id: Option<String>,
+    pub out_trade_no: String,
+    /// 二级商户号
+    pub sub_mchid: Option<String>,
+    pub amount: CombineAmount,
+    /// 优惠功能，享受优惠时返回该字段。
+    pub promotion_detail: Option<Vec<Value>>,
+}
+
+impl CombineSubOrderResult {
+    pub fn is_success(&self) -> bool {
+        self.trade_state == "SUCCESS"
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatCombineNotifyResponse {
+    /// 源数据
+    pub raw_data: Option<OriginNotifyResponse>,
+    /// 解密后的数据
+    pub result: Option<WechatCombineOrderResponse>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WechatRefundNotifyResponseV3 {
     /// 源数据
@@ -991,7 +1049,7 @@ impl WechatRefundNotifyResponseV3 {
 
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct WxScanPayNotifyResponse {
+pub struct WechatPayScanNotifyResponse {
     /// 用户标识
     pub openid: String,
     /// <pre>
@@ -1006,7 +1064,10 @@ pub struct WxScanPayNotifyResponse {
     pub product_id: String,
 }
 
-impl WxScanPayNotifyResponse {
+#[deprecated(note = "请使用`WechatPayScanNotifyResponse`")]
+pub type WxScanPayNotifyResponse = WechatPayScanNotifyResponse;
+
+impl WechatPayScanNotifyResponse {
     pub fn parse_xml(xml: String) -> LabradorResult<Self> {
         let package = xmlutil::parse(xml.to_owned());
         let doc = package.as_document();
@@ -1021,7 +1082,7 @@ impl WxScanPayNotifyResponse {
             let _sign = xmlutil::evaluate(&doc, "//xml/sign/text()").string();
             let _err_code = xmlutil::evaluate(&doc, "//xml/err_code/text()").string();
             if result_code.eq("SUCCESS") {
-                Ok(WxScanPayNotifyResponse {
+                Ok(WechatPayScanNotifyResponse {
                     openid,
                     is_subscribe,
                     product_id
@@ -1353,7 +1414,7 @@ impl WechatRefundNotifyResponse {
 
 /// 转换短链接结果对象类
 #[derive(Debug, Serialize, Deserialize,Clone)]
-pub struct WxPayShortUrlResponse {
+pub struct WechatPayShortUrlResponse {
     /// <pre>
     /// URL链接
     /// short_url
@@ -1365,16 +1426,19 @@ pub struct WxPayShortUrlResponse {
     pub short_url: String,
 }
 
+#[deprecated(note = "请使用`WechatPayShortUrlResponse`")]
+pub type WxPayShortUrlResponse = WechatPayShortUrlResponse;
+
 #[allow(unused)]
-impl WxPayShortUrlResponse {
-    pub fn parse_xml(xml: String) -> LabradorResult<WxPayShortUrlResponse> {
+impl WechatPayShortUrlResponse {
+    pub fn parse_xml(xml: String) -> LabradorResult<WechatPayShortUrlResponse> {
         let package = xmlutil::parse(xml.to_owned());
         let doc = package.as_document();
         let return_code = xmlutil::evaluate(&doc, "//xml/return_code/text()").string();
         let return_msg = xmlutil::evaluate(&doc, "//xml/return_msg/text()").string();
         if return_code.eq(&"SUCCESS") {
             let short_url = xmlutil::evaluate(&doc, "//xml/short_url/text()").string();
-            Ok(WxPayShortUrlResponse {
+            Ok(WechatPayShortUrlResponse {
                 short_url,
             })
         } else {
@@ -1382,4 +1446,425 @@ impl WxPayShortUrlResponse {
         }
 
     }
-}
\ No newline at end of file
+}
+
+/// 沙箱密钥
+#[derive(Debug, Serialize, Deserialize,Clone)]
+pub struct WechatPaySandboxSignKeyResponse {
+    /// 商户号
+    pub mch_id: String,
+    /// 沙箱密钥，沙箱环境中的V2接口均使用该密钥签名
+    pub sandbox_signkey: String,
+}
+
+#[allow(unused)]
+impl WechatPaySandboxSignKeyResponse {
+    pub fn parse_xml(xml: String) -> LabradorResult<WechatPaySandboxSignKeyResponse> {
+        let package = xmlutil::parse(xml.to_owned());
+        let doc = package.as_document();
+        let return_code = xmlutil::evaluate(&doc, "//xml/return_code/text()").string();
+        let return_msg = xmlutil::evaluate(&doc, "//xml/return_msg/text()").string();
+        let sandbox_signkey = xmlutil::evaluate(&doc, "//xml/sandbox_signkey/text()").string();
+        if return_code.eq(&"SUCCESS") && !sandbox_signkey.is_empty() {
+            let mch_id = xmlutil::evaluate(&doc, "//xml/mch_id/text()").string();
+            Ok(WechatPaySandboxSignKeyResponse {
+                mch_id,
+                sandbox_signkey,
+            })
+        } else {
+            Err(LabraError::ClientError{ errcode: "-1".to_string(), errmsg: return_msg})
+        }
+    }
+}
+
+/// 发放现金红包返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPaySendRedpackResponse {
+    /// 商户订单号
+    pub mch_billno: String,
+    /// 商户号
+    pub mch_id: String,
+    /// 公众账号appid
+    pub wxappid: String,
+    /// 用户openid
+    pub re_openid: String,
+    /// 付款金额，单位分
+    #[serde(with = "string_or_number")]
+    pub total_amount: i64,
+    /// 微信单号
+    pub send_listid: Option<String>,
+}
+
+/// 企业付款到零钱返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatPayTransfersResponse {
+    /// 商户账号appid
+    pub mch_appid: Option<String>,
+    /// 商户号
+    pub mchid: Option<String>,
+    /// 设备号
+    pub device_info: Option<String>,
+    /// 商户订单号
+    pub partner_trade_no: String,
+    /// 微信付款单号
+    pub payment_no: String,
+    /// 付款成功时间
+    pub payment_time: Option<String>,
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+// 电商收付通 ↓
+
+/// 二级商户进件返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatEcommerceApplymentResponse {
+    /// 微信支付申请单号
+    pub applyment_id: u64,
+    /// 业务申请编号
+    pub out_request_no: String,
+}
+
+/// 二级商户进件申请状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EcommerceApplymentState {
+    /// 资料校验中
+    Checking,
+    /// 待账户验证
+    AccountNeedVerify,
+    /// 审核中
+    Auditing,
+    /// 已驳回
+    Rejected,
+    /// 待签约
+    NeedSign,
+    /// 完成
+    Finish,
+    /// 已冻结
+    Frozen,
+    /// 未知状态
+    #[serde(other)]
+    Unknown,
+}
+
+/// 二级商户进件申请状态查询返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WechatEcommerceApplymentQueryResponse {
+    /// 申请状态
+    pub applyment_state: EcommerceApplymentState,
+    /// 申请状态描述
+    pub applyment_state_desc: Option<String>,
+    /// 签约状态，UNSIGNED：未签约，SIGNED：已签约，NOT_SIGNABLE：不可签约
+    pub sign_state: Option<String>,
+    /// 签约链接，状态为NEED_SIGN时返回
+    pub sign_url: Option<String>,
+    /// 电商平台二级商户号，状态为FINISH时返回
+    pub sub_mchid: Option<String>,
+    /// 汇款账户验证信息，状态为ACCOUNT_NEED_VERIFY时返回
+    pub account_validation: Option<EcommerceAccountValidation>,
+    /// 驳回原因详情，状态为REJECTED时返回
+    pub audit_detail: Option<Vec<EcommerceAuditDetail>>,
+    /// 法人验证链接
+    pub legal_validation_url: Option<String>,
+    /// 业务申请编号
+    pub out_request_no: String,
+    /// 微信支付申请单号
+    pub applyment_id: u64,
+}
+
+/// 汇款账户验证信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceAccountValidation {
+    /// 付款户名，使用商户证书公钥加密，可通过`WechatCryptoV3::decrypt_sensitive`解密
+    pub account_name: Option<String>,
+    /// 付款卡号，使用商户证书公钥加密
+    pub account_no: Option<String>,
+    /// 汇款金额，单位分
+    pub pay_amount: Option<i64>,
+    /// 收款卡号
+    pub destination_account_number: Option<String>,
+    /// 收款户名
+    pub destination_account_name: Option<String>,
+    /// 开户银行
+    pub destination_account_bank: Option<String>,
+    /// 省市信息
+    pub city: Option<String>,
+    /// 备注信息
+    pub remark: Option<String>,
+    /// 汇款截止时间
+    pub deadline: Option<String>,
+}
+
+/// 驳回原因详情
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcommerceAuditDetail {
+    /// 参数名称
+    pub param_name: Option<String>,
+    /// 驳回原因
+    pub reject_reason: Option<String>,
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+// 微信支付分 ↓
+
+/// 支付分订单（创建、查询、完结等接口的返回以及用户确认、支付成功通知解密后的数据）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WechatPayScoreOrderResponse {
+    pub appid: Option<String>,
+    pub mchid: Option<String>,
+    /// 服务ID
+    pub service_id: Option<String>,
+    /// 商户服务订单号
+    pub out_order_no: String,
+    /// 服务信息
+    pub service_introduction: Option<String>,
+    /// 服务订单状态：CREATED（商户已创建服务订单）、DOING（服务订单进行中）、DONE（服务订单完成）、REVOKED（商户取消服务订单）、EXPIRED（服务订单已失效）
+    pub state: Option<String>,
+    /// 订单状态说明：USER_CONFIRM（用户确认）、MCH_COMPLETE（商户完结）
+    pub state_description: Option<String>,
+    /// 总金额，单位分
+    pub total_amount: Option<i64>,
+    #[serde(default)]
+    pub post_payments: Vec<PayScorePostPayment>,
+    #[serde(default)]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    pub risk_fund: Option<PayScoreRiskFund>,
+    pub time_range: Option<PayScoreTimeRange>,
+    pub location: Option<PayScoreLocation>,
+    pub attach: Option<String>,
+    pub notify_url: Option<String>,
+    /// 微信支付服务订单号
+    pub order_id: Option<String>,
+    /// 用于跳转到微信侧小程序确认订单，仅需用户确认的订单创建时返回
+    pub package: Option<String>,
+    /// 是否需要收款
+    pub need_collection: Option<bool>,
+    /// 收款信息
+    pub collection: Option<PayScoreCollection>,
+    pub openid: Option<String>,
+}
+
+impl WechatPayScoreOrderResponse {
+    /// 用户已支付完成
+    pub fn is_paid(&self) -> bool {
+        self.collection.as_ref().map(|v| v.state == "USER_PAID").unwrap_or_default()
+    }
+}
+
+/// 收款信息
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PayScoreCollection {
+    /// 收款状态：USER_PAYING（待支付）、USER_PAID（已支付）
+    pub state: String,
+    /// 总收款金额
+    pub total_amount: Option<i64>,
+    /// 待收金额
+    pub paying_amount: Option<i64>,
+    /// 已收金额
+    pub paid_amount: Option<i64>,
+    /// 收款明细
+    #[serde(default)]
+    pub details: Vec<PayScoreCollectionDetail>,
+}
+
+/// 收款明细
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PayScoreCollectionDetail {
+    /// 收款序号
+    pub seq: Option<i64>,
+    /// 单笔收款金额
+    pub amount: Option<i64>,
+    /// 收款成功渠道：NEWTON（微信支付分）、MCH（商户渠道）
+    pub paid_type: Option<String>,
+    /// 收款成功时间
+    pub paid_time: Option<String>,
+    /// 微信支付交易单号
+    pub transaction_id: Option<String>,
+}
+
+/// <pre>
+/// 拉起支付分确认订单页的参数
+/// 小程序中作为wx.openBusinessView的extraData，businessType为wxpayScoreUse
+/// </pre>
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayScoreConfirmExtraData {
+    pub mch_id: String,
+    pub package: String,
+    pub timestamp: String,
+    pub nonce_str: String,
+    pub sign_type: String,
+    pub sign: String,
+}
+
+/// 商户预授权的返回
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatPayScorePermissionsResponse {
+    /// 预授权token，用于跳转支付分小程序授权页
+    pub apply_permissions_token: String,
+}
+
+/// 用户授权记录
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WechatPayScoreAuthorizationResponse {
+    pub appid: Option<String>,
+    pub mchid: Option<String>,
+    pub service_id: Option<String>,
+    pub openid: Option<String>,
+    /// 授权协议号
+    pub authorization_code: Option<String>,
+    /// 授权状态：UNAVAILABLE（用户未授权）、AVAILABLE（用户已授权）、UNBINDUSER（用户已解除授权）
+    pub authorization_state: Option<String>,
+    pub notify_url: Option<String>,
+    /// 最近一次解除授权时间
+    pub cancel_authorization_time: Option<String>,
+    /// 最近一次授权成功时间
+    pub authorization_success_time: Option<String>,
+}
+
+/// 支付分通知的数据
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum PayScoreNotifyResult {
+    /// PAYSCORE.USER_CONFIRM：用户确认订单
+    UserConfirm(WechatPayScoreOrderResponse),
+    /// PAYSCORE.USER_PAID：用户支付成功
+    UserPaid(WechatPayScoreOrderResponse),
+    /// 其他通知（如授权、解除授权），保留解密后的原始数据
+    Other(Value),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatPayScoreNotifyResponse {
+    /// 源数据
+    pub raw_data: Option<OriginNotifyResponse>,
+    /// 解密后的数据
+    pub result: Option<PayScoreNotifyResult>,
+}
+
+//----------------------------------------------------------------------------------------------------------------------------
+
+// 停车服务 ↓
+
+/// 车牌服务开通信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatPayParkingServiceResponse {
+    /// 车牌号
+    pub plate_number: String,
+    /// 车牌颜色
+    pub plate_color: PlateColor,
+    /// 车牌服务开通时间
+    #[serde(default, with = "option_rfc3339")]
+    pub service_open_time: Option<DateTime<FixedOffset>>,
+    /// 用户在商户appid下的唯一标识
+    pub openid: Option<String>,
+    /// 车牌服务开通状态
+    /// NORMAL：正常服务
+    /// PAUSE：暂停服务
+    /// OUT_SERVICE：未开通
+    pub service_state: String,
+}
+
+impl WechatPayParkingServiceResponse {
+    /// 用户是否已开通车牌服务，且可以正常扣费
+    pub fn is_normal(&self) -> bool {
+        self.service_state == "NORMAL"
+    }
+}
+
+/// 停车入场信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatPayParkingResponse {
+    /// 停车入场id，扣费时使用
+    pub id: String,
+    /// 商户侧入场标识id
+    pub out_parking_no: String,
+    /// 车牌号
+    pub plate_number: String,
+    /// 车牌颜色
+    pub plate_color: PlateColor,
+    /// 入场时间
+    #[serde(with = "rfc3339")]
+    pub start_time: DateTime<FixedOffset>,
+    /// 所在停车位车场的名称
+    pub parking_name: String,
+    /// 停车场的免费停车时长，单位为秒
+    pub free_duration: i64,
+    /// 本次入场车牌的服务状态
+    /// NORMAL：正常状态，可以使用微信支付分停车服务
+    /// BLOCKED：不可用状态，暂时不可以使用微信支付分停车服务
+    pub state: String,
+    /// 不可用服务状态描述，BLOCKED时返回
+    /// PAUSE：已暂停微信支付分停车服务
+    /// OVERDUE：已开通微信支付分停车服务，但存在支付分订单逾期
+    /// REMOVE：用户移除车牌
+    pub block_reason: Option<String>,
+}
+
+impl WechatPayParkingResponse {
+    /// 是否可以使用微信支付分停车服务
+    pub fn is_normal(&self) -> bool {
+        self.state == "NORMAL"
+    }
+}
+
+/// 停车扣费订单，扣费受理的返回与扣费结果通知的数据
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatPayParkingTransaction {
+    pub appid: Option<String>,
+    pub sub_appid: Option<String>,
+    pub sp_mchid: Option<String>,
+    pub sub_mchid: Option<String>,
+    pub description: Option<String>,
+    /// 订单创建时间
+    #[serde(default, with = "option_rfc3339")]
+    pub create_time: Option<DateTime<FixedOffset>>,
+    /// 商户订单号
+    pub out_trade_no: String,
+    /// 微信支付订单号，扣费成功后返回
+    pub transaction_id: Option<String>,
+    /// 交易状态
+    /// SUCCESS：支付成功
+    /// ACCEPTED：已接收，等待扣款
+    /// PAY_FAIL：支付失败（其他原因，如银行返回失败）
+    /// REFUND：转入退款
+    pub trade_state: String,
+    /// 交易状态描述
+    pub trade_state_description: Option<String>,
+    /// 支付完成时间
+    #[serde(default, with = "option_rfc3339")]
+    pub success_time: Option<DateTime<FixedOffset>>,
+    pub bank_type: Option<String>,
+    pub user_repaid: Option<String>,
+    pub attach: Option<String>,
+    pub trade_scene: Option<String>,
+    pub parking_info: Option<ParkingInfo>,
+    pub payer: Option<Payer>,
+    pub amount: Option<Amount>,
+}
+
+impl WechatPayParkingTransaction {
+    /// 是否已扣费成功
+    pub fn is_success(&self) -> bool {
+        self.trade_state == "SUCCESS"
+    }
+}
+
+/// 停车服务通知的数据
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum ParkingNotifyResult {
+    /// TRANSACTION.SUCCESS：扣费成功
+    TransactionSuccess(WechatPayParkingTransaction),
+    /// TRANSACTION.FAIL：扣费失败
+    TransactionFail(WechatPayParkingTransaction),
+    /// 其他通知（如入场状态变更），保留解密后的原始数据
+    Other(Value),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WechatPayParkingNotifyResponse {
+    /// 源数据
+    pub raw_data: Option<OriginNotifyResponse>,
+    /// 解密后的数据
+    pub result: Option<ParkingNotifyResult>,
+}
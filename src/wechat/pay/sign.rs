@@ -0,0 +1,219 @@
+//!
+//! 微信支付V2接口的签名与XML报文
+//!
+//! 签名规则：参数按参数名ASCII码从小到大排序，值为空的参数与`sign`不参与签名，
+//! 拼接为`key1=value1&key2=value2`后在末尾拼接`&key=商户API密钥`，再进行MD5或HMAC-SHA256运算并转为大写。
+//! 详见 [文档](https://pay.weixin.qq.com/wiki/doc/api/tools/cash_coupon.php?chapter=4_3)
+//!
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+use sxd_document::dom::{ChildOfElement, Element};
+use sxd_document::parser;
+
+use crate::{LabradorResult, LabraError};
+use crate::util::md5;
+use crate::util::prp::PrpCrypto;
+
+/// 签名参数名
+const SIGN: &str = "sign";
+/// 签名类型参数名
+const SIGN_TYPE: &str = "sign_type";
+
+/// V2接口签名类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WechatPaySignType {
+    /// `MD5`，未指定sign_type时的默认值
+    #[default]
+    Md5,
+    /// `HMAC-SHA256`
+    HmacSha256,
+}
+
+impl WechatPaySignType {
+    /// sign_type参数的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WechatPaySignType::Md5 => "MD5",
+            WechatPaySignType::HmacSha256 => "HMAC-SHA256",
+        }
+    }
+
+    /// 按sign_type参数的取值解析，未知的取值返回`None`
+    pub fn parse(sign_type: &str) -> Option<Self> {
+        match sign_type.to_uppercase().as_str() {
+            "MD5" => Some(WechatPaySignType::Md5),
+            "HMAC-SHA256" => Some(WechatPaySignType::HmacSha256),
+            _ => None,
+        }
+    }
+
+    /// 计算签名
+    pub fn sign(&self, params: &BTreeMap<String, String>, key: &str) -> LabradorResult<String> {
+        let content = Self::sign_content(params, key);
+        let sign = match self {
+            WechatPaySignType::Md5 => md5::md5(content)?,
+            WechatPaySignType::HmacSha256 => PrpCrypto::hmac_sha256_sign(key, &content)?,
+        };
+        Ok(sign.to_uppercase())
+    }
+
+    /// 待签名字符串：`key1=value1&key2=value2&key=商户API密钥`
+    pub fn sign_content(params: &BTreeMap<String, String>, key: &str) -> String {
+        let mut content = params.iter()
+            .filter(|(k, v)| k.as_str() != SIGN && !v.is_empty())
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<String>>()
+            .join("&");
+        if !content.is_empty() {
+            content.push('&');
+        }
+        content.push_str("key=");
+        content.push_str(key);
+        content
+    }
+
+    /// <pre>
+    /// 校验参数中的sign
+    /// 签名类型取参数中的sign_type，未指定时为MD5；sign缺失或sign_type未知时校验失败
+    /// </pre>
+    pub fn verify(params: &BTreeMap<String, String>, key: &str) -> bool {
+        let sign_type = match params.get(SIGN_TYPE) {
+            Some(sign_type) => WechatPaySignType::parse(sign_type),
+            None => Some(WechatPaySignType::Md5),
+        };
+        match (sign_type, params.get(SIGN)) {
+            (Some(sign_type), Some(sign)) if !sign.is_empty() => {
+                sign_type.sign(params, key).map(|expected| expected.eq_ignore_ascii_case(sign)).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// <pre>
+/// 生成请求XML
+/// 参数按参数名排序，sign放在最后；纯数字的值直接输出，其余的值使用CDATA包裹
+/// </pre>
+pub(crate) fn params_to_xml(params: &BTreeMap<String, String>) -> String {
+    let mut xml = String::from("<xml>");
+    for (k, v) in params.iter().filter(|(k, _)| k.as_str() != SIGN) {
+        push_element(&mut xml, k, v);
+    }
+    if let Some(sign) = params.get(SIGN) {
+        push_element(&mut xml, SIGN, sign);
+    }
+    xml.push_str("</xml>");
+    xml
+}
+
+fn push_element(xml: &mut String, name: &str, value: &str) {
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        xml.push_str(&format!("<{0}>{1}</{0}>", name, value));
+    } else {
+        // CDATA中不能出现`]]>`，拆分为两段
+        xml.push_str(&format!("<{0}><![CDATA[{1}]]></{0}>", name, value.replace("]]>", "]]]]><![CDATA[>")));
+    }
+}
+
+/// <pre>
+/// 解析应答XML为参数表
+/// 取根节点下各子节点的文本，CDATA与普通文本均可，子节点下的嵌套节点按文本拼接
+/// </pre>
+pub(crate) fn xml_to_params(xml: &str) -> LabradorResult<BTreeMap<String, String>> {
+    let package = parser::parse(xml).map_err(|(pos, _)| LabraError::DecodeError(format!("XML格式有误，位置：{}", pos).into()))?;
+    let doc = package.as_document();
+    let root = doc.root().children().into_iter().find_map(|child| child.element())
+        .ok_or_else(|| LabraError::DecodeError("XML缺少根节点".into()))?;
+    let mut params = BTreeMap::new();
+    for child in root.children() {
+        if let ChildOfElement::Element(element) = child {
+            params.insert(element.name().local_part().to_string(), element_text(element));
+        }
+    }
+    Ok(params)
+}
+
+fn element_text(element: Element) -> String {
+    element.children().into_iter().map(|child| match child {
+        ChildOfElement::Text(text) => text.text().to_string(),
+        ChildOfElement::Element(element) => element_text(element),
+        _ => String::default(),
+    }).collect::<Vec<String>>().concat()
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::testkit::wechat_pay::{v2_params as doc_params, V2_HMAC_SHA256_SIGN, V2_KEY as KEY, V2_MD5_SIGN, V2_SIGN_CONTENT};
+    use super::{params_to_xml, xml_to_params, WechatPaySignType};
+
+    #[test]
+    fn test_sign_doc_example() {
+        let mut params = doc_params();
+        assert_eq!(WechatPaySignType::sign_content(&params, KEY), V2_SIGN_CONTENT);
+        assert_eq!(WechatPaySignType::Md5.sign(&params, KEY).unwrap(), V2_MD5_SIGN);
+        assert_eq!(WechatPaySignType::HmacSha256.sign(&params, KEY).unwrap(), V2_HMAC_SHA256_SIGN);
+        // 空值与sign不参与签名
+        params.insert("attach".to_string(), String::default());
+        params.insert("sign".to_string(), V2_MD5_SIGN.to_string());
+        assert_eq!(WechatPaySignType::Md5.sign(&params, KEY).unwrap(), V2_MD5_SIGN);
+    }
+
+    #[test]
+    fn test_verify_sign() {
+        let mut params = doc_params();
+        assert!(!WechatPaySignType::verify(&params, KEY));
+        params.insert("sign".to_string(), V2_MD5_SIGN.to_lowercase());
+        assert!(WechatPaySignType::verify(&params, KEY));
+        assert!(!WechatPaySignType::verify(&params, "wrong_key"));
+        params.insert("body".to_string(), "test2".to_string());
+        assert!(!WechatPaySignType::verify(&params, KEY));
+
+        let mut params = doc_params();
+        params.insert("sign_type".to_string(), "HMAC-SHA256".to_string());
+        let sign = WechatPaySignType::HmacSha256.sign(&params, KEY).unwrap();
+        params.insert("sign".to_string(), sign);
+        assert!(WechatPaySignType::verify(&params, KEY));
+        params.insert("sign_type".to_string(), "RSA".to_string());
+        assert!(!WechatPaySignType::verify(&params, KEY));
+        assert_eq!(WechatPaySignType::parse("hmac-sha256"), Some(WechatPaySignType::HmacSha256));
+    }
+
+    #[test]
+    fn test_params_to_xml() {
+        let mut params = doc_params();
+        params.insert("sign".to_string(), "9A0A8659F005D6984697E2CA0A9CF3B7".to_string());
+        params.insert("wishing".to_string(), "<祝福>]]>".to_string());
+        assert_eq!(params_to_xml(&params), "<xml>\
+            <appid><![CDATA[wxd930ea5d5a258f4f]]></appid>\
+            <body><![CDATA[test]]></body>\
+            <device_info>1000</device_info>\
+            <mch_id>10000100</mch_id>\
+            <nonce_str><![CDATA[ibuaiVcKdpRxkhJA]]></nonce_str>\
+            <wishing><![CDATA[<祝福>]]]]><![CDATA[>]]></wishing>\
+            <sign><![CDATA[9A0A8659F005D6984697E2CA0A9CF3B7]]></sign>\
+            </xml>");
+        // 解析后与原参数一致
+        assert_eq!(xml_to_params(&params_to_xml(&params)).unwrap(), params);
+    }
+
+    #[test]
+    fn test_xml_to_params() {
+        let xml = "<xml>\n\
+            <return_code><![CDATA[SUCCESS]]></return_code>\n\
+            <return_msg>OK</return_msg>\n\
+            <total_amount>100</total_amount>\n\
+            <err_code_des><![CDATA[]]></err_code_des>\n\
+            </xml>";
+        let params = xml_to_params(xml).unwrap();
+        assert_eq!(params.len(), 4);
+        assert_eq!(params["return_code"], "SUCCESS");
+        assert_eq!(params["return_msg"], "OK");
+        assert_eq!(params["total_amount"], "100");
+        assert_eq!(params["err_code_des"], "");
+        assert!(xml_to_params("<xml><return_code>").is_err());
+    }
+}
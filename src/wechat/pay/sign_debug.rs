@@ -0,0 +1,77 @@
+use crate::{LabradorResult, SignDebugReport, SignProblem};
+use crate::util::check_value;
+use crate::wechat::cryptos::WechatCryptoV3;
+
+/// <pre>
+/// 排查微信支付V3请求的签名
+/// 签名串与签名均由正式请求使用的逻辑生成，可与Authorization中的timestamp、nonce_str对照。
+/// url为不含域名的绝对路径，带查询参数时需包含查询参数；body为实际发送的请求报文主体，GET请求为空。
+/// </pre>
+pub fn wechat_pay_v3_sign_debug<B: AsRef<[u8]>>(method: &str, url: &str, timestamp: i64, nonce_str: &str, body: B, private_key: &str) -> LabradorResult<SignDebugReport> {
+    let mut problems = Vec::new();
+    if method != method.to_uppercase() {
+        problems.push(SignProblem::LowercaseMethod(method.to_string()));
+    }
+    if !url.starts_with('/') {
+        problems.push(SignProblem::InvalidUrl(url.to_string()));
+    }
+    check_value("nonce_str", nonce_str.as_bytes(), &mut problems);
+    let body = body.as_ref();
+    let body = if body.is_empty() {
+        String::default()
+    } else {
+        if method.eq_ignore_ascii_case("GET") {
+            problems.push(SignProblem::GetWithBody);
+        }
+        check_value("body", body, &mut problems)
+    };
+    let sign_content = WechatCryptoV3::signature_message_v3(method, url, timestamp, nonce_str, &body);
+    let signature = WechatCryptoV3::signature_v3(&method.to_string(), &url.to_string(), timestamp, &nonce_str.to_string(), &body, &private_key.to_string())?;
+    Ok(SignDebugReport { sign_content, signature, problems })
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use crate::SignProblem;
+    use crate::testkit::{wechat_pay::{self, V3_NONCE as NONCE}, RSA_PRIVATE_KEY, RSA_PUBLIC_KEY};
+    use crate::util::prp::PrpCrypto;
+    use crate::wechat::pay::tests::generate_cert;
+    use super::wechat_pay_v3_sign_debug;
+
+    #[test]
+    fn test_sign_debug() {
+        let (private_key, cert) = generate_cert();
+        let body = r#"{"description":"Image形象店-深圳腾大-QQ公仔"}"#;
+        let report = wechat_pay_v3_sign_debug("POST", "/v3/pay/transactions/native", 1554208460, NONCE, body, &private_key).unwrap();
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!(report.sign_content, format!("POST\n/v3/pay/transactions/native\n1554208460\n{}\n{}\n", NONCE, body));
+        let public_key = String::from_utf8(cert.public_key).unwrap();
+        assert!(PrpCrypto::rsa_sha256_verify(&public_key, &report.sign_content, &report.signature).unwrap());
+
+        let report = wechat_pay_v3_sign_debug(wechat_pay::V3_METHOD, wechat_pay::V3_URL, wechat_pay::V3_TIMESTAMP, NONCE, wechat_pay::V3_BODY, RSA_PRIVATE_KEY).unwrap();
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!(report.sign_content, wechat_pay::V3_SIGN_CONTENT);
+        assert_eq!(report.signature, wechat_pay::V3_SIGNATURE);
+        wechat_pay::assert_v3_key_pair(RSA_PRIVATE_KEY, RSA_PUBLIC_KEY);
+        wechat_pay::assert_v3_key_pair(&private_key, &public_key);
+    }
+
+    #[test]
+    fn test_sign_debug_problems() {
+        let (private_key, _) = generate_cert();
+        let report = wechat_pay_v3_sign_debug("post", "https://api.mch.weixin.qq.com/v3/pay/transactions/native", 1554208460, NONCE, "{\"description\":\"test\"}\n", &private_key).unwrap();
+        assert_eq!(report.problems, vec![
+            SignProblem::LowercaseMethod("post".to_string()),
+            SignProblem::InvalidUrl("https://api.mch.weixin.qq.com/v3/pay/transactions/native".to_string()),
+            SignProblem::Whitespace("body".to_string()),
+        ]);
+        let report = wechat_pay_v3_sign_debug("GET", "/v3/certificates", 1554208460, " ", b"\xff{}".to_vec(), &private_key).unwrap();
+        assert_eq!(report.problems, vec![
+            SignProblem::Whitespace("nonce_str".to_string()),
+            SignProblem::GetWithBody,
+            SignProblem::NonUtf8("body".to_string()),
+        ]);
+        assert!(report.to_string().contains("GET请求的签名主体应为空"));
+    }
+}
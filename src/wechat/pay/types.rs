@@ -0,0 +1,269 @@
+//!
+//! 商户订单号、商户号、appid的校验
+//!
+//! 格式不合法的参数会被微信支付拒绝，且返回的错误信息往往不够明确，因此在发送请求前按以下规则校验：
+//! - 商户订单号、退款单号、批次单号：只能包含数字、大小写字母及`_-|*`，长度为6～32
+//! - 商户号：纯数字
+//! - appid：`wx`加16位十六进制字符
+//!
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{LabraError, LabradorResult, OUT_TRADE_NO_MAX_LEN as TRADE_NO_MAX_LEN};
+
+/// 商户订单号最短长度
+const TRADE_NO_MIN_LEN: usize = 6;
+/// appid中`wx`之后的十六进制字符数
+const APPID_HEX_LEN: usize = 16;
+
+/// 商户订单号允许的字符：数字、大小写字母及`_-|*`
+fn is_trade_no_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '|' | '*')
+}
+
+/// <pre>
+/// 商户订单号，用于out_trade_no、out_refund_no、out_batch_no等字段
+/// 只能包含数字、大小写字母及_-|*，长度为6～32，构造时校验，序列化为普通字符串
+/// </pre>
+///
+/// # Examples
+///
+/// ```
+/// use labrador::TradeNo;
+///
+/// let no = TradeNo::try_new("20150806125346").unwrap();
+/// assert_eq!(no.as_str(), "20150806125346");
+/// assert!(TradeNo::try_new("订单 001").is_err());
+/// assert_eq!(TradeNo::sanitize("订单 2015-08-06 001").unwrap().as_str(), "_2015-08-06_001");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TradeNo(String);
+
+impl TradeNo {
+    /// 严格校验，不符合规则时返回错误
+    pub fn try_new<S: Into<String>>(trade_no: S) -> LabradorResult<Self> {
+        let trade_no = trade_no.into();
+        Self::validate(&trade_no)?;
+        Ok(TradeNo(trade_no))
+    }
+
+    /// <pre>
+    /// 清理不合法的字符后构造
+    /// 空白字符替换为`_`，其余不合法的字符（如中文）直接去掉，超过32位时截断；清理后不足6位时返回错误
+    /// </pre>
+    pub fn sanitize(trade_no: &str) -> LabradorResult<Self> {
+        let sanitized = trade_no.chars()
+            .filter_map(|c| if c.is_whitespace() { Some('_') } else if is_trade_no_char(c) { Some(c) } else { None })
+            .take(TRADE_NO_MAX_LEN)
+            .collect::<String>();
+        Self::try_new(sanitized)
+    }
+
+    /// 校验商户订单号的字符与长度
+    pub fn validate(trade_no: &str) -> LabradorResult<()> {
+        if let Some(c) = trade_no.chars().find(|c| !is_trade_no_char(*c)) {
+            return Err(LabraError::RequestError(format!("商户订单号只能包含数字、大小写字母及_-|*，{}中包含不合法的字符：{}", trade_no, c)));
+        }
+        if trade_no.len() < TRADE_NO_MIN_LEN || trade_no.len() > TRADE_NO_MAX_LEN {
+            return Err(LabraError::RequestError(format!("商户订单号长度应为{}～{}，{}的长度为{}", TRADE_NO_MIN_LEN, TRADE_NO_MAX_LEN, trade_no, trade_no.len())));
+        }
+        Ok(())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+/// 商户号，纯数字
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MchId(String);
+
+impl MchId {
+    pub fn try_new<S: Into<String>>(mchid: S) -> LabradorResult<Self> {
+        let mchid = mchid.into();
+        Self::validate(&mchid)?;
+        Ok(MchId(mchid))
+    }
+
+    /// 校验商户号是否为纯数字
+    pub fn validate(mchid: &str) -> LabradorResult<()> {
+        if mchid.is_empty() || !mchid.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(LabraError::RequestError(format!("商户号只能包含数字，当前商户号：{}", mchid)));
+        }
+        Ok(())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+/// appid，`wx`加16位十六进制字符
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AppId(String);
+
+impl AppId {
+    pub fn try_new<S: Into<String>>(appid: S) -> LabradorResult<Self> {
+        let appid = appid.into();
+        Self::validate(&appid)?;
+        Ok(AppId(appid))
+    }
+
+    /// 校验appid的格式
+    pub fn validate(appid: &str) -> LabradorResult<()> {
+        let valid = appid.strip_prefix("wx")
+            .map(|hex| hex.len() == APPID_HEX_LEN && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .unwrap_or(false);
+        if !valid {
+            return Err(LabraError::RequestError(format!("appid应为wx加{}位十六进制字符，当前appid：{}", APPID_HEX_LEN, appid)));
+        }
+        Ok(())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+macro_rules! impl_validated_string {
+    ($($ty:ident),*) => {
+        $(
+            impl TryFrom<String> for $ty {
+                type Error = LabraError;
+
+                fn try_from(value: String) -> Result<Self, Self::Error> {
+                    $ty::try_new(value)
+                }
+            }
+
+            impl TryFrom<&str> for $ty {
+                type Error = LabraError;
+
+                fn try_from(value: &str) -> Result<Self, Self::Error> {
+                    $ty::try_new(value)
+                }
+            }
+
+            impl From<$ty> for String {
+                fn from(value: $ty) -> Self {
+                    value.0
+                }
+            }
+
+            impl Deref for $ty {
+                type Target = str;
+
+                fn deref(&self) -> &str {
+                    &self.0
+                }
+            }
+
+            impl AsRef<str> for $ty {
+                fn as_ref(&self) -> &str {
+                    &self.0
+                }
+            }
+
+            impl fmt::Display for $ty {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+
+            impl PartialEq<str> for $ty {
+                fn eq(&self, other: &str) -> bool {
+                    self.0 == other
+                }
+            }
+
+            impl PartialEq<&str> for $ty {
+                fn eq(&self, other: &&str) -> bool {
+                    self.0 == *other
+                }
+            }
+        )*
+    };
+}
+
+impl_validated_string!(TradeNo, MchId, AppId);
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use serde_json::json;
+
+    use super::{AppId, MchId, TradeNo};
+
+    #[test]
+    fn test_trade_no_rules() {
+        for no in ["123456", "20150806125346", "P2015_08-06|12*34", "12345678901234567890123456789012"].iter() {
+            assert_eq!(TradeNo::try_new(*no).unwrap(), *no);
+        }
+        // 长度
+        assert!(TradeNo::try_new("12345").is_err());
+        assert!(TradeNo::try_new("").is_err());
+        assert!(TradeNo::try_new("123456789012345678901234567890123").is_err());
+        // 字符
+        for no in ["2015 0806", "订单20150806", "20150806@qq", "2015.08.06", "2015/08/06"].iter() {
+            assert!(TradeNo::try_new(*no).is_err(), "{}", no);
+        }
+        assert!(TradeNo::try_from("20150806").is_ok());
+    }
+
+    #[test]
+    fn test_trade_no_sanitize() {
+        assert_eq!(TradeNo::sanitize("订单20150806001").unwrap(), "20150806001");
+        assert_eq!(TradeNo::sanitize(" 2015 0806\t001 ").unwrap(), "_2015_0806_001_");
+        assert_eq!(TradeNo::sanitize("退款-2015.08.06#001").unwrap(), "-20150806001");
+        // 超长截断，合法的订单号不变
+        assert_eq!(TradeNo::sanitize("1234567890123456789012345678901234567890").unwrap(), "12345678901234567890123456789012");
+        assert_eq!(TradeNo::sanitize("P20150806125346").unwrap(), "P20150806125346");
+        // 清理后不足6位
+        assert!(TradeNo::sanitize("订单号123").is_err());
+    }
+
+    #[test]
+    fn test_trade_no_serde() {
+        let no = TradeNo::try_new("20150806125346").unwrap();
+        assert_eq!(serde_json::to_value(&no).unwrap(), json!("20150806125346"));
+        assert_eq!(serde_json::from_value::<TradeNo>(json!("20150806125346")).unwrap(), no);
+        assert!(serde_json::from_value::<TradeNo>(json!("订单")).is_err());
+        assert_eq!(format!("/v3/pay/transactions/out-trade-no/{}", no), "/v3/pay/transactions/out-trade-no/20150806125346");
+    }
+
+    #[test]
+    fn test_mchid_and_appid() {
+        assert!(MchId::try_new("1230000109").is_ok());
+        for mchid in ["", "mchid", "123 456", "１２３"].iter() {
+            assert!(MchId::try_new(*mchid).is_err(), "{}", mchid);
+        }
+        assert!(AppId::try_new("wxd930ea5d5a258f4f").is_ok());
+        assert!(AppId::try_new("wxD930EA5D5A258F4F").is_ok());
+        for appid in ["", "appid", "wxd930ea5d5a258f4", "wxd930ea5d5a258f4f0", "wwd930ea5d5a258f4f", "wxd930ea5d5a258g4f"].iter() {
+            assert!(AppId::try_new(*appid).is_err(), "{}", appid);
+        }
+        assert_eq!(serde_json::to_value(MchId::try_new("1230000109").unwrap()).unwrap(), json!("1230000109"));
+        assert!(serde_json::from_value::<AppId>(json!("appid")).is_err());
+    }
+}
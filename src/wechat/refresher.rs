@@ -0,0 +1,127 @@
+//!
+//! access_token后台刷新
+//!
+//! 默认情况下access_token在请求时发现过期才会重新获取，这次请求要多等一次换取凭证的耗时。开启后台刷新后，
+//! 任务会在缓存的access_token过期前`margin`醒来，强制刷新并写入会话存储，请求时总能直接取到有效的凭证；
+//! 后台任务因网络等原因未能及时刷新时，请求仍按原逻辑自行刷新。
+//!
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::task::JoinHandle;
+
+use crate::LabradorResult;
+
+/// 刷新失败后的首次重试间隔
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// 重试间隔上限
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// 两次成功刷新之间的最短间隔，避免`margin`大于有效期时反复刷新
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// <pre>
+/// 后台刷新任务的句柄
+/// 调用`abort`或丢弃句柄时任务停止
+/// </pre>
+#[derive(Debug)]
+pub struct TokenRefreshHandle {
+    handle: JoinHandle<()>,
+}
+
+impl TokenRefreshHandle {
+    /// 停止后台刷新
+    pub fn abort(&self) {
+        self.handle.abort();
+    }
+
+    /// 任务是否已停止
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+impl Drop for TokenRefreshHandle {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// 距离`expires_at - margin`（秒级时间戳）的时长，已过时为0
+fn wait_until(expires_at: i64, margin: Duration) -> Duration {
+    let deadline = UNIX_EPOCH + Duration::from_secs(expires_at.max(0) as u64);
+    deadline.checked_sub(margin)
+        .and_then(|wake_at| wake_at.duration_since(SystemTime::now()).ok())
+        .unwrap_or_default()
+}
+
+/// <pre>
+/// 启动后台刷新任务，需要在tokio运行时中调用
+/// `expires_at`读取缓存的过期时间（秒级时间戳），`refresh`强制刷新并写入会话存储
+/// 启动时缓存为空或即将过期会立即刷新；刷新失败按500毫秒起、最长60秒的间隔退避重试
+/// </pre>
+pub(crate) fn spawn_token_refresher<E, R, F>(name: String, margin: Duration, expires_at: E, refresh: R) -> TokenRefreshHandle
+    where E: Fn() -> LabradorResult<i64> + Send + 'static,
+          R: Fn() -> F + Send + 'static,
+          F: Future<Output = LabradorResult<String>> + Send + 'static {
+    let handle = tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut min_wait = Duration::ZERO;
+        loop {
+            let wait = wait_until(expires_at().unwrap_or_default(), margin).max(min_wait);
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            match refresh().await {
+                Ok(_) => {
+                    backoff = INITIAL_BACKOFF;
+                    min_wait = MIN_INTERVAL;
+                }
+                Err(err) => {
+                    tracing::warn!("[后台刷新access_token] {}刷新失败:{}，{:?}后重试", name, err, backoff);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    min_wait = Duration::ZERO;
+                }
+            }
+        }
+    });
+    TokenRefreshHandle { handle }
+}
+
+/// <pre>
+/// 按固定间隔执行的后台任务，需要在tokio运行时中调用
+/// 启动后先等待一个间隔；执行失败时记录日志，下个间隔照常执行
+/// </pre>
+pub(crate) fn spawn_periodic<R, F>(name: String, interval: Duration, task: R) -> TokenRefreshHandle
+    where R: Fn() -> F + Send + 'static,
+          F: Future<Output = LabradorResult<()>> + Send + 'static {
+    let handle = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval.max(MIN_INTERVAL)).await;
+            if let Err(err) = task().await {
+                tracing::warn!("[后台任务] {}执行失败:{}，{:?}后重试", name, err, interval);
+            }
+        }
+    });
+    TokenRefreshHandle { handle }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::util::current_timestamp;
+
+    use super::wait_until;
+
+    #[test]
+    fn test_wait_until() {
+        let now = current_timestamp();
+        assert_eq!(wait_until(now - 10, Duration::from_secs(1)), Duration::ZERO);
+        assert_eq!(wait_until(0, Duration::from_secs(1)), Duration::ZERO);
+        assert_eq!(wait_until(now + 5, Duration::from_secs(10)), Duration::ZERO);
+        let wait = wait_until(now + 10, Duration::from_secs(2));
+        assert!(wait > Duration::from_secs(6) && wait <= Duration::from_secs(8), "{:?}", wait);
+    }
+}
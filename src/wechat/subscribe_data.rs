@@ -0,0 +1,295 @@
+//!
+//! 订阅消息的模板参数校验
+//!
+//! 公众号订阅通知与小程序订阅消息的模板关键词规则相同：关键词名称为类型加序号，如thing1、time2，
+//! 不符合类型要求时接口返回47003。发送前按关键词名称推断类型并校验取值，尽早在本地发现问题。
+//!
+use chrono::{NaiveDate, NaiveTime};
+use serde_json::{json, Map, Value};
+
+use crate::{LabradorResult, LabraError};
+
+/// <pre>
+/// 订阅消息模板关键词的类型
+/// 详见 [文档](https://developers.weixin.qq.com/miniprogram/dev/api-backend/open-api/subscribe-message/subscribeMessage.send.html)
+/// </pre>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WechatSubscribeKeyword {
+    /// 事物：20个以内字符，可汉字、数字、字母或符号组合
+    Thing,
+    /// 数字：32位以内数字，可带小数
+    Number,
+    /// 字母：32位以内字母
+    Letter,
+    /// 符号：5位以内符号
+    Symbol,
+    /// 字符串：32位以内数字、字母或符号
+    CharacterString,
+    /// 时间：24小时制时间格式（支持+年月日），支持填时间段，两个时间点之间用“~”连接，如15:01、2019年10月1日 15:01、2019-10-01 15:01:00
+    Time,
+    /// 日期：年月日格式（支持+24小时制时间），支持填时间段，两个日期之间用“~”连接，如2019年10月1日
+    Date,
+    /// 金额：1个币种符号+10位以内纯数字，可带小数，结尾可带“元”
+    Amount,
+    /// 电话：17位以内，数字、符号
+    PhoneNumber,
+    /// 车牌：8位以内，第一位与最后一位可为汉字，其余为字母或数字
+    CarNumber,
+    /// 姓名：10个以内纯汉字或20个以内纯字母或符号
+    Name,
+    /// 汉字：5个以内汉字
+    Phrase,
+}
+
+impl WechatSubscribeKeyword {
+    /// 按关键词名称（去掉末尾的序号）解析类型，未知的类型返回`None`
+    pub fn parse(key: &str) -> Option<Self> {
+        let keyword = match key.trim_end_matches(|c: char| c.is_ascii_digit()) {
+            "thing" => WechatSubscribeKeyword::Thing,
+            "number" => WechatSubscribeKeyword::Number,
+            "letter" => WechatSubscribeKeyword::Letter,
+            "symbol" => WechatSubscribeKeyword::Symbol,
+            "character_string" => WechatSubscribeKeyword::CharacterString,
+            "time" => WechatSubscribeKeyword::Time,
+            "date" => WechatSubscribeKeyword::Date,
+            "amount" => WechatSubscribeKeyword::Amount,
+            "phone_number" => WechatSubscribeKeyword::PhoneNumber,
+            "car_number" => WechatSubscribeKeyword::CarNumber,
+            "name" => WechatSubscribeKeyword::Name,
+            "phrase" => WechatSubscribeKeyword::Phrase,
+            _ => return None,
+        };
+        Some(keyword)
+    }
+
+    /// 取值的要求，用于错误提示
+    pub fn rule(&self) -> &'static str {
+        match self {
+            WechatSubscribeKeyword::Thing => "20个以内字符",
+            WechatSubscribeKeyword::Number => "32位以内数字，可带小数",
+            WechatSubscribeKeyword::Letter => "32位以内字母",
+            WechatSubscribeKeyword::Symbol => "5位以内符号",
+            WechatSubscribeKeyword::CharacterString => "32位以内数字、字母或符号",
+            WechatSubscribeKeyword::Time => "24小时制时间，如15:01、2019年10月1日 15:01、2019-10-01 15:01:00，时间段用~连接",
+            WechatSubscribeKeyword::Date => "年月日格式的日期，如2019年10月1日、2019-10-01，时间段用~连接",
+            WechatSubscribeKeyword::Amount => "1个币种符号+10位以内纯数字，可带小数，结尾可带“元”",
+            WechatSubscribeKeyword::PhoneNumber => "17位以内数字、符号",
+            WechatSubscribeKeyword::CarNumber => "8位以内，第一位与最后一位可为汉字，其余为字母或数字",
+            WechatSubscribeKeyword::Name => "10个以内纯汉字或20个以内纯字母或符号",
+            WechatSubscribeKeyword::Phrase => "5个以内汉字",
+        }
+    }
+
+    /// 校验取值是否符合该类型的要求
+    pub fn is_valid(&self, value: &str) -> bool {
+        let len = value.chars().count();
+        match self {
+            WechatSubscribeKeyword::Thing => len <= 20,
+            WechatSubscribeKeyword::Number => len <= 32 && is_decimal(value),
+            WechatSubscribeKeyword::Letter => len <= 32 && value.chars().all(|c| c.is_ascii_alphabetic()),
+            WechatSubscribeKeyword::Symbol => len <= 5 && value.chars().all(is_symbol),
+            WechatSubscribeKeyword::CharacterString => len <= 32 && value.chars().all(|c| c.is_ascii_alphanumeric() || is_symbol(c)),
+            WechatSubscribeKeyword::Time => is_datetime_range(value, true),
+            WechatSubscribeKeyword::Date => is_datetime_range(value, false),
+            WechatSubscribeKeyword::Amount => {
+                let amount = value.strip_suffix('元').unwrap_or(value);
+                let amount = match amount.chars().next() {
+                    Some(c) if !c.is_ascii_digit() && !is_chinese(c) => &amount[c.len_utf8()..],
+                    _ => amount,
+                };
+                is_decimal(amount) && amount.split('.').next().unwrap_or_default().len() <= 10
+            }
+            WechatSubscribeKeyword::PhoneNumber => {
+                len <= 17 && value.chars().any(|c| c.is_ascii_digit()) && value.chars().all(|c| c.is_ascii_digit() || is_symbol(c))
+            }
+            WechatSubscribeKeyword::CarNumber => {
+                len <= 8 && value.chars().enumerate().all(|(i, c)| c.is_ascii_alphanumeric() || ((i == 0 || i + 1 == len) && is_chinese(c)))
+                    && value.chars().any(|c| c.is_ascii_alphanumeric())
+            }
+            WechatSubscribeKeyword::Name => {
+                (len <= 10 && value.chars().all(is_chinese)) || (len <= 20 && value.chars().all(|c| c.is_ascii_alphabetic() || is_symbol(c) || c == ' '))
+            }
+            WechatSubscribeKeyword::Phrase => len <= 5 && value.chars().all(is_chinese),
+        }
+    }
+}
+
+fn is_chinese(c: char) -> bool {
+    ('\u{4e00}'..='\u{9fff}').contains(&c)
+}
+
+fn is_symbol(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+/// 数字，可带小数
+fn is_decimal(value: &str) -> bool {
+    let mut parts = value.splitn(2, '.');
+    let integer = parts.next().unwrap_or_default();
+    !integer.is_empty() && integer.bytes().all(|b| b.is_ascii_digit())
+        && parts.next().map(|decimal| !decimal.is_empty() && decimal.bytes().all(|b| b.is_ascii_digit())).unwrap_or(true)
+}
+
+/// 时间、日期及以“~”连接的时间段，need_time时每个时间点都需带时分
+fn is_datetime_range(value: &str, need_time: bool) -> bool {
+    let parts = value.split('~').collect::<Vec<_>>();
+    parts.len() <= 2 && parts.iter().all(|part| match parse_datetime(part.trim()) {
+        Some((date, time)) => if need_time { time } else { date },
+        None => false,
+    })
+}
+
+/// <pre>
+/// 解析单个时间点，返回是否包含日期、是否包含时间
+/// 日期：yyyy-MM-dd、yyyy/MM/dd、yyyy.MM.dd、yyyy年M月d日；时间：HH:mm、HH:mm:ss（冒号可为全角）
+/// </pre>
+fn parse_datetime(value: &str) -> Option<(bool, bool)> {
+    let (date, time) = match value.rfind(' ') {
+        Some(i) => (Some(value[..i].trim()), Some(value[i + 1..].trim())),
+        None if value.contains(':') || value.contains('：') => (None, Some(value)),
+        None => (Some(value), None),
+    };
+    if let Some(date) = date {
+        parse_date(date)?;
+    }
+    if let Some(time) = time {
+        parse_time(time)?;
+    }
+    Some((date.is_some(), time.is_some()))
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let fields = match value.strip_suffix('日') {
+        Some(value) => value.split(|c| c == '年' || c == '月').collect::<Vec<_>>(),
+        None => ['-', '/', '.'].iter().map(|sep| value.split(*sep).collect::<Vec<_>>()).find(|fields| fields.len() == 3)?,
+    };
+    match fields.as_slice() {
+        [year, month, day] if year.len() == 4 && [year, month, day].iter().all(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit())) => {
+            NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+        }
+        _ => None,
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let fields = value.split(|c| c == ':' || c == '：').collect::<Vec<_>>();
+    if !(2..=3).contains(&fields.len()) || fields.iter().any(|v| v.is_empty() || v.len() > 2 || !v.bytes().all(|b| b.is_ascii_digit())) {
+        return None;
+    }
+    NaiveTime::from_hms_opt(fields[0].parse().ok()?, fields[1].parse().ok()?, fields.get(2).map(|v| v.parse()).unwrap_or(Ok(0)).ok()?)
+}
+
+/// <pre>
+/// 校验订阅消息的模板参数
+/// data格式为`{"thing1": {"value": "..."}}`，按关键词名称的类型校验取值，未知类型的关键词不做校验
+/// </pre>
+pub fn check_subscribe_data(data: &Value) -> LabradorResult<()> {
+    let data = data.as_object().ok_or_else(|| LabraError::RequestError("订阅消息的data应为JSON对象".to_string()))?;
+    for (key, item) in data.iter() {
+        let value = match &item["value"] {
+            Value::String(value) => value.to_string(),
+            Value::Number(value) => value.to_string(),
+            _ => return Err(LabraError::RequestError(format!("订阅消息参数{}缺少value", key))),
+        };
+        check_keyword(key, &value)?;
+    }
+    Ok(())
+}
+
+fn check_keyword(key: &str, value: &str) -> LabradorResult<()> {
+    match WechatSubscribeKeyword::parse(key) {
+        Some(keyword) if !keyword.is_valid(value) => Err(LabraError::RequestError(format!("订阅消息参数{}的取值{}不符合要求：{}", key, value, keyword.rule()))),
+        _ => Ok(()),
+    }
+}
+
+/// <pre>
+/// 订阅消息模板参数
+/// 按关键词名称推断类型，build时校验全部取值，生成`{"thing1": {"value": "..."}}`格式的data
+/// </pre>
+#[derive(Debug, Clone, Default)]
+pub struct WechatSubscribeDataBuilder {
+    values: Vec<(String, String)>,
+}
+
+impl WechatSubscribeDataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 填写关键词的取值，同一关键词重复填写时以最后一次为准
+    pub fn value<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        let key = key.into();
+        self.values.retain(|(k, _)| k != &key);
+        self.values.push((key, value.into()));
+        self
+    }
+
+    pub fn build(&self) -> LabradorResult<Value> {
+        let mut data = Map::new();
+        for (key, value) in &self.values {
+            check_keyword(key, value)?;
+            data.insert(key.to_owned(), json!({ "value": value }));
+        }
+        Ok(Value::Object(data))
+    }
+}
+
+#[cfg(test)]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use serde_json::json;
+
+    use super::{check_subscribe_data, WechatSubscribeDataBuilder, WechatSubscribeKeyword};
+
+    #[test]
+    fn test_keyword_rules() {
+        let cases = vec![
+            ("thing1", vec!["门铃被按响", "TIT造舰厂", "一二三四五六七八九十一二三四五六七八九十"], vec!["门铃被按响，请及时查看门口的监控画面并确认来访人员", "一二三四五六七八九十一二三四五六七八九十一"]),
+            ("number2", vec!["100", "3.14", "12345678901234567890123456789012"], vec!["一百", "1.", "1.2.3", "-1", "123456789012345678901234567890123"]),
+            ("letter3", vec!["abcXYZ"], vec!["abc1", "字母"]),
+            ("symbol4", vec!["%", "->"], vec!["%%%%%%", "a"]),
+            ("character_string5", vec!["ZK-20191001/001"], vec!["订单001", "a b"]),
+            ("time6", vec!["15:01", "15:01:30", "2019年10月1日 15:01", "2019-10-01 15:01:00", "2019/10/01 15:01", "2019-10-01 15:01~2019-10-02 15:01", "15:01~16:30"],
+             vec!["明天中午", "2019年10月1日", "25:00", "15:60", "2019-13-01 15:01", "15:01~16:01~17:01", "2019-10-01 15:01~"]),
+            ("date7", vec!["2019年10月1日", "2019-10-01", "2019-10-01 15:01", "2019-10-01~2019-10-02"], vec!["国庆节", "", "2019-02-30", "15:01", "19-10-01"]),
+            ("amount8", vec!["¥100.01元", "100", "$9999999999"], vec!["¥12345678901", "一百元", "¥"]),
+            ("phone_number9", vec!["+86-0766-66888866", "13800138000"], vec!["电话", "+86-0766-668888661234", "--"]),
+            ("car_number10", vec!["粤A8Z888挂", "AB12345"], vec!["粤A8粤Z888", "粤A8Z888挂1", "粤挂"]),
+            ("name11", vec!["张三", "Tom Smith"], vec!["张三Tom", "欧阳司马诸葛上官东方长孙"]),
+            ("phrase12", vec!["配送中", "五个汉字啊"], vec!["配送中ing", "正在配送中呢"]),
+        ];
+        for (key, valid, invalid) in cases {
+            let keyword = WechatSubscribeKeyword::parse(key).unwrap();
+            for value in valid {
+                assert!(keyword.is_valid(value), "{} {}", key, value);
+            }
+            for value in invalid {
+                assert!(!keyword.is_valid(value), "{} {}", key, value);
+            }
+        }
+        assert_eq!(WechatSubscribeKeyword::parse("character_string"), Some(WechatSubscribeKeyword::CharacterString));
+        assert_eq!(WechatSubscribeKeyword::parse("unknown1"), None);
+    }
+
+    #[test]
+    fn test_check_subscribe_data() {
+        assert!(check_subscribe_data(&json!({"thing1": {"value": "门铃被按响"}, "number2": {"value": 100}, "unknown3": {"value": "不校验"}})).is_ok());
+        assert!(check_subscribe_data(&json!({"number2": {"value": "一百"}})).is_err());
+        assert!(check_subscribe_data(&json!({"thing1": "门铃被按响"})).is_err());
+        assert!(check_subscribe_data(&json!(["thing1"])).is_err());
+    }
+
+    #[test]
+    fn test_builder() {
+        let data = WechatSubscribeDataBuilder::new()
+            .value("thing1", "门铃被按响")
+            .value("time2", "2019-10-01 15:01:00")
+            .value("phone_number3", "13800138000")
+            .value("thing1", "快递已送达")
+            .build().unwrap();
+        assert_eq!(data, json!({"thing1": {"value": "快递已送达"}, "time2": {"value": "2019-10-01 15:01:00"}, "phone_number3": {"value": "13800138000"}}));
+        let err = WechatSubscribeDataBuilder::new().value("thing1", "门铃被按响").value("time2", "明天中午").build().unwrap_err();
+        assert!(err.to_string().contains("time2"), "{}", err);
+    }
+}
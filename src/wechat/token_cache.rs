@@ -0,0 +1,341 @@
+//!
+//! access_token的缓存与会话存储降级
+//!
+//! 默认情况下读取会话存储出错时直接返回错误。配置[`StoreFallbackPolicy`]后，读写会话存储出错时记录日志，
+//! 直接向微信换取access_token并缓存在进程内，之后每隔`retry_interval`重试会话存储，恢复后将进程内的access_token写回。
+//!
+//! 换取access_token时按缓存键在进程内加锁，同一客户端（及其克隆）同时只有一个请求向微信换取，其它请求等待后直接使用换取的结果，
+//! 避免access_token过期时并发请求同时换取而互相使旧的access_token失效。
+//!
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{LabradorResult, MetricsRecorder, SessionStore, TokenInfo};
+use crate::util::current_timestamp;
+
+/// <pre>
+/// 会话存储不可用时的降级策略
+/// retry_interval为降级期间重试会话存储的间隔，默认30秒
+/// </pre>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreFallbackPolicy {
+    pub retry_interval: Duration,
+}
+
+impl StoreFallbackPolicy {
+    pub fn new(retry_interval: Duration) -> Self {
+        StoreFallbackPolicy { retry_interval }
+    }
+}
+
+impl Default for StoreFallbackPolicy {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+#[derive(Debug, Default)]
+struct FallbackState {
+    /// 会话存储出错后下次重试的时间，None表示会话存储可用
+    retry_at: Option<Instant>,
+    /// 进程内缓存的access_token及其失效时间
+    token: Option<(String, i64)>,
+}
+
+/// 一个缓存键的换取锁
+#[derive(Debug, Default)]
+struct KeyLock {
+    lock: crate::runtime::Mutex<()>,
+    /// 已完成的换取次数，等待锁期间有变化说明其它请求已经换取
+    refreshed: AtomicU64,
+}
+
+/// 一次换取access_token涉及的会话存储、缓存键与指标记录
+pub(crate) struct TokenRequest<'a, S: SessionStore> {
+    pub(crate) session: &'a S,
+    pub(crate) token_key: &'a str,
+    pub(crate) expires_key: &'a str,
+    pub(crate) appid: &'a str,
+    pub(crate) metrics: &'a Arc<dyn MetricsRecorder>,
+}
+
+/// 客户端的access_token缓存，克隆客户端时共享降级状态与换取锁
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TokenCache {
+    policy: Option<StoreFallbackPolicy>,
+    state: Arc<Mutex<FallbackState>>,
+    /// 按缓存键换取access_token的进程内锁
+    locks: Arc<Mutex<HashMap<String, Arc<KeyLock>>>>,
+}
+
+#[allow(unused)]
+impl TokenCache {
+    pub(crate) fn with_policy(&self, policy: StoreFallbackPolicy) -> Self {
+        TokenCache { policy: policy.into(), ..self.to_owned() }
+    }
+
+    /// 会话存储是否可用，未配置降级策略时总为true
+    pub(crate) fn store_healthy(&self) -> bool {
+        self.state.lock().map(|state| state.retry_at.is_none()).unwrap_or(false)
+    }
+
+    /// 使进程内缓存的access_token失效，如轮换secret时
+    pub(crate) fn invalidate(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.token = None;
+        }
+    }
+
+    /// <pre>
+    /// 取得access_token，缓存过期或force_refresh时调用fetch换取，fetch返回access_token与有效期（秒）
+    /// 同一缓存键的换取与写入在锁内进行，其它请求在此期间已经换取时直接使用其结果（包括force_refresh）
+    /// 返回值的bool表示本次是否重新换取
+    /// </pre>
+    pub(crate) async fn token_info<S, F, Fut>(&self, req: &TokenRequest<'_, S>, force_refresh: bool, fetch: F) -> LabradorResult<(TokenInfo, bool)>
+        where S: SessionStore, F: FnOnce() -> Fut, Fut: Future<Output = LabradorResult<(String, i64)>> {
+        let key_lock = self.key_lock(req.token_key);
+        let refreshed = key_lock.refreshed.load(Ordering::SeqCst);
+        if !force_refresh {
+            if let Some((token, expires_at)) = self.cached(req)? {
+                return Ok((TokenInfo::from_cache(token, expires_at)?, false));
+            }
+        }
+        let _guard = key_lock.lock.lock().await;
+        // 等待锁期间其它请求已经换取
+        if key_lock.refreshed.load(Ordering::SeqCst) != refreshed {
+            if let Some((token, expires_at)) = self.cached(req)? {
+                return Ok((TokenInfo::from_cache(token, expires_at)?, false));
+            }
+        }
+        let (token, expires_in) = fetch().await?;
+        // 预留200秒的时间
+        let expires_at = current_timestamp() + expires_in - 200;
+        if self.policy.is_some() {
+            if let Ok(mut state) = self.state.lock() {
+                state.token = (token.to_owned(), expires_at).into();
+            }
+        }
+        self.store(req, &token, expires_at, expires_in);
+        key_lock.refreshed.fetch_add(1, Ordering::SeqCst);
+        Ok((TokenInfo::from_cache(token, expires_at)?, true))
+    }
+
+    fn key_lock(&self, token_key: &str) -> Arc<KeyLock> {
+        match self.locks.lock() {
+            Ok(mut locks) => locks.entry(token_key.to_owned()).or_default().clone(),
+            // 锁已中毒时不再合并换取
+            Err(_) => Arc::default(),
+        }
+    }
+
+    /// 未过期的access_token，优先读取会话存储，降级期间读取进程内缓存
+    fn cached<S: SessionStore>(&self, req: &TokenRequest<'_, S>) -> LabradorResult<Option<(String, i64)>> {
+        let read = || -> LabradorResult<(String, i64)> {
+            let token: String = req.session.get(req.token_key, Some("".to_owned()))?.unwrap_or_default();
+            let expires_at: i64 = req.session.get(req.expires_key, Some(0))?.unwrap_or_default();
+            Ok((token, expires_at))
+        };
+        let policy = match self.policy {
+            Some(policy) => policy,
+            None => {
+                let (token, expires_at) = read()?;
+                return Ok(if expires_at > current_timestamp() { Some((token, expires_at)) } else { None });
+            }
+        };
+        if !self.should_try_store() {
+            return Ok(self.local());
+        }
+        match read() {
+            Ok((token, expires_at)) => {
+                let recovered = !self.store_healthy();
+                self.mark_healthy(req.appid, req.metrics);
+                if expires_at > current_timestamp() {
+                    return Ok(Some((token, expires_at)));
+                }
+                let local = self.local();
+                if let (true, Some((token, expires_at))) = (recovered, &local) {
+                    // 会话存储恢复后写回降级期间换取的access_token，其它实例可直接使用
+                    let expires_in = expires_at - current_timestamp() + 200;
+                    self.store(req, token, *expires_at, expires_in);
+                }
+                Ok(local)
+            }
+            Err(err) => {
+                self.mark_unhealthy(&policy, req.appid, req.metrics, "读取", &err.to_string());
+                Ok(self.local())
+            }
+        }
+    }
+
+    /// 写入会话存储，未配置降级策略时与之前一样忽略写入错误
+    fn store<S: SessionStore>(&self, req: &TokenRequest<'_, S>, token: &str, expires_at: i64, expires_in: i64) {
+        let policy = match self.policy {
+            Some(policy) if self.should_try_store() => policy,
+            Some(_) => return,
+            None => {
+                let _ = req.session.set(req.token_key, token.to_owned(), Some(expires_in as usize));
+                let _ = req.session.set(req.expires_key, expires_at, Some(expires_in as usize));
+                return;
+            }
+        };
+        let written = req.session.set(req.token_key, token.to_owned(), Some(expires_in as usize))
+            .and_then(|_| req.session.set(req.expires_key, expires_at, Some(expires_in as usize)));
+        match written {
+            Ok(_) => self.mark_healthy(req.appid, req.metrics),
+            Err(err) => self.mark_unhealthy(&policy, req.appid, req.metrics, "写入", &err.to_string()),
+        }
+    }
+
+    fn local(&self) -> Option<(String, i64)> {
+        let state = self.state.lock().ok()?;
+        state.token.to_owned().filter(|(_, expires_at)| *expires_at > current_timestamp())
+    }
+
+    fn should_try_store(&self) -> bool {
+        self.state.lock().map(|state| state.retry_at.map(|retry_at| retry_at <= Instant::now()).unwrap_or(true)).unwrap_or(true)
+    }
+
+    fn mark_healthy(&self, appid: &str, metrics: &Arc<dyn MetricsRecorder>) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.retry_at.take().is_some() {
+                tracing::info!("[会话存储] {}的会话存储已恢复", appid);
+            }
+        }
+        metrics.store_healthy(appid, true);
+    }
+
+    fn mark_unhealthy(&self, policy: &StoreFallbackPolicy, appid: &str, metrics: &Arc<dyn MetricsRecorder>, action: &str, err: &str) {
+        tracing::warn!("[会话存储] {}{}access_token失败:{}，改为直接向微信换取并缓存在进程内，{:?}后重试", appid, action, err, policy.retry_interval);
+        if let Ok(mut state) = self.state.lock() {
+            state.retry_at = (Instant::now() + policy.retry_interval).into();
+        }
+        metrics.store_healthy(appid, false);
+    }
+}
+
+#[cfg(all(test, feature = "wechat-mp"))]
+#[allow(unused, non_snake_case)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use crate::{FromStore, LabraError, LabradorResult, MetricsRecorder, Outcome, SessionStore, SimpleStorage, StoreFallbackPolicy, ToStore, WechatMpClient};
+    use crate::util::mock::{MockResponse, MockServer};
+
+    const TOKEN: &str = r#"{"access_token":"ACCESS_TOKEN","expires_in":7200}"#;
+    const IP: &str = r#"{"ip_list":["127.0.0.1"]}"#;
+
+    /// 前failures次读写出错，之后恢复；SimpleStorage为全局存储，各测试使用不同的appid
+    #[derive(Debug, Clone)]
+    struct FlakyStorage {
+        failures: Arc<AtomicUsize>,
+        inner: SimpleStorage,
+    }
+
+    impl FlakyStorage {
+        fn new(failures: usize) -> Self {
+            FlakyStorage { failures: Arc::new(AtomicUsize::new(failures)), inner: SimpleStorage::new() }
+        }
+
+        fn check(&self) -> LabradorResult<()> {
+            match self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+                Ok(_) => Err(LabraError::StoreError("connection refused".into())),
+                Err(_) => Ok(()),
+            }
+        }
+    }
+
+    impl SessionStore for FlakyStorage {
+        fn get<'a, K: AsRef<str>, T: FromStore>(&self, key: K, default: Option<T>) -> LabradorResult<Option<T>> {
+            self.check()?;
+            self.inner.get(key, default)
+        }
+
+        fn set<'a, K: AsRef<str>, T: ToStore>(&self, key: K, value: T, ttl: Option<usize>) -> LabradorResult<()> {
+            self.check()?;
+            self.inner.set(key, value, ttl)
+        }
+    }
+
+    #[derive(Default)]
+    struct HealthRecorder {
+        healthy: Mutex<Vec<bool>>,
+    }
+
+    impl MetricsRecorder for HealthRecorder {
+        fn record(&self, _appid: &str, _method: &str, _status: Outcome, _elapsed: Duration, _attempt: usize) {}
+
+        fn store_healthy(&self, _appid: &str, healthy: bool) {
+            self.healthy.lock().unwrap().push(healthy);
+        }
+    }
+
+    fn token_requests(server: &MockServer) -> usize {
+        server.requests().iter().filter(|req| req.starts_with("GET /cgi-bin/token?")).count()
+    }
+
+    #[tokio::test]
+    async fn test_store_recovers() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN), MockResponse::json(IP), MockResponse::json(IP), MockResponse::json(IP)]).await;
+        let store = FlakyStorage::new(3);
+        let recorder = Arc::new(HealthRecorder::default());
+        let client = WechatMpClient::from_session("store_recovers", "secret", store.clone()).base_url(&server.url)
+            .metrics_recorder(recorder.clone()).store_fallback(StoreFallbackPolicy::new(Duration::ZERO));
+
+        // 读取失败后直接换取，写入也失败，仍能正常请求
+        assert_eq!(client.get_callback_ip(false).await.unwrap(), vec!["127.0.0.1"]);
+        assert!(!client.store_healthy());
+        assert_eq!(store.inner.get::<_, String>("store_recovers_access_token", None).unwrap(), None);
+        // 第3次读写仍失败，使用进程内缓存
+        assert_eq!(client.get_callback_ip(false).await.unwrap(), vec!["127.0.0.1"]);
+        assert!(!client.store_healthy());
+        // 恢复后写回会话存储
+        assert_eq!(client.get_callback_ip(false).await.unwrap(), vec!["127.0.0.1"]);
+        assert!(client.store_healthy());
+        assert_eq!(store.inner.get::<_, String>("store_recovers_access_token", None).unwrap(), Some("ACCESS_TOKEN".to_string()));
+        assert_eq!(token_requests(&server), 1);
+        assert_eq!(recorder.healthy.lock().unwrap().as_slice(), [false, false, false, true, true]);
+    }
+
+    #[tokio::test]
+    async fn test_store_down_without_stampede() {
+        let server = MockServer::start(std::iter::once(TOKEN).chain(vec![IP; 5]).map(MockResponse::json).collect()).await;
+        let client = WechatMpClient::from_session("store_down", "secret", FlakyStorage::new(usize::MAX)).base_url(&server.url)
+            .store_fallback(StoreFallbackPolicy::default());
+        let results = futures_util::future::join_all((0..5).map(|_| client.get_callback_ip(false))).await;
+        assert!(results.iter().all(|res| res.as_ref().unwrap() == &vec!["127.0.0.1".to_string()]));
+        assert_eq!(token_requests(&server), 1);
+        assert!(!client.store_healthy());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_refresh_coalesced() {
+        // 换取较慢时其它请求都在等待
+        let server = MockServer::start((0..2).map(|_| MockResponse::json(TOKEN).delay(Duration::from_millis(100))).collect()).await;
+        let client = WechatMpClient::from_session("concurrent_refresh", "secret", SimpleStorage::new()).base_url(&server.url);
+        let tasks = (0..16).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.access_token(false).await })
+        }).collect::<Vec<_>>();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), "ACCESS_TOKEN");
+        }
+        assert_eq!(token_requests(&server), 1);
+        // 并发的强制刷新同样只换取一次
+        let results = futures_util::future::join_all((0..5).map(|_| client.access_token(true))).await;
+        assert!(results.iter().all(|res| res.as_ref().unwrap() == "ACCESS_TOKEN"));
+        assert_eq!(token_requests(&server), 2);
+    }
+
+    #[tokio::test]
+    async fn test_store_down_without_policy() {
+        let server = MockServer::start(vec![MockResponse::json(TOKEN)]).await;
+        let client = WechatMpClient::from_session("store_down_without_policy", "secret", FlakyStorage::new(usize::MAX)).base_url(&server.url);
+        assert!(matches!(client.get_callback_ip(false).await, Err(LabraError::StoreError(_))));
+        assert!(server.requests().is_empty());
+    }
+}